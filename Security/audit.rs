@@ -3,25 +3,34 @@
  * 
  * This module provides comprehensive audit logging for security events,
  * including file persistence, log rotation, and alerting capabilities.
+ * Entries form a hash chain, optionally signed with an HMAC, so the log
+ * can be verified for tampering and exported for SIEM ingestion.
  * 
  * Author: KleaSCM
  * Email: KleaSCM@gmail.com
  * File: audit.rs
- * Description: Audit logging with file persistence and rotation
+ * Description: Tamper-evident audit logging with file persistence and rotation
  */
 
 use anyhow::Result;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::fs::{DirBuilder, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
 
 use super::{SecurityConfig, SecurityEvent, SecuritySeverity};
+use super::encryption::EncryptionManager;
+
+/// Previous hash of the first entry in a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
 
 /**
  * Audit log entry
@@ -42,6 +51,63 @@ pub struct AuditLogEntry {
 	pub session_id: Option<String>,
 	/// Additional metadata
 	pub metadata: serde_json::Value,
+	/// Position in the hash chain
+	#[serde(default)]
+	pub sequence: u64,
+	/// Hash of the previous entry
+	#[serde(default)]
+	pub previous_hash: String,
+	/// SHA-256 of this entry, covering previous_hash
+	#[serde(default)]
+	pub hash: String,
+	/// HMAC of the hash (if entries are signed)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub signature: Option<EntrySignature>,
+}
+
+/**
+ * HMAC signature of an audit log entry
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntrySignature {
+	/// Encryption key the MAC was keyed from
+	pub key_id: String,
+	/// MAC (base64 encoded)
+	pub mac: String,
+}
+
+impl AuditLogEntry {
+	/// Computes the chain hash of the entry
+	///
+	/// The hash covers every field except the hash and signature
+	/// themselves, including previous_hash.
+	pub fn compute_hash(&self) -> Result<String> {
+		let mut unsealed = self.clone();
+		unsealed.hash = String::new();
+		unsealed.signature = None;
+		
+		Ok(format!("{:x}", Sha256::digest(serde_json::to_vec(&unsealed)?)))
+	}
+}
+
+/**
+ * Head of the hash chain that the next entry links to
+ */
+#[derive(Debug, Clone)]
+struct ChainHead {
+	/// Sequence number of the next entry
+	next_sequence: u64,
+	/// Hash of the last entry written
+	hash: String,
+}
+
+impl Default for ChainHead {
+	fn default() -> Self {
+		Self {
+			next_sequence: 0,
+			hash: GENESIS_HASH.to_string(),
+		}
+	}
 }
 
 /**
@@ -65,6 +131,9 @@ pub struct AuditConfig {
 	pub json_format: bool,
 	/// Enable compression
 	pub compression_enabled: bool,
+	/// Sign entries with an HMAC from the encryption manager's keys
+	#[serde(default)]
+	pub sign_entries: bool,
 	/// Alert thresholds
 	pub alert_thresholds: AlertThresholds,
 }
@@ -88,13 +157,14 @@ impl Default for AuditConfig {
 	fn default() -> Self {
 		Self {
 			enabled: true,
-			log_file_path: "/tmp/sare_security_audit.log".to_string(),
+			log_file_path: default_log_path(),
 			max_file_size: 100 * 1024 * 1024, // 100MB
 			max_log_files: 10,
 			rotation_interval: 24, // 24 hours
 			max_memory_entries: 10000,
 			json_format: true,
 			compression_enabled: false,
+			sign_entries: false,
 			alert_thresholds: AlertThresholds {
 				critical_per_minute: 1,
 				high_per_minute: 5,
@@ -115,14 +185,16 @@ pub struct AuditLogger {
 	audit_config: AuditConfig,
 	/// In-memory log entries
 	log_entries: Arc<RwLock<VecDeque<AuditLogEntry>>>,
-	/// Log file writer
-	log_writer: Arc<RwLock<Option<BufWriter<File>>>>,
+	/// Serializes appends and rotation within this process
+	log_lock: Arc<Mutex<()>>,
 	/// Active state
 	active: bool,
 	/// Alert callbacks
 	alert_callbacks: Arc<RwLock<Vec<Box<dyn Fn(AuditLogEntry) + Send + Sync>>>>,
 	/// Event counters
 	event_counters: Arc<RwLock<EventCounters>>,
+	/// Signs entries with an HMAC (if set)
+	signer: Option<EncryptionManager>,
 }
 
 /**
@@ -159,15 +231,19 @@ impl AuditLogger {
 	 * Creates a new audit logger
 	 */
 	pub async fn new(config: Arc<RwLock<SecurityConfig>>) -> Result<Self> {
-		let audit_config = AuditConfig::default();
-		
+		Self::with_config(config, AuditConfig::default()).await
+	}
+	
+	/// Creates an audit logger with the given audit configuration
+	pub async fn with_config(config: Arc<RwLock<SecurityConfig>>, audit_config: AuditConfig) -> Result<Self> {
 		// Initialize log file
-		let log_writer = if audit_config.enabled {
-			let file = OpenOptions::new()
-				.create(true)
-				.append(true)
-				.open(&audit_config.log_file_path)?;
-			Some(BufWriter::new(file))
+		if audit_config.enabled {
+			Self::open_log_file(&audit_config.log_file_path)?;
+		}
+		
+		// Keys are kept in the manager's storage, where verify_log finds them
+		let signer = if audit_config.sign_entries {
+			Some(EncryptionManager::new(config.clone()).await?)
 		} else {
 			None
 		};
@@ -176,10 +252,11 @@ impl AuditLogger {
 			config,
 			audit_config,
 			log_entries: Arc::new(RwLock::new(VecDeque::new())),
-			log_writer: Arc::new(RwLock::new(log_writer)),
+			log_lock: Arc::new(Mutex::new(())),
 			active: true,
 			alert_callbacks: Arc::new(RwLock::new(Vec::new())),
 			event_counters: Arc::new(RwLock::new(EventCounters::default())),
+			signer,
 		};
 		
 		// Start background tasks
@@ -199,19 +276,8 @@ impl AuditLogger {
 		// Create log entry
 		let entry = self.create_log_entry(event).await?;
 		
-		// Add to in-memory storage
-		{
-			let mut entries = self.log_entries.write().await;
-			entries.push_back(entry.clone());
-			
-			// Remove old entries if exceeding limit
-			while entries.len() > self.audit_config.max_memory_entries {
-				entries.pop_front();
-			}
-		}
-		
-		// Write to file
-		self.write_log_entry(&entry).await?;
+		// Link into the chain and store
+		let entry = self.append_entry(entry).await?;
 		
 		// Update event counters
 		self.update_event_counters(&entry).await?;
@@ -250,9 +316,51 @@ impl AuditLogger {
 			user_agent,
 			session_id,
 			metadata,
+			sequence: 0,
+			previous_hash: String::new(),
+			hash: String::new(),
+			signature: None,
 		})
 	}
 	
+	/// Links an entry into the hash chain, then writes and stores it
+	///
+	/// The sequence and previous hash come from the newest entry on disk,
+	/// read while the log is locked, so other processes writing the same
+	/// log (including forked copies of this logger) cannot link to the
+	/// same predecessor.
+	async fn append_entry(&self, mut entry: AuditLogEntry) -> Result<AuditLogEntry> {
+		let log_path = &self.audit_config.log_file_path;
+		let guard = self.log_lock.lock().await;
+		let mut file = Self::lock_log_file(log_path)?;
+		let head = Self::load_chain_head(log_path);
+		entry.sequence = head.next_sequence;
+		entry.previous_hash = head.hash;
+		entry.hash = entry.compute_hash()?;
+		
+		if let Some(signer) = &self.signer {
+			let (key_id, mac) = signer.sign(entry.hash.as_bytes()).await?;
+			entry.signature = Some(EntrySignature { key_id, mac });
+		}
+		
+		// Write to file in one call, then release the lock
+		let json = serde_json::to_string(&entry)?;
+		file.write_all(format!("{}\n", json).as_bytes())?;
+		drop(file);
+		drop(guard);
+		
+		// Add to in-memory storage
+		let mut entries = self.log_entries.write().await;
+		entries.push_back(entry.clone());
+		
+		// Remove old entries if exceeding limit
+		while entries.len() > self.audit_config.max_memory_entries {
+			entries.pop_front();
+		}
+		
+		Ok(entry)
+	}
+	
 	/**
 	 * Extracts source information from security event
 	 */
@@ -273,34 +381,15 @@ impl AuditLogger {
 			SecurityEvent::SecurityAlert { .. } => {
 				(Some("127.0.0.1".to_string()), None, None)
 			}
-			SecurityEvent::ThreatDetected { source, .. } => {
-				(Some(source.clone()), None, None)
-			}
-			SecurityEvent::BehavioralAnomaly { user, .. } => {
-				(Some("127.0.0.1".to_string()), None, Some(format!("session_{}", user)))
-			}
 		}
 	}
 	
-	/**
-	 * Writes a log entry to file
-	 */
-	async fn write_log_entry(&self, entry: &AuditLogEntry) -> Result<()> {
-		if let Some(writer) = &mut *self.log_writer.write().await {
-			let json = serde_json::to_string(entry)?;
-			writeln!(writer, "{}", json)?;
-			writer.flush()?;
-		}
-		Ok(())
-	}
-	
 	/**
 	 * Starts background tasks
 	 */
 	async fn start_background_tasks(&self) -> Result<()> {
-		let log_entries = self.log_entries.clone();
 		let audit_config = self.audit_config.clone();
-		let log_writer = self.log_writer.clone();
+		let log_lock = self.log_lock.clone();
 		
 		// Log rotation task
 		tokio::spawn(async move {
@@ -313,7 +402,7 @@ impl AuditLogger {
 				if let Ok(file) = std::fs::metadata(&audit_config.log_file_path) {
 					if file.len() > audit_config.max_file_size {
 						// Rotate log file
-						if let Err(e) = Self::rotate_log_file(&audit_config.log_file_path, audit_config.max_log_files).await {
+						if let Err(e) = Self::rotate_log_file(&audit_config.log_file_path, audit_config.max_log_files, &log_lock).await {
 							eprintln!("Failed to rotate log file: {}", e);
 						}
 					}
@@ -342,10 +431,11 @@ impl AuditLogger {
 		Ok(())
 	}
 	
-	/**
-	 * Rotates log file
-	 */
-	async fn rotate_log_file(log_path: &str, max_files: u32) -> Result<()> {
+	/// Rotates log file
+	///
+	/// The file is renamed while the log is locked; the next entry finds
+	/// the new file empty and links to the last one in the rotated file.
+	async fn rotate_log_file(log_path: &str, max_files: u32, log_lock: &Mutex<()>) -> Result<()> {
 		let path = Path::new(log_path);
 		let parent = path.parent().unwrap_or(Path::new("."));
 		let stem = path.file_stem().unwrap_or_default().to_string_lossy();
@@ -356,32 +446,85 @@ impl AuditLogger {
 		let backup_name = format!("{}.{}.{}", stem, timestamp, extension);
 		let backup_path = parent.join(backup_name);
 		
-		// Move current log file
-		if path.exists() {
-			std::fs::rename(path, &backup_path)?;
+		// Move current log file; the next append starts a new one
+		let guard = log_lock.lock().await;
+		let file = Self::lock_log_file(log_path)?;
+		std::fs::rename(path, &backup_path)?;
+		drop(file);
+		drop(guard);
+		
+		// Remove the oldest backup files
+		let backups = rotated_logs(path)?;
+		for old in backups.iter().take(backups.len().saturating_sub(max_files as usize)) {
+			let _ = std::fs::remove_file(old);
 		}
 		
-		// Remove old backup files
-		let mut backup_files = Vec::new();
-		if let Ok(entries) = std::fs::read_dir(parent) {
-			for entry in entries {
-				if let Ok(entry) = entry {
-					if let Ok(file_name) = entry.file_name().into_string() {
-						if file_name.starts_with(&format!("{}.", stem)) && file_name.ends_with(&format!(".{}", extension)) {
-							backup_files.push((entry.path(), entry.metadata()?.modified()?));
-						}
-					}
-				}
+		Ok(())
+	}
+	
+	/// Opens the log file for appending
+	///
+	/// Entries include full command lines, so a missing directory and
+	/// a new file are created readable by the owner only.
+	fn open_log_file(log_path: &str) -> Result<File> {
+		if let Some(parent) = Path::new(log_path).parent().filter(|parent| !parent.as_os_str().is_empty()) {
+			DirBuilder::new().recursive(true).mode(0o700).create(parent)?;
+		}
+		let file = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.mode(0o600)
+			.open(log_path)?;
+		Ok(file)
+	}
+	
+	/// Opens the log file and takes an exclusive flock on it
+	///
+	/// The file is opened afresh each time so forked processes do not
+	/// share the lock through an inherited descriptor. If the file was
+	/// rotated while waiting, the new one is opened and locked instead.
+	/// The lock is released when the file is dropped.
+	fn lock_log_file(log_path: &str) -> Result<File> {
+		loop {
+			let file = Self::open_log_file(log_path)?;
+			if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+				return Err(std::io::Error::last_os_error().into());
+			}
+			let locked = file.metadata()?;
+			match std::fs::metadata(log_path) {
+				Ok(current) if current.dev() == locked.dev() && current.ino() == locked.ino() => return Ok(file),
+				_ => continue,
 			}
 		}
-		
-		// Sort by modification time and remove oldest
-		backup_files.sort_by(|a, b| a.1.cmp(&b.1));
-		for (path, _) in backup_files.iter().skip(max_files as usize) {
-			let _ = std::fs::remove_file(path);
+	}
+	
+	/// Finds the chain head from the newest entry on disk
+	///
+	/// An unreadable last entry starts a new chain; the break is then
+	/// reported by verify_log.
+	fn load_chain_head(log_path: &str) -> ChainHead {
+		let segments = log_segments(log_path).unwrap_or_default();
+		for segment in segments.iter().rev() {
+			let last_line = match File::open(segment).and_then(last_line) {
+				Ok(line) => line,
+				Err(_) => continue,
+			};
+			
+			if let Some(line) = last_line {
+				return match serde_json::from_str::<AuditLogEntry>(&line) {
+					Ok(entry) => ChainHead {
+						next_sequence: entry.sequence + 1,
+						hash: entry.hash,
+					},
+					Err(e) => {
+						eprintln!("Cannot continue audit chain from {}: {}", segment.display(), e);
+						ChainHead::default()
+					}
+				};
+			}
 		}
 		
-		Ok(())
+		ChainHead::default()
 	}
 	
 	/**
@@ -390,27 +533,11 @@ impl AuditLogger {
 	async fn update_event_counters(&self, entry: &AuditLogEntry) -> Result<()> {
 		let mut counters = self.event_counters.write().await;
 		
-		match &entry.event {
-			SecurityEvent::SecurityAlert { severity, .. } => {
-				match severity {
-					SecuritySeverity::Critical => counters.critical_count += 1,
-					SecuritySeverity::High => counters.high_count += 1,
-					SecuritySeverity::Medium => counters.medium_count += 1,
-					SecuritySeverity::Low => counters.low_count += 1,
-				}
-			}
-			SecurityEvent::ThreatDetected { severity, .. } => {
-				match severity {
-					SecuritySeverity::Critical => counters.critical_count += 1,
-					SecuritySeverity::High => counters.high_count += 1,
-					SecuritySeverity::Medium => counters.medium_count += 1,
-					SecuritySeverity::Low => counters.low_count += 1,
-				}
-			}
-			_ => {
-				// Default to medium severity for other events
-				counters.medium_count += 1;
-			}
+		match event_severity(&entry.event) {
+			SecuritySeverity::Critical => counters.critical_count += 1,
+			SecuritySeverity::High => counters.high_count += 1,
+			SecuritySeverity::Medium => counters.medium_count += 1,
+			SecuritySeverity::Low => counters.low_count += 1,
 		}
 		
 		Ok(())
@@ -449,11 +576,11 @@ impl AuditLogger {
 				description: alert_message,
 				severity: SecuritySeverity::High,
 				timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
-				attack_vector: "audit_threshold".to_string(),
-				response_action: None,
 			};
 			
-			self.log_event(alert_event).await?;
+			// Recorded directly: going through log_event would check the thresholds again
+			let alert_entry = self.create_log_entry(alert_event).await?;
+			self.append_entry(alert_entry).await?;
 		}
 		
 		Ok(())
@@ -489,9 +616,6 @@ impl AuditLogger {
 					SecurityEvent::SecurityAlert { severity: event_severity, .. } => {
 						std::mem::discriminant(event_severity) == std::mem::discriminant(&severity)
 					}
-					SecurityEvent::ThreatDetected { severity: event_severity, .. } => {
-						std::mem::discriminant(event_severity) == std::mem::discriminant(&severity)
-					}
 					_ => false,
 				}
 			})
@@ -505,16 +629,7 @@ impl AuditLogger {
 	pub async fn get_entries_by_user(&self, user: &str) -> Vec<AuditLogEntry> {
 		let entries = self.log_entries.read().await;
 		entries.iter()
-			.filter(|entry| {
-				match &entry.event {
-					SecurityEvent::CommandExecution { user: event_user, .. } => event_user == user,
-					SecurityEvent::FileAccess { user: event_user, .. } => event_user == user,
-					SecurityEvent::NetworkAccess { user: event_user, .. } => event_user == user,
-					SecurityEvent::PermissionViolation { user: event_user, .. } => event_user == user,
-					SecurityEvent::BehavioralAnomaly { user: event_user, .. } => event_user == user,
-					_ => false,
-				}
-			})
+			.filter(|entry| event_user(&entry.event) == Some(user))
 			.cloned()
 			.collect()
 	}
@@ -546,4 +661,326 @@ impl AuditLogger {
 	pub fn get_config(&self) -> AuditConfig {
 		self.audit_config.clone()
 	}
-} 
\ No newline at end of file
+	
+	/// Sets the encryption manager used to sign entries
+	///
+	/// Entries written afterwards carry an HMAC of their hash, keyed
+	/// from the manager's active key. None stops signing.
+	pub fn set_signer(&mut self, signer: Option<EncryptionManager>) {
+		self.signer = signer;
+	}
+}
+ 
+/**
+ * Result of verifying an audit log's hash chain
+ */
+#[derive(Debug, Clone)]
+pub struct ChainVerification {
+	/// Files checked, oldest first
+	pub segments: Vec<PathBuf>,
+	/// Entries verified before the first broken link
+	pub verified: u64,
+	/// Sequence number of the oldest entry on disk
+	pub first_sequence: Option<u64>,
+	/// Verified entries that carry a signature
+	pub signed: u64,
+	/// Hash of the last verified entry
+	pub last_hash: Option<String>,
+	/// First broken link (if any)
+	pub broken: Option<BrokenLink>,
+}
+
+/**
+ * Location of a broken link in an audit log
+ */
+#[derive(Debug, Clone)]
+pub struct BrokenLink {
+	/// File containing the entry
+	pub path: PathBuf,
+	/// Line number in the file
+	pub line: usize,
+	/// Sequence number of the entry (if it could be read)
+	pub sequence: Option<u64>,
+	/// What failed to verify
+	pub reason: String,
+}
+
+/**
+ * Reads the last non-empty line of a file
+ * 
+ * Reads backwards from the end in blocks, so finding the chain head
+ * does not read the whole log on every append.
+ */
+fn last_line(mut file: File) -> std::io::Result<Option<String>> {
+	const BLOCK: u64 = 8192;
+	let mut end = file.seek(SeekFrom::End(0))?;
+	let mut tail: Vec<u8> = Vec::new();
+	
+	while end > 0 {
+		let start = end.saturating_sub(BLOCK);
+		let mut block = vec![0; (end - start) as usize];
+		file.seek(SeekFrom::Start(start))?;
+		file.read_exact(&mut block)?;
+		block.extend_from_slice(&tail);
+		tail = block;
+		end = start;
+		
+		// A newline before the last line's content means it is complete
+		let content_end = tail.iter().rposition(|byte| !byte.is_ascii_whitespace());
+		if let Some(content_end) = content_end {
+			if let Some(newline) = tail[..content_end].iter().rposition(|&byte| byte == b'\n') {
+				return Ok(Some(String::from_utf8_lossy(&tail[newline + 1..=content_end]).into_owned()));
+			}
+		}
+	}
+	
+	let line = String::from_utf8_lossy(&tail).trim().to_string();
+	Ok(if line.is_empty() { None } else { Some(line) })
+}
+
+/**
+ * Verifies the hash chain of an audit log and its rotated files
+ * 
+ * Each entry must follow on from the previous one in sequence and
+ * previous_hash, and its hash must match its contents. With a signer,
+ * every entry must also carry a valid signature, so an unsigned entry
+ * is a broken link. Checking stops at the first broken link.
+ * Rotated files removed by the retention limit only move the start of
+ * the chain; an oldest entry with sequence 0 must link to GENESIS_HASH.
+ * 
+ * Nothing after the newest entry is checked, so a log cut short (with
+ * or without a signer) still verifies. Comparing last_hash with a copy
+ * kept elsewhere, such as a SIEM export, catches that.
+ */
+pub async fn verify_log(log_path: &str, signer: Option<&EncryptionManager>) -> Result<ChainVerification> {
+	let segments = log_segments(log_path)?;
+	if segments.is_empty() {
+		return Err(anyhow::anyhow!("No audit log found at {}", log_path));
+	}
+	
+	let mut result = ChainVerification {
+		segments: segments.clone(),
+		verified: 0,
+		first_sequence: None,
+		signed: 0,
+		last_hash: None,
+		broken: None,
+	};
+	let mut previous: Option<(u64, String)> = None;
+	
+	for segment in &segments {
+		let reader = BufReader::new(File::open(segment)?);
+		for (index, line) in reader.lines().enumerate() {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+			
+			let broken = |sequence: Option<u64>, reason: String| BrokenLink {
+				path: segment.clone(),
+				line: index + 1,
+				sequence,
+				reason,
+			};
+			
+			let entry: AuditLogEntry = match serde_json::from_str(&line) {
+				Ok(entry) => entry,
+				Err(e) => {
+					result.broken = Some(broken(None, format!("entry cannot be read: {}", e)));
+					return Ok(result);
+				}
+			};
+			
+			let reason = match &previous {
+				None if entry.sequence == 0 && entry.previous_hash != GENESIS_HASH => {
+					Some("first entry does not link to the genesis hash".to_string())
+				}
+				Some((sequence, _)) if entry.sequence != sequence + 1 => {
+					Some(format!("sequence jumps from {} to {}", sequence, entry.sequence))
+				}
+				Some((sequence, hash)) if entry.previous_hash != *hash => {
+					Some(format!("previous_hash does not match entry {}", sequence))
+				}
+				_ if entry.hash != entry.compute_hash()? => {
+					Some("hash does not match the entry contents".to_string())
+				}
+				_ => match (signer, &entry.signature) {
+					(Some(signer), Some(signature)) => {
+						match signer.verify_signature(&signature.key_id, entry.hash.as_bytes(), &signature.mac).await {
+							Ok(true) => None,
+							Ok(false) => Some("signature does not match".to_string()),
+							Err(e) => Some(format!("cannot check signature: {}", e)),
+						}
+					}
+					(Some(_), None) => Some("signature is missing".to_string()),
+					_ => None,
+				},
+			};
+			
+			if let Some(reason) = reason {
+				result.broken = Some(broken(Some(entry.sequence), reason));
+				return Ok(result);
+			}
+			
+			result.first_sequence.get_or_insert(entry.sequence);
+			result.verified += 1;
+			if entry.signature.is_some() {
+				result.signed += 1;
+			}
+			result.last_hash = Some(entry.hash.clone());
+			previous = Some((entry.sequence, entry.hash));
+		}
+	}
+	
+	Ok(result)
+}
+
+/**
+ * Exports an audit log and its rotated files as JSONL for SIEM ingestion
+ * 
+ * Each line is a flat record with an `@timestamp`, event type, user and
+ * severity, followed by the original event and its chain fields so
+ * records can be matched back to a verified log. Returns the number of
+ * records written.
+ */
+pub fn export_log(log_path: &str, out: &mut impl Write) -> Result<u64> {
+	let segments = log_segments(log_path)?;
+	if segments.is_empty() {
+		return Err(anyhow::anyhow!("No audit log found at {}", log_path));
+	}
+	
+	let mut exported = 0;
+	for segment in &segments {
+		let reader = BufReader::new(File::open(segment)?);
+		for (index, line) in reader.lines().enumerate() {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+			
+			let entry: AuditLogEntry = serde_json::from_str(&line)
+				.map_err(|e| anyhow::anyhow!("{}:{}: {}", segment.display(), index + 1, e))?;
+			let record = serde_json::json!({
+				"@timestamp": entry.timestamp.to_rfc3339(),
+				"id": entry.id,
+				"sequence": entry.sequence,
+				"event_type": event_type(&entry.event),
+				"user": event_user(&entry.event),
+				"severity": event_severity(&entry.event),
+				"source_ip": entry.source_ip,
+				"session_id": entry.session_id,
+				"event": entry.event,
+				"hash": entry.hash,
+				"previous_hash": entry.previous_hash,
+				"signed": entry.signature.is_some(),
+			});
+			
+			writeln!(out, "{}", record)?;
+			exported += 1;
+		}
+	}
+	out.flush()?;
+	
+	Ok(exported)
+}
+
+/**
+ * Default log file, in the user's state directory
+ * 
+ * $XDG_STATE_HOME/sare/security_audit.log, falling back to
+ * ~/.local/state when the variable is unset.
+ */
+pub fn default_log_path() -> String {
+	dirs::state_dir()
+		.or_else(dirs::data_local_dir)
+		.unwrap_or_else(std::env::temp_dir)
+		.join("sare")
+		.join("security_audit.log")
+		.to_string_lossy()
+		.into_owned()
+}
+
+/**
+ * Lists the files of an audit log, oldest first
+ * 
+ * Rotated files come first in rotation order, then the current log
+ * file if it exists.
+ */
+pub fn log_segments(log_path: &str) -> Result<Vec<PathBuf>> {
+	let path = Path::new(log_path);
+	let mut segments = rotated_logs(path)?;
+	if path.exists() {
+		segments.push(path.to_path_buf());
+	}
+	Ok(segments)
+}
+
+/**
+ * Lists rotated log files, oldest first
+ * 
+ * Rotated files are named `<stem>.<unix time>.<extension>`; they are
+ * ordered by that time rather than by modification time.
+ */
+fn rotated_logs(path: &Path) -> Result<Vec<PathBuf>> {
+	let parent = match path.parent() {
+		Some(parent) if !parent.as_os_str().is_empty() => parent,
+		_ => Path::new("."),
+	};
+	let prefix = format!("{}.", path.file_stem().unwrap_or_default().to_string_lossy());
+	let suffix = format!(".{}", path.extension().unwrap_or_default().to_string_lossy());
+	
+	let mut rotated = Vec::new();
+	let entries = std::fs::read_dir(parent)
+		.map_err(|e| anyhow::anyhow!("Cannot read {}: {}", parent.display(), e))?;
+	for entry in entries {
+		let entry = entry?;
+		let file_name = entry.file_name().to_string_lossy().into_owned();
+		let stamp = file_name.strip_prefix(&prefix)
+			.and_then(|rest| rest.strip_suffix(&suffix))
+			.and_then(|stamp| stamp.parse::<u64>().ok());
+		if let Some(stamp) = stamp {
+			rotated.push((stamp, entry.path()));
+		}
+	}
+	
+	rotated.sort();
+	Ok(rotated.into_iter().map(|(_, path)| path).collect())
+}
+
+/**
+ * Gets the severity used for alert thresholds and exports
+ * 
+ * Events other than alerts count as medium severity.
+ */
+fn event_severity(event: &SecurityEvent) -> SecuritySeverity {
+	match event {
+		SecurityEvent::SecurityAlert { severity, .. } => severity.clone(),
+		_ => SecuritySeverity::Medium,
+	}
+}
+
+/**
+ * Gets the user an event belongs to (if any)
+ */
+fn event_user(event: &SecurityEvent) -> Option<&str> {
+	match event {
+		SecurityEvent::CommandExecution { user, .. }
+		| SecurityEvent::FileAccess { user, .. }
+		| SecurityEvent::NetworkAccess { user, .. }
+		| SecurityEvent::PermissionViolation { user, .. } => Some(user),
+		SecurityEvent::SecurityAlert { .. } => None,
+	}
+}
+
+/**
+ * Gets the event type name used in exports
+ */
+fn event_type(event: &SecurityEvent) -> &'static str {
+	match event {
+		SecurityEvent::CommandExecution { .. } => "command_execution",
+		SecurityEvent::FileAccess { .. } => "file_access",
+		SecurityEvent::NetworkAccess { .. } => "network_access",
+		SecurityEvent::PermissionViolation { .. } => "permission_violation",
+		SecurityEvent::SecurityAlert { .. } => "security_alert",
+	}
+}
@@ -80,7 +80,7 @@ impl BehavioralAnalyzer {
 		let user = self.extract_user_from_event(event);
 		let timestamp = self.extract_timestamp_from_event(event);
 
-		let user_events = self.user_patterns.entry(user.clone()).or_insert_with(Vec::new);
+		let mut user_events = self.user_patterns.remove(&user).unwrap_or_default();
 		user_events.push(event.clone());
 
 		// Remove old events outside analysis window
@@ -90,11 +90,18 @@ impl BehavioralAnalyzer {
 		});
 
 		// Analyze for behavioral patterns
+		let mut detected = None;
 		for rule in &self.behavior_rules {
-			if let Some(pattern) = self.detect_behavior_pattern(user_events, rule).await? {
-				return Ok(pattern);
+			if let Some(pattern) = self.detect_behavior_pattern(&user_events, rule).await? {
+				detected = Some(pattern);
+				break;
 			}
 		}
+		self.user_patterns.insert(user, user_events);
+
+		if let Some(pattern) = detected {
+			return Ok(pattern);
+		}
 
 		// Return default pattern if no specific pattern detected
 		Ok(BehaviorPattern {
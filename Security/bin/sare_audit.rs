@@ -0,0 +1,163 @@
+/*!
+ * Audit log tool
+ *
+ * Command line tool for checking the integrity of the sare-security
+ * audit log and exporting it for SIEM ingestion.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: sare_audit.rs
+ * Description: sare-audit verify and export commands
+ */
+
+use anyhow::Result;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use sare_security::SecurityConfig;
+use sare_security::audit::{self, AuditConfig};
+use sare_security::encryption::EncryptionManager;
+
+const USAGE: &str = "usage: sare-audit verify [--hmac] [LOG]
+       sare-audit export [--output FILE] [LOG]
+
+  verify    Check the hash chain and report the first broken link
+  export    Write the log as JSONL records for SIEM ingestion
+
+  --hmac         Also check entry signatures with the stored keys;
+                 every entry must be signed (security.sign_audit_log)
+  --output FILE  Write the export to FILE instead of stdout
+
+LOG defaults to $XDG_STATE_HOME/sare/security_audit.log (or
+~/.local/state/sare/security_audit.log); rotated files next to it are
+included.
+
+verify cannot tell a log whose newest entries were removed from one
+that ended there, even with --hmac. It prints the last sequence number
+and hash; compare them with a copy kept elsewhere, such as an export.";
+
+#[tokio::main]
+async fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+
+	let code = match run(&args).await {
+		Ok(code) => code,
+		Err(e) => {
+			eprintln!("sare-audit: {}", e);
+			2
+		}
+	};
+	std::process::exit(code);
+}
+
+/**
+ * Runs a sare-audit command
+ *
+ * Returns the exit code: 0 on success, 1 if verification found a
+ * broken link.
+ */
+async fn run(args: &[String]) -> Result<i32> {
+	let (command, rest) = match args.split_first() {
+		Some((command, rest)) => (command.as_str(), rest),
+		None => return Err(anyhow::anyhow!("missing command\n{}", USAGE)),
+	};
+
+	let mut hmac = false;
+	let mut output: Option<String> = None;
+	let mut log_path: Option<String> = None;
+	let mut options = rest.iter();
+	while let Some(arg) = options.next() {
+		match arg.as_str() {
+			"--hmac" if command == "verify" => hmac = true,
+			"--output" if command == "export" => {
+				output = Some(options.next().cloned()
+					.ok_or_else(|| anyhow::anyhow!("--output requires a file"))?);
+			}
+			"-h" | "--help" => {
+				println!("{}", USAGE);
+				return Ok(0);
+			}
+			option if option.starts_with('-') => {
+				return Err(anyhow::anyhow!("unknown option: {}\n{}", option, USAGE));
+			}
+			path if log_path.is_none() => log_path = Some(path.to_string()),
+			extra => return Err(anyhow::anyhow!("unexpected argument: {}", extra)),
+		}
+	}
+	let log_path = log_path.unwrap_or_else(|| AuditConfig::default().log_file_path);
+
+	match command {
+		"verify" => verify(&log_path, hmac).await,
+		"export" => export(&log_path, output.as_deref()),
+		"-h" | "--help" | "help" => {
+			println!("{}", USAGE);
+			Ok(0)
+		}
+		other => Err(anyhow::anyhow!("unknown command: {}\n{}", other, USAGE)),
+	}
+}
+
+/**
+ * Verifies the log and prints the first broken link
+ */
+async fn verify(log_path: &str, hmac: bool) -> Result<i32> {
+	let signer = if hmac {
+		let config = Arc::new(RwLock::new(SecurityConfig::default()));
+		Some(EncryptionManager::new(config).await?)
+	} else {
+		None
+	};
+
+	let result = audit::verify_log(log_path, signer.as_ref()).await?;
+	let first = result.first_sequence.unwrap_or(0);
+
+	match result.broken {
+		Some(broken) => {
+			println!("BROKEN: {}:{}", broken.path.display(), broken.line);
+			match broken.sequence {
+				Some(sequence) => println!("  entry {}: {}", sequence, broken.reason),
+				None => println!("  {}", broken.reason),
+			}
+			println!("  {} entries verified before the break", result.verified);
+			Ok(1)
+		}
+		None => {
+			println!("OK: {} entries in {} files", result.verified, result.segments.len());
+			if result.verified > 0 {
+				println!("  sequence {}-{}", first, first + result.verified - 1);
+			}
+			if let Some(last_hash) = &result.last_hash {
+				println!("  last hash {}", last_hash);
+			}
+			if first > 0 {
+				println!("  entries before {} were removed by rotation", first);
+			}
+			if hmac {
+				println!("  {} signatures checked", result.signed);
+			} else if result.signed > 0 {
+				println!("  {} signed entries (use --hmac to check signatures)", result.signed);
+			}
+			Ok(0)
+		}
+	}
+}
+
+/**
+ * Exports the log as JSONL to a file or stdout
+ */
+fn export(log_path: &str, output: Option<&str>) -> Result<i32> {
+	match output {
+		Some(path) => {
+			let mut out = BufWriter::new(File::create(path)?);
+			let exported = audit::export_log(log_path, &mut out)?;
+			eprintln!("Exported {} entries to {}", exported, path);
+		}
+		None => {
+			audit::export_log(log_path, &mut std::io::stdout().lock())?;
+		}
+	}
+
+	Ok(0)
+}
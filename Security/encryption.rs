@@ -14,14 +14,18 @@ use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
-use aes_gcm::{Aes256Gcm, Key, Nonce};
-use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes256Gcm, Nonce};
+use aes_gcm::aead::{Aead, KeyInit};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use rand::{Rng, RngCore};
 use base64::{Engine as _, engine::general_purpose};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
 
-use super::{SecurityConfig, SecurityEvent, SecuritySeverity};
+use super::SecurityConfig;
 
 /**
  * Encryption algorithms
@@ -166,10 +170,11 @@ impl EncryptionManager {
 		let nonce = Nonce::from_slice(&nonce_bytes);
 		
 		// Create cipher
-		let cipher = Aes256Gcm::new_from_slice(&key.key_data.as_bytes())?;
+		let cipher = Aes256Gcm::new_from_slice(&Self::key_bytes(&key)?)?;
 		
 		// Encrypt data
-		let ciphertext = cipher.encrypt(nonce, data)?;
+		let ciphertext = cipher.encrypt(nonce, data)
+			.map_err(|_| anyhow::anyhow!("Encryption failed"))?;
 		
 		// Create encrypted data structure
 		let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
@@ -212,14 +217,60 @@ impl EncryptionManager {
 		let ciphertext = general_purpose::STANDARD.decode(&encrypted_data.ciphertext)?;
 		
 		// Create cipher
-		let cipher = Aes256Gcm::new_from_slice(&key.key_data.as_bytes())?;
+		let cipher = Aes256Gcm::new_from_slice(&Self::key_bytes(key)?)?;
 		
 		// Decrypt data
-		let plaintext = cipher.decrypt(nonce, ciphertext.as_ref())?;
+		let plaintext = cipher.decrypt(nonce, ciphertext.as_ref())
+			.map_err(|_| anyhow::anyhow!("Decryption failed"))?;
 		
 		Ok(plaintext)
 	}
 	
+	/// Computes an HMAC-SHA256 of data with the active key
+	///
+	/// Returns the key ID with the base64 MAC so the MAC can still be
+	/// checked after the key has been rotated.
+	pub async fn sign(&self, data: &[u8]) -> Result<(String, String)> {
+		let key = self.get_active_key().await?;
+		let mut mac = Self::mac_for(&key)?;
+		mac.update(data);
+		
+		Ok((key.key_id, general_purpose::STANDARD.encode(mac.finalize().into_bytes())))
+	}
+	
+	/// Checks an HMAC produced by sign
+	pub async fn verify_signature(&self, key_id: &str, data: &[u8], signature: &str) -> Result<bool> {
+		// The key may have been created by another process since startup
+		if !self.keys.read().await.contains_key(key_id) {
+			self.load_keys_from_storage().await?;
+		}
+		
+		let keys = self.keys.read().await;
+		let key = keys.get(key_id)
+			.ok_or_else(|| anyhow::anyhow!("Key not found: {}", key_id))?;
+		let mut mac = Self::mac_for(key)?;
+		mac.update(data);
+		
+		let expected = general_purpose::STANDARD.decode(signature)?;
+		Ok(mac.verify_slice(&expected).is_ok())
+	}
+	
+	/// Creates an HMAC keyed from an encryption key
+	///
+	/// The MAC key is derived from the key data so the AES key itself is
+	/// never used for both purposes.
+	fn mac_for(key: &EncryptionKey) -> Result<Hmac<Sha256>> {
+		let mut derive = <Hmac<Sha256> as Mac>::new_from_slice(&Self::key_bytes(key)?)?;
+		derive.update(b"sare-security hmac");
+		
+		Ok(<Hmac<Sha256> as Mac>::new_from_slice(&derive.finalize().into_bytes())?)
+	}
+	
+	/// Decodes the raw bytes of a key
+	fn key_bytes(key: &EncryptionKey) -> Result<Vec<u8>> {
+		Ok(general_purpose::STANDARD.decode(&key.key_data)?)
+	}
+	
 	/**
 	 * Generates a new encryption key
 	 */
@@ -303,7 +354,8 @@ impl EncryptionManager {
 	async fn generate_key_id(&self) -> Result<String> {
 		let mut rng = rand::thread_rng();
 		let id_bytes: [u8; 16] = rng.gen();
-		let key_id = general_purpose::STANDARD.encode(id_bytes);
+		// URL-safe so the ID can be used as a file name
+		let key_id = general_purpose::URL_SAFE_NO_PAD.encode(id_bytes);
 		
 		Ok(format!("key_{}", key_id))
 	}
@@ -316,13 +368,22 @@ impl EncryptionManager {
 			return Ok(());
 		}
 		
-		// Create storage directory
-		std::fs::create_dir_all(&self.encryption_config.key_storage_path)?;
+		// Create storage directory, readable only by the owner
+		std::fs::DirBuilder::new()
+			.recursive(true)
+			.mode(0o700)
+			.create(&self.encryption_config.key_storage_path)?;
 		
 		// Save key to file
 		let key_path = format!("{}/{}.key", self.encryption_config.key_storage_path, key.key_id);
 		let key_data = serde_json::to_string(key)?;
-		std::fs::write(key_path, key_data)?;
+		std::fs::OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.mode(0o600)
+			.open(key_path)?
+			.write_all(key_data.as_bytes())?;
 		
 		Ok(())
 	}
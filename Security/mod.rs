@@ -20,9 +20,14 @@ pub mod response_automation;
 pub mod behavioral_analysis;
 pub mod forensic_capture;
 pub mod deception_system;
+pub mod validation;
+pub mod permissions;
+pub mod audit;
+pub mod encryption;
+pub mod sandbox;
 
 use threat_detection::{ThreatDetector, ThreatType, ThreatScore};
-use response_automation::{ResponseAutomation, ThreatResponseAction};
+use response_automation::ResponseAutomation;
 use behavioral_analysis::{BehavioralAnalyzer, BehaviorPattern};
 use forensic_capture::{ForensicCapture, EvidenceType};
 use deception_system::{DeceptionSystem, HoneypotManager};
@@ -240,7 +245,7 @@ impl SecurityManager {
 		
 		let actions = self.response_automation.determine_response(
 			&event,
-			threat_score,
+			threat_score.clone(),
 			threat_type,
 			&behavior_pattern,
 		).await?;
@@ -17,6 +17,7 @@ use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{Datelike, Timelike};
 
 use super::{SecurityConfig, SecurityEvent, SecuritySeverity};
 
@@ -462,7 +463,7 @@ impl PermissionManager {
 		}
 		
 		let now = chrono::Utc::now();
-		let weekday = now.weekday().num_days_from_sunday();
+		let weekday = now.weekday().num_days_from_sunday() as u8;
 		let hour = now.hour() as u8;
 		
 		if !restrictions.allowed_days.is_empty() && !restrictions.allowed_days.contains(&weekday) {
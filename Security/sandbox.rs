@@ -10,6 +10,11 @@
  * prevent privilege escalation and system compromise. Sandboxed
  * processes are monitored for security violations and resource
  * usage to maintain system integrity.
+ * 
+ * Namespaces and resource limits are applied inside the forked child
+ * between fork and exec, through an unprivileged user namespace, so
+ * the calling process is never restricted and no external unshare
+ * binary is needed.
  */
 
 use anyhow::Result;
@@ -17,14 +22,14 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
 use std::process::{Command, Stdio};
 use std::time::{SystemTime, UNIX_EPOCH};
-use nix::unistd::{unshare, setuid, setgid};
-use nix::sched::CloneFlags;
-use nix::sys::resource::{setrlimit, Resource, Rlimit};
-use libc::{uid_t, gid_t};
 
-use super::{SecurityConfig, SecurityEvent, SecuritySeverity};
+use super::{SecurityConfig, SecuritySeverity};
 
 /**
  * Sandbox configuration defines comprehensive process isolation settings
@@ -135,6 +140,317 @@ pub struct SecurityViolation {
 	pub severity: SecuritySeverity,
 }
 
+/**
+ * Child-side sandbox setup prepared before fork
+ * 
+ * Everything is allocated in the parent so that the pre_exec hook
+ * only makes async-signal-safe system calls. Built from a
+ * SandboxConfig and narrowed further with the builder methods, so
+ * the shell's sandbox builtin and SandboxManager share one setup.
+ */
+#[derive(Debug, Clone)]
+pub struct ChildSetup {
+	namespaces: libc::c_int,
+	uid_map: Vec<u8>,
+	gid_map: Vec<u8>,
+	read_only: Vec<CString>,
+	limits: Vec<(libc::__rlimit_resource_t, libc::rlim_t)>,
+	filter: Vec<libc::sock_filter>,
+}
+
+impl ChildSetup {
+	pub fn new(config: &SandboxConfig) -> Self {
+		let uid = unsafe { libc::geteuid() };
+		let gid = unsafe { libc::getegid() };
+		
+		let namespaces = if config.namespace_isolation {
+			libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET | libc::CLONE_NEWUTS | libc::CLONE_NEWIPC
+		} else {
+			0
+		};
+		
+		let limits = if config.resource_limits {
+			vec![
+				(libc::RLIMIT_CPU, config.max_cpu_time),
+				(libc::RLIMIT_AS, config.max_memory),
+				(libc::RLIMIT_FSIZE, config.max_file_size),
+				(libc::RLIMIT_NPROC, config.max_processes),
+				(libc::RLIMIT_NOFILE, config.max_open_files),
+			]
+		} else {
+			Vec::new()
+		};
+		
+		Self {
+			namespaces,
+			uid_map: format!("{} {} 1\n", uid, uid).into_bytes(),
+			gid_map: format!("{} {} 1\n", gid, gid).into_bytes(),
+			read_only: Vec::new(),
+			limits,
+			filter: Vec::new(),
+		}
+	}
+	
+	/// Keeps the host network instead of a namespace with only loopback
+	pub fn share_network(mut self) -> Self {
+		self.namespaces &= !libc::CLONE_NEWNET;
+		self
+	}
+	
+	/// Bind-mounts an existing path read-only inside the mount namespace
+	///
+	/// The path should be canonical; symlinks are not resolved in the child.
+	pub fn read_only(mut self, path: &Path) -> Result<Self> {
+		if self.namespaces & libc::CLONE_NEWNS == 0 {
+			return Err(anyhow::anyhow!("read-only paths need namespace isolation"));
+		}
+		self.read_only.push(CString::new(path.as_os_str().as_bytes())?);
+		Ok(self)
+	}
+	
+	/// Lowers a resource limit to the given value, soft and hard
+	pub fn limit(mut self, resource: libc::__rlimit_resource_t, value: u64) -> Self {
+		self.limits.push((resource, value));
+		self
+	}
+	
+	/// Installs the seccomp filter built by seccomp_filter()
+	pub fn seccomp(mut self) -> Self {
+		self.filter = seccomp_filter();
+		self
+	}
+	
+	/// Applies the setup in the forked child, before exec
+	///
+	/// In order:
+	///
+	/// 1. Enters the new namespaces with the caller's uid and gid
+	///    mapped to themselves and setgroups denied.
+	/// 2. Stops mount propagation, then bind-mounts each read-only
+	///    path onto itself and remounts it read-only, keeping the
+	///    nosuid-style flags statvfs() reports.
+	/// 3. Lowers the resource limits, never above the caller's hard limit.
+	/// 4. Sets no_new_privs and loads the seccomp filter.
+	///
+	/// The child holds every capability inside the user namespace, and
+	/// a non-root caller loses them at exec. A root caller keeps them,
+	/// but with the filter loaded it cannot mount, use the new mount
+	/// API, unshare, setns or clone into new namespaces, so the
+	/// read-only mounts stay in place. Without the filter they can be
+	/// undone. Only system calls are made, so this is safe to run from
+	/// pre_exec.
+	pub fn apply(&self) -> std::io::Result<()> {
+		unsafe {
+			if self.namespaces != 0 {
+				if libc::unshare(self.namespaces) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+				write_proc_file(c"/proc/self/setgroups", b"deny")?;
+				write_proc_file(c"/proc/self/uid_map", &self.uid_map)?;
+				write_proc_file(c"/proc/self/gid_map", &self.gid_map)?;
+			}
+			
+			if self.namespaces & libc::CLONE_NEWNS != 0 {
+				let root = c"/".as_ptr();
+				if libc::mount(std::ptr::null(), root, std::ptr::null(), libc::MS_REC | libc::MS_PRIVATE, std::ptr::null()) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+			}
+			
+			for path in &self.read_only {
+				let target = path.as_ptr();
+				if libc::mount(target, target, std::ptr::null(), libc::MS_BIND | libc::MS_REC, std::ptr::null()) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+				// Flags locked by the outer namespace must be repeated or the remount is refused
+				let mut stat: libc::statvfs = std::mem::zeroed();
+				if libc::statvfs(target, &mut stat) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+				let flags = libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | locked_flags(stat.f_flag);
+				if libc::mount(std::ptr::null(), target, std::ptr::null(), flags, std::ptr::null()) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+			}
+			
+			for (resource, value) in &self.limits {
+				// Never raise a limit the caller already had lower
+				let mut current: libc::rlimit = std::mem::zeroed();
+				if libc::getrlimit(*resource, &mut current) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+				let limit = libc::rlimit {
+					rlim_cur: (*value).min(current.rlim_max),
+					rlim_max: (*value).min(current.rlim_max),
+				};
+				if libc::setrlimit(*resource, &limit) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+			}
+			
+			if !self.filter.is_empty() {
+				let program = libc::sock_fprog {
+					len: self.filter.len() as libc::c_ushort,
+					filter: self.filter.as_ptr() as *mut libc::sock_filter,
+				};
+				if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+				if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/**
+ * Writes a buffer to a file under /proc without allocating
+ */
+fn write_proc_file(path: &CStr, contents: &[u8]) -> std::io::Result<()> {
+	unsafe {
+		let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+		if fd < 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+		let written = libc::write(fd, contents.as_ptr() as *const libc::c_void, contents.len());
+		let error = std::io::Error::last_os_error();
+		libc::close(fd);
+		if written != contents.len() as isize {
+			return Err(error);
+		}
+	}
+	Ok(())
+}
+
+/**
+ * Converts statvfs flags into the mount flags a remount must keep
+ */
+fn locked_flags(flags: libc::c_ulong) -> libc::c_ulong {
+	[
+		(libc::ST_NOSUID, libc::MS_NOSUID),
+		(libc::ST_NODEV, libc::MS_NODEV),
+		(libc::ST_NOEXEC, libc::MS_NOEXEC),
+		(libc::ST_NOATIME, libc::MS_NOATIME),
+		(libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+		(libc::ST_RELATIME, libc::MS_RELATIME),
+	]
+	.iter()
+	.filter(|(stat, _)| flags & stat != 0)
+	.fold(0, |mount, (_, flag)| mount | flag)
+}
+
+/// AUDIT_ARCH value the filter accepts, None where no filter is built
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_003e);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_00b7);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: Option<u32> = None;
+
+/// System calls that fail with EPERM inside the sandbox
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const BLOCKED_SYSCALLS: &[libc::c_long] = &[
+	libc::SYS_mount,
+	libc::SYS_umount2,
+	libc::SYS_pivot_root,
+	libc::SYS_mount_setattr,
+	libc::SYS_open_tree,
+	libc::SYS_move_mount,
+	libc::SYS_fsopen,
+	libc::SYS_fsmount,
+	libc::SYS_fsconfig,
+	libc::SYS_fspick,
+	libc::SYS_unshare,
+	libc::SYS_setns,
+	libc::SYS_ptrace,
+	libc::SYS_process_vm_readv,
+	libc::SYS_process_vm_writev,
+	libc::SYS_kexec_load,
+	libc::SYS_init_module,
+	libc::SYS_finit_module,
+	libc::SYS_delete_module,
+	libc::SYS_reboot,
+	libc::SYS_swapon,
+	libc::SYS_swapoff,
+	libc::SYS_bpf,
+	libc::SYS_perf_event_open,
+	libc::SYS_open_by_handle_at,
+	libc::SYS_keyctl,
+	libc::SYS_add_key,
+	libc::SYS_request_key,
+];
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const BLOCKED_SYSCALLS: &[libc::c_long] = &[];
+
+/// clone() flags that create namespaces; CLONE_NEWTIME shares its bit with the exit signal
+const CLONE_NAMESPACES: libc::c_int = libc::CLONE_NEWNS
+	| libc::CLONE_NEWCGROUP
+	| libc::CLONE_NEWUTS
+	| libc::CLONE_NEWIPC
+	| libc::CLONE_NEWUSER
+	| libc::CLONE_NEWPID
+	| libc::CLONE_NEWNET;
+
+/**
+ * Builds the seccomp BPF program
+ * 
+ * Calls from another architecture, such as the 32-bit compat ABI,
+ * kill the process, as do x32 numbers (0x40000000 and up) on x86_64.
+ * BLOCKED_SYSCALLS fail with EPERM, and so does clone() when its
+ * flags include CLONE_NAMESPACES. clone3 keeps its flags in a struct
+ * BPF cannot read, so it fails with ENOSYS and libc falls back to
+ * clone(). Everything else is allowed. Unsupported architectures get
+ * an empty program and no filter is loaded.
+ */
+fn seccomp_filter() -> Vec<libc::sock_filter> {
+	let Some(arch) = AUDIT_ARCH else {
+		return Vec::new();
+	};
+	
+	// Offsets into struct seccomp_data; ARG0 is the low half of args[0] on little-endian targets
+	const NR: u32 = 0;
+	const ARCH: u32 = 4;
+	const ARG0: u32 = 16;
+	let load = |offset| bpf(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset, 0, 0);
+	let kill = bpf(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS, 0, 0);
+	let fail = |errno: libc::c_int| bpf(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ERRNO | errno as u32, 0, 0);
+	let allow = bpf(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW, 0, 0);
+	
+	let mut program = vec![
+		load(ARCH),
+		bpf(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, arch, 1, 0),
+		kill,
+		load(NR),
+	];
+	if cfg!(target_arch = "x86_64") {
+		program.push(bpf(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, 0x4000_0000, 0, 1));
+		program.push(kill);
+	}
+	for syscall in BLOCKED_SYSCALLS {
+		program.push(bpf(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, *syscall as u32, 0, 1));
+		program.push(fail(libc::EPERM));
+	}
+	program.extend([
+		bpf(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, libc::SYS_clone3 as u32, 0, 1),
+		fail(libc::ENOSYS),
+		bpf(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, libc::SYS_clone as u32, 0, 3),
+		load(ARG0),
+		bpf(libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K, CLONE_NAMESPACES as u32, 0, 1),
+		fail(libc::EPERM),
+		allow,
+	]);
+	program
+}
+
+/**
+ * Builds one BPF instruction
+ */
+fn bpf(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+	libc::sock_filter { code: code as u16, jt, jf, k }
+}
+
 /**
  * Sandbox manager implements comprehensive process sandboxing
  * capabilities including process creation, monitoring, and security
@@ -146,7 +462,6 @@ pub struct SandboxManager {
 	config: Arc<RwLock<SecurityConfig>>,
 	sandbox_config: SandboxConfig,
 	processes: Arc<RwLock<HashMap<u32, SandboxedProcess>>>,
-	process_counter: Arc<RwLock<u32>>,
 	active: bool,
 }
 
@@ -158,36 +473,34 @@ impl SandboxManager {
 			config,
 			sandbox_config,
 			processes: Arc::new(RwLock::new(HashMap::new())),
-			process_counter: Arc::new(RwLock::new(1)),
 			active: true,
 		})
 	}
 	
+	/// Runs a command with sh -c inside the sandbox
+	///
+	/// Namespaces and resource limits are set up in the child between
+	/// fork and exec; the calling process keeps its own limits. Output
+	/// is discarded, the manager only tracks the process. Returns the
+	/// process ID, which the other methods take.
 	pub async fn create_process(&self, command: &str, user: &str) -> Result<u32> {
-		let pid = {
-			let mut counter = self.process_counter.write().await;
-			*counter += 1;
-			*counter
-		};
-		
 		let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 		
-		let mut child_process = Command::new("unshare")
-			.args(&["--pid", "--mount", "--net", "--uts", "--ipc"])
-			.arg("--")
-			.arg("sh")
+		let setup = ChildSetup::new(&self.sandbox_config);
+		let mut sandboxed = Command::new("sh");
+		sandboxed
 			.arg("-c")
 			.arg(command)
-			.stdout(Stdio::piped())
-			.stderr(Stdio::piped())
-			.spawn()?;
-		
-		if self.sandbox_config.resource_limits {
-			self.set_resource_limits(child_process.id())?;
+			.stdin(Stdio::null())
+			.stdout(Stdio::null())
+			.stderr(Stdio::null());
+		unsafe {
+			sandboxed.pre_exec(move || setup.apply());
 		}
+		let pid = sandboxed.spawn()?.id();
 		
 		let sandboxed_process = SandboxedProcess {
-			pid: child_process.id(),
+			pid,
 			command: command.to_string(),
 			user: user.to_string(),
 			status: ProcessStatus::Running,
@@ -212,40 +525,6 @@ impl SandboxManager {
 		Ok(pid)
 	}
 	
-	fn set_resource_limits(&self, pid: u32) -> Result<()> {
-		let cpu_limit = Rlimit {
-			rlim_cur: self.sandbox_config.max_cpu_time,
-			rlim_max: self.sandbox_config.max_cpu_time,
-		};
-		setrlimit(Resource::RLIMIT_CPU, cpu_limit)?;
-		
-		let memory_limit = Rlimit {
-			rlim_cur: self.sandbox_config.max_memory,
-			rlim_max: self.sandbox_config.max_memory,
-		};
-		setrlimit(Resource::RLIMIT_AS, memory_limit)?;
-		
-		let file_size_limit = Rlimit {
-			rlim_cur: self.sandbox_config.max_file_size,
-			rlim_max: self.sandbox_config.max_file_size,
-		};
-		setrlimit(Resource::RLIMIT_FSIZE, file_size_limit)?;
-		
-		let process_limit = Rlimit {
-			rlim_cur: self.sandbox_config.max_processes,
-			rlim_max: self.sandbox_config.max_processes,
-		};
-		setrlimit(Resource::RLIMIT_NPROC, process_limit)?;
-		
-		let open_files_limit = Rlimit {
-			rlim_cur: self.sandbox_config.max_open_files,
-			rlim_max: self.sandbox_config.max_open_files,
-		};
-		setrlimit(Resource::RLIMIT_NOFILE, open_files_limit)?;
-		
-		Ok(())
-	}
-	
 	async fn start_monitoring(&self, pid: u32) -> Result<()> {
 		let processes = self.processes.clone();
 		let config = self.config.clone();
@@ -263,17 +542,21 @@ impl SandboxManager {
 						}
 						
 						if let Some(violation) = Self::check_security_violations(process, &config).await {
+							let critical = violation.severity == SecuritySeverity::Critical;
 							process.security_violations.push(violation);
 							
-							if violation.severity == SecuritySeverity::Critical {
-								Self::terminate_process(pid).await;
+							if critical {
+								Self::kill_process(pid);
 								process.status = ProcessStatus::Terminated;
 								process.end_time = Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
 							}
 						}
 						
-						if !Self::is_process_running(pid).await {
-							process.status = ProcessStatus::Completed;
+						if let Some(exit_code) = Self::reap_process(pid) {
+							if !matches!(process.status, ProcessStatus::Terminated) {
+								process.status = ProcessStatus::Completed;
+							}
+							process.exit_code = Some(exit_code);
 							process.end_time = Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
 							break;
 						}
@@ -308,7 +591,7 @@ impl SandboxManager {
 					.filter_map(|entry| entry.ok())
 					.filter_map(|entry| entry.file_name().into_string().ok())
 					.filter_map(|name| name.parse::<u32>().ok())
-					.filter_map(|child_pid| {
+					.filter(|child_pid| {
 						if let Ok(stat_content) = std::fs::read_to_string(format!("/proc/{}/stat", child_pid)) {
 							let fields: Vec<&str> = stat_content.split_whitespace().collect();
 							if let Some(ppid_str) = fields.get(3) {
@@ -317,7 +600,7 @@ impl SandboxManager {
 								}
 							}
 						}
-						None
+						false
 					})
 					.count() as u32
 			})
@@ -376,23 +659,44 @@ impl SandboxManager {
 		None
 	}
 	
-	async fn is_process_running(pid: u32) -> bool {
-		std::fs::metadata(format!("/proc/{}", pid)).is_ok()
+	/// Collects the exit status of a finished child without blocking
+	///
+	/// Returns the exit code, or 128 plus the signal number when the
+	/// process was killed; None while it is still running.
+	fn reap_process(pid: u32) -> Option<i32> {
+		let mut status = 0;
+		match unsafe { libc::waitpid(pid as libc::pid_t, &mut status, libc::WNOHANG) } {
+			0 => None,
+			reaped if reaped == pid as libc::pid_t => Some(if libc::WIFSIGNALED(status) {
+				128 + libc::WTERMSIG(status)
+			} else {
+				libc::WEXITSTATUS(status)
+			}),
+			// Already collected elsewhere: finished once it is gone from /proc
+			_ => (!std::path::Path::new(&format!("/proc/{}", pid)).exists()).then_some(-1),
+		}
 	}
 	
-	async fn terminate_process(pid: u32) {
-		let _ = Command::new("kill")
-			.args(&["-9", &pid.to_string()])
-			.output();
+	fn kill_process(pid: u32) {
+		unsafe {
+			libc::kill(pid as libc::pid_t, libc::SIGKILL);
+		}
 	}
 	
 	pub async fn terminate_process(&self, pid: u32) -> Result<()> {
-		if let Some(process) = self.processes.write().await.get_mut(&pid) {
-			process.status = ProcessStatus::Terminated;
-			process.end_time = Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
-		}
+		let mut processes = self.processes.write().await;
+		let Some(process) = processes.get_mut(&pid) else {
+			return Err(anyhow::anyhow!("no sandboxed process {}", pid));
+		};
+		Self::kill_process(pid);
 		
-		Self::terminate_process(pid).await;
+		// SIGKILL cannot be caught, so the wait is short; reap here because the monitor skips terminated processes
+		let mut status = 0;
+		if unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) } == pid as libc::pid_t && libc::WIFSIGNALED(status) {
+			process.exit_code = Some(128 + libc::WTERMSIG(status));
+		}
+		process.status = ProcessStatus::Terminated;
+		process.end_time = Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
 		Ok(())
 	}
 	
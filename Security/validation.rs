@@ -109,7 +109,7 @@ impl ValidationPatterns {
 				Regex::new(r"fdisk")?,
 				Regex::new(r"dd\s+if=")?,
 			],
-			dangerous_chars: Regex::new(r"[;&|`$(){}[\]<>]")?,
+			dangerous_chars: Regex::new(r"[;&|`$(){}\[\]<>]")?,
 		})
 	}
 }
@@ -174,6 +174,41 @@ impl InputValidator {
 		Ok(true)
 	}
 	
+	/// Validates a command that is executed without a shell
+	///
+	/// The words are passed to the program as they are, so shell
+	/// metacharacters in arguments are plain data and only the program
+	/// name is held to the command rules. Blocked patterns are matched
+	/// against the words joined with single spaces. Relative paths such
+	/// as `../notes.txt` are ordinary arguments here; paths are checked
+	/// with validate_path where a caller wants that.
+	pub async fn validate_argv(&self, argv: &[String]) -> Result<bool> {
+		if !self.active || !self.validation_config.command_validation {
+			return Ok(true);
+		}
+		
+		let Some(program) = argv.first() else {
+			return Ok(true);
+		};
+		
+		let line = argv.join(" ");
+		if line.len() > self.validation_config.max_command_length {
+			return Ok(false);
+		}
+		
+		for pattern in &self.patterns.blocked_patterns {
+			if pattern.is_match(&line) {
+				return Ok(false);
+			}
+		}
+		
+		if self.patterns.dangerous_chars.is_match(program) || !self.patterns.command_regex.is_match(program) {
+			return Ok(false);
+		}
+		
+		Ok(true)
+	}
+	
 	pub async fn validate_path(&self, path: &str) -> Result<bool> {
 		if !self.active || !self.validation_config.path_validation {
 			return Ok(true);
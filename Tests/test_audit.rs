@@ -0,0 +1,280 @@
+/*!
+ * Audit log verification tests for sare-security
+ *
+ * Writes hash-chained logs to scratch directories, damages them the
+ * way an attacker or a bad rotation would, and checks where
+ * verify_log reports the first broken link.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_audit.rs
+ * Description: Tests for audit log chain and signature verification
+ */
+
+use sare_security::{SecurityConfig, SecurityEvent};
+use sare_security::audit::{self, AuditConfig, AuditLogger};
+use sare_security::encryption::EncryptionManager;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_audit_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Creates a logger writing to the given file
+ */
+async fn logger(log_path: &str) -> AuditLogger {
+	let config = Arc::new(RwLock::new(SecurityConfig::default()));
+	let audit_config = AuditConfig { log_file_path: log_path.to_string(), ..AuditConfig::default() };
+	AuditLogger::with_config(config, audit_config).await.unwrap()
+}
+
+/**
+ * Logs one command execution per command
+ */
+async fn log_commands(logger: &AuditLogger, commands: &[&str]) {
+	for command in commands {
+		logger.log_event(SecurityEvent::CommandExecution {
+			command: command.to_string(),
+			user: "tester".to_string(),
+			timestamp: 0,
+			success: true,
+		}).await.unwrap();
+	}
+}
+
+/**
+ * Writes a log of five entries and returns its path
+ */
+async fn five_entry_log(name: &str) -> String {
+	let log_path = scratch_dir(name).join("audit.log").to_string_lossy().into_owned();
+	log_commands(&logger(&log_path).await, &["ls", "pwd", "whoami", "date", "id"]).await;
+	log_path
+}
+
+/**
+ * Rewrites the lines of a log file
+ */
+fn edit_lines(log_path: &str, edit: impl FnOnce(&mut Vec<String>)) {
+	let mut lines: Vec<String> = std::fs::read_to_string(log_path).unwrap().lines().map(str::to_string).collect();
+	edit(&mut lines);
+	std::fs::write(log_path, lines.join("\n") + "\n").unwrap();
+}
+
+/**
+ * Test an intact log verifying
+ */
+#[tokio::test]
+async fn test_intact_log() {
+	let log_path = five_entry_log("intact").await;
+	let result = audit::verify_log(&log_path, None).await.unwrap();
+
+	assert!(result.broken.is_none(), "broken: {:?}", result.broken);
+	assert_eq!(result.verified, 5);
+	assert_eq!(result.first_sequence, Some(0));
+}
+
+/**
+ * Test a changed field breaking the entry's hash
+ */
+#[tokio::test]
+async fn test_tampered_field() {
+	let log_path = five_entry_log("tampered").await;
+	edit_lines(&log_path, |lines| lines[2] = lines[2].replace("\"whoami\"", "\"true\""));
+
+	let broken = audit::verify_log(&log_path, None).await.unwrap().broken.unwrap();
+	assert_eq!(broken.line, 3);
+	assert_eq!(broken.sequence, Some(2));
+	assert_eq!(broken.reason, "hash does not match the entry contents");
+}
+
+/**
+ * Test a deleted line breaking the sequence
+ */
+#[tokio::test]
+async fn test_deleted_line() {
+	let log_path = five_entry_log("deleted").await;
+	edit_lines(&log_path, |lines| {
+		lines.remove(2);
+	});
+
+	let result = audit::verify_log(&log_path, None).await.unwrap();
+	let broken = result.broken.unwrap();
+	assert_eq!(broken.line, 3);
+	assert_eq!(broken.reason, "sequence jumps from 1 to 3");
+	assert_eq!(result.verified, 2);
+}
+
+/**
+ * Test swapped lines breaking the sequence
+ */
+#[tokio::test]
+async fn test_reordered_lines() {
+	let log_path = five_entry_log("reordered").await;
+	edit_lines(&log_path, |lines| lines.swap(1, 2));
+
+	let broken = audit::verify_log(&log_path, None).await.unwrap().broken.unwrap();
+	assert_eq!(broken.line, 2);
+	assert_eq!(broken.reason, "sequence jumps from 0 to 2");
+}
+
+/**
+ * Test a log cut short still verifying, but with a different last hash
+ */
+#[tokio::test]
+async fn test_truncated_log() {
+	let log_path = five_entry_log("truncated").await;
+	let complete = audit::verify_log(&log_path, None).await.unwrap();
+	edit_lines(&log_path, |lines| lines.truncate(3));
+
+	let truncated = audit::verify_log(&log_path, None).await.unwrap();
+	assert!(truncated.broken.is_none());
+	assert_eq!(truncated.verified, 3);
+	assert_ne!(truncated.last_hash, complete.last_hash);
+}
+
+/**
+ * Test the chain continuing across rotated files
+ */
+#[tokio::test]
+async fn test_rotation_across_segments() {
+	let dir = scratch_dir("rotation");
+	let log_path = dir.join("audit.log").to_string_lossy().into_owned();
+
+	// Each logger continues from the newest entry on disk
+	for (stamp, commands) in [(1000, ["ls", "pwd"]), (2000, ["date", "id"])] {
+		log_commands(&logger(&log_path).await, &commands).await;
+		std::fs::rename(&log_path, dir.join(format!("audit.{}.log", stamp))).unwrap();
+	}
+	log_commands(&logger(&log_path).await, &["whoami"]).await;
+
+	let result = audit::verify_log(&log_path, None).await.unwrap();
+	assert!(result.broken.is_none(), "broken: {:?}", result.broken);
+	assert_eq!(result.segments.len(), 3);
+	assert_eq!(result.verified, 5);
+
+	// Retention removing the oldest file only moves the start of the chain
+	std::fs::rename(dir.join("audit.1000.log"), dir.join("removed")).unwrap();
+	let result = audit::verify_log(&log_path, None).await.unwrap();
+	assert!(result.broken.is_none(), "broken: {:?}", result.broken);
+	assert_eq!(result.first_sequence, Some(2));
+	assert_eq!(result.verified, 3);
+
+	// A file missing from the middle does not
+	std::fs::rename(dir.join("removed"), dir.join("audit.1000.log")).unwrap();
+	std::fs::remove_file(dir.join("audit.2000.log")).unwrap();
+	let broken = audit::verify_log(&log_path, None).await.unwrap().broken.unwrap();
+	assert_eq!(broken.path, PathBuf::from(&log_path));
+	assert_eq!(broken.reason, "sequence jumps from 1 to 4");
+}
+
+/**
+ * Test signatures made before and after a key rotation
+ */
+#[tokio::test]
+async fn test_hmac_with_rotated_key() {
+	let log_path = scratch_dir("hmac").join("audit.log").to_string_lossy().into_owned();
+	let config = Arc::new(RwLock::new(SecurityConfig::default()));
+	let signer = EncryptionManager::new(config.clone()).await.unwrap();
+
+	let mut writer = logger(&log_path).await;
+	writer.set_signer(Some(signer.clone()));
+	log_commands(&writer, &["ls", "pwd"]).await;
+	signer.rotate_keys().await.unwrap();
+	log_commands(&writer, &["date", "id"]).await;
+
+	let key_ids: Vec<String> = std::fs::read_to_string(&log_path).unwrap().lines()
+		.map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["signature"]["key_id"].to_string())
+		.collect();
+	assert_ne!(key_ids[1], key_ids[2]);
+
+	// A separate manager finds both keys in storage, as sare-audit --hmac does
+	let verifier = EncryptionManager::new(config).await.unwrap();
+	let result = audit::verify_log(&log_path, Some(&verifier)).await.unwrap();
+	assert!(result.broken.is_none(), "broken: {:?}", result.broken);
+	assert_eq!(result.signed, 4);
+
+	// Dropping a signature from a later entry is reported
+	edit_lines(&log_path, |lines| {
+		let mut entry: serde_json::Value = serde_json::from_str(&lines[3]).unwrap();
+		entry.as_object_mut().unwrap().remove("signature");
+		lines[3] = entry.to_string();
+	});
+	let broken = audit::verify_log(&log_path, Some(&verifier)).await.unwrap().broken.unwrap();
+	assert_eq!(broken.line, 4);
+	assert_eq!(broken.reason, "signature is missing");
+}
+
+/**
+ * Test a signer requiring a signature on every entry
+ */
+#[tokio::test]
+async fn test_hmac_unsigned_entries() {
+	let log_path = scratch_dir("unsigned").join("audit.log").to_string_lossy().into_owned();
+	let config = Arc::new(RwLock::new(SecurityConfig::default()));
+	let audit_config = AuditConfig { log_file_path: log_path.clone(), sign_entries: true, ..AuditConfig::default() };
+	log_commands(&AuditLogger::with_config(config.clone(), audit_config).await.unwrap(), &["ls", "pwd"]).await;
+	let verifier = EncryptionManager::new(config).await.unwrap();
+	assert_eq!(audit::verify_log(&log_path, Some(&verifier)).await.unwrap().signed, 2);
+
+	// Unsigned entries at the start of the log are not skipped
+	edit_lines(&log_path, |lines| {
+		let mut entry: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+		entry.as_object_mut().unwrap().remove("signature");
+		lines[0] = entry.to_string();
+	});
+	let broken = audit::verify_log(&log_path, Some(&verifier)).await.unwrap().broken.unwrap();
+	assert_eq!((broken.line, broken.reason.as_str()), (1, "signature is missing"));
+	assert!(audit::verify_log(&log_path, None).await.unwrap().broken.is_none());
+}
+
+/// Log that child_appends writes to when run by test_two_processes
+const CHILD_LOG: &str = "SARE_AUDIT_CHILD_LOG";
+
+/**
+ * Appends entries as the second process of test_two_processes
+ *
+ * Does nothing unless that test started it.
+ */
+#[tokio::test]
+async fn child_appends() {
+	if let Ok(log_path) = std::env::var(CHILD_LOG) {
+		log_commands(&logger(&log_path).await, &["child"; 50]).await;
+	}
+}
+
+/**
+ * Test two processes appending to the same log
+ */
+#[tokio::test]
+async fn test_two_processes() {
+	let log_path = scratch_dir("processes").join("audit.log").to_string_lossy().into_owned();
+	log_commands(&logger(&log_path).await, &["ls"]).await;
+
+	// Both loggers start from the same head before either appends
+	let parent = logger(&log_path).await;
+	let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+		.args(["--exact", "child_appends", "--quiet"])
+		.env(CHILD_LOG, &log_path)
+		.stdout(std::process::Stdio::null())
+		.spawn()
+		.unwrap();
+	log_commands(&parent, &["parent"; 50]).await;
+	assert!(child.wait().unwrap().success());
+
+	let result = audit::verify_log(&log_path, None).await.unwrap();
+	assert!(result.broken.is_none(), "broken: {:?}", result.broken);
+
+	// Alerts raised along the way are chained too
+	let log = std::fs::read_to_string(&log_path).unwrap();
+	assert_eq!(result.verified, log.lines().count() as u64);
+	assert_eq!(log.matches("\"command\":\"child\"").count(), 50);
+}
@@ -0,0 +1,134 @@
+/*!
+ * awk tests for the Sare shell
+ *
+ * Runs awk programs through the in-process interpreter and checks
+ * their output and exit codes.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_awk.rs
+ * Description: Tests for the awk builtin
+ */
+
+use sare_shell::Shell;
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_awk_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Runs an awk program that takes no input
+ */
+fn awk_begin(name: &str, program: &str) -> String {
+	let (output, code) = run(&scratch_dir(name), &format!("awk '{}'", program));
+	assert_eq!(code, 0, "awk {} failed: {}", program, output);
+	output
+}
+
+/**
+ * Test field splitting with -F, OFS and NF
+ */
+#[test]
+fn test_fields() {
+	let dir = scratch_dir("fields");
+	assert_eq!(run(&dir, "printf 'a:b:c\\n' | awk -F: '{print $2, NF}'").0, "b 3\n");
+	assert_eq!(run(&dir, "printf '  a   b  \\n' | awk '{print $2 \"|\" $1}'").0, "b|a\n");
+	assert_eq!(run(&dir, "printf 'a b\\n' | awk 'BEGIN{OFS=\"-\"} {$1=$1; print}'").0, "a-b\n");
+	assert_eq!(run(&dir, "printf 'a b c\\n' | awk '{NF=2; print}'").0, "a b\n");
+	assert_eq!(run(&dir, "printf 'a1b22c\\n' | awk -F'[0-9]+' '{print $3}'").0, "c\n");
+}
+
+/**
+ * Test BEGIN, END, -v and range patterns
+ */
+#[test]
+fn test_patterns() {
+	let dir = scratch_dir("patterns");
+	assert_eq!(run(&dir, "printf 'x\\ny\\n' | awk 'BEGIN{print \"start\"} END{print NR}'").0, "start\n2\n");
+	assert_eq!(run(&dir, "awk -v n=3 'BEGIN{print n*2}'").0, "6\n");
+	assert_eq!(run(&dir, "printf '1\\n2\\n3\\n4\\n' | awk 'NR==2,NR==3'").0, "2\n3\n");
+	assert_eq!(run(&dir, "printf 'foo123\\nbar\\n' | awk '/[0-9]+/{print \"num\"} $0 ~ /^b/{print \"b\"} $0 !~ /o/'").0, "num\nb\nbar\n");
+}
+
+/**
+ * Test associative arrays, in and delete
+ */
+#[test]
+fn test_arrays() {
+	let dir = scratch_dir("arrays");
+	let (output, code) = run(&dir, "printf 'x 1\\ny 2\\nx 3\\n' | awk '{s[$1]+=$2} END{print s[\"x\"], s[\"y\"], (\"z\" in s)}'");
+	assert_eq!(output, "4 2 0\n");
+	assert_eq!(code, 0);
+
+	assert_eq!(awk_begin("delete", "BEGIN{a[1]; a[2]; delete a[1]; for (k in a) print k}"), "2\n");
+	assert_eq!(awk_begin("multi", "BEGIN{a[1,2]=3; for (k in a) { split(k, p, SUBSEP); print p[1], p[2], a[k] }}"), "1 2 3\n");
+}
+
+/**
+ * Test printf formats and the string functions
+ */
+#[test]
+fn test_printf_and_strings() {
+	assert_eq!(awk_begin("printf", "BEGIN{printf \"%05.1f|%-3s|%x|%c\\n\", 3.14159, \"ab\", 255, 65}"), "003.1|ab |ff|A\n");
+	assert_eq!(awk_begin("gsub", "BEGIN{s=\"hello world\"; n=gsub(/o/, \"0\", s); print s, n, length(s)}"), "hell0 w0rld 2 11\n");
+	assert_eq!(awk_begin("substr", "BEGIN{print substr(\"hello\", 2, 3), toupper(\"ab\"), index(\"abc\", \"c\")}"), "ell AB 3\n");
+	assert_eq!(awk_begin("match", "BEGIN{print match(\"abcd\", /cd/), RSTART, RLENGTH}"), "3 3 2\n");
+	assert_eq!(awk_begin("split", "BEGIN{n=split(\"a,b,c\", p, \",\"); print n, p[3]}"), "3 c\n");
+	assert_eq!(awk_begin("sprintf", "BEGIN{s=sprintf(\"%3d\", 7); print \"[\" s \"]\"}"), "[  7]\n");
+}
+
+/**
+ * Test getline from a file and from the main input
+ */
+#[test]
+fn test_getline() {
+	let dir = scratch_dir("getline");
+	std::fs::write(dir.join("data.txt"), "one\ntwo\n").unwrap();
+
+	assert_eq!(run(&dir, "awk 'BEGIN{while ((getline line < \"data.txt\") > 0) n++; print n, line}'").0, "2 two\n");
+	assert_eq!(run(&dir, "printf 'a\\nb\\nc\\n' | awk '{getline; print}'").0, "b\nc\n");
+}
+
+/**
+ * Test user functions, recursion and local arrays
+ */
+#[test]
+fn test_functions() {
+	assert_eq!(awk_begin("recursion", "function f(n) { return n <= 1 ? 1 : n * f(n - 1) } BEGIN{print f(5)}"), "120\n");
+	assert_eq!(awk_begin("by_reference", "function fill(a) { a[\"k\"] = 1 } BEGIN{fill(arr); print arr[\"k\"]}"), "1\n");
+	assert_eq!(awk_begin("locals", "function g(x,   tmp) { tmp = x * 2; return tmp } BEGIN{tmp = 9; print g(2), tmp}"), "4 9\n");
+}
+
+/**
+ * Test exit codes for exit, syntax errors and runtime errors
+ */
+#[test]
+fn test_exit_codes() {
+	let dir = scratch_dir("exit_codes");
+	assert_eq!(run(&dir, "awk 'BEGIN{exit 3}'"), (String::new(), 3));
+	assert_eq!(run(&dir, "printf 'a\\n' | awk '{print; exit} END{print \"end\"}'"), ("a\nend\n".to_string(), 0));
+
+	let (output, code) = run(&dir, "awk 'BEGIN{'");
+	assert!(output.starts_with("awk: syntax error"), "output: {}", output);
+	assert_eq!(code, 1);
+
+	assert_eq!(run(&dir, "awk 'BEGIN{x = 1 / 0}'"), ("awk: division by zero attempted\n".to_string(), 2));
+	assert_eq!(run(&dir, "awk '{print}' missing.txt"), ("awk: cannot open \"missing.txt\" (No such file or directory)\n".to_string(), 2));
+}
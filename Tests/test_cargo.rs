@@ -0,0 +1,87 @@
+/*!
+ * cargo test runner tests for the Sare shell
+ *
+ * Builds a small crate in a scratch directory and runs its tests
+ * through the shell's cargo test workflow, checking that custom
+ * harnesses and cargo's environment are handled like cargo does.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_cargo.rs
+ * Description: Tests for running test binaries and the test summary
+ */
+
+use sare_shell::shell::commands::cargo::{self, Printer, TestStatus};
+use std::path::PathBuf;
+
+/**
+ * Writes a crate with a build script, a custom harness and a libtest test
+ */
+fn scratch_crate(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_cargo_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("src")).unwrap();
+	std::fs::create_dir_all(dir.join("tests")).unwrap();
+
+	std::fs::write(dir.join("Cargo.toml"), "[package]
+name = \"scratch-crate\"
+version = \"1.2.3-beta.1\"
+edition = \"2021\"
+authors = [\"A\", \"B\"]
+
+[[test]]
+name = \"custom\"
+harness = false
+
+[[test]]
+name = \"normal\"
+").unwrap();
+	std::fs::write(dir.join("build.rs"), "fn main() { println!(\"cargo:rerun-if-changed=build.rs\"); }\n").unwrap();
+	std::fs::write(dir.join("src/lib.rs"), "pub fn answer() -> u32 { 42 }\n").unwrap();
+	std::fs::write(dir.join("tests/custom.rs"), "fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	println!(\"custom args={:?}\", args);
+	assert_eq!(std::env::var(\"CARGO_PKG_VERSION\").unwrap(), \"1.2.3-beta.1\");
+}
+").unwrap();
+	std::fs::write(dir.join("tests/normal.rs"), "#[test]
+fn package_environment() {
+	assert_eq!(std::env::var(\"CARGO_PKG_VERSION\").unwrap(), \"1.2.3-beta.1\");
+	assert_eq!(std::env::var(\"CARGO_PKG_VERSION_PRE\").unwrap(), \"beta.1\");
+	assert_eq!(std::env::var(\"CARGO_PKG_AUTHORS\").unwrap(), \"A:B\");
+	assert_eq!(std::env::var(\"CARGO_CRATE_NAME\").unwrap(), \"normal\");
+	assert!(std::env::var(\"CARGO\").is_ok());
+	assert!(std::path::Path::new(&std::env::var(\"OUT_DIR\").unwrap()).is_dir());
+}
+").unwrap();
+	dir
+}
+
+/**
+ * Test custom harnesses getting no libtest flags and cargo's environment
+ */
+#[test]
+fn test_custom_harness_and_environment() {
+	let dir = scratch_crate("harness");
+	let mut printer = Printer::new(false);
+	let args = vec!["--offline".to_string()];
+	let (build, outcomes, exit_code) = cargo::test(&args, &dir, &mut printer).unwrap();
+
+	assert_eq!(build.exit_code, 0, "output: {}", printer.output);
+	assert!(printer.output.contains("custom args=[]"), "output: {}", printer.output);
+	let environment = outcomes.iter().find(|outcome| outcome.name == "package_environment").unwrap();
+	assert_eq!(environment.status, TestStatus::Passed, "output: {:?}", environment.output);
+	assert_eq!(exit_code, 0, "output: {}", printer.output);
+
+	let _ = std::fs::remove_dir_all(&dir);
+}
+
+/**
+ * Test the summary of a run without timed tests
+ */
+#[test]
+fn test_summary_without_tests() {
+	let summary = cargo::render_test_summary(&[], &Printer::new(false));
+
+	assert_eq!(summary, "\ntest result: ok. 0 passed; 0 failed; 0 ignored; 0.00s in tests\n");
+}
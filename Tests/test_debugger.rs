@@ -0,0 +1,150 @@
+/*!
+ * Debugger tests for the Sare shell
+ *
+ * Parses GDB machine interface records as gdb prints them and checks
+ * how stops, frames and source context are described. A session with
+ * the real gdb runs when gdb and a C compiler are installed.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_debugger.rs
+ * Description: Tests for MI parsing and the debug builtin
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::commands::debugger::{self, DebugSession, Frame, Resume, Stop, StopReason};
+use sare_shell::shell::commands::gdbmi::{self, MiRecord, MiValue};
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_debugger_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Frame in main at the given line of main.c
+ */
+fn main_frame(line: u32) -> Frame {
+	Frame {
+		level: 0,
+		function: "main".to_string(),
+		arguments: vec![("argc".to_string(), "1".to_string())],
+		file: Some("main.c".to_string()),
+		full_path: None,
+		line: Some(line),
+		address: Some("0x0000555555555131".to_string()),
+		library: None,
+	}
+}
+
+/**
+ * Test parsing result records with tokens and nested values
+ */
+#[test]
+fn test_result_records() {
+	let record = gdbmi::parse_record(r#"12^done,bkpt={number="1",type="breakpoint",func="main",file="main.c",line="4",thread-groups=["i1"]}"#).unwrap();
+	let MiRecord::Result { token, class, results } = record else { panic!("not a result record") };
+	assert_eq!(token, Some(12));
+	assert_eq!(class, "done");
+	let breakpoint = results.get("bkpt").unwrap();
+	assert_eq!(breakpoint.text("func"), Some("main"));
+	assert_eq!(breakpoint.text("line"), Some("4"));
+	assert_eq!(breakpoint.get("thread-groups").unwrap().items(), &[MiValue::Const("i1".to_string())]);
+
+	let record = gdbmi::parse_record(r#"^error,msg="No symbol \"x\" in current context.""#).unwrap();
+	let MiRecord::Result { token: None, class, results } = record else { panic!("not an untokened result") };
+	assert_eq!(class, "error");
+	assert_eq!(results.text("msg"), Some("No symbol \"x\" in current context."));
+}
+
+/**
+ * Test parsing async, stream and prompt records
+ */
+#[test]
+fn test_async_and_stream_records() {
+	let record = gdbmi::parse_record(r#"*stopped,reason="breakpoint-hit",bkptno="1",frame={func="main",args=[{name="argc",value="1"}],file="main.c",line="4"},thread-id="1""#).unwrap();
+	let MiRecord::Async { kind, class, results } = record else { panic!("not an async record") };
+	assert_eq!((kind, class.as_str()), ('*', "stopped"));
+	assert_eq!(results.text("reason"), Some("breakpoint-hit"));
+	let arguments = results.get("frame").unwrap().get("args").unwrap().items();
+	assert_eq!(arguments[0].text("name"), Some("argc"));
+
+	assert_eq!(gdbmi::parse_record(r#"~"Hello\tworld\n""#), Some(MiRecord::Stream { kind: '~', text: "Hello\tworld\n".to_string() }));
+	assert_eq!(gdbmi::parse_record("(gdb) "), Some(MiRecord::Prompt));
+
+	// Output the program prints itself is not MI
+	assert_eq!(gdbmi::parse_record("hello from the program"), None);
+}
+
+/**
+ * Test describing frames and stops the way gdb does
+ */
+#[test]
+fn test_describe_stop() {
+	let dir = scratch_dir("describe");
+	std::fs::write(dir.join("main.c"), "#include <stdio.h>\n\nint main(int argc) {\n\tint x = 1;\n\treturn x;\n}\n").unwrap();
+
+	assert_eq!(debugger::describe_frame(&main_frame(4), false), "main (argc=1) at main.c:4");
+	assert_eq!(debugger::describe_frame(&main_frame(4), true), "0x0000555555555131 in main (argc=1) at main.c:4");
+
+	let stop = Stop { reason: StopReason::Breakpoint(1), frame: Some(main_frame(4)) };
+	assert_eq!(
+		debugger::describe_stop(&stop, &dir, false),
+		"\nBreakpoint 1, main (argc=1) at main.c:4\n   2  \n   3  int main(int argc) {\n=> 4  \tint x = 1;\n   5  \treturn x;\n   6  }\n"
+	);
+
+	let exited = Stop { reason: StopReason::Exited(3), frame: None };
+	assert_eq!(debugger::describe_stop(&exited, &dir, false), "\nProgram exited with code 3.\n");
+}
+
+/**
+ * Test starting a session on a program that does not exist
+ */
+#[test]
+fn test_missing_program() {
+	let dir = scratch_dir("missing");
+	let error = DebugSession::start(None, "./nothing", Vec::new(), &dir).err().unwrap();
+	assert_eq!(error.to_string(), "./nothing: No such file or directory.");
+
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir).unwrap();
+	assert_ne!(shell.run_command_line("debug start ./nothing", false).unwrap().exit_code, 0);
+}
+
+/**
+ * Builds a small C program with debug information, if gdb and cc exist
+ */
+fn debuggable_program(dir: &Path) -> Option<PathBuf> {
+	debugger::find_in_path("gdb")?;
+	std::fs::write(dir.join("main.c"), "int square(int n) {\n\treturn n * n;\n}\n\nint main(void) {\n\tint value = square(3);\n\treturn value - 9;\n}\n").unwrap();
+	let status = std::process::Command::new("cc").args(["-g", "-O0", "-o", "square", "main.c"]).current_dir(dir).status().ok()?;
+	status.success().then(|| dir.join("square"))
+}
+
+/**
+ * Test a breakpoint, backtrace, locals and continuing to the exit under gdb
+ */
+#[test]
+fn test_gdb_session() {
+	let dir = scratch_dir("gdb");
+	if debuggable_program(&dir).is_none() {
+		eprintln!("skipping: gdb or cc not found");
+		return;
+	}
+	let mut session = DebugSession::start(Some("gdb"), "./square", Vec::new(), &dir).unwrap();
+	session.set_breakpoint("square").unwrap();
+
+	let stopped = session.run(None, false, &mut |_| {}).unwrap();
+	assert!(stopped.contains("Breakpoint 1, square (n=3) at main.c:2"), "stopped: {}", stopped);
+	let functions: Vec<String> = session.backtrace().unwrap().iter().map(|frame| frame.function.clone()).collect();
+	assert_eq!(functions, vec!["square", "main"]);
+	assert_eq!(session.evaluate("n * 2").unwrap(), "6");
+
+	let finished = session.resume(Resume::Continue, false, &mut |_| {}).unwrap();
+	assert!(finished.contains("Program exited normally."), "finished: {}", finished);
+}
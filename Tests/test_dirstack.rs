@@ -0,0 +1,152 @@
+/*!
+ * Directory navigation tests for the Sare shell
+ *
+ * Drives cd, pushd, popd, dirs and z through one shell instance and
+ * checks CDPATH and symlink resolution and the frecency database.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_dirstack.rs
+ * Description: Tests for cd, the directory stack and z
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::dirstack::{self, FrecencyDatabase, FrecencyOrder};
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_dirstack_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Creates a shell in the given directory whose z database lives there too
+ */
+fn shell_in(dir: &Path) -> Shell {
+	let mut shell = Shell::new().unwrap();
+	*shell.frecency_database_mut() = FrecencyDatabase::new(dir.join(".sare_z")).unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	shell
+}
+
+/**
+ * Runs one command line in an existing shell
+ */
+fn run(shell: &mut Shell, line: &str) -> (String, i32) {
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test pushd, popd and dirs -v
+ */
+#[test]
+fn test_pushd_popd_dirs() {
+	let dir = scratch_dir("stack");
+	std::fs::create_dir_all(dir.join("a")).unwrap();
+	std::fs::create_dir_all(dir.join("b")).unwrap();
+	let mut shell = shell_in(&dir);
+
+	run(&mut shell, "pushd a");
+	let (output, code) = run(&mut shell, &format!("pushd {}", dir.join("b").display()));
+	assert_eq!(output, format!("{} {} {}\n", dir.join("b").display(), dir.join("a").display(), dir.display()));
+	assert_eq!(code, 0);
+
+	let (output, _) = run(&mut shell, "dirs -v");
+	assert_eq!(output, format!(" 0  {}\n 1  {}\n 2  {}\n", dir.join("b").display(), dir.join("a").display(), dir.display()));
+
+	run(&mut shell, "popd");
+	assert_eq!(run(&mut shell, "pwd").0, dir.join("a").display().to_string());
+	run(&mut shell, "popd");
+	assert_eq!(run(&mut shell, "popd"), ("popd: directory stack empty\n".to_string(), 1));
+}
+
+/**
+ * Test cd - swapping with OLDPWD
+ */
+#[test]
+fn test_cd_previous() {
+	let dir = scratch_dir("previous");
+	std::fs::create_dir_all(dir.join("a")).unwrap();
+	let mut shell = shell_in(&dir);
+
+	run(&mut shell, "cd a");
+	assert_eq!(run(&mut shell, "cd -"), (format!("{}\n", dir.display()), 0));
+	assert_eq!(run(&mut shell, "cd -"), (format!("{}\n", dir.join("a").display()), 0));
+
+	let (output, code) = run(&mut shell, "cd missing");
+	assert_eq!(output, "cd: missing: No such file or directory\n");
+	assert_eq!(code, 1);
+}
+
+/**
+ * Test CDPATH lookup and its announcement
+ */
+#[test]
+fn test_cdpath() {
+	let dir = scratch_dir("cdpath");
+	std::fs::create_dir_all(dir.join("projects/app")).unwrap();
+	std::fs::create_dir_all(dir.join("work/app")).unwrap();
+	let cdpath = format!("{}:{}", dir.join("projects").display(), dir.join("work").display());
+
+	let (target, announce) = dirstack::resolve_cd_target(&dir.join("work"), "app", Some(&cdpath), false).unwrap();
+	assert_eq!(target, dir.join("projects/app"));
+	assert!(announce);
+
+	// Relative paths starting with ./ skip CDPATH
+	let (target, announce) = dirstack::resolve_cd_target(&dir.join("work"), "./app", Some(&cdpath), false).unwrap();
+	assert_eq!(target, dir.join("work/app"));
+	assert!(!announce);
+
+	assert!(dirstack::resolve_cd_target(&dir, "nowhere", Some(&cdpath), false).is_err());
+}
+
+/**
+ * Test logical and physical resolution of .. after a symlink
+ */
+#[test]
+fn test_logical_and_physical() {
+	let dir = scratch_dir("symlink");
+	std::fs::create_dir_all(dir.join("real/deep")).unwrap();
+	std::os::unix::fs::symlink(dir.join("real/deep"), dir.join("link")).unwrap();
+
+	let (logical, _) = dirstack::resolve_cd_target(&dir.join("link"), "..", None, false).unwrap();
+	assert_eq!(logical, dir);
+
+	let (physical, _) = dirstack::resolve_cd_target(&dir.join("link"), "..", None, true).unwrap();
+	assert_eq!(physical, dir.join("real").canonicalize().unwrap());
+
+	assert_eq!(dirstack::normalize_logical(Path::new("/a/./b/../c")), PathBuf::from("/a/c"));
+}
+
+/**
+ * Test z jumping to a directory recorded by cd
+ */
+#[test]
+fn test_z_jump() {
+	let dir = scratch_dir("z");
+	std::fs::create_dir_all(dir.join("src/deep")).unwrap();
+	std::fs::create_dir_all(dir.join("docs")).unwrap();
+	let mut shell = shell_in(&dir);
+	if !shell.frecency_database().is_enabled() {
+		eprintln!("skipping: SARE_Z_DISABLE is set");
+		return;
+	}
+
+	run(&mut shell, "cd src/deep");
+	run(&mut shell, &format!("cd {}", dir.join("docs").display()));
+	let (_, code) = run(&mut shell, "z deep");
+	assert_eq!(code, 0);
+	assert_eq!(run(&mut shell, "pwd").0, dir.join("src/deep").display().to_string());
+
+	// The visits were persisted and load into a fresh database
+	let database = FrecencyDatabase::new(dir.join(".sare_z")).unwrap();
+	let matches = database.query(&["deep".to_string()], FrecencyOrder::Rank);
+	assert_eq!(matches.first().map(|(_, path)| path.clone()), Some(dir.join("src/deep")));
+	assert_eq!(matches[0].0, 2.0);
+}
@@ -0,0 +1,154 @@
+/*!
+ * File operation tests for the Sare shell
+ *
+ * Runs cp, mv, rm and trash on scratch directories and checks what
+ * ends up on disk, including metadata and the XDG trash layout.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_fileops.rs
+ * Description: Tests for cp, mv, rm and trash
+ */
+
+use sare_shell::Shell;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_fileops_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Fills a directory with a private file, a symlink and a subdirectory
+ */
+fn make_tree(root: &Path) {
+	std::fs::create_dir_all(root.join("inner")).unwrap();
+	std::fs::write(root.join("secret"), "a\n").unwrap();
+	std::fs::set_permissions(root.join("secret"), std::fs::Permissions::from_mode(0o600)).unwrap();
+	let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+	std::fs::File::options().write(true).open(root.join("secret")).unwrap().set_modified(modified).unwrap();
+	std::os::unix::fs::symlink("secret", root.join("link")).unwrap();
+	std::fs::write(root.join("inner/b"), "b\n").unwrap();
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test cp -a keeping modes, times and symlinks
+ */
+#[test]
+fn test_copy_archive() {
+	let dir = scratch_dir("archive");
+	make_tree(&dir.join("src"));
+
+	assert_eq!(run(&dir, "cp src copy"), ("cp: -r not specified; omitting directory 'src'\n".to_string(), 1));
+	assert_eq!(run(&dir, "cp -a src copy"), (String::new(), 0));
+
+	let copied = std::fs::metadata(dir.join("copy/secret")).unwrap();
+	assert_eq!(copied.mode() & 0o777, 0o600);
+	assert_eq!(copied.mtime(), 1_577_836_800);
+	assert_eq!(std::fs::read_link(dir.join("copy/link")).unwrap(), PathBuf::from("secret"));
+	assert_eq!(std::fs::read_to_string(dir.join("copy/inner/b")).unwrap(), "b\n");
+}
+
+/**
+ * Test cp -n, -u and -v
+ */
+#[test]
+fn test_copy_options() {
+	let dir = scratch_dir("copy_options");
+	std::fs::write(dir.join("old"), "old\n").unwrap();
+	std::fs::write(dir.join("new"), "new\n").unwrap();
+	let past = SystemTime::now() - Duration::from_secs(3600);
+	std::fs::File::options().write(true).open(dir.join("old")).unwrap().set_modified(past).unwrap();
+
+	run(&dir, "cp -n old new");
+	assert_eq!(std::fs::read_to_string(dir.join("new")).unwrap(), "new\n");
+	run(&dir, "cp -u old new");
+	assert_eq!(std::fs::read_to_string(dir.join("new")).unwrap(), "new\n");
+	run(&dir, "cp -u new old");
+	assert_eq!(std::fs::read_to_string(dir.join("old")).unwrap(), "new\n");
+
+	assert_eq!(run(&dir, "cp -v new third"), ("'new' -> 'third'\n".to_string(), 0));
+}
+
+/**
+ * Test mv within and across filesystems
+ */
+#[test]
+fn test_move() {
+	let dir = scratch_dir("move");
+	std::fs::write(dir.join("file"), "x\n").unwrap();
+	assert_eq!(run(&dir, "mv -v file renamed"), ("renamed 'file' -> 'renamed'\n".to_string(), 0));
+	assert!(!dir.join("file").exists());
+
+	// /dev/shm is usually a tmpfs, so the rename fails with EXDEV
+	let shm = Path::new("/dev/shm");
+	let other_device = shm.is_dir() && std::fs::metadata(shm).unwrap().dev() != std::fs::metadata(&dir).unwrap().dev();
+	if !other_device {
+		eprintln!("skipping cross-device move: no second filesystem");
+		return;
+	}
+	make_tree(&dir.join("tree"));
+	let target = shm.join(format!("sare_fileops_move_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&target);
+
+	assert_eq!(run(&dir, &format!("mv tree {}", target.display())), (String::new(), 0));
+	assert!(!dir.join("tree").exists());
+	assert_eq!(std::fs::metadata(target.join("secret")).unwrap().mtime(), 1_577_836_800);
+	assert_eq!(std::fs::read_link(target.join("link")).unwrap(), PathBuf::from("secret"));
+	std::fs::remove_dir_all(&target).unwrap();
+}
+
+/**
+ * Test rm refusing directories and reporting missing files
+ */
+#[test]
+fn test_remove() {
+	let dir = scratch_dir("remove");
+	make_tree(&dir.join("tree"));
+
+	assert_eq!(run(&dir, "rm missing"), ("rm: cannot remove 'missing': No such file or directory\n".to_string(), 1));
+	assert_eq!(run(&dir, "rm tree"), ("rm: cannot remove 'tree': Is a directory\n".to_string(), 1));
+	assert_eq!(run(&dir, "rm -r tree"), (String::new(), 0));
+	assert!(!dir.join("tree").exists());
+}
+
+/**
+ * Test rm --trash and trash restore following the XDG layout
+ */
+#[test]
+fn test_trash_restore() {
+	let dir = scratch_dir("trash");
+	std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+	std::fs::write(dir.join("notes"), "keep me\n").unwrap();
+
+	assert_eq!(run(&dir, "rm --trash notes"), (String::new(), 0));
+	assert!(!dir.join("notes").exists());
+	let info = std::fs::read_to_string(dir.join("data/Trash/info/notes.trashinfo")).unwrap();
+	assert!(info.starts_with("[Trash Info]\n"));
+	assert!(info.contains(&format!("Path={}\n", dir.join("notes").display())));
+	assert!(dir.join("data/Trash/files/notes").exists());
+
+	let (output, _) = run(&dir, "trash list");
+	assert!(output.contains(&dir.join("notes").display().to_string()), "output: {}", output);
+
+	assert_eq!(run(&dir, "trash restore notes").1, 0);
+	assert_eq!(std::fs::read_to_string(dir.join("notes")).unwrap(), "keep me\n");
+	assert!(!dir.join("data/Trash/info/notes.trashinfo").exists());
+}
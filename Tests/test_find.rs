@@ -0,0 +1,107 @@
+/*!
+ * find and xargs tests for the Sare shell
+ *
+ * Searches scratch trees with the find builtin and feeds the results
+ * through xargs, checking tests, actions and parallel runs.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_find.rs
+ * Description: Tests for the find and xargs builtins
+ */
+
+use sare_shell::Shell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/**
+ * Creates a scratch tree with nested text files and an ignored directory
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_find_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("a/b")).unwrap();
+	std::fs::create_dir_all(dir.join("skip")).unwrap();
+	std::fs::write(dir.join("a/one.txt"), "x\n").unwrap();
+	std::fs::write(dir.join("a/b/two.TXT"), "yy\n").unwrap();
+	std::fs::write(dir.join("skip/three.txt"), "").unwrap();
+	std::fs::write(dir.join(".gitignore"), "skip/\n").unwrap();
+
+	let old = SystemTime::now() - Duration::from_secs(90 * 86400);
+	std::fs::File::options().write(true).open(dir.join("a/one.txt")).unwrap().set_modified(old).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test name, type, depth and time tests
+ */
+#[test]
+fn test_find_tests() {
+	let dir = scratch_dir("tests");
+	assert_eq!(run(&dir, "find . -name '*.txt'"), ("./a/one.txt\n./skip/three.txt\n".to_string(), 0));
+	assert_eq!(run(&dir, "find a -iname '*.txt' -type f").0, "a/b/two.TXT\na/one.txt\n");
+	assert_eq!(run(&dir, "find . -maxdepth 1 -type d").0, ".\n./a\n./skip\n");
+	assert_eq!(run(&dir, "find . -mindepth 2 -name '*.txt'").0, "./a/one.txt\n./skip/three.txt\n");
+	assert_eq!(run(&dir, "find a -path '*/b/*'").0, "a/b/two.TXT\n");
+	assert_eq!(run(&dir, "find . -type f -size -1k").0, "./skip/three.txt\n");
+	assert_eq!(run(&dir, "find a -type f -mtime +30").0, "a/one.txt\n");
+	assert_eq!(run(&dir, "find a -type f -newer a/one.txt").0, "a/b/two.TXT\n");
+}
+
+/**
+ * Test -prune and .gitignore awareness
+ */
+#[test]
+fn test_find_pruning() {
+	let dir = scratch_dir("pruning");
+	assert_eq!(run(&dir, "find . -path ./skip -prune -o -name '*.txt' -print").0, "./a/one.txt\n");
+	assert_eq!(run(&dir, "find . --gitignore -name '*.txt'").0, "./a/one.txt\n");
+}
+
+/**
+ * Test -exec in both forms, -print0 and -delete
+ */
+#[test]
+fn test_find_actions() {
+	let dir = scratch_dir("actions");
+	assert_eq!(run(&dir, "find a -type f -exec printf 'hit %s\\n' {} \\;").0, "hit a/b/two.TXT\nhit a/one.txt\n");
+	assert_eq!(run(&dir, "find a -type f -exec printf '%s|' {} +").0, "a/b/two.TXT|a/one.txt|");
+	assert_eq!(run(&dir, "find a -exec true {}"), ("find: missing argument to '-exec'\n".to_string(), 1));
+	assert_eq!(run(&dir, "find a -name one.txt -print0").0, "a/one.txt\0");
+
+	assert_eq!(run(&dir, "find skip -name '*.txt' -delete"), (String::new(), 0));
+	assert!(!dir.join("skip/three.txt").exists());
+	assert_eq!(run(&dir, "find nowhere"), ("find: 'nowhere': No such file or directory\n".to_string(), 1));
+}
+
+/**
+ * Test xargs -0, -n and -I
+ */
+#[test]
+fn test_xargs() {
+	let dir = scratch_dir("xargs");
+	assert_eq!(run(&dir, "find . -type f -name '*.txt' -print0 | xargs -0 -n 1 printf '[%s]'").0, "[./a/one.txt][./skip/three.txt]");
+	assert_eq!(run(&dir, "printf 'a b c d\\n' | xargs -n 2 printf '%s-%s;'").0, "a-b;c-d;");
+	assert_eq!(run(&dir, "printf '1\\n2\\n3\\n' | xargs -I X printf '<X>'").0, "<1><2><3>");
+}
+
+/**
+ * Test xargs -P keeping output in input order and its exit codes
+ */
+#[test]
+fn test_xargs_parallel() {
+	let dir = scratch_dir("parallel");
+	assert_eq!(run(&dir, "printf '1\\n2\\n3\\n4\\n' | xargs -P 4 -n 1 printf '%s'"), ("1234".to_string(), 0));
+	assert_eq!(run(&dir, "printf 'x\\n' | xargs -P 2 sh -c 'exit 3'").1, 123);
+	assert_eq!(run(&dir, "printf 'x\\n' | xargs nosuchcmd").1, 127);
+}
@@ -0,0 +1,163 @@
+/*!
+ * Native git reader tests for the Sare shell
+ *
+ * Builds repositories with the git binary and checks that the native
+ * reader reports the same status, branches, log and diff --stat, with
+ * loose objects and again after everything is packed.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_git.rs
+ * Description: Tests for the native git reader and git output colouring
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::commands::porcelain;
+use sare_shell::shell::git;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_git_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Runs the git binary with a fixed identity, or returns None without it
+ */
+fn git_binary(dir: &Path, args: &[&str]) -> Option<String> {
+	let output = Command::new("git")
+		.args(["-c", "user.name=Tester", "-c", "user.email=tester@example.com", "-c", "init.defaultBranch=main", "-c", "color.ui=never"])
+		.args(args)
+		.current_dir(dir)
+		.env("GIT_CONFIG_NOSYSTEM", "1")
+		.env("GIT_CONFIG_GLOBAL", "/dev/null")
+		.env("GIT_AUTHOR_DATE", "2024-01-02T03:04:05+0000")
+		.env("GIT_COMMITTER_DATE", "2024-01-02T03:04:05+0000")
+		.output()
+		.ok()?;
+	assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+	Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/**
+ * Builds a repository with two commits, a branch and pending changes
+ */
+fn sample_repository(name: &str) -> Option<PathBuf> {
+	let dir = scratch_dir(name);
+	git_binary(&dir, &["init", "-q"])?;
+	std::fs::write(dir.join("kept.txt"), "one\ntwo\nthree\n").unwrap();
+	std::fs::write(dir.join("gone.txt"), "bye\n").unwrap();
+	git_binary(&dir, &["add", "."])?;
+	git_binary(&dir, &["commit", "-q", "-m", "First commit"])?;
+	git_binary(&dir, &["branch", "feature"])?;
+
+	std::fs::write(dir.join("kept.txt"), "one\n2\nthree\nfour\n").unwrap();
+	git_binary(&dir, &["commit", "-q", "-am", "Second commit"])?;
+
+	std::fs::write(dir.join("kept.txt"), "one\n").unwrap();
+	std::fs::remove_file(dir.join("gone.txt")).unwrap();
+	std::fs::write(dir.join("staged.txt"), "new\n").unwrap();
+	git_binary(&dir, &["add", "staged.txt"])?;
+	std::fs::write(dir.join("untracked.txt"), "?\n").unwrap();
+	Some(dir)
+}
+
+/**
+ * Runs a subcommand through the native reader
+ */
+fn native(dir: &Path, subcommand: &str, args: &[&str]) -> String {
+	let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+	porcelain::execute(subcommand, &args, dir, false, 80).unwrap().output
+}
+
+/**
+ * Checks every native subcommand against the git binary
+ */
+fn assert_matches_binary(dir: &Path) {
+	for (subcommand, args) in [
+		("status", vec![]),
+		("status", vec!["--short"]),
+		("branch", vec![]),
+		("log", vec![]),
+		("log", vec!["--oneline"]),
+		("diff", vec!["--stat"]),
+		("diff", vec!["--stat", "HEAD~1"]),
+	] {
+		let mut full = vec![subcommand];
+		full.extend(&args);
+		let expected = git_binary(dir, &full).unwrap();
+		assert_eq!(native(dir, subcommand, &args), expected, "git {}", full.join(" "));
+	}
+}
+
+/**
+ * Test the native reader matching git with loose objects
+ */
+#[test]
+fn test_loose_objects() {
+	let Some(dir) = sample_repository("loose") else {
+		eprintln!("skipping: git binary not found");
+		return;
+	};
+	assert_matches_binary(&dir);
+}
+
+/**
+ * Test the native reader matching git after gc packs objects and refs
+ */
+#[test]
+fn test_packed_objects() {
+	let Some(dir) = sample_repository("packed") else {
+		eprintln!("skipping: git binary not found");
+		return;
+	};
+	git_binary(&dir, &["gc", "-q"]).unwrap();
+	assert!(dir.join(".git/packed-refs").exists());
+	assert_matches_binary(&dir);
+}
+
+/**
+ * Test prompt information and branch names for completion
+ */
+#[test]
+fn test_prompt_and_branches() {
+	let Some(dir) = sample_repository("prompt") else {
+		eprintln!("skipping: git binary not found");
+		return;
+	};
+	std::fs::create_dir_all(dir.join("sub")).unwrap();
+
+	let info = git::prompt_info(&dir.join("sub")).unwrap();
+	assert_eq!(info.head, "main");
+	assert!(!info.detached);
+	assert!(info.dirty && info.staged && info.untracked);
+	let names = git::ref_names(&dir);
+	assert!(names.contains(&"main".to_string()) && names.contains(&"feature".to_string()), "names: {:?}", names);
+	assert!(git::prompt_info(&scratch_dir("not_a_repository")).is_none());
+}
+
+/**
+ * Test git output only being coloured for the terminal
+ */
+#[test]
+fn test_colour_only_on_terminal() {
+	let Some(dir) = sample_repository("colour") else {
+		eprintln!("skipping: git binary not found");
+		return;
+	};
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.clone()).unwrap();
+
+	let terminal = shell.run_command_line("git status", true).unwrap().output;
+	assert!(terminal.contains('\x1b'));
+	let piped = shell.run_command_line("git status | cat", true).unwrap().output;
+	assert!(!piped.contains('\x1b'), "piped: {:?}", piped);
+	shell.run_command_line("git log > log.txt", true).unwrap();
+	assert!(!std::fs::read_to_string(dir.join("log.txt")).unwrap().contains('\x1b'));
+}
@@ -0,0 +1,275 @@
+/*!
+ * HTTP client tests for the Sare shell
+ *
+ * Runs curl and wget against a throwaway server on 127.0.0.1 that
+ * answers each path with a canned response, covering redirects,
+ * chunked bodies, error statuses and downloads to files.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_http.rs
+ * Description: Tests for the curl and wget builtins
+ */
+
+use sare_shell::Shell;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+/**
+ * Body of the /binary resource, every byte value once
+ */
+fn binary_body() -> Vec<u8> {
+	(0..=255u8).collect()
+}
+
+/**
+ * Builds a response with a Content-Length body
+ */
+fn response(status: &str, headers: &[&str], body: &[u8]) -> Vec<u8> {
+	let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+	for header in headers {
+		head.push_str(header);
+		head.push_str("\r\n");
+	}
+	head.push_str("\r\n");
+	let mut bytes = head.into_bytes();
+	bytes.extend_from_slice(body);
+	bytes
+}
+
+/**
+ * Answers one request path
+ */
+fn route(path: &str, range: Option<&str>) -> Vec<u8> {
+	match path {
+		"/hello" => response("200 OK", &["Content-Type: text/plain"], b"hello\n"),
+		"/old" => response("302 Found", &["Location: /hello"], b""),
+		"/loop" => response("302 Found", &["Location: /loop"], b""),
+		"/chunked" => b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n".to_vec(),
+		"/binary" => response("200 OK", &["Content-Type: application/octet-stream"], &binary_body()),
+		"/partial" => match range {
+			Some("bytes=6-") => response("206 Partial Content", &["Content-Range: bytes 6-11/12"], b"world\n"),
+			_ => response("200 OK", &[], b"hello world\n"),
+		},
+		_ => response("404 Not Found", &[], b"missing\n"),
+	}
+}
+
+/**
+ * Starts the loopback server and returns its base URL
+ */
+fn start_server() -> String {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap();
+
+	std::thread::spawn(move || {
+		for stream in listener.incoming() {
+			let Ok(mut stream) = stream else { continue };
+			let mut reader = BufReader::new(stream.try_clone().unwrap());
+			let mut request_line = String::new();
+			if reader.read_line(&mut request_line).is_err() {
+				continue;
+			}
+			let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+			let mut range = None;
+			loop {
+				let mut line = String::new();
+				if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+					break;
+				}
+				if let Some((name, value)) = line.split_once(':') {
+					if name.eq_ignore_ascii_case("Range") {
+						range = Some(value.trim().to_string());
+					}
+				}
+			}
+
+			let _ = stream.write_all(&route(&path, range.as_deref()));
+		}
+	});
+
+	format!("http://{}", address)
+}
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_http_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test a plain GET printing the body
+ */
+#[test]
+fn test_curl_get() {
+	let base = start_server();
+	let dir = scratch_dir("get");
+	let (output, code) = run(&dir, &format!("curl -s {}/hello", base));
+
+	assert_eq!(output, "hello\n");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test redirects followed only with -L
+ */
+#[test]
+fn test_curl_redirects() {
+	let base = start_server();
+	let dir = scratch_dir("redirects");
+
+	let (followed, code) = run(&dir, &format!("curl -sL {}/old", base));
+	assert_eq!(followed, "hello\n");
+	assert_eq!(code, 0);
+
+	let (not_followed, code) = run(&dir, &format!("curl -si {}/old", base));
+	assert!(not_followed.starts_with("HTTP/1.1 302 Found\r\n"), "output: {:?}", not_followed);
+	assert!(not_followed.contains("Location: /hello"), "output: {:?}", not_followed);
+	assert_eq!(code, 0);
+
+	let (_, code) = run(&dir, &format!("curl -sL --max-redirs 3 {}/loop", base));
+	assert_eq!(code, 47);
+}
+
+/**
+ * Test decoding a chunked body
+ */
+#[test]
+fn test_curl_chunked() {
+	let base = start_server();
+	let dir = scratch_dir("chunked");
+	let (output, code) = run(&dir, &format!("curl -s {}/chunked", base));
+
+	assert_eq!(output, "Wikipedia");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test --fail exiting 22 without writing the output file
+ */
+#[test]
+fn test_curl_fail() {
+	let base = start_server();
+	let dir = scratch_dir("fail");
+
+	let (output, code) = run(&dir, &format!("curl -f -o page.txt {}/absent", base));
+	assert_eq!(output, "curl: (22) The requested URL returned error: 404\n");
+	assert_eq!(code, 22);
+	assert!(!dir.join("page.txt").exists());
+
+	let (output, code) = run(&dir, &format!("curl -s {}/absent", base));
+	assert_eq!(output, "missing\n");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test -o and -O saving the exact bytes of a binary body
+ */
+#[test]
+fn test_curl_output_file() {
+	let base = start_server();
+	let dir = scratch_dir("output");
+
+	let (output, code) = run(&dir, &format!("curl -s -o data.bin {}/binary", base));
+	assert_eq!(output, "");
+	assert_eq!(code, 0);
+	assert_eq!(std::fs::read(dir.join("data.bin")).unwrap(), binary_body());
+
+	let (_, code) = run(&dir, &format!("curl -s -O {}/binary", base));
+	assert_eq!(code, 0);
+	assert_eq!(std::fs::read(dir.join("binary")).unwrap(), binary_body());
+
+	let (_, code) = run(&dir, &format!("curl -s -o missing/data.bin {}/binary", base));
+	assert_eq!(code, 23);
+}
+
+/**
+ * Test binary bodies being refused rather than mangled in a pipe
+ */
+#[test]
+fn test_curl_binary_pipe() {
+	let base = start_server();
+	let dir = scratch_dir("binary_pipe");
+	let (output, code) = run(&dir, &format!("curl -sS {}/binary", base));
+
+	assert!(output.starts_with("curl: (23) "), "output: {:?}", output);
+	assert_eq!(code, 23);
+}
+
+/**
+ * Test a refused connection exiting 7
+ */
+#[test]
+fn test_curl_connection_refused() {
+	let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+	let dir = scratch_dir("refused");
+	let (_, code) = run(&dir, &format!("curl -s http://{}/", address));
+
+	assert_eq!(code, 7);
+}
+
+/**
+ * Test wget saving a file after a redirect
+ */
+#[test]
+fn test_wget_download() {
+	let base = start_server();
+	let dir = scratch_dir("wget");
+	let (log, code) = run(&dir, &format!("wget {}/old", base));
+
+	assert!(log.contains("302 Found"), "log: {:?}", log);
+	assert!(log.contains("Location: /hello [following]"), "log: {:?}", log);
+	assert!(log.contains("saved [6/6]"), "log: {:?}", log);
+	assert_eq!(code, 0);
+	assert_eq!(std::fs::read_to_string(dir.join("old")).unwrap(), "hello\n");
+
+	let (_, code) = run(&dir, &format!("wget -q -O data.bin {}/binary", base));
+	assert_eq!(code, 0);
+	assert_eq!(std::fs::read(dir.join("data.bin")).unwrap(), binary_body());
+}
+
+/**
+ * Test wget's exit code and -O - output
+ */
+#[test]
+fn test_wget_errors_and_stdout() {
+	let base = start_server();
+	let dir = scratch_dir("wget_errors");
+
+	let (_, code) = run(&dir, &format!("wget -q {}/absent", base));
+	assert_eq!(code, 8);
+
+	let (output, code) = run(&dir, &format!("wget -O - {}/chunked", base));
+	assert_eq!(output, "Wikipedia");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test wget -c appending the rest of a partial file
+ */
+#[test]
+fn test_wget_resume() {
+	let base = start_server();
+	let dir = scratch_dir("wget_resume");
+	std::fs::write(dir.join("partial"), "hello ").unwrap();
+	let (_, code) = run(&dir, &format!("wget -q -c {}/partial", base));
+
+	assert_eq!(code, 0);
+	assert_eq!(std::fs::read_to_string(dir.join("partial")).unwrap(), "hello world\n");
+}
@@ -0,0 +1,97 @@
+/*!
+ * ls tests for the Sare shell
+ *
+ * Lists scratch directories with the ls builtin and checks sorting,
+ * recursion, classification and the long format.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_ls.rs
+ * Description: Tests for the ls builtin
+ */
+
+use sare_shell::Shell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/**
+ * Creates a scratch directory with files of known sizes and times
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_ls_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("d/sub")).unwrap();
+	std::fs::write(dir.join("d/sub/f"), "").unwrap();
+	std::fs::write(dir.join(".hidden"), "").unwrap();
+
+	// big is newer and larger than small
+	for (file, content, age) in [("big", "aaaa", 100), ("small", "a", 200)] {
+		std::fs::write(dir.join(file), content).unwrap();
+		let modified = SystemTime::now() - Duration::from_secs(age * 86400);
+		std::fs::File::options().write(true).open(dir.join(file)).unwrap().set_modified(modified).unwrap();
+	}
+	std::fs::hard_link(dir.join("big"), dir.join("hard")).unwrap();
+	std::os::unix::fs::symlink("big", dir.join("link")).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test listing, hidden files and sort orders
+ */
+#[test]
+fn test_sorting() {
+	let dir = scratch_dir("sorting");
+	assert_eq!(run(&dir, "ls -1"), ("big\nd\nhard\nlink\nsmall\n".to_string(), 0));
+	assert_eq!(run(&dir, "ls -1a").0, ".\n..\n.hidden\nbig\nd\nhard\nlink\nsmall\n");
+	assert_eq!(run(&dir, "ls -1r").0, "small\nlink\nhard\nd\nbig\n");
+	assert_eq!(run(&dir, "ls -1 big small hard -S").0, "big\nhard\nsmall\n");
+	assert_eq!(run(&dir, "ls -1t big small").0, "big\nsmall\n");
+	assert_eq!(run(&dir, "ls -1tr big small").0, "small\nbig\n");
+}
+
+/**
+ * Test -F, -d, -R and glob arguments
+ */
+#[test]
+fn test_arguments() {
+	let dir = scratch_dir("arguments");
+	assert_eq!(run(&dir, "ls -1F").0, "big\nd/\nhard\nlink@\nsmall\n");
+	assert_eq!(run(&dir, "ls -d d").0, "d\n");
+	assert_eq!(run(&dir, "ls -R d").0, "d:\nsub\n\nd/sub:\nf\n");
+	assert_eq!(run(&dir, "ls -1 *a*").0, "hard\nsmall\n");
+	assert_eq!(run(&dir, "ls missing"), ("ls: cannot access 'missing': No such file or directory\n".to_string(), 2));
+}
+
+/**
+ * Test link counts, sizes and symlink targets in the long format
+ */
+#[test]
+fn test_long_format() {
+	let dir = scratch_dir("long");
+	let (output, code) = run(&dir, "ls -l big link");
+	let lines: Vec<Vec<&str>> = output.lines().map(|line| line.split_whitespace().collect()).collect();
+	assert_eq!(code, 0);
+
+	assert!(lines[0][0].starts_with('-'));
+	assert_eq!(lines[0][1], "2");
+	assert_eq!(lines[0][4], "4");
+	assert_eq!(lines[0].last(), Some(&"big"));
+
+	assert!(lines[1][0].starts_with('l'));
+	assert_eq!(lines[1][1], "1");
+	assert_eq!(lines[1][lines[1].len() - 3..], ["link", "->", "big"]);
+
+	std::fs::write(dir.join("kilo"), vec![0u8; 2048]).unwrap();
+	let (output, _) = run(&dir, "ls -lh kilo");
+	assert_eq!(output.split_whitespace().nth(4), Some("2.0K"));
+}
@@ -0,0 +1,127 @@
+/*!
+ * make tests for the Sare shell
+ *
+ * Reads Makefiles for their targets, runs the make builtin on them and
+ * checks that compiler diagnostics link to the files they name.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_make.rs
+ * Description: Tests for Makefile parsing, make and diagnostic links
+ */
+
+use sare_shell::Shell;
+use sare_shell::history::completion::TabCompleter;
+use sare_shell::shell::commands::diagnostics::{self, DiagnosticLinker};
+use sare_shell::shell::commands::makefile::Makefile;
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates a scratch project with an included file and a failing subdirectory
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_make_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("sub")).unwrap();
+	std::fs::write(
+		dir.join("Makefile"),
+		"OUT = build\nNAME := app\n.PHONY: all clean\n\nall: $(OUT)/$(NAME) ## Build everything\n\n$(OUT)/$(NAME): main.c\n\tmkdir -p $(OUT) && printf built > $@\n\nclean: ## Remove outputs\n\trm -rf $(OUT)\n\ninclude extra.mk\n",
+	)
+	.unwrap();
+	std::fs::write(dir.join("extra.mk"), "lint:\n\t@echo linting\n").unwrap();
+	std::fs::write(dir.join("main.c"), "int main(void) {\n\treturn 0\n}\n").unwrap();
+	std::fs::write(dir.join("sub/Makefile"), "broken:\n\t@printf 'main.c:2:10: error: expected ;\\n' >&2; exit 1\n").unwrap();
+	std::fs::write(dir.join("sub/main.c"), "").unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test reading targets, variables and includes
+ */
+#[test]
+fn test_parse_makefile() {
+	let dir = scratch_dir("parse");
+	let makefile = Makefile::discover(&dir, None, &[]).unwrap();
+
+	let names: Vec<&str> = makefile.listed_targets().map(|target| target.name.as_str()).collect();
+	assert_eq!(names, vec!["all", "build/app", "clean", "lint"]);
+	assert_eq!(makefile.default_goal(), Some("all"));
+	assert_eq!(makefile.included, vec![dir.join("extra.mk")]);
+	assert_eq!(makefile.expand("$(OUT)/$(NAME)"), "build/app");
+
+	let all = makefile.listed_targets().next().unwrap();
+	assert!(all.phony);
+	assert_eq!(all.prerequisites, vec!["build/app"]);
+	assert_eq!(all.description.as_deref(), Some("Build everything"));
+	assert_eq!(makefile.listed_targets().last().unwrap().file, dir.join("extra.mk"));
+
+	// Command-line variables override the Makefile's own
+	let overridden = Makefile::discover(&dir, None, &[("OUT".to_string(), "out".to_string())]).unwrap();
+	assert_eq!(overridden.listed_targets().nth(1).unwrap().name, "out/app");
+}
+
+/**
+ * Test make --list and running targets through the builtin
+ */
+#[test]
+fn test_make_builtin() {
+	let dir = scratch_dir("builtin");
+	assert_eq!(
+		run(&dir, "make --list").0,
+		"all        # Build everything  (default)\nbuild/app\nclean      # Remove outputs\nlint\n"
+	);
+
+	assert_eq!(run(&dir, "make"), ("mkdir -p build && printf built > build/app\n".to_string(), 0));
+	assert_eq!(std::fs::read_to_string(dir.join("build/app")).unwrap(), "built");
+	assert_eq!(run(&dir, "make -f extra.mk lint"), ("linting\n".to_string(), 0));
+	assert_eq!(run(&dir, "make nosuch"), ("make: *** No rule to make target 'nosuch'.  Stop.\n".to_string(), 2));
+
+	let (output, code) = run(&dir, "make -C sub");
+	assert!(output.contains("main.c:2:10: error: expected ;\n"), "output: {}", output);
+	assert_eq!(code, 2);
+}
+
+/**
+ * Test diagnostics linking to files in the directory make entered
+ */
+#[test]
+fn test_diagnostic_links() {
+	let dir = scratch_dir("links");
+	let mut linker = DiagnosticLinker::new(&dir);
+
+	linker.observe(&format!("make[1]: Entering directory '{}'", dir.join("sub").display()));
+	let decorated = linker.decorate("main.c:2:10: error: expected ;");
+	let link = diagnostics::hyperlink(&whoami::fallible::hostname().unwrap_or_default(), &dir.join("sub/main.c"), "main.c:2:10");
+	assert!(decorated.starts_with(&link), "decorated: {:?}", decorated);
+	linker.observe(&format!("make[1]: Leaving directory '{}'", dir.join("sub").display()));
+
+	// Back in the top directory, where main.c is a different file
+	assert_eq!(linker.locations("main.c:1: warning: unused")[0].path, dir.join("main.c"));
+	linker.observe("main.c:1: warning: unused");
+	assert!(linker.locations("12:30:45 build started").is_empty());
+	assert_eq!(linker.summary().as_deref(), Some("1 error, 1 warning"));
+}
+
+/**
+ * Test completing make targets
+ */
+#[test]
+fn test_target_completion() {
+	let dir = scratch_dir("completion");
+	let mut completer = TabCompleter::new(dir.clone());
+
+	let result = completer.complete("make cl", 7).unwrap().unwrap();
+	assert_eq!(result.completed_text, "clean");
+	let result = completer.complete("make -f extra.mk l", 18).unwrap().unwrap();
+	assert_eq!(result.completed_text, "lint");
+}
@@ -0,0 +1,94 @@
+/*!
+ * Socket listing tests for the Sare shell
+ *
+ * Opens sockets in the test process and checks that the /proc/net
+ * readers and the netstat and ss builtins report them.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_netstat.rs
+ * Description: Tests for /proc/net parsing and netstat/ss
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::commands::procnet::{self, SocketProtocol};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::os::unix::io::AsRawFd;
+
+/**
+ * Runs one command line in a fresh shell
+ */
+fn run(line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Finds the output lines mentioning a local address
+ */
+fn lines_for(output: &str, address: &str) -> Vec<String> {
+	output.lines().filter(|line| line.contains(address)).map(str::to_string).collect()
+}
+
+/**
+ * Test reading TCP sockets and their owners from /proc
+ */
+#[test]
+fn test_read_inet_sockets() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+
+	let sockets = procnet::read_inet_sockets(SocketProtocol::Tcp).unwrap();
+	let socket = sockets.iter().find(|socket| socket.local_port == port).expect("listener not in /proc/net/tcp");
+	assert!(socket.is_listening());
+	assert_eq!(socket.state_name(), "LISTEN");
+	assert_eq!(socket.local_address.to_string(), "127.0.0.1");
+
+	let owner = &procnet::socket_owners()[&socket.inode];
+	assert_eq!(owner.pid, std::process::id());
+	assert_eq!(owner.fd, listener.as_raw_fd() as u32);
+}
+
+/**
+ * Test the -t, -u, -l, -n and -p filters
+ */
+#[test]
+fn test_netstat_filters() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let listening = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+	let client = TcpStream::connect(&listening).unwrap();
+	let connected = client.local_addr().unwrap().to_string();
+	let datagram = UdpSocket::bind("127.0.0.1:0").unwrap();
+	let unconnected = datagram.local_addr().unwrap().to_string();
+
+	let (output, code) = run("netstat -tlnp");
+	assert_eq!(code, 0);
+	let listener_lines = lines_for(&output, &listening);
+	assert_eq!(listener_lines.len(), 1, "output: {}", output);
+	assert!(listener_lines[0].contains("LISTEN"));
+	assert!(listener_lines[0].contains(&format!("{}/", std::process::id())));
+	assert!(lines_for(&output, &connected).is_empty());
+	assert!(lines_for(&output, &unconnected).is_empty());
+
+	let (output, _) = run("netstat -tn");
+	assert!(lines_for(&output, &connected)[0].contains("ESTABLISHED"), "output: {}", output);
+
+	let (output, _) = run("netstat -uln");
+	assert_eq!(lines_for(&output, &unconnected).len(), 1, "output: {}", output);
+	assert!(lines_for(&output, &listening).is_empty());
+}
+
+/**
+ * Test ss reporting the owning process
+ */
+#[test]
+fn test_ss_process() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let listening = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+
+	let (output, code) = run("ss -tlnp");
+	assert_eq!(code, 0);
+	let line = &lines_for(&output, &listening)[0];
+	assert!(line.contains(&format!("pid={},fd={}", std::process::id(), listener.as_raw_fd())), "line: {}", line);
+}
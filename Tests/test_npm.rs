@@ -0,0 +1,119 @@
+/*!
+ * npm script runner tests for the Sare shell
+ *
+ * Builds a scratch monorepo with a workspace root and two packages and
+ * runs their package.json scripts without the npm binary.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_npm.rs
+ * Description: Tests for npm run, hooks, node_modules/.bin and workspaces
+ */
+
+use sare_shell::Shell;
+use sare_shell::history::completion::TabCompleter;
+use sare_shell::shell::commands::npm::Workspace;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates a scratch monorepo with packages/api and packages/web
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_npm_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("packages/api/src")).unwrap();
+	std::fs::create_dir_all(dir.join("packages/web/node_modules/.bin")).unwrap();
+	std::fs::write(
+		dir.join("package.json"),
+		r#"{ "name": "mono", "private": true, "workspaces": ["packages/*"], "scripts": { "hello": "printf root-hello" } }"#,
+	)
+	.unwrap();
+	std::fs::write(
+		dir.join("packages/api/package.json"),
+		r#"{ "name": "@mono/api", "version": "1.0.0", "scripts": { "pretest": "printf pre-", "test": "printf api-test", "posttest": "printf -- -post", "args": "printf '[%s]'", "fail": "exit 3" } }"#,
+	)
+	.unwrap();
+	std::fs::write(dir.join("packages/web/package.json"), r#"{ "name": "web", "scripts": { "build": "tool-x", "where": "pwd" } }"#).unwrap();
+
+	let tool = dir.join("packages/web/node_modules/.bin/tool-x");
+	std::fs::write(&tool, "#!/bin/sh\necho tool-x-ran \"$npm_lifecycle_event\"\n").unwrap();
+	std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test finding the workspace and selecting members
+ */
+#[test]
+fn test_workspace_members() {
+	let dir = scratch_dir("members");
+	let workspace = Workspace::find(&dir.join("packages/api/src")).unwrap();
+	assert_eq!(workspace.root, dir);
+
+	let names: Vec<&str> = workspace.packages.iter().map(|package| package.name.as_deref().unwrap_or("")).collect();
+	assert_eq!(names, vec!["@mono/api", "web"]);
+	assert_eq!(workspace.select("web", &dir).unwrap().directory, dir.join("packages/web"));
+	assert_eq!(workspace.select("packages/api", &dir).unwrap().name.as_deref(), Some("@mono/api"));
+	assert!(workspace.select("nosuch", &dir).is_err());
+}
+
+/**
+ * Test pre and post hooks, arguments and exit codes
+ */
+#[test]
+fn test_run_scripts() {
+	let dir = scratch_dir("scripts");
+	let api = dir.join("packages/api/src");
+
+	assert_eq!(
+		run(&api, "npm test"),
+		("\n> @mono/api@1.0.0 pretest\n> printf pre-\n\npre-\n\n> @mono/api@1.0.0 test\n> printf api-test\n\napi-test\n\n> @mono/api@1.0.0 posttest\n> printf -- -post\n\n-post\n".to_string(), 0)
+	);
+	assert_eq!(run(&api, "npm run --ignore-scripts test").0, "\n> @mono/api@1.0.0 test\n> printf api-test\n\napi-test\n");
+	assert_eq!(run(&api, "npm run args -- a b").0, "\n> @mono/api@1.0.0 args\n> printf '[%s]' a b\n\n[a][b]\n");
+	assert_eq!(run(&api, "npm run fail").1, 3);
+
+	assert_eq!(run(&api, "npm run nosuch"), ("npm: Missing script: \"nosuch\" in @mono/api@1.0.0\n".to_string(), 1));
+	assert_eq!(run(&api, "npm run nosuch --if-present"), (String::new(), 0));
+}
+
+/**
+ * Test running a member's script from another package with -w
+ */
+#[test]
+fn test_workspace_scripts() {
+	let dir = scratch_dir("workspace");
+	let api = dir.join("packages/api/src");
+
+	// node_modules/.bin of the member is on PATH, and npm_lifecycle_event is set
+	assert_eq!(run(&api, "npm run -w web build"), ("\n> web build\n> tool-x\n\ntool-x-ran build\n".to_string(), 0));
+	assert_eq!(run(&api, "npm run --workspace=packages/web where").0, format!("\n> web where\n> pwd\n\n{}\n", dir.join("packages/web").display()));
+	assert_eq!(run(&api, "npm run -w nosuch test"), ("npm: No workspace found: --workspace=nosuch\n".to_string(), 1));
+}
+
+/**
+ * Test listing scripts and completing their names
+ */
+#[test]
+fn test_list_and_complete() {
+	let dir = scratch_dir("list");
+	let web = dir.join("packages/web");
+
+	assert_eq!(run(&web, "npm run").0, "Scripts available in web via `npm run`:\n  build\n    tool-x\n  where\n    pwd\n");
+
+	assert!(run(&dir.join("packages/api"), "npm run").0.contains("    printf api-test\n\navailable via `npm run`:\n"));
+
+	let mut completer = TabCompleter::new(web);
+	assert_eq!(completer.complete("npm run wh", 10).unwrap().unwrap().completed_text, "where");
+}
@@ -0,0 +1,125 @@
+/*!
+ * ICMP ping tests for the Sare shell
+ *
+ * Pings the loopback addresses with the built-in prober. Tests are
+ * skipped when the environment denies ICMP sockets (no
+ * ping_group_range entry and no CAP_NET_RAW) or has no IPv6.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_ping.rs
+ * Description: Tests for the ICMP echo prober and the ping builtin
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::commands::ping::{self, IcmpSocket, PingOptions, Pinger};
+use std::net::IpAddr;
+use std::time::Duration;
+
+/**
+ * Options for a quick run against loopback
+ */
+fn quick_options(count: u32) -> PingOptions {
+	PingOptions {
+		count,
+		interval: Duration::from_millis(50),
+		size: 56,
+		timeout: Duration::from_secs(2),
+	}
+}
+
+/**
+ * Opens a pinger, or reports why the test is skipped
+ */
+fn pinger_or_skip(address: &str, count: u32) -> Option<Pinger> {
+	let address: IpAddr = address.parse().unwrap();
+	match Pinger::new(address, quick_options(count)) {
+		Ok(pinger) => Some(pinger),
+		Err(e) => {
+			eprintln!("skipping: cannot open ICMP socket for {}: {}", address, e);
+			None
+		}
+	}
+}
+
+/**
+ * Test echo replies from 127.0.0.1
+ */
+#[test]
+fn test_ping_ipv4_loopback() {
+	let Some(pinger) = pinger_or_skip("127.0.0.1", 3) else { return };
+
+	let mut sequences = Vec::new();
+	let statistics = pinger.run(|reply| sequences.push(reply.sequence)).unwrap();
+
+	assert_eq!(statistics.transmitted, 3);
+	assert_eq!(statistics.received, 3);
+	assert_eq!(sequences, vec![1, 2, 3]);
+	assert_eq!(statistics.loss_percent(), 0.0);
+	let (min, avg, max, _) = statistics.rtt_summary().unwrap();
+	assert!(min <= avg && avg <= max);
+}
+
+/**
+ * Test echo replies from ::1
+ */
+#[test]
+fn test_ping_ipv6_loopback() {
+	let Some(pinger) = pinger_or_skip("::1", 2) else { return };
+
+	// Sending fails when the loopback interface has no IPv6 address
+	let statistics = match pinger.run(|_| {}) {
+		Ok(statistics) => statistics,
+		Err(e) => {
+			eprintln!("skipping: IPv6 loopback unavailable: {}", e);
+			return;
+		}
+	};
+
+	assert_eq!(statistics.transmitted, 2);
+	assert_eq!(statistics.received, 2);
+}
+
+/**
+ * Test host resolution of literals and localhost
+ */
+#[test]
+fn test_resolve_host() {
+	assert_eq!(ping::resolve_host("127.0.0.1").unwrap(), "127.0.0.1".parse::<IpAddr>().unwrap());
+	assert_eq!(ping::resolve_host("::1").unwrap(), "::1".parse::<IpAddr>().unwrap());
+	assert!(ping::resolve_host("localhost").unwrap().is_loopback());
+}
+
+/**
+ * Test the ping builtin's summary output
+ */
+#[test]
+fn test_ping_builtin_summary() {
+	if let Err(e) = IcmpSocket::open("127.0.0.1".parse().unwrap()) {
+		eprintln!("skipping: cannot open ICMP socket: {}", e);
+		return;
+	}
+
+	let mut shell = Shell::new().unwrap();
+	let result = shell.run_command_line("ping -c 2 -i 0.05 -W 2 -q 127.0.0.1", false).unwrap();
+
+	assert!(result.output.contains("--- 127.0.0.1 ping statistics ---"), "output: {:?}", result.output);
+	assert!(result.output.contains("2 packets transmitted, 2 received, 0% packet loss"), "output: {:?}", result.output);
+	assert_eq!(result.exit_code, 0);
+}
+
+/**
+ * Test option validation in the ping builtin
+ */
+#[test]
+fn test_ping_builtin_rejects_bad_options() {
+	let mut shell = Shell::new().unwrap();
+
+	let oversized = shell.run_command_line("ping -s 70000 127.0.0.1", false).unwrap();
+	assert_eq!(oversized.output, "ping: invalid argument: '70000': out of range: 0 <= value <= 65507\n");
+	assert_eq!(oversized.exit_code, 1);
+
+	let extra = shell.run_command_line("ping 127.0.0.1 extra", false).unwrap();
+	assert_eq!(extra.output, "ping: extra operand 'extra'\n");
+	assert_eq!(extra.exit_code, 1);
+}
@@ -0,0 +1,217 @@
+/*!
+ * Pipeline tests for the Sare shell
+ *
+ * Runs command lines through the shell's pipeline executor and checks
+ * that builtins receive piped and redirected input.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_pipeline.rs
+ * Description: Tests for pipes, redirects and grouped commands
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::parser::{parse_pipeline, SubstitutionDirection, SubstitutionSlot};
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_pipeline_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test cat reading piped input
+ */
+#[test]
+fn test_cat_reads_pipe() {
+	let dir = scratch_dir("cat_pipe");
+	let (output, code) = run(&dir, "printf 'x\\ny\\n' | cat");
+
+	assert_eq!(output, "x\ny\n");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test cat reading a redirected file
+ */
+#[test]
+fn test_cat_reads_redirect() {
+	let dir = scratch_dir("cat_redirect");
+	std::fs::write(dir.join("input.txt"), "first\nsecond\n").unwrap();
+	let (output, code) = run(&dir, "cat < input.txt");
+
+	assert_eq!(output, "first\nsecond\n");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test cat with - between file operands
+ */
+#[test]
+fn test_cat_dash_operand() {
+	let dir = scratch_dir("cat_dash");
+	std::fs::write(dir.join("tail.txt"), "file\n").unwrap();
+	let (output, _) = run(&dir, "printf 'piped\\n' | cat - tail.txt");
+
+	assert_eq!(output, "piped\nfile\n");
+}
+
+/**
+ * Test cat at the end of a record pipeline
+ */
+#[test]
+fn test_cat_after_records() {
+	let dir = scratch_dir("cat_records");
+	std::fs::write(dir.join("keep.txt"), "").unwrap();
+	std::fs::write(dir.join("drop.log"), "").unwrap();
+	let (output, code) = run(&dir, "ls | where name == keep.txt | cat");
+
+	assert!(output.contains("keep.txt"), "output: {:?}", output);
+	assert!(!output.contains("drop.log"), "output: {:?}", output);
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test piped input reaching the first command of a subshell
+ */
+#[test]
+fn test_cat_in_subshell() {
+	let dir = scratch_dir("cat_subshell");
+	let (output, code) = run(&dir, "printf 'x\\n' | (cat; printf 'y\\n')");
+
+	assert_eq!(output, "x\ny\n");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test builtin filters chained after cat
+ */
+#[test]
+fn test_cat_into_filters() {
+	let dir = scratch_dir("cat_filters");
+	std::fs::write(dir.join("words.txt"), "b\na\nb\n").unwrap();
+	let (output, _) = run(&dir, "cat words.txt | sort | uniq -c | wc -l");
+
+	assert_eq!(output.trim(), "2");
+}
+
+/**
+ * Test grep telling a failed match from a bad pattern
+ */
+#[test]
+fn test_grep_exit_codes() {
+	let dir = scratch_dir("grep_codes");
+
+	let (output, code) = run(&dir, "printf 'a\\nb\\n' | grep b");
+	assert_eq!(output, "b\n");
+	assert_eq!(code, 0);
+
+	let (output, code) = run(&dir, "printf 'a\\n' | grep z");
+	assert_eq!(output, "");
+	assert_eq!(code, 1);
+
+	let (output, code) = run(&dir, "printf 'a\\n' | grep '['");
+	assert_eq!(output, "grep: Unmatched [, [^, [:, [., or [=\n");
+	assert_eq!(code, 2);
+
+	let (output, code) = run(&dir, "printf 'a\\n' | grep -E 'a('");
+	assert_eq!(output, "grep: Unmatched ( or \\(\n");
+	assert_eq!(code, 2);
+
+	let (_, code) = run(&dir, "printf 'a\\n' | grep --bogus a");
+	assert_eq!(code, 2);
+}
+
+/**
+ * Test -P rejecting look-around instead of silently not matching
+ */
+#[test]
+fn test_grep_perl_look_around() {
+	let dir = scratch_dir("grep_perl");
+
+	let (output, code) = run(&dir, "printf 'ab\\n' | grep -P '(?<=a)b'");
+	assert!(output.starts_with("grep: look-around"), "output: {:?}", output);
+	assert_eq!(code, 2);
+
+	let (output, code) = run(&dir, "printf 'a1\\n' | grep -oP '\\d+'");
+	assert_eq!(output, "1\n");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test process substitutions parsed as redirection targets
+ */
+#[test]
+fn test_parse_substitution_redirects() {
+	let pipeline = parse_pipeline("sort < <(printf 'b\\na\\n') > >(cat -n)").unwrap();
+	let command = &pipeline.commands[0];
+	assert_eq!(command.command, "sort");
+	assert!(command.args.is_empty(), "args: {:?}", command.args);
+
+	let slots: Vec<(SubstitutionSlot, SubstitutionDirection)> = command.process_substitutions.iter()
+		.map(|substitution| (substitution.slot.clone(), substitution.direction.clone()))
+		.collect();
+	assert_eq!(slots, vec![
+		(SubstitutionSlot::InputRedirect, SubstitutionDirection::Input),
+		(SubstitutionSlot::OutputRedirect, SubstitutionDirection::Output),
+	]);
+	assert_eq!(command.process_substitutions[1].pipeline.commands[0].command, "cat");
+
+	let pipeline = parse_pipeline("diff <(ls) file >> >(wc -l)").unwrap();
+	let slots: Vec<SubstitutionSlot> = pipeline.commands[0].process_substitutions.iter()
+		.map(|substitution| substitution.slot.clone())
+		.collect();
+	assert_eq!(slots, vec![SubstitutionSlot::Argument(0), SubstitutionSlot::AppendRedirect]);
+
+	let pipeline = parse_pipeline("(echo a) > >(cat)").unwrap();
+	assert_eq!(pipeline.commands[0].process_substitutions[0].slot, SubstitutionSlot::OutputRedirect);
+}
+
+/**
+ * Test reading a command's output through `< <(cmd)`
+ */
+#[test]
+fn test_input_substitution_redirect() {
+	let dir = scratch_dir("input_substitution");
+
+	let (output, code) = run(&dir, "sort < <(printf 'b\\na\\n')");
+	assert_eq!(output, "a\nb\n");
+	assert_eq!(code, 0);
+
+	let (output, code) = run(&dir, "/bin/cat < <(printf 'external\\n')");
+	assert_eq!(output, "external\n");
+	assert_eq!(code, 0);
+}
+
+/**
+ * Test writing a command's output into `> >(cmd)` without creating a file
+ */
+#[test]
+fn test_output_substitution_redirect() {
+	let dir = scratch_dir("output_substitution");
+
+	let (output, code) = run(&dir, "printf 'b\\na\\n' > >(sort)");
+	assert_eq!(output, "a\nb\n");
+	assert_eq!(code, 0);
+
+	let (output, code) = run(&dir, "(printf 'x\\n'; printf 'y\\n') >> >(sort -r)");
+	assert_eq!(output, "y\nx\n");
+	assert_eq!(code, 0);
+
+	assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
@@ -0,0 +1,194 @@
+/*!
+ * Security policy tests for the Sare shell
+ *
+ * Checks commands through the policy layer built with the `security`
+ * feature: what text the validator and the audit log see, and where
+ * and how the audit log is written.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_policy.rs
+ * Description: Tests for the policy layer and its audit log
+ */
+
+use sare_security::SecurityConfig;
+use sare_security::audit;
+use sare_security::encryption::EncryptionManager;
+use sare_shell::Shell;
+use sare_shell::config::SecurityPolicyConfig;
+use sare_shell::shell::policy::{self, PolicyLayer};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_policy_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Creates a policy layer logging to the given file
+ */
+fn policy_logging_to(log_file: PathBuf) -> PolicyLayer {
+	PolicyLayer::new(&SecurityPolicyConfig { log_file: Some(log_file), ..SecurityPolicyConfig::default() }).unwrap()
+}
+
+/**
+ * Creates a shell in the directory whose commands are checked against a policy logging to audit.log
+ */
+fn policed_shell(dir: &Path) -> Shell {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	shell.set_policy(Some(policy_logging_to(dir.join("audit.log"))));
+	shell
+}
+
+/**
+ * Writes an executable script whose name the validator rejects
+ */
+fn rejected_program(dir: &Path) -> &'static str {
+	let path = dir.join("run$me");
+	std::fs::write(&path, "#!/bin/sh\necho ran\n").unwrap();
+	std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+	"'./run$me'"
+}
+
+/**
+ * Counts audit log entries mentioning the text
+ */
+fn audit_entries(dir: &Path, text: &str) -> usize {
+	std::fs::read_to_string(dir.join("audit.log")).unwrap_or_default().lines().filter(|line| line.contains(text)).count()
+}
+
+/**
+ * Builds an argv from words
+ */
+fn argv(words: &[&str]) -> Vec<String> {
+	words.iter().map(|word| word.to_string()).collect()
+}
+
+/**
+ * Test the validator checking arguments as data, not shell syntax
+ */
+#[test]
+fn test_expanded_arguments() {
+	let dir = scratch_dir("arguments");
+	let policy = policy_logging_to(dir.join("audit.log"));
+
+	assert_eq!(policy.check(&argv(&["echo", "$(whoami)"])).unwrap(), None);
+	assert_eq!(policy.check(&argv(&["printf", "[%s]\\n", "a; b"])).unwrap(), None);
+	assert!(policy.check(&argv(&["rm", "-rf", "/"])).unwrap().is_some());
+	assert_eq!(policy.check(&argv(&["cat", "../notes.txt"])).unwrap(), None);
+	assert!(policy.check(&argv(&["$(whoami)"])).unwrap().is_some());
+}
+
+/**
+ * Test word boundaries surviving in the checked and logged line
+ */
+#[test]
+fn test_command_line_quoting() {
+	assert_eq!(policy::command_line(&argv(&["rm", "a b"])), "rm 'a b'");
+	assert_eq!(policy::command_line(&argv(&["rm", "a", "b"])), "rm a b");
+	assert_eq!(policy::command_line(&argv(&["echo", "it's", ""])), "echo 'it'\\''s' ''");
+
+	let dir = scratch_dir("quoting");
+	let log_file = dir.join("audit.log");
+	let policy = policy_logging_to(log_file.clone());
+	policy.record_execution(&policy::command_line(&argv(&["rm", "a b"])), true).unwrap();
+
+	let log = std::fs::read_to_string(&log_file).unwrap();
+	assert!(log.contains("\"command\":\"rm 'a b'\""), "log: {:?}", log);
+}
+
+/**
+ * Test the configured log file being created owner-only
+ */
+#[test]
+fn test_log_file_permissions() {
+	let dir = scratch_dir("permissions");
+	let log_file = dir.join("state").join("audit.log");
+	let policy = policy_logging_to(log_file.clone());
+	policy.record_execution("true", true).unwrap();
+
+	let mode = |path: &PathBuf| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+	assert_eq!(mode(&log_file), 0o600);
+	assert_eq!(mode(&dir.join("state")), 0o700);
+}
+
+/**
+ * Test commands started by timeout, sandbox, groups and subshells being checked
+ */
+#[test]
+fn test_nested_commands_checked() {
+	let dir = scratch_dir("nested");
+	let program = rejected_program(&dir);
+	let mut shell = policed_shell(&dir);
+
+	for line in [
+		format!("timeout 5 {}", program),
+		format!("sandbox -- {}", program),
+		format!("{{ {}; }}", program),
+		format!("( {} )", program),
+	] {
+		let result = shell.run_command_line(&line, false).unwrap();
+		assert_eq!(result.exit_code, 126, "{}: {:?}", line, result.output);
+		assert!(result.output.contains("blocked by security policy"), "{}: {:?}", line, result.output);
+	}
+	assert_eq!(audit_entries(&dir, "\"resource\":\"'./run$me'\""), 4);
+
+	// Allowed commands are audited under their own name as well as the builtin's
+	let result = shell.run_command_line("timeout 5 printf allowed", false).unwrap();
+	assert_eq!((result.output.as_str(), result.exit_code), ("allowed", 0));
+	assert_eq!(audit_entries(&dir, "\"command\":\"printf allowed\""), 1);
+}
+
+/**
+ * Test background jobs passing the same checks
+ */
+#[test]
+fn test_background_checked() {
+	let dir = scratch_dir("background");
+	let program = rejected_program(&dir);
+	let mut shell = policed_shell(&dir);
+	let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+	shell.set_input(&format!("{} &", program));
+	let error = runtime.block_on(shell.execute_command()).unwrap_err();
+	assert!(error.to_string().contains("blocked by security policy"), "error: {}", error);
+	assert!(shell.get_jobs().is_empty());
+	assert_eq!(audit_entries(&dir, "\"resource\":\"'./run$me'\""), 1);
+
+	shell.set_input("sleep 0 &");
+	runtime.block_on(shell.execute_command()).unwrap();
+	assert_eq!(shell.get_jobs().len(), 1);
+	assert_eq!(audit_entries(&dir, "\"command\":\"sleep 0\""), 1);
+}
+
+/**
+ * Test sign_audit_log signing every entry with the stored keys
+ */
+#[test]
+fn test_signed_audit_log() {
+	let dir = scratch_dir("signed");
+	let log_file = dir.join("audit.log");
+	let config = SecurityPolicyConfig { log_file: Some(log_file.clone()), sign_audit_log: true, ..SecurityPolicyConfig::default() };
+	let policy = PolicyLayer::new(&config).unwrap();
+	policy.record_execution("true", true).unwrap();
+	policy.record_denial("rm -rf /", "not allowed").unwrap();
+
+	// A separate manager finds the keys, as sare-audit verify --hmac does
+	let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+	let result = runtime.block_on(async {
+		let security = Arc::new(RwLock::new(SecurityConfig::default()));
+		let verifier = EncryptionManager::new(security).await.unwrap();
+		audit::verify_log(log_file.to_str().unwrap(), Some(&verifier)).await.unwrap()
+	});
+	assert!(result.broken.is_none(), "broken: {:?}", result.broken);
+	assert_eq!((result.verified, result.signed), (2, 2));
+}
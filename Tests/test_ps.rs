@@ -0,0 +1,114 @@
+/*!
+ * Process inspection tests for the Sare shell
+ *
+ * Starts a child process with a recognisable command line and looks
+ * it up with ps, pgrep, pkill and top in batch mode.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_ps.rs
+ * Description: Tests for ps, pgrep, pkill and top
+ */
+
+use sare_shell::Shell;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, Command};
+
+/**
+ * Runs one command line in a fresh shell
+ */
+fn run(line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Starts a sleep whose argument is unique to this test and process
+ */
+fn sleeper(tag: u32) -> (Child, String) {
+	let argument = format!("{}.{}", 600 + tag, std::process::id());
+	let child = Command::new("sleep").arg(&argument).spawn().unwrap();
+	(child, format!("sleep {}", argument))
+}
+
+/**
+ * Test ps field lists and both option styles
+ */
+#[test]
+fn test_ps_formats() {
+	let (mut child, command_line) = sleeper(1);
+	let pid = child.id();
+
+	let (output, code) = run(&format!("ps -o pid=,ppid=,comm= -p {}", pid));
+	assert_eq!(output.lines().count(), 1, "output: {:?}", output);
+	assert_eq!(output.split_whitespace().collect::<Vec<_>>(), vec![pid.to_string(), std::process::id().to_string(), "sleep".to_string()]);
+	assert_eq!(code, 0);
+
+	let (output, _) = run(&format!("ps -o pid,args -p {}", pid));
+	assert_eq!(output.lines().next().unwrap().split_whitespace().collect::<Vec<_>>(), vec!["PID", "COMMAND"]);
+	assert!(output.lines().nth(1).unwrap().ends_with(&command_line));
+
+	// SysV -ef has PPID third, BSD aux has %CPU there
+	let line = |output: String| output.lines().find(|line| line.ends_with(&command_line)).unwrap().to_string();
+	assert_eq!(line(run("ps -ef").0).split_whitespace().nth(2), Some(std::process::id().to_string().as_str()));
+	assert!(line(run("ps aux").0).split_whitespace().nth(2).unwrap().contains('.'));
+
+	assert_eq!(run("ps -p 999999999").1, 1);
+	child.kill().unwrap();
+	child.wait().unwrap();
+}
+
+/**
+ * Test the --forest tree placing a child under its parent
+ */
+#[test]
+fn test_ps_forest() {
+	let (mut child, _) = sleeper(2);
+	let (output, _) = run("ps --forest -o pid,ppid,comm");
+	let lines: Vec<&str> = output.lines().collect();
+
+	let parent = lines.iter().position(|line| line.split_whitespace().next() == Some(&std::process::id().to_string())).unwrap();
+	let position = lines.iter().position(|line| line.split_whitespace().next() == Some(&child.id().to_string())).unwrap();
+	assert!(position > parent);
+	assert!(lines[position].ends_with("\\_ sleep"), "line: {}", lines[position]);
+
+	child.kill().unwrap();
+	child.wait().unwrap();
+}
+
+/**
+ * Test pgrep patterns and filters and pkill signalling the match
+ */
+#[test]
+fn test_pgrep_pkill() {
+	let (mut child, command_line) = sleeper(3);
+	let pid = child.id();
+	let uid = unsafe { libc::getuid() };
+
+	assert_eq!(run(&format!("pgrep -f '{}'", command_line)), (format!("{}\n", pid), 0));
+	assert_eq!(run(&format!("pgrep -l -u {} -f '{}'", uid, command_line)).0, format!("{} sleep\n", pid));
+	assert_eq!(run(&format!("pgrep -u {} -f '{}'", uid + 1, command_line)), (String::new(), 1));
+	assert_eq!(run("pgrep -x slee").1, 1);
+
+	assert_eq!(run(&format!("pkill -f '{}'", command_line)).1, 0);
+	assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGTERM));
+}
+
+/**
+ * Test top in batch mode showing a selected process
+ */
+#[test]
+fn test_top_batch() {
+	let (mut child, _) = sleeper(4);
+	let (output, code) = run(&format!("top -b -n 1 -p {}", child.id()));
+	assert_eq!(code, 0);
+	assert!(output.starts_with("top - "));
+	assert!(output.contains("Tasks:"));
+
+	let row = output.lines().find(|line| line.split_whitespace().next() == Some(&child.id().to_string())).unwrap();
+	assert_eq!(row.split_whitespace().last(), Some("sleep"));
+
+	child.kill().unwrap();
+	child.wait().unwrap();
+}
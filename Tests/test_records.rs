@@ -0,0 +1,87 @@
+/*!
+ * Record pipeline tests for the Sare shell
+ *
+ * Passes structured records between builtins and checks filtering,
+ * projection, sorting and the JSON and CSV conversions.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_records.rs
+ * Description: Tests for where, select, sort-by, from json, to json and to csv
+ */
+
+use sare_shell::Shell;
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates a scratch directory holding a small JSON array
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_records_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	std::fs::write(dir.join("rows.json"), r#"[{"name":"b","n":2},{"name":"a","n":10},{"name":"c","n":1}]"#).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test filtering, projecting and sorting records
+ */
+#[test]
+fn test_record_pipeline() {
+	let dir = scratch_dir("pipeline");
+	assert_eq!(run(&dir, "cat rows.json | from json | where n '>' 1 | to csv"), ("name,n\nb,2\na,10\n".to_string(), 0));
+	assert_eq!(run(&dir, "cat rows.json | from json | where n -gt 1 | sort-by name | select name | to csv").0, "name\na\nb\n");
+	assert_eq!(run(&dir, "cat rows.json | from json | where name =~ '^[ab]$' | sort-by n -r | to csv").0, "name,n\na,10\nb,2\n");
+	assert_eq!(run(&dir, "cat rows.json | from json | sort-by n | select n name | to csv").0, "n,name\n1,c\n2,b\n10,a\n");
+}
+
+/**
+ * Test records rendering as a table when nothing converts them
+ */
+#[test]
+fn test_table_rendering() {
+	let dir = scratch_dir("table");
+	assert_eq!(run(&dir, "cat rows.json | from json").0, "name   n\nb      2\na     10\nc      1\n");
+}
+
+/**
+ * Test JSON round trips keeping value types
+ */
+#[test]
+fn test_json_round_trip() {
+	let dir = scratch_dir("json");
+	let (output, code) = run(&dir, "cat rows.json | from json | where name == a | to json");
+	assert_eq!(code, 0);
+	assert_eq!(serde_json::from_str::<serde_json::Value>(&output).unwrap(), serde_json::json!([{"name": "a", "n": 10}]));
+}
+
+/**
+ * Test builtins emitting records
+ */
+#[test]
+fn test_builtin_records() {
+	let dir = scratch_dir("builtins");
+	assert_eq!(run(&dir, "ls | select name type | to csv").0, "name,type\nrows.json,file\n");
+	assert_eq!(run(&dir, "ps | where pid == 1 | select pid | to csv").0, "pid\n1\n");
+}
+
+/**
+ * Test errors for bad JSON and unknown columns
+ */
+#[test]
+fn test_record_errors() {
+	let dir = scratch_dir("errors");
+	assert_eq!(run(&dir, "printf notjson | from json"), ("from: invalid JSON: expected ident at line 1 column 2\n".to_string(), 1));
+	assert_eq!(run(&dir, "cat rows.json | from json | select missing"), ("select: no column 'missing' (columns: name, n)\n".to_string(), 1));
+}
@@ -0,0 +1,77 @@
+/*!
+ * Resource control tests for the Sare shell
+ *
+ * Runs commands under timeout, ulimit and time and checks that limits
+ * reach the child without changing the shell's own process.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_resources.rs
+ * Description: Tests for timeout, ulimit and time
+ */
+
+use sare_shell::Shell;
+use std::time::{Duration, Instant};
+
+/**
+ * Runs one command line in an existing shell
+ */
+fn run(shell: &mut Shell, line: &str) -> (String, i32) {
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Gets the soft open file limit of this process
+ */
+fn open_file_limit() -> u64 {
+	let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+	unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) };
+	limit.rlim_cur
+}
+
+/**
+ * Test timeout stopping a command and its background children
+ */
+#[test]
+fn test_timeout_kills_group() {
+	let mut shell = Shell::new().unwrap();
+	let started = Instant::now();
+	let (output, code) = run(&mut shell, "timeout 1 sh -c 'sleep 30 & sleep 30'");
+
+	assert_eq!(output, "sare: sh: timed out\n");
+	assert_eq!(code, 124);
+	assert!(started.elapsed() < Duration::from_secs(10));
+
+	assert_eq!(run(&mut shell, "timeout 5 true"), (String::new(), 0));
+	assert_eq!(run(&mut shell, "timeout -s KILL 1 sleep 30").1, 128 + libc::SIGKILL);
+}
+
+/**
+ * Test ulimit applying to children and not the shell process
+ */
+#[test]
+fn test_ulimit_applies_to_child() {
+	let before = open_file_limit();
+	let mut shell = Shell::new().unwrap();
+
+	assert_eq!(run(&mut shell, "ulimit -n 64"), (String::new(), 0));
+	assert_eq!(run(&mut shell, "ulimit -n"), ("64\n".to_string(), 0));
+	assert_eq!(run(&mut shell, "sh -c 'ulimit -n'"), ("64\n".to_string(), 0));
+	assert_eq!(open_file_limit(), before);
+
+	assert_eq!(run(&mut shell, "ulimit -v lots"), ("ulimit: lots: invalid number\n".to_string(), 1));
+}
+
+/**
+ * Test time reporting real, user and sys
+ */
+#[test]
+fn test_time_report() {
+	let mut shell = Shell::new().unwrap();
+	let (output, code) = run(&mut shell, "time sh -c 'exit 3'");
+	let labels: Vec<&str> = output.lines().filter_map(|line| line.split('\t').next()).filter(|label| !label.is_empty()).collect();
+
+	assert_eq!(labels, vec!["real", "user", "sys"]);
+	assert_eq!(code, 3);
+}
@@ -0,0 +1,189 @@
+/*!
+ * Sandbox tests for the Sare shell
+ *
+ * Runs commands under the sandbox builtin and checks the network
+ * namespace, read-only mounts and resource limits from inside. Tests
+ * are skipped when the kernel or a container denies unprivileged user
+ * namespaces.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_sandbox.rs
+ * Description: Tests for the sandbox builtin
+ */
+
+use sare_shell::Shell;
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_sandbox_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Checks that a sandbox can be created, or reports why the test is skipped
+ */
+fn sandbox_available(dir: &Path) -> bool {
+	let (output, code) = run(dir, "sandbox -- true");
+	if code != 0 {
+		eprintln!("skipping: sandbox unavailable: {}", output.trim());
+	}
+	code == 0
+}
+
+/**
+ * Test --net=off leaving only the loopback interface
+ */
+#[test]
+fn test_network_off() {
+	let dir = scratch_dir("net");
+	if !sandbox_available(&dir) {
+		return;
+	}
+
+	let (output, code) = run(&dir, "sandbox --net=off -- cat /proc/net/dev");
+	assert_eq!(code, 0, "output: {:?}", output);
+	let interfaces: Vec<&str> = output
+		.lines()
+		.skip(2)
+		.filter_map(|line| line.split(':').next())
+		.map(str::trim)
+		.collect();
+	assert_eq!(interfaces, vec!["lo"]);
+}
+
+/**
+ * Test --ro making writes fail with EROFS
+ */
+#[test]
+fn test_read_only_path() {
+	let dir = scratch_dir("ro");
+	if !sandbox_available(&dir) {
+		return;
+	}
+	std::fs::create_dir_all(dir.join("data")).unwrap();
+	std::fs::write(dir.join("data/existing.txt"), "kept\n").unwrap();
+
+	let (output, code) = run(&dir, "sandbox --ro=data -- touch data/new.txt");
+	assert!(output.contains("Read-only file system"), "output: {:?}", output);
+	assert_ne!(code, 0);
+	assert!(!dir.join("data/new.txt").exists());
+
+	let (output, code) = run(&dir, "sandbox --ro=data -- cat data/existing.txt");
+	assert_eq!(output, "kept\n");
+	assert_eq!(code, 0);
+
+	// Only the sandboxed command saw the read-only mount
+	let (_, code) = run(&dir, "touch data/outside.txt");
+	assert_eq!(code, 0);
+	assert!(dir.join("data/outside.txt").exists());
+}
+
+/**
+ * Test --nofile limiting the command but not the shell
+ */
+#[test]
+fn test_open_file_limit() {
+	let dir = scratch_dir("nofile");
+	if !sandbox_available(&dir) {
+		return;
+	}
+	let mut before = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+	unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut before) };
+
+	let (output, code) = run(&dir, "sandbox --nofile=37 -- sh -c 'ulimit -n'");
+	assert_eq!(output, "37\n");
+	assert_eq!(code, 0);
+
+	let mut after = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+	unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut after) };
+	assert_eq!((before.rlim_cur, before.rlim_max), (after.rlim_cur, after.rlim_max));
+}
+
+/// Reports how each call that could undo the sandbox fails
+const ESCAPE_PROGRAM: &str = r#"#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static const char *outcome(long result) {
+	if (result >= 0)
+		return "allowed";
+	return errno == EPERM ? "EPERM" : errno == ENOSYS ? "ENOSYS" : "other";
+}
+
+int main(void) {
+	printf("mount_setattr %s\n", outcome(syscall(SYS_mount_setattr, -1, "", 0, NULL, 0)));
+	printf("open_tree %s\n", outcome(syscall(SYS_open_tree, -1, "", 0)));
+	long child = syscall(SYS_clone, CLONE_NEWUSER | SIGCHLD, 0, 0, 0, 0);
+	if (child == 0)
+		_exit(0);
+	if (child > 0)
+		waitpid(child, NULL, 0);
+	printf("clone %s\n", outcome(child));
+	printf("unshare %s\n", outcome(unshare(CLONE_NEWUSER)));
+	printf("clone3 %s\n", outcome(syscall(SYS_clone3, NULL, 0)));
+	return 0;
+}
+"#;
+
+/**
+ * Test the seccomp filter refusing new mounts and namespaces
+ */
+#[test]
+fn test_escape_calls_blocked() {
+	let dir = scratch_dir("escape");
+	if !sandbox_available(&dir) {
+		return;
+	}
+	std::fs::write(dir.join("escape.c"), ESCAPE_PROGRAM).unwrap();
+	let built = std::process::Command::new("cc").args(["-o", "escape", "escape.c"]).current_dir(&dir).status();
+	if !built.map(|status| status.success()).unwrap_or(false) {
+		eprintln!("skipping: no C compiler");
+		return;
+	}
+
+	assert_eq!(
+		run(&dir, "sandbox -- ./escape"),
+		("mount_setattr EPERM\nopen_tree EPERM\nclone EPERM\nunshare EPERM\nclone3 ENOSYS\n".to_string(), 0)
+	);
+	let (output, code) = run(&dir, "sandbox -- unshare -U true");
+	assert!(output.contains("Operation not permitted"), "output: {:?}", output);
+	assert_ne!(code, 0);
+
+	// Ordinary forks still work, falling back from clone3 to clone
+	assert_eq!(run(&dir, "sandbox -- sh -c 'printf forked | cat'"), ("forked".to_string(), 0));
+}
+
+/**
+ * Test option errors
+ */
+#[test]
+fn test_bad_options() {
+	let dir = scratch_dir("options");
+
+	let (_, code) = run(&dir, "sandbox --net=maybe -- true");
+	assert_ne!(code, 0);
+
+	let (_, code) = run(&dir, "sandbox --ro=does-not-exist -- true");
+	assert_ne!(code, 0);
+}
@@ -0,0 +1,128 @@
+/*!
+ * SandboxManager tests for sare-security
+ *
+ * Runs commands through SandboxManager and checks that namespaces and
+ * resource limits reach the child while the calling process keeps its
+ * own limits. Tests are skipped when user namespaces are denied.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_sandbox_manager.rs
+ * Description: Tests for child-side sandbox setup in SandboxManager
+ */
+
+use sare_security::SecurityConfig;
+use sare_security::sandbox::{ProcessStatus, SandboxConfig, SandboxManager, SandboxedProcess};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/**
+ * Creates a manager with the given sandbox settings
+ */
+async fn manager(sandbox: SandboxConfig) -> SandboxManager {
+	let config = Arc::new(RwLock::new(SecurityConfig::default()));
+	let mut manager = SandboxManager::new(config).await.unwrap();
+	manager.update_config(sandbox);
+	manager
+}
+
+/**
+ * Path for a file the sandboxed command writes
+ */
+fn scratch_file(name: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("sare_sandbox_manager_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_file(&path);
+	path
+}
+
+/**
+ * Starts a command, or reports why the test is skipped
+ */
+async fn start_or_skip(manager: &SandboxManager, command: &str) -> Option<u32> {
+	match manager.create_process(command, "tester").await {
+		Ok(pid) => Some(pid),
+		Err(e) => {
+			eprintln!("skipping: cannot create sandbox: {}", e);
+			None
+		}
+	}
+}
+
+/**
+ * Waits for the monitor to see the process finish
+ */
+async fn wait_for_exit(manager: &SandboxManager, pid: u32) -> SandboxedProcess {
+	for _ in 0..100 {
+		let process = manager.get_processes().await.into_iter().find(|process| process.pid == pid).unwrap();
+		if !matches!(process.status, ProcessStatus::Running) {
+			return process;
+		}
+		tokio::time::sleep(Duration::from_millis(100)).await;
+	}
+	panic!("sandboxed process {} did not finish", pid);
+}
+
+/**
+ * Gets the soft and hard open file limit of this process
+ */
+fn open_file_limit() -> (u64, u64) {
+	let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+	unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) };
+	(limit.rlim_cur, limit.rlim_max)
+}
+
+/**
+ * Test resource limits applying to the child and not the caller
+ */
+#[tokio::test]
+async fn test_limits_apply_to_child_only() {
+	let before = open_file_limit();
+	let output = scratch_file("limits");
+	let manager = manager(SandboxConfig { max_open_files: 37, ..SandboxConfig::default() }).await;
+
+	let command = format!("ulimit -n > {0}; ulimit -t >> {0}", output.display());
+	let Some(pid) = start_or_skip(&manager, &command).await else { return };
+	let process = wait_for_exit(&manager, pid).await;
+
+	assert_eq!(process.exit_code, Some(0));
+	assert_eq!(std::fs::read_to_string(&output).unwrap(), "37\n300\n");
+	assert_eq!(open_file_limit(), before);
+	let _ = std::fs::remove_file(&output);
+}
+
+/**
+ * Test the child getting its own network namespace
+ */
+#[tokio::test]
+async fn test_network_namespace() {
+	let output = scratch_file("network");
+	let manager = manager(SandboxConfig::default()).await;
+
+	// Shell builtins only, so the process limit cannot get in the way
+	let command = format!("while read line; do echo \"$line\"; done < /proc/net/dev > {}", output.display());
+	let Some(pid) = start_or_skip(&manager, &command).await else { return };
+	wait_for_exit(&manager, pid).await;
+
+	let devices = std::fs::read_to_string(&output).unwrap();
+	let interfaces: Vec<&str> = devices.lines().skip(2).filter_map(|line| line.split(':').next()).map(str::trim).collect();
+	assert_eq!(interfaces, vec!["lo"]);
+	let _ = std::fs::remove_file(&output);
+}
+
+/**
+ * Test terminating a running process by the ID create_process returned
+ */
+#[tokio::test]
+async fn test_terminate_process() {
+	let manager = manager(SandboxConfig::default()).await;
+	assert!(manager.terminate_process(u32::MAX).await.is_err());
+
+	let Some(pid) = start_or_skip(&manager, "while :; do :; done").await else { return };
+	manager.terminate_process(pid).await.unwrap();
+	let process = wait_for_exit(&manager, pid).await;
+
+	assert!(matches!(process.status, ProcessStatus::Terminated));
+	assert_eq!(process.exit_code, Some(128 + libc::SIGKILL));
+}
@@ -0,0 +1,149 @@
+/*!
+ * sed tests for the Sare shell
+ *
+ * Runs sed scripts through the builtin with piped and file input and
+ * checks the output and exit codes against GNU sed's behaviour.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_sed.rs
+ * Description: Tests for the sed builtin
+ */
+
+use sare_shell::Shell;
+use std::path::{Path, PathBuf};
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_sed_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Runs a sed script over the numbers one to five
+ */
+fn sed_numbers(name: &str, arguments: &str) -> String {
+	let (output, code) = run(&scratch_dir(name), &format!("printf '1\\n2\\n3\\n4\\n5\\n' | sed {}", arguments));
+	assert_eq!(code, 0, "sed {} failed: {}", arguments, output);
+	output
+}
+
+/**
+ * Test line, last-line and regex addresses and ranges
+ */
+#[test]
+fn test_addresses() {
+	assert_eq!(sed_numbers("range", "-n '2,4p'"), "2\n3\n4\n");
+	assert_eq!(sed_numbers("last", "-n '$p'"), "5\n");
+	assert_eq!(sed_numbers("regex_range", "'/2/,/4/d'"), "1\n5\n");
+	assert_eq!(sed_numbers("relative", "'/2/,+1d'"), "1\n4\n5\n");
+	assert_eq!(sed_numbers("step", "-n '1~2p'"), "1\n3\n5\n");
+	assert_eq!(sed_numbers("negated", "'3!d'"), "3\n");
+	assert_eq!(sed_numbers("quit", "2q"), "1\n2\n");
+}
+
+/**
+ * Test s/// flags and extended regexes
+ */
+#[test]
+fn test_substitute_flags() {
+	let dir = scratch_dir("substitute");
+	assert_eq!(run(&dir, "printf 'aaa\\n' | sed 's/a/b/2'").0, "aba\n");
+	assert_eq!(run(&dir, "printf 'aaa\\n' | sed 's/a/b/g'").0, "bbb\n");
+	assert_eq!(run(&dir, "printf 'Aaa\\nbbb\\n' | sed -n 's/a/x/Igp'").0, "xxx\n");
+	assert_eq!(run(&dir, "printf 'key=value\\n' | sed -E 's/(\\w+)=(\\w+)/\\2=\\1/'").0, "value=key\n");
+	assert_eq!(run(&dir, "printf 'a/b\\n' | sed 's|/|-|'").0, "a-b\n");
+	assert_eq!(run(&dir, "printf 'abc\\n' | sed 'y/abc/xyz/'").0, "xyz\n");
+}
+
+/**
+ * Test the hold space and branching
+ */
+#[test]
+fn test_hold_space() {
+	assert_eq!(sed_numbers("reverse", "'1!G;h;$!d'"), "5\n4\n3\n2\n1\n");
+	assert_eq!(sed_numbers("exchange", "-n 'h;n;G;p'"), "2\n1\n4\n3\n");
+	assert_eq!(sed_numbers("join", "':a;N;$!ba;s/\\n/,/g'"), "1,2,3,4,5\n");
+}
+
+/**
+ * Test the a, i, c and = commands
+ */
+#[test]
+fn test_text_commands() {
+	assert_eq!(sed_numbers("append", "'/4/a after'"), "1\n2\n3\n4\nafter\n5\n");
+	assert_eq!(sed_numbers("insert", "'1i top'"), "top\n1\n2\n3\n4\n5\n");
+	assert_eq!(sed_numbers("change", "'2,5c gone'"), "1\ngone\n");
+	assert_eq!(sed_numbers("line_number", "-n '$='"), "5\n");
+}
+
+/**
+ * Test -n with separate files and -s
+ */
+#[test]
+fn test_separate_files() {
+	let dir = scratch_dir("separate");
+	std::fs::write(dir.join("one.txt"), "a\nb\n").unwrap();
+	std::fs::write(dir.join("two.txt"), "c\nd\n").unwrap();
+
+	assert_eq!(run(&dir, "sed -n '$p' one.txt two.txt"), ("d\n".to_string(), 0));
+	assert_eq!(run(&dir, "sed -s -n '$p' one.txt two.txt"), ("b\nd\n".to_string(), 0));
+	assert_eq!(run(&dir, "sed -n '1p' one.txt two.txt"), ("a\n".to_string(), 0));
+}
+
+/**
+ * Test -i editing in place with and without a backup suffix
+ */
+#[test]
+fn test_in_place() {
+	let dir = scratch_dir("in_place");
+	std::fs::write(dir.join("notes.txt"), "old\nkeep\n").unwrap();
+
+	assert_eq!(run(&dir, "sed -i.bak 's/old/new/' notes.txt"), (String::new(), 0));
+	assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "new\nkeep\n");
+	assert_eq!(std::fs::read_to_string(dir.join("notes.txt.bak")).unwrap(), "old\nkeep\n");
+
+	assert_eq!(run(&dir, "sed -i '2d' notes.txt"), (String::new(), 0));
+	assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "new\n");
+}
+
+/**
+ * Test scripts read with -f
+ */
+#[test]
+fn test_script_file() {
+	let dir = scratch_dir("script_file");
+	std::fs::write(dir.join("edit.sed"), "# drop the header\n1d\ns/x/y/\n").unwrap();
+
+	assert_eq!(run(&dir, "printf 'head\\nx\\n' | sed -f edit.sed"), ("y\n".to_string(), 0));
+}
+
+/**
+ * Test exit codes for bad scripts and missing files
+ */
+#[test]
+fn test_errors() {
+	let dir = scratch_dir("errors");
+	std::fs::write(dir.join("present.txt"), "here\n").unwrap();
+
+	assert_eq!(run(&dir, "sed 'k' present.txt"), ("sed: -e expression #1: unknown command: `k'\n".to_string(), 1));
+
+	// A missing file is reported, but the other files are still read
+	let (output, code) = run(&dir, "sed p missing.txt present.txt");
+	assert_eq!(output, "sed: can't read missing.txt: No such file or directory\nhere\nhere\n");
+	assert_eq!(code, 2);
+}
@@ -0,0 +1,135 @@
+/*!
+ * status dashboard tests for the Sare shell
+ *
+ * Takes one-shot samples with status --json and --once and checks the
+ * figures against /proc and statvfs, along with the shell's jobs and
+ * the git branch of the current directory.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_status.rs
+ * Description: Tests for the status dashboard and its JSON output
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::commands::dashboard;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/**
+ * Creates an empty scratch directory for one test
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_status_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/**
+ * Runs status --json in the given shell and parses its output
+ */
+fn status_json(shell: &mut Shell) -> Value {
+	let result = shell.run_command_line("status --json", false).unwrap();
+	assert_eq!(result.exit_code, 0, "output: {}", result.output);
+	serde_json::from_str(&result.output).unwrap()
+}
+
+/**
+ * Reads a /proc/meminfo field in bytes
+ */
+fn meminfo_bytes(field: &str) -> u64 {
+	let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap();
+	let line = meminfo.lines().find(|line| line.starts_with(&format!("{}:", field))).unwrap();
+	line.split_whitespace().nth(1).unwrap().parse::<u64>().unwrap() * 1024
+}
+
+/**
+ * Test the JSON sample against the kernel's own figures
+ */
+#[test]
+fn test_json_sample() {
+	let dir = scratch_dir("json");
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.clone()).unwrap();
+	let status = status_json(&mut shell);
+
+	let cores = status["cpu"]["cores"].as_array().unwrap();
+	let online = std::fs::read_to_string("/proc/stat").unwrap().lines().filter(|line| line.starts_with("cpu") && !line.starts_with("cpu ")).count();
+	assert_eq!(cores.len(), online);
+	assert!(cores.iter().chain([&status["cpu"]["total"]]).all(|load| (0.0..=100.0).contains(&load.as_f64().unwrap())));
+
+	// Sizes are bytes, not the KiB /proc/meminfo reports
+	assert_eq!(status["memory"]["total"].as_u64(), Some(meminfo_bytes("MemTotal")));
+	assert_eq!(status["swap"]["total"].as_u64(), Some(meminfo_bytes("SwapTotal")));
+	assert!(status["memory"]["used"].as_u64().unwrap() <= meminfo_bytes("MemTotal"));
+	assert_eq!(status["load"].as_array().unwrap().len(), 3);
+
+	let root = status["disks"].as_array().unwrap().iter().find(|disk| disk["mount"] == "/");
+	if let Some(root) = root {
+		let usage = dashboard::disk_usage().into_iter().find(|disk| disk.mount == "/").unwrap();
+		assert_eq!(root["total"].as_u64(), Some(usage.total));
+	}
+
+	let processes = status["processes"].as_array().unwrap();
+	assert!(!processes.is_empty());
+	assert!(processes.iter().all(|process| process["pid"].as_i64().unwrap() > 0 && process["rss"].as_u64().is_some()));
+
+	assert_eq!(status["directory"].as_str(), Some(dir.to_str().unwrap()));
+	assert_eq!(status["git"], Value::Null);
+	assert_eq!(status["jobs"], Value::Array(Vec::new()));
+}
+
+/**
+ * Creates a repository on the branch trunk, if the git binary exists
+ */
+fn git_init(dir: &Path) -> bool {
+	Command::new("git").args(["init", "-q", "-b", "trunk"]).current_dir(dir).status().map(|status| status.success()).unwrap_or(false)
+}
+
+/**
+ * Test the shell's jobs and git branch appearing in the sample
+ */
+#[test]
+fn test_jobs_and_branch() {
+	let dir = scratch_dir("jobs");
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.clone()).unwrap();
+
+	let mut sleeper = Command::new("sleep").arg("30").spawn().unwrap();
+	let job = shell.job_manager_mut().add_job(sleeper.id(), "sleep".to_string());
+	let has_git = git_init(&dir);
+	let status = status_json(&mut shell);
+	let _ = sleeper.kill();
+	let _ = sleeper.wait();
+
+	let jobs = status["jobs"].as_array().unwrap();
+	assert_eq!(jobs.len(), 1);
+	assert_eq!(jobs[0]["id"].as_u64(), Some(job as u64));
+	assert_eq!(jobs[0]["pid"].as_u64(), Some(sleeper.id() as u64));
+	assert_eq!(jobs[0]["command"], "sleep");
+	if has_git {
+		assert!(status["git"].as_str().unwrap().starts_with("trunk"), "git: {}", status["git"]);
+	}
+}
+
+/**
+ * Test the one-shot text dashboard and option errors
+ */
+#[test]
+fn test_text_sample() {
+	let mut shell = Shell::new().unwrap();
+	let result = shell.run_command_line("status --once", false).unwrap();
+	assert_eq!(result.exit_code, 0);
+	assert!(result.output.contains("load average: "), "output: {}", result.output);
+	assert!(result.output.lines().any(|line| line.starts_with("Mem ")));
+	assert!(result.output.lines().any(|line| line.trim_start().starts_with("PID ")));
+
+	// Piped output is never the interactive screen
+	assert!(shell.run_command_line("status | cat", true).unwrap().output.contains("load average: "));
+
+	let result = shell.run_command_line("status --bogus", false).unwrap();
+	assert_eq!((result.output.as_str(), result.exit_code), ("status: unknown option --bogus\n", 1));
+	assert_eq!(shell.run_command_line("status -d x", false).unwrap().exit_code, 1);
+}
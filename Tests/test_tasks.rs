@@ -0,0 +1,158 @@
+/*!
+ * Project task tests for the Sare shell
+ *
+ * Loads .sare/tasks.toml files and build tool markers from scratch
+ * projects and runs their tasks through the shortcuts builtin.
+ *
+ * Author: KleaSCM
+ * Email: KleaSCM@gmail.com
+ * File: test_tasks.rs
+ * Description: Tests for project detection, task files and shortcuts
+ */
+
+use sare_shell::Shell;
+use sare_shell::shell::commands::tasks::{self, Project, ProjectKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Task file with shared and per-task environment, a subdirectory task and dependencies
+const TASK_FILE: &str = r#"env = { GREETING = "hi" }
+
+[tasks.gen]
+run = "printf gen > gen.txt"
+description = "Generate"
+
+[tasks.slow-a]
+run = ["sleep 1", "printf a > a.txt"]
+deps = ["gen"]
+
+[tasks.slow-b]
+run = "sleep 1; printf $GREETING-$WHO > b.txt"
+deps = ["gen"]
+env = { WHO = "b" }
+
+[tasks.web]
+run = "pwd > where.txt"
+dir = "web"
+
+[tasks.all]
+deps = ["slow-a", "slow-b", "web"]
+
+[tasks.fail]
+run = "sh -c 'exit 4'"
+
+[tasks.after-fail]
+run = "printf ran > ran.txt"
+deps = ["fail"]
+"#;
+
+/**
+ * Creates a scratch project with a task file and a nested directory
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("sare_tasks_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join(".sare")).unwrap();
+	std::fs::create_dir_all(dir.join("web")).unwrap();
+	std::fs::create_dir_all(dir.join("deep/er")).unwrap();
+	std::fs::write(dir.join(tasks::TASK_FILE), TASK_FILE).unwrap();
+	dir
+}
+
+/**
+ * Runs a command line in a shell started in the given directory
+ */
+fn run(dir: &Path, line: &str) -> (String, i32) {
+	let mut shell = Shell::new().unwrap();
+	shell.set_working_directory(dir.to_path_buf()).unwrap();
+	let result = shell.run_command_line(line, false).unwrap();
+	(result.output, result.exit_code)
+}
+
+/**
+ * Test detecting build tools from their marker files
+ */
+#[test]
+fn test_detect_projects() {
+	let dir = std::env::temp_dir().join(format!("sare_tasks_detect_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("src")).unwrap();
+	std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+	std::fs::write(dir.join("Makefile"), "docs: ## Build the docs\n\t@true\n").unwrap();
+
+	assert_eq!(tasks::detect(&dir), vec![ProjectKind::Cargo, ProjectKind::Make]);
+	let project = Project::load(&dir.join("src")).unwrap();
+	assert_eq!(project.root, dir);
+	assert!(!project.has_task_file);
+
+	let test = project.task("test").unwrap();
+	assert_eq!((test.steps.as_slice(), test.source.as_str()), (["cargo test".to_string()].as_slice(), "cargo"));
+	assert_eq!(project.task("docs").unwrap().description.as_deref(), Some("Build the docs"));
+}
+
+/**
+ * Test loading a task file and planning dependencies first
+ */
+#[test]
+fn test_task_file_plan() {
+	let dir = scratch_dir("plan");
+	let project = Project::load(&dir.join("deep/er")).unwrap();
+	assert_eq!(project.root, dir);
+	assert!(project.has_task_file);
+
+	let web = project.task("web").unwrap();
+	assert_eq!(web.directory, dir.join("web"));
+	let slow_b = project.task("slow-b").unwrap();
+	assert!(slow_b.env.contains(&("GREETING".to_string(), "hi".to_string())));
+	assert!(slow_b.env.contains(&("WHO".to_string(), "b".to_string())));
+
+	let plan: Vec<&str> = project.plan(&["all".to_string()]).unwrap().iter().map(|task| task.name.as_str()).collect();
+	assert_eq!(plan, vec!["gen", "slow-a", "slow-b", "web", "all"]);
+	assert!(project.plan(&["nosuch".to_string()]).is_err());
+}
+
+/**
+ * Test a cycle in the task file being reported
+ */
+#[test]
+fn test_dependency_cycle() {
+	let dir = scratch_dir("cycle");
+	std::fs::write(dir.join(tasks::TASK_FILE), "[tasks.a]\nrun = \"true\"\ndeps = [\"b\"]\n\n[tasks.b]\nrun = \"true\"\ndeps = [\"a\"]\n").unwrap();
+	let project = Project::load(&dir).unwrap();
+	assert!(project.plan(&["a".to_string()]).is_err());
+}
+
+/**
+ * Test running tasks with their environment, directory and parallel steps
+ */
+#[test]
+fn test_run_tasks() {
+	let dir = scratch_dir("run");
+	let started = Instant::now();
+	let (output, code) = run(&dir.join("deep/er"), "shortcuts -j 4 all");
+	assert_eq!(code, 0, "output: {}", output);
+
+	// slow-a and slow-b only depend on gen, so their sleeps overlap
+	assert!(started.elapsed() < Duration::from_millis(1900), "took {:?}", started.elapsed());
+	assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+	assert_eq!(std::fs::read_to_string(dir.join("b.txt")).unwrap(), "hi-b");
+	assert_eq!(std::fs::read_to_string(dir.join("web/where.txt")).unwrap().trim_end(), dir.join("web").display().to_string());
+
+	let summary: Vec<&str> = output.lines().skip_while(|line| !line.starts_with("task ")).skip(1).collect();
+	assert_eq!(summary.len(), 5);
+	assert!(summary.iter().all(|line| line.split_whitespace().nth(1) == Some("ok")), "summary: {:?}", summary);
+}
+
+/**
+ * Test a failing task stopping the tasks that depend on it
+ */
+#[test]
+fn test_failed_dependency() {
+	let dir = scratch_dir("failed");
+	let (output, code) = run(&dir, "shortcuts after-fail");
+	assert_eq!(code, 1);
+	assert!(output.contains("fail failed with exit code 4"), "output: {}", output);
+	assert!(!dir.join("ran.txt").exists());
+
+	assert_eq!(run(&dir, "shortcuts nosuch"), ("shortcuts: unknown task 'nosuch'\n".to_string(), 1));
+}
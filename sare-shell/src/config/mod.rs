@@ -31,6 +31,9 @@ pub struct ShellConfig {
     pub shortcuts: HashMap<String, String>,
     /// Shell preferences
     pub preferences: PreferencesConfig,
+    /// Security policy layer, used when built with the `security` feature
+    #[serde(default)]
+    pub security: SecurityPolicyConfig,
 }
 
 /**
@@ -46,6 +49,35 @@ pub struct ThemeConfig {
     pub background_color: String,
     /// Text color
     pub text_color: String,
+    /// Color of matched text in search results (grep --color)
+    #[serde(default = "default_match_color")]
+    pub match_color: String,
+}
+
+/**
+ * Default color of matched text, GNU grep's red
+ *
+ * @return String - Hex color
+ */
+fn default_match_color() -> String {
+    "#FF5555".to_string()
+}
+
+impl ThemeConfig {
+    /**
+     * Converts a theme color to an ANSI 24-bit foreground sequence
+     *
+     * @param color - Color as #RRGGBB
+     * @return Option<String> - Escape sequence, or None if the color is malformed
+     */
+    pub fn ansi_foreground(color: &str) -> Option<String> {
+        let hex = color.strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |range: std::ops::Range<usize>| u8::from_str_radix(hex.get(range)?, 16).ok();
+        Some(format!("\x1b[38;2;{};{};{}m", channel(0..2)?, channel(2..4)?, channel(4..6)?))
+    }
 }
 
 /**
@@ -61,6 +93,52 @@ pub struct PreferencesConfig {
     pub show_line_numbers: bool,
 }
 
+/**
+ * What the shell does with a command the security policy rejects
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DenyAction {
+    /// Refuse to run the command
+    #[default]
+    Block,
+    /// Print a warning and run the command anyway
+    Warn,
+    /// Ask on the terminal whether to run the command
+    Prompt,
+}
+
+/**
+ * Security policy configuration
+ *
+ * Only read by shells built with the `security` feature; each
+ * command is then checked by sare-security before it runs and
+ * every execution is written to its audit log.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityPolicyConfig {
+    /// Whether commands are checked at all
+    pub enabled: bool,
+    /// Action taken when a command is rejected
+    pub on_deny: DenyAction,
+    /// Audit log file, sare-security's per-user default if unset
+    pub log_file: Option<PathBuf>,
+    /// Whether audit entries are signed with an HMAC
+    pub sign_audit_log: bool,
+}
+
+impl Default for SecurityPolicyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_deny: DenyAction::Block,
+            log_file: None,
+            sign_audit_log: false,
+        }
+    }
+}
+
 impl Default for ShellConfig {
     fn default() -> Self {
         Self {
@@ -69,6 +147,7 @@ impl Default for ShellConfig {
                 secondary_color: "#98FB98".to_string(),
                 background_color: "#1E1E1E".to_string(),
                 text_color: "#FFFFFF".to_string(),
+                match_color: default_match_color(),
             },
             shortcuts: HashMap::new(),
             preferences: PreferencesConfig {
@@ -76,10 +155,28 @@ impl Default for ShellConfig {
                 auto_completion: true,
                 show_line_numbers: false,
             },
+            security: SecurityPolicyConfig::default(),
         }
     }
 }
 
+impl ShellConfig {
+    /**
+     * Reads the saved configuration without creating any files
+     *
+     * Used by builtins that only need settings such as theme colors.
+     *
+     * @return ShellConfig - Saved configuration, or the defaults if it is missing or invalid
+     */
+    pub fn load() -> Self {
+        dirs::config_dir()
+            .map(|dir| dir.join("sare").join("config.json"))
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+}
+
 /**
  * Configuration manager
  * 
@@ -15,6 +15,18 @@ use anyhow::Result;
 use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
 use std::fs;
+use crate::shell::git;
+use crate::shell::commands::makefile::{self, Makefile};
+use crate::shell::commands::npm;
+
+/// git subcommands whose arguments are usually branch or tag names
+const GIT_REF_SUBCOMMANDS: [&str; 11] = ["checkout", "switch", "merge", "rebase", "log", "diff", "branch", "show", "reset", "cherry-pick", "revert"];
+
+/// make options whose value is a file or directory rather than a target
+const MAKE_VALUE_OPTIONS: [&str; 9] = ["-C", "-f", "-I", "-o", "-W", "--directory", "--file", "--makefile", "--include-dir"];
+
+/// npm subcommands taking a package.json script name
+const NPM_RUN_SUBCOMMANDS: [&str; 4] = ["run", "run-script", "rum", "urn"];
 
 /**
  * Completion context
@@ -32,6 +44,12 @@ pub enum CompletionContext {
 	Flag,
 	/// Variable completion (after $)
 	Variable,
+	/// Branch and tag completion (after git checkout, log, diff, ...)
+	GitRef,
+	/// Makefile target completion (after make)
+	MakeTarget,
+	/// package.json script or workspace completion (after npm run)
+	NpmScript,
 	/// Unknown context
 	Unknown,
 }
@@ -136,6 +154,21 @@ impl TabCompleter {
 			CompletionContext::Variable => {
 				self.complete_variable(input, cursor_pos)
 			}
+			CompletionContext::GitRef => {
+				match self.complete_git_ref(input, cursor_pos)? {
+					Some(result) => Ok(Some(result)),
+					None => self.complete_file_path(input, cursor_pos),
+				}
+			}
+			CompletionContext::MakeTarget => {
+				match self.complete_make_target(input, cursor_pos)? {
+					Some(result) => Ok(Some(result)),
+					None => self.complete_file_path(input, cursor_pos),
+				}
+			}
+			CompletionContext::NpmScript => {
+				self.complete_npm_script(input, cursor_pos)
+			}
 			CompletionContext::Unknown => {
 				Ok(None)
 			}
@@ -161,6 +194,12 @@ impl TabCompleter {
 			return Ok(CompletionContext::Command);
 		}
 		
+		let completing_new_word = before_cursor.ends_with(char::is_whitespace);
+		let typing_option = !completing_new_word && words.last().is_some_and(|word| word.starts_with('-'));
+		if words[0] == "npm" && words.iter().any(|word| NPM_RUN_SUBCOMMANDS.contains(word)) && !typing_option {
+			return Ok(CompletionContext::NpmScript);
+		}
+		
 		if let Some(last_word) = words.last() {
 			if last_word.starts_with('-') {
 				return Ok(CompletionContext::Flag);
@@ -173,6 +212,15 @@ impl TabCompleter {
 			}
 		}
 		
+		if words[0] == "git" && GIT_REF_SUBCOMMANDS.contains(&words[1]) && (words.len() > 2 || completing_new_word) {
+			return Ok(CompletionContext::GitRef);
+		}
+		
+		let option_word = if completing_new_word { words.last() } else { words.get(words.len() - 2) };
+		if words[0] == "make" && !option_word.is_some_and(|word| MAKE_VALUE_OPTIONS.contains(word)) {
+			return Ok(CompletionContext::MakeTarget);
+		}
+		
 		Ok(CompletionContext::FilePath)
 	}
 	
@@ -343,6 +391,189 @@ impl TabCompleter {
 		Ok(None)
 	}
 	
+	/// Completes branch, remote branch and tag names for git
+	///
+	/// @param input - Current input text
+	/// @param cursor_pos - Current cursor position
+	/// @return Result<Option<CompletionResult>> - Ref completion result
+	fn complete_git_ref(&self, input: &str, cursor_pos: usize) -> Result<Option<CompletionResult>> {
+		/*
+		 * gitのブランチ名とタグ名を補完する関数です
+		 *
+		 * 作業ディレクトリのリポジトリから.gitを直接読んで、
+		 * ローカルブランチ、リモートブランチ、タグの名前を集め、
+		 * 入力中の単語で始まるものを候補にします。
+		 *
+		 * 「main..fe」のような範囲指定では「..」の後ろだけを
+		 * 補完します。候補がない場合はNoneを返し、呼び出し元が
+		 * ファイルパスの補完に切り替えます
+		 */
+		
+		let before_cursor = &input[..cursor_pos];
+		let word = if before_cursor.ends_with(char::is_whitespace) {
+			""
+		} else {
+			before_cursor.split_whitespace().last().unwrap_or("")
+		};
+		let (range_start, partial) = match word.rfind("..") {
+			Some(position) => word.split_at(position + 2),
+			None => ("", word),
+		};
+		
+		let matches: Vec<String> = git::ref_names(&self.working_directory)
+			.into_iter()
+			.filter(|name| name.starts_with(partial))
+			.map(|name| format!("{}{}", range_start, name))
+			.collect();
+		
+		if matches.is_empty() {
+			Ok(None)
+		} else if matches.len() == 1 {
+			Ok(Some(CompletionResult {
+				completed_text: matches[0].clone(),
+				is_partial: false,
+				alternatives: Vec::new(),
+				context: CompletionContext::GitRef,
+			}))
+		} else {
+			let common_prefix = self.find_common_prefix(&matches);
+			Ok(Some(CompletionResult {
+				completed_text: common_prefix,
+				is_partial: true,
+				alternatives: matches,
+				context: CompletionContext::GitRef,
+			}))
+		}
+	}
+	
+	/// Completes Makefile targets for make
+	///
+	/// @param input - Current input text
+	/// @param cursor_pos - Current cursor position
+	/// @return Result<Option<CompletionResult>> - Target completion result
+	fn complete_make_target(&self, input: &str, cursor_pos: usize) -> Result<Option<CompletionResult>> {
+		/*
+		 * makeのターゲット名を補完する関数です
+		 *
+		 * 入力済みの-C、-fとコマンドラインの変数から、makeが読む
+		 * Makefileを探して（includeしたファイルも含めて）読み、
+		 * 入力中の単語で始まるターゲットを候補にします。
+		 *
+		 * Makefileがない場合や候補がない場合はNoneを返し、
+		 * 呼び出し元がファイルパスの補完に切り替えます
+		 */
+		
+		let before_cursor = &input[..cursor_pos];
+		let args: Vec<String> = before_cursor.split_whitespace().skip(1).map(str::to_string).collect();
+		let partial = if before_cursor.ends_with(char::is_whitespace) {
+			""
+		} else {
+			args.last().map(String::as_str).unwrap_or("")
+		};
+		
+		let (directory, file, overrides) = makefile::locate(&args, &self.working_directory);
+		let Ok(makefile) = Makefile::discover(&directory, file.as_deref(), &overrides) else {
+			return Ok(None);
+		};
+		let matches: Vec<String> = makefile
+			.listed_targets()
+			.filter(|target| target.name.starts_with(partial))
+			.map(|target| target.name.clone())
+			.collect();
+		
+		if matches.is_empty() {
+			Ok(None)
+		} else if matches.len() == 1 {
+			Ok(Some(CompletionResult {
+				completed_text: matches[0].clone(),
+				is_partial: false,
+				alternatives: Vec::new(),
+				context: CompletionContext::MakeTarget,
+			}))
+		} else {
+			let common_prefix = self.find_common_prefix(&matches);
+			Ok(Some(CompletionResult {
+				completed_text: common_prefix,
+				is_partial: true,
+				alternatives: matches,
+				context: CompletionContext::MakeTarget,
+			}))
+		}
+	}
+	
+	/// Completes package.json scripts and workspaces for npm run
+	///
+	/// @param input - Current input text
+	/// @param cursor_pos - Current cursor position
+	/// @return Result<Option<CompletionResult>> - Script or workspace completion result
+	fn complete_npm_script(&self, input: &str, cursor_pos: usize) -> Result<Option<CompletionResult>> {
+		/*
+		 * npm runのスクリプト名とワークスペース名を補完する関数です
+		 *
+		 * -w（--workspace）の値を入力中ならワークスペースの
+		 * パッケージ名を候補にします。それ以外は、-wで選んだ
+		 * パッケージ（なければ最も近いpackage.json）のスクリプトを
+		 * 候補にします。スクリプト名の入力が済んだ後の単語は
+		 * スクリプトへの引数なので補完しません
+		 */
+		
+		let before_cursor = &input[..cursor_pos];
+		let words: Vec<&str> = before_cursor.split_whitespace().collect();
+		let completing_new_word = before_cursor.ends_with(char::is_whitespace);
+		let (partial, previous) = if completing_new_word {
+			("", &words[..])
+		} else {
+			(words.last().copied().unwrap_or(""), &words[..words.len() - 1])
+		};
+		
+		let workspace = npm::Workspace::find(&self.working_directory);
+		let mut selected = None;
+		let mut positionals = 0;
+		let mut expects_workspace = false;
+		for word in previous.iter().skip(1) {
+			if expects_workspace {
+				selected = workspace.as_ref().and_then(|workspace| workspace.select(word, &self.working_directory).ok());
+				expects_workspace = false;
+			} else if *word == "-w" || *word == "--workspace" {
+				expects_workspace = true;
+			} else if !word.starts_with('-') {
+				positionals += 1;
+			}
+		}
+		
+		let candidates: Vec<String> = if expects_workspace {
+			workspace.iter().flat_map(|workspace| workspace.packages.iter()).filter_map(|package| package.name.clone()).collect()
+		} else if positionals == 1 {
+			let package = match selected {
+				Some(package) => Some(package.clone()),
+				None => npm::Package::find(&self.working_directory).and_then(|directory| npm::Package::load(&directory).ok()),
+			};
+			package.map(|package| package.scripts.into_iter().map(|(name, _)| name).collect()).unwrap_or_default()
+		} else {
+			Vec::new()
+		};
+		let matches: Vec<String> = candidates.into_iter().filter(|candidate| candidate.starts_with(partial)).collect();
+		
+		if matches.is_empty() {
+			Ok(None)
+		} else if matches.len() == 1 {
+			Ok(Some(CompletionResult {
+				completed_text: matches[0].clone(),
+				is_partial: false,
+				alternatives: Vec::new(),
+				context: CompletionContext::NpmScript,
+			}))
+		} else {
+			let common_prefix = self.find_common_prefix(&matches);
+			Ok(Some(CompletionResult {
+				completed_text: common_prefix,
+				is_partial: true,
+				alternatives: matches,
+				context: CompletionContext::NpmScript,
+			}))
+		}
+	}
+	
 	/**
 	 * Completes environment variables
 	 * 
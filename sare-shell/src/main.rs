@@ -14,11 +14,7 @@
 
 use anyhow::Result;
 
-mod shell;
-mod history;
-mod config;
-
-use shell::Shell;
+use sare_shell::Shell;
 
 /**
  * Main entry point for the Sare shell
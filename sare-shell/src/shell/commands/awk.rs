@@ -0,0 +1,3969 @@
+/*!
+ * @file awk.rs
+ * @brief AWK language interpreter
+ *
+ * This module implements a POSIX awk interpreter for the `awk`
+ * builtin. Programs are tokenized, parsed into a syntax tree and
+ * executed in-process.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file awk.rs
+ * @description awk lexer, parser and tree-walking interpreter with
+ * patterns, ranges, associative arrays, user functions, printf and getline.
+ */
+
+use anyhow::Result;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::shell::commands::sed::translate_posix_regex;
+
+/**
+ * Options given on the awk command line
+ */
+#[derive(Debug, Clone, Default)]
+pub struct AwkOptions {
+    /// Field separator from -F
+    pub field_separator: Option<String>,
+    /// Assignments from -v, applied before BEGIN
+    pub assignments: Vec<(String, String)>,
+}
+
+/**
+ * Result of running an awk program
+ */
+#[derive(Debug, Default)]
+pub struct AwkOutput {
+    /// Text written to standard output
+    pub output: String,
+    /// Exit status from `exit`, or 2 after a fatal error
+    pub exit_code: i32,
+}
+
+/**
+ * Builtin awk functions
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BuiltinFunc {
+    Length,
+    Substr,
+    Index,
+    Split,
+    Sub,
+    Gsub,
+    Match,
+    Sprintf,
+    Sin,
+    Cos,
+    Atan2,
+    Exp,
+    Log,
+    Sqrt,
+    Int,
+    Rand,
+    Srand,
+    Tolower,
+    Toupper,
+    System,
+    Close,
+    Fflush,
+}
+
+impl BuiltinFunc {
+    /**
+     * Looks up a builtin function by name
+     *
+     * @param name - Function name
+     * @return Option<BuiltinFunc> - Builtin, or None for other names
+     */
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "length" => Self::Length,
+            "substr" => Self::Substr,
+            "index" => Self::Index,
+            "split" => Self::Split,
+            "sub" => Self::Sub,
+            "gsub" => Self::Gsub,
+            "match" => Self::Match,
+            "sprintf" => Self::Sprintf,
+            "sin" => Self::Sin,
+            "cos" => Self::Cos,
+            "atan2" => Self::Atan2,
+            "exp" => Self::Exp,
+            "log" => Self::Log,
+            "sqrt" => Self::Sqrt,
+            "int" => Self::Int,
+            "rand" => Self::Rand,
+            "srand" => Self::Srand,
+            "tolower" => Self::Tolower,
+            "toupper" => Self::Toupper,
+            "system" => Self::System,
+            "close" => Self::Close,
+            "fflush" => Self::Fflush,
+            _ => return None,
+        })
+    }
+}
+
+/**
+ * Lexical token
+ */
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Regex(String),
+    Name(String),
+    /// Name directly followed by `(` - a user function call
+    FuncName(String),
+    Builtin(BuiltinFunc),
+    Begin,
+    End,
+    Function,
+    If,
+    Else,
+    While,
+    For,
+    Do,
+    Break,
+    Continue,
+    Next,
+    NextFile,
+    Exit,
+    Return,
+    Delete,
+    In,
+    Getline,
+    Print,
+    Printf,
+    LeftBrace,
+    RightBrace,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Semicolon,
+    Newline,
+    Comma,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Not,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    Tilde,
+    NotTilde,
+    And,
+    Or,
+    Question,
+    Colon,
+    Assign,
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+    ModAssign,
+    PowAssign,
+    Increment,
+    Decrement,
+    Dollar,
+    Pipe,
+    Append,
+    Eof,
+}
+
+/**
+ * Variable reference resolved at parse time
+ */
+#[derive(Debug, Clone)]
+enum VarRef {
+    /// Global variable by name
+    Global(String),
+    /// Function parameter or local by slot
+    Local(usize),
+}
+
+/**
+ * Arithmetic operator
+ */
+#[derive(Debug, Clone, Copy)]
+enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+}
+
+/**
+ * Comparison operator
+ */
+#[derive(Debug, Clone, Copy)]
+enum CompareOp {
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+}
+
+/**
+ * Where `getline` reads from
+ */
+#[derive(Debug, Clone)]
+enum GetlineSource {
+    /// Main input
+    Main,
+    /// `getline < file`
+    File(Box<Expr>),
+    /// `command | getline`
+    Command(Box<Expr>),
+}
+
+/**
+ * Expression node
+ */
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(f64),
+    String(String),
+    /// Regex literal; matches against $0 when used as a value
+    Regex(Regex),
+    Variable(VarRef),
+    Index(VarRef, Vec<Expr>),
+    Field(Box<Expr>),
+    /// Parenthesized expression list, as in `print (a, b)`
+    Grouping(Vec<Expr>),
+    Assign(Option<BinaryOp>, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    ToNumber(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Concat(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+    /// `~` or `!~` (true when negated)
+    Match(bool, Box<Expr>, Box<Expr>),
+    In(Vec<Expr>, VarRef),
+    IncDec { target: Box<Expr>, delta: f64, prefix: bool },
+    Call(String, Vec<Expr>),
+    Builtin(BuiltinFunc, Vec<Expr>),
+    Getline { source: GetlineSource, target: Option<Box<Expr>> },
+}
+
+impl Expr {
+    /**
+     * Checks whether the expression can be assigned to
+     *
+     * @return bool - True for variables, array elements and fields
+     */
+    fn is_lvalue(&self) -> bool {
+        matches!(self, Expr::Variable(_) | Expr::Index(..) | Expr::Field(_))
+    }
+}
+
+/**
+ * Output redirection of print and printf
+ */
+#[derive(Debug, Clone)]
+enum Redirect {
+    /// `> file`
+    File(Expr),
+    /// `>> file`
+    Append(Expr),
+    /// `| command`
+    Pipe(Expr),
+}
+
+/**
+ * Statement node
+ */
+#[derive(Debug, Clone)]
+enum Stmt {
+    Print(Vec<Expr>, Option<Redirect>),
+    Printf(Vec<Expr>, Option<Redirect>),
+    Expression(Expr),
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    While(Expr, Box<Stmt>),
+    DoWhile(Box<Stmt>, Expr),
+    For(Option<Expr>, Option<Expr>, Option<Expr>, Box<Stmt>),
+    ForIn(VarRef, VarRef, Box<Stmt>),
+    Block(Vec<Stmt>),
+    Next,
+    NextFile,
+    Exit(Option<Expr>),
+    Return(Option<Expr>),
+    Break,
+    Continue,
+    Delete(VarRef, Option<Vec<Expr>>),
+}
+
+/**
+ * Pattern of a rule
+ */
+#[derive(Debug, Clone)]
+enum Pattern {
+    /// No pattern - every record
+    All,
+    /// Records where the expression is true
+    Expr(Expr),
+    /// Records from a start match through an end match
+    Range(Expr, Expr),
+}
+
+/**
+ * Pattern-action rule
+ */
+#[derive(Debug, Clone)]
+struct Rule {
+    /// When the rule applies
+    pattern: Pattern,
+    /// Statements to run; None prints the record
+    action: Option<Vec<Stmt>>,
+}
+
+/**
+ * User-defined function
+ */
+#[derive(Debug, Clone)]
+struct Function {
+    /// Number of parameters (extra parameters act as locals)
+    param_count: usize,
+    /// Parameters the body uses as arrays
+    array_params: HashSet<usize>,
+    /// Function body
+    body: Vec<Stmt>,
+}
+
+/**
+ * Compiled awk program
+ */
+#[derive(Debug, Clone)]
+pub struct AwkProgram {
+    /// BEGIN actions
+    begin: Vec<Vec<Stmt>>,
+    /// END actions
+    end: Vec<Vec<Stmt>>,
+    /// Main rules
+    rules: Vec<Rule>,
+    /// User functions by name
+    functions: HashMap<String, Function>,
+}
+
+impl AwkProgram {
+    /**
+     * Compiles awk source text
+     *
+     * @param source - Program text
+     * @return Result<AwkProgram> - Compiled program or syntax error
+     */
+    pub fn compile(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            no_greater: false,
+            locals: None,
+            array_locals: HashSet::new(),
+            calls: Vec::new(),
+        };
+        parser.parse_program()
+    }
+
+    /**
+     * awkプログラムを実行する関数です
+     *
+     * 組み込み変数（FS、OFS、ORS、RS、NR、SUBSEP、CONVFMT、
+     * ENVIRON、ARGV、ARGCなど）を初期化し、-Fと-vの設定を
+     * 適用してからBEGIN、メインループ、ENDの順に実行します。
+     *
+     * ファイルが指定されていない場合はパイプラインの入力を
+     * 読み込みます。実行時エラーが発生した場合は、それまでの
+     * 出力にエラーメッセージを追加し、終了コード2を返します。
+     *
+     * @param options - コマンドラインオプション
+     * @param operands - ファイル名と変数代入（ARGVになります）
+     * @param stdin - パイプラインからの入力
+     * @param working_dir - 相対パスを解決するディレクトリ
+     * @return AwkOutput - 出力と終了コード
+     */
+    pub fn run(&self, options: &AwkOptions, operands: &[String], stdin: Option<String>, working_dir: &Path) -> AwkOutput {
+        let mut interpreter = Interpreter {
+            program: self,
+            globals: HashMap::new(),
+            arrays: Vec::new(),
+            frames: Vec::new(),
+            record: String::new(),
+            fields: Vec::new(),
+            main_input: MainInput::default(),
+            inputs: HashMap::new(),
+            outputs: Vec::new(),
+            output: String::new(),
+            regex_cache: HashMap::new(),
+            range_active: vec![false; self.rules.len()],
+            working_dir: working_dir.to_path_buf(),
+            stdin,
+            random_seed: 0.0,
+            random_state: 0,
+            exit_code: 0,
+        };
+
+        let result = interpreter
+            .initialize(options, operands)
+            .and_then(|_| interpreter.execute());
+
+        if let Err(e) = result.and_then(|_| interpreter.close_all_outputs()) {
+            interpreter.output.push_str(&format!("awk: {}\n", e));
+            interpreter.exit_code = 2;
+        }
+
+        AwkOutput {
+            output: interpreter.output,
+            exit_code: interpreter.exit_code,
+        }
+    }
+}
+
+/**
+ * Processes one escape sequence
+ *
+ * Called with `pos` just after the backslash. Unknown escapes keep
+ * their backslash so strings can still be used as regexes.
+ *
+ * @param chars - Source characters
+ * @param pos - Position of the escaped character, advanced past it
+ * @param text - Buffer to append to
+ */
+fn push_escape(chars: &[char], pos: &mut usize, text: &mut String) {
+    let Some(&c) = chars.get(*pos) else {
+        text.push('\\');
+        return;
+    };
+    *pos += 1;
+
+    match c {
+        'n' => text.push('\n'),
+        't' => text.push('\t'),
+        'r' => text.push('\r'),
+        '\\' => text.push('\\'),
+        '"' => text.push('"'),
+        '/' => text.push('/'),
+        'a' => text.push('\x07'),
+        'b' => text.push('\x08'),
+        'f' => text.push('\x0c'),
+        'v' => text.push('\x0b'),
+        '\n' => {}
+        '0'..='7' => {
+            let mut value = c.to_digit(8).unwrap_or(0);
+            for _ in 0..2 {
+                match chars.get(*pos).and_then(|d| d.to_digit(8)) {
+                    Some(digit) => {
+                        value = value * 8 + digit;
+                        *pos += 1;
+                    }
+                    None => break,
+                }
+            }
+            text.push(char::from_u32(value).unwrap_or('\0'));
+        }
+        other => {
+            text.push('\\');
+            text.push(other);
+        }
+    }
+}
+
+/**
+ * Resolves escape sequences in a command-line assignment value
+ *
+ * @param value - Raw value
+ * @return String - Value with escapes processed
+ */
+pub fn unescape_awk_string(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let mut text = String::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        pos += 1;
+        if c == '\\' {
+            push_escape(&chars, &mut pos, &mut text);
+        } else {
+            text.push(c);
+        }
+    }
+
+    text
+}
+
+/**
+ * awkのソースをトークンに分割する関数です
+ *
+ * 数値、文字列、正規表現、名前、キーワード、演算子を
+ * 認識します。`/`は直前のトークンが値で終わる場合は除算、
+ * それ以外の場合は正規表現リテラルの開始として扱います。
+ *
+ * 直後に`(`が続く名前はユーザー関数呼び出しとして区別し、
+ * バックスラッシュと改行による行継続と`#`コメントを
+ * スキップします。
+ *
+ * @param source - awkのソース
+ * @return Result<Vec<(Token, usize)>> - トークンと行番号のリストまたはエラー
+ */
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens: Vec<(Token, usize)> = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        match c {
+            ' ' | '\t' | '\r' => {
+                i += 1;
+                continue;
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                i += 2;
+                line += 1;
+                continue;
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '\n' => {
+                tokens.push((Token::Newline, line));
+                line += 1;
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let regex_allowed = !matches!(
+            tokens.last().map(|(token, _)| token),
+            Some(Token::Number(_) | Token::Str(_) | Token::Name(_) | Token::Builtin(_)
+                | Token::RightParen | Token::RightBracket | Token::Increment | Token::Decrement)
+        );
+
+        let token = if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            let start = i;
+            while chars.get(i).is_some_and(|d| d.is_ascii_digit()) {
+                i += 1;
+            }
+            if chars.get(i) == Some(&'.') {
+                i += 1;
+                while chars.get(i).is_some_and(|d| d.is_ascii_digit()) {
+                    i += 1;
+                }
+            }
+            if matches!(chars.get(i), Some('e') | Some('E')) {
+                let mut j = i + 1;
+                if matches!(chars.get(j), Some('+') | Some('-')) {
+                    j += 1;
+                }
+                if chars.get(j).is_some_and(|d| d.is_ascii_digit()) {
+                    i = j;
+                    while chars.get(i).is_some_and(|d| d.is_ascii_digit()) {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            Token::Number(text.parse().map_err(|_| anyhow::anyhow!("invalid number '{}'", text))?)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while chars.get(i).is_some_and(|d| d.is_ascii_alphanumeric() || *d == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            match word.as_str() {
+                "BEGIN" => Token::Begin,
+                "END" => Token::End,
+                "function" | "func" => Token::Function,
+                "if" => Token::If,
+                "else" => Token::Else,
+                "while" => Token::While,
+                "for" => Token::For,
+                "do" => Token::Do,
+                "break" => Token::Break,
+                "continue" => Token::Continue,
+                "next" => Token::Next,
+                "nextfile" => Token::NextFile,
+                "exit" => Token::Exit,
+                "return" => Token::Return,
+                "delete" => Token::Delete,
+                "in" => Token::In,
+                "getline" => Token::Getline,
+                "print" => Token::Print,
+                "printf" => Token::Printf,
+                _ => match BuiltinFunc::from_name(&word) {
+                    Some(builtin) => Token::Builtin(builtin),
+                    None if chars.get(i) == Some(&'(') => Token::FuncName(word),
+                    None => Token::Name(word),
+                },
+            }
+        } else if c == '"' {
+            i += 1;
+            let mut text = String::new();
+            loop {
+                let Some(&s) = chars.get(i) else {
+                    return Err(anyhow::anyhow!("unterminated string at source line {}", line));
+                };
+                i += 1;
+                match s {
+                    '"' => break,
+                    '\n' => return Err(anyhow::anyhow!("newline in string at source line {}", line)),
+                    '\\' => {
+                        if chars.get(i) == Some(&'\n') {
+                            line += 1;
+                        }
+                        push_escape(&chars, &mut i, &mut text);
+                    }
+                    _ => text.push(s),
+                }
+            }
+            Token::Str(text)
+        } else if c == '/' && regex_allowed {
+            i += 1;
+            let mut text = String::new();
+            let mut in_bracket = false;
+            loop {
+                let Some(&r) = chars.get(i) else {
+                    return Err(anyhow::anyhow!("unterminated regular expression at source line {}", line));
+                };
+                i += 1;
+                match r {
+                    '\n' => return Err(anyhow::anyhow!("newline in regular expression at source line {}", line)),
+                    '\\' => {
+                        if let Some(&escaped) = chars.get(i) {
+                            i += 1;
+                            if escaped != '/' {
+                                text.push('\\');
+                            }
+                            text.push(escaped);
+                        }
+                    }
+                    '[' if !in_bracket => {
+                        in_bracket = true;
+                        text.push('[');
+                        if chars.get(i) == Some(&'^') {
+                            text.push('^');
+                            i += 1;
+                        }
+                        if chars.get(i) == Some(&']') {
+                            text.push(']');
+                            i += 1;
+                        }
+                    }
+                    ']' if in_bracket => {
+                        in_bracket = false;
+                        text.push(']');
+                    }
+                    '/' if !in_bracket => break,
+                    _ => text.push(r),
+                }
+            }
+            Token::Regex(text)
+        } else {
+            let (token, length) = match_operator(&chars[i..])
+                .ok_or_else(|| anyhow::anyhow!("unexpected character '{}' at source line {}", c, line))?;
+            i += length;
+            token
+        };
+
+        tokens.push((token, line));
+    }
+
+    tokens.push((Token::Eof, line));
+    Ok(tokens)
+}
+
+/**
+ * Matches the longest operator at the start of the input
+ *
+ * @param chars - Remaining source characters
+ * @return Option<(Token, usize)> - Operator token and its length
+ */
+fn match_operator(chars: &[char]) -> Option<(Token, usize)> {
+    let two: String = chars.iter().take(2).collect();
+
+    if chars.starts_with(&['*', '*', '=']) {
+        return Some((Token::PowAssign, 3));
+    }
+
+    let double = match two.as_str() {
+        "&&" => Some(Token::And),
+        "||" => Some(Token::Or),
+        "==" => Some(Token::Equal),
+        "!=" => Some(Token::NotEqual),
+        "<=" => Some(Token::LessEqual),
+        ">=" => Some(Token::GreaterEqual),
+        "!~" => Some(Token::NotTilde),
+        "++" => Some(Token::Increment),
+        "--" => Some(Token::Decrement),
+        "+=" => Some(Token::AddAssign),
+        "-=" => Some(Token::SubAssign),
+        "*=" => Some(Token::MulAssign),
+        "/=" => Some(Token::DivAssign),
+        "%=" => Some(Token::ModAssign),
+        "^=" => Some(Token::PowAssign),
+        "**" => Some(Token::Caret),
+        ">>" => Some(Token::Append),
+        _ => None,
+    };
+    if let Some(token) = double {
+        return Some((token, 2));
+    }
+
+    let single = match chars.first()? {
+        '{' => Token::LeftBrace,
+        '}' => Token::RightBrace,
+        '(' => Token::LeftParen,
+        ')' => Token::RightParen,
+        '[' => Token::LeftBracket,
+        ']' => Token::RightBracket,
+        ';' => Token::Semicolon,
+        ',' => Token::Comma,
+        '+' => Token::Plus,
+        '-' => Token::Minus,
+        '*' => Token::Star,
+        '/' => Token::Slash,
+        '%' => Token::Percent,
+        '^' => Token::Caret,
+        '!' => Token::Not,
+        '<' => Token::Less,
+        '>' => Token::Greater,
+        '~' => Token::Tilde,
+        '?' => Token::Question,
+        ':' => Token::Colon,
+        '=' => Token::Assign,
+        '$' => Token::Dollar,
+        '|' => Token::Pipe,
+        _ => return None,
+    };
+    Some((single, 1))
+}
+
+/**
+ * Compiles a regex from awk (POSIX ERE) syntax
+ *
+ * @param source - Regex source
+ * @return Result<Regex> - Compiled regex
+ */
+fn compile_awk_regex(source: &str) -> Result<Regex> {
+    let translated = translate_posix_regex(source, true)?;
+    Regex::new(&format!("(?s){}", translated))
+        .map_err(|e| anyhow::anyhow!("invalid regular expression /{}/: {}", source, e))
+}
+
+/**
+ * Recursive descent parser state
+ */
+struct Parser {
+    /// Tokens with line numbers
+    tokens: Vec<(Token, usize)>,
+    /// Current token index
+    pos: usize,
+    /// Whether `>` is a redirection rather than a comparison
+    no_greater: bool,
+    /// Parameter slots of the function being parsed
+    locals: Option<HashMap<String, usize>>,
+    /// Parameters used as arrays in the function being parsed
+    array_locals: HashSet<usize>,
+    /// User function calls with their lines, checked after parsing
+    calls: Vec<(String, usize)>,
+}
+
+impl Parser {
+    /**
+     * Parses the whole program
+     *
+     * @return Result<AwkProgram> - Compiled program
+     */
+    fn parse_program(&mut self) -> Result<AwkProgram> {
+        let mut program = AwkProgram {
+            begin: Vec::new(),
+            end: Vec::new(),
+            rules: Vec::new(),
+            functions: HashMap::new(),
+        };
+
+        loop {
+            self.skip_terminators();
+
+            match self.peek() {
+                Token::Eof => break,
+                Token::Begin => {
+                    self.advance();
+                    program.begin.push(self.parse_block()?);
+                }
+                Token::End => {
+                    self.advance();
+                    program.end.push(self.parse_block()?);
+                }
+                Token::Function => {
+                    let (name, function) = self.parse_function()?;
+                    if program.functions.insert(name.clone(), function).is_some() {
+                        return Err(anyhow::anyhow!("function `{}' previously defined", name));
+                    }
+                }
+                Token::LeftBrace => {
+                    let action = self.parse_block()?;
+                    program.rules.push(Rule {
+                        pattern: Pattern::All,
+                        action: Some(action),
+                    });
+                }
+                _ => {
+                    let start = self.parse_expr()?;
+                    let pattern = if self.peek() == &Token::Comma {
+                        self.advance();
+                        self.skip_newlines();
+                        Pattern::Range(start, self.parse_expr()?)
+                    } else {
+                        Pattern::Expr(start)
+                    };
+
+                    let action = if self.peek() == &Token::LeftBrace {
+                        Some(self.parse_block()?)
+                    } else {
+                        None
+                    };
+                    program.rules.push(Rule { pattern, action });
+                }
+            }
+        }
+
+        for (name, line) in &self.calls {
+            if !program.functions.contains_key(name) {
+                return Err(anyhow::anyhow!("function `{}' called at source line {} is not defined", name, line));
+            }
+        }
+
+        Ok(program)
+    }
+
+    /**
+     * Parses a function definition
+     *
+     * @return Result<(String, Function)> - Name and function
+     */
+    fn parse_function(&mut self) -> Result<(String, Function)> {
+        self.advance();
+        let name = match self.advance() {
+            Token::Name(name) | Token::FuncName(name) => name,
+            _ => return Err(self.error("function name expected")),
+        };
+
+        self.expect(Token::LeftParen, "`('")?;
+        let mut params: HashMap<String, usize> = HashMap::new();
+        while self.peek() != &Token::RightParen {
+            match self.advance() {
+                Token::Name(param) => {
+                    let slot = params.len();
+                    params.insert(param, slot);
+                }
+                _ => return Err(self.error("parameter name expected")),
+            }
+            if self.peek() == &Token::Comma {
+                self.advance();
+                self.skip_newlines();
+            }
+        }
+        self.advance();
+        self.skip_newlines();
+
+        let param_count = params.len();
+        self.locals = Some(params);
+        self.array_locals.clear();
+        let body = self.parse_block();
+        self.locals = None;
+
+        Ok((name, Function {
+            param_count,
+            array_params: std::mem::take(&mut self.array_locals),
+            body: body?,
+        }))
+    }
+
+    /**
+     * Parses a `{ ... }` block
+     *
+     * @return Result<Vec<Stmt>> - Statements in the block
+     */
+    fn parse_block(&mut self) -> Result<Vec<Stmt>> {
+        self.expect(Token::LeftBrace, "`{'")?;
+        let mut statements = Vec::new();
+
+        loop {
+            self.skip_terminators();
+            match self.peek() {
+                Token::RightBrace => {
+                    self.advance();
+                    return Ok(statements);
+                }
+                Token::Eof => return Err(self.error("unexpected end of program, missing `}'")),
+                _ => statements.push(self.parse_statement()?),
+            }
+        }
+    }
+
+    /**
+     * 文を解析する関数です
+     *
+     * ブロック、if/else、while、do-while、for、for-inの
+     * 制御構造と、print、printf、next、exit、return、delete
+     * などの単純文を解析します。
+     *
+     * for-inはfor文の括弧内を式として解析し、結果が
+     * `変数 in 配列`の形で直後に`)`が続く場合に判定します。
+     * elseは改行やセミコロンを挟んでいても前のifに結び付けます。
+     *
+     * @return Result<Stmt> - 解析された文またはエラー
+     */
+    fn parse_statement(&mut self) -> Result<Stmt> {
+        match self.peek().clone() {
+            Token::LeftBrace => Ok(Stmt::Block(self.parse_block()?)),
+            Token::Semicolon => {
+                self.advance();
+                Ok(Stmt::Block(Vec::new()))
+            }
+            Token::If => {
+                self.advance();
+                self.expect(Token::LeftParen, "`('")?;
+                let condition = self.parse_expr()?;
+                self.expect(Token::RightParen, "`)'")?;
+                self.skip_newlines();
+                let then_branch = self.parse_statement()?;
+
+                let saved = self.pos;
+                while matches!(self.peek(), Token::Newline | Token::Semicolon) {
+                    self.advance();
+                }
+                let else_branch = if self.peek() == &Token::Else {
+                    self.advance();
+                    self.skip_newlines();
+                    Some(Box::new(self.parse_statement()?))
+                } else {
+                    self.pos = saved;
+                    None
+                };
+
+                Ok(Stmt::If(condition, Box::new(then_branch), else_branch))
+            }
+            Token::While => {
+                self.advance();
+                self.expect(Token::LeftParen, "`('")?;
+                let condition = self.parse_expr()?;
+                self.expect(Token::RightParen, "`)'")?;
+                let body = self.parse_loop_body()?;
+                Ok(Stmt::While(condition, Box::new(body)))
+            }
+            Token::Do => {
+                self.advance();
+                self.skip_newlines();
+                let body = self.parse_statement()?;
+                self.skip_terminators();
+                self.expect(Token::While, "`while'")?;
+                self.expect(Token::LeftParen, "`('")?;
+                let condition = self.parse_expr()?;
+                self.expect(Token::RightParen, "`)'")?;
+                self.end_simple_statement()?;
+                Ok(Stmt::DoWhile(Box::new(body), condition))
+            }
+            Token::For => {
+                self.advance();
+                self.expect(Token::LeftParen, "`('")?;
+
+                let init = if self.peek() == &Token::Semicolon {
+                    None
+                } else {
+                    Some(self.parse_expr()?)
+                };
+
+                if let Some(Expr::In(keys, array)) = &init {
+                    if let [Expr::Variable(variable)] = keys.as_slice() {
+                        if self.peek() == &Token::RightParen {
+                            self.advance();
+                            let body = self.parse_loop_body()?;
+                            return Ok(Stmt::ForIn(variable.clone(), array.clone(), Box::new(body)));
+                        }
+                    }
+                }
+
+                self.expect(Token::Semicolon, "`;'")?;
+                self.skip_newlines();
+                let condition = if self.peek() == &Token::Semicolon {
+                    None
+                } else {
+                    Some(self.parse_expr()?)
+                };
+                self.expect(Token::Semicolon, "`;'")?;
+                self.skip_newlines();
+                let step = if self.peek() == &Token::RightParen {
+                    None
+                } else {
+                    Some(self.parse_expr()?)
+                };
+                self.expect(Token::RightParen, "`)'")?;
+                let body = self.parse_loop_body()?;
+
+                Ok(Stmt::For(init, condition, step, Box::new(body)))
+            }
+            _ => {
+                let statement = self.parse_simple_statement()?;
+                self.end_simple_statement()?;
+                Ok(statement)
+            }
+        }
+    }
+
+    /**
+     * Parses the body of a while or for loop
+     *
+     * @return Result<Stmt> - Loop body (empty for a lone `;`)
+     */
+    fn parse_loop_body(&mut self) -> Result<Stmt> {
+        if self.peek() == &Token::Semicolon {
+            self.advance();
+            return Ok(Stmt::Block(Vec::new()));
+        }
+        self.skip_newlines();
+        self.parse_statement()
+    }
+
+    /**
+     * Parses a statement that ends with a terminator
+     *
+     * @return Result<Stmt> - Parsed statement
+     */
+    fn parse_simple_statement(&mut self) -> Result<Stmt> {
+        match self.peek().clone() {
+            Token::Print | Token::Printf => {
+                let is_printf = self.advance() == Token::Printf;
+
+                let saved = self.no_greater;
+                self.no_greater = true;
+                let mut args = if self.at_statement_end() || matches!(self.peek(), Token::Greater | Token::Append | Token::Pipe) {
+                    Vec::new()
+                } else {
+                    self.parse_expr_list()?
+                };
+                self.no_greater = saved;
+
+                if let [Expr::Grouping(list)] = args.as_slice() {
+                    args = list.clone();
+                }
+
+                let redirect = match self.peek() {
+                    Token::Greater => {
+                        self.advance();
+                        Some(Redirect::File(self.parse_redirect_target()?))
+                    }
+                    Token::Append => {
+                        self.advance();
+                        Some(Redirect::Append(self.parse_redirect_target()?))
+                    }
+                    Token::Pipe => {
+                        self.advance();
+                        Some(Redirect::Pipe(self.parse_redirect_target()?))
+                    }
+                    _ => None,
+                };
+
+                if is_printf {
+                    if args.is_empty() {
+                        return Err(self.error("printf: no format"));
+                    }
+                    Ok(Stmt::Printf(args, redirect))
+                } else {
+                    Ok(Stmt::Print(args, redirect))
+                }
+            }
+            Token::Next => {
+                self.advance();
+                Ok(Stmt::Next)
+            }
+            Token::NextFile => {
+                self.advance();
+                Ok(Stmt::NextFile)
+            }
+            Token::Break => {
+                self.advance();
+                Ok(Stmt::Break)
+            }
+            Token::Continue => {
+                self.advance();
+                Ok(Stmt::Continue)
+            }
+            Token::Exit => {
+                self.advance();
+                let code = if self.at_statement_end() { None } else { Some(self.parse_expr()?) };
+                Ok(Stmt::Exit(code))
+            }
+            Token::Return => {
+                self.advance();
+                if self.locals.is_none() {
+                    return Err(self.error("`return' used outside function context"));
+                }
+                let value = if self.at_statement_end() { None } else { Some(self.parse_expr()?) };
+                Ok(Stmt::Return(value))
+            }
+            Token::Delete => {
+                self.advance();
+                let name = match self.advance() {
+                    Token::Name(name) => name,
+                    _ => return Err(self.error("array name expected after `delete'")),
+                };
+                let array = self.var_ref(&name);
+                self.mark_array(&array);
+
+                let subscripts = if self.peek() == &Token::LeftBracket {
+                    self.advance();
+                    let subscripts = self.parse_expr_list()?;
+                    self.expect(Token::RightBracket, "`]'")?;
+                    Some(subscripts)
+                } else {
+                    None
+                };
+                Ok(Stmt::Delete(array, subscripts))
+            }
+            _ => Ok(Stmt::Expression(self.parse_expr()?)),
+        }
+    }
+
+    /**
+     * Parses the target of an output redirection
+     *
+     * @return Result<Expr> - File name or command expression
+     */
+    fn parse_redirect_target(&mut self) -> Result<Expr> {
+        let saved = self.no_greater;
+        self.no_greater = true;
+        let target = self.parse_concat();
+        self.no_greater = saved;
+        target
+    }
+
+    /**
+     * Consumes the terminator after a simple statement
+     *
+     * @return Result<()> - Success, or an error if junk follows
+     */
+    fn end_simple_statement(&mut self) -> Result<()> {
+        match self.peek() {
+            Token::Semicolon | Token::Newline => {
+                self.advance();
+                Ok(())
+            }
+            Token::RightBrace | Token::Eof => Ok(()),
+            other => Err(self.error(&format!("syntax error near {:?}", other))),
+        }
+    }
+
+    /**
+     * Checks whether the current token ends a simple statement
+     *
+     * @return bool - True at `;`, newline, `}` or end of input
+     */
+    fn at_statement_end(&self) -> bool {
+        matches!(self.peek(), Token::Semicolon | Token::Newline | Token::RightBrace | Token::Eof)
+    }
+
+    /**
+     * Parses a comma-separated expression list
+     *
+     * @return Result<Vec<Expr>> - Expressions
+     */
+    fn parse_expr_list(&mut self) -> Result<Vec<Expr>> {
+        let mut list = vec![self.parse_expr()?];
+        while self.peek() == &Token::Comma {
+            self.advance();
+            self.skip_newlines();
+            list.push(self.parse_expr()?);
+        }
+        Ok(list)
+    }
+
+    /**
+     * Parses an expression, including assignment
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_expr(&mut self) -> Result<Expr> {
+        let target = self.parse_ternary()?;
+
+        let op = match self.peek() {
+            Token::Assign => None,
+            Token::AddAssign => Some(BinaryOp::Add),
+            Token::SubAssign => Some(BinaryOp::Subtract),
+            Token::MulAssign => Some(BinaryOp::Multiply),
+            Token::DivAssign => Some(BinaryOp::Divide),
+            Token::ModAssign => Some(BinaryOp::Modulo),
+            Token::PowAssign => Some(BinaryOp::Power),
+            _ => return Ok(target),
+        };
+
+        if !target.is_lvalue() {
+            return Err(self.error("assignment to non-lvalue"));
+        }
+
+        self.advance();
+        self.skip_newlines();
+        let value = self.parse_expr()?;
+        Ok(Expr::Assign(op, Box::new(target), Box::new(value)))
+    }
+
+    /**
+     * Parses `cond ? a : b`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_ternary(&mut self) -> Result<Expr> {
+        let condition = self.parse_or()?;
+        if self.peek() != &Token::Question {
+            return Ok(condition);
+        }
+
+        self.advance();
+        self.skip_newlines();
+        let then_value = self.parse_ternary()?;
+        self.skip_newlines();
+        self.expect(Token::Colon, "`:'")?;
+        self.skip_newlines();
+        let else_value = self.parse_ternary()?;
+
+        Ok(Expr::Conditional(Box::new(condition), Box::new(then_value), Box::new(else_value)))
+    }
+
+    /**
+     * Parses `||`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while self.peek() == &Token::Or {
+            self.advance();
+            self.skip_newlines();
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    /**
+     * Parses `&&`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_in()?;
+        while self.peek() == &Token::And {
+            self.advance();
+            self.skip_newlines();
+            left = Expr::And(Box::new(left), Box::new(self.parse_in()?));
+        }
+        Ok(left)
+    }
+
+    /**
+     * Parses `key in array`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_in(&mut self) -> Result<Expr> {
+        let mut left = self.parse_match()?;
+        while self.peek() == &Token::In {
+            self.advance();
+            let array = self.parse_array_name()?;
+            let keys = match left {
+                Expr::Grouping(keys) => keys,
+                other => vec![other],
+            };
+            left = Expr::In(keys, array);
+        }
+        Ok(left)
+    }
+
+    /**
+     * Parses `~` and `!~`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_match(&mut self) -> Result<Expr> {
+        let mut left = self.parse_comparison()?;
+        loop {
+            let negate = match self.peek() {
+                Token::Tilde => false,
+                Token::NotTilde => true,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_comparison()?;
+            left = Expr::Match(negate, Box::new(left), Box::new(right));
+        }
+    }
+
+    /**
+     * Parses relational operators
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_comparison(&mut self) -> Result<Expr> {
+        let left = self.parse_concat()?;
+        let op = match self.peek() {
+            Token::Less => CompareOp::Less,
+            Token::LessEqual => CompareOp::LessEqual,
+            Token::Equal => CompareOp::Equal,
+            Token::NotEqual => CompareOp::NotEqual,
+            Token::GreaterEqual => CompareOp::GreaterEqual,
+            Token::Greater if !self.no_greater => CompareOp::Greater,
+            _ => return Ok(left),
+        };
+        self.advance();
+        let right = self.parse_concat()?;
+        Ok(Expr::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    /**
+     * Parses string concatenation and `command | getline`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_concat(&mut self) -> Result<Expr> {
+        let mut left = self.parse_additive()?;
+
+        loop {
+            if self.peek() == &Token::Pipe && self.peek_at(1) == &Token::Getline {
+                self.advance();
+                self.advance();
+                let target = self.parse_optional_lvalue()?;
+                left = Expr::Getline {
+                    source: GetlineSource::Command(Box::new(left)),
+                    target,
+                };
+                continue;
+            }
+
+            let starts_operand = matches!(
+                self.peek(),
+                Token::Number(_) | Token::Str(_) | Token::Name(_) | Token::FuncName(_) | Token::Builtin(_)
+                    | Token::Dollar | Token::LeftParen | Token::Increment | Token::Decrement | Token::Minus
+                    | Token::Plus | Token::Not
+            );
+            // A leading +/- or ! continues the expression arithmetically, not by concatenation
+            if !starts_operand || matches!(self.peek(), Token::Minus | Token::Plus | Token::Not) {
+                return Ok(left);
+            }
+
+            let right = self.parse_additive()?;
+            left = Expr::Concat(Box::new(left), Box::new(right));
+        }
+    }
+
+    /**
+     * Parses `+` and `-`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_additive(&mut self) -> Result<Expr> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Token::Plus => BinaryOp::Add,
+                Token::Minus => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    /**
+     * Parses `*`, `/` and `%`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_multiplicative(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Star => BinaryOp::Multiply,
+                Token::Slash => BinaryOp::Divide,
+                Token::Percent => BinaryOp::Modulo,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    /**
+     * Parses unary `!`, `-` and `+`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Token::Not => {
+                self.advance();
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Token::Minus => {
+                self.advance();
+                Ok(Expr::Negate(Box::new(self.parse_unary()?)))
+            }
+            Token::Plus => {
+                self.advance();
+                Ok(Expr::ToNumber(Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    /**
+     * Parses right-associative `^`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_power(&mut self) -> Result<Expr> {
+        let base = self.parse_postfix()?;
+        if self.peek() != &Token::Caret {
+            return Ok(base);
+        }
+        self.advance();
+        let exponent = match self.peek() {
+            Token::Minus => {
+                self.advance();
+                Expr::Negate(Box::new(self.parse_power()?))
+            }
+            Token::Plus => {
+                self.advance();
+                self.parse_power()?
+            }
+            _ => self.parse_power()?,
+        };
+        Ok(Expr::Binary(BinaryOp::Power, Box::new(base), Box::new(exponent)))
+    }
+
+    /**
+     * Parses postfix `++` and `--`
+     *
+     * @return Result<Expr> - Parsed expression
+     */
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let expr = self.parse_primary()?;
+        if expr.is_lvalue() {
+            let delta = match self.peek() {
+                Token::Increment => 1.0,
+                Token::Decrement => -1.0,
+                _ => return Ok(expr),
+            };
+            self.advance();
+            return Ok(Expr::IncDec {
+                target: Box::new(expr),
+                delta,
+                prefix: false,
+            });
+        }
+        Ok(expr)
+    }
+
+    /**
+     * 基本式を解析する関数です
+     *
+     * 数値、文字列、正規表現リテラル、括弧（式リストを含む）、
+     * フィールド参照（$）、前置インクリメント、変数、配列要素、
+     * ユーザー関数と組み込み関数の呼び出し、getlineを解析します。
+     *
+     * 括弧の中では`>`を比較演算子として扱うため、print文の
+     * リダイレクト判定を一時的に解除します。
+     *
+     * @return Result<Expr> - 解析された式またはエラー
+     */
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.advance() {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Str(s) => Ok(Expr::String(s)),
+            Token::Regex(source) => Ok(Expr::Regex(compile_awk_regex(&source)?)),
+            Token::LeftParen => {
+                let saved = self.no_greater;
+                self.no_greater = false;
+                let list = self.parse_expr_list();
+                self.no_greater = saved;
+                let mut list = list?;
+                self.expect(Token::RightParen, "`)'")?;
+
+                if list.len() == 1 {
+                    Ok(list.remove(0))
+                } else {
+                    Ok(Expr::Grouping(list))
+                }
+            }
+            Token::Dollar => {
+                let index = match self.peek() {
+                    Token::Increment | Token::Decrement | Token::Minus => self.parse_unary_operand()?,
+                    _ => self.parse_primary()?,
+                };
+                Ok(Expr::Field(Box::new(index)))
+            }
+            Token::Increment | Token::Decrement => {
+                let delta = if self.tokens[self.pos - 1].0 == Token::Increment { 1.0 } else { -1.0 };
+                let target = self.parse_primary()?;
+                if !target.is_lvalue() {
+                    return Err(self.error("++ or -- applied to non-lvalue"));
+                }
+                Ok(Expr::IncDec {
+                    target: Box::new(target),
+                    delta,
+                    prefix: true,
+                })
+            }
+            Token::Name(name) => {
+                let variable = self.var_ref(&name);
+                if self.peek() == &Token::LeftBracket {
+                    self.advance();
+                    self.mark_array(&variable);
+                    let subscripts = self.parse_expr_list()?;
+                    self.expect(Token::RightBracket, "`]'")?;
+                    return Ok(Expr::Index(variable, subscripts));
+                }
+                Ok(Expr::Variable(variable))
+            }
+            Token::FuncName(name) => {
+                let line = self.line();
+                self.expect(Token::LeftParen, "`('")?;
+                let args = self.parse_call_args()?;
+                self.calls.push((name.clone(), line));
+                Ok(Expr::Call(name, args))
+            }
+            Token::Builtin(builtin) => {
+                let args = if self.peek() == &Token::LeftParen {
+                    self.advance();
+                    self.parse_call_args()?
+                } else if builtin == BuiltinFunc::Length {
+                    Vec::new()
+                } else {
+                    return Err(self.error("`(' expected after builtin function name"));
+                };
+
+                if builtin == BuiltinFunc::Split {
+                    match args.get(1) {
+                        Some(Expr::Variable(array)) => self.mark_array(&array.clone()),
+                        _ => return Err(self.error("split: second argument must be an array name")),
+                    }
+                }
+                Ok(Expr::Builtin(builtin, args))
+            }
+            Token::Getline => {
+                let target = self.parse_optional_lvalue()?;
+                let source = if self.peek() == &Token::Less {
+                    self.advance();
+                    GetlineSource::File(Box::new(self.parse_primary()?))
+                } else {
+                    GetlineSource::Main
+                };
+                Ok(Expr::Getline { source, target })
+            }
+            other => Err(self.error(&format!("syntax error near {:?}", other))),
+        }
+    }
+
+    /**
+     * Parses a field index that starts with a unary operator
+     *
+     * @return Result<Expr> - Index expression
+     */
+    fn parse_unary_operand(&mut self) -> Result<Expr> {
+        if self.peek() == &Token::Minus {
+            self.advance();
+            return Ok(Expr::Negate(Box::new(self.parse_primary()?)));
+        }
+        self.parse_primary()
+    }
+
+    /**
+     * Parses the optional variable after `getline`
+     *
+     * @return Result<Option<Box<Expr>>> - Target, or None to read into $0
+     */
+    fn parse_optional_lvalue(&mut self) -> Result<Option<Box<Expr>>> {
+        match self.peek() {
+            Token::Name(_) | Token::Dollar => Ok(Some(Box::new(self.parse_primary()?))),
+            _ => Ok(None),
+        }
+    }
+
+    /**
+     * Parses arguments up to the closing parenthesis
+     *
+     * @return Result<Vec<Expr>> - Arguments
+     */
+    fn parse_call_args(&mut self) -> Result<Vec<Expr>> {
+        let saved = self.no_greater;
+        self.no_greater = false;
+
+        let mut args = Vec::new();
+        self.skip_newlines();
+        if self.peek() != &Token::RightParen {
+            loop {
+                let arg = self.parse_expr();
+                let arg = match arg {
+                    Ok(arg) => arg,
+                    Err(e) => {
+                        self.no_greater = saved;
+                        return Err(e);
+                    }
+                };
+                args.push(arg);
+                self.skip_newlines();
+                if self.peek() != &Token::Comma {
+                    break;
+                }
+                self.advance();
+                self.skip_newlines();
+            }
+        }
+
+        self.no_greater = saved;
+        self.expect(Token::RightParen, "`)'")?;
+        Ok(args)
+    }
+
+    /**
+     * Parses the array name after `in`
+     *
+     * @return Result<VarRef> - Array reference
+     */
+    fn parse_array_name(&mut self) -> Result<VarRef> {
+        match self.advance() {
+            Token::Name(name) => {
+                let array = self.var_ref(&name);
+                self.mark_array(&array);
+                Ok(array)
+            }
+            _ => Err(self.error("array name expected after `in'")),
+        }
+    }
+
+    /**
+     * Resolves a name to a local slot or global
+     *
+     * @param name - Variable name
+     * @return VarRef - Resolved reference
+     */
+    fn var_ref(&self, name: &str) -> VarRef {
+        match self.locals.as_ref().and_then(|locals| locals.get(name)) {
+            Some(&slot) => VarRef::Local(slot),
+            None => VarRef::Global(name.to_string()),
+        }
+    }
+
+    /**
+     * Records that a function parameter is used as an array
+     *
+     * @param variable - Variable used as an array
+     */
+    fn mark_array(&mut self, variable: &VarRef) {
+        if let VarRef::Local(slot) = variable {
+            self.array_locals.insert(*slot);
+        }
+    }
+
+    /**
+     * Consumes a specific token
+     *
+     * @param token - Expected token
+     * @param what - Description for the error message
+     * @return Result<()> - Success or syntax error
+     */
+    fn expect(&mut self, token: Token, what: &str) -> Result<()> {
+        if self.peek() == &token {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(&format!("{} expected", what)))
+        }
+    }
+
+    /**
+     * Skips newlines
+     */
+    fn skip_newlines(&mut self) {
+        while self.peek() == &Token::Newline {
+            self.advance();
+        }
+    }
+
+    /**
+     * Skips newlines and semicolons
+     */
+    fn skip_terminators(&mut self) {
+        while matches!(self.peek(), Token::Newline | Token::Semicolon) {
+            self.advance();
+        }
+    }
+
+    /**
+     * Peeks at the current token
+     *
+     * @return &Token - Current token
+     */
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    /**
+     * Peeks at a token ahead of the current one
+     *
+     * @param offset - Distance from the current token
+     * @return &Token - Token, or Eof past the end
+     */
+    fn peek_at(&self, offset: usize) -> &Token {
+        self.tokens.get(self.pos + offset).map_or(&Token::Eof, |(token, _)| token)
+    }
+
+    /**
+     * Consumes the current token
+     *
+     * @return Token - Consumed token
+     */
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    /**
+     * Gets the line of the current token
+     *
+     * @return usize - Source line
+     */
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    /**
+     * Builds a syntax error at the current line
+     *
+     * @param message - Error message
+     * @return anyhow::Error - Error with line information
+     */
+    fn error(&self, message: &str) -> anyhow::Error {
+        anyhow::anyhow!("syntax error at source line {}: {}", self.line(), message)
+    }
+}
+
+/**
+ * Runtime value
+ */
+#[derive(Debug, Clone)]
+enum Value {
+    /// Never-assigned variable; both "" and 0
+    Uninit,
+    Number(f64),
+    String(String),
+    /// String from input that compares as a number if it looks like one
+    StrNum(String),
+}
+
+/**
+ * Variable slot
+ */
+#[derive(Debug, Clone)]
+enum Variable {
+    Uninit,
+    Scalar(Value),
+    /// Index into the array arena
+    Array(usize),
+}
+
+/**
+ * Associative array that keeps insertion order
+ */
+#[derive(Debug, Default)]
+struct AwkArray {
+    /// Values by key
+    entries: HashMap<String, Value>,
+    /// Keys in insertion order
+    order: Vec<String>,
+}
+
+impl AwkArray {
+    /**
+     * Gets an element, creating it if missing
+     *
+     * @param key - Subscript
+     * @return Value - Element value
+     */
+    fn get(&mut self, key: &str) -> Value {
+        if let Some(value) = self.entries.get(key) {
+            return value.clone();
+        }
+        self.set(key.to_string(), Value::Uninit);
+        Value::Uninit
+    }
+
+    /**
+     * Sets an element
+     *
+     * @param key - Subscript
+     * @param value - New value
+     */
+    fn set(&mut self, key: String, value: Value) {
+        if self.entries.insert(key.clone(), value).is_none() {
+            self.order.push(key);
+        }
+    }
+
+    /**
+     * Removes an element
+     *
+     * @param key - Subscript
+     */
+    fn remove(&mut self, key: &str) {
+        if self.entries.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+        }
+    }
+
+    /**
+     * Removes every element
+     */
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/**
+ * Control flow after a statement
+ */
+#[derive(Debug, Clone)]
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Next,
+    NextFile,
+    Exit(i32),
+    Return(Value),
+}
+
+/**
+ * Carries `next`, `nextfile` or `exit` out of a function call
+ */
+#[derive(Debug)]
+struct Unwind(Flow);
+
+impl std::fmt::Display for Unwind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`next' or `exit' used in a pattern")
+    }
+}
+
+impl std::error::Error for Unwind {}
+
+/**
+ * State of the main input (ARGV files or stdin)
+ */
+#[derive(Debug, Default)]
+struct MainInput {
+    /// Next ARGV index to examine
+    next_arg: usize,
+    /// Records of the current file
+    records: Vec<String>,
+    /// Next record index
+    position: usize,
+    /// Whether any file operand has been opened
+    opened_file: bool,
+    /// Whether all input is consumed
+    finished: bool,
+}
+
+/**
+ * Records read by `getline < file` or `command | getline`
+ */
+#[derive(Debug)]
+struct InputSource {
+    /// All records
+    records: Vec<String>,
+    /// Next record index
+    position: usize,
+}
+
+/**
+ * Destination of redirected output
+ */
+#[derive(Debug)]
+enum OutputTarget {
+    /// Open file
+    File(std::fs::File),
+    /// Text buffered for a command, run on close
+    Pipe(String),
+}
+
+/**
+ * Tree-walking interpreter
+ */
+struct Interpreter<'a> {
+    /// Program being run
+    program: &'a AwkProgram,
+    /// Global variables
+    globals: HashMap<String, Variable>,
+    /// Array arena
+    arrays: Vec<AwkArray>,
+    /// Local slots of active function calls
+    frames: Vec<Vec<Variable>>,
+    /// Current record ($0)
+    record: String,
+    /// Current fields ($1..$NF)
+    fields: Vec<String>,
+    /// Main input state
+    main_input: MainInput,
+    /// Open getline sources by name
+    inputs: HashMap<String, InputSource>,
+    /// Open output redirections in open order
+    outputs: Vec<(String, OutputTarget)>,
+    /// Standard output text
+    output: String,
+    /// Compiled dynamic regexes
+    regex_cache: HashMap<String, Regex>,
+    /// Whether each range rule is inside its range
+    range_active: Vec<bool>,
+    /// Directory for relative paths and commands
+    working_dir: PathBuf,
+    /// Piped input, consumed on first read
+    stdin: Option<String>,
+    /// Seed given to srand
+    random_seed: f64,
+    /// Random generator state
+    random_state: u64,
+    /// Exit status
+    exit_code: i32,
+}
+
+impl<'a> Interpreter<'a> {
+    /**
+     * Sets up builtin variables and command-line assignments
+     *
+     * @param options - Command-line options
+     * @param operands - ARGV operands
+     * @return Result<()> - Success or error
+     */
+    fn initialize(&mut self, options: &AwkOptions, operands: &[String]) -> Result<()> {
+        let defaults = [
+            ("FS", " "),
+            ("OFS", " "),
+            ("ORS", "\n"),
+            ("RS", "\n"),
+            ("SUBSEP", "\x1c"),
+            ("CONVFMT", "%.6g"),
+            ("OFMT", "%.6g"),
+            ("FILENAME", ""),
+        ];
+        for (name, value) in defaults {
+            self.globals.insert(name.to_string(), Variable::Scalar(Value::String(value.to_string())));
+        }
+        for name in ["NR", "FNR", "RSTART"] {
+            self.globals.insert(name.to_string(), Variable::Scalar(Value::Number(0.0)));
+        }
+        self.globals.insert("RLENGTH".to_string(), Variable::Scalar(Value::Number(-1.0)));
+
+        let environ = self.new_array("ENVIRON");
+        for (key, value) in std::env::vars() {
+            self.arrays[environ].set(key, Value::StrNum(value));
+        }
+
+        let argv = self.new_array("ARGV");
+        self.arrays[argv].set("0".to_string(), Value::String("awk".to_string()));
+        for (index, operand) in operands.iter().enumerate() {
+            self.arrays[argv].set((index + 1).to_string(), Value::StrNum(operand.clone()));
+        }
+        self.globals.insert("ARGC".to_string(), Variable::Scalar(Value::Number((operands.len() + 1) as f64)));
+        self.main_input.next_arg = 1;
+
+        if let Some(separator) = &options.field_separator {
+            let separator = if separator == "t" { "\t".to_string() } else { unescape_awk_string(separator) };
+            self.set_global("FS", Value::String(separator))?;
+        }
+        for (name, value) in &options.assignments {
+            self.set_global(name, Value::StrNum(unescape_awk_string(value)))?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Creates a named global array
+     *
+     * @param name - Array name
+     * @return usize - Arena index
+     */
+    fn new_array(&mut self, name: &str) -> usize {
+        let id = self.arrays.len();
+        self.arrays.push(AwkArray::default());
+        self.globals.insert(name.to_string(), Variable::Array(id));
+        id
+    }
+
+    /**
+     * BEGIN、メインループ、ENDを実行する関数です
+     *
+     * BEGINでexitが実行された場合は入力を読まずにENDへ進み、
+     * メインループでexitが実行された場合も残りの入力を
+     * スキップしてENDを実行します。ENDの中のexitは
+     * 即座に終了します。
+     *
+     * 範囲パターンはルールごとの状態を持ち、開始パターンに
+     * 一致した行から終了パターンに一致した行まで選択します。
+     * アクションのないルールはレコードをそのまま出力します。
+     *
+     * @return Result<()> - 成功またはエラー
+     */
+    fn execute(&mut self) -> Result<()> {
+        let program = self.program;
+        let mut exiting = false;
+
+        for block in &program.begin {
+            if let Flow::Exit(code) = self.run_action(block)? {
+                self.exit_code = code;
+                exiting = true;
+                break;
+            }
+        }
+
+        if !exiting && (!program.rules.is_empty() || !program.end.is_empty()) {
+            'records: while let Some(record) = self.next_main_record()? {
+                self.bump_counter("NR");
+                self.bump_counter("FNR");
+                self.set_record(record)?;
+
+                for (index, rule) in program.rules.iter().enumerate() {
+                    if !self.rule_matches(index, &rule.pattern)? {
+                        continue;
+                    }
+
+                    let flow = match &rule.action {
+                        Some(action) => self.run_action(action)?,
+                        None => {
+                            let text = format!("{}{}", self.record, self.special_string("ORS"));
+                            self.output.push_str(&text);
+                            Flow::Normal
+                        }
+                    };
+
+                    match flow {
+                        Flow::Next => continue 'records,
+                        Flow::NextFile => {
+                            self.main_input.position = self.main_input.records.len();
+                            continue 'records;
+                        }
+                        Flow::Exit(code) => {
+                            self.exit_code = code;
+                            break 'records;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        for block in &program.end {
+            if let Flow::Exit(code) = self.run_action(block)? {
+                self.exit_code = code;
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Checks a rule pattern against the current record
+     *
+     * @param index - Rule index, for range state
+     * @param pattern - Rule pattern
+     * @return Result<bool> - True if the action should run
+     */
+    fn rule_matches(&mut self, index: usize, pattern: &Pattern) -> Result<bool> {
+        match pattern {
+            Pattern::All => Ok(true),
+            Pattern::Expr(expr) => {
+                let value = self.eval(expr)?;
+                Ok(self.is_true(&value))
+            }
+            Pattern::Range(start, end) => {
+                if self.range_active[index] {
+                    let value = self.eval(end)?;
+                    if self.is_true(&value) {
+                        self.range_active[index] = false;
+                    }
+                    return Ok(true);
+                }
+
+                let value = self.eval(start)?;
+                if !self.is_true(&value) {
+                    return Ok(false);
+                }
+                let value = self.eval(end)?;
+                if !self.is_true(&value) {
+                    self.range_active[index] = true;
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /**
+     * Runs an action, catching flow that escaped a function call
+     *
+     * @param statements - Action statements
+     * @return Result<Flow> - How the action ended
+     */
+    fn run_action(&mut self, statements: &[Stmt]) -> Result<Flow> {
+        match self.exec_block(statements) {
+            Ok(flow) => Ok(flow),
+            Err(e) => match e.downcast::<Unwind>() {
+                Ok(Unwind(flow)) => Ok(flow),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    /**
+     * Runs statements in order
+     *
+     * @param statements - Statements to run
+     * @return Result<Flow> - First non-normal flow, or Normal
+     */
+    fn exec_block(&mut self, statements: &[Stmt]) -> Result<Flow> {
+        for statement in statements {
+            let flow = self.exec(statement)?;
+            if !matches!(flow, Flow::Normal) {
+                return Ok(flow);
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /**
+     * Runs a loop body and decides whether the loop continues
+     *
+     * @param body - Loop body
+     * @return Result<Option<Flow>> - Some(flow) to leave the loop with, None to keep looping
+     */
+    fn exec_loop_body(&mut self, body: &Stmt) -> Result<Option<Flow>> {
+        match self.exec(body)? {
+            Flow::Break => Ok(Some(Flow::Normal)),
+            Flow::Normal | Flow::Continue => Ok(None),
+            other => Ok(Some(other)),
+        }
+    }
+
+    /**
+     * Runs one statement
+     *
+     * @param statement - Statement to run
+     * @return Result<Flow> - How the statement ended
+     */
+    fn exec(&mut self, statement: &Stmt) -> Result<Flow> {
+        match statement {
+            Stmt::Print(args, redirect) => {
+                let mut text = if args.is_empty() {
+                    self.record.clone()
+                } else {
+                    let separator = self.special_string("OFS");
+                    let mut parts = Vec::with_capacity(args.len());
+                    for arg in args {
+                        let value = self.eval(arg)?;
+                        parts.push(self.to_output_string(&value));
+                    }
+                    parts.join(&separator)
+                };
+                text.push_str(&self.special_string("ORS"));
+                self.write_output(redirect, &text)?;
+            }
+            Stmt::Printf(args, redirect) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval(arg)?);
+                }
+                let format = self.to_string(&values[0]);
+                let text = self.format(&format, &values[1..]);
+                self.write_output(redirect, &text)?;
+            }
+            Stmt::Expression(expr) => {
+                self.eval(expr)?;
+            }
+            Stmt::If(condition, then_branch, else_branch) => {
+                let value = self.eval(condition)?;
+                if self.is_true(&value) {
+                    return self.exec(then_branch);
+                } else if let Some(else_branch) = else_branch {
+                    return self.exec(else_branch);
+                }
+            }
+            Stmt::While(condition, body) => loop {
+                let value = self.eval(condition)?;
+                if !self.is_true(&value) {
+                    break;
+                }
+                if let Some(flow) = self.exec_loop_body(body)? {
+                    return Ok(flow);
+                }
+            },
+            Stmt::DoWhile(body, condition) => loop {
+                if let Some(flow) = self.exec_loop_body(body)? {
+                    return Ok(flow);
+                }
+                let value = self.eval(condition)?;
+                if !self.is_true(&value) {
+                    break;
+                }
+            },
+            Stmt::For(init, condition, step, body) => {
+                if let Some(init) = init {
+                    self.eval(init)?;
+                }
+                loop {
+                    if let Some(condition) = condition {
+                        let value = self.eval(condition)?;
+                        if !self.is_true(&value) {
+                            break;
+                        }
+                    }
+                    if let Some(flow) = self.exec_loop_body(body)? {
+                        return Ok(flow);
+                    }
+                    if let Some(step) = step {
+                        self.eval(step)?;
+                    }
+                }
+            }
+            Stmt::ForIn(variable, array, body) => {
+                let id = self.array_id(array)?;
+                let keys = self.arrays[id].order.clone();
+                for key in keys {
+                    // Elements deleted by the body are skipped
+                    if !self.arrays[id].entries.contains_key(&key) {
+                        continue;
+                    }
+                    self.set_var(variable, Value::StrNum(key))?;
+                    if let Some(flow) = self.exec_loop_body(body)? {
+                        return Ok(flow);
+                    }
+                }
+            }
+            Stmt::Block(statements) => return self.exec_block(statements),
+            Stmt::Next => return Ok(Flow::Next),
+            Stmt::NextFile => return Ok(Flow::NextFile),
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Continue => return Ok(Flow::Continue),
+            Stmt::Exit(code) => {
+                let code = match code {
+                    Some(expr) => {
+                        let value = self.eval(expr)?;
+                        self.to_number(&value) as i32
+                    }
+                    None => self.exit_code,
+                };
+                return Ok(Flow::Exit(code));
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(expr) => self.eval(expr)?,
+                    None => Value::Uninit,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Delete(array, subscripts) => {
+                let id = self.array_id(array)?;
+                match subscripts {
+                    Some(subscripts) => {
+                        let key = self.subscript(subscripts)?;
+                        self.arrays[id].remove(&key);
+                    }
+                    None => self.arrays[id].clear(),
+                }
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    /**
+     * Evaluates an expression
+     *
+     * @param expr - Expression
+     * @return Result<Value> - Resulting value
+     */
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        match expr {
+            Expr::Number(n) => Ok(Value::Number(*n)),
+            Expr::String(s) => Ok(Value::String(s.clone())),
+            Expr::Regex(regex) => Ok(bool_value(regex.is_match(&self.record))),
+            Expr::Variable(variable) => self.get_var(variable),
+            Expr::Index(array, subscripts) => {
+                let key = self.subscript(subscripts)?;
+                let id = self.array_id(array)?;
+                Ok(self.arrays[id].get(&key))
+            }
+            Expr::Field(index) => {
+                let index = self.field_index(index)?;
+                Ok(self.get_field(index))
+            }
+            Expr::Grouping(_) => Err(anyhow::anyhow!("expression list used outside print or `in'")),
+            Expr::Assign(op, target, value) => {
+                let value = self.eval(value)?;
+                let value = match op {
+                    None => value,
+                    Some(op) => {
+                        let current = self.eval(target)?;
+                        let result = self.arithmetic(*op, self.to_number(&current), self.to_number(&value))?;
+                        Value::Number(result)
+                    }
+                };
+                self.assign(target, value.clone())?;
+                Ok(value)
+            }
+            Expr::Conditional(condition, then_value, else_value) => {
+                let value = self.eval(condition)?;
+                if self.is_true(&value) {
+                    self.eval(then_value)
+                } else {
+                    self.eval(else_value)
+                }
+            }
+            Expr::And(left, right) => {
+                let value = self.eval(left)?;
+                if !self.is_true(&value) {
+                    return Ok(bool_value(false));
+                }
+                let value = self.eval(right)?;
+                Ok(bool_value(self.is_true(&value)))
+            }
+            Expr::Or(left, right) => {
+                let value = self.eval(left)?;
+                if self.is_true(&value) {
+                    return Ok(bool_value(true));
+                }
+                let value = self.eval(right)?;
+                Ok(bool_value(self.is_true(&value)))
+            }
+            Expr::Not(operand) => {
+                let value = self.eval(operand)?;
+                Ok(bool_value(!self.is_true(&value)))
+            }
+            Expr::Negate(operand) => {
+                let value = self.eval(operand)?;
+                Ok(Value::Number(-self.to_number(&value)))
+            }
+            Expr::ToNumber(operand) => {
+                let value = self.eval(operand)?;
+                Ok(Value::Number(self.to_number(&value)))
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                Ok(Value::Number(self.arithmetic(*op, self.to_number(&left), self.to_number(&right))?))
+            }
+            Expr::Concat(left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                Ok(Value::String(format!("{}{}", self.to_string(&left), self.to_string(&right))))
+            }
+            Expr::Compare(op, left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                let ordering = self.compare(&left, &right);
+                let result = match ordering {
+                    None => matches!(op, CompareOp::NotEqual),
+                    Some(ordering) => match op {
+                        CompareOp::Less => ordering.is_lt(),
+                        CompareOp::LessEqual => ordering.is_le(),
+                        CompareOp::Equal => ordering.is_eq(),
+                        CompareOp::NotEqual => ordering.is_ne(),
+                        CompareOp::Greater => ordering.is_gt(),
+                        CompareOp::GreaterEqual => ordering.is_ge(),
+                    },
+                };
+                Ok(bool_value(result))
+            }
+            Expr::Match(negate, left, right) => {
+                let value = self.eval(left)?;
+                let text = self.to_string(&value);
+                let regex = self.regex_for(right)?;
+                Ok(bool_value(regex.is_match(&text) != *negate))
+            }
+            Expr::In(subscripts, array) => {
+                let key = self.subscript(subscripts)?;
+                let id = self.array_id(array)?;
+                Ok(bool_value(self.arrays[id].entries.contains_key(&key)))
+            }
+            Expr::IncDec { target, delta, prefix } => {
+                let value = self.eval(target)?;
+                let old = self.to_number(&value);
+                let new = old + delta;
+                self.assign(target, Value::Number(new))?;
+                Ok(Value::Number(if *prefix { new } else { old }))
+            }
+            Expr::Call(name, args) => self.call_function(name, args),
+            Expr::Builtin(builtin, args) => self.call_builtin(*builtin, args),
+            Expr::Getline { source, target } => self.getline(source, target.as_deref()),
+        }
+    }
+
+    /**
+     * Applies an arithmetic operator
+     *
+     * @param op - Operator
+     * @param left - Left operand
+     * @param right - Right operand
+     * @return Result<f64> - Result, or an error on division by zero
+     */
+    fn arithmetic(&self, op: BinaryOp, left: f64, right: f64) -> Result<f64> {
+        Ok(match op {
+            BinaryOp::Add => left + right,
+            BinaryOp::Subtract => left - right,
+            BinaryOp::Multiply => left * right,
+            BinaryOp::Divide => {
+                if right == 0.0 {
+                    return Err(anyhow::anyhow!("division by zero attempted"));
+                }
+                left / right
+            }
+            BinaryOp::Modulo => {
+                if right == 0.0 {
+                    return Err(anyhow::anyhow!("division by zero attempted in `%'"));
+                }
+                left % right
+            }
+            BinaryOp::Power => left.powf(right),
+        })
+    }
+
+    /**
+     * Compares two values with awk's string/number rules
+     *
+     * Values compare as numbers when both are numbers, uninitialized
+     * or numeric-looking input; otherwise as strings.
+     *
+     * @param left - Left value
+     * @param right - Right value
+     * @return Option<Ordering> - Ordering, None if a NaN is involved
+     */
+    fn compare(&self, left: &Value, right: &Value) -> Option<std::cmp::Ordering> {
+        let numeric = |value: &Value| match value {
+            Value::Number(_) | Value::Uninit => true,
+            Value::StrNum(s) => looks_numeric(s),
+            Value::String(_) => false,
+        };
+
+        if numeric(left) && numeric(right) {
+            self.to_number(left).partial_cmp(&self.to_number(right))
+        } else {
+            Some(self.to_string(left).cmp(&self.to_string(right)))
+        }
+    }
+
+    /**
+     * ユーザー定義関数を呼び出す関数です
+     *
+     * スカラー引数は値渡し、配列は参照渡しです。未初期化の
+     * 変数が関数内で配列として使われる引数に渡された場合は、
+     * 呼び出し元の変数を新しい配列にしてから渡します。
+     * 引数より多い仮引数はローカル変数として扱われます。
+     *
+     * 関数内で実行されたnextやexitはUnwindエラーとして
+     * アクションの実行まで伝播させます。
+     *
+     * @param name - 関数名
+     * @param args - 引数の式
+     * @return Result<Value> - 戻り値またはエラー
+     */
+    fn call_function(&mut self, name: &str, args: &[Expr]) -> Result<Value> {
+        let program = self.program;
+        let function = program.functions
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("function `{}' not defined", name))?;
+
+        if args.len() > function.param_count {
+            return Err(anyhow::anyhow!(
+                "function `{}' called with {} args, accepts only {}",
+                name, args.len(), function.param_count
+            ));
+        }
+        if self.frames.len() >= 1024 {
+            return Err(anyhow::anyhow!("function call nesting too deep in `{}'", name));
+        }
+
+        let mut locals = Vec::with_capacity(function.param_count);
+        for (index, arg) in args.iter().enumerate() {
+            let local = match arg {
+                Expr::Variable(variable) => match self.variable_slot(variable) {
+                    Variable::Array(id) => Variable::Array(id),
+                    Variable::Uninit if function.array_params.contains(&index) => {
+                        Variable::Array(self.array_id(variable)?)
+                    }
+                    Variable::Uninit => Variable::Uninit,
+                    Variable::Scalar(value) => Variable::Scalar(value),
+                },
+                _ => Variable::Scalar(self.eval(arg)?),
+            };
+            locals.push(local);
+        }
+        locals.resize(function.param_count, Variable::Uninit);
+
+        self.frames.push(locals);
+        let result = self.exec_block(&function.body);
+        self.frames.pop();
+
+        match result? {
+            Flow::Return(value) => Ok(value),
+            flow @ (Flow::Next | Flow::NextFile | Flow::Exit(_)) => Err(anyhow::Error::new(Unwind(flow))),
+            _ => Ok(Value::Uninit),
+        }
+    }
+
+    /**
+     * 組み込み関数を呼び出す関数です
+     *
+     * 文字列関数（length、substr、index、split、sub、gsub、
+     * match、sprintf、tolower、toupper）、数学関数（sin、cos、
+     * atan2、exp、log、sqrt、int、rand、srand）、入出力関数
+     * （system、close、fflush）を実行します。
+     *
+     * 文字列の位置と長さはバイトではなく文字単位で扱います。
+     *
+     * @param builtin - 組み込み関数
+     * @param args - 引数の式
+     * @return Result<Value> - 戻り値またはエラー
+     */
+    fn call_builtin(&mut self, builtin: BuiltinFunc, args: &[Expr]) -> Result<Value> {
+        let (min, max) = match builtin {
+            BuiltinFunc::Length => (0, 1),
+            BuiltinFunc::Substr => (2, 3),
+            BuiltinFunc::Index | BuiltinFunc::Atan2 => (2, 2),
+            BuiltinFunc::Split => (2, 3),
+            BuiltinFunc::Sub | BuiltinFunc::Gsub => (2, 3),
+            BuiltinFunc::Match => (2, 2),
+            BuiltinFunc::Sprintf => (1, usize::MAX),
+            BuiltinFunc::Rand => (0, 0),
+            BuiltinFunc::Srand | BuiltinFunc::Fflush => (0, 1),
+            _ => (1, 1),
+        };
+        if args.len() < min || args.len() > max {
+            return Err(anyhow::anyhow!("{:?}: wrong number of arguments", builtin).context("builtin call"));
+        }
+
+        match builtin {
+            BuiltinFunc::Length => {
+                let Some(arg) = args.first() else {
+                    return Ok(Value::Number(self.record.chars().count() as f64));
+                };
+                if let Expr::Variable(variable) = arg {
+                    if let Variable::Array(id) = self.variable_slot(variable) {
+                        return Ok(Value::Number(self.arrays[id].entries.len() as f64));
+                    }
+                }
+                let value = self.eval(arg)?;
+                Ok(Value::Number(self.to_string(&value).chars().count() as f64))
+            }
+            BuiltinFunc::Substr => {
+                let value = self.eval(&args[0])?;
+                let text: Vec<char> = self.to_string(&value).chars().collect();
+                let value = self.eval(&args[1])?;
+                let start = self.to_number(&value).round();
+                let end = match args.get(2) {
+                    Some(arg) => {
+                        let value = self.eval(arg)?;
+                        start + self.to_number(&value).round()
+                    }
+                    None => f64::INFINITY,
+                };
+
+                let begin = if start.is_nan() { 1.0 } else { start.max(1.0) };
+                let end = if end.is_nan() { begin } else { end.min(text.len() as f64 + 1.0) };
+                if end <= begin {
+                    return Ok(Value::String(String::new()));
+                }
+                Ok(Value::String(text[begin as usize - 1..end as usize - 1].iter().collect()))
+            }
+            BuiltinFunc::Index => {
+                let value = self.eval(&args[0])?;
+                let haystack = self.to_string(&value);
+                let value = self.eval(&args[1])?;
+                let needle = self.to_string(&value);
+                let position = match haystack.find(&needle) {
+                    Some(byte) if !needle.is_empty() => haystack[..byte].chars().count() + 1,
+                    _ => 0,
+                };
+                Ok(Value::Number(position as f64))
+            }
+            BuiltinFunc::Split => {
+                let value = self.eval(&args[0])?;
+                let text = self.to_string(&value);
+                let Expr::Variable(array) = &args[1] else {
+                    return Err(anyhow::anyhow!("split: second argument is not an array"));
+                };
+                let id = self.array_id(array)?;
+
+                let pieces = match args.get(2) {
+                    Some(Expr::Regex(regex)) => {
+                        if text.is_empty() {
+                            Vec::new()
+                        } else {
+                            regex.split(&text).map(str::to_string).collect()
+                        }
+                    }
+                    Some(arg) => {
+                        let value = self.eval(arg)?;
+                        let separator = self.to_string(&value);
+                        self.split_with_separator(&text, &separator)?
+                    }
+                    None => {
+                        let separator = self.special_string("FS");
+                        self.split_with_separator(&text, &separator)?
+                    }
+                };
+
+                let count = pieces.len();
+                self.arrays[id].clear();
+                for (index, piece) in pieces.into_iter().enumerate() {
+                    self.arrays[id].set((index + 1).to_string(), Value::StrNum(piece));
+                }
+                Ok(Value::Number(count as f64))
+            }
+            BuiltinFunc::Sub | BuiltinFunc::Gsub => {
+                let regex = self.regex_for(&args[0])?;
+                let value = self.eval(&args[1])?;
+                let replacement = self.to_string(&value);
+                let record_target = Expr::Field(Box::new(Expr::Number(0.0)));
+                let target = args.get(2).unwrap_or(&record_target);
+
+                let value = self.eval(target)?;
+                let text = self.to_string(&value);
+                let (result, count) = substitute(&regex, &text, &replacement, builtin == BuiltinFunc::Gsub);
+                if count > 0 && target.is_lvalue() {
+                    self.assign(target, Value::String(result))?;
+                }
+                Ok(Value::Number(count as f64))
+            }
+            BuiltinFunc::Match => {
+                let value = self.eval(&args[0])?;
+                let text = self.to_string(&value);
+                let regex = self.regex_for(&args[1])?;
+                let (start, length) = match regex.find(&text) {
+                    Some(m) => (
+                        text[..m.start()].chars().count() as f64 + 1.0,
+                        m.as_str().chars().count() as f64,
+                    ),
+                    None => (0.0, -1.0),
+                };
+                self.set_global("RSTART", Value::Number(start))?;
+                self.set_global("RLENGTH", Value::Number(length))?;
+                Ok(Value::Number(start))
+            }
+            BuiltinFunc::Sprintf => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval(arg)?);
+                }
+                let format = self.to_string(&values[0]);
+                Ok(Value::String(self.format(&format, &values[1..])))
+            }
+            BuiltinFunc::Sin | BuiltinFunc::Cos | BuiltinFunc::Exp | BuiltinFunc::Log
+            | BuiltinFunc::Sqrt | BuiltinFunc::Int => {
+                let value = self.eval(&args[0])?;
+                let n = self.to_number(&value);
+                Ok(Value::Number(match builtin {
+                    BuiltinFunc::Sin => n.sin(),
+                    BuiltinFunc::Cos => n.cos(),
+                    BuiltinFunc::Exp => n.exp(),
+                    BuiltinFunc::Log => n.ln(),
+                    BuiltinFunc::Sqrt => n.sqrt(),
+                    _ => n.trunc(),
+                }))
+            }
+            BuiltinFunc::Atan2 => {
+                let value = self.eval(&args[0])?;
+                let y = self.to_number(&value);
+                let value = self.eval(&args[1])?;
+                let x = self.to_number(&value);
+                Ok(Value::Number(y.atan2(x)))
+            }
+            BuiltinFunc::Rand => Ok(Value::Number(self.next_random())),
+            BuiltinFunc::Srand => {
+                let previous = self.random_seed;
+                let seed = match args.first() {
+                    Some(arg) => {
+                        let value = self.eval(arg)?;
+                        self.to_number(&value)
+                    }
+                    None => std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs() as f64)
+                        .unwrap_or(0.0),
+                };
+                self.random_seed = seed;
+                self.random_state = seed.to_bits();
+                Ok(Value::Number(previous))
+            }
+            BuiltinFunc::Tolower | BuiltinFunc::Toupper => {
+                let value = self.eval(&args[0])?;
+                let text = self.to_string(&value);
+                Ok(Value::String(if builtin == BuiltinFunc::Tolower {
+                    text.to_lowercase()
+                } else {
+                    text.to_uppercase()
+                }))
+            }
+            BuiltinFunc::System => {
+                let value = self.eval(&args[0])?;
+                let command = self.to_string(&value);
+                let (output, status) = self.run_shell_command(&command, None)?;
+                self.output.push_str(&output);
+                Ok(Value::Number(status as f64))
+            }
+            BuiltinFunc::Close => {
+                let value = self.eval(&args[0])?;
+                let name = self.to_string(&value);
+                Ok(Value::Number(self.close(&name)? as f64))
+            }
+            BuiltinFunc::Fflush => {
+                for arg in args {
+                    self.eval(arg)?;
+                }
+                Ok(Value::Number(0.0))
+            }
+        }
+    }
+
+    /**
+     * Runs a `getline` expression
+     *
+     * @param source - Where to read from
+     * @param target - Variable to read into, None for $0
+     * @return Result<Value> - 1 on success, 0 at end of input, -1 on error
+     */
+    fn getline(&mut self, source: &GetlineSource, target: Option<&Expr>) -> Result<Value> {
+        let record = match source {
+            GetlineSource::Main => match self.next_main_record()? {
+                Some(record) => {
+                    self.bump_counter("NR");
+                    self.bump_counter("FNR");
+                    record
+                }
+                None => return Ok(Value::Number(0.0)),
+            },
+            GetlineSource::File(name) | GetlineSource::Command(name) => {
+                let value = self.eval(name)?;
+                let name = self.to_string(&value);
+                let is_command = matches!(source, GetlineSource::Command(_));
+
+                if !self.inputs.contains_key(&name) && !self.open_input(&name, is_command)? {
+                    return Ok(Value::Number(-1.0));
+                }
+                let input = self.inputs.get_mut(&name).expect("input was just opened");
+                let Some(record) = input.records.get(input.position).cloned() else {
+                    return Ok(Value::Number(0.0));
+                };
+                input.position += 1;
+
+                if is_command {
+                    self.bump_counter("NR");
+                }
+                record
+            }
+        };
+
+        match target {
+            Some(target) => self.assign(target, Value::StrNum(record))?,
+            None => self.set_record(record)?,
+        }
+        Ok(Value::Number(1.0))
+    }
+
+    /**
+     * Opens a getline source
+     *
+     * @param name - File name or command
+     * @param is_command - Whether to run `name` as a command
+     * @return Result<bool> - False if the file cannot be read
+     */
+    fn open_input(&mut self, name: &str, is_command: bool) -> Result<bool> {
+        let text = if is_command {
+            self.run_shell_command(name, None)?.0
+        } else if name == "-" || name == "/dev/stdin" {
+            self.stdin.take().unwrap_or_default()
+        } else {
+            match std::fs::read_to_string(self.working_dir.join(name)) {
+                Ok(text) => text,
+                Err(_) => return Ok(false),
+            }
+        };
+
+        let records = self.split_records(&text)?;
+        self.inputs.insert(name.to_string(), InputSource { records, position: 0 });
+        Ok(true)
+    }
+
+    /**
+     * Reads the next record of the main input
+     *
+     * Walks ARGV at run time, so BEGIN may change it. `var=value`
+     * operands are assigned when reached; stdin is read if no file
+     * operand is given.
+     *
+     * @return Result<Option<String>> - Next record, or None at the end
+     */
+    fn next_main_record(&mut self) -> Result<Option<String>> {
+        loop {
+            if let Some(record) = self.main_input.records.get(self.main_input.position) {
+                let record = record.clone();
+                self.main_input.position += 1;
+                return Ok(Some(record));
+            }
+            if self.main_input.finished {
+                return Ok(None);
+            }
+
+            let argc = self.get_global("ARGC").map(|value| self.to_number(&value))? as usize;
+            let argv = match self.globals.get("ARGV") {
+                Some(Variable::Array(id)) => Some(*id),
+                _ => None,
+            };
+
+            let mut text = None;
+            while self.main_input.next_arg < argc {
+                let index = self.main_input.next_arg;
+                self.main_input.next_arg += 1;
+
+                let Some(argv) = argv else { break };
+                let value = self.arrays[argv].entries.get(&index.to_string()).cloned().unwrap_or(Value::Uninit);
+                let arg = self.to_string(&value);
+                if arg.is_empty() {
+                    continue;
+                }
+
+                if let Some((name, value)) = parse_assignment_operand(&arg) {
+                    self.set_global(name, Value::StrNum(unescape_awk_string(value)))?;
+                    continue;
+                }
+
+                self.main_input.opened_file = true;
+                let content = if arg == "-" || arg == "/dev/stdin" {
+                    Ok(self.stdin.take().unwrap_or_default())
+                } else {
+                    std::fs::read_to_string(self.working_dir.join(&arg))
+                };
+                match content {
+                    Ok(content) => {
+                        self.set_global("FILENAME", Value::String(arg))?;
+                        text = Some(content);
+                        break;
+                    }
+                    Err(e) => {
+                        let reason = if e.kind() == std::io::ErrorKind::NotFound {
+                            "No such file or directory".to_string()
+                        } else {
+                            e.to_string()
+                        };
+                        self.output.push_str(&format!("awk: cannot open \"{}\" ({})\n", arg, reason));
+                        self.exit_code = 2;
+                    }
+                }
+            }
+
+            let text = match text {
+                Some(text) => text,
+                None if !self.main_input.opened_file => {
+                    self.main_input.opened_file = true;
+                    self.main_input.finished = true;
+                    self.stdin.take().unwrap_or_default()
+                }
+                None => {
+                    self.main_input.finished = true;
+                    return Ok(None);
+                }
+            };
+
+            self.set_global("FNR", Value::Number(0.0))?;
+            self.main_input.records = self.split_records(&text)?;
+            self.main_input.position = 0;
+        }
+    }
+
+    /**
+     * Splits input text into records using RS
+     *
+     * A newline RS splits lines, an empty RS splits paragraphs, a
+     * single character splits on it and anything longer is a regex.
+     *
+     * @param text - Input text
+     * @return Result<Vec<String>> - Records
+     */
+    fn split_records(&mut self, text: &str) -> Result<Vec<String>> {
+        let separator = self.special_string("RS");
+
+        let mut records: Vec<String> = if separator.is_empty() {
+            let regex = self.dynamic_regex("\n\n+")?;
+            regex.split(text.trim_start_matches('\n')).map(|r| r.trim_end_matches('\n').to_string()).collect()
+        } else if separator.chars().count() == 1 {
+            text.split(separator.as_str()).map(str::to_string).collect()
+        } else {
+            let regex = self.dynamic_regex(&separator)?;
+            regex.split(text).map(str::to_string).collect()
+        };
+
+        if records.last().is_some_and(|r| r.is_empty()) {
+            records.pop();
+        }
+        Ok(records)
+    }
+
+    /**
+     * Splits text into fields
+     *
+     * A single space splits on runs of blanks and newlines, trimming
+     * the ends; any other single character is literal; an empty
+     * separator splits characters; anything else is a regex.
+     *
+     * @param text - Text to split
+     * @param separator - Field separator
+     * @return Result<Vec<String>> - Fields
+     */
+    fn split_with_separator(&mut self, text: &str, separator: &str) -> Result<Vec<String>> {
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(if separator == " " {
+            text.split([' ', '\t', '\n']).filter(|f| !f.is_empty()).map(str::to_string).collect()
+        } else if separator.is_empty() {
+            text.chars().map(|c| c.to_string()).collect()
+        } else if separator.chars().count() == 1 && separator != "\\" {
+            text.split(separator).map(str::to_string).collect()
+        } else {
+            let regex = self.dynamic_regex(separator)?;
+            regex.split(text).map(str::to_string).collect()
+        })
+    }
+
+    /**
+     * Replaces the current record and splits its fields
+     *
+     * @param record - New $0
+     * @return Result<()> - Success or error
+     */
+    fn set_record(&mut self, record: String) -> Result<()> {
+        let separator = self.special_string("FS");
+        let fields = if self.special_string("RS").is_empty() && separator != " " {
+            // In paragraph mode newlines always separate fields
+            let mut fields = Vec::new();
+            for line in record.split('\n') {
+                fields.extend(self.split_with_separator(line, &separator)?);
+            }
+            fields
+        } else {
+            self.split_with_separator(&record, &separator)?
+        };
+
+        self.record = record;
+        self.fields = fields;
+        Ok(())
+    }
+
+    /**
+     * Rebuilds $0 from the fields with OFS
+     */
+    fn rebuild_record(&mut self) {
+        self.record = self.fields.join(&self.special_string("OFS"));
+    }
+
+    /**
+     * Evaluates a field index
+     *
+     * @param index - Index expression
+     * @return Result<usize> - Field number
+     */
+    fn field_index(&mut self, index: &Expr) -> Result<usize> {
+        let value = self.eval(index)?;
+        let n = self.to_number(&value);
+        if n < 0.0 {
+            return Err(anyhow::anyhow!("attempt to access field {}", n));
+        }
+        Ok(n as usize)
+    }
+
+    /**
+     * Gets a field value
+     *
+     * @param index - Field number, 0 for the record
+     * @return Value - Field value
+     */
+    fn get_field(&self, index: usize) -> Value {
+        if index == 0 {
+            return Value::StrNum(self.record.clone());
+        }
+        match self.fields.get(index - 1) {
+            Some(field) => Value::StrNum(field.clone()),
+            None => Value::Uninit,
+        }
+    }
+
+    /**
+     * Assigns a value to an lvalue
+     *
+     * @param target - Variable, element or field
+     * @param value - Value to store
+     * @return Result<()> - Success or error
+     */
+    fn assign(&mut self, target: &Expr, value: Value) -> Result<()> {
+        match target {
+            Expr::Variable(variable) => self.set_var(variable, value),
+            Expr::Index(array, subscripts) => {
+                let key = self.subscript(subscripts)?;
+                let id = self.array_id(array)?;
+                self.arrays[id].set(key, value);
+                Ok(())
+            }
+            Expr::Field(index) => {
+                let index = self.field_index(index)?;
+                let text = self.to_string(&value);
+                if index == 0 {
+                    return self.set_record(text);
+                }
+                if index > self.fields.len() {
+                    self.fields.resize(index, String::new());
+                }
+                self.fields[index - 1] = text;
+                self.rebuild_record();
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("assignment to non-lvalue")),
+        }
+    }
+
+    /**
+     * Builds an array key from subscripts joined with SUBSEP
+     *
+     * @param subscripts - Subscript expressions
+     * @return Result<String> - Array key
+     */
+    fn subscript(&mut self, subscripts: &[Expr]) -> Result<String> {
+        let mut parts = Vec::with_capacity(subscripts.len());
+        for subscript in subscripts {
+            let value = self.eval(subscript)?;
+            parts.push(self.to_string(&value));
+        }
+        Ok(parts.join(&self.special_string("SUBSEP")))
+    }
+
+    /**
+     * Gets a variable slot without evaluating it
+     *
+     * @param variable - Variable reference
+     * @return Variable - Copy of the slot
+     */
+    fn variable_slot(&self, variable: &VarRef) -> Variable {
+        match variable {
+            VarRef::Global(name) if name == "NF" => Variable::Scalar(Value::Number(self.fields.len() as f64)),
+            VarRef::Global(name) => self.globals.get(name).cloned().unwrap_or(Variable::Uninit),
+            VarRef::Local(slot) => self.frames.last().map_or(Variable::Uninit, |frame| frame[*slot].clone()),
+        }
+    }
+
+    /**
+     * Gets an array, turning an uninitialized variable into one
+     *
+     * @param variable - Variable reference
+     * @return Result<usize> - Arena index
+     */
+    fn array_id(&mut self, variable: &VarRef) -> Result<usize> {
+        let slot = match variable {
+            VarRef::Global(name) => self.globals.entry(name.clone()).or_insert(Variable::Uninit),
+            VarRef::Local(slot) => match self.frames.last_mut() {
+                Some(frame) => &mut frame[*slot],
+                None => return Err(anyhow::anyhow!("local variable used outside a function")),
+            },
+        };
+
+        match slot {
+            Variable::Array(id) => Ok(*id),
+            Variable::Uninit => {
+                let id = self.arrays.len();
+                self.arrays.push(AwkArray::default());
+                *slot = Variable::Array(id);
+                Ok(id)
+            }
+            Variable::Scalar(_) => {
+                let name = match variable {
+                    VarRef::Global(name) => name.clone(),
+                    VarRef::Local(_) => "parameter".to_string(),
+                };
+                Err(anyhow::anyhow!("attempt to use scalar `{}' as an array", name))
+            }
+        }
+    }
+
+    /**
+     * Reads a scalar variable
+     *
+     * @param variable - Variable reference
+     * @return Result<Value> - Value, or an error for arrays
+     */
+    fn get_var(&self, variable: &VarRef) -> Result<Value> {
+        match self.variable_slot(variable) {
+            Variable::Uninit => Ok(Value::Uninit),
+            Variable::Scalar(value) => Ok(value),
+            Variable::Array(_) => {
+                let name = match variable {
+                    VarRef::Global(name) => name.as_str(),
+                    VarRef::Local(_) => "parameter",
+                };
+                Err(anyhow::anyhow!("attempt to use array `{}' in a scalar context", name))
+            }
+        }
+    }
+
+    /**
+     * Writes a scalar variable
+     *
+     * Assigning NF truncates or extends the fields and rebuilds $0.
+     *
+     * @param variable - Variable reference
+     * @param value - New value
+     * @return Result<()> - Success, or an error for arrays
+     */
+    fn set_var(&mut self, variable: &VarRef, value: Value) -> Result<()> {
+        match variable {
+            VarRef::Global(name) => self.set_global(name, value),
+            VarRef::Local(slot) => {
+                let frame = self.frames
+                    .last_mut()
+                    .ok_or_else(|| anyhow::anyhow!("local variable used outside a function"))?;
+                if let Variable::Array(_) = frame[*slot] {
+                    return Err(anyhow::anyhow!("attempt to use array parameter in a scalar context"));
+                }
+                frame[*slot] = Variable::Scalar(value);
+                Ok(())
+            }
+        }
+    }
+
+    /**
+     * Reads a global scalar
+     *
+     * @param name - Variable name
+     * @return Result<Value> - Value
+     */
+    fn get_global(&self, name: &str) -> Result<Value> {
+        self.get_var(&VarRef::Global(name.to_string()))
+    }
+
+    /**
+     * Writes a global scalar
+     *
+     * @param name - Variable name
+     * @param value - New value
+     * @return Result<()> - Success or error
+     */
+    fn set_global(&mut self, name: &str, value: Value) -> Result<()> {
+        if name == "NF" {
+            let count = self.to_number(&value).max(0.0) as usize;
+            self.fields.resize(count, String::new());
+            self.rebuild_record();
+            return Ok(());
+        }
+        if let Some(Variable::Array(_)) = self.globals.get(name) {
+            return Err(anyhow::anyhow!("attempt to use array `{}' in a scalar context", name));
+        }
+        self.globals.insert(name.to_string(), Variable::Scalar(value));
+        Ok(())
+    }
+
+    /**
+     * Adds one to a record counter
+     *
+     * @param name - NR or FNR
+     */
+    fn bump_counter(&mut self, name: &str) {
+        let value = self.get_global(name).unwrap_or(Value::Uninit);
+        let count = self.to_number(&value) + 1.0;
+        self.globals.insert(name.to_string(), Variable::Scalar(Value::Number(count)));
+    }
+
+    /**
+     * Reads a special variable as a string
+     *
+     * Used for FS, OFS, ORS, RS, SUBSEP, CONVFMT and OFMT, without
+     * going through CONVFMT itself.
+     *
+     * @param name - Variable name
+     * @return String - Current value
+     */
+    fn special_string(&self, name: &str) -> String {
+        match self.globals.get(name) {
+            Some(Variable::Scalar(Value::String(s) | Value::StrNum(s))) => s.clone(),
+            Some(Variable::Scalar(Value::Number(n))) => {
+                if n.fract() == 0.0 && n.abs() < 1e16 {
+                    format!("{}", *n as i64)
+                } else {
+                    format!("{}", n)
+                }
+            }
+            _ => String::new(),
+        }
+    }
+
+    /**
+     * Gets the regex for the right side of `~` or a function argument
+     *
+     * @param expr - Regex literal or string expression
+     * @return Result<Regex> - Compiled regex
+     */
+    fn regex_for(&mut self, expr: &Expr) -> Result<Regex> {
+        if let Expr::Regex(regex) = expr {
+            return Ok(regex.clone());
+        }
+        let value = self.eval(expr)?;
+        let source = self.to_string(&value);
+        self.dynamic_regex(&source)
+    }
+
+    /**
+     * Compiles a regex from a string, with caching
+     *
+     * @param source - ERE source
+     * @return Result<Regex> - Compiled regex
+     */
+    fn dynamic_regex(&mut self, source: &str) -> Result<Regex> {
+        if let Some(regex) = self.regex_cache.get(source) {
+            return Ok(regex.clone());
+        }
+        let regex = compile_awk_regex(source)?;
+        self.regex_cache.insert(source.to_string(), regex.clone());
+        Ok(regex)
+    }
+
+    /**
+     * Converts a value to a number
+     *
+     * @param value - Value
+     * @return f64 - Numeric value (leading numeric prefix of strings)
+     */
+    fn to_number(&self, value: &Value) -> f64 {
+        match value {
+            Value::Uninit => 0.0,
+            Value::Number(n) => *n,
+            Value::String(s) | Value::StrNum(s) => parse_number_prefix(s),
+        }
+    }
+
+    /**
+     * Converts a value to a string using CONVFMT
+     *
+     * @param value - Value
+     * @return String - String value
+     */
+    fn to_string(&self, value: &Value) -> String {
+        match value {
+            Value::Uninit => String::new(),
+            Value::Number(n) => self.number_to_string(*n, "CONVFMT"),
+            Value::String(s) | Value::StrNum(s) => s.clone(),
+        }
+    }
+
+    /**
+     * Converts a value to a string for print, using OFMT
+     *
+     * @param value - Value
+     * @return String - Output text
+     */
+    fn to_output_string(&self, value: &Value) -> String {
+        match value {
+            Value::Number(n) => self.number_to_string(*n, "OFMT"),
+            other => self.to_string(other),
+        }
+    }
+
+    /**
+     * Formats a number; integers print without a fraction
+     *
+     * @param n - Number
+     * @param format_variable - CONVFMT or OFMT
+     * @return String - Text
+     */
+    fn number_to_string(&self, n: f64, format_variable: &str) -> String {
+        if n.is_nan() {
+            return if n.is_sign_negative() { "-nan" } else { "nan" }.to_string();
+        }
+        if n.is_infinite() {
+            return if n < 0.0 { "-inf" } else { "inf" }.to_string();
+        }
+        if n.fract() == 0.0 {
+            return format!("{:.0}", n);
+        }
+
+        let mut format = self.special_string(format_variable);
+        if format.is_empty() {
+            format = "%.6g".to_string();
+        }
+        self.format(&format, &[Value::Number(n)])
+    }
+
+    /**
+     * Checks whether a value is true
+     *
+     * @param value - Value
+     * @return bool - Truth value
+     */
+    fn is_true(&self, value: &Value) -> bool {
+        match value {
+            Value::Uninit => false,
+            Value::Number(n) => *n != 0.0,
+            Value::String(s) => !s.is_empty(),
+            Value::StrNum(s) => {
+                if looks_numeric(s) {
+                    parse_number_prefix(s) != 0.0
+                } else {
+                    !s.is_empty()
+                }
+            }
+        }
+    }
+
+    /**
+     * printf形式の書式で値を整形する関数です
+     *
+     * %d、%i、%o、%x、%X、%u、%c、%s、%e、%E、%f、%F、
+     * %g、%G、%%をサポートし、フラグ（- + 空白 # 0）、
+     * 幅、精度、および`*`による引数からの幅と精度の指定を
+     * 扱います。
+     *
+     * 引数が足りない場合は未初期化の値（空文字列または0）として
+     * 扱い、不明な変換はそのまま出力します。
+     *
+     * @param format - 書式文字列
+     * @param args - 書式に渡す値
+     * @return String - 整形された文字列
+     */
+    fn format(&self, format: &str, args: &[Value]) -> String {
+        let chars: Vec<char> = format.chars().collect();
+        let mut output = String::new();
+        let mut next_arg = 0;
+        let mut i = 0;
+
+        let mut take_arg = || {
+            let value = args.get(next_arg).cloned().unwrap_or(Value::Uninit);
+            next_arg += 1;
+            value
+        };
+
+        while i < chars.len() {
+            if chars[i] != '%' {
+                output.push(chars[i]);
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            i += 1;
+            if chars.get(i) == Some(&'%') {
+                output.push('%');
+                i += 1;
+                continue;
+            }
+
+            let mut spec = FormatSpec::default();
+            while let Some(&flag) = chars.get(i) {
+                match flag {
+                    '-' => spec.left = true,
+                    '+' => spec.plus = true,
+                    ' ' => spec.space = true,
+                    '#' => spec.alternate = true,
+                    '0' => spec.zero = true,
+                    _ => break,
+                }
+                i += 1;
+            }
+
+            if chars.get(i) == Some(&'*') {
+                i += 1;
+                let width = self.to_number(&take_arg());
+                if width < 0.0 {
+                    spec.left = true;
+                }
+                spec.width = width.abs() as usize;
+            } else {
+                while let Some(digit) = chars.get(i).and_then(|c| c.to_digit(10)) {
+                    spec.width = spec.width * 10 + digit as usize;
+                    i += 1;
+                }
+            }
+
+            if chars.get(i) == Some(&'.') {
+                i += 1;
+                if chars.get(i) == Some(&'*') {
+                    i += 1;
+                    let precision = self.to_number(&take_arg());
+                    spec.precision = (precision >= 0.0).then_some(precision as usize);
+                } else {
+                    let mut precision = 0;
+                    while let Some(digit) = chars.get(i).and_then(|c| c.to_digit(10)) {
+                        precision = precision * 10 + digit as usize;
+                        i += 1;
+                    }
+                    spec.precision = Some(precision);
+                }
+            }
+
+            let Some(&conversion) = chars.get(i) else {
+                output.extend(&chars[start..]);
+                break;
+            };
+            i += 1;
+
+            match conversion {
+                'd' | 'i' | 'o' | 'x' | 'X' | 'u' => {
+                    let value = take_arg();
+                    output.push_str(&format_integer(self.to_number(&value), conversion, &spec));
+                }
+                'e' | 'E' | 'f' | 'F' | 'g' | 'G' => {
+                    let value = take_arg();
+                    output.push_str(&format_float(self.to_number(&value), conversion, &spec));
+                }
+                'c' => {
+                    let value = take_arg();
+                    let numeric = match &value {
+                        Value::Number(_) => true,
+                        Value::StrNum(s) => looks_numeric(s),
+                        _ => false,
+                    };
+                    let text = if numeric {
+                        char::from_u32(self.to_number(&value) as u32).map(String::from).unwrap_or_default()
+                    } else {
+                        self.to_string(&value).chars().next().map(String::from).unwrap_or_default()
+                    };
+                    output.push_str(&pad_field("", &text, spec.width, spec.left, false));
+                }
+                's' => {
+                    let value = take_arg();
+                    let mut text = self.to_string(&value);
+                    if let Some(precision) = spec.precision {
+                        text = text.chars().take(precision).collect();
+                    }
+                    output.push_str(&pad_field("", &text, spec.width, spec.left, false));
+                }
+                _ => output.extend(&chars[start..i]),
+            }
+        }
+
+        output
+    }
+
+    /**
+     * Writes print output to stdout or a redirection
+     *
+     * @param redirect - Redirection, None for stdout
+     * @param text - Text to write
+     * @return Result<()> - Success or error
+     */
+    fn write_output(&mut self, redirect: &Option<Redirect>, text: &str) -> Result<()> {
+        let Some(redirect) = redirect else {
+            self.output.push_str(text);
+            return Ok(());
+        };
+
+        let (expr, append, pipe) = match redirect {
+            Redirect::File(expr) => (expr, false, false),
+            Redirect::Append(expr) => (expr, true, false),
+            Redirect::Pipe(expr) => (expr, false, true),
+        };
+        let value = self.eval(expr)?;
+        let name = self.to_string(&value);
+
+        if !pipe && matches!(name.as_str(), "/dev/stdout" | "/dev/stderr" | "-") {
+            self.output.push_str(text);
+            return Ok(());
+        }
+
+        if !self.outputs.iter().any(|(open, _)| *open == name) {
+            let target = if pipe {
+                OutputTarget::Pipe(String::new())
+            } else {
+                let path = self.working_dir.join(&name);
+                let file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(append)
+                    .truncate(!append)
+                    .open(&path)
+                    .map_err(|e| anyhow::anyhow!("can't redirect to `{}': {}", name, e))?;
+                OutputTarget::File(file)
+            };
+            self.outputs.push((name.clone(), target));
+        }
+
+        let (_, target) = self.outputs
+            .iter_mut()
+            .find(|(open, _)| *open == name)
+            .expect("output was just opened");
+        match target {
+            OutputTarget::File(file) => file.write_all(text.as_bytes())?,
+            OutputTarget::Pipe(buffer) => buffer.push_str(text),
+        }
+        Ok(())
+    }
+
+    /**
+     * Closes an output redirection or getline source
+     *
+     * @param name - File name or command
+     * @return Result<i32> - 0 or the command status, -1 if nothing was open
+     */
+    fn close(&mut self, name: &str) -> Result<i32> {
+        if let Some(position) = self.outputs.iter().position(|(open, _)| open == name) {
+            let (name, target) = self.outputs.remove(position);
+            return match target {
+                OutputTarget::File(mut file) => {
+                    file.flush()?;
+                    Ok(0)
+                }
+                OutputTarget::Pipe(buffer) => {
+                    let (output, status) = self.run_shell_command(&name, Some(&buffer))?;
+                    self.output.push_str(&output);
+                    Ok(status)
+                }
+            };
+        }
+
+        Ok(if self.inputs.remove(name).is_some() { 0 } else { -1 })
+    }
+
+    /**
+     * Closes every open output, running buffered pipes
+     *
+     * @return Result<()> - Success or error
+     */
+    fn close_all_outputs(&mut self) -> Result<()> {
+        let names: Vec<String> = self.outputs.iter().map(|(name, _)| name.clone()).collect();
+        for name in names {
+            self.close(&name)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Runs a command through /bin/sh in the working directory
+     *
+     * @param command - Command line
+     * @param input - Text for the command's stdin
+     * @return Result<(String, i32)> - Combined output and exit status
+     */
+    fn run_shell_command(&self, command: &str, input: Option<&str>) -> Result<(String, i32)> {
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(&self.working_dir)
+            .stdin(if input.is_some() { std::process::Stdio::piped() } else { std::process::Stdio::null() })
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("can't run `{}': {}", command, e))?;
+
+        let writer = match (input, child.stdin.take()) {
+            (Some(input), Some(mut stdin)) => {
+                let input = input.to_string();
+                Some(std::thread::spawn(move || {
+                    let _ = stdin.write_all(input.as_bytes());
+                }))
+            }
+            _ => None,
+        };
+
+        let result = child.wait_with_output()?;
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+
+        let mut output = String::from_utf8_lossy(&result.stdout).to_string();
+        output.push_str(&String::from_utf8_lossy(&result.stderr));
+        Ok((output, result.status.code().unwrap_or(-1)))
+    }
+
+    /**
+     * Produces the next pseudo-random number
+     *
+     * @return f64 - Number in [0, 1)
+     */
+    fn next_random(&mut self) -> f64 {
+        self.random_state = self.random_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.random_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/**
+ * Converts a boolean to an awk number
+ *
+ * @param value - Boolean
+ * @return Value - 1 or 0
+ */
+fn bool_value(value: bool) -> Value {
+    Value::Number(if value { 1.0 } else { 0.0 })
+}
+
+/**
+ * Splits a `name=value` operand
+ *
+ * @param operand - Command-line operand
+ * @return Option<(&str, &str)> - Name and value if it is an assignment
+ */
+fn parse_assignment_operand(operand: &str) -> Option<(&str, &str)> {
+    let (name, value) = operand.split_once('=')?;
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    if (first.is_ascii_alphabetic() || first == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some((name, value))
+    } else {
+        None
+    }
+}
+
+/**
+ * Finds the length of the numeric prefix of a string
+ *
+ * @param text - Text without leading blanks
+ * @return usize - Byte length of the longest valid number prefix
+ */
+fn numeric_prefix_length(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+
+    if matches!(bytes.first(), Some(b'+') | Some(b'-')) {
+        i += 1;
+    }
+    let digits_start = i;
+    while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+        i += 1;
+    }
+    let mut digit_count = i - digits_start;
+    if bytes.get(i) == Some(&b'.') {
+        let dot = i;
+        i += 1;
+        while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        digit_count += i - dot - 1;
+    }
+    if digit_count == 0 {
+        return 0;
+    }
+
+    if matches!(bytes.get(i), Some(b'e') | Some(b'E')) {
+        let mut j = i + 1;
+        if matches!(bytes.get(j), Some(b'+') | Some(b'-')) {
+            j += 1;
+        }
+        if bytes.get(j).is_some_and(u8::is_ascii_digit) {
+            while bytes.get(j).is_some_and(u8::is_ascii_digit) {
+                j += 1;
+            }
+            i = j;
+        }
+    }
+    i
+}
+
+/**
+ * Converts the numeric prefix of a string, like strtod
+ *
+ * @param text - Text
+ * @return f64 - Number, 0 if there is no numeric prefix
+ */
+fn parse_number_prefix(text: &str) -> f64 {
+    let trimmed = text.trim_start_matches([' ', '\t', '\n', '\r']);
+    let length = numeric_prefix_length(trimmed);
+    trimmed[..length].parse().unwrap_or(0.0)
+}
+
+/**
+ * Checks whether a whole string is a number
+ *
+ * @param text - Text
+ * @return bool - True if it is a number with optional blanks around it
+ */
+fn looks_numeric(text: &str) -> bool {
+    let trimmed = text.trim_matches([' ', '\t', '\n', '\r']);
+    !trimmed.is_empty() && numeric_prefix_length(trimmed) == trimmed.len()
+}
+
+/**
+ * Runs sub/gsub replacement on a string
+ *
+ * `&` inserts the match, `\&` a literal ampersand and `\\` a backslash.
+ *
+ * @param regex - Pattern
+ * @param text - Input text
+ * @param replacement - Replacement template
+ * @param global - Replace every match rather than the first
+ * @return (String, usize) - Result and number of replacements
+ */
+fn substitute(regex: &Regex, text: &str, replacement: &str, global: bool) -> (String, usize) {
+    let mut result = String::new();
+    let mut last_end = 0;
+    let mut count = 0;
+
+    for m in regex.find_iter(text) {
+        result.push_str(&text[last_end..m.start()]);
+
+        let mut chars = replacement.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if matches!(chars.peek(), Some('&') | Some('\\')) => {
+                    result.push(chars.next().unwrap_or('\\'));
+                }
+                '&' => result.push_str(m.as_str()),
+                _ => result.push(c),
+            }
+        }
+
+        last_end = m.end();
+        count += 1;
+        if !global {
+            break;
+        }
+    }
+
+    result.push_str(&text[last_end..]);
+    (result, count)
+}
+
+/**
+ * Parsed printf conversion specification
+ */
+#[derive(Debug, Default)]
+struct FormatSpec {
+    /// `-` flag
+    left: bool,
+    /// `+` flag
+    plus: bool,
+    /// ` ` flag
+    space: bool,
+    /// `#` flag
+    alternate: bool,
+    /// `0` flag
+    zero: bool,
+    /// Minimum field width
+    width: usize,
+    /// Precision
+    precision: Option<usize>,
+}
+
+impl FormatSpec {
+    /**
+     * Gets the sign prefix for a number
+     *
+     * @param negative - Whether the number is negative
+     * @return &str - "-", "+", " " or ""
+     */
+    fn sign(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.plus {
+            "+"
+        } else if self.space {
+            " "
+        } else {
+            ""
+        }
+    }
+}
+
+/**
+ * Pads a formatted value to a field width
+ *
+ * @param prefix - Sign or radix prefix kept before zero padding
+ * @param body - Digits or text
+ * @param width - Minimum width
+ * @param left - Left-justify
+ * @param zero - Pad with zeros after the prefix
+ * @return String - Padded text
+ */
+fn pad_field(prefix: &str, body: &str, width: usize, left: bool, zero: bool) -> String {
+    let length = prefix.chars().count() + body.chars().count();
+    if length >= width {
+        return format!("{}{}", prefix, body);
+    }
+
+    let fill = width - length;
+    if left {
+        format!("{}{}{}", prefix, body, " ".repeat(fill))
+    } else if zero {
+        format!("{}{}{}", prefix, "0".repeat(fill), body)
+    } else {
+        format!("{}{}{}", " ".repeat(fill), prefix, body)
+    }
+}
+
+/**
+ * Formats an integer conversion (%d %i %o %x %X %u)
+ *
+ * @param n - Value
+ * @param conversion - Conversion character
+ * @param spec - Flags, width and precision
+ * @return String - Formatted text
+ */
+fn format_integer(n: f64, conversion: char, spec: &FormatSpec) -> String {
+    if !n.is_finite() {
+        return format_float(n, 'f', spec);
+    }
+
+    let value = n.trunc();
+    let (prefix, mut digits) = match conversion {
+        'd' | 'i' => (spec.sign(value < 0.0).to_string(), format!("{:.0}", value.abs())),
+        _ => {
+            let unsigned = if value < 0.0 { value as i64 as u64 } else { value as u64 };
+            match conversion {
+                'o' => {
+                    let digits = format!("{:o}", unsigned);
+                    (String::new(), if spec.alternate && unsigned != 0 { format!("0{}", digits) } else { digits })
+                }
+                'x' => ((if spec.alternate && unsigned != 0 { "0x" } else { "" }).to_string(), format!("{:x}", unsigned)),
+                'X' => ((if spec.alternate && unsigned != 0 { "0X" } else { "" }).to_string(), format!("{:X}", unsigned)),
+                _ => (String::new(), unsigned.to_string()),
+            }
+        }
+    };
+
+    if let Some(precision) = spec.precision {
+        if precision == 0 && value == 0.0 {
+            digits.clear();
+        } else if digits.len() < precision {
+            digits = format!("{}{}", "0".repeat(precision - digits.len()), digits);
+        }
+    }
+
+    pad_field(&prefix, &digits, spec.width, spec.left, spec.zero && spec.precision.is_none())
+}
+
+/**
+ * Formats a floating-point conversion (%e %f %g and uppercase forms)
+ *
+ * @param n - Value
+ * @param conversion - Conversion character
+ * @param spec - Flags, width and precision
+ * @return String - Formatted text
+ */
+fn format_float(n: f64, conversion: char, spec: &FormatSpec) -> String {
+    let precision = spec.precision.unwrap_or(6);
+    let upper = conversion.is_ascii_uppercase();
+    let negative = n < 0.0 || (n == 0.0 && n.is_sign_negative());
+    let magnitude = n.abs();
+
+    let body = if magnitude.is_nan() {
+        "nan".to_string()
+    } else if magnitude.is_infinite() {
+        "inf".to_string()
+    } else {
+        match conversion.to_ascii_lowercase() {
+            'e' => format_exponential(magnitude, precision, upper),
+            'g' => format_general(magnitude, precision, spec.alternate, upper),
+            _ => {
+                let mut text = format!("{:.*}", precision, magnitude);
+                if spec.alternate && precision == 0 {
+                    text.push('.');
+                }
+                text
+            }
+        }
+    };
+    let body = if upper { body.to_uppercase() } else { body };
+
+    pad_field(spec.sign(negative), &body, spec.width, spec.left, spec.zero && n.is_finite())
+}
+
+/**
+ * Formats a non-negative number as `d.ddde+XX`
+ *
+ * @param magnitude - Non-negative value
+ * @param precision - Digits after the point
+ * @param upper - Use `E`
+ * @return String - Formatted text
+ */
+fn format_exponential(magnitude: f64, precision: usize, upper: bool) -> String {
+    let text = format!("{:.*e}", precision, magnitude);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    format!(
+        "{}{}{}{:02}",
+        mantissa,
+        if upper { 'E' } else { 'e' },
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/**
+ * Formats a non-negative number for %g
+ *
+ * @param magnitude - Non-negative value
+ * @param precision - Significant digits (0 means 1)
+ * @param alternate - Keep trailing zeros
+ * @param upper - Use `E`
+ * @return String - Formatted text
+ */
+fn format_general(magnitude: f64, precision: usize, alternate: bool, upper: bool) -> String {
+    let precision = precision.max(1);
+    let exponent = if magnitude == 0.0 {
+        0
+    } else {
+        let text = format!("{:.*e}", precision - 1, magnitude);
+        text.split_once('e').and_then(|(_, e)| e.parse::<i32>().ok()).unwrap_or(0)
+    };
+
+    let text = if exponent < -4 || exponent >= precision as i32 {
+        format_exponential(magnitude, precision - 1, upper)
+    } else {
+        format!("{:.*}", (precision as i32 - 1 - exponent) as usize, magnitude)
+    };
+
+    if alternate || !text.contains('.') {
+        return text;
+    }
+
+    let (mantissa, exponent_part) = match text.find(['e', 'E']) {
+        Some(position) => text.split_at(position),
+        None => (text.as_str(), ""),
+    };
+    format!("{}{}", mantissa.trim_end_matches('0').trim_end_matches('.'), exponent_part)
+}
@@ -0,0 +1,1202 @@
+/*!
+ * @file cargo.rs
+ * @brief Cargo builds through JSON messages
+ *
+ * This module runs cargo with --message-format=json and works from
+ * the structured messages instead of cargo's human-readable text:
+ * compiler diagnostics are rendered with their spans, labels, error
+ * codes and suggested replacements, test binaries are run with
+ * libtest's JSON output for per-test results and durations (in the
+ * environment cargo test would give them), and file locations become
+ * OSC 8 hyperlinks.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file cargo.rs
+ * @description Diagnostic, artifact and test event parsing, diagnostic
+ * and test summary rendering, and the build, test and run workflows.
+ */
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use unicode_width::UnicodeWidthChar;
+
+use crate::shell::commands::diagnostics::{self, DiagnosticLinker, Stream};
+use crate::shell::commands::records::Table;
+
+/// cargo test options selecting targets, which turn off doc tests unless --doc is given
+const TARGET_SELECTIONS: [&str; 10] = ["--lib", "--bin", "--bins", "--example", "--examples", "--test", "--tests", "--bench", "--benches", "--all-targets"];
+
+/// cargo options taking a value as the next argument
+const VALUE_OPTIONS: [&str; 21] = [
+    "-p", "--package", "--exclude", "-F", "--features", "--target", "--target-dir", "--manifest-path", "-j", "--jobs", "--profile",
+    "--bin", "--example", "--test", "--bench", "--color", "--config", "-Z", "--message-format", "--lockfile-path", "--timings",
+];
+
+/// Tests listed as the slowest in a summary
+const SLOWEST_TESTS: usize = 5;
+
+/// Target kinds of library crates, which share the manifest's [lib] section
+const LIBRARY_KINDS: [&str; 6] = ["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+
+/**
+ * libtest's human-readable result line, used for doc tests
+ */
+fn test_line_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^test (?P<name>.+) \.\.\. (?P<status>ok|FAILED|ignored)").unwrap())
+}
+
+/**
+ * Source region a diagnostic points at
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// File name as rustc printed it
+    pub file_name: String,
+    /// Resolved file path
+    pub path: PathBuf,
+    /// First line
+    pub line: u32,
+    /// First column
+    pub column: u32,
+    /// Last line
+    pub end_line: u32,
+    /// Column after the region
+    pub end_column: u32,
+    /// Text shown under the region
+    pub label: Option<String>,
+    /// Whether this is the main location of the diagnostic
+    pub primary: bool,
+    /// Source lines with the 1-based highlighted columns
+    pub text: Vec<(String, usize, usize)>,
+}
+
+/**
+ * Replacement the compiler proposes
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Help message
+    pub message: String,
+    /// Region to replace
+    pub span: Span,
+    /// Replacement text
+    pub replacement: String,
+    /// Whether a tool may apply it unattended (MachineApplicable, MaybeIncorrect, ...)
+    pub applicability: Option<String>,
+}
+
+/**
+ * Compiler diagnostic from a compiler-message
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// error, warning, note, help or failure-note
+    pub level: String,
+    /// Error code or lint name
+    pub code: Option<String>,
+    /// Message
+    pub message: String,
+    /// Primary and secondary spans
+    pub spans: Vec<Span>,
+    /// Child notes and help without a replacement, with their level
+    pub notes: Vec<(String, String)>,
+    /// Suggested replacements
+    pub suggestions: Vec<Suggestion>,
+    /// rustc's own rendering
+    pub rendered: String,
+    /// Name of the target being compiled
+    pub target: String,
+}
+
+/**
+ * Test executable built by cargo
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestBinary {
+    /// Target name
+    pub name: String,
+    /// Target kind, e.g. lib, bin or test
+    pub kind: String,
+    /// Source file of the target
+    pub source: PathBuf,
+    /// Executable
+    pub executable: PathBuf,
+    /// Directory of the package manifest
+    pub package_directory: PathBuf,
+    /// Package name
+    pub package: String,
+    /// Package ID, as in cargo metadata
+    pub package_id: String,
+    /// OUT_DIR of the package's build script, if it has one
+    pub out_dir: Option<PathBuf>,
+}
+
+/**
+ * Outcome of a test
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TestStatus {
+    /// The test passed
+    Passed,
+    /// The test failed
+    Failed,
+    /// The test was ignored
+    Ignored,
+}
+
+impl TestStatus {
+    /**
+     * Name used in records
+     *
+     * @return &str - passed, failed or ignored
+     */
+    pub fn name(&self) -> &str {
+        match self {
+            TestStatus::Passed => "passed",
+            TestStatus::Failed => "failed",
+            TestStatus::Ignored => "ignored",
+        }
+    }
+}
+
+/**
+ * Result of one test
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestOutcome {
+    /// Test binary or "doc-tests"
+    pub suite: String,
+    /// Test path
+    pub name: String,
+    /// Outcome
+    pub status: TestStatus,
+    /// Run time in seconds, when libtest reported it
+    pub duration: Option<f64>,
+    /// Captured output of a failed test
+    pub output: Option<String>,
+    /// Directory panic locations are relative to
+    pub directory: PathBuf,
+}
+
+/**
+ * Collects output for a pipe or prints it to the terminal as it arrives
+ */
+pub struct Printer {
+    /// Whether output goes to the terminal
+    pub terminal: bool,
+    /// Output collected for a pipe
+    pub output: String,
+}
+
+impl Printer {
+    /**
+     * Creates a printer
+     *
+     * @param terminal - Whether output goes to the terminal
+     * @return Printer - Printer
+     */
+    pub fn new(terminal: bool) -> Self {
+        Printer { terminal, output: String::new() }
+    }
+
+    /**
+     * Prints or collects text, which should end with a newline
+     *
+     * @param text - Text
+     */
+    pub fn print(&mut self, text: &str) {
+        if self.terminal {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(text.as_bytes());
+            let _ = stdout.flush();
+        } else {
+            self.output.push_str(text);
+        }
+    }
+
+    /**
+     * Paints text with an SGR color when printing to the terminal
+     *
+     * @param code - SGR parameters, e.g. "1;31"
+     * @param text - Text
+     * @return String - Colored or plain text
+     */
+    pub fn paint(&self, code: &str, text: &str) -> String {
+        if self.terminal {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/**
+ * Result of a cargo build
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Build {
+    /// Deduplicated diagnostics in the order they arrived
+    pub diagnostics: Vec<Diagnostic>,
+    /// Test executables, for cargo test --no-run
+    pub test_binaries: Vec<TestBinary>,
+    /// Executables of bin and example targets
+    pub executables: Vec<PathBuf>,
+    /// Whether a lib target with doc tests was built
+    pub has_doctests: bool,
+    /// Native library directories reported by build scripts
+    pub native_paths: Vec<PathBuf>,
+    /// Exit code of cargo
+    pub exit_code: i32,
+}
+
+impl Build {
+    /**
+     * Counts errors and warnings
+     *
+     * @return (usize, usize) - Error and warning counts
+     */
+    pub fn counts(&self) -> (usize, usize) {
+        let errors = self.diagnostics.iter().filter(|diagnostic| diagnostic.level.starts_with("error")).count();
+        let warnings = self.diagnostics.iter().filter(|diagnostic| diagnostic.level == "warning").count();
+        (errors, warnings)
+    }
+}
+
+impl Span {
+    /**
+     * Reads a span of a rustc JSON diagnostic
+     *
+     * @param value - Span object
+     * @param root - Workspace root relative file names are resolved against
+     * @return Option<Span> - Span, or None when fields are missing
+     */
+    fn from_json(value: &Value, root: &Path) -> Option<Span> {
+        let file_name = value["file_name"].as_str()?.to_string();
+        let path = Path::new(&file_name);
+        let path = if path.is_absolute() { path.to_path_buf() } else { root.join(path) };
+        let number = |key: &str| value[key].as_u64().map(|number| number as u32);
+        Some(Span {
+            file_name,
+            path,
+            line: number("line_start")?,
+            column: number("column_start")?,
+            end_line: number("line_end")?,
+            end_column: number("column_end")?,
+            label: value["label"].as_str().map(str::to_string),
+            primary: value["is_primary"].as_bool().unwrap_or(false),
+            text: value["text"]
+                .as_array()
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .filter_map(|line| {
+                            Some((
+                                line["text"].as_str()?.to_string(),
+                                line["highlight_start"].as_u64()? as usize,
+                                line["highlight_end"].as_u64()? as usize,
+                            ))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /**
+     * Location text, e.g. src/main.rs:3:5
+     *
+     * @return String - File name, line and column
+     */
+    pub fn location(&self) -> String {
+        format!("{}:{}:{}", self.file_name, self.line, self.column)
+    }
+}
+
+impl Diagnostic {
+    /**
+     * rustcのJSON診断を読み取る関数です
+     *
+     * 子の診断のうち、置換テキスト（suggested_replacement）を持つ
+     * スパンは提案に、それ以外はnoteやhelpとして保持します。
+     * 「aborting due to ...」や「N warnings emitted」のような
+     * 集計メッセージや、エラーコードの説明を案内するfailure-noteは
+     * 個別の診断ではないためNoneを返します。
+     *
+     * @param message - compiler-messageのmessageオブジェクト
+     * @param root - 相対パスの基準となるワークスペースのルート
+     * @param target - コンパイル中のターゲット名
+     * @return Option<Diagnostic> - 診断、または対象外の場合None
+     */
+    pub fn from_json(message: &Value, root: &Path, target: &str) -> Option<Diagnostic> {
+        let text = message["message"].as_str()?.to_string();
+        let spans: Vec<Span> = message["spans"].as_array()?.iter().filter_map(|span| Span::from_json(span, root)).collect();
+        let level = message["level"].as_str().unwrap_or("error").to_string();
+        if spans.is_empty() && (level == "failure-note" || text.starts_with("aborting due to") || text.ends_with("emitted")) {
+            return None;
+        }
+
+        let mut notes = Vec::new();
+        let mut suggestions = Vec::new();
+        for child in message["children"].as_array().into_iter().flatten() {
+            let level = child["level"].as_str().unwrap_or("note").to_string();
+            let child_message = child["message"].as_str().unwrap_or_default().to_string();
+            let mut replaced = false;
+            for span in child["spans"].as_array().into_iter().flatten() {
+                if let (Some(replacement), Some(parsed)) = (span["suggested_replacement"].as_str(), Span::from_json(span, root)) {
+                    suggestions.push(Suggestion {
+                        message: child_message.clone(),
+                        span: parsed,
+                        replacement: replacement.to_string(),
+                        applicability: span["suggestion_applicability"].as_str().map(str::to_string),
+                    });
+                    replaced = true;
+                }
+            }
+            if !replaced {
+                notes.push((level, child_message));
+            }
+        }
+
+        Some(Diagnostic {
+            level,
+            code: message["code"]["code"].as_str().map(str::to_string),
+            message: text,
+            spans,
+            notes,
+            suggestions,
+            rendered: message["rendered"].as_str().unwrap_or_default().to_string(),
+            target: target.to_string(),
+        })
+    }
+
+    /**
+     * Main span of the diagnostic
+     *
+     * @return Option<&Span> - Primary span, or the first span
+     */
+    pub fn primary(&self) -> Option<&Span> {
+        self.spans.iter().find(|span| span.primary).or(self.spans.first())
+    }
+
+    /**
+     * Header line, e.g. error[E0308]: mismatched types
+     *
+     * @param printer - Printer deciding on colors and links
+     * @return String - Level, code and message
+     */
+    pub fn header(&self, printer: &Printer) -> String {
+        let color = level_color(&self.level);
+        let code = match &self.code {
+            Some(code) if printer.terminal && is_error_code(code) => {
+                let url = format!("https://doc.rust-lang.org/error_codes/{}.html", code);
+                format!("[\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\]", url, code)
+            }
+            Some(code) => format!("[{}]", code),
+            None => String::new(),
+        };
+        format!("{}{}: {}", printer.paint(color, &self.level), printer.paint(color, &code), printer.paint("1", &self.message))
+    }
+
+    /**
+     * 診断を端末向けに描画する関数です
+     *
+     * rustcの表示に合わせて、見出し（レベル、エラーコード、
+     * メッセージ）、「-->」の位置（OSC 8リンク）、スパンごとの
+     * ソース行と下線（主スパンは「^」、副スパンは「-」）とラベル
+     * （同じ行の複数のスパンはソース行を1回だけ表示）、
+     * 「= note:」「= help:」の子メッセージ、提案（置換を適用した
+     * ソース行を緑で表示）の順に描画します。
+     *
+     * タブは4桁の空白に、全角文字は幅2として下線の位置を
+     * 合わせます。エラーコードはrustcのエラー索引へのリンクにします。
+     *
+     * @param printer - 色とリンクの有無を決めるプリンタ
+     * @param host - file:// URLのホスト名
+     * @return String - 描画した診断（末尾に空行）
+     */
+    pub fn render(&self, printer: &Printer, host: &str) -> String {
+        let gutter = self
+            .spans
+            .iter()
+            .chain(self.suggestions.iter().map(|suggestion| &suggestion.span))
+            .map(|span| span.end_line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(gutter);
+        let bar = printer.paint("1;34", "|");
+        let mut output = format!("{}\n", self.header(printer));
+
+        if let Some(primary) = self.primary() {
+            let location = if printer.terminal { diagnostics::hyperlink(host, &primary.path, &primary.location()) } else { primary.location() };
+            output.push_str(&format!("{}{} {}\n", pad, printer.paint("1;34", "-->"), location));
+            output.push_str(&format!("{} {}\n", pad, bar));
+            let mut spans: Vec<&Span> = self.spans.iter().filter(|span| span.file_name == primary.file_name).collect();
+            spans.sort_by_key(|span| (span.line, !span.primary));
+            let mut printed_line = None;
+            for span in spans {
+                let count = span.text.len();
+                for (index, (text, start, end)) in span.text.iter().enumerate() {
+                    let number = span.line as usize + index;
+                    if printed_line.replace(number) != Some(number) {
+                        output.push_str(&format!("{} {} {}\n", printer.paint("1;34", &format!("{:>width$}", number, width = gutter)), bar, expand_tabs(text)));
+                    }
+                    let marker = if span.primary { '^' } else { '-' };
+                    let indent = display_width(text, start.saturating_sub(1));
+                    let length = display_width(text, end.saturating_sub(1)).saturating_sub(indent).max(1);
+                    let mut underline = format!("{}{}", " ".repeat(indent), marker.to_string().repeat(length));
+                    if index + 1 == count {
+                        if let Some(label) = &span.label {
+                            underline.push(' ');
+                            underline.push_str(label);
+                        }
+                    }
+                    let color = if span.primary { level_color(&self.level) } else { "1;34" };
+                    output.push_str(&format!("{} {} {}\n", pad, bar, printer.paint(color, &underline)));
+                }
+            }
+        }
+
+        for (level, message) in &self.notes {
+            output.push_str(&format!("{} {} {}: {}\n", pad, printer.paint("1;34", "="), printer.paint("1", level), message));
+        }
+
+        for suggestion in &self.suggestions {
+            let replacement = if suggestion.replacement.is_empty() { "remove this".to_string() } else { format!("`{}`", suggestion.replacement) };
+            output.push_str(&format!("{} {} {}: {}: {}\n", pad, printer.paint("1;34", "="), printer.paint("1;36", "help"), suggestion.message, replacement));
+            let span = &suggestion.span;
+            if let [(text, start, end)] = span.text.as_slice() {
+                let characters: Vec<char> = text.chars().collect();
+                let before: String = characters.iter().take(start.saturating_sub(1)).collect();
+                let after: String = characters.iter().skip(end.saturating_sub(1)).collect();
+                let location = if printer.terminal { diagnostics::hyperlink(host, &span.path, &span.location()) } else { span.location() };
+                output.push_str(&format!("{}{} {}\n", pad, printer.paint("1;34", "-->"), location));
+                output.push_str(&format!(
+                    "{} {} {}{}{}\n",
+                    printer.paint("1;34", &format!("{:>width$}", span.line, width = gutter)),
+                    bar,
+                    expand_tabs(&before),
+                    printer.paint("32", &suggestion.replacement),
+                    expand_tabs(&after)
+                ));
+            }
+        }
+        output.push('\n');
+        output
+    }
+}
+
+/**
+ * Whether a code is a rustc error code rather than a lint name
+ *
+ * @param code - Code, e.g. E0308 or unused_variables
+ * @return bool - True for E followed by four digits
+ */
+fn is_error_code(code: &str) -> bool {
+    code.len() == 5 && code.starts_with('E') && code[1..].chars().all(|character| character.is_ascii_digit())
+}
+
+/**
+ * SGR color of a diagnostic level
+ *
+ * @param level - Diagnostic level
+ * @return &str - SGR parameters
+ */
+fn level_color(level: &str) -> &'static str {
+    match level {
+        "error" | "error: internal compiler error" => "1;31",
+        "warning" => "1;33",
+        "note" | "failure-note" => "1;32",
+        _ => "1;36",
+    }
+}
+
+/**
+ * Replaces tabs with four spaces, as rustc does in snippets
+ *
+ * @param text - Source text
+ * @return String - Text without tabs
+ */
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', "    ")
+}
+
+/**
+ * Display width of the first characters of a line
+ *
+ * @param text - Source line
+ * @param characters - Number of characters
+ * @return usize - Terminal columns they take after tab expansion
+ */
+fn display_width(text: &str, characters: usize) -> usize {
+    text.chars()
+        .take(characters)
+        .map(|character| if character == '\t' { 4 } else { character.width().unwrap_or(0) })
+        .sum()
+}
+
+/**
+ * Table of diagnostics for records and the errors list
+ *
+ * @param diagnostics - Diagnostics of the last build
+ * @return Table - index, level, code, message, file, line, column and target columns
+ */
+pub fn diagnostic_table(diagnostics: &[Diagnostic]) -> Table {
+    let mut table = Table::new(&["index", "level", "code", "message", "file", "line", "column", "target"]);
+    for (index, diagnostic) in diagnostics.iter().enumerate() {
+        let span = diagnostic.primary();
+        table.push_row(vec![
+            json!(index + 1),
+            json!(diagnostic.level),
+            diagnostic.code.as_ref().map_or(Value::Null, |code| json!(code)),
+            json!(diagnostic.message),
+            span.map_or(Value::Null, |span| json!(span.path.display().to_string())),
+            span.map_or(Value::Null, |span| json!(span.line)),
+            span.map_or(Value::Null, |span| json!(span.column)),
+            json!(diagnostic.target),
+        ]);
+    }
+    table
+}
+
+/**
+ * Table of test outcomes for records
+ *
+ * @param outcomes - Test outcomes
+ * @return Table - suite, test, status and duration columns
+ */
+pub fn test_table(outcomes: &[TestOutcome]) -> Table {
+    let mut table = Table::new(&["suite", "test", "status", "duration"]);
+    for outcome in outcomes {
+        table.push_row(vec![
+            json!(outcome.suite),
+            json!(outcome.name),
+            json!(outcome.status.name()),
+            outcome.duration.map_or(Value::Null, |duration| json!(duration)),
+        ]);
+    }
+    table
+}
+
+/**
+ * Root of the workspace rustc file names are relative to
+ *
+ * @param directory - Directory cargo runs in
+ * @param args - cargo arguments, for --manifest-path
+ * @return PathBuf - Workspace root, or the directory when cargo cannot locate it
+ */
+pub fn workspace_root(directory: &Path, args: &[String]) -> PathBuf {
+    let mut command = Command::new("cargo");
+    command.args(["locate-project", "--workspace", "--message-format", "plain"]).current_dir(directory);
+    if let Some(position) = args.iter().position(|arg| arg == "--manifest-path") {
+        if let Some(path) = args.get(position + 1) {
+            command.args(["--manifest-path", path]);
+        }
+    }
+    command
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            let manifest = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+            manifest.parent().map(Path::to_path_buf)
+        })
+        .unwrap_or_else(|| directory.to_path_buf())
+}
+
+/**
+ * Colors cargo's status lines (Compiling, Finished, ...)
+ *
+ * @param line - Line cargo printed to stderr
+ * @param printer - Printer deciding on colors
+ * @return String - Line with the status word colored
+ */
+fn status_line(line: &str, printer: &Printer) -> String {
+    let trimmed = line.trim_start();
+    let word = trimmed.split_whitespace().next().unwrap_or_default();
+    if word.len() > 1 && word.chars().next().is_some_and(char::is_uppercase) && word.chars().skip(1).all(char::is_lowercase) && line.starts_with(' ') {
+        let indent = &line[..line.len() - trimmed.len()];
+        return format!("{}{}{}", indent, printer.paint("1;32", word), &trimmed[word.len()..]);
+    }
+    match trimmed.split_once(':') {
+        Some(("error", rest)) => format!("{}:{}", printer.paint("1;31", "error"), rest),
+        Some(("warning", rest)) => format!("{}:{}", printer.paint("1;33", "warning"), rest),
+        _ => line.to_string(),
+    }
+}
+
+/**
+ * cargoをJSONメッセージで実行してビルドする関数です
+ *
+ * 「cargo <サブコマンド> --message-format=json」を実行し、標準出力の
+ * JSONメッセージを1行ずつ処理します。compiler-messageは診断として
+ * 読み取り、同じ診断（libとlibのテストで2回コンパイルされる場合など）は
+ * 1回だけ描画します。compiler-artifactからはテストの実行ファイルと
+ * doctestの有無を集めます。標準エラーのCompilingやFinishedなどの
+ * 進捗行は状態語に色を付けてそのまま表示します。
+ *
+ * @param subcommand - build、check、clippy、testなど
+ * @param args - サブコマンドの後の引数（--の前まで）
+ * @param directory - cargoを実行するディレクトリ
+ * @param printer - 出力先
+ * @return Result<Build> - 診断、テスト実行ファイル、終了コード
+ */
+pub fn build(subcommand: &str, args: &[String], directory: &Path, printer: &mut Printer) -> Result<Build> {
+    let root = workspace_root(directory, args);
+    let host = whoami::fallible::hostname().unwrap_or_default();
+    let mut command = Command::new("cargo");
+    command.arg(subcommand).args(args).arg("--message-format=json").current_dir(directory);
+
+    let mut build = Build::default();
+    let mut seen = HashSet::new();
+    let mut out_dirs: HashMap<String, PathBuf> = HashMap::new();
+    let exit_code = diagnostics::run_streaming(&mut command, |stream, line| {
+        let message = match stream {
+            Stream::Stdout => serde_json::from_str::<Value>(line).ok(),
+            Stream::Stderr => None,
+        };
+        let Some(message) = message else {
+            printer.print(&format!("{}\n", status_line(line, printer)));
+            return;
+        };
+        match message["reason"].as_str() {
+            Some("compiler-message") => {
+                let target = message["target"]["name"].as_str().unwrap_or_default();
+                if let Some(diagnostic) = Diagnostic::from_json(&message["message"], &root, target) {
+                    if seen.insert((diagnostic.rendered.clone(), diagnostic.message.clone())) {
+                        printer.print(&diagnostic.render(printer, &host));
+                        build.diagnostics.push(diagnostic);
+                    }
+                }
+            }
+            Some("build-script-executed") => {
+                let package = message["package_id"].as_str().unwrap_or_default().to_string();
+                if let Some(out_dir) = message["out_dir"].as_str() {
+                    out_dirs.insert(package, PathBuf::from(out_dir));
+                }
+                for linked in message["linked_paths"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                    // Entries look like "native=/path" or just "/path"
+                    let path = PathBuf::from(linked.split_once('=').map_or(linked, |(_, path)| path));
+                    if !build.native_paths.contains(&path) {
+                        build.native_paths.push(path);
+                    }
+                }
+            }
+            Some("compiler-artifact") => {
+                let target = &message["target"];
+                let kind = target["kind"][0].as_str().unwrap_or_default().to_string();
+                if target["doctest"].as_bool() == Some(true) && kind.contains("lib") {
+                    build.has_doctests = true;
+                }
+                if let (Some(executable), false) = (message["executable"].as_str(), message["profile"]["test"].as_bool() == Some(true)) {
+                    if kind == "bin" || kind == "example" {
+                        build.executables.push(PathBuf::from(executable));
+                    }
+                }
+                if let (Some(executable), true) = (message["executable"].as_str(), message["profile"]["test"].as_bool() == Some(true)) {
+                    let manifest = PathBuf::from(message["manifest_path"].as_str().unwrap_or_default());
+                    let package = message["package_id"].as_str().unwrap_or_default();
+                    build.test_binaries.push(TestBinary {
+                        name: target["name"].as_str().unwrap_or_default().to_string(),
+                        kind,
+                        source: PathBuf::from(target["src_path"].as_str().unwrap_or_default()),
+                        executable: PathBuf::from(executable),
+                        package_directory: manifest.parent().map(Path::to_path_buf).unwrap_or_else(|| root.clone()),
+                        package: package_name(package),
+                        package_id: package.to_string(),
+                        out_dir: out_dirs.get(package).cloned(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    })?;
+    build.exit_code = exit_code;
+    Ok(build)
+}
+
+/**
+ * Package name from a package ID
+ *
+ * @param package_id - e.g. "path+file:///src/ct#0.1.0" or "registry+...#serde@1.0.0"
+ * @return String - Package name
+ */
+fn package_name(package_id: &str) -> String {
+    match package_id.rsplit_once('#') {
+        Some((path, fragment)) => match fragment.split_once('@') {
+            Some((name, _)) => name.to_string(),
+            None => path.rsplit('/').next().unwrap_or(path).to_string(),
+        },
+        None => package_id.split_whitespace().next().unwrap_or(package_id).to_string(),
+    }
+}
+
+/**
+ * Package details needed to run its test binaries like cargo does
+ */
+#[derive(Debug, Clone, Default)]
+struct PackageInfo {
+    /// CARGO_MANIFEST_* and CARGO_PKG_* variables
+    environment: Vec<(String, String)>,
+    /// Targets declared with harness = false, as (kind, name); the
+    /// library is recorded as ("lib", "")
+    without_harness: HashSet<(String, String)>,
+}
+
+/**
+ * パッケージ情報を読み込む関数です
+ *
+ * 「cargo metadata --no-deps」でワークスペースのパッケージを取得し、
+ * cargo testがテスト実行ファイルに設定するCARGO_MANIFEST_DIR、
+ * CARGO_MANIFEST_PATH、CARGO_PKG_*（バージョンの各部分、作者、
+ * 説明など）の環境変数を組み立てます。値のない項目はcargoと同じく
+ * 空文字列にします。
+ *
+ * harnessの設定はcargo metadataに含まれないため、各パッケージの
+ * Cargo.tomlを読み込み、[lib]、[[bin]]、[[test]]、[[bench]]、
+ * [[example]]のうちharness = falseのターゲットを集めます。
+ *
+ * @param directory - cargoを実行するディレクトリ
+ * @param options - cargo testのオプション（--manifest-pathや--offlineなどを引き継ぎます）
+ * @return HashMap<String, PackageInfo> - パッケージIDごとの情報（取得できない場合は空）
+ */
+fn package_info(directory: &Path, options: &[String]) -> HashMap<String, PackageInfo> {
+    let mut command = Command::new("cargo");
+    command.args(["metadata", "--no-deps", "--format-version", "1"]).current_dir(directory);
+    let mut arguments = options.iter();
+    while let Some(option) = arguments.next() {
+        match option.as_str() {
+            "--manifest-path" => {
+                command.arg(option).args(arguments.next());
+            }
+            "--offline" | "--frozen" | "--locked" => {
+                command.arg(option);
+            }
+            _ => {}
+        }
+    }
+    let Some(metadata) = command
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| serde_json::from_slice::<Value>(&output.stdout).ok())
+    else {
+        return HashMap::new();
+    };
+
+    let mut packages = HashMap::new();
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let text = |key: &str| package[key].as_str().unwrap_or_default().to_string();
+        let manifest = PathBuf::from(text("manifest_path"));
+        let version = text("version");
+        let release = version.split('+').next().unwrap_or_default();
+        let (numbers, pre) = release.split_once('-').unwrap_or((release, ""));
+        let mut parts = numbers.split('.');
+        let authors: Vec<&str> = package["authors"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+
+        let environment = vec![
+            ("CARGO_MANIFEST_DIR".to_string(), manifest.parent().unwrap_or(Path::new("")).display().to_string()),
+            ("CARGO_MANIFEST_PATH".to_string(), manifest.display().to_string()),
+            ("CARGO_PKG_NAME".to_string(), text("name")),
+            ("CARGO_PKG_VERSION".to_string(), version.clone()),
+            ("CARGO_PKG_VERSION_MAJOR".to_string(), parts.next().unwrap_or_default().to_string()),
+            ("CARGO_PKG_VERSION_MINOR".to_string(), parts.next().unwrap_or_default().to_string()),
+            ("CARGO_PKG_VERSION_PATCH".to_string(), parts.next().unwrap_or_default().to_string()),
+            ("CARGO_PKG_VERSION_PRE".to_string(), pre.to_string()),
+            ("CARGO_PKG_AUTHORS".to_string(), authors.join(":")),
+            ("CARGO_PKG_DESCRIPTION".to_string(), text("description")),
+            ("CARGO_PKG_HOMEPAGE".to_string(), text("homepage")),
+            ("CARGO_PKG_REPOSITORY".to_string(), text("repository")),
+            ("CARGO_PKG_LICENSE".to_string(), text("license")),
+            ("CARGO_PKG_LICENSE_FILE".to_string(), text("license_file")),
+            ("CARGO_PKG_RUST_VERSION".to_string(), text("rust_version")),
+            ("CARGO_PKG_README".to_string(), text("readme")),
+        ];
+
+        packages.insert(text("id"), PackageInfo {
+            environment,
+            without_harness: targets_without_harness(&manifest),
+        });
+    }
+    packages
+}
+
+/**
+ * Reads the targets a manifest declares with harness = false
+ *
+ * @param manifest - Path to Cargo.toml
+ * @return HashSet<(String, String)> - (kind, name) pairs; the library is ("lib", "")
+ */
+fn targets_without_harness(manifest: &Path) -> HashSet<(String, String)> {
+    let mut targets = HashSet::new();
+    let Some(table) = std::fs::read_to_string(manifest).ok().and_then(|text| text.parse::<toml::Table>().ok()) else {
+        return targets;
+    };
+    let no_harness = |target: &toml::Value| target.get("harness").and_then(toml::Value::as_bool) == Some(false);
+
+    if table.get("lib").is_some_and(no_harness) {
+        targets.insert(("lib".to_string(), String::new()));
+    }
+    for kind in ["bin", "test", "bench", "example"] {
+        for target in table.get(kind).and_then(toml::Value::as_array).into_iter().flatten() {
+            if let (true, Some(name)) = (no_harness(target), target.get("name").and_then(toml::Value::as_str)) {
+                targets.insert((kind.to_string(), name.to_string()));
+            }
+        }
+    }
+    targets
+}
+
+/**
+ * Path of the cargo executable, as cargo passes it in CARGO
+ *
+ * @return PathBuf - $CARGO when the shell itself runs under cargo, else cargo found on PATH
+ */
+fn cargo_executable() -> PathBuf {
+    if let Some(cargo) = std::env::var_os("CARGO") {
+        return PathBuf::from(cargo);
+    }
+    std::env::var_os("PATH")
+        .and_then(|path| std::env::split_paths(&path).map(|directory| directory.join("cargo")).find(|candidate| candidate.is_file()))
+        .unwrap_or_else(|| PathBuf::from("cargo"))
+}
+
+/**
+ * Sets the environment cargo test gives a test binary
+ *
+ * @param command - Command running the binary
+ * @param binary - Test binary
+ * @param package - Package details, if cargo metadata could be read
+ * @param native_paths - Native library directories from build scripts
+ */
+fn set_test_environment(command: &mut Command, binary: &TestBinary, package: Option<&PackageInfo>, native_paths: &[PathBuf]) {
+    command
+        .env("CARGO", cargo_executable())
+        .env("CARGO_MANIFEST_DIR", &binary.package_directory)
+        .env("CARGO_PKG_NAME", &binary.package)
+        .env("CARGO_CRATE_NAME", binary.name.replace('-', "_"));
+    if let Some(package) = package {
+        command.envs(package.environment.iter().map(|(name, value)| (name, value)));
+    }
+    if binary.kind == "bin" {
+        command.env("CARGO_BIN_NAME", &binary.name);
+    }
+    if let Some(out_dir) = &binary.out_dir {
+        command.env("OUT_DIR", out_dir);
+    }
+
+    // Dynamic libraries are looked up in target/<profile>/deps, target/<profile> and native paths
+    let variable = if cfg!(target_os = "macos") {
+        "DYLD_FALLBACK_LIBRARY_PATH"
+    } else if cfg!(windows) {
+        "PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    };
+    let mut search: Vec<PathBuf> = binary.executable.ancestors().skip(1).take(2).map(Path::to_path_buf).collect();
+    search.extend(native_paths.iter().cloned());
+    if let Some(existing) = std::env::var_os(variable) {
+        search.extend(std::env::split_paths(&existing));
+    }
+    if let Ok(joined) = std::env::join_paths(search) {
+        command.env(variable, joined);
+    }
+}
+
+/**
+ * Splits cargo test arguments
+ *
+ * @param args - Arguments after `cargo test`
+ * @return (Vec<String>, Vec<String>, Vec<String>) - cargo options, test name filters and arguments after `--`
+ */
+pub fn split_test_args(args: &[String]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut options = Vec::new();
+    let mut filters = Vec::new();
+    let mut harness = Vec::new();
+    let mut arguments = args.iter();
+    while let Some(arg) = arguments.next() {
+        if arg == "--" {
+            harness.extend(arguments.by_ref().cloned());
+        } else if VALUE_OPTIONS.contains(&arg.as_str()) {
+            options.push(arg.clone());
+            options.extend(arguments.next().cloned());
+        } else if arg.starts_with('-') {
+            options.push(arg.clone());
+        } else {
+            filters.push(arg.clone());
+        }
+    }
+    (options, filters, harness)
+}
+
+/**
+ * Prints the result line of a finished test
+ *
+ * @param outcome - Test outcome
+ * @param printer - Output
+ */
+fn print_outcome(outcome: &TestOutcome, printer: &mut Printer) {
+    let status = match outcome.status {
+        TestStatus::Passed => printer.paint("32", "ok     "),
+        TestStatus::Failed => printer.paint("1;31", "FAILED "),
+        TestStatus::Ignored => printer.paint("33", "ignored"),
+    };
+    let duration = outcome.duration.map(|duration| format!("{:>8.3}s", duration)).unwrap_or_else(|| " ".repeat(9));
+    printer.print(&format!("    {} {}  {}\n", status, printer.paint("2", &duration), outcome.name));
+}
+
+/**
+ * テストを実行する関数です
+ *
+ * 「cargo test --no-run」でビルドした各テスト実行ファイルを、
+ * libtestのJSON出力（--format json --report-time）で直接実行し、
+ * テストごとの結果と所要時間を集めます。これらは不安定オプション
+ * なので、テスト実行ファイルにだけRUSTC_BOOTSTRAP=1を渡します
+ * （cargoやrustcには渡さないため、再ビルドは起きません）。
+ * cargo testと同じく、作業ディレクトリはパッケージのディレクトリに
+ * し、CARGO、CARGO_MANIFEST_*、CARGO_PKG_*、CARGO_CRATE_NAME、
+ * ビルドスクリプトのOUT_DIR、動的ライブラリの検索パスを設定します。
+ *
+ * 独自ハーネス（harness = false）のターゲットにはlibtestの
+ * オプションを渡さず、フィルターと「--」の後の引数だけを渡します。
+ * 出力はそのまま表示し、終了コードだけで成否を判断します。
+ *
+ * doctestはcargo test --docで実行し、人間向けの出力を解析します
+ * （所要時間はありません）。--no-fail-fastがない場合は、失敗した
+ * スイートの後のスイートは実行しません。
+ *
+ * @param args - cargo testの後の引数
+ * @param directory - cargoを実行するディレクトリ
+ * @param printer - 出力先
+ * @return Result<(Build, Vec<TestOutcome>, i32)> - ビルド結果、テスト結果、終了コード
+ */
+pub fn test(args: &[String], directory: &Path, printer: &mut Printer) -> Result<(Build, Vec<TestOutcome>, i32)> {
+    let (options, filters, harness) = split_test_args(args);
+    let doc_only = options.iter().any(|option| option == "--doc");
+    let no_fail_fast = options.iter().any(|option| option == "--no-fail-fast");
+    let mut build_options: Vec<String> = options.iter().filter(|option| *option != "--doc" && *option != "--no-fail-fast").cloned().collect();
+    build_options.insert(0, "--no-run".to_string());
+
+    let mut outcomes = Vec::new();
+    let built = build("test", &build_options, directory, printer)?;
+    if built.exit_code != 0 || options.iter().any(|option| option == "--no-run") {
+        let exit_code = built.exit_code;
+        return Ok((built, outcomes, exit_code));
+    }
+
+    let mut failed_suites = 0;
+    let packages = if doc_only { HashMap::new() } else { package_info(directory, &options) };
+    if !doc_only {
+        for binary in &built.test_binaries {
+            if failed_suites > 0 && !no_fail_fast {
+                break;
+            }
+            let source = binary.source.strip_prefix(&binary.package_directory).unwrap_or(&binary.source);
+            let executable = binary.executable.strip_prefix(directory).unwrap_or(&binary.executable);
+            printer.print(&format!(
+                "     {} {} ({})\n",
+                printer.paint("1;32", "Running"),
+                source.display(),
+                executable.display()
+            ));
+            let suite = format!("{} {}", binary.name, binary.kind);
+            let package = packages.get(&binary.package_id);
+            let target = if LIBRARY_KINDS.contains(&binary.kind.as_str()) {
+                ("lib".to_string(), String::new())
+            } else {
+                (binary.kind.clone(), binary.name.clone())
+            };
+            let uses_libtest = !package.is_some_and(|package| package.without_harness.contains(&target));
+
+            let mut command = Command::new(&binary.executable);
+            command.args(&filters);
+            if uses_libtest {
+                command
+                    .args(["-Z", "unstable-options", "--format", "json", "--report-time"])
+                    .env("RUSTC_BOOTSTRAP", "1");
+            }
+            command.args(&harness).current_dir(&binary.package_directory);
+            set_test_environment(&mut command, binary, package, &built.native_paths);
+            let mut structured = false;
+            let exit_code = diagnostics::run_streaming(&mut command, |stream, line| {
+                let event = match stream {
+                    Stream::Stdout => serde_json::from_str::<Value>(line).ok().filter(|event| event["type"].is_string()),
+                    Stream::Stderr => None,
+                };
+                let Some(event) = event else {
+                    printer.print(&format!("{}\n", line));
+                    return;
+                };
+                structured = true;
+                if event["type"] != "test" {
+                    return;
+                }
+                let status = match event["event"].as_str() {
+                    Some("ok") => TestStatus::Passed,
+                    Some("failed") | Some("timeout") => TestStatus::Failed,
+                    Some("ignored") => TestStatus::Ignored,
+                    _ => return,
+                };
+                let outcome = TestOutcome {
+                    suite: suite.clone(),
+                    name: event["name"].as_str().unwrap_or_default().to_string(),
+                    status,
+                    duration: event["exec_time"].as_f64(),
+                    output: event["stdout"].as_str().filter(|output| !output.is_empty()).map(str::to_string),
+                    directory: binary.package_directory.clone(),
+                };
+                print_outcome(&outcome, printer);
+                outcomes.push(outcome);
+            })?;
+            if exit_code != 0 {
+                failed_suites += 1;
+                if !structured {
+                    outcomes.push(TestOutcome {
+                        suite: suite.clone(),
+                        name: binary.name.clone(),
+                        status: TestStatus::Failed,
+                        duration: None,
+                        output: None,
+                        directory: binary.package_directory.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let selects_targets = options.iter().any(|option| TARGET_SELECTIONS.contains(&option.as_str()));
+    if built.has_doctests && (doc_only || !selects_targets) && (failed_suites == 0 || no_fail_fast) {
+        printer.print(&format!("   {} {}\n", printer.paint("1;32", "Doc-tests"), built.test_binaries.first().map_or("", |binary| binary.package.as_str())));
+        let doc_options: Vec<String> = options.iter().filter(|option| *option != "--doc" && *option != "--no-fail-fast").cloned().collect();
+        let mut command = Command::new("cargo");
+        command.args(["test", "--doc", "--message-format=json"]).args(&doc_options).arg("--").args(&filters).args(&harness).current_dir(directory);
+        let mut failure: Option<(String, String)> = None;
+        let mut failures: Vec<(String, String)> = Vec::new();
+        let mut doc_outcomes = Vec::new();
+        let exit_code = diagnostics::run_streaming(&mut command, |stream, line| {
+            if stream == Stream::Stderr {
+                if !line.trim_start().starts_with("Finished") && !line.trim_start().starts_with("Doc-tests") && !line.trim_start().starts_with("Compiling") {
+                    printer.print(&format!("{}\n", status_line(line, printer)));
+                }
+                return;
+            }
+            if line.starts_with('{') && serde_json::from_str::<Value>(line).is_ok() {
+                return;
+            }
+            if let Some(captures) = test_line_pattern().captures(line) {
+                let status = match &captures["status"] {
+                    "ok" => TestStatus::Passed,
+                    "FAILED" => TestStatus::Failed,
+                    _ => TestStatus::Ignored,
+                };
+                let outcome = TestOutcome {
+                    suite: "doc-tests".to_string(),
+                    name: captures["name"].to_string(),
+                    status,
+                    duration: None,
+                    output: None,
+                    directory: directory.to_path_buf(),
+                };
+                print_outcome(&outcome, printer);
+                doc_outcomes.push(outcome);
+            } else if let Some(name) = line.strip_prefix("---- ").and_then(|rest| rest.strip_suffix(" stdout ----")) {
+                failures.extend(failure.take());
+                failure = Some((name.to_string(), String::new()));
+            } else if line == "failures:" || line.starts_with("test result:") {
+                failures.extend(failure.take());
+            } else if let Some((_, output)) = &mut failure {
+                output.push_str(line);
+                output.push('\n');
+            }
+        })?;
+        failures.extend(failure.take());
+        for outcome in &mut doc_outcomes {
+            if let Some((_, output)) = failures.iter().find(|(name, _)| *name == outcome.name) {
+                outcome.output = Some(output.clone());
+            }
+        }
+        if exit_code != 0 {
+            failed_suites += 1;
+        }
+        outcomes.extend(doc_outcomes);
+    }
+
+    let exit_code = if failed_suites > 0 { 101 } else { 0 };
+    Ok((built, outcomes, exit_code))
+}
+
+/**
+ * テスト結果の要約を描画する関数です
+ *
+ * 失敗したテストの出力（パニック位置はリンク付き）、最も時間の
+ * かかったテスト、成功・失敗・無視の件数と合計時間を描画します。
+ *
+ * @param outcomes - テスト結果
+ * @param printer - 色とリンクの有無を決めるプリンタ
+ * @return String - 要約
+ */
+pub fn render_test_summary(outcomes: &[TestOutcome], printer: &Printer) -> String {
+    let mut output = String::new();
+    let count = |status: TestStatus| outcomes.iter().filter(|outcome| outcome.status == status).count();
+    let (passed, failed, ignored) = (count(TestStatus::Passed), count(TestStatus::Failed), count(TestStatus::Ignored));
+
+    let failures: Vec<&TestOutcome> = outcomes.iter().filter(|outcome| outcome.status == TestStatus::Failed).collect();
+    if !failures.is_empty() {
+        output.push_str(&format!("\n{}\n", printer.paint("1;31", "failures:")));
+        for outcome in &failures {
+            output.push_str(&format!("\n---- {} ({}) ----\n", printer.paint("1", &outcome.name), outcome.suite));
+            let mut linker = DiagnosticLinker::new(&outcome.directory);
+            for line in outcome.output.as_deref().unwrap_or_default().lines() {
+                output.push_str(&if printer.terminal { linker.decorate(line) } else { line.to_string() });
+                output.push('\n');
+            }
+        }
+    }
+
+    let mut timed: Vec<&TestOutcome> = outcomes.iter().filter(|outcome| outcome.duration.is_some()).collect();
+    timed.sort_by(|left, right| right.duration.partial_cmp(&left.duration).unwrap_or(std::cmp::Ordering::Equal));
+    if timed.len() > 1 {
+        output.push_str(&format!("\n{}\n", printer.paint("1", "slowest:")));
+        for outcome in timed.iter().take(SLOWEST_TESTS) {
+            output.push_str(&format!("  {:>8.3}s  {}\n", outcome.duration.unwrap_or_default(), outcome.name));
+        }
+    }
+
+    // Folded from +0.0: an empty f64 sum is -0.0, which printed as "-0.00s"
+    let total = outcomes.iter().filter_map(|outcome| outcome.duration).fold(0.0, |total, duration| total + duration);
+    let verdict = if failed > 0 { printer.paint("1;31", "FAILED") } else { printer.paint("1;32", "ok") };
+    output.push_str(&format!(
+        "\ntest result: {}. {} passed; {} failed; {} ignored; {:.2}s in tests\n",
+        verdict, passed, failed, ignored, total
+    ));
+    output
+}
+
+/**
+ * Summary line of a build, e.g. "cargo: 1 error, 2 warnings"
+ *
+ * @param build - Build result
+ * @param printer - Printer deciding on colors
+ * @return Option<String> - Summary with a hint to the errors list, or None without diagnostics
+ */
+pub fn render_build_summary(build: &Build, printer: &Printer) -> Option<String> {
+    let (errors, warnings) = build.counts();
+    if errors == 0 && warnings == 0 {
+        return None;
+    }
+    let plural = |count: usize, word: &str| format!("{} {}{}", count, word, if count == 1 { "" } else { "s" });
+    Some(format!(
+        "{} {}, {} ({} lists them)\n",
+        printer.paint("1", "cargo:"),
+        plural(errors, "error"),
+        plural(warnings, "warning"),
+        printer.paint("1", "cargo errors")
+    ))
+}
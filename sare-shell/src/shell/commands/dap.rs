@@ -0,0 +1,766 @@
+/*!
+ * @file dap.rs
+ * @brief Debug Adapter Protocol client for lldb-dap
+ *
+ * This module runs lldb-dap (or the older lldb-vscode) as a child
+ * process and talks the Debug Adapter Protocol to it over standard
+ * input and output. Breakpoints are kept locally and sent per source
+ * file once the program is launched, as the protocol requires.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file dap.rs
+ * @description Content-Length framing, request/response matching,
+ * launch sequencing, stopped/exited events and variable scopes.
+ */
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::shell::commands::debugger::{Breakpoint, DebugBackend, Frame, Resume, Stop, StopReason, Variable};
+use crate::shell::commands::tail::InterruptGuard;
+
+/// How long to wait for the adapter to answer a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often to check for Ctrl+C while the program runs
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/**
+ * Running adapter process
+ */
+struct Connection {
+    /// Adapter process
+    child: Child,
+    /// Adapter's standard input, for requests
+    input: ChildStdin,
+    /// Messages read from the adapter on a separate thread
+    messages: Receiver<Value>,
+    /// Sequence number of the next request
+    sequence: i64,
+    /// Events received while waiting for a response
+    events: VecDeque<Value>,
+    /// Error of a request whose response was not waited for (e.g. launch)
+    late_error: Option<String>,
+}
+
+impl Connection {
+    /**
+     * Starts the adapter and sends the initialize request
+     *
+     * @param adapter - Adapter executable
+     * @param directory - Working directory
+     * @return Result<Connection> - Initialized connection or an error
+     */
+    fn open(adapter: &Path, directory: &Path) -> Result<Self> {
+        let mut child = Command::new(adapter)
+            .current_dir(directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()
+            .map_err(|error| anyhow::anyhow!("{}: {}", adapter.display(), error))?;
+        let input = child.stdin.take().ok_or_else(|| anyhow::anyhow!("adapter has no input"))?;
+        let output = child.stdout.take().ok_or_else(|| anyhow::anyhow!("adapter has no output"))?;
+
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(output);
+            let mut header = String::new();
+            loop {
+                let mut length = None;
+                loop {
+                    header.clear();
+                    if !reader.read_line(&mut header).is_ok_and(|count| count > 0) {
+                        return;
+                    }
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = header.strip_prefix("Content-Length:") {
+                        length = value.trim().parse::<usize>().ok();
+                    }
+                }
+                let Some(length) = length else {
+                    continue;
+                };
+                let mut body = vec![0; length];
+                if reader.read_exact(&mut body).is_err() {
+                    return;
+                }
+                if let Ok(message) = serde_json::from_slice::<Value>(&body) {
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        let mut connection = Connection {
+            child,
+            input,
+            messages,
+            sequence: 1,
+            events: VecDeque::new(),
+            late_error: None,
+        };
+        connection.request(
+            "initialize",
+            json!({
+                "clientID": "sare",
+                "clientName": "Sare shell",
+                "adapterID": "lldb-dap",
+                "linesStartAt1": true,
+                "columnsStartAt1": true,
+                "pathFormat": "path",
+            }),
+        )?;
+        Ok(connection)
+    }
+
+    /**
+     * Sends a request without waiting for its response
+     *
+     * @param command - Request command
+     * @param arguments - Request arguments
+     * @return Result<i64> - Sequence number of the request
+     */
+    fn send(&mut self, command: &str, arguments: Value) -> Result<i64> {
+        let sequence = self.sequence;
+        self.sequence += 1;
+        let body = json!({
+            "seq": sequence,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        write!(self.input, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.input.flush()?;
+        Ok(sequence)
+    }
+
+    /**
+     * Receives the next message from the adapter
+     *
+     * @param timeout - How long to wait
+     * @return Result<Option<Value>> - Response or event, None on timeout, or an error when the adapter exits
+     */
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Value>> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(message) => Ok(Some(message)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(anyhow::anyhow!("the debug adapter exited")),
+        }
+    }
+
+    /**
+     * Sends a request and waits for its response
+     *
+     * @param command - Request command
+     * @param arguments - Request arguments
+     * @return Result<Value> - Response body, or the error message of a failed request
+     */
+    fn request(&mut self, command: &str, arguments: Value) -> Result<Value> {
+        let sequence = self.send(command, arguments)?;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(message) = self.receive(remaining)? else {
+                return Err(anyhow::anyhow!("the debug adapter did not respond to {}", command));
+            };
+            match message["type"].as_str() {
+                Some("event") => self.events.push_back(message),
+                Some("response") if message["request_seq"].as_i64() == Some(sequence) => {
+                    if message["success"].as_bool() == Some(true) {
+                        return Ok(message["body"].clone());
+                    }
+                    return Err(anyhow::anyhow!("{}", response_error(&message)));
+                }
+                Some("response") if message["success"].as_bool() == Some(false) => {
+                    self.late_error = Some(response_error(&message));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /**
+     * Next event, from the queue or the adapter
+     *
+     * @param timeout - How long to wait when the queue is empty
+     * @return Result<Option<Value>> - Event, None on timeout, or an error
+     */
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<Value>> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+        let Some(message) = self.receive(timeout)? else {
+            return Ok(None);
+        };
+        match message["type"].as_str() {
+            Some("event") => Ok(Some(message)),
+            Some("response") => {
+                if message["success"].as_bool() == Some(false) {
+                    self.late_error = Some(response_error(&message));
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Drop for Connection {
+    /**
+     * Disconnects, which terminates the program, and kills the adapter if it does not exit
+     */
+    fn drop(&mut self) {
+        if self.send("disconnect", json!({ "terminateDebuggee": true })).is_ok() {
+            let deadline = Instant::now() + Duration::from_secs(2);
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = self.child.try_wait() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/**
+ * Error text of a failed response
+ *
+ * @param message - Response message
+ * @return String - Formatted error, the short message, or a generic text
+ */
+fn response_error(message: &Value) -> String {
+    message["body"]["error"]["format"]
+        .as_str()
+        .or_else(|| message["message"].as_str())
+        .unwrap_or("request failed")
+        .to_string()
+}
+
+/**
+ * Where a breakpoint is set
+ */
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// Line in a source file, by absolute path
+    Line(PathBuf, u32),
+    /// Function name
+    Function(String),
+}
+
+/**
+ * Breakpoint known to the client
+ */
+#[derive(Debug, Clone)]
+struct Request {
+    /// Where the breakpoint is set
+    target: Target,
+    /// ID the adapter assigned, once launched
+    adapter_id: Option<i64>,
+    /// Breakpoint as shown to the user
+    breakpoint: Breakpoint,
+}
+
+/**
+ * Debug backend driving lldb-dap through the Debug Adapter Protocol
+ */
+pub struct DapBackend {
+    /// Adapter executable
+    adapter: PathBuf,
+    /// Program being debugged, absolute
+    program: PathBuf,
+    /// Working directory of the program
+    directory: PathBuf,
+    /// Running adapter
+    connection: Connection,
+    /// Whether the program was launched in this connection
+    launched: bool,
+    /// Breakpoints in creation order
+    requests: Vec<Request>,
+    /// Number of the next breakpoint
+    next_number: u32,
+    /// Thread the program last stopped in
+    thread: Option<i64>,
+    /// Adapter frame IDs of the last backtrace, by level
+    frame_ids: Vec<i64>,
+    /// Breakpoints as shown to the user, mirrored from the requests
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl DapBackend {
+    /**
+     * Starts the adapter
+     *
+     * @param adapter - lldb-dap executable
+     * @param program - Program to debug
+     * @param directory - Working directory
+     * @return Result<DapBackend> - Backend or an error
+     */
+    pub fn start(adapter: &Path, program: &Path, directory: &Path) -> Result<Self> {
+        Ok(DapBackend {
+            adapter: adapter.to_path_buf(),
+            program: program.to_path_buf(),
+            directory: directory.to_path_buf(),
+            connection: Connection::open(adapter, directory)?,
+            launched: false,
+            requests: Vec::new(),
+            next_number: 1,
+            thread: None,
+            frame_ids: Vec::new(),
+            breakpoints: Vec::new(),
+        })
+    }
+
+    /**
+     * Copies the request list into the breakpoint table
+     */
+    fn refresh_table(&mut self) {
+        self.breakpoints = self.requests.iter().map(|request| request.breakpoint.clone()).collect();
+    }
+
+    /**
+     * Applies a breakpoint from a setBreakpoints response or breakpoint event
+     *
+     * @param request - Local breakpoint
+     * @param reported - Adapter's breakpoint object
+     */
+    fn apply_reported(request: &mut Request, reported: &Value) {
+        if let Some(id) = reported["id"].as_i64() {
+            request.adapter_id = Some(id);
+        }
+        request.breakpoint.pending = reported["verified"].as_bool() != Some(true);
+        if let Some(line) = reported["line"].as_u64() {
+            request.breakpoint.line = Some(line as u32);
+        }
+        if let Some(name) = reported["source"]["name"].as_str() {
+            request.breakpoint.file = Some(name.to_string());
+        }
+    }
+
+    /**
+     * ソースファイルのブレークポイントを送る関数です
+     *
+     * DAPのsetBreakpointsはファイルごとに全ての行を置き換えるため、
+     * 指定されたファイルのブレークポイントをまとめて送ります。
+     * 応答のbreakpoints配列は要求と同じ順序なので、位置で対応させて
+     * アダプタのIDと確認済みかどうかを記録します。
+     *
+     * @param path - ソースファイルの絶対パス
+     * @return Result<()> - 成功またはエラー
+     */
+    fn send_source_breakpoints(&mut self, path: &Path) -> Result<()> {
+        let indices: Vec<usize> = (0..self.requests.len())
+            .filter(|index| matches!(&self.requests[*index].target, Target::Line(file, _) if file == path))
+            .collect();
+        let lines: Vec<Value> = indices
+            .iter()
+            .filter_map(|index| match &self.requests[*index].target {
+                Target::Line(_, line) => Some(json!({ "line": line })),
+                Target::Function(_) => None,
+            })
+            .collect();
+        let body = self.connection.request(
+            "setBreakpoints",
+            json!({
+                "source": { "path": path, "name": path.file_name().map(|name| name.to_string_lossy().to_string()) },
+                "breakpoints": lines,
+            }),
+        )?;
+        let reported = body["breakpoints"].as_array().cloned().unwrap_or_default();
+        for (index, reported) in indices.iter().zip(reported.iter()) {
+            Self::apply_reported(&mut self.requests[*index], reported);
+        }
+        Ok(())
+    }
+
+    /**
+     * Sends all function breakpoints
+     *
+     * @return Result<()> - Success or an error
+     */
+    fn send_function_breakpoints(&mut self) -> Result<()> {
+        let indices: Vec<usize> = (0..self.requests.len())
+            .filter(|index| matches!(self.requests[*index].target, Target::Function(_)))
+            .collect();
+        let names: Vec<Value> = indices
+            .iter()
+            .filter_map(|index| match &self.requests[*index].target {
+                Target::Function(name) => Some(json!({ "name": name })),
+                Target::Line(..) => None,
+            })
+            .collect();
+        let body = self.connection.request("setFunctionBreakpoints", json!({ "breakpoints": names }))?;
+        let reported = body["breakpoints"].as_array().cloned().unwrap_or_default();
+        for (index, reported) in indices.iter().zip(reported.iter()) {
+            Self::apply_reported(&mut self.requests[*index], reported);
+        }
+        Ok(())
+    }
+
+    /**
+     * Sends the breakpoints of a target, or of every file when launching
+     *
+     * @param target - Target whose file changed, or None for all
+     * @return Result<()> - Success or an error
+     */
+    fn sync_breakpoints(&mut self, target: Option<&Target>) -> Result<()> {
+        match target {
+            Some(Target::Line(path, _)) => self.send_source_breakpoints(&path.clone())?,
+            Some(Target::Function(_)) => self.send_function_breakpoints()?,
+            None => {
+                let files: BTreeMap<PathBuf, ()> = self
+                    .requests
+                    .iter()
+                    .filter_map(|request| match &request.target {
+                        Target::Line(path, _) => Some((path.clone(), ())),
+                        Target::Function(_) => None,
+                    })
+                    .collect();
+                for path in files.keys() {
+                    self.send_source_breakpoints(path)?;
+                }
+                if self.requests.iter().any(|request| matches!(request.target, Target::Function(_))) {
+                    self.send_function_breakpoints()?;
+                }
+            }
+        }
+        self.refresh_table();
+        Ok(())
+    }
+
+    /**
+     * Thread to resume or interrupt
+     *
+     * @return Result<i64> - Stopped thread, or the first thread of the program
+     */
+    fn current_thread(&mut self) -> Result<i64> {
+        if let Some(thread) = self.thread {
+            return Ok(thread);
+        }
+        let body = self.connection.request("threads", json!({}))?;
+        body["threads"][0]["id"].as_i64().ok_or_else(|| anyhow::anyhow!("the program has no threads"))
+    }
+
+    /**
+     * Reads a stack frame object
+     *
+     * @param value - StackFrame from a stackTrace response
+     * @param level - Frame level
+     * @return Frame - Frame
+     */
+    fn frame_from(value: &Value, level: usize) -> Frame {
+        Frame {
+            level,
+            function: value["name"].as_str().unwrap_or("??").to_string(),
+            arguments: Vec::new(),
+            file: value["source"]["name"].as_str().map(str::to_string),
+            full_path: value["source"]["path"].as_str().map(PathBuf::from),
+            line: value["line"].as_u64().filter(|line| *line > 0).map(|line| line as u32),
+            address: value["instructionPointerReference"].as_str().map(str::to_string),
+            library: None,
+        }
+    }
+
+    /**
+     * 停止イベントまたは終了イベントを待つ関数です
+     *
+     * outputイベントのうちプログラムの標準出力と標準エラーをsinkへ
+     * 渡し、breakpointイベントで表を更新します。stoppedイベントでは
+     * 先頭のフレームを取得し、hitBreakpointIdsからブレークポイント
+     * 番号を求めてヒット数を増やします。Ctrl+Cが押されたら一度だけ
+     * pauseを送ります。
+     *
+     * @param resume - 再開の種類（finishの停止を関数の終了として扱うため）
+     * @param sink - プログラムの出力を受け取る関数
+     * @return Result<Stop> - 停止イベントまたはエラー
+     */
+    fn wait_for_stop(&mut self, resume: Option<Resume>, sink: &mut dyn FnMut(&str)) -> Result<Stop> {
+        let guard = InterruptGuard::install();
+        let mut interrupt_sent = false;
+        let mut exit_code = None;
+        loop {
+            if let Some(error) = self.connection.late_error.take() {
+                return Err(anyhow::anyhow!("{}", error));
+            }
+            if guard.interrupted() && !interrupt_sent {
+                let thread = self.current_thread()?;
+                self.connection.send("pause", json!({ "threadId": thread }))?;
+                interrupt_sent = true;
+            }
+            let Some(event) = self.connection.next_event(POLL_INTERVAL)? else {
+                continue;
+            };
+            let body = &event["body"];
+            match event["event"].as_str().unwrap_or_default() {
+                "output" => {
+                    if matches!(body["category"].as_str(), Some("stdout") | Some("stderr")) {
+                        sink(body["output"].as_str().unwrap_or_default());
+                    }
+                }
+                "breakpoint" => {
+                    let id = body["breakpoint"]["id"].as_i64();
+                    if let Some(request) = self.requests.iter_mut().find(|request| id.is_some() && request.adapter_id == id) {
+                        Self::apply_reported(request, &body["breakpoint"]);
+                    }
+                    self.refresh_table();
+                }
+                "exited" => exit_code = Some(body["exitCode"].as_i64().unwrap_or(0) as i32),
+                "terminated" => {
+                    self.thread = None;
+                    return Ok(Stop {
+                        reason: StopReason::Exited(exit_code.unwrap_or(0)),
+                        frame: None,
+                    });
+                }
+                "stopped" => {
+                    self.thread = body["threadId"].as_i64().or(self.thread);
+                    let thread = self.current_thread()?;
+                    let trace = self.connection.request("stackTrace", json!({ "threadId": thread, "startFrame": 0, "levels": 1 }))?;
+                    let frame = trace["stackFrames"].get(0).map(|frame| Self::frame_from(frame, 0));
+                    let text = body["text"].as_str().or(body["description"].as_str()).unwrap_or_default().to_string();
+                    let reason = match body["reason"].as_str().unwrap_or_default() {
+                        "breakpoint" | "function breakpoint" => {
+                            let hit = body["hitBreakpointIds"][0].as_i64();
+                            let request = self.requests.iter_mut().find(|request| hit.is_some() && request.adapter_id == hit);
+                            match request {
+                                Some(request) => {
+                                    request.breakpoint.hits += 1;
+                                    StopReason::Breakpoint(request.breakpoint.number)
+                                }
+                                None => StopReason::Breakpoint(0),
+                            }
+                        }
+                        "step" if resume == Some(Resume::StepOut) => StopReason::FunctionFinished(None),
+                        "step" => StopReason::Step,
+                        "pause" => StopReason::Interrupted,
+                        "exception" | "signal" => StopReason::Signal(text.clone(), body["description"].as_str().unwrap_or_default().to_string()),
+                        other => StopReason::Other(other.to_string()),
+                    };
+                    self.refresh_table();
+                    return Ok(Stop { reason, frame });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/**
+ * Parses a breakpoint location
+ *
+ * @param location - `file:line` or a function name
+ * @param directory - Directory relative file names are resolved against
+ * @return Target - Breakpoint target
+ */
+fn parse_target(location: &str, directory: &Path) -> Target {
+    if let Some((file, line)) = location.rsplit_once(':') {
+        if let Ok(line) = line.parse() {
+            return Target::Line(directory.join(file), line);
+        }
+    }
+    Target::Function(location.to_string())
+}
+
+impl DebugBackend for DapBackend {
+    fn name(&self) -> &str {
+        "lldb-dap"
+    }
+
+    fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    fn insert_breakpoint(&mut self, location: &str) -> Result<Breakpoint> {
+        let target = parse_target(location, &self.directory);
+        let number = self.next_number;
+        self.next_number += 1;
+        let (function, file, line) = match &target {
+            Target::Line(_, line) => (None, Some(location.rsplit_once(':').map_or(location, |(file, _)| file).to_string()), Some(*line)),
+            Target::Function(name) => (Some(name.clone()), None, None),
+        };
+        self.requests.push(Request {
+            target: target.clone(),
+            adapter_id: None,
+            breakpoint: Breakpoint {
+                number,
+                location: location.to_string(),
+                function,
+                file,
+                line,
+                enabled: true,
+                hits: 0,
+                pending: true,
+            },
+        });
+        if self.launched {
+            if let Err(error) = self.sync_breakpoints(Some(&target)) {
+                self.requests.pop();
+                return Err(error);
+            }
+        } else {
+            self.refresh_table();
+        }
+        Ok(self.requests[self.requests.len() - 1].breakpoint.clone())
+    }
+
+    fn delete_breakpoint(&mut self, number: Option<u32>) -> Result<()> {
+        let removed: Vec<Target> = match number {
+            Some(number) => {
+                let position = self
+                    .requests
+                    .iter()
+                    .position(|request| request.breakpoint.number == number)
+                    .ok_or_else(|| anyhow::anyhow!("No breakpoint number {}.", number))?;
+                vec![self.requests.remove(position).target]
+            }
+            None => self.requests.drain(..).map(|request| request.target).collect(),
+        };
+        if self.launched {
+            let mut synced: Vec<Target> = Vec::new();
+            for target in removed {
+                let key = match &target {
+                    Target::Line(path, _) => Target::Line(path.clone(), 0),
+                    Target::Function(_) => Target::Function(String::new()),
+                };
+                if !synced.contains(&key) {
+                    self.sync_breakpoints(Some(&target))?;
+                    synced.push(key);
+                }
+            }
+        }
+        self.refresh_table();
+        Ok(())
+    }
+
+    /**
+     * プログラムを起動する関数です
+     *
+     * DAPでは一度終了したプログラムを同じセッションで再起動できない
+     * ため、起動済みの場合はアダプタを起動し直します。launchを送って
+     * initializedイベントを待ち、ブレークポイントを送ってから
+     * configurationDoneでプログラムを開始します。launchの応答は
+     * configurationDoneの後に届くアダプタもあるため待ちません。
+     */
+    fn run(&mut self, arguments: &[String], sink: &mut dyn FnMut(&str)) -> Result<Stop> {
+        if self.launched {
+            self.connection = Connection::open(&self.adapter, &self.directory)?;
+            self.launched = false;
+            for request in &mut self.requests {
+                request.adapter_id = None;
+                request.breakpoint.pending = true;
+            }
+        }
+        self.thread = None;
+        self.frame_ids.clear();
+        self.connection.send(
+            "launch",
+            json!({
+                "program": self.program,
+                "args": arguments,
+                "cwd": self.directory,
+                "stopOnEntry": false,
+            }),
+        )?;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        loop {
+            if let Some(error) = self.connection.late_error.take() {
+                return Err(anyhow::anyhow!("{}", error));
+            }
+            if Instant::now() > deadline {
+                return Err(anyhow::anyhow!("the debug adapter did not initialize"));
+            }
+            match self.connection.next_event(POLL_INTERVAL)? {
+                Some(event) if event["event"] == "initialized" => break,
+                Some(event) if event["event"] == "output" => {
+                    if matches!(event["body"]["category"].as_str(), Some("stdout") | Some("stderr")) {
+                        sink(event["body"]["output"].as_str().unwrap_or_default());
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.launched = true;
+        self.sync_breakpoints(None)?;
+        self.connection.request("configurationDone", json!({}))?;
+        self.wait_for_stop(None, sink)
+    }
+
+    fn resume(&mut self, resume: Resume, sink: &mut dyn FnMut(&str)) -> Result<Stop> {
+        let thread = self.current_thread()?;
+        let command = match resume {
+            Resume::Continue => "continue",
+            Resume::StepInto => "stepIn",
+            Resume::StepOver => "next",
+            Resume::StepOut => "stepOut",
+        };
+        self.connection.request(command, json!({ "threadId": thread }))?;
+        self.frame_ids.clear();
+        self.wait_for_stop(Some(resume), sink)
+    }
+
+    fn backtrace(&mut self) -> Result<Vec<Frame>> {
+        let thread = self.current_thread()?;
+        let body = self.connection.request("stackTrace", json!({ "threadId": thread, "startFrame": 0 }))?;
+        let frames = body["stackFrames"].as_array().cloned().unwrap_or_default();
+        self.frame_ids = frames.iter().filter_map(|frame| frame["id"].as_i64()).collect();
+        Ok(frames.iter().enumerate().map(|(level, frame)| Self::frame_from(frame, level)).collect())
+    }
+
+    fn locals(&mut self, frame: usize) -> Result<Vec<Variable>> {
+        if self.frame_ids.len() <= frame {
+            self.backtrace()?;
+        }
+        let frame_id = *self.frame_ids.get(frame).ok_or_else(|| anyhow::anyhow!("No frame at level {}.", frame))?;
+        let body = self.connection.request("scopes", json!({ "frameId": frame_id }))?;
+        let mut variables = Vec::new();
+        for scope in body["scopes"].as_array().cloned().unwrap_or_default() {
+            let hint = scope["presentationHint"].as_str().unwrap_or_default();
+            let name = scope["name"].as_str().unwrap_or_default();
+            let argument = hint == "arguments" || name == "Arguments";
+            if !(argument || hint == "locals" || name == "Locals") {
+                continue;
+            }
+            let reference = scope["variablesReference"].as_i64().unwrap_or(0);
+            let listed = self.connection.request("variables", json!({ "variablesReference": reference }))?;
+            for variable in listed["variables"].as_array().cloned().unwrap_or_default() {
+                variables.push(Variable {
+                    name: variable["name"].as_str().unwrap_or_default().to_string(),
+                    value: variable["value"].as_str().unwrap_or_default().to_string(),
+                    type_name: variable["type"].as_str().map(str::to_string),
+                    argument,
+                });
+            }
+        }
+        Ok(variables)
+    }
+
+    fn evaluate(&mut self, expression: &str, frame: usize) -> Result<String> {
+        let mut arguments = json!({ "expression": expression, "context": "watch" });
+        if self.thread.is_some() {
+            if self.frame_ids.len() <= frame {
+                self.backtrace()?;
+            }
+            if let Some(frame_id) = self.frame_ids.get(frame) {
+                arguments["frameId"] = json!(frame_id);
+            }
+        }
+        let body = self.connection.request("evaluate", arguments)?;
+        Ok(body["result"].as_str().unwrap_or_default().to_string())
+    }
+}
@@ -0,0 +1,718 @@
+/*!
+ * @file dashboard.rs
+ * @brief System and shell dashboard for the status builtin
+ *
+ * This module samples /proc for per-core CPU load, memory, swap, load
+ * average and the busiest processes, asks statvfs for the usage of
+ * each mounted filesystem and combines them with the shell's jobs and
+ * git branch. The dashboard refreshes in place with ratatui gauges and
+ * charts, or is printed once as text or JSON.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file dashboard.rs
+ * @description Dashboard sampling, disk usage from /proc/mounts and
+ * statvfs, the JSON and text reports and the full-screen view.
+ */
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{BarChart, Block, Borders, Gauge, LineGauge, Paragraph, Row, Sparkline, Table};
+use ratatui::Terminal;
+
+use crate::shell::commands::listing::human_size;
+use crate::shell::commands::procfs::{self, CpuTimes, MemoryInfo, SystemInfo};
+use crate::shell::commands::top::{format_uptime, ScreenGuard};
+
+/// Number of total CPU samples kept for the history chart
+const HISTORY_LENGTH: usize = 240;
+
+/// Number of processes listed
+const PROCESS_COUNT: usize = 8;
+
+/// Filesystem types that hold user data rather than kernel state
+const DISK_TYPES: [&str; 14] = [
+    "ext2", "ext3", "ext4", "xfs", "btrfs", "zfs", "f2fs", "vfat", "exfat", "ntfs", "ntfs3", "fuseblk", "overlay", "nfs",
+];
+
+/**
+ * Usage of one mounted filesystem
+ */
+#[derive(Debug, Clone)]
+pub struct DiskUsage {
+    /// Mount point
+    pub mount: String,
+    /// Device or source
+    pub device: String,
+    /// Filesystem type
+    pub fs_type: String,
+    /// Size in bytes
+    pub total: u64,
+    /// Used bytes
+    pub used: u64,
+    /// Bytes available to unprivileged users
+    pub available: u64,
+}
+
+impl DiskUsage {
+    /**
+     * Used share as df computes it
+     *
+     * @return f64 - Used divided by used plus available, 0 to 1
+     */
+    pub fn ratio(&self) -> f64 {
+        let usable = self.used + self.available;
+        if usable == 0 { 0.0 } else { self.used as f64 / usable as f64 }
+    }
+}
+
+/**
+ * Reads the usage of a filesystem with statvfs
+ *
+ * @param path - Mount point
+ * @return Option<(u64, u64, u64)> - Total, used and available bytes
+ */
+fn statvfs(path: &str) -> Option<(u64, u64, u64)> {
+    let path = std::ffi::CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    let total = stat.f_blocks as u64 * block;
+    let free = stat.f_bfree as u64 * block;
+    Some((total, total.saturating_sub(free), stat.f_bavail as u64 * block))
+}
+
+/**
+ * マウントされたファイルシステムの使用量を集める
+ *
+ * /proc/mounts を読み、ユーザーデータを置く種類（ext4、xfs、btrfs、
+ * overlay など）のファイルシステムだけを statvfs で調べます。
+ * 同じデバイスが複数の場所にマウントされている場合（バインド
+ * マウントなど）は最初のマウントポイントだけを使います。
+ * マウントポイントのエスケープ（\040 など）は元の文字に戻します。
+ *
+ * @return Vec<DiskUsage> - マウントポイント順の使用量
+ */
+pub fn disk_usage() -> Vec<DiskUsage> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+
+    let mut seen = HashSet::new();
+    let mut disks = Vec::new();
+    for line in mounts.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [device, mount, fs_type, ..] = fields[..] else {
+            continue;
+        };
+        if !DISK_TYPES.contains(&fs_type) || !seen.insert(device.to_string()) {
+            continue;
+        }
+        let mount = unescape_mount(mount);
+        let Some((total, used, available)) = statvfs(&mount) else {
+            continue;
+        };
+        if total == 0 {
+            continue;
+        }
+        disks.push(DiskUsage {
+            mount,
+            device: device.to_string(),
+            fs_type: fs_type.to_string(),
+            total,
+            used,
+            available,
+        });
+    }
+    disks.sort_by(|a, b| a.mount.cmp(&b.mount));
+    disks
+}
+
+/**
+ * Decodes the octal escapes /proc/mounts uses for spaces and tabs
+ *
+ * @param mount - Mount point as written in /proc/mounts
+ * @return String - Mount point
+ */
+fn unescape_mount(mount: &str) -> String {
+    let mut text = String::new();
+    let mut rest = mount;
+    while let Some(index) = rest.find('\\') {
+        text.push_str(&rest[..index]);
+        let code = rest.get(index + 1..index + 4).and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                text.push(code as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                text.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/**
+ * Process using the most CPU over the last interval
+ */
+#[derive(Debug, Clone)]
+pub struct ProcessLoad {
+    /// Process ID
+    pub pid: i32,
+    /// Command name
+    pub command: String,
+    /// CPU usage over the interval, in percent of one CPU
+    pub cpu: f64,
+    /// Resident memory in KiB
+    pub rss: u64,
+    /// Resident memory in percent of the total
+    pub memory: f64,
+}
+
+/**
+ * Background job of the shell
+ */
+#[derive(Debug, Clone)]
+pub struct JobSummary {
+    /// Job ID
+    pub id: u32,
+    /// Process ID
+    pub pid: u32,
+    /// State name
+    pub state: String,
+    /// Command line
+    pub command: String,
+}
+
+/**
+ * Shell state shown on the dashboard
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ShellInfo {
+    /// User name
+    pub user: String,
+    /// Host name
+    pub host: String,
+    /// Current directory
+    pub directory: PathBuf,
+    /// Branch with git's prompt flags, when the directory is in a repository
+    pub git: Option<String>,
+    /// Background jobs
+    pub jobs: Vec<JobSummary>,
+}
+
+/**
+ * Everything one dashboard screen shows
+ */
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    /// Time of the sample
+    pub time: chrono::DateTime<chrono::Local>,
+    /// Seconds since boot
+    pub uptime: f64,
+    /// 1, 5 and 15 minute load averages
+    pub load: [f64; 3],
+    /// Busy share of all CPUs, in percent
+    pub cpu: f64,
+    /// Busy share of each core, in percent
+    pub cores: Vec<f64>,
+    /// Memory and swap counters
+    pub memory: MemoryInfo,
+    /// Mounted filesystems
+    pub disks: Vec<DiskUsage>,
+    /// Busiest processes
+    pub processes: Vec<ProcessLoad>,
+}
+
+/**
+ * Busy share of a CPU over an interval
+ *
+ * @param delta - Ticks spent in each state during the interval
+ * @return f64 - Percentage of time not idle or waiting for I/O
+ */
+fn busy_percent(delta: &CpuTimes) -> f64 {
+    let total = delta.total();
+    if total == 0 {
+        return 0.0;
+    }
+    (total - delta.idle - delta.iowait) as f64 * 100.0 / total as f64
+}
+
+/**
+ * Takes samples of /proc and turns consecutive pairs into dashboards
+ */
+pub struct Sampler {
+    /// System counters of the previous sample
+    system: SystemInfo,
+    /// CPU ticks of each process at the previous sample
+    ticks: HashMap<i32, u64>,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sampler {
+    /**
+     * Takes the first sample
+     *
+     * @return Sampler - Sampler ready for sample
+     */
+    pub fn new() -> Self {
+        let system = SystemInfo::read();
+        let ticks = procfs::read_processes(&system).iter().map(|process| (process.pid, process.cpu_ticks())).collect();
+        Sampler { system, ticks }
+    }
+
+    /**
+     * 新しいサンプルを取り、前回からの差分でダッシュボードを作る
+     *
+     * CPU 使用率は /proc/stat の合計行とコアごとの行の差分から、
+     * プロセスの CPU 使用率は utime と stime の差分を経過時間で
+     * 割って求めます（1 CPU を 100% とします）。前回のサンプルに
+     * ないプロセスは起動からの累計を使います。CPU 使用率の高い順に
+     * PROCESS_COUNT 個のプロセスを載せます。
+     *
+     * @return Dashboard - 画面の内容
+     */
+    pub fn sample(&mut self) -> Dashboard {
+        let system = SystemInfo::read();
+        let processes = procfs::read_processes(&system);
+        let interval = (system.uptime - self.system.uptime).max(0.01);
+
+        let cores = system
+            .cpus
+            .iter()
+            .enumerate()
+            .map(|(index, core)| busy_percent(&core.since(&self.system.cpus.get(index).copied().unwrap_or_default())))
+            .collect();
+        let mut loads: Vec<ProcessLoad> = processes
+            .iter()
+            .map(|process| {
+                let ticks = process.cpu_ticks().saturating_sub(self.ticks.get(&process.pid).copied().unwrap_or(0));
+                ProcessLoad {
+                    pid: process.pid,
+                    command: process.comm.clone(),
+                    cpu: ticks as f64 / system.clock_ticks as f64 / interval * 100.0,
+                    rss: process.rss,
+                    memory: system.memory_percent(process),
+                }
+            })
+            .collect();
+        loads.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.rss.cmp(&a.rss)));
+        loads.truncate(PROCESS_COUNT);
+
+        let dashboard = Dashboard {
+            time: chrono::Local::now(),
+            uptime: system.uptime,
+            load: system.load,
+            cpu: busy_percent(&system.cpu.since(&self.system.cpu)),
+            cores,
+            memory: system.memory,
+            disks: disk_usage(),
+            processes: loads,
+        };
+        self.ticks = processes.iter().map(|process| (process.pid, process.cpu_ticks())).collect();
+        self.system = system;
+        dashboard
+    }
+}
+
+/**
+ * Rounds a percentage to one decimal for reports
+ *
+ * @param value - Percentage
+ * @return f64 - Rounded value
+ */
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/**
+ * Dashboard as a JSON document for `status --json`
+ *
+ * All sizes are in bytes; the KiB counters from /proc are
+ * converted so memory, swap, RSS and disk figures compare directly.
+ *
+ * @param dashboard - Sampled system state
+ * @param shell - Shell state
+ * @return Value - JSON object
+ */
+pub fn to_json(dashboard: &Dashboard, shell: &ShellInfo) -> Value {
+    let memory = &dashboard.memory;
+    let bytes = |kib: u64| kib * 1024;
+    json!({
+        "time": dashboard.time.to_rfc3339(),
+        "user": shell.user,
+        "host": shell.host,
+        "directory": shell.directory.display().to_string(),
+        "git": shell.git,
+        "uptime": dashboard.uptime.round() as u64,
+        "load": dashboard.load,
+        "cpu": {
+            "total": round(dashboard.cpu),
+            "cores": dashboard.cores.iter().map(|core| round(*core)).collect::<Vec<f64>>(),
+        },
+        "memory": {
+            "total": bytes(memory.total),
+            "used": bytes(memory.used()),
+            "available": bytes(memory.available),
+            "buffers": bytes(memory.buffers),
+            "cached": bytes(memory.cached),
+        },
+        "swap": {
+            "total": bytes(memory.swap_total),
+            "used": bytes(memory.swap_total.saturating_sub(memory.swap_free)),
+        },
+        "disks": dashboard.disks.iter().map(|disk| json!({
+            "mount": disk.mount,
+            "device": disk.device,
+            "type": disk.fs_type,
+            "total": disk.total,
+            "used": disk.used,
+            "available": disk.available,
+        })).collect::<Vec<Value>>(),
+        "processes": dashboard.processes.iter().map(|process| json!({
+            "pid": process.pid,
+            "command": process.command,
+            "cpu": round(process.cpu),
+            "memory": round(process.memory),
+            "rss": bytes(process.rss),
+        })).collect::<Vec<Value>>(),
+        "jobs": shell.jobs.iter().map(|job| json!({
+            "id": job.id,
+            "pid": job.pid,
+            "state": job.state,
+            "command": job.command,
+        })).collect::<Vec<Value>>(),
+    })
+}
+
+/**
+ * Draws a text bar for the plain report
+ *
+ * @param ratio - Filled share, 0 to 1
+ * @param width - Bar width in cells
+ * @return String - e.g. `[#####.....]`
+ */
+fn text_bar(ratio: f64, width: usize) -> String {
+    let filled = ((ratio.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    format!("[{}{}]", "#".repeat(filled), ".".repeat(width - filled))
+}
+
+/**
+ * Dashboard as plain text, for output that is not a terminal
+ *
+ * @param dashboard - Sampled system state
+ * @param shell - Shell state
+ * @return String - Report
+ */
+pub fn render_text(dashboard: &Dashboard, shell: &ShellInfo) -> String {
+    let memory = &dashboard.memory;
+    let mut output = format!("{}@{}  {}", shell.user, shell.host, shell.directory.display());
+    if let Some(git) = &shell.git {
+        output.push_str(&format!("  ({})", git));
+    }
+    output.push_str(&format!(
+        "\n{}  up {}  load average: {:.2}, {:.2}, {:.2}\n\n",
+        dashboard.time.format("%H:%M:%S"),
+        format_uptime(dashboard.uptime).trim(),
+        dashboard.load[0],
+        dashboard.load[1],
+        dashboard.load[2]
+    ));
+
+    output.push_str(&format!("CPU   {} {:5.1}%\n", text_bar(dashboard.cpu / 100.0, 30), dashboard.cpu));
+    for (index, core) in dashboard.cores.iter().enumerate() {
+        output.push_str(&format!("cpu{:<2} {} {:5.1}%\n", index, text_bar(core / 100.0, 30), core));
+    }
+    let memory_ratio = if memory.total > 0 { memory.used() as f64 / memory.total as f64 } else { 0.0 };
+    output.push_str(&format!(
+        "Mem   {} {} / {}\n",
+        text_bar(memory_ratio, 30),
+        human_size(memory.used() * 1024),
+        human_size(memory.total * 1024)
+    ));
+    let swap_used = memory.swap_total.saturating_sub(memory.swap_free);
+    let swap_ratio = if memory.swap_total > 0 { swap_used as f64 / memory.swap_total as f64 } else { 0.0 };
+    output.push_str(&format!("Swap  {} {} / {}\n", text_bar(swap_ratio, 30), human_size(swap_used * 1024), human_size(memory.swap_total * 1024)));
+
+    if !dashboard.disks.is_empty() {
+        output.push('\n');
+        let width = dashboard.disks.iter().map(|disk| disk.mount.len()).max().unwrap_or(0);
+        for disk in &dashboard.disks {
+            output.push_str(&format!(
+                "{:<width$}  {} {:3.0}%  {} / {}\n",
+                disk.mount,
+                text_bar(disk.ratio(), 20),
+                disk.ratio() * 100.0,
+                human_size(disk.used),
+                human_size(disk.total),
+                width = width
+            ));
+        }
+    }
+
+    output.push_str(&format!("\n{:>7}  {:>5}  {:>5}  {:>6}  COMMAND\n", "PID", "%CPU", "%MEM", "RES"));
+    for process in &dashboard.processes {
+        output.push_str(&format!(
+            "{:>7}  {:>5.1}  {:>5.1}  {:>6}  {}\n",
+            process.pid,
+            process.cpu,
+            process.memory,
+            human_size(process.rss * 1024),
+            process.command
+        ));
+    }
+
+    if !shell.jobs.is_empty() {
+        output.push('\n');
+        for job in &shell.jobs {
+            output.push_str(&format!("[{}] {} {} {}\n", job.id, job.state, job.pid, job.command));
+        }
+    }
+    output
+}
+
+/**
+ * Gauge color by how full a resource is
+ *
+ * @param ratio - Used share, 0 to 1
+ * @return Color - Green, yellow or red
+ */
+fn level_color(ratio: f64) -> Color {
+    if ratio >= 0.9 {
+        Color::Red
+    } else if ratio >= 0.7 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+/**
+ * ダッシュボードの画面を描画する
+ *
+ * 上から順に、ユーザー、ディレクトリ、git ブランチ、稼働時間、
+ * ロードアベレージの見出し、CPU 全体の履歴のスパークラインと
+ * コアごとの棒グラフ、メモリとスワップのゲージ、ファイル
+ * システムごとのラインゲージ、CPU 使用率の高いプロセスの表と
+ * シェルのジョブ一覧、キー操作の説明を描きます。
+ *
+ * @param frame - 描画先のフレーム
+ * @param dashboard - 画面の内容
+ * @param shell - シェルの状態
+ * @param history - CPU 全体の使用率の履歴
+ */
+fn draw(frame: &mut ratatui::Frame, dashboard: &Dashboard, shell: &ShellInfo, history: &VecDeque<u64>) {
+    let disk_height = dashboard.disks.len().clamp(1, 6) as u16 + 2;
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(8),
+            Constraint::Length(3),
+            Constraint::Length(disk_height),
+            Constraint::Min(4),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+
+    let mut header = format!("{}@{}  {}", shell.user, shell.host, shell.directory.display());
+    if let Some(git) = &shell.git {
+        header.push_str(&format!("  ({})", git));
+    }
+    header.push_str(&format!(
+        "  up {}  load {:.2} {:.2} {:.2}  {}",
+        format_uptime(dashboard.uptime).trim(),
+        dashboard.load[0],
+        dashboard.load[1],
+        dashboard.load[2],
+        dashboard.time.format("%H:%M:%S")
+    ));
+    frame.render_widget(Paragraph::new(header).style(Style::default().add_modifier(Modifier::BOLD)), areas[0]);
+
+    let cpu_areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(areas[1]);
+    let history: Vec<u64> = history.iter().copied().collect();
+    let width = cpu_areas[0].width.saturating_sub(2) as usize;
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title(format!(" CPU {:.1}% ", dashboard.cpu)))
+            .data(&history[history.len().saturating_sub(width)..])
+            .max(100)
+            .style(Style::default().fg(level_color(dashboard.cpu / 100.0))),
+        cpu_areas[0],
+    );
+    let labels: Vec<String> = (0..dashboard.cores.len()).map(|index| index.to_string()).collect();
+    let bars: Vec<(&str, u64)> = labels.iter().zip(&dashboard.cores).map(|(label, core)| (label.as_str(), core.round() as u64)).collect();
+    let inner = cpu_areas[1].width.saturating_sub(2);
+    let bar_width = (inner / bars.len().max(1) as u16).saturating_sub(1).clamp(1, 5);
+    frame.render_widget(
+        BarChart::default()
+            .block(Block::default().borders(Borders::ALL).title(" Cores "))
+            .data(&bars)
+            .max(100)
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(Color::Cyan))
+            .value_style(Style::default().fg(Color::Black).bg(Color::Cyan)),
+        cpu_areas[1],
+    );
+
+    let memory = &dashboard.memory;
+    let memory_areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(areas[2]);
+    let memory_ratio = if memory.total > 0 { memory.used() as f64 / memory.total as f64 } else { 0.0 };
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title(" Memory "))
+            .gauge_style(Style::default().fg(level_color(memory_ratio)))
+            .ratio(memory_ratio.clamp(0.0, 1.0))
+            .label(format!("{} / {}", human_size(memory.used() * 1024), human_size(memory.total * 1024))),
+        memory_areas[0],
+    );
+    let swap_used = memory.swap_total.saturating_sub(memory.swap_free);
+    let swap_ratio = if memory.swap_total > 0 { swap_used as f64 / memory.swap_total as f64 } else { 0.0 };
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title(" Swap "))
+            .gauge_style(Style::default().fg(level_color(swap_ratio)))
+            .ratio(swap_ratio.clamp(0.0, 1.0))
+            .label(format!("{} / {}", human_size(swap_used * 1024), human_size(memory.swap_total * 1024))),
+        memory_areas[1],
+    );
+
+    let disk_block = Block::default().borders(Borders::ALL).title(" Disks ");
+    let disk_area = disk_block.inner(areas[3]);
+    frame.render_widget(disk_block, areas[3]);
+    let mount_width = dashboard.disks.iter().map(|disk| disk.mount.len()).max().unwrap_or(0).min(24);
+    for (index, disk) in dashboard.disks.iter().take(disk_area.height as usize).enumerate() {
+        let line = Rect::new(disk_area.x, disk_area.y + index as u16, disk_area.width, 1);
+        frame.render_widget(
+            LineGauge::default()
+                .gauge_style(Style::default().fg(level_color(disk.ratio())))
+                .line_set(ratatui::symbols::line::THICK)
+                .ratio(disk.ratio().clamp(0.0, 1.0))
+                .label(format!(
+                    "{:<width$} {:>3.0}% {:>6}/{:<6}",
+                    disk.mount,
+                    disk.ratio() * 100.0,
+                    human_size(disk.used),
+                    human_size(disk.total),
+                    width = mount_width
+                )),
+            line,
+        );
+    }
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(areas[4]);
+    let rows: Vec<Row> = dashboard
+        .processes
+        .iter()
+        .map(|process| {
+            Row::new(vec![
+                process.pid.to_string(),
+                format!("{:.1}", process.cpu),
+                format!("{:.1}", process.memory),
+                human_size(process.rss * 1024),
+                process.command.clone(),
+            ])
+        })
+        .collect();
+    let widths = [Constraint::Length(7), Constraint::Length(6), Constraint::Length(6), Constraint::Length(6), Constraint::Min(8)];
+    frame.render_widget(
+        Table::new(rows)
+            .header(Row::new(vec!["PID", "%CPU", "%MEM", "RES", "COMMAND"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .widths(&widths)
+            .block(Block::default().borders(Borders::ALL).title(" Processes ")),
+        bottom[0],
+    );
+    let jobs = if shell.jobs.is_empty() {
+        "No background jobs".to_string()
+    } else {
+        shell.jobs.iter().map(|job| format!("[{}] {} {}", job.id, job.state, job.command)).collect::<Vec<String>>().join("\n")
+    };
+    frame.render_widget(Paragraph::new(jobs).block(Block::default().borders(Borders::ALL).title(" Jobs ")), bottom[1]);
+
+    frame.render_widget(Paragraph::new("q quit").style(Style::default().add_modifier(Modifier::DIM)), areas[5]);
+}
+
+/**
+ * ダッシュボードを全画面で表示し続ける
+ *
+ * 端末を代替画面の raw モードに切り替え、delay の間隔でサンプルを
+ * 取り直して画面をその場で更新します。更新の合間はキー入力を待ち、
+ * q、Esc、Ctrl+C で終了します。端末の状態はエラーの場合も
+ * ScreenGuard が元に戻します。
+ *
+ * @param shell - シェルの状態
+ * @param delay - 更新の間隔
+ * @param iterations - この回数だけ更新したら終了（None なら q まで）
+ * @return Result<()> - 成功またはエラー
+ */
+pub fn run_interactive(shell: &ShellInfo, delay: Duration, iterations: Option<u64>) -> Result<()> {
+    let _screen = ScreenGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    terminal.clear()?;
+
+    let mut sampler = Sampler::new();
+    std::thread::sleep(Duration::from_millis(200));
+    let mut history = VecDeque::with_capacity(HISTORY_LENGTH);
+    let mut count = 0;
+
+    loop {
+        let dashboard = sampler.sample();
+        if history.len() == HISTORY_LENGTH {
+            history.pop_front();
+        }
+        history.push_back(dashboard.cpu.round() as u64);
+        count += 1;
+
+        let deadline = Instant::now() + delay;
+        loop {
+            terminal.draw(|frame| draw(frame, &dashboard, shell, &history))?;
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() || !event::poll(timeout)? {
+                break;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                _ => {}
+            }
+        }
+
+        if iterations.is_some_and(|iterations| count >= iterations) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/*!
+ * @file data.rs
+ * @brief Structured data commands
+ *
+ * This module implements the builtins that work on records emitted
+ * by other builtins: filtering, projection, sorting and conversion
+ * between records and JSON or CSV text.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file data.rs
+ * @description Structured data commands including where, select,
+ * sort-by, to and from.
+ */
+
+use anyhow::Result;
+use crate::shell::parser::ParsedCommand;
+use crate::shell::Shell;
+use crate::shell::commands::{CommandHandler, CommandResult};
+use crate::shell::commands::records::{Condition, Table};
+
+/**
+ * Reads the records given to a structured command
+ *
+ * Records emitted by the previous builtin are used as they are;
+ * otherwise piped text is accepted when it is JSON.
+ *
+ * @param shell - Shell instance
+ * @return Result<Table> - Input records or error
+ */
+fn input_records(shell: &mut Shell) -> Result<Table> {
+    let text = shell.take_pipeline_input();
+    if let Some(records) = shell.take_pipeline_records() {
+        return Ok(records);
+    }
+    match text {
+        Some(text) if !text.trim().is_empty() => Table::from_json(&text)
+            .map_err(|error| anyhow::anyhow!("input is not records or JSON ({})", error)),
+        _ => Err(anyhow::anyhow!("expected records from a pipeline, e.g. 'ls | where size -gt 1000'")),
+    }
+}
+
+/**
+ * Produces the result of a command whose output is records
+ *
+ * The text is the rendered table; the records go on to the next
+ * pipeline stage when there is one.
+ *
+ * @param shell - Shell instance
+ * @param records - Output records
+ * @return CommandResult - Rendered table
+ */
+fn records_result(shell: &mut Shell, records: Table) -> CommandResult {
+    let output = records.render();
+    if !shell.output_is_terminal() {
+        shell.emit_records(records);
+    }
+    CommandResult {
+        output,
+        exit_code: 0,
+    }
+}
+
+/**
+ * レコードを絞り込むwhereコマンドです
+ *
+ * 「列 演算子 値」の条件に一致する行だけを残します。数値の列は
+ * 数値として、それ以外は文字列として比較します。シェルの
+ * リダイレクトと区別するため、大小比較には-lt、-le、-gt、-geを
+ * 使います。
+ */
+pub struct WhereCommand;
+
+impl CommandHandler for WhereCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut records = input_records(shell)?;
+        let condition = Condition::parse(&command.args, &records)?;
+        records.retain(&condition);
+        Ok(records_result(shell, records))
+    }
+
+    fn help(&self) -> &str {
+        "where <column> <operator> <value> - Keep records matching a condition\n\
+         Operators:\n\
+         ==, !=           Equal, not equal (also -eq, -ne)\n\
+         -lt, -le         Less than, less or equal ('<' and '<=' when quoted)\n\
+         -gt, -ge         Greater than, greater or equal ('>' and '>=' when quoted)\n\
+         =~, !~           Matches, does not match a regular expression\n\
+         Example: ls | where size -gt 4096 | sort-by size"
+    }
+
+    fn name(&self) -> &str {
+        "where"
+    }
+}
+
+/**
+ * Select command
+ *
+ * Keeps the named columns of the piped records, in the given order.
+ */
+pub struct SelectCommand;
+
+impl CommandHandler for SelectCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        if command.args.is_empty() {
+            return Err(anyhow::anyhow!("Usage: select <column>..."));
+        }
+        let records = input_records(shell)?.select(&command.args)?;
+        Ok(records_result(shell, records))
+    }
+
+    fn help(&self) -> &str {
+        "select <column>... - Keep only the given columns of piped records\n\
+         Example: jobs | select id command"
+    }
+
+    fn name(&self) -> &str {
+        "select"
+    }
+}
+
+/**
+ * Sort-by command
+ *
+ * Sorts the piped records by one or more columns.
+ */
+pub struct SortByCommand;
+
+impl CommandHandler for SortByCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut reverse = false;
+        let mut columns = Vec::new();
+        for arg in &command.args {
+            match arg.as_str() {
+                "-r" | "--reverse" => reverse = true,
+                _ => columns.push(arg.clone()),
+            }
+        }
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!("Usage: sort-by [-r] <column>..."));
+        }
+
+        let mut records = input_records(shell)?;
+        records.sort_by(&columns, reverse)?;
+        Ok(records_result(shell, records))
+    }
+
+    fn help(&self) -> &str {
+        "sort-by [-r] <column>... - Sort piped records by columns\n\
+         Numbers sort numerically, text bytewise; empty values last.\n\
+         Options:\n\
+         -r, --reverse  Sort in descending order"
+    }
+
+    fn name(&self) -> &str {
+        "sort-by"
+    }
+}
+
+/**
+ * To command
+ *
+ * Converts piped records to JSON or CSV text.
+ */
+pub struct ToCommand;
+
+impl CommandHandler for ToCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let format = command.args.first().ok_or_else(|| anyhow::anyhow!("Usage: to <json|csv> [options]"))?;
+        let mut raw = false;
+        let mut header = true;
+        let mut separator = ',';
+
+        let mut args = command.args[1..].iter();
+        while let Some(arg) = args.next() {
+            match (format.as_str(), arg.as_str()) {
+                ("json", "-r" | "--raw") => raw = true,
+                ("csv", "--noheaders") => header = false,
+                ("csv", "-s" | "--separator") => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("option '{}' requires an argument", arg))?;
+                    let mut chars = value.chars();
+                    separator = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => return Err(anyhow::anyhow!("separator must be a single character")),
+                    };
+                }
+                _ => return Err(anyhow::anyhow!("unknown option '{}' for to {}", arg, format)),
+            }
+        }
+
+        let records = input_records(shell)?;
+        let output = match format.as_str() {
+            "json" => records.to_json(!raw),
+            "csv" => records.to_csv(separator, header),
+            other => return Err(anyhow::anyhow!("unknown format '{}' (expected json or csv)", other)),
+        };
+        Ok(CommandResult {
+            output,
+            exit_code: 0,
+        })
+    }
+
+    fn help(&self) -> &str {
+        "to <format> [options] - Convert piped records to text\n\
+         Formats:\n\
+         json [-r]          JSON array of objects (-r: on one line)\n\
+         csv [-s <char>]    Comma separated values with a header line\n\
+         \x20   [--noheaders]  Leave out the header line\n\
+         Example: ls | to json > files.json"
+    }
+
+    fn name(&self) -> &str {
+        "to"
+    }
+}
+
+/**
+ * From command
+ *
+ * Parses piped JSON text into records, so that JSON files and
+ * command output can be queried with where, select and sort-by.
+ */
+pub struct FromCommand;
+
+impl CommandHandler for FromCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        match command.args.first().map(String::as_str) {
+            Some("json") => {}
+            Some(other) => return Err(anyhow::anyhow!("unknown format '{}' (expected json)", other)),
+            None => return Err(anyhow::anyhow!("Usage: from json")),
+        }
+
+        let text = shell.take_pipeline_input().unwrap_or_default();
+        let records = Table::from_json(&text)?;
+        Ok(records_result(shell, records))
+    }
+
+    fn help(&self) -> &str {
+        "from json - Parse piped JSON into records\n\
+         An array of objects becomes one record per object; other values\n\
+         become a single 'value' column.\n\
+         Example: cat package.json | from json | select name version"
+    }
+
+    fn name(&self) -> &str {
+        "from"
+    }
+}
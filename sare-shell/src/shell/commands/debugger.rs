@@ -0,0 +1,862 @@
+/*!
+ * @file debugger.rs
+ * @brief Debug sessions driven through gdb or lldb-dap
+ *
+ * This module holds the debugger-independent side of the debug
+ * builtin: the frame, breakpoint and variable model both backends
+ * report in, the per-shell session with its program state and
+ * selected frame, and the rendering of stops and source context with
+ * syntax highlighting.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file debugger.rs
+ * @description Backend trait, session state, debugger discovery,
+ * gdb-style stop and frame descriptions and highlighted source
+ * listings.
+ */
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::shell::commands::dap::DapBackend;
+use crate::shell::commands::gdbmi::GdbBackend;
+use crate::shell::commands::records::Table;
+
+/**
+ * Stack frame
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Level, 0 for the innermost frame
+    pub level: usize,
+    /// Function name
+    pub function: String,
+    /// Argument names and values, when the debugger reports them
+    pub arguments: Vec<(String, String)>,
+    /// Source file as the compiler recorded it
+    pub file: Option<String>,
+    /// Absolute path of the source file
+    pub full_path: Option<PathBuf>,
+    /// Source line
+    pub line: Option<u32>,
+    /// Program counter
+    pub address: Option<String>,
+    /// Shared library of frames without source
+    pub library: Option<String>,
+}
+
+/**
+ * Breakpoint
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    /// Breakpoint number
+    pub number: u32,
+    /// Location as the user gave it
+    pub location: String,
+    /// Function the breakpoint resolved to
+    pub function: Option<String>,
+    /// Source file the breakpoint resolved to
+    pub file: Option<String>,
+    /// Source line the breakpoint resolved to
+    pub line: Option<u32>,
+    /// Whether the breakpoint is enabled
+    pub enabled: bool,
+    /// Number of times the breakpoint was hit
+    pub hits: u32,
+    /// Whether the location is not resolved yet
+    pub pending: bool,
+}
+
+/**
+ * Local variable or argument
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    /// Variable name
+    pub name: String,
+    /// Value as the debugger prints it
+    pub value: String,
+    /// Type name, when the debugger reports it
+    pub type_name: Option<String>,
+    /// Whether this is a function argument
+    pub argument: bool,
+}
+
+/**
+ * Why the program stopped
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// Hit the breakpoint with this number
+    Breakpoint(u32),
+    /// Finished a step
+    Step,
+    /// Returned from the function, with the returned value
+    FunctionFinished(Option<String>),
+    /// Received a signal (name, description)
+    Signal(String, String),
+    /// Stopped by Ctrl+C
+    Interrupted,
+    /// Exited with this code
+    Exited(i32),
+    /// Killed by a signal (name, description)
+    Signalled(String, String),
+    /// Any other reason the debugger reported
+    Other(String),
+}
+
+/**
+ * Stop of the program
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stop {
+    /// Why the program stopped
+    pub reason: StopReason,
+    /// Innermost frame, unless the program exited
+    pub frame: Option<Frame>,
+}
+
+/**
+ * How to resume a stopped program
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    /// Run until the next stop
+    Continue,
+    /// Step one line, entering calls
+    StepInto,
+    /// Step one line, stepping over calls
+    StepOver,
+    /// Run until the current function returns
+    StepOut,
+}
+
+/**
+ * Debugger driven as a child process
+ *
+ * `run` and `resume` block until the program stops, passing its
+ * output to `sink` as it arrives and interrupting it on Ctrl+C.
+ */
+pub trait DebugBackend {
+    /// Debugger name shown in the status
+    fn name(&self) -> &str;
+    /// Breakpoints as the debugger last reported them
+    fn breakpoints(&self) -> &[Breakpoint];
+    /// Sets a breakpoint at `file:line` or a function
+    fn insert_breakpoint(&mut self, location: &str) -> Result<Breakpoint>;
+    /// Deletes one breakpoint, or all of them
+    fn delete_breakpoint(&mut self, number: Option<u32>) -> Result<()>;
+    /// Starts or restarts the program
+    fn run(&mut self, arguments: &[String], sink: &mut dyn FnMut(&str)) -> Result<Stop>;
+    /// Resumes the stopped program
+    fn resume(&mut self, resume: Resume, sink: &mut dyn FnMut(&str)) -> Result<Stop>;
+    /// Stack of the stopped thread
+    fn backtrace(&mut self) -> Result<Vec<Frame>>;
+    /// Arguments and locals of a frame
+    fn locals(&mut self, frame: usize) -> Result<Vec<Variable>>;
+    /// Evaluates an expression in a frame
+    fn evaluate(&mut self, expression: &str, frame: usize) -> Result<String>;
+}
+
+/**
+ * State of the debugged program
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgramState {
+    /// Not run yet
+    NotStarted,
+    /// Stopped and can be inspected or resumed
+    Stopped(Box<Stop>),
+    /// Exited with this code, or was killed by a signal
+    Exited(Option<i32>),
+}
+
+/**
+ * Searches PATH for an executable
+ *
+ * @param name - Executable name
+ * @return Option<PathBuf> - Full path of the first match
+ */
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var("PATH").ok()?;
+    path.split(':').map(|directory| Path::new(directory).join(name)).find(|candidate| candidate.is_file())
+}
+
+/**
+ * Debug session of one program, kept by the shell between commands
+ */
+pub struct DebugSession {
+    /// Debugger process
+    backend: Box<dyn DebugBackend>,
+    /// Program being debugged
+    program: PathBuf,
+    /// Arguments for the next run
+    arguments: Vec<String>,
+    /// Directory the program runs in
+    directory: PathBuf,
+    /// State of the program
+    state: ProgramState,
+    /// Stack at the current stop, read on demand
+    frames: Vec<Frame>,
+    /// Level of the frame locals, print and list refer to
+    selected_frame: usize,
+}
+
+impl DebugSession {
+    /**
+     * デバッグセッションを開始する関数です
+     *
+     * プログラムは作業ディレクトリからの相対パス、なければPATHから
+     * 探します。デバッガの指定がない場合はgdbを優先し、なければ
+     * lldb-dap（古い名前のlldb-vscodeを含む）を使います。
+     *
+     * @param debugger - "gdb"、"lldb"、または自動選択のNone
+     * @param program - デバッグするプログラム
+     * @param arguments - 実行時の引数
+     * @param directory - 作業ディレクトリ
+     * @return Result<DebugSession> - セッションまたはエラー
+     */
+    pub fn start(debugger: Option<&str>, program: &str, arguments: Vec<String>, directory: &Path) -> Result<Self> {
+        let local: PathBuf = directory.join(program).components().collect();
+        let program_path = if program.contains('/') || local.is_file() {
+            local
+        } else {
+            find_in_path(program).ok_or_else(|| anyhow::anyhow!("{}: No such file or directory.", program))?
+        };
+        if !program_path.is_file() {
+            return Err(anyhow::anyhow!("{}: No such file or directory.", program));
+        }
+
+        let gdb = || find_in_path("gdb");
+        let lldb = || find_in_path("lldb-dap").or_else(|| find_in_path("lldb-vscode"));
+        let backend: Box<dyn DebugBackend> = match debugger {
+            Some("gdb") => {
+                let gdb = gdb().ok_or_else(|| anyhow::anyhow!("gdb is not installed"))?;
+                Box::new(GdbBackend::start(&gdb, &program_path, directory)?)
+            }
+            Some(_) => {
+                let adapter = lldb().ok_or_else(|| anyhow::anyhow!("lldb-dap is not installed"))?;
+                Box::new(DapBackend::start(&adapter, &program_path, directory)?)
+            }
+            None => match (gdb(), lldb()) {
+                (Some(gdb), _) => Box::new(GdbBackend::start(&gdb, &program_path, directory)?),
+                (None, Some(adapter)) => Box::new(DapBackend::start(&adapter, &program_path, directory)?),
+                (None, None) => return Err(anyhow::anyhow!("neither gdb nor lldb-dap is installed")),
+            },
+        };
+
+        Ok(DebugSession {
+            backend,
+            program: program_path,
+            arguments,
+            directory: directory.to_path_buf(),
+            state: ProgramState::NotStarted,
+            frames: Vec::new(),
+            selected_frame: 0,
+        })
+    }
+
+    /**
+     * Gets the debugger name
+     *
+     * @return &str - "gdb" or "lldb-dap"
+     */
+    pub fn debugger(&self) -> &str {
+        self.backend.name()
+    }
+
+    /**
+     * Gets the program being debugged
+     *
+     * @return &Path - Program path
+     */
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    /**
+     * Gets the breakpoints
+     *
+     * @return &[Breakpoint] - Breakpoints in number order
+     */
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        self.backend.breakpoints()
+    }
+
+    /**
+     * Sets a breakpoint
+     *
+     * @param location - `file:line` or a function name
+     * @return Result<String> - Confirmation in gdb's wording
+     */
+    pub fn set_breakpoint(&mut self, location: &str) -> Result<String> {
+        let breakpoint = self.backend.insert_breakpoint(location)?;
+        Ok(match (&breakpoint.file, breakpoint.line) {
+            (Some(file), Some(line)) if !breakpoint.pending => format!("Breakpoint {} at {}:{}\n", breakpoint.number, file, line),
+            _ => format!("Breakpoint {} ({}) pending.\n", breakpoint.number, breakpoint.location),
+        })
+    }
+
+    /**
+     * Deletes one breakpoint, or all of them
+     *
+     * @param number - Breakpoint number, or None for all
+     * @return Result<()> - Success or an unknown number
+     */
+    pub fn delete_breakpoint(&mut self, number: Option<u32>) -> Result<()> {
+        self.backend.delete_breakpoint(number)
+    }
+
+    /**
+     * Starts the program and waits for it to stop
+     *
+     * @param arguments - New arguments, or None to reuse the previous ones
+     * @param color - Whether to highlight the source context
+     * @param sink - Receives the program's output
+     * @return Result<String> - Description of the stop
+     */
+    pub fn run(&mut self, arguments: Option<Vec<String>>, color: bool, sink: &mut dyn FnMut(&str)) -> Result<String> {
+        if let Some(arguments) = arguments {
+            self.arguments = arguments;
+        }
+        let stop = self.backend.run(&self.arguments, sink);
+        self.record(stop, color)
+    }
+
+    /**
+     * Resumes the stopped program and waits for it to stop again
+     *
+     * @param resume - How to resume
+     * @param color - Whether to highlight the source context
+     * @param sink - Receives the program's output
+     * @return Result<String> - Description of the stop
+     */
+    pub fn resume(&mut self, resume: Resume, color: bool, sink: &mut dyn FnMut(&str)) -> Result<String> {
+        self.require_stopped()?;
+        let stop = self.backend.resume(resume, sink);
+        self.record(stop, color)
+    }
+
+    /**
+     * Updates the state after run or resume
+     *
+     * @param stop - Stop reported by the backend
+     * @param color - Whether to highlight the source context
+     * @return Result<String> - Description of the stop
+     */
+    fn record(&mut self, stop: Result<Stop>, color: bool) -> Result<String> {
+        self.frames.clear();
+        self.selected_frame = 0;
+        let stop = match stop {
+            Ok(stop) => stop,
+            Err(error) => {
+                self.state = ProgramState::Exited(None);
+                return Err(error);
+            }
+        };
+        let description = describe_stop(&stop, &self.directory, color);
+        self.state = match &stop.reason {
+            StopReason::Exited(code) => ProgramState::Exited(Some(*code)),
+            StopReason::Signalled(..) => ProgramState::Exited(None),
+            _ => ProgramState::Stopped(Box::new(stop)),
+        };
+        Ok(description)
+    }
+
+    /**
+     * Fails unless the program is stopped
+     *
+     * @return Result<()> - Success, or gdb's "not being run" error
+     */
+    fn require_stopped(&self) -> Result<()> {
+        match self.state {
+            ProgramState::Stopped(_) => Ok(()),
+            _ => Err(anyhow::anyhow!("The program is not being run.")),
+        }
+    }
+
+    /**
+     * Gets the stack of the stopped program
+     *
+     * @return Result<&[Frame]> - Frames from the innermost outwards
+     */
+    pub fn backtrace(&mut self) -> Result<&[Frame]> {
+        self.require_stopped()?;
+        if self.frames.is_empty() {
+            self.frames = self.backend.backtrace()?;
+        }
+        Ok(&self.frames)
+    }
+
+    /**
+     * Selects the frame locals, print and list refer to
+     *
+     * @param level - Frame level
+     * @param color - Whether to highlight the source context
+     * @return Result<String> - Frame description with source context
+     */
+    pub fn select_frame(&mut self, level: usize, color: bool) -> Result<String> {
+        let frame = self
+            .backtrace()?
+            .get(level)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No frame at level {}.", level))?;
+        self.selected_frame = level;
+        let mut output = format!("#{:<3}{}\n", level, describe_frame(&frame, level > 0));
+        output.push_str(&source_context(&frame, &self.directory, 2, color).unwrap_or_default());
+        Ok(output)
+    }
+
+    /**
+     * Gets the arguments and locals of the selected frame
+     *
+     * @return Result<Vec<Variable>> - Variables
+     */
+    pub fn locals(&mut self) -> Result<Vec<Variable>> {
+        self.require_stopped()?;
+        self.backend.locals(self.selected_frame)
+    }
+
+    /**
+     * Evaluates an expression in the selected frame
+     *
+     * @param expression - Expression in the program's language
+     * @return Result<String> - Value
+     */
+    pub fn evaluate(&mut self, expression: &str) -> Result<String> {
+        self.backend.evaluate(expression, self.selected_frame)
+    }
+
+    /**
+     * Lists the source around the selected frame
+     *
+     * @param color - Whether to highlight the source
+     * @return Result<String> - Numbered source lines
+     */
+    pub fn list(&mut self, color: bool) -> Result<String> {
+        let frame = match self.selected_frame {
+            0 => match &self.state {
+                ProgramState::Stopped(stop) => stop.frame.clone(),
+                _ => None,
+            },
+            level => self.backtrace()?.get(level).cloned(),
+        };
+        let frame = frame.ok_or_else(|| anyhow::anyhow!("No frame selected."))?;
+        source_context(&frame, &self.directory, 5, color).ok_or_else(|| anyhow::anyhow!("No source file for {}.", frame.function))
+    }
+
+    /**
+     * Describes the session
+     *
+     * @return String - Debugger, program, state and breakpoint count
+     */
+    pub fn status(&self) -> String {
+        let state = match &self.state {
+            ProgramState::NotStarted => "not started".to_string(),
+            ProgramState::Stopped(stop) => match &stop.frame {
+                Some(frame) => format!("stopped in {}", describe_frame(frame, false)),
+                None => "stopped".to_string(),
+            },
+            ProgramState::Exited(Some(code)) => format!("exited with code {}", code),
+            ProgramState::Exited(None) => "terminated".to_string(),
+        };
+        format!(
+            "Debugger:    {}\nProgram:     {}\nArguments:   {}\nState:       {}\nBreakpoints: {}\n",
+            self.backend.name(),
+            self.program.display(),
+            self.arguments.join(" "),
+            state,
+            self.backend.breakpoints().len()
+        )
+    }
+}
+
+/**
+ * Describes a frame the way gdb's backtrace does
+ *
+ * @param frame - Frame
+ * @param with_address - Whether to prefix the program counter
+ * @return String - e.g. `main (argc=1) at src/main.c:10`
+ */
+pub fn describe_frame(frame: &Frame, with_address: bool) -> String {
+    let mut text = String::new();
+    if let (true, Some(address)) = (with_address || frame.line.is_none(), &frame.address) {
+        text.push_str(&format!("{} in ", address));
+    }
+    let arguments: Vec<String> = frame.arguments.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+    text.push_str(&format!("{} ({})", frame.function, arguments.join(", ")));
+    match (&frame.file, frame.line, &frame.library) {
+        (Some(file), Some(line), _) => text.push_str(&format!(" at {}:{}", file, line)),
+        (_, _, Some(library)) => text.push_str(&format!(" from {}", library)),
+        _ => {}
+    }
+    text
+}
+
+/**
+ * 停止をgdbと同じ言い回しで説明する関数です
+ *
+ * 停止理由の行（ブレークポイント番号、シグナル、戻り値など）の後に
+ * フレームの説明と前後2行のソースを続けます。プログラムが終了した
+ * 場合は終了コードだけを表示します。
+ *
+ * @param stop - 停止イベント
+ * @param directory - 相対パスのソースを探すディレクトリ
+ * @param color - ソースをハイライトするかどうか
+ * @return String - 説明
+ */
+pub fn describe_stop(stop: &Stop, directory: &Path, color: bool) -> String {
+    let frame_text = stop.frame.as_ref().map(|frame| describe_frame(frame, false)).unwrap_or_default();
+    let mut output = match &stop.reason {
+        StopReason::Exited(0) => return "\nProgram exited normally.\n".to_string(),
+        StopReason::Exited(code) => return format!("\nProgram exited with code {}.\n", code),
+        StopReason::Signalled(name, meaning) => return format!("\nProgram terminated with signal {}, {}.\n", name, meaning),
+        StopReason::Breakpoint(0) => format!("\nBreakpoint, {}\n", frame_text),
+        StopReason::Breakpoint(number) => format!("\nBreakpoint {}, {}\n", number, frame_text),
+        StopReason::Signal(name, meaning) => format!("\nProgram received signal {}, {}.\n{}\n", name, meaning, frame_text),
+        StopReason::Interrupted => format!("\nProgram interrupted.\n{}\n", frame_text),
+        StopReason::FunctionFinished(Some(value)) => format!("{}\nValue returned: {}\n", frame_text, value),
+        StopReason::FunctionFinished(None) | StopReason::Step => format!("{}\n", frame_text),
+        StopReason::Other(reason) if reason.is_empty() => format!("{}\n", frame_text),
+        StopReason::Other(reason) => format!("\nStopped ({}).\n{}\n", reason, frame_text),
+    };
+    if let Some(context) = stop.frame.as_ref().and_then(|frame| source_context(frame, directory, 2, color)) {
+        output.push_str(&context);
+    }
+    output
+}
+
+/**
+ * Numbered source lines around a frame's line
+ *
+ * @param frame - Frame with a source position
+ * @param directory - Directory relative source paths are resolved against
+ * @param radius - Lines to show before and after
+ * @param color - Whether to highlight the source
+ * @return Option<String> - Listing with `=>` on the frame's line, or None without readable source
+ */
+pub fn source_context(frame: &Frame, directory: &Path, radius: u32, color: bool) -> Option<String> {
+    let line = frame.line?;
+    let path = frame
+        .full_path
+        .clone()
+        .filter(|path| path.is_file())
+        .or_else(|| frame.file.as_ref().map(|file| directory.join(file)))?;
+    let contents = fs::read(&path).ok()?;
+    let contents = String::from_utf8_lossy(&contents);
+    let lines: Vec<&str> = contents.lines().collect();
+    let first = line.saturating_sub(radius).max(1);
+    let last = (line + radius).min(lines.len() as u32);
+    if first > last {
+        return None;
+    }
+
+    let syntax = color.then(|| Syntax::for_path(&path)).flatten();
+    let mut in_comment = false;
+    if let Some(syntax) = &syntax {
+        for text in &lines[..first as usize - 1] {
+            highlight_line(text, syntax, &mut in_comment);
+        }
+    }
+    let width = last.to_string().len();
+    let mut output = String::new();
+    for number in first..=last {
+        let text = lines[number as usize - 1];
+        let text = match &syntax {
+            Some(syntax) => highlight_line(text, syntax, &mut in_comment),
+            None => text.to_string(),
+        };
+        let marker = if number == line { "=>" } else { "  " };
+        if color && number == line {
+            output.push_str(&format!("\x1b[1;33m{}\x1b[0m \x1b[1m{:>width$}\x1b[0m  {}\n", marker, number, text, width = width));
+        } else if color {
+            output.push_str(&format!("{} \x1b[90m{:>width$}\x1b[0m  {}\n", marker, number, text, width = width));
+        } else {
+            output.push_str(&format!("{} {:>width$}  {}\n", marker, number, text, width = width));
+        }
+    }
+    Some(output)
+}
+
+/**
+ * Table of frames for structured output
+ *
+ * @param frames - Frames
+ * @return Table - level, function, file, line and address columns
+ */
+pub fn frame_table(frames: &[Frame]) -> Table {
+    let mut table = Table::new(&["level", "function", "file", "line", "address"]);
+    for frame in frames {
+        table.push_row(vec![
+            json!(frame.level),
+            json!(frame.function),
+            frame.file.as_ref().or(frame.library.as_ref()).map_or(Value::Null, |file| json!(file)),
+            frame.line.map_or(Value::Null, |line| json!(line)),
+            frame.address.as_ref().map_or(Value::Null, |address| json!(address)),
+        ]);
+    }
+    table
+}
+
+/**
+ * Table of breakpoints for display and structured output
+ *
+ * @param breakpoints - Breakpoints
+ * @return Table - number, enabled, location, function, file, line and hits columns
+ */
+pub fn breakpoint_table(breakpoints: &[Breakpoint]) -> Table {
+    let mut table = Table::new(&["number", "enabled", "location", "function", "file", "line", "hits"]);
+    for breakpoint in breakpoints {
+        table.push_row(vec![
+            json!(breakpoint.number),
+            json!(breakpoint.enabled),
+            json!(if breakpoint.pending { format!("{} (pending)", breakpoint.location) } else { breakpoint.location.clone() }),
+            breakpoint.function.as_ref().map_or(Value::Null, |function| json!(function)),
+            breakpoint.file.as_ref().map_or(Value::Null, |file| json!(file)),
+            breakpoint.line.map_or(Value::Null, |line| json!(line)),
+            json!(breakpoint.hits),
+        ]);
+    }
+    table
+}
+
+/**
+ * Table of variables for structured output
+ *
+ * @param variables - Variables
+ * @return Table - name, type, value and argument columns
+ */
+pub fn variable_table(variables: &[Variable]) -> Table {
+    let mut table = Table::new(&["name", "type", "value", "argument"]);
+    for variable in variables {
+        table.push_row(vec![
+            json!(variable.name),
+            variable.type_name.as_ref().map_or(Value::Null, |type_name| json!(type_name)),
+            json!(variable.value),
+            json!(variable.argument),
+        ]);
+    }
+    table
+}
+
+/**
+ * Category of a source token, following the terminal's semantic highlighting types
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    /// Language keyword
+    Keyword,
+    /// String or character literal
+    String,
+    /// Comment
+    Comment,
+    /// Numeric literal
+    Number,
+    /// Called function or macro
+    Function,
+    /// Type name
+    Class,
+}
+
+impl TokenKind {
+    /**
+     * ANSI color of the category
+     *
+     * @return &'static str - SGR parameters
+     */
+    fn color(self) -> &'static str {
+        match self {
+            TokenKind::Keyword => "35",
+            TokenKind::String => "32",
+            TokenKind::Comment => "90",
+            TokenKind::Number => "36",
+            TokenKind::Function => "34",
+            TokenKind::Class => "33",
+        }
+    }
+}
+
+/**
+ * Lexical rules of a source language
+ */
+struct Syntax {
+    /// Reserved words
+    keywords: &'static [&'static str],
+    /// Line comment introducer
+    line_comment: &'static str,
+    /// Whether `/* */` comments exist
+    block_comments: bool,
+    /// Whether single quotes delimit strings rather than only character literals
+    single_quote_strings: bool,
+}
+
+/// Rust keywords
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while",
+];
+
+/// C and C++ keywords
+const C_KEYWORDS: &[&str] = &[
+    "auto", "bool", "break", "case", "char", "class", "const", "constexpr", "continue", "default", "delete", "do", "double", "else", "enum",
+    "extern", "false", "float", "for", "goto", "if", "inline", "int", "long", "namespace", "new", "nullptr", "private", "protected",
+    "public", "register", "return", "short", "signed", "sizeof", "static", "struct", "switch", "template", "this", "true", "typedef",
+    "typename", "union", "unsigned", "using", "virtual", "void", "volatile", "while", "#include", "#define", "#if", "#ifdef", "#ifndef",
+    "#endif", "#else",
+];
+
+/// Go keywords
+const GO_KEYWORDS: &[&str] = &[
+    "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "false", "for", "func", "go", "goto", "if",
+    "import", "interface", "map", "nil", "package", "range", "return", "select", "struct", "switch", "true", "type", "var",
+];
+
+/// Python keywords
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else", "except", "False", "finally", "for",
+    "from", "global", "if", "import", "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True", "try",
+    "while", "with", "yield",
+];
+
+impl Syntax {
+    /**
+     * Rules for a source file, by extension
+     *
+     * @param path - Source path
+     * @return Option<Syntax> - Rules, or None for unknown languages
+     */
+    fn for_path(path: &Path) -> Option<Syntax> {
+        let extension = path.extension()?.to_str()?;
+        let (keywords, line_comment, block_comments, single_quote_strings) = match extension {
+            "rs" => (RUST_KEYWORDS, "//", true, false),
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => (C_KEYWORDS, "//", true, false),
+            "go" => (GO_KEYWORDS, "//", true, false),
+            "py" => (PYTHON_KEYWORDS, "#", false, true),
+            _ => return None,
+        };
+        Some(Syntax {
+            keywords,
+            line_comment,
+            block_comments,
+            single_quote_strings,
+        })
+    }
+}
+
+/**
+ * Wraps text in an ANSI color
+ *
+ * @param text - Token text
+ * @param kind - Token category
+ * @return String - Colored text
+ */
+fn paint(text: &str, kind: TokenKind) -> String {
+    format!("\x1b[{}m{}\x1b[0m", kind.color(), text)
+}
+
+/**
+ * ソースの1行をハイライトする関数です
+ *
+ * 左から順に、ブロックコメントの続き、行コメント、ブロック
+ * コメントの開始、文字列、数値、識別子を判定します。識別子は
+ * キーワード、直後に「(」か「!」が続く関数呼び出しやマクロ、
+ * 大文字で始まる型名に分類します。Rustのライフタイム（'a）と
+ * 文字リテラルを区別するため、単一引用符は1文字（またはエスケープ）
+ * の直後に閉じ引用符がある場合だけ文字リテラルとして扱います
+ * （Pythonでは文字列です）。
+ *
+ * @param line - ソースの行
+ * @param syntax - 言語の規則
+ * @param in_comment - ブロックコメントの中かどうか（行をまたいで更新）
+ * @return String - ANSIカラー付きの行
+ */
+fn highlight_line(line: &str, syntax: &Syntax, in_comment: &mut bool) -> String {
+    let characters: Vec<char> = line.chars().collect();
+    let text = |start: usize, end: usize| characters[start..end].iter().collect::<String>();
+    let starts_with = |position: usize, pattern: &str| {
+        let pattern: Vec<char> = pattern.chars().collect();
+        characters[position..].starts_with(&pattern)
+    };
+    let mut output = String::new();
+    let mut position = 0;
+
+    while position < characters.len() {
+        if *in_comment || (syntax.block_comments && starts_with(position, "/*")) {
+            let search_from = if *in_comment { position } else { position + 2 };
+            let end = (search_from..characters.len().saturating_sub(1))
+                .find(|index| characters[*index] == '*' && characters[*index + 1] == '/')
+                .map(|index| index + 2);
+            *in_comment = end.is_none();
+            let end = end.unwrap_or(characters.len());
+            output.push_str(&paint(&text(position, end), TokenKind::Comment));
+            position = end;
+            continue;
+        }
+        if starts_with(position, syntax.line_comment) {
+            output.push_str(&paint(&text(position, characters.len()), TokenKind::Comment));
+            break;
+        }
+
+        let character = characters[position];
+        if character == '"' || (character == '\'' && syntax.single_quote_strings) {
+            let mut end = position + 1;
+            while end < characters.len() && characters[end] != character {
+                end += if characters[end] == '\\' { 2 } else { 1 };
+            }
+            let end = (end + 1).min(characters.len());
+            output.push_str(&paint(&text(position, end), TokenKind::String));
+            position = end;
+        } else if character == '\'' {
+            let close = if characters.get(position + 1) == Some(&'\\') {
+                (position + 3..characters.len().min(position + 12)).find(|index| characters[*index] == '\'')
+            } else {
+                (characters.get(position + 2) == Some(&'\'')).then_some(position + 2)
+            };
+            match close {
+                Some(close) => {
+                    output.push_str(&paint(&text(position, close + 1), TokenKind::String));
+                    position = close + 1;
+                }
+                None => {
+                    output.push(character);
+                    position += 1;
+                }
+            }
+        } else if character.is_ascii_digit() {
+            let end = (position..characters.len())
+                .find(|index| !(characters[*index].is_alphanumeric() || characters[*index] == '_' || characters[*index] == '.'))
+                .unwrap_or(characters.len());
+            output.push_str(&paint(&text(position, end), TokenKind::Number));
+            position = end;
+        } else if character.is_alphabetic() || character == '_' || (character == '#' && syntax.line_comment != "#") {
+            let end = (position + 1..characters.len())
+                .find(|index| !(characters[*index].is_alphanumeric() || characters[*index] == '_'))
+                .unwrap_or(characters.len());
+            let word = text(position, end);
+            let next = characters[end..].iter().find(|character| !character.is_whitespace());
+            let kind = if syntax.keywords.contains(&word.as_str()) {
+                Some(TokenKind::Keyword)
+            } else if next == Some(&'(') || characters.get(end) == Some(&'!') {
+                Some(TokenKind::Function)
+            } else if word.starts_with(|first: char| first.is_uppercase()) {
+                Some(TokenKind::Class)
+            } else {
+                None
+            };
+            match kind {
+                Some(kind) => output.push_str(&paint(&word, kind)),
+                None => output.push_str(&word),
+            }
+            position = end;
+        } else {
+            output.push(character);
+            position += 1;
+        }
+    }
+    output
+}
@@ -16,6 +16,11 @@ use anyhow::Result;
 use crate::shell::parser::ParsedCommand;
 use crate::shell::Shell;
 use crate::shell::commands::{CommandHandler, CommandResult};
+use crate::shell::commands::listing::terminal_columns;
+use crate::shell::commands::porcelain;
+use crate::shell::commands::debugger::{self, DebugSession, Resume};
+use crate::shell::commands::{cargo, dashboard, diagnostics, makefile, npm, tasks};
+use std::io::Write;
 
 /**
  * Gitバージョン管理システムを操作するコマンドです
@@ -26,8 +31,8 @@ use crate::shell::commands::{CommandHandler, CommandResult};
  * status、add、commit、log、branch、diffなどの主要なGitコマンドを
  * サポートし、各コマンドの出力に適切な色付けを行います。
  * 
- * Gitが利用できない場合はシミュレーション出力を提供し、
- * 開発環境での作業を継続できるようにします。
+ * Gitが利用できない場合は.gitディレクトリを直接読み、
+ * status、branch、log、diff --statを同じ形式で表示します。
  */
 pub struct GitCommand;
 
@@ -65,7 +70,7 @@ impl CommandHandler for GitCommand {
                 }
                 
                 // Apply syntax highlighting for git output
-                let highlighted_output = GitCommand::highlight_git_output(&combined_output, git_command);
+                let highlighted_output = GitCommand::highlight_git_output(&combined_output, git_command, shell.output_is_terminal());
                 
                 Ok(CommandResult {
                     output: highlighted_output,
@@ -73,88 +78,17 @@ impl CommandHandler for GitCommand {
                 })
             }
             Err(_) => {
-                // Fallback to simulated output if git is not available
-                let mut output = String::new();
-        
-        match git_command.as_str() {
-            "status" => {
-                output.push_str("On branch main\n");
-                output.push_str("Your branch is up to date with 'origin/main'.\n");
-                output.push_str("\n");
-                output.push_str("Changes not staged for commit:\n");
-                output.push_str("  (use \"git add <file>...\" to update what will be committed)\n");
-                output.push_str("  (use \"git restore <file>...\" to discard changes in working directory)\n");
-                output.push_str("        modified:   src/main.rs\n");
-                output.push_str("\n");
-                output.push_str("no changes added to commit (use \"git add\" and/or \"git commit -a\")\n");
-            }
-            "add" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: git add <file>"));
-                }
-                output.push_str(&format!("Added {} to staging area\n", args.join(", ")));
-            }
-            "commit" => {
-                let message = args.iter()
-                    .find(|arg| arg.starts_with("-m"))
-                    .and_then(|arg| arg.split('=').nth(1))
-                    .unwrap_or("Update");
-                
-                output.push_str(&format!("[main abc1234] {}\n", message));
-                output.push_str(" 1 file changed, 5 insertions(+), 2 deletions(-)\n");
-            }
-            "log" => {
-                output.push_str("commit abc1234567890abcdef1234567890abcdef1234\n");
-                output.push_str("Author: KleaSCM <kleascm@gmail.com>\n");
-                output.push_str("Date:   Mon Jan 1 12:00:00 2024 +0000\n");
-                output.push_str("\n");
-                output.push_str("    Update main.rs\n");
-                output.push_str("\n");
-                output.push_str("commit def1234567890abcdef1234567890abcdef1235\n");
-                output.push_str("Author: KleaSCM <kleascm@gmail.com>\n");
-                output.push_str("Date:   Sun Dec 31 12:00:00 2023 +0000\n");
-                output.push_str("\n");
-                output.push_str("    Initial commit\n");
-            }
-            "branch" => {
-                output.push_str("* main\n");
-                output.push_str("  feature/new-command\n");
-                output.push_str("  bugfix/fix-parser\n");
-            }
-            "checkout" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: git checkout <branch>"));
-                }
-                let branch = &args[0];
-                output.push_str(&format!("Switched to branch '{}'\n", branch));
-            }
-            "pull" => {
-                output.push_str("From https://github.com/user/repo\n");
-                output.push_str("   abc1234..def5678  main     -> origin/main\n");
-                output.push_str("Updating abc1234..def5678\n");
-                output.push_str("Fast-forward\n");
-                output.push_str(" src/main.rs | 5 +++++\n");
-                output.push_str(" 1 file changed, 5 insertions(+)\n");
-            }
-            "push" => {
-                output.push_str("Enumerating objects: 5, done.\n");
-                output.push_str("Counting objects: 100% (5/5), done.\n");
-                output.push_str("Delta compression using up to 8 threads\n");
-                output.push_str("Compressing objects: 100% (3/3), done.\n");
-                output.push_str("Writing objects: 100% (3/3), 234 bytes | 234.00 KiB/s, done.\n");
-                output.push_str("Total 3 (delta 2), reused 0 (delta 0), pack-reused 0\n");
-                output.push_str("To https://github.com/user/repo.git\n");
-                output.push_str("   def5678..abc1234  main -> main\n");
-            }
-            _ => {
-                return Err(anyhow::anyhow!("Unknown git command: {}", git_command));
-            }
-        }
-        
-        Ok(CommandResult {
-            output,
-            exit_code: 0,
-        })
+                // Fall back to the native repository reader if git is not available
+                let width = if shell.output_is_terminal() {
+                    terminal_columns(shell.get_environment_variable("COLUMNS"))
+                } else {
+                    80
+                };
+                let result = porcelain::execute(git_command, args, shell.current_path(), shell.output_is_terminal(), width)?;
+                Ok(CommandResult {
+                    output: GitCommand::highlight_git_output(&result.output, git_command, shell.output_is_terminal()),
+                    exit_code: result.exit_code,
+                })
             }
         }
     }
@@ -169,7 +103,9 @@ impl CommandHandler for GitCommand {
          branch    List, create, or delete branches\n\
          checkout  Switch branches or restore files\n\
          pull      Fetch from and integrate with repository\n\
-         push      Update remote refs along with objects"
+         push      Update remote refs along with objects\n\
+         Without the git binary, status, branch, log and diff --stat\n\
+         are read directly from the .git directory"
     }
     
     fn name(&self) -> &str {
@@ -183,12 +119,14 @@ impl GitCommand {
      * 
      * Applies syntax highlighting to git command output
      * for better readability and developer experience.
+     * Output that is piped or captured is returned unchanged.
      * 
      * @param output - Raw git output
      * @param command - Git command that was executed
+     * @param terminal - Whether the output reaches a terminal
      * @return String - Highlighted output
      */
-    fn highlight_git_output(output: &str, command: &str) -> String {
+    fn highlight_git_output(output: &str, command: &str, terminal: bool) -> String {
         		/**
 		 * Gitコマンドの出力にシンタックスハイライトを適用する関数です
 		 * 
@@ -201,6 +139,10 @@ impl GitCommand {
 		 * 対応していないコマンドの場合は元の行をそのまま返します。
 		 */
         
+        if !terminal {
+            return output.to_string();
+        }
+        
         let mut highlighted = String::new();
         let lines: Vec<&str> = output.lines().collect();
         
@@ -299,361 +241,426 @@ impl GitCommand {
 }
 
 /**
- * Cargo command
+ * Rustのパッケージマネージャcargoを実行するコマンドです
+ * 
+ * build、check、clippy、test、runはcargoを--message-format=jsonで
+ * 実行し、診断をスパン、ラベル、エラーコード、提案付きで描画します。
+ * ファイル位置はクリックできるリンクになり、最後のビルドの診断は
+ * 「cargo errors」で一覧表示したり、エディタで開いたりできます。
  * 
- * Implements the cargo command for Rust package management.
- * Supports basic cargo operations and build management.
+ * testはテストごとの結果と所要時間を集めて要約します。その他の
+ * サブコマンドはcargoの出力をそのまま流します。
  */
 pub struct CargoCommand;
 
-impl CommandHandler for CargoCommand {
-    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: cargo <command> [args...]"));
-        }
-        
-        let cargo_command = &command.args[0];
-        let args = &command.args[1..];
-        
-        // Execute actual cargo command
-        let mut cargo_process = std::process::Command::new("cargo");
-        cargo_process.args(&[cargo_command]);
-        cargo_process.args(args);
-        
-        // Set working directory to current shell path
-        cargo_process.current_dir(shell.current_path());
-        
-        // Capture output
-        let output_result = cargo_process.output();
-        
-        match output_result {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                
-                let mut combined_output = String::new();
-                if !stdout.is_empty() {
-                    combined_output.push_str(&stdout);
-                }
-                if !stderr.is_empty() {
-                    combined_output.push_str(&stderr);
-                }
-                
-                // Apply syntax highlighting for cargo output
-                let highlighted_output = CargoCommand::highlight_cargo_output(&combined_output, cargo_command);
-                
-                Ok(CommandResult {
-                    output: highlighted_output,
-                    exit_code: output.status.code().unwrap_or(1),
-                })
-            }
-            Err(_) => {
-                // Fallback to simulated output if cargo is not available
-                let mut output = String::new();
-        
-        match cargo_command.as_str() {
-            "build" => {
-                let release = args.iter().any(|arg| arg == "--release");
-                let target = if release { "release" } else { "debug" };
-                
-                output.push_str(&format!("   Compiling {} v0.1.0 ({})\n", 
-                    shell.get_project_name().unwrap_or_else(|| "project".to_string()), 
-                    shell.current_path().display()));
-                output.push_str("    Finished ");
-                output.push_str(target);
-                output.push_str(" [optimized");
-                if release { output.push_str(" + debug"); }
-                output.push_str("] target(s) in 1.23s\n");
-            }
-            "run" => {
-                output.push_str("    Finished dev [unoptimized + debuginfo] target(s) in 0.12s\n");
-                output.push_str("     Running `target/debug/project`\n");
-                output.push_str("Hello, World!\n");
-            }
-            "test" => {
-                output.push_str("   Compiling project v0.1.0 (/path/to/project)\n");
-                output.push_str("    Finished test [unoptimized + debuginfo] target(s) in 0.45s\n");
-                output.push_str("     Running unittests src/lib.rs (target/debug/deps/project-abc123)\n");
-                output.push_str("\n");
-                output.push_str("running 3 tests\n");
-                output.push_str("test tests::test_function ... ok\n");
-                output.push_str("test tests::test_another_function ... ok\n");
-                output.push_str("test tests::test_third_function ... ok\n");
-                output.push_str("\n");
-                output.push_str("test result: ok. 3 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n");
-            }
-            "check" => {
-                output.push_str("    Checking project v0.1.0 (/path/to/project)\n");
-                output.push_str("    Finished dev [unoptimized + debuginfo] target(s) in 0.23s\n");
-            }
-            "new" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: cargo new <project_name>"));
-                }
-                let project_name = &args[0];
-                output.push_str(&format!("     Created binary (application) `{}` package\n", project_name));
-            }
-            "init" => {
-                output.push_str("     Created binary (application) package\n");
-            }
-            "add" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: cargo add <dependency>"));
-                }
-                let dependency = &args[0];
-                output.push_str(&format!("    Adding {} to dependencies\n", dependency));
-            }
-            "update" => {
-                output.push_str("    Updating crates.io index\n");
-                output.push_str("    Updating serde v1.0.219 -> v1.0.220\n");
-                output.push_str("    Updating tokio v1.47.1 -> v1.47.2\n");
-            }
-            _ => {
-                return Err(anyhow::anyhow!("Unknown cargo command: {}", cargo_command));
-            }
+impl CargoCommand {
+    /**
+     * Compiles with JSON messages (build, check, clippy, doc, bench --no-run)
+     *
+     * @param subcommand - cargo subcommand
+     * @param args - Arguments after the subcommand
+     * @param shell - Shell instance
+     * @return Result<CommandResult> - Collected output and cargo's exit code
+     */
+    fn compile(&self, subcommand: &str, args: &[String], shell: &mut Shell) -> Result<CommandResult> {
+        let mut printer = cargo::Printer::new(shell.output_is_terminal());
+        let build = cargo::build(subcommand, args, shell.current_path(), &mut printer)?;
+        CargoCommand::keep_diagnostics(&build, &mut printer, shell);
+        if !printer.terminal {
+            shell.emit_records(cargo::diagnostic_table(&build.diagnostics));
         }
-        
         Ok(CommandResult {
-            output,
-            exit_code: 0,
+            output: printer.output,
+            exit_code: build.exit_code,
         })
-            }
-        }
-    }
-    
-    fn help(&self) -> &str {
-        "cargo <command> [args...] - Rust package manager\n\
-         Commands:\n\
-         build     Compile the current package\n\
-         run       Run a binary or example of the local package\n\
-         test      Run the tests\n\
-         check     Check that your code compiles\n\
-         new       Create a new cargo package\n\
-         init      Create a new cargo package in existing directory\n\
-         add       Add dependencies to a manifest file\n\
-         update    Update dependencies in Cargo.lock"
-    }
-    
-    fn name(&self) -> &str {
-        "cargo"
     }
-}
 
-impl CargoCommand {
     /**
-     * Highlights cargo output with syntax highlighting
-     * 
-     * Applies syntax highlighting to cargo command output
-     * for better readability and developer experience.
-     * 
-     * @param output - Raw cargo output
-     * @param command - Cargo command that was executed
-     * @return String - Highlighted output
+     * Prints the build summary and keeps the diagnostics for cargo errors
+     *
+     * @param build - Build result
+     * @param printer - Output
+     * @param shell - Shell instance
      */
-    fn highlight_cargo_output(output: &str, command: &str) -> String {
-        		/**
-		 * Cargoコマンドの出力にシンタックスハイライトを適用する関数です
-		 * 
-		 * 各Cargoコマンド（build、test、run、check）の出力に
-		 * 適切な色付けを適用して、開発者が読みやすい形式に変換します。
-		 * 
-		 * コマンドの種類に応じて適切なハイライト関数を呼び出し、
-		 * コンパイル情報、エラー、警告、成功メッセージに色付けを適用します。
-		 * 
-		 * 対応していないコマンドの場合は元の行をそのまま返します。
-		 */
-        
-        let mut highlighted = String::new();
-        let lines: Vec<&str> = output.lines().collect();
-        
-        for line in lines {
-            let highlighted_line = match command {
-                "build" => CargoCommand::highlight_build_line(line),
-                "test" => CargoCommand::highlight_test_line(line),
-                "run" => CargoCommand::highlight_run_line(line),
-                "check" => CargoCommand::highlight_check_line(line),
-                _ => line.to_string(),
-            };
-            highlighted.push_str(&highlighted_line);
-            highlighted.push_str("\n");
+    fn keep_diagnostics(build: &cargo::Build, printer: &mut cargo::Printer, shell: &mut Shell) {
+        if let Some(summary) = cargo::render_build_summary(build, printer) {
+            printer.print(&summary);
         }
-        
-        highlighted
+        *shell.build_diagnostics_mut() = build.diagnostics.clone();
     }
-    
+
     /**
-     * Highlights cargo build line
-     * 
-     * @param line - Build line to highlight
-     * @return String - Highlighted line
+     * Runs the tests with per-test results and durations
+     *
+     * @param args - Arguments after `cargo test`
+     * @param shell - Shell instance
+     * @return Result<CommandResult> - Collected output, exit code 101 when a test failed
      */
-    fn highlight_build_line(line: &str) -> String {
-        if line.contains("Compiling") {
-            format!("\x1b[36m{}\x1b[0m", line) // Cyan for compilation
-        } else if line.contains("Finished") {
-            format!("\x1b[32m{}\x1b[0m", line) // Green for success
-        } else if line.contains("error:") {
-            format!("\x1b[31m{}\x1b[0m", line) // Red for errors
-        } else if line.contains("warning:") {
-            format!("\x1b[33m{}\x1b[0m", line) // Yellow for warnings
-        } else {
-            line.to_string()
+    fn test(&self, args: &[String], shell: &mut Shell) -> Result<CommandResult> {
+        let mut printer = cargo::Printer::new(shell.output_is_terminal());
+        let directory = shell.current_path().clone();
+        let (build, outcomes, exit_code) = cargo::test(args, &directory, &mut printer)?;
+        CargoCommand::keep_diagnostics(&build, &mut printer, shell);
+        if !outcomes.is_empty() {
+            let summary = cargo::render_test_summary(&outcomes, &printer);
+            printer.print(&summary);
+        }
+        if !printer.terminal {
+            shell.emit_records(cargo::test_table(&outcomes));
         }
+        Ok(CommandResult {
+            output: printer.output,
+            exit_code,
+        })
     }
-    
+
     /**
-     * Highlights cargo test line
+     * cargo runを実行する関数です
      * 
-     * @param line - Test line to highlight
-     * @return String - Highlighted line
+     * まず同じ引数でcargo buildをJSONメッセージで実行して診断を
+     * 描画し、成功した場合にビルドされた実行ファイルを直接実行します。
+     * 実行ファイルが1つに決まらない場合は、cargoに選ばせるため
+     * cargo run --quietを実行します（ビルド済みなので再コンパイルは
+     * ありません）。
+     * プログラムが端末を使えるように、端末に出力する場合は標準入出力を
+     * 引き継ぎます。
+     * 
+     * @param args - cargo runの後の引数（--の後はプログラムの引数）
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - 出力とプログラムの終了コード
      */
-    fn highlight_test_line(line: &str) -> String {
-        if line.starts_with("running") {
-            format!("\x1b[36m{}\x1b[0m", line) // Cyan for test info
-        } else if line.contains("... ok") {
-            format!("\x1b[32m{}\x1b[0m", line) // Green for passed tests
-        } else if line.contains("... FAILED") {
-            format!("\x1b[31m{}\x1b[0m", line) // Red for failed tests
-        } else if line.contains("test result:") {
-            format!("\x1b[35m{}\x1b[0m", line) // Magenta for test summary
-        } else {
-            line.to_string()
+    fn run(&self, args: &[String], shell: &mut Shell) -> Result<CommandResult> {
+        let (build_args, program_args) = match args.iter().position(|arg| arg == "--") {
+            Some(position) => (&args[..position], &args[position + 1..]),
+            None => (args, &[][..]),
+        };
+        let mut printer = cargo::Printer::new(shell.output_is_terminal());
+        let build = cargo::build("build", build_args, shell.current_path(), &mut printer)?;
+        CargoCommand::keep_diagnostics(&build, &mut printer, shell);
+        if build.exit_code != 0 {
+            return Ok(CommandResult {
+                output: printer.output,
+                exit_code: build.exit_code,
+            });
         }
+
+        let mut run = match build.executables.as_slice() {
+            [executable] => {
+                let mut run = std::process::Command::new(executable);
+                run.args(program_args);
+                run
+            }
+            _ => {
+                let mut run = std::process::Command::new("cargo");
+                run.args(["run", "--quiet"]).args(build_args).arg("--").args(program_args);
+                run
+            }
+        };
+        run.current_dir(shell.current_path());
+        let exit_code = if printer.terminal {
+            run.status()?.code().unwrap_or(1)
+        } else {
+            let output = run.output()?;
+            printer.output.push_str(&String::from_utf8_lossy(&output.stdout));
+            printer.output.push_str(&String::from_utf8_lossy(&output.stderr));
+            output.status.code().unwrap_or(1)
+        };
+        Ok(CommandResult {
+            output: printer.output,
+            exit_code,
+        })
     }
-    
+
     /**
-     * Highlights cargo run line
+     * 最後のビルドの診断を扱う関数です
      * 
-     * @param line - Run line to highlight
-     * @return String - Highlighted line
+     * 引数がない場合は番号付きの一覧（位置はリンク）を表示し、
+     * パイプの場合はレコードも出力します。番号を指定すると診断の
+     * 全体を描画し、--editを付けると$VISUALまたは$EDITOR（既定はvi）で
+     * その位置を開きます。VS Code系のエディタには-g file:line:colを、
+     * それ以外には+lineを渡します。
+     * 
+     * @param args - errorsの後の引数
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - 一覧、診断、またはエディタの終了コード
      */
-    fn highlight_run_line(line: &str) -> String {
-        if line.contains("Running") {
-            format!("\x1b[36m{}\x1b[0m", line) // Cyan for run info
-        } else if line.contains("Finished") {
-            format!("\x1b[32m{}\x1b[0m", line) // Green for success
-        } else {
-            line.to_string()
+    fn errors(&self, args: &[String], shell: &mut Shell) -> Result<CommandResult> {
+        let diagnostics = shell.build_diagnostics_mut().clone();
+        let printer = cargo::Printer::new(shell.output_is_terminal());
+        let edit = args.iter().any(|arg| arg == "--edit" || arg == "-e");
+        let index = args.iter().find(|arg| !arg.starts_with('-'));
+
+        let Some(index) = index else {
+            if diagnostics.is_empty() {
+                return Ok(CommandResult {
+                    output: "cargo: no diagnostics from the last build\n".to_string(),
+                    exit_code: 0,
+                });
+            }
+            if !printer.terminal {
+                shell.emit_records(cargo::diagnostic_table(&diagnostics));
+            }
+            let host = whoami::fallible::hostname().unwrap_or_default();
+            let labels: Vec<String> = diagnostics
+                .iter()
+                .map(|diagnostic| format!("{}{}", diagnostic.level, diagnostic.code.as_ref().map(|code| format!("[{}]", code)).unwrap_or_default()))
+                .collect();
+            let locations: Vec<String> = diagnostics.iter().map(|diagnostic| diagnostic.primary().map(|span| span.location()).unwrap_or_default()).collect();
+            let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+            let location_width = locations.iter().map(String::len).max().unwrap_or(0);
+            let number_width = diagnostics.len().to_string().len();
+            let mut output = String::new();
+            for (number, diagnostic) in diagnostics.iter().enumerate() {
+                let color = if diagnostic.level == "warning" { "1;33" } else if diagnostic.level.starts_with("error") { "1;31" } else { "1;36" };
+                let label = format!("{:<width$}", labels[number], width = label_width);
+                let location = format!("{:<width$}", locations[number], width = location_width);
+                let location = match diagnostic.primary() {
+                    Some(span) if printer.terminal => location.replacen(&locations[number], &diagnostics::hyperlink(&host, &span.path, &locations[number]), 1),
+                    _ => location,
+                };
+                output.push_str(&format!("{:>width$}  {}  {}  {}\n", number + 1, printer.paint(color, &label), location, diagnostic.message, width = number_width));
+            }
+            return Ok(CommandResult { output, exit_code: 0 });
+        };
+
+        let number: usize = index.parse().map_err(|_| anyhow::anyhow!("errors: '{}' is not a diagnostic number", index))?;
+        let diagnostic = number
+            .checked_sub(1)
+            .and_then(|position| diagnostics.get(position))
+            .ok_or_else(|| anyhow::anyhow!("errors: no diagnostic {} (the last build had {})", number, diagnostics.len()))?;
+
+        if edit {
+            let span = diagnostic.primary().ok_or_else(|| anyhow::anyhow!("errors: diagnostic {} has no location", number))?;
+            let editor = shell
+                .get_environment_variable("VISUAL")
+                .or_else(|| shell.get_environment_variable("EDITOR"))
+                .unwrap_or("vi")
+                .to_string();
+            let mut words = editor.split_whitespace();
+            let program = words.next().unwrap_or("vi");
+            let mut command = std::process::Command::new(program);
+            command.args(words).current_dir(shell.current_path());
+            let name = std::path::Path::new(program).file_name().and_then(|name| name.to_str()).unwrap_or(program);
+            if name.starts_with("code") || name == "codium" {
+                command.arg("-g").arg(format!("{}:{}:{}", span.path.display(), span.line, span.column));
+            } else {
+                command.arg(format!("+{}", span.line)).arg(&span.path);
+            }
+            let status = command.status().map_err(|error| anyhow::anyhow!("errors: {}: {}", program, error))?;
+            return Ok(CommandResult {
+                output: String::new(),
+                exit_code: status.code().unwrap_or(1),
+            });
         }
+
+        let host = whoami::fallible::hostname().unwrap_or_default();
+        Ok(CommandResult {
+            output: diagnostic.render(&printer, &host),
+            exit_code: 0,
+        })
     }
-    
-    /**
-     * Highlights cargo check line
-     * 
-     * @param line - Check line to highlight
-     * @return String - Highlighted line
-     */
-    fn highlight_check_line(line: &str) -> String {
-        if line.contains("Checking") {
-            format!("\x1b[36m{}\x1b[0m", line) // Cyan for check info
-        } else if line.contains("Finished") {
-            format!("\x1b[32m{}\x1b[0m", line) // Green for success
-        } else if line.contains("error:") {
-            format!("\x1b[31m{}\x1b[0m", line) // Red for errors
-        } else {
-            line.to_string()
+}
+
+impl CommandHandler for CargoCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        if command.args.is_empty() {
+            return Err(anyhow::anyhow!("Usage: cargo <command> [args...]"));
+        }
+        
+        let cargo_command = command.args[0].as_str();
+        let args = &command.args[1..];
+        let json_supported = !args.iter().any(|arg| arg.starts_with("--message-format"));
+        match cargo_command {
+            "errors" => self.errors(args, shell),
+            "test" | "t" if json_supported => self.test(args, shell),
+            "run" | "r" if json_supported => self.run(args, shell),
+            "build" | "b" | "check" | "c" | "clippy" | "doc" | "rustc" if json_supported => self.compile(cargo_command, args, shell),
+            _ => {
+                let mut cargo_process = std::process::Command::new("cargo");
+                cargo_process.args(&command.args).current_dir(shell.current_path());
+                let mut linker = diagnostics::DiagnosticLinker::new(shell.current_path());
+                let result = diagnostics::run_build(&mut cargo_process, &mut linker, shell.output_is_terminal())?;
+                Ok(CommandResult {
+                    output: result.output,
+                    exit_code: result.exit_code,
+                })
+            }
         }
     }
+    
+    fn help(&self) -> &str {
+        "cargo <command> [args...] - Rust package manager\n\
+         Commands:\n\
+         build     Compile the current package\n\
+         check     Check that your code compiles\n\
+         clippy    Lint the current package\n\
+         run       Build, then run a binary or example of the local package\n\
+         test      Run the tests with per-test results and durations\n\
+         errors    List diagnostics of the last build\n\
+         errors <n> [--edit]  Show diagnostic n, or open it in $EDITOR\n\
+         Other commands are passed to cargo. Diagnostics link to their source."
+    }
+    
+    fn name(&self) -> &str {
+        "cargo"
+    }
 }
 
 /**
- * Debug command
+ * デバッガを操作するコマンドです
+ * 
+ * gdbをマシンインターフェース（--interpreter=mi3）で、gdbがない
+ * 場合はlldb-dapをDebug Adapter Protocolで子プロセスとして起動し、
+ * セッションをシェルに保持します。break、run、step、continue、
+ * backtrace、localsなどを各デバッガのコマンドに変換し、停止位置の
+ * ソースをハイライトして表示します。
  * 
- * Implements debugging support for GDB/LLDB integration.
- * Provides debugging commands and breakpoint management.
+ * 実行中のプログラムの出力は端末ならそのまま表示し、Ctrl+Cで
+ * プログラムを中断できます。パイプに出力する場合、backtrace、
+ * locals、breakpointsはレコードも出力します。
  */
 pub struct DebugCommand;
 
 impl CommandHandler for DebugCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
+        let Some(debug_command) = command.args.first() else {
             return Err(anyhow::anyhow!("Usage: debug <command> [args...]"));
-        }
-        
-        let debug_command = &command.args[0];
+        };
         let args = &command.args[1..];
-        
-        let mut output = String::new();
+        let terminal = shell.output_is_terminal();
         
         match debug_command.as_str() {
-            "gdb" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: debug gdb <binary>"));
-                }
-                let binary = &args[0];
-                output.push_str(&format!("Starting GDB debugger for {}\n", binary));
-                output.push_str("GNU gdb (GDB) 13.1\n");
-                output.push_str("Copyright (C) 2023 Free Software Foundation, Inc.\n");
-                output.push_str("License GPLv3+: GNU GPL version 3 or later <http://gnu.org/licenses/gpl.html>\n");
-                output.push_str("This is free software: you are free to change and redistribute it.\n");
-                output.push_str("There is NO WARRANTY, to the extent permitted by law.\n");
-                output.push_str("Type \"show copying\" and \"show warranty\" for details.\n");
-                output.push_str("This GDB was configured as \"x86_64-pc-linux-gnu\".\n");
-                output.push_str("Type \"show configuration\" for configuration details.\n");
-                output.push_str("For bug reporting instructions, please see:\n");
-                output.push_str("<https://www.gnu.org/software/gdb/bugs/>.\n");
-                output.push_str("Find the GDB manual and other documentation resources online at:\n");
-                output.push_str("    <http://www.gnu.org/software/gdb/documentation/>.\n");
-                output.push_str("\n");
-                output.push_str("For help, type \"help\".\n");
-                output.push_str("Type \"apropos word\" to search for commands related to \"word\"...\n");
-                output.push_str("Reading symbols from target/debug/sare...\n");
-                output.push_str("(gdb) ");
+            "start" | "gdb" | "lldb" => {
+                let program = args.first().ok_or_else(|| anyhow::anyhow!("Usage: debug {} <program> [args...]", debug_command))?;
+                let debugger = (debug_command != "start").then_some(debug_command.as_str());
+                let directory = shell.current_path().clone();
+                *shell.debug_session_mut() = None;
+                let session = DebugSession::start(debugger, program, args[1..].to_vec(), &directory)?;
+                let output = format!("Debugging {} with {}.\n", session.program().display(), session.debugger());
+                *shell.debug_session_mut() = Some(session);
+                return Ok(CommandResult { output, exit_code: 0 });
             }
-            "lldb" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: debug lldb <binary>"));
+            "quit" | "q" => {
+                if shell.debug_session_mut().take().is_none() {
+                    return Err(anyhow::anyhow!("no debug session"));
                 }
-                let binary = &args[0];
-                output.push_str(&format!("Starting LLDB debugger for {}\n", binary));
-                output.push_str("(lldb) target create \"target/debug/sare\"\n");
-                output.push_str("Current executable set to 'target/debug/sare' (x86_64).\n");
-                output.push_str("(lldb) ");
+                return Ok(CommandResult { output: String::new(), exit_code: 0 });
             }
-            "break" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: debug break <file>:<line>"));
-                }
-                let location = &args[0];
-                output.push_str(&format!("Breakpoint set at {}\n", location));
+            _ => {}
+        }
+        
+        let session = shell
+            .debug_session_mut()
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("no debug session; start one with 'debug start <program>'"))?;
+        let mut program_output = String::new();
+        let mut sink = |text: &str| {
+            if terminal {
+                print!("{}", text);
+                let _ = std::io::stdout().flush();
+            } else {
+                program_output.push_str(text);
+            }
+        };
+        let mut records = None;
+        
+        let message = match debug_command.as_str() {
+            "break" | "b" => {
+                let location = args.first().ok_or_else(|| anyhow::anyhow!("Usage: debug break <file:line|function>"))?;
+                session.set_breakpoint(location)?
+            }
+            "delete" | "d" => {
+                let number = args
+                    .first()
+                    .map(|number| number.parse::<u32>().map_err(|_| anyhow::anyhow!("Invalid breakpoint number: {}", number)))
+                    .transpose()?;
+                session.delete_breakpoint(number)?;
+                String::new()
+            }
+            "breakpoints" | "info" => {
+                let table = debugger::breakpoint_table(session.breakpoints());
+                let text = if table.rows.is_empty() { "No breakpoints.\n".to_string() } else { table.render() };
+                records = Some(table);
+                text
             }
-            "run" => {
-                output.push_str("Starting program: target/debug/sare\n");
-                output.push_str("Breakpoint 1, main () at src/main.rs:10\n");
-                output.push_str("10\t\tlet mut shell = Shell::new();\n");
+            "run" | "r" => session.run((!args.is_empty()).then(|| args.to_vec()), terminal, &mut sink)?,
+            "continue" | "c" => session.resume(Resume::Continue, terminal, &mut sink)?,
+            "step" | "s" => session.resume(Resume::StepInto, terminal, &mut sink)?,
+            "next" | "n" => session.resume(Resume::StepOver, terminal, &mut sink)?,
+            "finish" => session.resume(Resume::StepOut, terminal, &mut sink)?,
+            "backtrace" | "bt" => {
+                let frames = session.backtrace()?;
+                let text: String = frames
+                    .iter()
+                    .map(|frame| format!("#{:<3}{}\n", frame.level, debugger::describe_frame(frame, frame.level > 0)))
+                    .collect();
+                records = Some(debugger::frame_table(frames));
+                text
             }
-            "step" => {
-                output.push_str("11\t\tlet result = shell.run();\n");
+            "frame" | "f" => {
+                let level = match args.first() {
+                    Some(level) => level.parse::<usize>().map_err(|_| anyhow::anyhow!("Invalid frame level: {}", level))?,
+                    None => 0,
+                };
+                session.select_frame(level, terminal)?
             }
-            "continue" => {
-                output.push_str("Continuing.\n");
-                output.push_str("Program finished normally.\n");
+            "locals" => {
+                let variables = session.locals()?;
+                let text = if variables.is_empty() {
+                    "No locals.\n".to_string()
+                } else {
+                    variables.iter().map(|variable| format!("{} = {}\n", variable.name, variable.value)).collect()
+                };
+                records = Some(debugger::variable_table(&variables));
+                text
             }
-            "backtrace" => {
-                output.push_str("#0  main () at src/main.rs:10\n");
-                output.push_str("#1  0x00007ffff7c29d90 in __libc_start_main () from /usr/lib/libc.so.6\n");
+            "print" | "p" => {
+                if args.is_empty() {
+                    return Err(anyhow::anyhow!("Usage: debug print <expression>"));
+                }
+                let expression = args.join(" ");
+                format!("{} = {}\n", expression, session.evaluate(&expression)?)
             }
+            "list" | "l" => session.list(terminal)?,
+            "status" => session.status(),
             _ => {
                 return Err(anyhow::anyhow!("Unknown debug command: {}", debug_command));
             }
-        }
+        };
         
+        if let Some(records) = records {
+            if !terminal {
+                shell.emit_records(records);
+            }
+        }
+        program_output.push_str(&message);
         Ok(CommandResult {
-            output,
+            output: program_output,
             exit_code: 0,
         })
     }
     
     fn help(&self) -> &str {
-        "debug <command> [args...] - Debugging support\n\
+        "debug <command> [args...] - Debug programs with gdb or lldb-dap\n\
          Commands:\n\
-         gdb <binary>     Start GDB debugger\n\
-         lldb <binary>    Start LLDB debugger\n\
-         break <location> Set breakpoint\n\
-         run              Run program\n\
-         step             Step into function\n\
-         continue         Continue execution\n\
-         backtrace        Show call stack"
+         start <program> [args]  Start a session with gdb, or lldb-dap without gdb\n\
+         gdb <program> [args]    Start a session with gdb\n\
+         lldb <program> [args]   Start a session with lldb-dap\n\
+         break <file:line|func>  Set a breakpoint (b)\n\
+         delete [number]         Delete one or all breakpoints (d)\n\
+         breakpoints             List breakpoints\n\
+         run [args]              Run the program until it stops (r)\n\
+         continue                Continue execution (c)\n\
+         step                    Step into function calls (s)\n\
+         next                    Step over function calls (n)\n\
+         finish                  Run until the current function returns\n\
+         backtrace               Show the call stack (bt)\n\
+         frame [level]           Select a stack frame (f)\n\
+         locals                  Show arguments and locals of the selected frame\n\
+         print <expression>      Evaluate an expression (p)\n\
+         list                    Show source around the selected frame (l)\n\
+         status                  Show the session state\n\
+         quit                    End the session (q)"
     }
     
     fn name(&self) -> &str {
@@ -662,47 +669,86 @@ impl CommandHandler for DebugCommand {
 }
 
 /**
- * Make command
+ * Makeビルドシステムを実行するコマンドです
+ * 
+ * 実際のmakeを引数（-j、-f、-Cなど）をそのまま渡して実行し、
+ * 出力を行単位で流します。端末に出力する場合は、コンパイラの
+ * エラーや警告のファイル位置をクリックできるリンクにします。
  * 
- * Implements the make command for build system operations.
- * Supports basic makefile processing and target building.
+ * 「make --list」はMakefile（includeしたファイルを含む）を読んで
+ * ターゲットを一覧表示します。パイプの場合はレコードを出力します。
  */
 pub struct MakeCommand;
 
+impl MakeCommand {
+    /**
+     * Lists the targets of the Makefile
+     *
+     * @param args - make arguments without --list
+     * @param shell - Shell instance
+     * @return Result<CommandResult> - Target list or records
+     */
+    fn list(&self, args: &[String], shell: &mut Shell) -> Result<CommandResult> {
+        let (directory, file, overrides) = makefile::locate(args, shell.current_path());
+        let makefile = makefile::Makefile::discover(&directory, file.as_deref(), &overrides)?;
+        let terminal = shell.output_is_terminal();
+        if !terminal {
+            shell.emit_records(makefile.target_table());
+        }
+
+        let paint = |code: &str, text: String| if terminal { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text };
+        let default_goal = makefile.default_goal();
+        let targets: Vec<&makefile::Target> = makefile.listed_targets().collect();
+        let width = targets.iter().map(|target| target.name.len()).max().unwrap_or(0);
+        let mut output = String::new();
+        for target in targets {
+            let mut line = format!("{:<width$}", target.name, width = width);
+            if let Some(description) = &target.description {
+                line.push_str(&format!("  {}", paint("2", format!("# {}", description))));
+            }
+            if Some(target.name.as_str()) == default_goal {
+                line.push_str(&format!("  {}", paint("1;32", "(default)".to_string())));
+            }
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+        Ok(CommandResult { output, exit_code: 0 })
+    }
+}
+
 impl CommandHandler for MakeCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        let target = command.args.first();
-        let jobs = command.args.iter()
-            .find(|arg| arg.starts_with("-j"))
-            .and_then(|arg| arg[2..].parse::<u32>().ok())
-            .unwrap_or(1);
-        
-        let mut output = String::new();
-        
-        if let Some(target_name) = target {
-            output.push_str(&format!("make: Entering directory '{}'\n", shell.current_path().display()));
-            output.push_str(&format!("make: '{}' is up to date.\n", target_name));
-        } else {
-            output.push_str(&format!("make: Entering directory '{}'\n", shell.current_path().display()));
-            output.push_str("make: Nothing to be done for 'all'.\n");
+        if command.args.iter().any(|arg| arg == "--list") {
+            let args: Vec<String> = command.args.iter().filter(|arg| *arg != "--list").cloned().collect();
+            return self.list(&args, shell);
+        }
+
+        let (directory, _, _) = makefile::locate(&command.args, shell.current_path());
+        let terminal = shell.output_is_terminal();
+        let mut make = std::process::Command::new("make");
+        make.args(&command.args).current_dir(shell.current_path());
+        let mut linker = diagnostics::DiagnosticLinker::new(&directory);
+        let result = diagnostics::run_build(&mut make, &mut linker, terminal)?;
+
+        if terminal {
+            if let Some(summary) = linker.summary() {
+                println!("\x1b[1mmake:\x1b[0m {}", summary);
+            }
         }
-        
-        output.push_str(&format!("make: Leaving directory '{}'\n", shell.current_path().display()));
-        
         Ok(CommandResult {
-            output,
-            exit_code: 0,
+            output: result.output,
+            exit_code: result.exit_code,
         })
     }
     
     fn help(&self) -> &str {
-        "make [options] [target] - GNU make utility\n\
-         Options:\n\
+        "make [options] [target...] - Run GNU make\n\
+         Options are passed to make, including:\n\
          -j <jobs>    Allow N jobs at once\n\
          -f <file>    Use specified makefile\n\
          -C <dir>     Change to directory before reading makefiles\n\
-         clean        Remove build artifacts\n\
-         install      Install the project"
+         --list       List the targets of the makefile\n\
+         Compiler errors in the output link to their file and line."
     }
     
     fn name(&self) -> &str {
@@ -711,98 +757,133 @@ impl CommandHandler for MakeCommand {
 }
 
 /**
- * Shortcuts command
+ * プロジェクトのタスクを実行するコマンドです
+ * 
+ * プロジェクトルートの .sare/tasks.toml で定義したタスクと、
+ * Cargo.toml、package.json、Makefile、justfile、pyproject.toml から
+ * 検出したデフォルトタスクを実行します。依存タスクを先に実行し、
+ * 互いに独立したタスクは並列に実行して、最後に結果の表を表示します。
  * 
- * Implements developer shortcuts for common operations.
- * Provides quick access to frequently used commands and workflows.
+ * 引数なし（または --list）でタスクを一覧表示し、-n で実行順だけを
+ * 表示します。パイプの場合は一覧や結果をレコードとしても出力します。
  */
 pub struct ShortcutsCommand;
 
-impl CommandHandler for ShortcutsCommand {
-    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: shortcuts <command> [args...]"));
+impl ShortcutsCommand {
+    /**
+     * Lists the tasks of the project
+     *
+     * @param project - Project
+     * @param shell - Shell instance
+     * @return Result<CommandResult> - Task list or records
+     */
+    fn list(&self, project: &tasks::Project, shell: &mut Shell) -> Result<CommandResult> {
+        let terminal = shell.output_is_terminal();
+        if !terminal {
+            shell.emit_records(project.task_table());
         }
-        
-        let shortcut_command = &command.args[0];
-        let args = &command.args[1..];
-        
-        let mut output = String::new();
-        
-        match shortcut_command.as_str() {
-            "build" => {
-                // Quick build shortcut
-                output.push_str("🚀 Quick build initiated...\n");
-                output.push_str("📦 Running cargo build...\n");
-                output.push_str("✅ Build completed successfully!\n");
-            }
-            "test" => {
-                // Quick test shortcut
-                output.push_str("🧪 Quick test suite...\n");
-                output.push_str("📋 Running cargo test...\n");
-                output.push_str("✅ All tests passed!\n");
-            }
-            "dev" => {
-                // Development mode shortcut
-                output.push_str("🔧 Development mode activated...\n");
-                output.push_str("📝 Auto-reload enabled\n");
-                output.push_str("🐛 Debug symbols enabled\n");
-                output.push_str("⚡ Hot reload ready\n");
-            }
-            "deploy" => {
-                // Deployment shortcut
-                output.push_str("🚀 Deployment pipeline...\n");
-                output.push_str("📦 Building release...\n");
-                output.push_str("🔒 Signing artifacts...\n");
-                output.push_str("📤 Uploading to production...\n");
-                output.push_str("✅ Deployment successful!\n");
-            }
-            "clean" => {
-                // Clean shortcut
-                output.push_str("🧹 Cleaning project...\n");
-                output.push_str("🗑️  Removing build artifacts...\n");
-                output.push_str("📁 Cleaning cache...\n");
-                output.push_str("✅ Clean completed!\n");
-            }
-            "status" => {
-                // Status shortcut
-                output.push_str("📊 Project Status:\n");
-                output.push_str("📍 Branch: main\n");
-                output.push_str("📦 Dependencies: up to date\n");
-                output.push_str("🧪 Tests: passing\n");
-                output.push_str("🔧 Build: ready\n");
-                output.push_str("🚀 Deploy: ready\n");
+
+        let paint = |code: &str, text: String| if terminal { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text };
+        let kinds: Vec<&str> = project.kinds.iter().map(|kind| kind.name()).collect();
+        let mut output = paint("1", project.root.display().to_string());
+        if !kinds.is_empty() {
+            output.push_str(&format!(" {}", paint("2", format!("({})", kinds.join(", ")))));
+        }
+        output.push('\n');
+        if project.tasks.is_empty() {
+            output.push_str(&format!("No tasks: create {} or add a build file\n", tasks::TASK_FILE));
+            return Ok(CommandResult { output, exit_code: 0 });
+        }
+
+        let width = project.tasks.iter().map(|task| task.name.len()).max().unwrap_or(0);
+        let source_width = project.tasks.iter().map(|task| task.source.len()).max().unwrap_or(0);
+        for task in &project.tasks {
+            let detail = task.description.clone().unwrap_or_else(|| task.steps.join(" && "));
+            let mut line = format!("  {:<width$}  {}  {}", task.name, paint("36", format!("{:<width$}", task.source, width = source_width)), detail, width = width);
+            if !task.deps.is_empty() {
+                line.push_str(&format!("  {}", paint("2", format!("(after {})", task.deps.join(", ")))));
             }
-            "help" => {
-                output.push_str("🎯 Developer Shortcuts:\n");
-                output.push_str("  build   - Quick build with cargo\n");
-                output.push_str("  test    - Run test suite\n");
-                output.push_str("  dev     - Enable development mode\n");
-                output.push_str("  deploy  - Deploy to production\n");
-                output.push_str("  clean   - Clean build artifacts\n");
-                output.push_str("  status  - Show project status\n");
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+        Ok(CommandResult { output, exit_code: 0 })
+    }
+}
+
+impl CommandHandler for ShortcutsCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut names = Vec::new();
+        let mut jobs = std::thread::available_parallelism().map_or(4, |count| count.get());
+        let mut dry_run = false;
+        let mut list = false;
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-l" | "--list" => list = true,
+                "-n" | "--dry-run" => dry_run = true,
+                "-j" | "--jobs" => {
+                    jobs = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|jobs| *jobs > 0)
+                        .ok_or_else(|| anyhow::anyhow!("{} needs a positive number", arg))?;
+                }
+                _ if arg.starts_with('-') => return Err(anyhow::anyhow!("unknown option {}", arg)),
+                _ => names.push(arg.clone()),
             }
-            _ => {
-                return Err(anyhow::anyhow!("Unknown shortcut: {}", shortcut_command));
+        }
+
+        let project = tasks::Project::load(shell.current_path())?;
+        if list || names.is_empty() {
+            return self.list(&project, shell);
+        }
+        let plan = project.plan(&names)?;
+
+        if dry_run {
+            let mut output = String::new();
+            for task in &plan {
+                output.push_str(&format!("{}:\n", task.name));
+                for step in &task.steps {
+                    output.push_str(&format!("  {}\n", step));
+                }
             }
+            return Ok(CommandResult { output, exit_code: 0 });
         }
-        
+
+        let mut printer = cargo::Printer::new(shell.output_is_terminal());
+        let runs = tasks::run(&plan, jobs, shell, &mut printer)?;
+        let summary = tasks::summary_table(&runs);
+        printer.print(&format!("\n{}", summary.render()));
+        if !printer.terminal {
+            shell.emit_records(summary);
+        }
+
+        let failed = runs.iter().any(|run| run.status != tasks::TaskStatus::Passed);
         Ok(CommandResult {
-            output,
-            exit_code: 0,
+            output: printer.output,
+            exit_code: if failed { 1 } else { 0 },
         })
     }
     
     fn help(&self) -> &str {
-        "shortcuts <command> [args...] - Developer shortcuts\n\
-         Commands:\n\
-         build   - Quick build with cargo\n\
-         test    - Run test suite\n\
-         dev     - Enable development mode\n\
-         deploy  - Deploy to production\n\
-         clean   - Clean build artifacts\n\
-         status  - Show project status\n\
-         help    - Show this help"
+        "shortcuts [options] [task...] - Run project tasks\n\
+         Tasks come from .sare/tasks.toml in the project root and from\n\
+         the detected build tools (Cargo, npm, make, just, Python).\n\
+         Without a task, lists the available tasks.\n\
+         Options:\n\
+         -l, --list      List tasks\n\
+         -n, --dry-run   Show the tasks that would run, in order\n\
+         -j, --jobs <n>  Run at most n tasks at once\n\
+         Task file format:\n\
+         [env]\n\
+         RUST_LOG = \"info\"\n\
+         [tasks.ci]\n\
+         deps = [\"lint\", \"test\"]\n\
+         [tasks.web]\n\
+         run = [\"npm ci\", \"npm run build\"]\n\
+         dir = \"frontend\"\n\
+         env = { NODE_ENV = \"production\" }\n\
+         description = \"Build the frontend\""
     }
     
     fn name(&self) -> &str {
@@ -811,69 +892,83 @@ impl CommandHandler for ShortcutsCommand {
 }
 
 /**
- * Status command
+ * システムとシェルの状態を表示するダッシュボードのコマンドです
  * 
- * Implements status bar with shell information.
- * Provides comprehensive system and shell status information.
+ * /procからコアごとのCPU負荷、メモリとスワップ、ロードアベレージ、
+ * CPU使用率の高いプロセスを、statvfsからディスク使用量を読み、
+ * シェルのジョブとgitブランチと一緒に表示します。端末では全画面で
+ * その場で更新し続け、qで終了します。
+ * 
+ * 「--json」は一度だけサンプルを取ってJSONを出力します。端末以外に
+ * 出力する場合はテキストの報告を一度だけ出力します。
  */
 pub struct StatusCommand;
 
 impl CommandHandler for StatusCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        let mut output = String::new();
-        
-        // Get current working directory
-        let current_dir = shell.current_path();
-        let dir_display = current_dir.display();
-        
-        // Get shell information
-        let shell_name = "Sare";
-        let shell_version = "1.0.0";
-        
-        // Get system information
-        let hostname = whoami::hostname();
-        let username = whoami::username();
-        
-        // Get git status if available
-        let git_status = if current_dir.join(".git").exists() {
-            "📦 Git repository detected"
-        } else {
-            "📁 No git repository"
+        let mut json = false;
+        let mut once = false;
+        let mut delay = std::time::Duration::from_secs(1);
+        let mut iterations = None;
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" | "-j" => json = true,
+                "--once" | "-1" => once = true,
+                "-d" | "--delay" => {
+                    let seconds: f64 = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|seconds: &f64| seconds.is_finite() && *seconds >= 0.0)
+                        .ok_or_else(|| anyhow::anyhow!("{} needs a number of seconds", arg))?;
+                    delay = std::time::Duration::from_secs_f64(seconds.max(0.1));
+                }
+                "-n" => {
+                    let count: u64 = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| anyhow::anyhow!("-n needs a number of updates"))?;
+                    iterations = Some(count.max(1));
+                }
+                _ => return Err(anyhow::anyhow!("unknown option {}", arg)),
+            }
+        }
+
+        let info = dashboard::ShellInfo {
+            user: whoami::username(),
+            host: whoami::fallible::hostname().unwrap_or_default(),
+            directory: shell.current_path().clone(),
+            git: crate::shell::git::prompt_info(shell.current_path()).map(|info| info.to_string()),
+            jobs: shell
+                .get_jobs()
+                .iter()
+                .map(|job| dashboard::JobSummary {
+                    id: job.id,
+                    pid: job.pid,
+                    state: format!("{:?}", job.state),
+                    command: job.command.clone(),
+                })
+                .collect(),
         };
-        
-        // Get cargo project info if available
-        let cargo_status = if current_dir.join("Cargo.toml").exists() {
-            "🦀 Rust project detected"
+
+        let on_terminal = shell.output_is_terminal()
+            && unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::isatty(libc::STDOUT_FILENO) == 1 };
+        if on_terminal && !json && !once {
+            dashboard::run_interactive(&info, delay, iterations)?;
+            return Ok(CommandResult {
+                output: String::new(),
+                exit_code: 0,
+            });
+        }
+
+        let mut sampler = dashboard::Sampler::new();
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let snapshot = sampler.sample();
+        let output = if json {
+            format!("{}\n", serde_json::to_string_pretty(&dashboard::to_json(&snapshot, &info))?)
         } else {
-            "📁 No Rust project"
+            dashboard::render_text(&snapshot, &info)
         };
-        
-        // Build status bar
-        output.push_str("╭─────────────────────────────────────────────────────────────╮\n");
-        output.push_str("│ 🐚 Sare Terminal Status Bar                              │\n");
-        output.push_str("├─────────────────────────────────────────────────────────────┤\n");
-        output.push_str(&format!("│ 📁 Directory: {:<50} │\n", dir_display));
-        output.push_str(&format!("│ 👤 User: {}@{:<45} │\n", username, hostname));
-        output.push_str(&format!("│ 🐚 Shell: {} v{:<45} │\n", shell_name, shell_version));
-        output.push_str(&format!("│ {} │\n", git_status));
-        output.push_str(&format!("│ {} │\n", cargo_status));
-        output.push_str("├─────────────────────────────────────────────────────────────┤\n");
-        output.push_str("│ 💡 Use 'status -h' for detailed information              │\n");
-        output.push_str("╰─────────────────────────────────────────────────────────────╯\n");
-        
-        // Check for detailed flag
-        if command.args.contains(&"-h".to_string()) || command.args.contains(&"--help".to_string()) {
-            output.push_str("\n📊 Detailed Status Information:\n");
-            output.push_str("─────────────────────────────────────────────────────────────\n");
-            output.push_str(&format!("📁 Working Directory: {}\n", dir_display));
-            output.push_str(&format!("👤 Current User: {}\n", username));
-            output.push_str(&format!("🖥️  Hostname: {}\n", hostname));
-            output.push_str(&format!("🐚 Shell: {} v{}\n", shell_name, shell_version));
-            output.push_str(&format!("⏰ Session Start: {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-            output.push_str(&format!("💾 Memory Usage: {} MB\n", std::process::id()));
-            output.push_str("─────────────────────────────────────────────────────────────\n");
-        }
-        
         Ok(CommandResult {
             output,
             exit_code: 0,
@@ -881,12 +976,15 @@ impl CommandHandler for StatusCommand {
     }
     
     fn help(&self) -> &str {
-        "status [options] - Show shell status information\n\
+        "status [options] - Live system and shell dashboard\n\
+         Shows per-core CPU load, memory and swap, load average, disk usage,\n\
+         the busiest processes, background jobs and the git branch.\n\
+         Refreshes in place until q is pressed.\n\
          Options:\n\
-         -h, --help    Show detailed status information\n\
-         \n\
-         Shows current directory, user info, shell version,\n\
-         and project status information."
+         -j, --json        Print one sample as JSON (sizes in bytes) and exit\n\
+         -1, --once        Print one sample as text and exit (the default when piped)\n\
+         -d <seconds>      Delay between updates (default 1)\n\
+         -n <count>        Exit after count updates"
     }
     
     fn name(&self) -> &str {
@@ -895,132 +993,193 @@ impl CommandHandler for StatusCommand {
 }
 
 /**
- * Npm command
+ * package.jsonのスクリプトを実行するnpmコマンドです
+ * 
+ * 「npm run」「npm test」「npm start」「npm stop」「npm restart」は
+ * npmを起動せずにpackage.jsonを直接読み、pre/postフックと
+ * node_modules/.binを含むPATHでスクリプトを実行します。
+ * スクリプト名を省略すると一覧を表示します（パイプではレコード）。
  * 
- * Implements the npm command for Node.js package management.
- * Supports basic npm operations and package management.
+ * -w（名前またはディレクトリ）と-wsでnpm、yarn、pnpmの
+ * ワークスペースのパッケージを選べるので、モノレポ内のどの
+ * ディレクトリからでも実行できます。その他のサブコマンドは
+ * 実際のnpmにそのまま渡します。
  */
 pub struct NpmCommand;
 
-impl CommandHandler for NpmCommand {
-    fn execute(&self, command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: npm <command> [args...]"));
-        }
-        
-        let npm_command = &command.args[0];
-        let args = &command.args[1..];
-        
-        let mut output = String::new();
-        
-        match npm_command.as_str() {
-            "install" => {
-                if args.is_empty() {
-                    output.push_str("npm WARN saveError ENOENT: no such file or directory, open 'package.json'\n");
-                    output.push_str("npm WARN enoent ENOENT: no such file or directory, open 'package.json'\n");
-                    output.push_str("npm WARN project No description\n");
-                    output.push_str("npm WARN project No repository field.\n");
-                    output.push_str("npm WARN project No README data\n");
-                    output.push_str("npm WARN project No license field.\n");
-                    output.push_str("up to date in 0.123s\n");
-                } else {
-                    let package = &args[0];
-                    output.push_str(&format!("npm notice created a lockfile as package-lock.json. You should commit this file.\n"));
-                    output.push_str(&format!("npm WARN project@1.0.0 No description\n"));
-                    output.push_str(&format!("npm WARN project@1.0.0 No repository field.\n"));
-                    output.push_str(&format!("npm WARN project@1.0.0 No README data\n"));
-                    output.push_str(&format!("npm WARN project@1.0.0 No license field.\n"));
-                    output.push_str(&format!("+ {}@1.0.0\n", package));
-                    output.push_str("added 1 package, and audited 1 package in 0.234s\n");
-                    output.push_str("found 0 vulnerabilities\n");
+/**
+ * Arguments of an npm script command
+ */
+#[derive(Debug, Default)]
+struct NpmArgs {
+    /// Positional arguments, starting with the subcommand
+    positionals: Vec<String>,
+    /// Script run options
+    options: npm::RunOptions,
+    /// Packages selected with -w
+    workspaces: Vec<String>,
+    /// Whether -ws selected every workspace package
+    all_workspaces: bool,
+    /// Whether the workspace root runs along with -ws
+    include_root: bool,
+}
+
+impl NpmCommand {
+    /**
+     * Parses npm arguments, options being allowed before `--` anywhere
+     *
+     * @param args - npm arguments
+     * @return Result<NpmArgs> - Parsed arguments
+     */
+    fn parse(&self, args: &[String]) -> Result<NpmArgs> {
+        let mut parsed = NpmArgs::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (option, inline) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => (option, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            match option {
+                "--" => parsed.options.args.extend(args.by_ref().cloned()),
+                "-w" | "--workspace" => {
+                    let value = inline
+                        .or_else(|| args.next().cloned())
+                        .ok_or_else(|| anyhow::anyhow!("{} needs a workspace name or directory", option))?;
+                    parsed.workspaces.push(value);
+                }
+                "-ws" | "--workspaces" => parsed.all_workspaces = true,
+                "--include-workspace-root" => parsed.include_root = true,
+                "--if-present" => parsed.options.if_present = true,
+                "--ignore-scripts" => parsed.options.ignore_scripts = true,
+                "-s" | "--silent" => parsed.options.silent = true,
+                _ if option.starts_with('-') && option.len() > 1 => {
+                    parsed.options.config.push((option.trim_start_matches('-').to_string(), inline.unwrap_or_else(|| "true".to_string())));
                 }
+                _ => parsed.positionals.push(arg.clone()),
             }
-            "start" => {
-                output.push_str("\n> project@1.0.0 start\n");
-                output.push_str("> node index.js\n");
-                output.push_str("Server running on port 3000\n");
+        }
+        Ok(parsed)
+    }
+
+    /**
+     * Finds the packages a script command applies to
+     *
+     * @param parsed - Parsed arguments
+     * @param current - Current directory
+     * @return Result<Vec<npm::Package>> - Selected packages
+     */
+    fn packages(&self, parsed: &NpmArgs, current: &std::path::Path) -> Result<Vec<npm::Package>> {
+        if parsed.workspaces.is_empty() && !parsed.all_workspaces {
+            let directory = npm::Package::find(current)
+                .ok_or_else(|| anyhow::anyhow!("no package.json in {} or any parent directory", current.display()))?;
+            return Ok(vec![npm::Package::load(&directory)?]);
+        }
+
+        let workspace = npm::Workspace::find(current)
+            .ok_or_else(|| anyhow::anyhow!("no workspaces found from {}", current.display()))?;
+        let mut packages = Vec::new();
+        if parsed.include_root {
+            packages.push(npm::Package::load(&workspace.root)?);
+        }
+        if parsed.all_workspaces {
+            packages.extend(workspace.packages.iter().cloned());
+        }
+        for selector in &parsed.workspaces {
+            let package = workspace.select(selector, current)?;
+            if !packages.iter().any(|selected: &npm::Package| selected.directory == package.directory) {
+                packages.push(package.clone());
             }
-            "test" => {
-                output.push_str("\n> project@1.0.0 test\n");
-                output.push_str("> jest\n");
-                output.push_str("\n");
-                output.push_str(" PASS  ./test/example.test.js\n");
-                output.push_str("  ✓ should pass\n");
-                output.push_str("  ✓ should also pass\n");
-                output.push_str("\n");
-                output.push_str("Test Suites: 1 passed, 1 total\n");
-                output.push_str("Tests:       2 passed, 2 total\n");
-                output.push_str("Snapshots:   0 total\n");
-                output.push_str("Time:        0.123s\n");
+        }
+        Ok(packages)
+    }
+
+    /**
+     * Runs a script, or lists the scripts when none is given
+     *
+     * @param script - Script name
+     * @param parsed - Parsed arguments
+     * @param shell - Shell instance
+     * @return Result<CommandResult> - Script output and exit code
+     */
+    fn run(&self, script: Option<&str>, parsed: &NpmArgs, shell: &mut Shell) -> Result<CommandResult> {
+        let current = shell.current_path().clone();
+        let packages = self.packages(parsed, &current)?;
+        let mut printer = cargo::Printer::new(shell.output_is_terminal());
+
+        let Some(script) = script else {
+            if !printer.terminal {
+                shell.emit_records(npm::script_table(&packages.iter().collect::<Vec<_>>()));
             }
-            "run" => {
-                if args.is_empty() {
-                    return Err(anyhow::anyhow!("Usage: npm run <script>"));
-                }
-                let script = &args[0];
-                output.push_str(&format!("\n> project@1.0.0 {}\n", script));
-                output.push_str(&format!("> echo 'Running {script}'\n"));
-                output.push_str(&format!("Running {}\n", script));
+            for package in &packages {
+                let scripts = npm::render_scripts(package, &printer);
+                printer.print(&scripts);
             }
-            "init" => {
-                output.push_str("This utility will walk you through creating a package.json file.\n");
-                output.push_str("It only covers the most common items, and tries to guess sensible defaults.\n");
-                output.push_str("\n");
-                output.push_str("See `npm help json` for definitive documentation on these fields\n");
-                output.push_str("and exactly what they do.\n");
-                output.push_str("\n");
-                output.push_str("Use `npm install <pkg>` afterwards to install a package and\n");
-                output.push_str("save it as a dependency in the package.json file.\n");
-                output.push_str("\n");
-                output.push_str("Press ^C at any time to quit.\n");
-                output.push_str("package name: (project) \n");
-                output.push_str("version: (1.0.0) \n");
-                output.push_str("description: \n");
-                output.push_str("entry point: (index.js) \n");
-                output.push_str("test command: \n");
-                output.push_str("git repository: \n");
-                output.push_str("keywords: \n");
-                output.push_str("author: \n");
-                output.push_str("license: (ISC) \n");
-                output.push_str("About to write to /path/to/project/package.json:\n");
-                output.push_str("\n");
-                output.push_str("{\n");
-                output.push_str("  \"name\": \"project\",\n");
-                output.push_str("  \"version\": \"1.0.0\",\n");
-                output.push_str("  \"description\": \"\",\n");
-                output.push_str("  \"main\": \"index.js\",\n");
-                output.push_str("  \"scripts\": {\n");
-                output.push_str("    \"test\": \"echo \\\"Error: no test specified\\\" && exit 1\"\n");
-                output.push_str("  },\n");
-                output.push_str("  \"keywords\": [],\n");
-                output.push_str("  \"author\": \"\",\n");
-                output.push_str("  \"license\": \"ISC\"\n");
-                output.push_str("}\n");
-                output.push_str("\n");
-                output.push_str("Is this OK? (yes) \n");
-                output.push_str("Wrote to /path/to/project/package.json\n");
+            return Ok(CommandResult { output: printer.output, exit_code: 0 });
+        };
+
+        let mut exit_code = 0;
+        for package in &packages {
+            let result = if script == "restart" && package.script("restart").is_none() {
+                let stop = npm::RunOptions { if_present: true, ..parsed.options.clone() };
+                npm::run_script(package, "stop", &stop, &current, &mut printer).and_then(|code| match code {
+                    0 => npm::run_script(package, "start", &parsed.options, &current, &mut printer),
+                    code => Ok(code),
+                })
+            } else {
+                npm::run_script(package, script, &parsed.options, &current, &mut printer)
+            };
+            let code = result.unwrap_or_else(|error| {
+                printer.print(&format!("npm: {}\n", error));
+                1
+            });
+            if exit_code == 0 {
+                exit_code = code;
             }
+        }
+        Ok(CommandResult { output: printer.output, exit_code })
+    }
+}
+
+impl CommandHandler for NpmCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut parsed = self.parse(&command.args)?;
+        let (script, script_index) = match parsed.positionals.first().map(String::as_str) {
+            None if command.args.is_empty() => return Err(anyhow::anyhow!("Usage: npm <command> [args...]")),
+            Some("run" | "run-script" | "rum" | "urn") => (parsed.positionals.get(1).cloned(), 1),
+            Some("test" | "t" | "tst") => (Some("test".to_string()), 0),
+            Some(lifecycle @ ("start" | "stop" | "restart")) => (Some(lifecycle.to_string()), 0),
             _ => {
-                return Err(anyhow::anyhow!("Unknown npm command: {}", npm_command));
+                let terminal = shell.output_is_terminal();
+                let mut npm = std::process::Command::new("npm");
+                npm.args(&command.args).current_dir(shell.current_path());
+                let mut linker = diagnostics::DiagnosticLinker::new(shell.current_path());
+                let result = diagnostics::run_build(&mut npm, &mut linker, terminal)?;
+                return Ok(CommandResult {
+                    output: result.output,
+                    exit_code: result.exit_code,
+                });
             }
-        }
-        
-        Ok(CommandResult {
-            output,
-            exit_code: 0,
-        })
+        };
+
+        let extra = parsed.positionals.split_off((script_index + 1).min(parsed.positionals.len()));
+        parsed.options.args.splice(0..0, extra);
+        self.run(script.as_deref(), &parsed, shell)
     }
     
     fn help(&self) -> &str {
         "npm <command> [args...] - Node.js package manager\n\
-         Commands:\n\
-         install    Install a package\n\
-         start      Start a package\n\
-         test       Test a package\n\
-         run        Run arbitrary package scripts\n\
-         init       Initialize a package\n\
-         publish    Publish a package\n\
-         update     Update packages"
+         Scripts are read from package.json and run without npm:\n\
+         run [script] [-- args]   Run a script with its pre/post hooks (lists scripts without one)\n\
+         test, start, stop        Run the lifecycle script\n\
+         restart                  Run restart, or stop then start\n\
+         Script options:\n\
+         -w, --workspace <name|dir>  Run in a workspace package (repeatable)\n\
+         -ws, --workspaces           Run in every workspace package\n\
+         --include-workspace-root    Also run in the workspace root\n\
+         --if-present                Skip packages without the script\n\
+         --ignore-scripts            Skip pre/post hooks\n\
+         -s, --silent                Hide the script headers\n\
+         Other commands (install, ci, publish, ...) run the real npm."
     }
     
     fn name(&self) -> &str {
@@ -0,0 +1,375 @@
+/*!
+ * @file diagnostics.rs
+ * @brief Compiler diagnostics in build tool output
+ *
+ * This module runs build tools with their output streamed line by
+ * line and recognises compiler diagnostics in it. File locations that
+ * exist on disk become OSC 8 file:// hyperlinks, which terminals and
+ * the sare terminal's HyperlinkManager open with a click, and the
+ * severity words are colored.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file diagnostics.rs
+ * @description Diagnostic location matching for gcc, clang and rustc,
+ * make directory tracking, OSC 8 links and streaming process output.
+ */
+
+use anyhow::Result;
+use regex::Regex;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use crate::shell::commands::tail::InterruptGuard;
+
+/**
+ * `path:line[:column]` location, as printed by gcc, clang, rustc and most linters
+ */
+fn location_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"(?P<path>[A-Za-z0-9_./~+-][^\s:'`()\[\]]*):(?P<line>\d+)(?::(?P<column>\d+))?").unwrap())
+}
+
+/**
+ * Severity word following a location
+ */
+fn severity_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b(?P<severity>fatal error|error|warning|note|help)(?P<rest>(\[[^\]]*\])?:)").unwrap())
+}
+
+/**
+ * make's "Entering directory" and "Leaving directory" lines
+ */
+fn directory_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^\S*make(\[\d+\])?: (?P<action>Entering|Leaving) directory ['`](?P<directory>.*)'$").unwrap())
+}
+
+/**
+ * File location found in a line of output
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// Byte range of the location text in the line
+    pub range: std::ops::Range<usize>,
+    /// Absolute path of the file
+    pub path: PathBuf,
+    /// Line number
+    pub line: u32,
+    /// Column number, when given
+    pub column: Option<u32>,
+}
+
+/**
+ * Decorates build output with links to the files it mentions
+ */
+#[derive(Debug, Clone)]
+pub struct DiagnosticLinker {
+    /// Directories make entered, innermost last
+    directories: Vec<PathBuf>,
+    /// Host name for file:// URLs
+    host: String,
+    /// Number of error diagnostics seen
+    pub errors: usize,
+    /// Number of warning diagnostics seen
+    pub warnings: usize,
+}
+
+impl DiagnosticLinker {
+    /**
+     * Creates a linker resolving relative paths against a directory
+     *
+     * @param directory - Directory the build tool runs in
+     * @return DiagnosticLinker - Linker
+     */
+    pub fn new(directory: &Path) -> Self {
+        DiagnosticLinker {
+            directories: vec![directory.to_path_buf()],
+            host: whoami::fallible::hostname().unwrap_or_default(),
+            errors: 0,
+            warnings: 0,
+        }
+    }
+
+    /**
+     * Directory relative paths are currently resolved against
+     *
+     * @return &Path - Innermost directory make entered
+     */
+    pub fn directory(&self) -> &Path {
+        self.directories.last().map(PathBuf::as_path).unwrap_or(Path::new("."))
+    }
+
+    /**
+     * Follows make's directory changes and counts diagnostics
+     *
+     * @param line - Line of output without its newline
+     */
+    pub fn observe(&mut self, line: &str) {
+        if let Some(captures) = directory_pattern().captures(line) {
+            if &captures["action"] == "Entering" {
+                self.directories.push(PathBuf::from(&captures["directory"]));
+            } else if self.directories.len() > 1 {
+                self.directories.pop();
+            }
+            return;
+        }
+        if let Some(captures) = severity_pattern().captures(line) {
+            if self.locations(line).first().is_some_and(|location| location.range.end <= captures.get(0).map_or(0, |found| found.start())) || line.starts_with(&captures[0]) {
+                match &captures["severity"] {
+                    "error" | "fatal error" => self.errors += 1,
+                    "warning" => self.warnings += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /**
+     * ファイル位置を探す関数です
+     *
+     * 「パス:行[:列]」の形を探し、現在のディレクトリ（makeが
+     * 入ったディレクトリ）を基準に解決したパスが実在するファイルの
+     * ものだけを返します。時刻（12:30:45）やURLのポート番号などの
+     * 誤検出は、ファイルが存在しないことで除外されます。
+     *
+     * @param line - 出力の1行
+     * @return Vec<Location> - 見つかったファイル位置
+     */
+    pub fn locations(&self, line: &str) -> Vec<Location> {
+        location_pattern()
+            .captures_iter(line)
+            .filter_map(|captures| {
+                let whole = captures.get(0)?;
+                let path = Path::new(&captures["path"]);
+                let path = if path.is_absolute() { path.to_path_buf() } else { self.directory().join(path) };
+                if !path.is_file() {
+                    return None;
+                }
+                Some(Location {
+                    range: whole.range(),
+                    path: path.components().collect(),
+                    line: captures["line"].parse().ok()?,
+                    column: captures.name("column").and_then(|column| column.as_str().parse().ok()),
+                })
+            })
+            .collect()
+    }
+
+    /**
+     * 出力の1行を装飾する関数です
+     *
+     * ディレクトリの移動と診断の数を記録した後、ファイル位置を
+     * OSC 8ハイパーリンクで囲み、errorを赤、warningを黄、noteと
+     * helpをシアンの太字にします。リンク先はファイルのfile:// URLで、
+     * 行番号は表示テキストに残します（xdg-openなどのハンドラが
+     * フラグメント付きのURLを開けないため）。
+     *
+     * @param line - 出力の1行（改行なし）
+     * @return String - 端末向けに装飾した行
+     */
+    pub fn decorate(&mut self, line: &str) -> String {
+        self.observe(line);
+        let mut output = String::new();
+        let mut last = 0;
+        for location in self.locations(line) {
+            output.push_str(&color_severity(&line[last..location.range.start]));
+            output.push_str(&hyperlink(&self.host, &location.path, &line[location.range.clone()]));
+            last = location.range.end;
+        }
+        output.push_str(&color_severity(&line[last..]));
+        output
+    }
+
+    /**
+     * Summary of the diagnostics seen
+     *
+     * @return Option<String> - "N errors, M warnings", or None when there were none
+     */
+    pub fn summary(&self) -> Option<String> {
+        if self.errors == 0 && self.warnings == 0 {
+            return None;
+        }
+        let plural = |count: usize, word: &str| format!("{} {}{}", count, word, if count == 1 { "" } else { "s" });
+        Some(format!("{}, {}", plural(self.errors, "error"), plural(self.warnings, "warning")))
+    }
+}
+
+/**
+ * Colors severity words in a piece of a line
+ *
+ * @param text - Text outside file locations
+ * @return String - Text with error, warning, note and help colored
+ */
+fn color_severity(text: &str) -> String {
+    severity_pattern()
+        .replace_all(text, |captures: &regex::Captures| {
+            let color = match &captures["severity"] {
+                "error" | "fatal error" => "\x1b[1;31m",
+                "warning" => "\x1b[1;33m",
+                _ => "\x1b[1;36m",
+            };
+            format!("{}{}\x1b[0m{}", color, &captures["severity"], &captures["rest"])
+        })
+        .into_owned()
+}
+
+/**
+ * Wraps text in an OSC 8 hyperlink to a file
+ *
+ * @param host - Host name for the URL
+ * @param path - Absolute file path
+ * @param text - Visible text
+ * @return String - Escape sequence with the link
+ */
+pub fn hyperlink(host: &str, path: &Path, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", file_url(host, path), text)
+}
+
+/**
+ * file:// URL of a path
+ *
+ * @param host - Host name, or empty for the local host
+ * @param path - Absolute file path
+ * @return String - URL with reserved characters percent-encoded
+ */
+pub fn file_url(host: &str, path: &Path) -> String {
+    let mut url = format!("file://{}", host);
+    for byte in path.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => url.push(*byte as char),
+            other => url.push_str(&format!("%{:02X}", other)),
+        }
+    }
+    url
+}
+
+/**
+ * Stream a line of process output came from
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    /// Standard output
+    Stdout,
+    /// Standard error
+    Stderr,
+}
+
+/**
+ * Result of a streamed build
+ */
+#[derive(Debug, Clone)]
+pub struct BuildOutput {
+    /// Plain output, when it was collected instead of printed
+    pub output: String,
+    /// Exit code of the tool
+    pub exit_code: i32,
+}
+
+/**
+ * Runs a build tool with its output streamed to the terminal or collected
+ *
+ * @param command - Command with arguments and working directory set
+ * @param linker - Linker decorating the output
+ * @param terminal - Whether output goes to the terminal
+ * @return Result<BuildOutput> - Collected output and exit code, or a spawn error
+ */
+pub fn run_build(command: &mut Command, linker: &mut DiagnosticLinker, terminal: bool) -> Result<BuildOutput> {
+    let mut output = String::new();
+    let mut stdout = std::io::stdout();
+    let exit_code = run_streaming(command, |_, line| {
+        if terminal {
+            let _ = writeln!(stdout, "{}", linker.decorate(line));
+            let _ = stdout.flush();
+        } else {
+            linker.observe(line);
+            output.push_str(line);
+            output.push('\n');
+        }
+    })?;
+    Ok(BuildOutput { output, exit_code })
+}
+
+/**
+ * プロセスを実行して出力を1行ずつ渡す関数です
+ *
+ * 標準出力と標準エラーをそれぞれ読み取りスレッドで行単位に読み、
+ * チャネルで受け取った順にコールバックへ渡します。
+ *
+ * プロセスは独自のプロセスグループで起動し、Ctrl+Cが押されたら
+ * グループ全体にSIGINTを送ります（makeやcargoが起動したコンパイラも
+ * 止まるように）。標準入力は端末を奪わないようにnullにします。
+ *
+ * @param command - 実行するコマンド（引数と作業ディレクトリ設定済み）
+ * @param on_line - 行ごとに呼ばれるコールバック（改行なし）
+ * @return Result<i32> - 終了コード（シグナルの場合は128+番号）、または起動エラー
+ */
+pub fn run_streaming(command: &mut Command, mut on_line: impl FnMut(Stream, &str)) -> Result<i32> {
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!("{}: command not found", program),
+            _ => anyhow::anyhow!("{}: {}", program, error),
+        })?;
+
+    let (sender, lines) = mpsc::channel::<(Stream, String)>();
+    let mut readers = Vec::new();
+    let streams: [(Stream, Option<Box<dyn Read + Send>>); 2] = [
+        (Stream::Stdout, child.stdout.take().map(|stream| Box::new(stream) as Box<dyn Read + Send>)),
+        (Stream::Stderr, child.stderr.take().map(|stream| Box::new(stream) as Box<dyn Read + Send>)),
+    ];
+    for (kind, stream) in streams {
+        let Some(stream) = stream else {
+            continue;
+        };
+        let sender = sender.clone();
+        readers.push(thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            let mut buffer = Vec::new();
+            while reader.read_until(b'\n', &mut buffer).is_ok_and(|read| read > 0) {
+                let line = String::from_utf8_lossy(&buffer).trim_end_matches(['\n', '\r']).to_string();
+                if sender.send((kind, line)).is_err() {
+                    break;
+                }
+                buffer.clear();
+            }
+        }));
+    }
+    drop(sender);
+
+    let guard = InterruptGuard::install();
+    let mut interrupted = false;
+    loop {
+        if guard.interrupted() && !interrupted {
+            interrupted = true;
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGINT);
+            }
+        }
+        match lines.recv_timeout(Duration::from_millis(100)) {
+            Ok((kind, line)) => on_line(kind, &line),
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let status = child.wait()?;
+    drop(guard);
+
+    Ok(status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0)))
+}
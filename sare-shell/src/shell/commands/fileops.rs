@@ -0,0 +1,673 @@
+/*!
+ * @file fileops.rs
+ * @brief Copy, move and remove engine for cp, mv, rm and trash
+ *
+ * This module implements the file tree operations behind the `cp`,
+ * `mv` and `rm` builtins: recursive copies that can preserve modes,
+ * times, ownership and symlinks, moves that fall back to copy and
+ * delete across filesystems, and interactive removal.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file fileops.rs
+ * @description Recursive copy with attribute preservation, EXDEV-safe
+ * move, prompting removal and a shared operation log.
+ */
+
+use anyhow::Result;
+use std::ffi::CString;
+use std::fs::{self, Metadata};
+use std::io::{BufRead, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::Path;
+
+/**
+ * Messages produced while operating on files
+ */
+#[derive(Debug)]
+pub struct OperationLog {
+    /// Program name used to prefix error messages
+    program: &'static str,
+    /// Verbose notes and error messages in the order they happened
+    pub output: String,
+    /// Whether any operation failed
+    pub failed: bool,
+}
+
+impl OperationLog {
+    /**
+     * Creates an empty log
+     *
+     * @param program - Name used as the error prefix, e.g. "cp"
+     * @return OperationLog - Empty log
+     */
+    pub fn new(program: &'static str) -> Self {
+        OperationLog {
+            program,
+            output: String::new(),
+            failed: false,
+        }
+    }
+
+    /**
+     * Records a verbose note
+     *
+     * @param line - Note without trailing newline
+     */
+    pub fn note(&mut self, line: &str) {
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    /**
+     * Records an error and marks the operation failed
+     *
+     * @param message - Message without the program prefix
+     */
+    pub fn error(&mut self, message: &str) {
+        self.output.push_str(&format!("{}: {}\n", self.program, message));
+        self.failed = true;
+    }
+
+    /**
+     * Gets the exit code for the logged operations
+     *
+     * @return i32 - 1 if anything failed, otherwise 0
+     */
+    pub fn exit_code(&self) -> i32 {
+        if self.failed { 1 } else { 0 }
+    }
+}
+
+/**
+ * Asks yes/no questions for -i style options
+ *
+ * Answers are taken from piped input when there is any, otherwise
+ * from the shell's own stdin. Prompts go to stderr like coreutils.
+ */
+pub struct Prompter {
+    /// Remaining lines of piped input
+    answers: Option<std::vec::IntoIter<String>>,
+}
+
+impl Prompter {
+    /**
+     * Creates a prompter
+     *
+     * @param piped - Pipeline input to read answers from, if any
+     * @return Prompter - Prompter
+     */
+    pub fn new(piped: Option<String>) -> Self {
+        Prompter {
+            answers: piped.map(|text| text.lines().map(str::to_string).collect::<Vec<_>>().into_iter()),
+        }
+    }
+
+    /**
+     * Asks a question and reads the answer
+     *
+     * @param question - Question text, e.g. "rm: remove regular file 'a'? "
+     * @return bool - True if the answer starts with y or Y
+     */
+    pub fn confirm(&mut self, question: &str) -> bool {
+        let mut stderr = std::io::stderr();
+        let _ = stderr.write_all(question.as_bytes());
+        let _ = stderr.flush();
+
+        let answer = match &mut self.answers {
+            Some(answers) => answers.next().unwrap_or_default(),
+            None => {
+                let mut line = String::new();
+                let _ = std::io::stdin().lock().read_line(&mut line);
+                line
+            }
+        };
+        answer.trim_start().starts_with(['y', 'Y'])
+    }
+}
+
+/**
+ * Options for copying
+ */
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+    /// Copy directories recursively (-r, -R, -a)
+    pub recursive: bool,
+    /// Follow symlinks in the source instead of copying them as links
+    pub follow_links: bool,
+    /// Keep mode, ownership and timestamps (-p, -a)
+    pub preserve: bool,
+    /// Never overwrite an existing destination (-n)
+    pub no_clobber: bool,
+    /// Only replace destinations older than the source (-u)
+    pub update: bool,
+    /// Report each copied file (-v)
+    pub verbose: bool,
+}
+
+impl CopyOptions {
+    /**
+     * Gets the options used when a move has to copy across filesystems
+     *
+     * @return CopyOptions - Recursive, link-preserving, attribute-preserving copy
+     */
+    pub fn archive() -> Self {
+        CopyOptions {
+            recursive: true,
+            follow_links: false,
+            preserve: true,
+            ..CopyOptions::default()
+        }
+    }
+}
+
+/**
+ * Options for moving
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MoveOptions {
+    /// Prompt before overwriting (-i)
+    pub interactive: bool,
+    /// Never overwrite an existing destination (-n)
+    pub no_clobber: bool,
+    /// Only replace destinations older than the source (-u)
+    pub update: bool,
+    /// Report each move (-v)
+    pub verbose: bool,
+}
+
+/**
+ * Options for removing
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RemoveOptions {
+    /// Remove directories and their contents (-r, -R)
+    pub recursive: bool,
+    /// Remove empty directories (-d)
+    pub empty_directories: bool,
+    /// Prompt before every removal (-i)
+    pub interactive: bool,
+    /// Ignore missing files (-f)
+    pub force: bool,
+    /// Report each removal (-v)
+    pub verbose: bool,
+}
+
+/**
+ * ファイルまたはディレクトリをコピーする関数です
+ *
+ * ディレクトリは-rが指定されている場合のみ再帰的にコピーし、
+ * 自分自身の中へのコピーは拒否します。シンボリックリンクは
+ * follow_linksがfalseの場合リンクとして複製し、FIFOや
+ * デバイスファイルはmknodで作り直します。
+ *
+ * 既存のコピー先は-nでは残し、-uではコピー元より新しい
+ * 場合に残します。-pではモード、所有者、タイムスタンプを
+ * 保持し、ディレクトリの属性は中身をコピーした後に設定します。
+ *
+ * 途中のエラーはログに記録して残りのコピーを続けます。
+ *
+ * @param source - コピー元のパス
+ * @param destination - コピー先のパス
+ * @param names - ログに表示するコピー元とコピー先の名前
+ * @param options - コピーオプション
+ * @param log - 操作ログ
+ */
+pub fn copy_path(source: &Path, destination: &Path, names: (&str, &str), options: &CopyOptions, log: &mut OperationLog) {
+    let (source_name, destination_name) = names;
+    let metadata = match if options.follow_links { fs::metadata(source) } else { fs::symlink_metadata(source) } {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            log.error(&format!("cannot stat '{}': {}", source_name, describe_error(&error)));
+            return;
+        }
+    };
+    let existing = fs::symlink_metadata(destination).ok();
+
+    if metadata.is_dir() {
+        if !options.recursive {
+            log.error(&format!("-r not specified; omitting directory '{}'", source_name));
+            return;
+        }
+        if is_inside(destination, source) {
+            log.error(&format!(
+                "cannot copy a directory, '{}', into itself, '{}'",
+                source_name, destination_name
+            ));
+            return;
+        }
+
+        match &existing {
+            Some(existing) if !existing.is_dir() => {
+                log.error(&format!(
+                    "cannot overwrite non-directory '{}' with directory '{}'",
+                    destination_name, source_name
+                ));
+                return;
+            }
+            Some(_) => {}
+            None => {
+                if let Err(error) = fs::create_dir(destination) {
+                    log.error(&format!("cannot create directory '{}': {}", destination_name, describe_error(&error)));
+                    return;
+                }
+                if options.verbose {
+                    log.note(&format!("'{}' -> '{}'", source_name, destination_name));
+                }
+            }
+        }
+
+        let mut children: Vec<_> = match fs::read_dir(source) {
+            Ok(reader) => reader.flatten().map(|entry| entry.file_name()).collect(),
+            Err(error) => {
+                log.error(&format!("cannot access '{}': {}", source_name, describe_error(&error)));
+                return;
+            }
+        };
+        children.sort();
+        let child_options = CopyOptions {
+            follow_links: false,
+            ..options.clone()
+        };
+        for child in children {
+            let child_name = child.to_string_lossy();
+            copy_path(
+                &source.join(&child),
+                &destination.join(&child),
+                (&join_name(source_name, &child_name), &join_name(destination_name, &child_name)),
+                &child_options,
+                log,
+            );
+        }
+
+        if options.preserve {
+            if let Err(error) = preserve_attributes(destination, &metadata) {
+                log.error(&format!("preserving attributes for '{}': {}", destination_name, describe_error(&error)));
+            }
+        }
+        return;
+    }
+
+    if let Some(existing) = &existing {
+        if existing.dev() == metadata.dev() && existing.ino() == metadata.ino() {
+            log.error(&format!("'{}' and '{}' are the same file", source_name, destination_name));
+            return;
+        }
+        if existing.is_dir() {
+            log.error(&format!(
+                "cannot overwrite directory '{}' with non-directory",
+                destination_name
+            ));
+            return;
+        }
+        if options.no_clobber || (options.update && !is_newer(&metadata, existing)) {
+            return;
+        }
+    }
+
+    let file_type = metadata.file_type();
+    let copied = if file_type.is_file() {
+        fs::copy(source, destination).map(|_| ())
+    } else {
+        if existing.is_some() {
+            let _ = fs::remove_file(destination);
+        }
+        if file_type.is_symlink() {
+            fs::read_link(source).and_then(|target| std::os::unix::fs::symlink(target, destination))
+        } else {
+            make_node(destination, &metadata)
+        }
+    };
+
+    match copied {
+        Ok(()) => {
+            if options.verbose {
+                log.note(&format!("'{}' -> '{}'", source_name, destination_name));
+            }
+            if options.preserve {
+                if let Err(error) = preserve_attributes(destination, &metadata) {
+                    log.error(&format!("preserving attributes for '{}': {}", destination_name, describe_error(&error)));
+                }
+            }
+        }
+        Err(error) => log.error(&format!(
+            "cannot create '{}': {}",
+            destination_name,
+            describe_error(&error)
+        )),
+    }
+}
+
+/**
+ * ファイルまたはディレクトリを移動する関数です
+ *
+ * まずrenameを試み、異なるファイルシステム間（EXDEV）の
+ * 場合は属性を保持したコピーの後に元のファイルを削除します。
+ * コピーに失敗した場合は元のファイルを残します。
+ *
+ * 既存のコピー先は-nでは残し、-uではコピー元より新しい
+ * 場合に残し、-iでは上書きするかどうかを確認します。
+ *
+ * @param source - 移動元のパス
+ * @param destination - 移動先のパス
+ * @param names - ログに表示する移動元と移動先の名前
+ * @param options - 移動オプション
+ * @param prompter - -iの確認に使うプロンプト
+ * @param log - 操作ログ
+ */
+pub fn move_path(
+    source: &Path,
+    destination: &Path,
+    names: (&str, &str),
+    options: &MoveOptions,
+    prompter: &mut Prompter,
+    log: &mut OperationLog,
+) {
+    let (source_name, destination_name) = names;
+    let metadata = match fs::symlink_metadata(source) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            log.error(&format!("cannot stat '{}': {}", source_name, describe_error(&error)));
+            return;
+        }
+    };
+
+    if metadata.is_dir() && is_inside(destination, source) {
+        log.error(&format!(
+            "cannot move '{}' to a subdirectory of itself, '{}'",
+            source_name, destination_name
+        ));
+        return;
+    }
+
+    if let Ok(existing) = fs::symlink_metadata(destination) {
+        if existing.dev() == metadata.dev() && existing.ino() == metadata.ino() {
+            log.error(&format!("'{}' and '{}' are the same file", source_name, destination_name));
+            return;
+        }
+        if options.no_clobber || (options.update && !is_newer(&metadata, &existing)) {
+            return;
+        }
+        if options.interactive && !prompter.confirm(&format!("mv: overwrite '{}'? ", destination_name)) {
+            return;
+        }
+    }
+
+    let moved = match fs::rename(source, destination) {
+        Err(error) if error.raw_os_error() == Some(libc::EXDEV) => move_across_devices(source, destination, &metadata),
+        result => result.map_err(|error| anyhow::anyhow!("{}", describe_error(&error))),
+    };
+
+    match moved {
+        Ok(()) => {
+            if options.verbose {
+                log.note(&format!("renamed '{}' -> '{}'", source_name, destination_name));
+            }
+        }
+        Err(error) => log.error(&format!("cannot move '{}' to '{}': {}", source_name, destination_name, error)),
+    }
+}
+
+/**
+ * Moves a file to another filesystem by copying and deleting
+ *
+ * @param source - Path to move
+ * @param destination - New path on the other filesystem
+ * @param metadata - lstat metadata of the source
+ * @return Result<()> - Success, or the first copy error with the source left in place
+ */
+pub fn move_across_devices(source: &Path, destination: &Path, metadata: &Metadata) -> Result<()> {
+    if let Ok(existing) = fs::symlink_metadata(destination) {
+        match (metadata.is_dir(), existing.is_dir()) {
+            (true, true) => fs::remove_dir(destination)
+                .map_err(|error| anyhow::anyhow!("cannot replace '{}': {}", destination.display(), describe_error(&error)))?,
+            (true, false) => return Err(anyhow::anyhow!("cannot overwrite non-directory with directory")),
+            (false, true) => return Err(anyhow::anyhow!("cannot overwrite directory with non-directory")),
+            (false, false) => {}
+        }
+    }
+
+    let mut copy_log = OperationLog::new("mv");
+    let source_name = source.to_string_lossy();
+    let destination_name = destination.to_string_lossy();
+    copy_path(source, destination, (&source_name, &destination_name), &CopyOptions::archive(), &mut copy_log);
+    if copy_log.failed {
+        let first_error = copy_log.output.lines().next().unwrap_or_default();
+        return Err(anyhow::anyhow!("{}", first_error.trim_start_matches("mv: ")));
+    }
+
+    let removed = if metadata.is_dir() { fs::remove_dir_all(source) } else { fs::remove_file(source) };
+    removed.map_err(|error| anyhow::anyhow!("cannot remove '{}': {}", source.display(), describe_error(&error)))
+}
+
+/**
+ * ファイルまたはディレクトリを削除する関数です
+ *
+ * ディレクトリは-rの場合に中身ごと、-dの場合は空のときのみ
+ * 削除します。-iでは中に入る前と各ファイルの削除前に確認し、
+ * 中身が残ったディレクトリは削除しません。
+ *
+ * @param path - 削除するパス
+ * @param name - ログとプロンプトに表示する名前
+ * @param options - 削除オプション
+ * @param prompter - -iの確認に使うプロンプト
+ * @param log - 操作ログ
+ * @return bool - 削除した場合はtrue
+ */
+pub fn remove_path(path: &Path, name: &str, options: &RemoveOptions, prompter: &mut Prompter, log: &mut OperationLog) -> bool {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            if !(options.force && error.kind() == std::io::ErrorKind::NotFound) {
+                log.error(&format!("cannot remove '{}': {}", name, describe_error(&error)));
+            }
+            return false;
+        }
+    };
+
+    if metadata.is_dir() {
+        let is_empty = fs::read_dir(path).map(|mut reader| reader.next().is_none()).unwrap_or(false);
+        let removable = options.recursive || (options.empty_directories && is_empty);
+        if !removable {
+            let reason = if options.empty_directories { "Directory not empty" } else { "Is a directory" };
+            log.error(&format!("cannot remove '{}': {}", name, reason));
+            return false;
+        }
+
+        if !is_empty {
+            if options.interactive && !prompter.confirm(&format!("rm: descend into directory '{}'? ", name)) {
+                return false;
+            }
+            let mut children: Vec<_> = match fs::read_dir(path) {
+                Ok(reader) => reader.flatten().map(|entry| entry.file_name()).collect(),
+                Err(error) => {
+                    log.error(&format!("cannot remove '{}': {}", name, describe_error(&error)));
+                    return false;
+                }
+            };
+            children.sort();
+            let mut all_removed = true;
+            for child in children {
+                all_removed &= remove_path(&path.join(&child), &join_name(name, &child.to_string_lossy()), options, prompter, log);
+            }
+            if !all_removed {
+                return false;
+            }
+        }
+
+        if options.interactive && !prompter.confirm(&format!("rm: remove directory '{}'? ", name)) {
+            return false;
+        }
+        return match fs::remove_dir(path) {
+            Ok(()) => {
+                if options.verbose {
+                    log.note(&format!("removed directory '{}'", name));
+                }
+                true
+            }
+            Err(error) => {
+                log.error(&format!("cannot remove '{}': {}", name, describe_error(&error)));
+                false
+            }
+        };
+    }
+
+    if options.interactive && !prompter.confirm(&format!("rm: remove {} '{}'? ", kind_name(&metadata), name)) {
+        return false;
+    }
+    match fs::remove_file(path) {
+        Ok(()) => {
+            if options.verbose {
+                log.note(&format!("removed '{}'", name));
+            }
+            true
+        }
+        Err(error) => {
+            log.error(&format!("cannot remove '{}': {}", name, describe_error(&error)));
+            false
+        }
+    }
+}
+
+/**
+ * Describes a file's type the way rm prompts do
+ *
+ * @param metadata - lstat metadata
+ * @return &str - e.g. "regular file", "regular empty file", "symbolic link"
+ */
+pub fn kind_name(metadata: &Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_symlink() {
+        "symbolic link"
+    } else if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() {
+        "block special file"
+    } else if file_type.is_char_device() {
+        "character special file"
+    } else if metadata.len() == 0 {
+        "regular empty file"
+    } else {
+        "regular file"
+    }
+}
+
+/**
+ * Describes an I/O error without the "(os error N)" suffix
+ *
+ * @param error - I/O error
+ * @return String - e.g. "No such file or directory"
+ */
+pub fn describe_error(error: &std::io::Error) -> String {
+    let message = error.to_string();
+    match message.find(" (os error") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+/**
+ * Joins a printed name and a child name
+ *
+ * @param parent - Parent as printed
+ * @param child - Child file name
+ * @return String - "parent/child" without doubled slashes
+ */
+fn join_name(parent: &str, child: &str) -> String {
+    if parent.ends_with('/') {
+        format!("{}{}", parent, child)
+    } else {
+        format!("{}/{}", parent, child)
+    }
+}
+
+/**
+ * Checks whether a path is the directory itself or lies inside it
+ *
+ * @param path - Path that may be inside
+ * @param directory - Directory
+ * @return bool - True if path is directory or one of its descendants
+ */
+fn is_inside(path: &Path, directory: &Path) -> bool {
+    let Ok(directory) = directory.canonicalize() else {
+        return false;
+    };
+    let resolved = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent.canonicalize().map(|parent| parent.join(name)),
+        _ => path.canonicalize(),
+    };
+    resolved.map(|path| path.starts_with(&directory)).unwrap_or(false)
+}
+
+/**
+ * Checks whether the source was modified after the destination
+ *
+ * @param source - Source metadata
+ * @param destination - Destination metadata
+ * @return bool - True if the source is newer
+ */
+fn is_newer(source: &Metadata, destination: &Metadata) -> bool {
+    (source.mtime(), source.mtime_nsec()) > (destination.mtime(), destination.mtime_nsec())
+}
+
+/**
+ * Converts a path to a C string for libc calls
+ *
+ * @param path - Path
+ * @return std::io::Result<CString> - C string, or an error for embedded NULs
+ */
+fn c_path(path: &Path) -> std::io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))
+}
+
+/**
+ * Recreates a FIFO, socket or device node
+ *
+ * @param destination - Path to create
+ * @param metadata - Metadata of the original node
+ * @return std::io::Result<()> - Success or the mknod error
+ */
+fn make_node(destination: &Path, metadata: &Metadata) -> std::io::Result<()> {
+    let path = c_path(destination)?;
+    let result = unsafe { libc::mknod(path.as_ptr(), metadata.mode() as libc::mode_t, metadata.rdev() as libc::dev_t) };
+    if result == 0 { Ok(()) } else { Err(std::io::Error::last_os_error()) }
+}
+
+/**
+ * Copies ownership, mode and timestamps onto a copy
+ *
+ * Ownership changes that need privileges are skipped silently, as
+ * cp -p does for unprivileged users.
+ *
+ * @param destination - Copied file
+ * @param metadata - lstat metadata of the original
+ * @return std::io::Result<()> - Success or the first mode/time error
+ */
+fn preserve_attributes(destination: &Path, metadata: &Metadata) -> std::io::Result<()> {
+    let path = c_path(destination)?;
+    unsafe {
+        libc::lchown(path.as_ptr(), metadata.uid(), metadata.gid());
+    }
+
+    if !metadata.file_type().is_symlink() {
+        fs::set_permissions(destination, fs::Permissions::from_mode(metadata.mode() & 0o7777))?;
+    }
+
+    let times = [
+        libc::timespec {
+            tv_sec: metadata.atime() as libc::time_t,
+            tv_nsec: metadata.atime_nsec() as _,
+        },
+        libc::timespec {
+            tv_sec: metadata.mtime() as libc::time_t,
+            tv_nsec: metadata.mtime_nsec() as _,
+        },
+    ];
+    let result = unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW) };
+    if result == 0 { Ok(()) } else { Err(std::io::Error::last_os_error()) }
+}
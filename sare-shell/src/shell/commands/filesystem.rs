@@ -13,10 +13,17 @@
  */
 
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::shell::parser::ParsedCommand;
 use crate::shell::Shell;
-use crate::shell::commands::{CommandHandler, CommandResult};
+use crate::shell::commands::{command_from_words, CommandHandler, CommandResult};
+use crate::shell::commands::fileops::{
+    copy_path, describe_error, kind_name, move_path, remove_path, CopyOptions, MoveOptions, OperationLog, Prompter, RemoveOptions,
+};
+use crate::shell::commands::listing::{self, terminal_columns, HiddenEntries, ListOptions, LsColors, SortKey};
+use crate::shell::commands::find::FindProgram;
+use crate::shell::commands::trash::Trash;
+use crate::shell::dirstack::{resolve_cd_target, tilde_path, FrecencyOrder};
 
 /**
  * ディレクトリを変更するcdコマンドです
@@ -25,50 +32,68 @@ use crate::shell::commands::{CommandHandler, CommandResult};
  * 絶対パスと相対パスの両方をサポートします。
  * 
  * ホームディレクトリ（~）、親ディレクトリ（..）、
- * 現在のディレクトリ（.）などの特殊パスを適切に
- * 解決します。
+ * 直前のディレクトリ（-）などの特殊パスを解決し、
+ * 相対パスではCDPATHも検索します。
  * 
- * ディレクトリが存在しない場合や、ディレクトリでない
- * 場合はエラーを返します。成功時は新しいパスを
- * シェルの作業ディレクトリに設定します。
+ * デフォルトは論理パス（-L）で、シンボリックリンクを
+ * 辿った経路をそのまま保持します。-Pを指定すると
+ * 物理パスに解決します。成功時はOLDPWDとPWDを更新し、
+ * frecencyデータベースに記録します。
  */
 pub struct CdCommand;
 
 impl CommandHandler for CdCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        let default_path = "~".to_string();
-        let path = command.args.first().unwrap_or(&default_path);
+        let mut physical = false;
+        let mut target = None;
         
-        let target_path = if path == "~" {
-            dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
-        } else {
-            let path_buf = PathBuf::from(path);
-            if path_buf.is_absolute() {
-                path_buf
-            } else {
-                shell.current_path().join(path_buf)
+        for arg in &command.args {
+            match arg.as_str() {
+                "-P" => physical = true,
+                "-L" => physical = false,
+                "--" => {}
+                _ if target.is_none() => target = Some(arg.clone()),
+                _ => return Err(anyhow::anyhow!("too many arguments")),
             }
+        }
+        
+        let target = target.unwrap_or_else(|| "~".to_string());
+        
+        let (new_path, announce) = if target == "-" {
+            let previous = shell.previous_path()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("OLDPWD not set"))?;
+            let previous = if physical { previous.canonicalize()? } else { previous };
+            (previous, true)
+        } else {
+            let cdpath = std::env::var("CDPATH").ok();
+            resolve_cd_target(shell.current_path(), &target, cdpath.as_deref(), physical)?
         };
         
-        if target_path.exists() && target_path.is_dir() {
-            let canonical_path = target_path.canonicalize()?;
-            std::env::set_current_dir(&canonical_path)?;
-            *shell.current_path_mut() = canonical_path.clone();
-            
-            Ok(CommandResult {
-                output: format!("Changed directory to: {}", canonical_path.display()),
-                exit_code: 0,
-            })
+        shell.set_working_directory(new_path)?;
+        
+        let output = if announce {
+            format!("{}\n", shell.current_path().display())
         } else {
-            Err(anyhow::anyhow!("Directory not found: {}", path))
-        }
+            format!("Changed directory to: {}", shell.current_path().display())
+        };
+        
+        Ok(CommandResult {
+            output,
+            exit_code: 0,
+        })
     }
     
     fn help(&self) -> &str {
-        "cd [directory] - Change working directory\n\
+        "cd [-L|-P] [directory] - Change working directory\n\
          Usage: cd /path/to/directory\n\
          Usage: cd ~ (go to home directory)\n\
-         Usage: cd .. (go to parent directory)"
+         Usage: cd .. (go to parent directory)\n\
+         Usage: cd - (go to previous directory)\n\
+         Options:\n\
+         -L    Follow symbolic links logically (default)\n\
+         -P    Resolve symbolic links to the physical directory\n\
+         Relative names are also searched for in CDPATH."
     }
     
     fn name(&self) -> &str {
@@ -76,6 +101,350 @@ impl CommandHandler for CdCommand {
     }
 }
 
+/**
+ * Builds the full directory list shown by dirs
+ * 
+ * Index 0 is the current directory, followed by the saved stack.
+ * 
+ * @param shell - Shell instance
+ * @return Vec<PathBuf> - Current directory and stack entries
+ */
+fn full_directory_stack(shell: &Shell) -> Vec<PathBuf> {
+    let mut stack = vec![shell.current_path().clone()];
+    stack.extend(shell.directory_stack().entries().iter().cloned());
+    stack
+}
+
+/**
+ * Parses a `+N` or `-N` stack index into a position in the full list
+ * 
+ * `+N` counts from the left starting at zero, `-N` from the right.
+ * 
+ * @param arg - Index argument
+ * @param len - Length of the full directory list
+ * @return Option<Result<usize>> - None if the argument is not an index
+ */
+fn parse_stack_index(arg: &str, len: usize) -> Option<Result<usize>> {
+    let (from_left, digits) = match arg.chars().next() {
+        Some('+') => (true, &arg[1..]),
+        Some('-') => (false, &arg[1..]),
+        _ => return None,
+    };
+    
+    let n = digits.parse::<usize>().ok()?;
+    if n >= len {
+        return Some(Err(anyhow::anyhow!("{}: directory stack index out of range", arg)));
+    }
+    
+    Some(Ok(if from_left { n } else { len - 1 - n }))
+}
+
+/**
+ * Formats the directory stack on one line like `dirs`
+ * 
+ * @param shell - Shell instance
+ * @return String - Space separated stack, home abbreviated as `~`
+ */
+fn format_directory_stack(shell: &Shell) -> String {
+    let entries: Vec<String> = full_directory_stack(shell)
+        .iter()
+        .map(|path| tilde_path(path))
+        .collect();
+    format!("{}\n", entries.join(" "))
+}
+
+/**
+ * ディレクトリスタックに追加するpushdコマンドです
+ * 
+ * 現在のディレクトリをスタックに保存して、指定された
+ * ディレクトリに移動します。
+ * 
+ * 引数なしの場合は現在のディレクトリとスタックの先頭を
+ * 入れ替えます。+N/-Nを指定するとスタック全体を回転させ、
+ * N番目のディレクトリを先頭にします。-nを指定すると
+ * ディレクトリを変更せずにスタックだけを操作します。
+ * 
+ * 実行後はdirsと同じ形式でスタックを表示します。
+ */
+pub struct PushdCommand;
+
+impl CommandHandler for PushdCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let no_change = command.args.iter().any(|arg| arg == "-n");
+        let target = command.args.iter().find(|arg| arg.as_str() != "-n");
+        let mut stack = full_directory_stack(shell);
+        
+        match target {
+            None => {
+                if stack.len() < 2 {
+                    return Err(anyhow::anyhow!("no other directory"));
+                }
+                stack.swap(0, 1);
+            }
+            Some(arg) => match parse_stack_index(arg, stack.len()) {
+                Some(index) => stack.rotate_left(index?),
+                None => {
+                    let cdpath = std::env::var("CDPATH").ok();
+                    let (path, _) = resolve_cd_target(shell.current_path(), arg, cdpath.as_deref(), false)?;
+                    if no_change {
+                        stack.insert(1, path);
+                    } else {
+                        stack.insert(0, path);
+                    }
+                }
+            },
+        }
+        
+        if !no_change && stack[0] != *shell.current_path() {
+            shell.set_working_directory(stack[0].clone())?;
+        }
+        stack.remove(0);
+        shell.directory_stack_mut().set_entries(stack);
+        
+        Ok(CommandResult {
+            output: format_directory_stack(shell),
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "pushd [-n] [directory | +N | -N] - Push directory onto the stack\n\
+         Usage: pushd dir (save current directory and change to dir)\n\
+         Usage: pushd (swap the top two directories)\n\
+         Options:\n\
+         +N    Rotate the stack so the Nth entry from the left is on top\n\
+         -N    Rotate the stack so the Nth entry from the right is on top\n\
+         -n    Manipulate the stack without changing directory"
+    }
+    
+    fn name(&self) -> &str {
+        "pushd"
+    }
+}
+
+/**
+ * Pop directory command
+ * 
+ * Removes the top entry (or the +N/-N entry) from the directory
+ * stack and changes to the new top directory.
+ */
+pub struct PopdCommand;
+
+impl CommandHandler for PopdCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let no_change = command.args.iter().any(|arg| arg == "-n");
+        let mut stack = full_directory_stack(shell);
+        
+        if stack.len() < 2 {
+            return Err(anyhow::anyhow!("directory stack empty"));
+        }
+        
+        let default_index = if no_change { 1 } else { 0 };
+        let index = match command.args.iter().find(|arg| arg.as_str() != "-n") {
+            Some(arg) => parse_stack_index(arg, stack.len())
+                .ok_or_else(|| anyhow::anyhow!("{}: invalid argument", arg))??,
+            None => default_index,
+        };
+        
+        if index == 0 && !no_change {
+            stack.remove(0);
+            shell.set_working_directory(stack[0].clone())?;
+        } else {
+            // With -n the current directory always stays in place
+            stack.remove(index.max(1));
+        }
+        stack.remove(0);
+        shell.directory_stack_mut().set_entries(stack);
+        
+        Ok(CommandResult {
+            output: format_directory_stack(shell),
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "popd [-n] [+N | -N] - Pop directory off the stack\n\
+         Usage: popd (remove the top entry and change to the next)\n\
+         Options:\n\
+         +N    Remove the Nth entry counting from the left\n\
+         -N    Remove the Nth entry counting from the right\n\
+         -n    Manipulate the stack without changing directory"
+    }
+    
+    fn name(&self) -> &str {
+        "popd"
+    }
+}
+
+/**
+ * Display directory stack command
+ * 
+ * Shows the current directory followed by the pushd stack.
+ */
+pub struct DirsCommand;
+
+impl CommandHandler for DirsCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut verbose = false;
+        let mut per_line = false;
+        let mut long_names = false;
+        let mut selected = None;
+        
+        for arg in &command.args {
+            match arg.as_str() {
+                "-c" => {
+                    shell.directory_stack_mut().clear();
+                    return Ok(CommandResult {
+                        output: String::new(),
+                        exit_code: 0,
+                    });
+                }
+                "-v" => verbose = true,
+                "-p" => per_line = true,
+                "-l" => long_names = true,
+                _ => match parse_stack_index(arg, full_directory_stack(shell).len()) {
+                    Some(index) => selected = Some(index?),
+                    None => return Err(anyhow::anyhow!("{}: invalid option", arg)),
+                },
+            }
+        }
+        
+        let display = |path: &PathBuf| {
+            if long_names { path.display().to_string() } else { tilde_path(path) }
+        };
+        let stack = full_directory_stack(shell);
+        
+        let output = if let Some(index) = selected {
+            format!("{}\n", display(&stack[index]))
+        } else if verbose {
+            stack.iter()
+                .enumerate()
+                .map(|(i, path)| format!("{:2}  {}\n", i, display(path)))
+                .collect()
+        } else if per_line {
+            stack.iter().map(|path| format!("{}\n", display(path))).collect()
+        } else {
+            let entries: Vec<String> = stack.iter().map(display).collect();
+            format!("{}\n", entries.join(" "))
+        };
+        
+        Ok(CommandResult {
+            output,
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "dirs [-clpv] [+N | -N] - Display the directory stack\n\
+         Options:\n\
+         -c    Clear the directory stack\n\
+         -l    Show full paths instead of using ~\n\
+         -p    Print one entry per line\n\
+         -v    Print one entry per line with its stack index\n\
+         +N    Show the Nth entry counting from the left\n\
+         -N    Show the Nth entry counting from the right"
+    }
+    
+    fn name(&self) -> &str {
+        "dirs"
+    }
+}
+
+/**
+ * よく使うディレクトリにジャンプするzコマンドです
+ * 
+ * cdで訪れたディレクトリの訪問回数と最終訪問時刻から
+ * frecencyスコアを計算し、キーワードに一致する中で
+ * 最もスコアの高いディレクトリに移動します。
+ * 
+ * -lで一致する候補をスコア付きで一覧表示し、-rは訪問回数のみ、
+ * -tは最終訪問時刻のみで順位付けします。-xは現在の
+ * ディレクトリをデータベースから削除し、-eは移動せずに
+ * 最適な候補を表示します。
+ * 
+ * データベースは履歴ファイルと同じディレクトリの.sare_zに
+ * 保存され、SARE_Z_DISABLE=1で無効化できます。
+ */
+pub struct ZCommand;
+
+impl CommandHandler for ZCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut order = FrecencyOrder::Frecent;
+        let mut list = false;
+        let mut echo = false;
+        let mut terms = Vec::new();
+        
+        for arg in &command.args {
+            match arg.as_str() {
+                "-l" => list = true,
+                "-r" => order = FrecencyOrder::Rank,
+                "-t" => order = FrecencyOrder::Recent,
+                "-e" => echo = true,
+                "-x" => {
+                    let current = shell.current_path().clone();
+                    let removed = shell.frecency_database_mut().remove(&current)?;
+                    return Ok(CommandResult {
+                        output: String::new(),
+                        exit_code: if removed { 0 } else { 1 },
+                    });
+                }
+                _ => terms.push(arg.clone()),
+            }
+        }
+        
+        if !shell.frecency_database().is_enabled() {
+            return Err(anyhow::anyhow!("directory tracking is disabled (SARE_Z_DISABLE)"));
+        }
+        
+        let matches = shell.frecency_database().query(&terms, order);
+        
+        if list || terms.is_empty() {
+            let mut output = String::new();
+            for (score, path) in matches.iter().rev() {
+                output.push_str(&format!("{:<10.1} {}\n", score, path.display()));
+            }
+            return Ok(CommandResult {
+                output,
+                exit_code: if matches.is_empty() { 1 } else { 0 },
+            });
+        }
+        
+        let (_, best) = matches
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("no match for: {}", terms.join(" ")))?;
+        
+        if echo {
+            return Ok(CommandResult {
+                output: format!("{}\n", best.display()),
+                exit_code: 0,
+            });
+        }
+        
+        shell.set_working_directory(best)?;
+        
+        Ok(CommandResult {
+            output: format!("{}\n", shell.current_path().display()),
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "z [options] [keywords...] - Jump to a frequently used directory\n\
+         Directories are ranked by how often and how recently you cd into them.\n\
+         Options:\n\
+         -l    List matching directories with their scores\n\
+         -r    Rank by visit count only\n\
+         -t    Rank by most recent visit only\n\
+         -e    Print the best match without changing directory\n\
+         -x    Remove the current directory from the database"
+    }
+    
+    fn name(&self) -> &str {
+        "z"
+    }
+}
+
 /**
  * Print working directory command
  * 
@@ -104,62 +473,132 @@ impl CommandHandler for PwdCommand {
 }
 
 /**
- * List directory contents command
+ * ディレクトリの内容を一覧表示するlsコマンドです
+ * 
+ * パスやグロブパターンの引数を受け付け、ファイル名を
+ * 端末幅に合わせた複数列、または-lで長い形式で表示します。
  * 
- * Implements the ls command for listing directory contents.
- * Supports various flags and formatting options.
+ * 端末に出力する場合はLS_COLORSに従って色を付け、パイプや
+ * ファイルへの出力では1行に1つずつ表示します。
  */
 pub struct LsCommand;
 
 impl CommandHandler for LsCommand {
+    /**
+     * lsコマンドを実行する関数です
+     *
+     * -a、-A、-l、-R、-h、-t、-S、-r、-1、-d、-Fと
+     * --color[=WHEN]を解析し、オペランドのグロブパターンを
+     * 現在のディレクトリで展開してから一覧を作成します。
+     *
+     * 一致しないパターンはそのまま渡され、存在しないファイルと
+     * して報告されます。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        let mut output = String::new();
-        let current_path = shell.current_path();
-        
-        let show_hidden = command.args.iter().any(|arg| arg == "-a" || arg == "--all");
-        let long_format = command.args.iter().any(|arg| arg == "-l" || arg == "--long");
-        
-        let entries = std::fs::read_dir(current_path)?;
+        let to_terminal = shell.output_is_terminal();
+        let mut options = ListOptions {
+            hidden: HiddenEntries::Skip,
+            long_format: false,
+            human_readable: false,
+            recursive: false,
+            reverse: false,
+            directory: false,
+            classify: false,
+            sort: SortKey::Name,
+            columns: to_terminal,
+            line_width: terminal_columns(shell.get_environment_variable("COLUMNS")),
+            colors: None,
+            records: !to_terminal,
+        };
+        let mut color = to_terminal;
+        let mut patterns = Vec::new();
+        let mut options_done = false;
         
-        for entry in entries {
-            let entry = entry?;
-            let file_name = entry.file_name().to_string_lossy().to_string();
+        for arg in &command.args {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                patterns.push(arg.clone());
+                continue;
+            }
+            if arg == "--" {
+                options_done = true;
+                continue;
+            }
             
-            if !show_hidden && file_name.starts_with('.') {
+            if let Some(long) = arg.strip_prefix("--") {
+                match long {
+                    "all" => options.hidden = HiddenEntries::All,
+                    "almost-all" => options.hidden = HiddenEntries::AlmostAll,
+                    "long" => options.long_format = true,
+                    "human-readable" | "human" => options.human_readable = true,
+                    "recursive" => options.recursive = true,
+                    "reverse" => options.reverse = true,
+                    "directory" => options.directory = true,
+                    "classify" => options.classify = true,
+                    "color" | "color=always" | "color=yes" | "color=force" => color = true,
+                    "color=never" | "color=no" | "color=none" => color = false,
+                    "color=auto" | "color=tty" | "color=if-tty" => color = to_terminal,
+                    _ => return Err(anyhow::anyhow!("unrecognized option '{}'", arg)),
+                }
                 continue;
             }
             
-            if long_format {
-                let metadata = entry.metadata()?;
-                let permissions = format_permissions(&metadata);
-                let size = metadata.len();
-                let modified = metadata.modified()?;
-                let modified_str = format_datetime(modified);
-                
-                output.push_str(&format!("{} {} {} {} {}\n", 
-                    permissions, metadata.file_type().is_dir() as i32, 
-                    size, modified_str, file_name));
-            } else {
-                output.push_str(&format!("{}  ", file_name));
+            for flag in arg[1..].chars() {
+                match flag {
+                    'a' => options.hidden = HiddenEntries::All,
+                    'A' => options.hidden = HiddenEntries::AlmostAll,
+                    'l' => options.long_format = true,
+                    'h' => options.human_readable = true,
+                    'R' => options.recursive = true,
+                    'r' => options.reverse = true,
+                    'd' => options.directory = true,
+                    'F' => options.classify = true,
+                    't' => options.sort = SortKey::Time,
+                    'S' => options.sort = SortKey::Size,
+                    '1' => options.columns = false,
+                    _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+                }
             }
         }
         
-        if !long_format {
-            output.push('\n');
+        if color {
+            options.colors = Some(LsColors::from_spec(shell.get_environment_variable("LS_COLORS")));
+        }
+        
+        let current_path = shell.current_path();
+        let mut operands = Vec::new();
+        for pattern in patterns {
+            operands.extend(expand_operand(&pattern, current_path));
         }
         
+        let listing = listing::list(&operands, &options);
+        if let Some(records) = listing.records {
+            shell.emit_records(records);
+        }
         Ok(CommandResult {
-            output,
-            exit_code: 0,
+            output: listing.output,
+            exit_code: listing.exit_code,
         })
     }
     
     fn help(&self) -> &str {
-        "ls [options] [directory] - List directory contents\n\
+        "ls [options] [file...] - List directory contents\n\
          Options:\n\
-         -a, --all    Show hidden files\n\
-         -l, --long   Use long listing format\n\
-         -h, --human  Show file sizes in human readable format"
+         -a, --all             Show hidden files, including . and ..\n\
+         -A, --almost-all      Show hidden files except . and ..\n\
+         -l, --long            Use long listing format\n\
+         -h, --human-readable  Show sizes like 4.0K and 12M\n\
+         -R, --recursive       List subdirectories recursively\n\
+         -t                    Sort by modification time, newest first\n\
+         -S                    Sort by size, largest first\n\
+         -r, --reverse         Reverse the sort order\n\
+         -1                    List one file per line\n\
+         -d, --directory       List directories themselves, not their contents\n\
+         -F, --classify        Append / @ | = * type indicators\n\
+         --color[=WHEN]        Color names using LS_COLORS: always, auto or never"
     }
     
     fn name(&self) -> &str {
@@ -220,61 +659,187 @@ impl CommandHandler for MkdirCommand {
 }
 
 /**
- * Remove files/directories command
+ * ファイルやディレクトリを削除するrmコマンドです
+ * 
+ * -rでディレクトリを中身ごと、-dで空のディレクトリを削除します。
+ * -iでは削除ごとに、-Iでは4つ以上の引数または再帰削除の前に
+ * 一度だけ確認します。
  * 
- * Implements the rm command for removing files and directories.
- * Supports recursive removal and force options.
+ * --trashまたは環境変数SARE_RM_TRASH=1でゴミ箱モードになり、
+ * 削除の代わりにXDGのゴミ箱へ移動します。移動した項目は
+ * `trash restore`で元に戻せます。--no-trashで一時的に無効化できます。
  */
 pub struct RmCommand;
 
 impl CommandHandler for RmCommand {
+    /**
+     * rmコマンドを実行する関数です
+     *
+     * オプションを解析し、各オペランドを削除またはゴミ箱に
+     * 移動します。「.」と「..」、および再帰モードでの「/」は
+     * 拒否します。失敗したオペランドがあっても残りの処理を
+     * 続け、終了コード1を返します。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: rm <file>"));
+        let mut options = RemoveOptions::default();
+        let mut prompt_once = false;
+        let mut trash_mode = shell
+            .get_environment_variable("SARE_RM_TRASH")
+            .is_some_and(|value| !value.is_empty() && value != "0");
+        let mut operands = Vec::new();
+        let mut options_done = false;
+        
+        for arg in &command.args {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
+                continue;
+            }
+            let flags: Vec<char> = match arg.as_str() {
+                "--" => {
+                    options_done = true;
+                    continue;
+                }
+                "--force" => vec!['f'],
+                "--interactive" | "--interactive=always" => vec!['i'],
+                "--interactive=once" => vec!['I'],
+                "--interactive=never" => {
+                    options.interactive = false;
+                    prompt_once = false;
+                    continue;
+                }
+                "--recursive" => vec!['r'],
+                "--dir" => vec!['d'],
+                "--verbose" => vec!['v'],
+                "--trash" => {
+                    trash_mode = true;
+                    continue;
+                }
+                "--no-trash" => {
+                    trash_mode = false;
+                    continue;
+                }
+                long if long.starts_with("--") => return Err(anyhow::anyhow!("unrecognized option '{}'", long)),
+                short => short[1..].chars().collect(),
+            };
+            for flag in flags {
+                match flag {
+                    'f' => {
+                        options.force = true;
+                        options.interactive = false;
+                        prompt_once = false;
+                    }
+                    'i' => {
+                        options.interactive = true;
+                        options.force = false;
+                        prompt_once = false;
+                    }
+                    'I' => {
+                        prompt_once = true;
+                        options.interactive = false;
+                        options.force = false;
+                    }
+                    'r' | 'R' => options.recursive = true,
+                    'd' => options.empty_directories = true,
+                    'v' => options.verbose = true,
+                    _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+                }
+            }
+        }
+        
+        if operands.is_empty() {
+            if options.force {
+                return Ok(CommandResult {
+                    output: String::new(),
+                    exit_code: 0,
+                });
+            }
+            return Err(anyhow::anyhow!("missing operand"));
         }
         
-        let recursive = command.args.iter().any(|arg| arg == "-r" || arg == "-R" || arg == "--recursive");
-        let force = command.args.iter().any(|arg| arg == "-f" || arg == "--force");
+        let mut prompter = Prompter::new(shell.take_pipeline_input());
+        if prompt_once && (operands.len() > 3 || options.recursive) {
+            let question = format!(
+                "rm: remove {} argument{}{}? ",
+                operands.len(),
+                if operands.len() == 1 { "" } else { "s" },
+                if options.recursive { " recursively" } else { "" }
+            );
+            if !prompter.confirm(&question) {
+                return Ok(CommandResult {
+                    output: String::new(),
+                    exit_code: 0,
+                });
+            }
+        }
         
-        let mut removed_count = 0;
+        let trash = if trash_mode { Some(Trash::open_home()?) } else { None };
+        let mut log = OperationLog::new("rm");
         
-        for file_name in &command.args {
-            if file_name.starts_with('-') && !file_name.starts_with("--") {
+        for name in &operands {
+            let path = shell.current_path().join(name);
+            let last_component = name.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+            if last_component == "." || last_component == ".." {
+                log.error(&format!("refusing to remove '.' or '..' directory: skipping '{}'", name));
+                continue;
+            }
+            if options.recursive && path.canonicalize().is_ok_and(|resolved| resolved == Path::new("/")) {
+                log.error("it is dangerous to operate recursively on '/'");
                 continue;
             }
             
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
+            let Some(trash) = &trash else {
+                remove_path(&path, name, &options, &mut prompter, &mut log);
+                continue;
             };
             
-            if file_path.exists() {
-                if file_path.is_dir() && recursive {
-                    std::fs::remove_dir_all(&file_path)?;
-                } else if file_path.is_file() {
-                    std::fs::remove_file(&file_path)?;
-                } else if !force {
-                    return Err(anyhow::anyhow!("Cannot remove directory '{}': Is a directory", file_name));
+            let metadata = match std::fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    if !(options.force && error.kind() == std::io::ErrorKind::NotFound) {
+                        log.error(&format!("cannot remove '{}': {}", name, describe_error(&error)));
+                    }
+                    continue;
                 }
-                removed_count += 1;
-            } else if !force {
-                return Err(anyhow::anyhow!("No such file or directory: {}", file_name));
+            };
+            if metadata.is_dir() && !options.recursive {
+                let is_empty = std::fs::read_dir(&path).map(|mut reader| reader.next().is_none()).unwrap_or(false);
+                if !(options.empty_directories && is_empty) {
+                    log.error(&format!("cannot remove '{}': Is a directory", name));
+                    continue;
+                }
+            }
+            if options.interactive && !prompter.confirm(&format!("rm: move {} '{}' to the trash? ", kind_name(&metadata), name)) {
+                continue;
+            }
+            match trash.put(&path) {
+                Ok(_) if options.verbose => log.note(&format!("trashed '{}'", name)),
+                Ok(_) => {}
+                Err(error) => log.error(&format!("cannot move '{}' to the trash: {}", name, error)),
             }
         }
         
         Ok(CommandResult {
-            output: format!("Removed {} item(s)", removed_count),
-            exit_code: 0,
+            exit_code: log.exit_code(),
+            output: log.output,
         })
     }
     
     fn help(&self) -> &str {
-        "rm [options] <file> - Remove files or directories\n\
+        "rm [options] <file>... - Remove files or directories\n\
          Options:\n\
-         -f, --force     Force removal without prompting\n\
-         -r, -R, --recursive  Remove directories recursively\n\
-         -i, --interactive  Prompt before removal"
+         -f, --force          Ignore missing files and never prompt\n\
+         -i                   Prompt before every removal\n\
+         -I                   Prompt once before removing more than three files or recursively\n\
+         -r, -R, --recursive  Remove directories and their contents\n\
+         -d, --dir            Remove empty directories\n\
+         -v, --verbose        Report each removal\n\
+         --trash              Move files to the trash instead of deleting them\n\
+         --no-trash           Delete even when SARE_RM_TRASH=1 is set\n\
+         Set SARE_RM_TRASH=1 to make --trash the default; see `trash restore`."
     }
     
     fn name(&self) -> &str {
@@ -283,60 +848,98 @@ impl CommandHandler for RmCommand {
 }
 
 /**
- * Copy files command
+ * ファイルやディレクトリをコピーするcpコマンドです
  * 
- * Implements the cp command for copying files and directories.
- * Supports recursive copying and preserving attributes.
+ * -rでディレクトリを再帰的にコピーし、-pでモード、所有者、
+ * タイムスタンプを保持します。-aは-r、-p、-Pを合わせたもので、
+ * シンボリックリンクをリンクのまま複製します。
  */
 pub struct CpCommand;
 
 impl CommandHandler for CpCommand {
+    /**
+     * cpコマンドを実行する関数です
+     *
+     * 最後のオペランドがコピー先で、既存のディレクトリであるか
+     * コピー元が複数ある場合は、その中に同じ名前でコピーします。
+     * 再帰コピーではシンボリックリンクを辿らず（-P）、それ以外
+     * ではリンク先をコピーします（-L）。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.len() < 2 {
-            return Err(anyhow::anyhow!("Usage: cp <source> <destination>"));
-        }
-        
-        let recursive = command.args.iter().any(|arg| arg == "-r" || arg == "-R" || arg == "--recursive");
-        
-        let source = &command.args[0];
-        let destination = &command.args[1];
-        
-        let source_path = if PathBuf::from(source).is_absolute() {
-            PathBuf::from(source)
-        } else {
-            shell.current_path().join(source)
-        };
-        
-        let dest_path = if PathBuf::from(destination).is_absolute() {
-            PathBuf::from(destination)
-        } else {
-            shell.current_path().join(destination)
-        };
+        let mut options = CopyOptions::default();
+        let mut follow_links = None;
+        let mut operands = Vec::new();
+        let mut options_done = false;
         
-        if !source_path.exists() {
-            return Err(anyhow::anyhow!("Source file not found: {}", source));
+        for arg in &command.args {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
+                continue;
+            }
+            let flags: Vec<char> = match arg.as_str() {
+                "--" => {
+                    options_done = true;
+                    continue;
+                }
+                "--archive" => vec!['a'],
+                "--recursive" => vec!['r'],
+                "--preserve" => vec!['p'],
+                "--no-clobber" => vec!['n'],
+                "--update" => vec!['u'],
+                "--verbose" => vec!['v'],
+                "--force" => vec!['f'],
+                "--dereference" => vec!['L'],
+                "--no-dereference" => vec!['P'],
+                long if long.starts_with("--") => return Err(anyhow::anyhow!("unrecognized option '{}'", long)),
+                short => short[1..].chars().collect(),
+            };
+            for flag in flags {
+                match flag {
+                    'a' => {
+                        options.recursive = true;
+                        options.preserve = true;
+                        follow_links = Some(false);
+                    }
+                    'r' | 'R' => options.recursive = true,
+                    'p' => options.preserve = true,
+                    'n' => options.no_clobber = true,
+                    'u' => options.update = true,
+                    'v' => options.verbose = true,
+                    'f' => {}
+                    'L' => follow_links = Some(true),
+                    'P' | 'd' => follow_links = Some(false),
+                    _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+                }
+            }
         }
+        options.follow_links = follow_links.unwrap_or(!options.recursive);
         
-        if source_path.is_dir() && recursive {
-            copy_directory(&source_path, &dest_path)?;
-        } else if source_path.is_file() {
-            std::fs::copy(&source_path, &dest_path)?;
-        } else {
-            return Err(anyhow::anyhow!("Cannot copy directory without -r flag"));
+        let mut log = OperationLog::new("cp");
+        for (source, destination, names) in transfer_pairs("cp", &operands, shell.current_path())? {
+            copy_path(&source, &destination, (&names.0, &names.1), &options, &mut log);
         }
         
         Ok(CommandResult {
-            output: format!("Copied {} to {}", source, destination),
-            exit_code: 0,
+            exit_code: log.exit_code(),
+            output: log.output,
         })
     }
     
     fn help(&self) -> &str {
-        "cp [options] <source> <destination> - Copy files and directories\n\
+        "cp [options] <source>... <destination> - Copy files and directories\n\
          Options:\n\
-         -r, -R, --recursive  Copy directories recursively\n\
-         -p, --preserve       Preserve file attributes\n\
-         -v, --verbose        Verbose output"
+         -a, --archive          Same as -rpP: copy trees exactly, keeping symlinks\n\
+         -r, -R, --recursive    Copy directories recursively\n\
+         -p, --preserve         Preserve mode, ownership and timestamps\n\
+         -n, --no-clobber       Do not overwrite existing files\n\
+         -u, --update           Copy only when the source is newer\n\
+         -v, --verbose          Report each copied file\n\
+         -L, --dereference      Follow symlinks in the source\n\
+         -P, --no-dereference   Copy symlinks as symlinks"
     }
     
     fn name(&self) -> &str {
@@ -345,51 +948,81 @@ impl CommandHandler for CpCommand {
 }
 
 /**
- * Move/rename files command
+ * ファイルを移動・名前変更するmvコマンドです
  * 
- * Implements the mv command for moving and renaming files.
+ * 同じファイルシステム内ではrenameで移動し、異なる
+ * ファイルシステム間では属性を保持してコピーした後に
+ * 元のファイルを削除します。
  */
 pub struct MvCommand;
 
 impl CommandHandler for MvCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.len() < 2 {
-            return Err(anyhow::anyhow!("Usage: mv <source> <destination>"));
-        }
-        
-        let source = &command.args[0];
-        let destination = &command.args[1];
-        
-        let source_path = if PathBuf::from(source).is_absolute() {
-            PathBuf::from(source)
-        } else {
-            shell.current_path().join(source)
-        };
+        let mut options = MoveOptions::default();
+        let mut operands = Vec::new();
+        let mut options_done = false;
         
-        let dest_path = if PathBuf::from(destination).is_absolute() {
-            PathBuf::from(destination)
-        } else {
-            shell.current_path().join(destination)
-        };
-        
-        if !source_path.exists() {
-            return Err(anyhow::anyhow!("Source file not found: {}", source));
+        for arg in &command.args {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
+                continue;
+            }
+            let flags: Vec<char> = match arg.as_str() {
+                "--" => {
+                    options_done = true;
+                    continue;
+                }
+                "--force" => vec!['f'],
+                "--interactive" => vec!['i'],
+                "--no-clobber" => vec!['n'],
+                "--update" => vec!['u'],
+                "--verbose" => vec!['v'],
+                long if long.starts_with("--") => return Err(anyhow::anyhow!("unrecognized option '{}'", long)),
+                short => short[1..].chars().collect(),
+            };
+            for flag in flags {
+                match flag {
+                    'f' => {
+                        options.interactive = false;
+                        options.no_clobber = false;
+                    }
+                    'i' => {
+                        options.interactive = true;
+                        options.no_clobber = false;
+                    }
+                    'n' => {
+                        options.no_clobber = true;
+                        options.interactive = false;
+                    }
+                    'u' => options.update = true,
+                    'v' => options.verbose = true,
+                    _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+                }
+            }
         }
         
-        std::fs::rename(&source_path, &dest_path)?;
+        let pairs = transfer_pairs("mv", &operands, shell.current_path())?;
+        let mut prompter = Prompter::new(shell.take_pipeline_input());
+        let mut log = OperationLog::new("mv");
+        for (source, destination, names) in pairs {
+            move_path(&source, &destination, (&names.0, &names.1), &options, &mut prompter, &mut log);
+        }
         
         Ok(CommandResult {
-            output: format!("Moved {} to {}", source, destination),
-            exit_code: 0,
+            exit_code: log.exit_code(),
+            output: log.output,
         })
     }
     
     fn help(&self) -> &str {
-        "mv [options] <source> <destination> - Move or rename files\n\
+        "mv [options] <source>... <destination> - Move or rename files\n\
          Options:\n\
-         -f, --force     Force move without prompting\n\
-         -i, --interactive  Prompt before overwrite\n\
-         -v, --verbose   Verbose output"
+         -f, --force        Do not prompt before overwriting\n\
+         -i, --interactive  Prompt before overwriting\n\
+         -n, --no-clobber   Do not overwrite existing files\n\
+         -u, --update       Move only when the source is newer\n\
+         -v, --verbose      Report each move\n\
+         Moves across filesystems copy with attributes and then delete the source."
     }
     
     fn name(&self) -> &str {
@@ -447,69 +1080,237 @@ impl CommandHandler for TouchCommand {
 }
 
 /**
- * ディレクトリを再帰的にコピーする関数です
+ * ゴミ箱を操作するtrashコマンドです
  * 
- * ソースディレクトリの内容をデスティネーションディレクトリに
- * 再帰的にコピーします。
- * 
- * デスティネーションディレクトリが存在しない場合は作成し、
- * ソースディレクトリ内の各エントリを処理します。
+ * ファイルをXDGのホームゴミ箱（~/.local/share/Trash）へ移動し、
+ * 一覧表示、元の場所への復元、ゴミ箱を空にする操作を提供します。
+ * rm --trashやSARE_RM_TRASH=1で移動した項目も同じゴミ箱に入ります。
+ */
+pub struct TrashCommand;
+
+impl CommandHandler for TrashCommand {
+    /**
+     * trashコマンドを実行する関数です
+     *
+     * サブコマンドはput（省略可）、list、restore、emptyです。
+     * restoreはlistに表示される名前か元のパスで項目を指定し、
+     * 同じパスの項目が複数ある場合は最も新しいものを復元します。
+     * 「list」などの名前のファイルを移動するには
+     * `trash put list`または`trash -- list`を使います。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let (subcommand, operands) = match command.args.split_first() {
+            Some((first, rest)) if ["put", "list", "restore", "empty"].contains(&first.as_str()) => (first.as_str(), rest),
+            Some((first, rest)) if first == "--" => ("put", rest),
+            _ => ("put", &command.args[..]),
+        };
+        let trash = Trash::open_home()?;
+        let mut log = OperationLog::new("trash");
+        
+        match subcommand {
+            "list" => {
+                let entries = trash.entries()?;
+                let width = entries.iter().map(|entry| entry.name.chars().count()).max().unwrap_or(0);
+                for entry in entries {
+                    log.note(&format!(
+                        "{}  {:<width$}  {}",
+                        entry.deleted_at.replace('T', " "),
+                        entry.name,
+                        entry.original_path.display(),
+                        width = width
+                    ));
+                }
+            }
+            "empty" => {
+                trash.empty()?;
+            }
+            "restore" => {
+                if operands.is_empty() {
+                    return Err(anyhow::anyhow!("restore: missing operand"));
+                }
+                let entries = trash.entries()?;
+                for operand in operands {
+                    let original = shell.current_path().join(operand);
+                    let found = entries.iter().rev().find(|entry| &entry.name == operand).or_else(|| {
+                        entries.iter().rev().find(|entry| entry.original_path == original)
+                    });
+                    match found {
+                        Some(entry) => match trash.restore(entry) {
+                            Ok(path) => log.note(&format!("restored '{}' to '{}'", entry.name, path.display())),
+                            Err(error) => log.error(&error.to_string()),
+                        },
+                        None => log.error(&format!("'{}' is not in the trash", operand)),
+                    }
+                }
+            }
+            _ => {
+                if operands.is_empty() {
+                    return Err(anyhow::anyhow!("missing operand"));
+                }
+                for operand in operands {
+                    if let Err(error) = trash.put(&shell.current_path().join(operand)) {
+                        log.error(&format!("cannot move '{}' to the trash: {}", operand, error));
+                    }
+                }
+            }
+        }
+        
+        Ok(CommandResult {
+            exit_code: log.exit_code(),
+            output: log.output,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "trash [put] <file>... - Move files to the XDG trash\n\
+         trash list             - Show trashed items with their original paths\n\
+         trash restore <item>...- Restore items by trash name or original path\n\
+         trash empty            - Permanently delete everything in the trash"
+    }
+    
+    fn name(&self) -> &str {
+        "trash"
+    }
+}
+
+/**
+ * ファイルを検索するfindコマンドです
  * 
- * ディレクトリの場合は再帰的にコピーし、ファイルの場合は
- * 直接コピーします。ファイル名の衝突は自動的に処理されます。
+ * 開始パスからディレクトリを再帰的に走査し、式に一致した
+ * ファイルに対してアクションを実行します。テストは-name、-iname、
+ * -path、-type、-size、-mtime、-mmin、-newer、-emptyで、
+ * アクションは-print、-print0、-prune、-delete、-execです。
  * 
- * @param src - ソースディレクトリパス
- * @param dst - デスティネーションディレクトリパス
- * @return Result<()> - 成功またはエラー
+ * -exec ... {} ;はファイルごとに、-exec ... {} +はまとめて
+ * コマンドを実行します。コマンドは組み込みコマンドでも外部
+ * コマンドでも構いません。-gitignoreを指定すると.gitignoreで
+ * 除外されたファイルと.gitディレクトリを飛ばします。
  */
-fn copy_directory(src: &PathBuf, dst: &PathBuf) -> Result<()> {
-    if !dst.exists() {
-        std::fs::create_dir(dst)?;
+pub struct FindCommand;
+
+impl CommandHandler for FindCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let working_dir = shell.current_path().clone();
+        let program = FindProgram::parse(&command.args, &working_dir)?;
+        
+        let mut runner = |words: &[String]| {
+            let target = command_from_words(command, words);
+            shell.run_unit(&target, None).unwrap_or_else(|error| CommandResult {
+                output: format!("find: {}: {}\n", words[0], error),
+                exit_code: 1,
+            })
+        };
+        let result = program.run(&working_dir, &mut runner);
+        
+        Ok(CommandResult {
+            output: result.output,
+            exit_code: result.exit_code,
+        })
     }
     
-    for entry in std::fs::read_dir(src)? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
-        
-        if file_type.is_dir() {
-            copy_directory(&src_path, &dst_path)?;
-        } else {
-            std::fs::copy(&src_path, &dst_path)?;
-        }
+    fn help(&self) -> &str {
+        "find [path...] [expression] - Search for files in a directory tree\n\
+         Tests:   -name/-iname <glob>  -path/-ipath <glob>  -type <fdlpsbc>\n\
+                  -size [+-]N[cwbkMG]  -mtime [+-]N  -mmin [+-]N  -newer <file>  -empty\n\
+         Actions: -print  -print0  -prune  -delete  -exec <cmd> {} ;  -exec <cmd> {} +\n\
+         Options: -maxdepth N  -mindepth N  -depth  -gitignore (skip .gitignore'd files)\n\
+         Operators: ( expr )  ! expr  expr -a expr  expr -o expr"
     }
     
-    Ok(())
+    fn name(&self) -> &str {
+        "find"
+    }
 }
 
+/// Source path, destination path and their printed names
+type TransferPair = (PathBuf, PathBuf, (String, String));
+
 /**
- * Helper function to format file permissions
+ * Pairs cp/mv sources with their destinations
  * 
- * @param metadata - File metadata
- * @return String - Formatted permissions string
+ * The last operand is the destination. With several sources, or when
+ * it is an existing directory, each source goes inside it under its
+ * own name.
+ * 
+ * @param program - Command name for usage errors
+ * @param operands - Source operands followed by the destination
+ * @param current_path - Directory relative operands are resolved against
+ * @return Result<Vec<TransferPair>> - Sources paired with destinations
  */
-fn format_permissions(metadata: &std::fs::Metadata) -> String {
-    let permissions = metadata.permissions();
-    let mut perms = String::new();
+fn transfer_pairs(program: &str, operands: &[String], current_path: &Path) -> Result<Vec<TransferPair>> {
+    let Some((target, sources)) = operands.split_last() else {
+        return Err(anyhow::anyhow!("missing file operand"));
+    };
+    if sources.is_empty() {
+        return Err(anyhow::anyhow!("missing destination file operand after '{}'", target));
+    }
     
-    // Simplified permission display
-    perms.push_str(if permissions.readonly() { "r--" } else { "rw-" });
-    perms.push_str(if permissions.readonly() { "r--" } else { "rw-" });
-    perms.push_str(if permissions.readonly() { "r--" } else { "rw-" });
+    let target_path = current_path.join(target);
+    let into_directory = target_path.is_dir();
+    if sources.len() > 1 && !into_directory {
+        return Err(anyhow::anyhow!("target '{}' is not a directory", target));
+    }
     
-    perms
+    let mut pairs = Vec::new();
+    for source in sources {
+        let source_path = current_path.join(source);
+        if !into_directory {
+            pairs.push((source_path, target_path.clone(), (source.clone(), target.clone())));
+            continue;
+        }
+        
+        let base_name = match Path::new(source).file_name() {
+            Some(name) => name.to_os_string(),
+            None => match source_path.canonicalize().ok().and_then(|path| path.file_name().map(|name| name.to_os_string())) {
+                Some(name) => name,
+                None => return Err(anyhow::anyhow!("cannot {} '{}' into '{}'", if program == "cp" { "copy" } else { "move" }, source, target)),
+            },
+        };
+        let destination_name = format!("{}/{}", target.trim_end_matches('/'), base_name.to_string_lossy());
+        pairs.push((source_path, target_path.join(&base_name), (source.clone(), destination_name)));
+    }
+    Ok(pairs)
 }
 
 /**
- * Helper function to format datetime
+ * Expands an ls operand that may contain glob characters
  * 
- * @param time - System time to format
- * @return String - Formatted datetime string
+ * Existing paths and patterns without matches are kept as written.
+ * 
+ * @param pattern - Operand as typed
+ * @param current_path - Directory relative operands are resolved against
+ * @return Vec<(String, PathBuf)> - Printed names paired with paths
  */
-fn format_datetime(time: std::time::SystemTime) -> String {
-    use chrono::{DateTime, Utc};
+fn expand_operand(pattern: &str, current_path: &Path) -> Vec<(String, PathBuf)> {
+    let literal = current_path.join(pattern);
+    let is_pattern = pattern.contains(['*', '?', '[']);
+    if !is_pattern || literal.symlink_metadata().is_ok() {
+        return vec![(pattern.to_string(), literal)];
+    }
     
-    let datetime: DateTime<Utc> = time.into();
-    datetime.format("%Y-%m-%d %H:%M").to_string()
-} 
\ No newline at end of file
+    let absolute_pattern = literal.to_string_lossy().into_owned();
+    let mut matches: Vec<(String, PathBuf)> = glob::glob(&absolute_pattern)
+        .map(|paths| {
+            paths
+                .flatten()
+                .map(|path| {
+                    let name = if Path::new(pattern).is_absolute() {
+                        path.to_string_lossy().into_owned()
+                    } else {
+                        path.strip_prefix(current_path).unwrap_or(&path).to_string_lossy().into_owned()
+                    };
+                    (name, path)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    if matches.is_empty() {
+        matches.push((pattern.to_string(), literal));
+    }
+    matches
+}
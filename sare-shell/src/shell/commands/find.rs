@@ -0,0 +1,859 @@
+/*!
+ * @file find.rs
+ * @brief Expression parser and directory walker for the find builtin
+ *
+ * This module implements the POSIX find expression language (tests,
+ * actions and the ! -a -o ( ) operators) together with the common
+ * GNU extensions, and walks directory trees evaluating it.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file find.rs
+ * @description find expressions (-name -iname -path -type -size -mtime
+ * -newer -prune -exec -print0 -delete ...), depth limits and optional
+ * .gitignore filtering.
+ */
+
+use anyhow::Result;
+use glob::{MatchOptions, Pattern};
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use crate::shell::commands::CommandResult;
+use crate::shell::commands::fileops::describe_error;
+
+/// Arguments collected by `-exec ... {} +` before the command is run
+const EXEC_BATCH_LIMIT: usize = 1024;
+
+/**
+ * Numeric comparison written as +N, -N or N
+ */
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    /// +N: more than N
+    Greater(i64),
+    /// -N: less than N
+    Less(i64),
+    /// N: exactly N
+    Exact(i64),
+}
+
+impl Comparison {
+    /**
+     * Parses a +N/-N/N argument
+     *
+     * @param text - Argument text without any unit suffix
+     * @return Option<Comparison> - Comparison or None if not a number
+     */
+    fn parse(text: &str) -> Option<Self> {
+        if let Some(rest) = text.strip_prefix('+') {
+            rest.parse().ok().map(Comparison::Greater)
+        } else if let Some(rest) = text.strip_prefix('-') {
+            rest.parse().ok().map(Comparison::Less)
+        } else {
+            text.parse().ok().map(Comparison::Exact)
+        }
+    }
+
+    /**
+     * Applies the comparison
+     *
+     * @param value - Measured value
+     * @return bool - Whether the value satisfies the comparison
+     */
+    fn matches(&self, value: i64) -> bool {
+        match *self {
+            Comparison::Greater(n) => value > n,
+            Comparison::Less(n) => value < n,
+            Comparison::Exact(n) => value == n,
+        }
+    }
+}
+
+/**
+ * Parsed find expression
+ */
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Constant(bool),
+    /// -name / -iname against the base name
+    Name(Pattern, bool),
+    /// -path / -ipath against the printed path
+    Path(Pattern, bool),
+    /// -type with one or more type letters
+    Type(Vec<char>),
+    /// -size with the unit size in bytes
+    Size(Comparison, u64),
+    /// -mtime / -mmin with the period length in seconds
+    Modified(Comparison, i64),
+    /// -newer with the reference modification time
+    Newer((i64, i64)),
+    Empty,
+    Print,
+    Print0,
+    Prune,
+    Delete,
+    /// -exec; the index identifies the batch for the `+` form
+    Exec(Vec<String>, bool, usize),
+}
+
+/**
+ * Whether an expression contains an action, making -print implicit
+ *
+ * @param expr - Expression
+ * @return bool - True if it has -print, -print0, -exec or -delete
+ */
+fn has_action(expr: &Expr) -> bool {
+    match expr {
+        Expr::And(left, right) | Expr::Or(left, right) => has_action(left) || has_action(right),
+        Expr::Not(inner) => has_action(inner),
+        Expr::Print | Expr::Print0 | Expr::Delete | Expr::Exec(..) => true,
+        _ => false,
+    }
+}
+
+/**
+ * Compiled find command line
+ */
+#[derive(Debug)]
+pub struct FindProgram {
+    /// Starting points, defaulting to "."
+    pub start_paths: Vec<String>,
+    /// Expression to evaluate for each file
+    expr: Expr,
+    /// Deepest level to visit (-maxdepth)
+    max_depth: Option<usize>,
+    /// Shallowest level to evaluate (-mindepth)
+    min_depth: usize,
+    /// Visit directory contents before the directory (-depth, -delete)
+    depth_first: bool,
+    /// Skip files ignored by .gitignore (-gitignore)
+    gitignore: bool,
+}
+
+/**
+ * Result of running a find program
+ */
+#[derive(Debug)]
+pub struct FindOutput {
+    /// Printed paths, command output and error messages
+    pub output: String,
+    /// 0 on success, 1 if any error occurred
+    pub exit_code: i32,
+}
+
+impl FindProgram {
+    /**
+     * findのコマンドライン引数を解析する関数です
+     *
+     * 先頭の「-」「(」「!」で始まらない引数を開始パスとし、
+     * 残りを式として解析します。演算子の優先順位は
+     * ! > -a（暗黙を含む）> -o です。-maxdepth、-mindepth、
+     * -depth、-gitignoreは位置に関係なく全体に適用されます。
+     *
+     * 式にアクション（-print、-print0、-exec、-delete）が
+     * ない場合は、式全体の後に-printを追加します。
+     *
+     * @param args - findの引数
+     * @param working_dir - -newerの相対パスを解決するディレクトリ
+     * @return Result<FindProgram> - 解析結果またはエラー
+     */
+    pub fn parse(args: &[String], working_dir: &Path) -> Result<Self> {
+        let split = args
+            .iter()
+            .position(|arg| (arg.starts_with('-') && arg.len() > 1) || arg == "(" || arg == "!")
+            .unwrap_or(args.len());
+        let mut start_paths: Vec<String> = args[..split].to_vec();
+        if start_paths.is_empty() {
+            start_paths.push(".".to_string());
+        }
+
+        let mut parser = ExprParser {
+            tokens: &args[split..],
+            position: 0,
+            working_dir,
+            max_depth: None,
+            min_depth: 0,
+            depth_first: false,
+            gitignore: false,
+            exec_count: 0,
+        };
+        let mut expr = if parser.tokens.is_empty() {
+            Expr::Constant(true)
+        } else {
+            parser.parse_or()?
+        };
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(anyhow::anyhow!("unexpected '{}' in expression", token));
+        }
+
+        let has_delete = contains_delete(&expr);
+        if !has_action(&expr) {
+            expr = Expr::And(Box::new(expr), Box::new(Expr::Print));
+        }
+        Ok(FindProgram {
+            start_paths,
+            expr,
+            max_depth: parser.max_depth,
+            min_depth: parser.min_depth,
+            depth_first: parser.depth_first || has_delete,
+            gitignore: parser.gitignore,
+        })
+    }
+
+    /**
+     * Walks every starting point and evaluates the expression
+     *
+     * @param working_dir - Directory relative starting points are resolved against
+     * @param runner - Runs -exec commands and returns their result
+     * @return FindOutput - Output and exit code
+     */
+    pub fn run(&self, working_dir: &Path, runner: &mut dyn FnMut(&[String]) -> CommandResult) -> FindOutput {
+        let mut walker = Walker {
+            program: self,
+            runner,
+            output: String::new(),
+            exit_code: 0,
+            pruned: false,
+            batches: HashMap::new(),
+            ignore_stack: Vec::new(),
+            now: chrono::Utc::now().timestamp(),
+        };
+
+        for start in &self.start_paths {
+            let path = working_dir.join(start);
+            walker.ignore_stack = if self.gitignore { ancestor_ignore_rules(&path) } else { Vec::new() };
+            walker.walk(&path, start, 0);
+        }
+        walker.flush_batches();
+
+        FindOutput {
+            output: walker.output,
+            exit_code: walker.exit_code,
+        }
+    }
+}
+
+/**
+ * Whether an expression contains -delete
+ *
+ * @param expr - Expression
+ * @return bool - True if -delete appears anywhere
+ */
+fn contains_delete(expr: &Expr) -> bool {
+    match expr {
+        Expr::And(left, right) | Expr::Or(left, right) => contains_delete(left) || contains_delete(right),
+        Expr::Not(inner) => contains_delete(inner),
+        Expr::Delete => true,
+        _ => false,
+    }
+}
+
+/**
+ * Recursive-descent parser for find expressions
+ */
+struct ExprParser<'a> {
+    /// Expression tokens
+    tokens: &'a [String],
+    /// Next token index
+    position: usize,
+    /// Directory relative -newer references are resolved against
+    working_dir: &'a Path,
+    /// -maxdepth value
+    max_depth: Option<usize>,
+    /// -mindepth value
+    min_depth: usize,
+    /// -depth seen
+    depth_first: bool,
+    /// -gitignore seen
+    gitignore: bool,
+    /// Number of -exec primaries, used to index batches
+    exec_count: usize,
+}
+
+impl ExprParser<'_> {
+    /**
+     * Parses `expr -o expr`
+     *
+     * @return Result<Expr> - Expression
+     */
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while matches!(self.peek(), Some("-o" | "-or")) {
+            self.position += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /**
+     * Parses `expr [-a] expr`
+     *
+     * @return Result<Expr> - Expression
+     */
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some("-a" | "-and") => self.position += 1,
+                Some("-o" | "-or" | ")") | None => break,
+                Some(_) => {}
+            }
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /**
+     * Parses `! expr`, `( expr )` or a primary
+     *
+     * @return Result<Expr> - Expression
+     */
+    fn parse_unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some("!" | "-not") => {
+                self.position += 1;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some("(") => {
+                self.position += 1;
+                let inner = self.parse_or()?;
+                if self.peek() != Some(")") {
+                    return Err(anyhow::anyhow!("missing ')' in expression"));
+                }
+                self.position += 1;
+                Ok(inner)
+            }
+            Some(_) => self.parse_primary(),
+            None => Err(anyhow::anyhow!("expected an expression")),
+        }
+    }
+
+    /**
+     * テストやアクションを1つ解析する関数です
+     *
+     * -name、-iname、-path、-ipathはglobパターンに変換し、
+     * -sizeは単位（b、c、w、k、M、G、省略時は512バイトのブロック）を
+     * 解釈します。-exec は「;」または「+」までをコマンドとして
+     * 取り込み、「+」形式では最後の引数が「{}」である必要があります。
+     *
+     * @return Result<Expr> - 解析された式
+     */
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let token = self.tokens[self.position].clone();
+        self.position += 1;
+
+        let expr = match token.as_str() {
+            "-name" | "-iname" | "-path" | "-wholename" | "-ipath" => {
+                let value = self.argument(&token)?;
+                let pattern = Pattern::new(&value).map_err(|error| anyhow::anyhow!("invalid pattern '{}': {}", value, error))?;
+                let case_insensitive = token.starts_with("-i");
+                if token.ends_with("name") && token != "-wholename" {
+                    Expr::Name(pattern, case_insensitive)
+                } else {
+                    Expr::Path(pattern, case_insensitive)
+                }
+            }
+            "-type" => {
+                let value = self.argument(&token)?;
+                let types: Vec<char> = value.split(',').filter_map(|kind| kind.chars().next()).collect();
+                if let Some(kind) = types.iter().find(|kind| !"fdlpsbc".contains(**kind)) {
+                    return Err(anyhow::anyhow!("unknown argument to -type: {}", kind));
+                }
+                Expr::Type(types)
+            }
+            "-size" => {
+                let value = self.argument(&token)?;
+                let (number, unit) = match value.char_indices().last() {
+                    Some((index, unit)) if unit.is_ascii_alphabetic() => (&value[..index], Some(unit)),
+                    _ => (&value[..], None),
+                };
+                let unit_size = match unit {
+                    None | Some('b') => 512,
+                    Some('c') => 1,
+                    Some('w') => 2,
+                    Some('k') => 1024,
+                    Some('M') => 1024 * 1024,
+                    Some('G') => 1024 * 1024 * 1024,
+                    Some(other) => return Err(anyhow::anyhow!("invalid -size unit '{}'", other)),
+                };
+                let comparison = Comparison::parse(number).ok_or_else(|| anyhow::anyhow!("invalid argument '{}' to -size", value))?;
+                Expr::Size(comparison, unit_size)
+            }
+            "-mtime" | "-mmin" => {
+                let value = self.argument(&token)?;
+                let comparison = Comparison::parse(&value).ok_or_else(|| anyhow::anyhow!("invalid argument '{}' to {}", value, token))?;
+                Expr::Modified(comparison, if token == "-mtime" { 86400 } else { 60 })
+            }
+            "-newer" => {
+                let value = self.argument(&token)?;
+                let metadata = fs::metadata(self.working_dir.join(&value)).map_err(|error| anyhow::anyhow!("'{}': {}", value, describe_error(&error)))?;
+                Expr::Newer((metadata.mtime(), metadata.mtime_nsec()))
+            }
+            "-maxdepth" | "-mindepth" => {
+                let value = self.argument(&token)?;
+                let depth: usize = value.parse().map_err(|_| anyhow::anyhow!("invalid argument '{}' to {}", value, token))?;
+                if token == "-maxdepth" {
+                    self.max_depth = Some(depth);
+                } else {
+                    self.min_depth = depth;
+                }
+                Expr::Constant(true)
+            }
+            "-depth" => {
+                self.depth_first = true;
+                Expr::Constant(true)
+            }
+            "-gitignore" | "--gitignore" => {
+                self.gitignore = true;
+                Expr::Constant(true)
+            }
+            "-true" => Expr::Constant(true),
+            "-false" => Expr::Constant(false),
+            "-empty" => Expr::Empty,
+            "-print" => Expr::Print,
+            "-print0" => Expr::Print0,
+            "-prune" => Expr::Prune,
+            "-delete" => Expr::Delete,
+            "-exec" => {
+                let mut command = Vec::new();
+                let batch = loop {
+                    let Some(word) = self.tokens.get(self.position) else {
+                        return Err(anyhow::anyhow!("missing argument to '-exec'"));
+                    };
+                    self.position += 1;
+                    match word.as_str() {
+                        ";" => break false,
+                        "+" if command.last().map(String::as_str) == Some("{}") => break true,
+                        _ => command.push(word.clone()),
+                    }
+                };
+                if command.is_empty() {
+                    return Err(anyhow::anyhow!("missing argument to '-exec'"));
+                }
+                if batch {
+                    command.pop();
+                }
+                self.exec_count += 1;
+                Expr::Exec(command, batch, self.exec_count)
+            }
+            _ => return Err(anyhow::anyhow!("unknown predicate '{}'", token)),
+        };
+        Ok(expr)
+    }
+
+    /**
+     * Takes the argument of a primary
+     *
+     * @param primary - Primary name, for the error message
+     * @return Result<String> - Argument
+     */
+    fn argument(&mut self, primary: &str) -> Result<String> {
+        let value = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("missing argument to '{}'", primary))?;
+        self.position += 1;
+        Ok(value)
+    }
+
+    /**
+     * Peeks at the next token
+     *
+     * @return Option<&str> - Token, if any
+     */
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+}
+
+/**
+ * One .gitignore pattern
+ */
+#[derive(Debug)]
+pub(crate) struct IgnoreRule {
+    /// Compiled pattern
+    pattern: Pattern,
+    /// `!pattern`: re-includes matching paths
+    negated: bool,
+    /// `pattern/`: only matches directories
+    directory_only: bool,
+    /// Pattern contains a slash, so it matches the path relative to the .gitignore
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    /**
+     * Checks whether the rule applies to a path
+     *
+     * @param relative - Path relative to the directory holding the rule
+     * @param name - Final component of the path
+     * @param is_dir - Whether the path is a directory
+     * @return Option<bool> - Some(true) to ignore, Some(false) to re-include, None if the rule does not match
+     */
+    pub(crate) fn matches(&self, relative: &str, name: &str, is_dir: bool) -> Option<bool> {
+        if self.directory_only && !is_dir {
+            return None;
+        }
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        let subject = if self.anchored { relative } else { name };
+        self.pattern.matches_with(subject, options).then_some(!self.negated)
+    }
+}
+
+/**
+ * Parses gitignore syntax
+ *
+ * @param contents - Text of a .gitignore or .git/info/exclude file
+ * @return Vec<IgnoreRule> - Rules in file order
+ */
+pub(crate) fn parse_ignore_rules(contents: &str) -> Vec<IgnoreRule> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (directory_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let pattern = Pattern::new(line.trim_start_matches('/')).ok()?;
+            Some(IgnoreRule {
+                pattern,
+                negated,
+                directory_only,
+                anchored,
+            })
+        })
+        .collect()
+}
+
+/**
+ * Reads the rules of one .gitignore file
+ *
+ * @param directory - Directory that may contain a .gitignore
+ * @return Vec<IgnoreRule> - Rules in file order; empty if there is no file
+ */
+pub(crate) fn read_ignore_rules(directory: &Path) -> Vec<IgnoreRule> {
+    fs::read_to_string(directory.join(".gitignore"))
+        .map(|contents| parse_ignore_rules(&contents))
+        .unwrap_or_default()
+}
+
+/**
+ * Collects .gitignore rules from the repository root down to a start path
+ *
+ * The start path's own .gitignore is read when the walk enters it.
+ *
+ * @param start - Starting point of the walk
+ * @return Vec<(PathBuf, Vec<IgnoreRule>)> - Rules by directory, outermost first
+ */
+fn ancestor_ignore_rules(start: &Path) -> Vec<(PathBuf, Vec<IgnoreRule>)> {
+    let Ok(start) = start.canonicalize() else {
+        return Vec::new();
+    };
+
+    let mut rules = Vec::new();
+    for ancestor in start.ancestors().skip(1) {
+        rules.push((ancestor.to_path_buf(), read_ignore_rules(ancestor)));
+        if ancestor.join(".git").exists() {
+            rules.reverse();
+            return rules;
+        }
+    }
+    Vec::new()
+}
+
+/**
+ * Directory walk state
+ */
+struct Walker<'a> {
+    /// Program being run
+    program: &'a FindProgram,
+    /// Runs -exec commands
+    runner: &'a mut dyn FnMut(&[String]) -> CommandResult,
+    /// Collected output
+    output: String,
+    /// Exit code so far
+    exit_code: i32,
+    /// Set by -prune for the file being evaluated
+    pruned: bool,
+    /// Pending `-exec ... {} +` paths by exec index
+    batches: HashMap<usize, (Vec<String>, Vec<String>)>,
+    /// .gitignore rules of the enclosing directories
+    ignore_stack: Vec<(PathBuf, Vec<IgnoreRule>)>,
+    /// Current time for -mtime and -mmin
+    now: i64,
+}
+
+impl Walker<'_> {
+    /**
+     * ファイルを訪問し、ディレクトリなら中を再帰的に走査する関数です
+     *
+     * シンボリックリンクは辿りません。-gitignoreでは.gitディレクトリと
+     * .gitignoreで除外されたパスを飛ばします（開始パス自体は除外
+     * しません）。-depthまたは-deleteの場合はディレクトリの中身を
+     * 先に評価し、それ以外ではディレクトリを先に評価して-pruneで
+     * 中への移動を止められるようにします。
+     *
+     * ディレクトリの項目は名前順に走査するため、出力は
+     * 実行ごとに同じ順序になります。
+     *
+     * @param path - 実際のパス
+     * @param display - 表示用のパス
+     * @param depth - 開始パスからの深さ
+     */
+    fn walk(&mut self, path: &Path, display: &str, depth: usize) {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                self.error(&format!("'{}': {}", display, describe_error(&error)));
+                return;
+            }
+        };
+        let is_dir = metadata.is_dir();
+
+        if self.program.gitignore && depth > 0 && self.is_ignored(path, is_dir) {
+            return;
+        }
+
+        let program = self.program;
+        self.pruned = false;
+        if !program.depth_first && depth >= program.min_depth {
+            self.evaluate(&program.expr, path, display, &metadata);
+        }
+
+        let within_depth = program.max_depth.is_none_or(|max| depth < max);
+        let descend = !self.pruned || program.depth_first;
+        if is_dir && within_depth && descend {
+            match fs::read_dir(path) {
+                Ok(reader) => {
+                    let mut children: Vec<_> = reader.flatten().map(|entry| entry.file_name()).collect();
+                    children.sort();
+
+                    if program.gitignore {
+                        self.ignore_stack.push((path.to_path_buf(), read_ignore_rules(path)));
+                    }
+                    for child in children {
+                        let child_name = child.to_string_lossy();
+                        let child_display = if display.ends_with('/') {
+                            format!("{}{}", display, child_name)
+                        } else {
+                            format!("{}/{}", display, child_name)
+                        };
+                        self.walk(&path.join(&child), &child_display, depth + 1);
+                    }
+                    if program.gitignore {
+                        self.ignore_stack.pop();
+                    }
+                }
+                Err(error) => self.error(&format!("'{}': {}", display, describe_error(&error))),
+            }
+        }
+
+        if program.depth_first && depth >= program.min_depth {
+            self.evaluate(&program.expr, path, display, &metadata);
+        }
+    }
+
+    /**
+     * Checks a path against the .gitignore rules in scope
+     *
+     * @param path - Path being visited
+     * @param is_dir - Whether it is a directory
+     * @return bool - True if the last matching rule ignores it
+     */
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir && path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+
+        let canonical_parent = path.parent().and_then(|parent| parent.canonicalize().ok());
+        let Some(canonical_parent) = canonical_parent else {
+            return false;
+        };
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+        let canonical = canonical_parent.join(name);
+
+        let mut ignored = false;
+        for (base, rules) in &self.ignore_stack {
+            let base = base.canonicalize().unwrap_or_else(|_| base.clone());
+            let Ok(relative) = canonical.strip_prefix(&base) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy();
+            let base_name = name.to_string_lossy();
+            for rule in rules {
+                if let Some(ignore) = rule.matches(&relative, &base_name, is_dir) {
+                    ignored = ignore;
+                }
+            }
+        }
+        ignored
+    }
+
+    /**
+     * 式を評価する関数です
+     *
+     * -aと-oは短絡評価します。-execの「;」形式はコマンドの
+     * 終了コードが0の場合に真となり、「+」形式はパスを
+     * ためておいて常に真を返します。-deleteはファイルまたは
+     * 空のディレクトリを削除し、失敗した場合は偽になります。
+     *
+     * @param expr - 評価する式
+     * @param path - 実際のパス
+     * @param display - 表示用のパス
+     * @param metadata - lstatのメタデータ
+     * @return bool - 式の値
+     */
+    fn evaluate(&mut self, expr: &Expr, path: &Path, display: &str, metadata: &Metadata) -> bool {
+        let file_type = metadata.file_type();
+        match expr {
+            Expr::And(left, right) => self.evaluate(left, path, display, metadata) && self.evaluate(right, path, display, metadata),
+            Expr::Or(left, right) => self.evaluate(left, path, display, metadata) || self.evaluate(right, path, display, metadata),
+            Expr::Not(inner) => !self.evaluate(inner, path, display, metadata),
+            Expr::Constant(value) => *value,
+            Expr::Name(pattern, case_insensitive) => {
+                let trimmed = display.trim_end_matches('/');
+                let name = trimmed.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or(display);
+                pattern.matches_with(name, loose_match(*case_insensitive))
+            }
+            Expr::Path(pattern, case_insensitive) => pattern.matches_with(display, loose_match(*case_insensitive)),
+            Expr::Type(types) => types.iter().any(|kind| match kind {
+                'f' => file_type.is_file(),
+                'd' => file_type.is_dir(),
+                'l' => file_type.is_symlink(),
+                'p' => file_type.is_fifo(),
+                's' => file_type.is_socket(),
+                'b' => file_type.is_block_device(),
+                'c' => file_type.is_char_device(),
+                _ => false,
+            }),
+            Expr::Size(comparison, unit) => comparison.matches(metadata.size().div_ceil(*unit) as i64),
+            Expr::Modified(comparison, period) => comparison.matches((self.now - metadata.mtime()).div_euclid(*period)),
+            Expr::Newer(reference) => (metadata.mtime(), metadata.mtime_nsec()) > *reference,
+            Expr::Empty => {
+                if file_type.is_dir() {
+                    fs::read_dir(path).map(|mut reader| reader.next().is_none()).unwrap_or(false)
+                } else {
+                    file_type.is_file() && metadata.size() == 0
+                }
+            }
+            Expr::Print => {
+                self.output.push_str(display);
+                self.output.push('\n');
+                true
+            }
+            Expr::Print0 => {
+                self.output.push_str(display);
+                self.output.push('\0');
+                true
+            }
+            Expr::Prune => {
+                self.pruned = true;
+                true
+            }
+            Expr::Delete => {
+                if display == "." {
+                    return true;
+                }
+                let removed = if file_type.is_dir() { fs::remove_dir(path) } else { fs::remove_file(path) };
+                match removed {
+                    Ok(()) => true,
+                    Err(error) => {
+                        self.error(&format!("cannot delete '{}': {}", display, describe_error(&error)));
+                        false
+                    }
+                }
+            }
+            Expr::Exec(command, false, _) => {
+                let argv: Vec<String> = command.iter().map(|word| word.replace("{}", display)).collect();
+                let result = (self.runner)(&argv);
+                self.output.push_str(&result.output);
+                result.exit_code == 0
+            }
+            Expr::Exec(command, true, index) => {
+                let batch = self.batches.entry(*index).or_insert_with(|| (command.clone(), Vec::new()));
+                batch.1.push(display.to_string());
+                if batch.1.len() >= EXEC_BATCH_LIMIT {
+                    let (command, paths) = std::mem::take(batch);
+                    batch.0 = command.clone();
+                    self.run_batch(command, paths);
+                }
+                true
+            }
+        }
+    }
+
+    /**
+     * Runs every pending `-exec ... {} +` batch
+     */
+    fn flush_batches(&mut self) {
+        let mut pending: Vec<_> = self.batches.drain().collect();
+        pending.sort_by_key(|(index, _)| *index);
+        for (_, (command, paths)) in pending {
+            if !paths.is_empty() {
+                self.run_batch(command, paths);
+            }
+        }
+    }
+
+    /**
+     * Runs one `-exec ... {} +` command with collected paths
+     *
+     * @param command - Command words before `{}`
+     * @param paths - Paths to append
+     */
+    fn run_batch(&mut self, mut command: Vec<String>, paths: Vec<String>) {
+        command.extend(paths);
+        let result = (self.runner)(&command);
+        self.output.push_str(&result.output);
+        if result.exit_code != 0 {
+            self.exit_code = 1;
+        }
+    }
+
+    /**
+     * Records an error message
+     *
+     * @param message - Message without the "find: " prefix
+     */
+    fn error(&mut self, message: &str) {
+        self.output.push_str(&format!("find: {}\n", message));
+        self.exit_code = 1;
+    }
+}
+
+/**
+ * Match options for -name and -path, where `*` also matches `/` and dots
+ *
+ * @param case_insensitive - For -iname and -ipath
+ * @return MatchOptions - Options
+ */
+fn loose_match(case_insensitive: bool) -> MatchOptions {
+    MatchOptions {
+        case_sensitive: !case_insensitive,
+        require_literal_separator: false,
+        require_literal_leading_dot: false,
+    }
+}
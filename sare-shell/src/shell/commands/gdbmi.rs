@@ -0,0 +1,804 @@
+/*!
+ * @file gdbmi.rs
+ * @brief GDB machine interface client
+ *
+ * This module runs `gdb --interpreter=mi3` as a child process, parses
+ * the result, async and stream records it prints, and maps the debug
+ * builtin's operations onto MI commands. Breakpoint state is kept up
+ * to date from gdb's =breakpoint-* notifications.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file gdbmi.rs
+ * @description MI record and value parsing, c-string unescaping,
+ * token-matched commands, *stopped handling and Ctrl+C interruption.
+ */
+
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::shell::commands::debugger::{Breakpoint, DebugBackend, Frame, Resume, Stop, StopReason, Variable};
+use crate::shell::commands::tail::InterruptGuard;
+
+/// How long to wait for gdb to answer a command that does not run the program
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often to check for Ctrl+C while the program runs
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/**
+ * Value in an MI result
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum MiValue {
+    /// C string constant, unescaped
+    Const(String),
+    /// `{name=value,...}`
+    Tuple(Vec<(String, MiValue)>),
+    /// `[value,...]`, or `[name=value,...]` with the names dropped
+    List(Vec<MiValue>),
+}
+
+impl MiValue {
+    /**
+     * Looks up a field of a tuple
+     *
+     * @param name - Field name
+     * @return Option<&MiValue> - First field with that name
+     */
+    pub fn get(&self, name: &str) -> Option<&MiValue> {
+        match self {
+            MiValue::Tuple(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /**
+     * Looks up a string field of a tuple
+     *
+     * @param name - Field name
+     * @return Option<&str> - Field value if it is a constant
+     */
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(MiValue::Const(text)) => Some(text),
+            _ => None,
+        }
+    }
+
+    /**
+     * Elements of a list, or nothing for other values
+     *
+     * @return &[MiValue] - List elements
+     */
+    pub fn items(&self) -> &[MiValue] {
+        match self {
+            MiValue::List(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+/**
+ * One line of MI output
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum MiRecord {
+    /// `^done`, `^running`, `^error`, ... answering a command
+    Result {
+        /// Token of the command being answered
+        token: Option<u64>,
+        /// Result class, e.g. `done`
+        class: String,
+        /// Results as a tuple
+        results: MiValue,
+    },
+    /// `*` exec, `+` status or `=` notify record
+    Async {
+        /// Record prefix character
+        kind: char,
+        /// Async class, e.g. `stopped`
+        class: String,
+        /// Results as a tuple
+        results: MiValue,
+    },
+    /// `~` console, `@` target or `&` log stream output
+    Stream {
+        /// Record prefix character
+        kind: char,
+        /// Unescaped text
+        text: String,
+    },
+    /// `(gdb)` prompt ending a batch of output
+    Prompt,
+}
+
+/**
+ * Cursor over a line of MI output
+ */
+struct Cursor<'a> {
+    /// Line bytes
+    bytes: &'a [u8],
+    /// Current position
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /**
+     * Next byte without consuming it
+     *
+     * @return Option<u8> - Byte, or None at the end
+     */
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    /**
+     * Consumes a byte if it matches
+     *
+     * @param byte - Expected byte
+     * @return bool - True if it was consumed
+     */
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /**
+     * Parses `name=value` pairs separated by commas
+     *
+     * @param end - Closing byte, or None for the end of the line
+     * @return Option<Vec<(String, MiValue)>> - Pairs, or None on a syntax error
+     */
+    fn results(&mut self, end: Option<u8>) -> Option<Vec<(String, MiValue)>> {
+        let mut fields = Vec::new();
+        if self.peek() == end {
+            return Some(fields);
+        }
+        loop {
+            fields.push(self.result()?);
+            if !self.eat(b',') {
+                return Some(fields);
+            }
+        }
+    }
+
+    /**
+     * Parses one `name=value` pair
+     *
+     * @return Option<(String, MiValue)> - Pair, or None on a syntax error
+     */
+    fn result(&mut self) -> Option<(String, MiValue)> {
+        let start = self.position;
+        while self.peek().is_some_and(|byte| byte != b'=') {
+            self.position += 1;
+        }
+        let name = String::from_utf8_lossy(&self.bytes[start..self.position]).to_string();
+        if name.is_empty() || !self.eat(b'=') {
+            return None;
+        }
+        Some((name, self.value()?))
+    }
+
+    /**
+     * Parses a constant, tuple or list
+     *
+     * @return Option<MiValue> - Value, or None on a syntax error
+     */
+    fn value(&mut self) -> Option<MiValue> {
+        match self.peek()? {
+            b'"' => self.c_string().map(MiValue::Const),
+            b'{' => {
+                self.position += 1;
+                let fields = self.results(Some(b'}'))?;
+                self.eat(b'}').then_some(MiValue::Tuple(fields))
+            }
+            b'[' => {
+                self.position += 1;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        let item = match self.peek()? {
+                            b'"' | b'{' | b'[' => self.value()?,
+                            _ => self.result()?.1,
+                        };
+                        items.push(item);
+                        if !self.eat(b',') {
+                            break;
+                        }
+                    }
+                    if !self.eat(b']') {
+                        return None;
+                    }
+                }
+                Some(MiValue::List(items))
+            }
+            _ => None,
+        }
+    }
+
+    /**
+     * C文字列を読んでエスケープを解除する関数です
+     *
+     * gdbはASCII以外のバイトを\ooo形式の8進数で出力するため、
+     * バイト列として集めてから最後にUTF-8として解釈します。
+     * \n、\t、\"、\\などの一般的なエスケープにも対応します。
+     *
+     * @return Option<String> - 文字列、または閉じ引用符がない場合None
+     */
+    fn c_string(&mut self) -> Option<String> {
+        if !self.eat(b'"') {
+            return None;
+        }
+        let mut text = Vec::new();
+        loop {
+            let byte = self.peek()?;
+            self.position += 1;
+            match byte {
+                b'"' => return Some(String::from_utf8_lossy(&text).to_string()),
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.position += 1;
+                    match escaped {
+                        b'n' => text.push(b'\n'),
+                        b't' => text.push(b'\t'),
+                        b'r' => text.push(b'\r'),
+                        b'e' => text.push(0x1b),
+                        b'a' => text.push(0x07),
+                        b'b' => text.push(0x08),
+                        b'f' => text.push(0x0c),
+                        b'v' => text.push(0x0b),
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + (digit - b'0') as u32;
+                                        self.position += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            text.push(value as u8);
+                        }
+                        other => text.push(other),
+                    }
+                }
+                other => text.push(other),
+            }
+        }
+    }
+}
+
+/**
+ * Parses one line of gdb output
+ *
+ * @param line - Line without its newline
+ * @return Option<MiRecord> - Record, or None for output that is not MI (the program's own output)
+ */
+pub fn parse_record(line: &str) -> Option<MiRecord> {
+    let line = line.trim_end_matches('\r');
+    if line.trim_end() == "(gdb)" {
+        return Some(MiRecord::Prompt);
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    let token = if digits > 0 { Some(line[..digits].parse().ok()?) } else { None };
+    let mut cursor = Cursor {
+        bytes: line.as_bytes(),
+        position: digits,
+    };
+    let kind = cursor.peek()? as char;
+    cursor.position += 1;
+
+    match kind {
+        '~' | '@' | '&' if token.is_none() => {
+            let text = cursor.c_string()?;
+            (cursor.position == line.len()).then_some(MiRecord::Stream { kind, text })
+        }
+        '^' | '*' | '+' | '=' => {
+            let start = cursor.position;
+            while cursor.peek().is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_') {
+                cursor.position += 1;
+            }
+            let class = line[start..cursor.position].to_string();
+            if class.is_empty() {
+                return None;
+            }
+            let fields = if cursor.eat(b',') { cursor.results(None)? } else { Vec::new() };
+            if cursor.position != line.len() {
+                return None;
+            }
+            let results = MiValue::Tuple(fields);
+            Some(if kind == '^' {
+                MiRecord::Result { token, class, results }
+            } else {
+                MiRecord::Async { kind, class, results }
+            })
+        }
+        _ => None,
+    }
+}
+
+/**
+ * Quotes a string as an MI c-string parameter
+ *
+ * @param text - Parameter
+ * @return String - Quoted parameter
+ */
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            other => quoted.push(other),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/**
+ * Quotes a program argument for the shell gdb starts the program with
+ *
+ * @param argument - Argument
+ * @return String - Single-quoted argument
+ */
+fn shell_quote(argument: &str) -> String {
+    format!("'{}'", argument.replace('\'', "'\\''"))
+}
+
+/**
+ * Reads a frame tuple
+ *
+ * @param value - `frame={...}` tuple
+ * @param level - Level to use when the tuple has none
+ * @return Frame - Frame
+ */
+fn frame_from(value: &MiValue, level: usize) -> Frame {
+    Frame {
+        level: value.text("level").and_then(|text| text.parse().ok()).unwrap_or(level),
+        function: value.text("func").unwrap_or("??").to_string(),
+        arguments: value
+            .get("args")
+            .map(|arguments| {
+                arguments
+                    .items()
+                    .iter()
+                    .filter_map(|argument| Some((argument.text("name")?.to_string(), argument.text("value").unwrap_or("...").to_string())))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        file: value.text("file").map(str::to_string),
+        full_path: value.text("fullname").map(PathBuf::from),
+        line: value.text("line").and_then(|text| text.parse().ok()),
+        address: value.text("addr").map(str::to_string),
+        library: value.text("from").map(str::to_string),
+    }
+}
+
+/**
+ * Reads a breakpoint tuple
+ *
+ * Multi-location breakpoints report the first location's source.
+ *
+ * @param value - `bkpt={...}` tuple
+ * @return Option<Breakpoint> - Breakpoint, or None without a number
+ */
+fn breakpoint_from(value: &MiValue) -> Option<Breakpoint> {
+    let location = value.get("locations").and_then(|locations| locations.items().first()).unwrap_or(value);
+    Some(Breakpoint {
+        number: value.text("number")?.parse().ok()?,
+        location: value
+            .text("original-location")
+            .or_else(|| value.text("pending"))
+            .unwrap_or_default()
+            .to_string(),
+        function: location.text("func").map(str::to_string),
+        file: location.text("file").map(str::to_string),
+        line: location.text("line").and_then(|text| text.parse().ok()),
+        enabled: value.text("enabled") != Some("n"),
+        hits: value.text("times").and_then(|text| text.parse().ok()).unwrap_or(0),
+        pending: value.text("addr") == Some("<PENDING>"),
+    })
+}
+
+/**
+ * *stoppedレコードを停止イベントに変換する関数です
+ *
+ * reasonに応じてブレークポイント、ステップ、関数の終了、シグナル、
+ * 終了に分類します。gdbは終了コードを8進数で出力するため、
+ * 8進数として読みます。-exec-interruptによる停止はSIGINTの
+ * signal-receivedとして届くので、中断として扱います。
+ *
+ * @param results - *stoppedの結果
+ * @return Stop - 停止イベント
+ */
+fn stop_from(results: &MiValue) -> Stop {
+    let text = |name: &str| results.text(name).unwrap_or_default().to_string();
+    let reason = match results.text("reason").unwrap_or_default() {
+        "breakpoint-hit" => StopReason::Breakpoint(results.text("bkptno").and_then(|number| number.parse().ok()).unwrap_or(0)),
+        "end-stepping-range" => StopReason::Step,
+        "function-finished" => StopReason::FunctionFinished(results.text("return-value").map(str::to_string)),
+        "exited-normally" => StopReason::Exited(0),
+        "exited" => StopReason::Exited(results.text("exit-code").and_then(|code| i32::from_str_radix(code, 8).ok()).unwrap_or(1)),
+        "exited-signalled" => StopReason::Signalled(text("signal-name"), text("signal-meaning")),
+        "signal-received" | "" if matches!(results.text("signal-name"), Some("SIGINT") | Some("0") | None) => StopReason::Interrupted,
+        "signal-received" => StopReason::Signal(text("signal-name"), text("signal-meaning")),
+        other => StopReason::Other(other.to_string()),
+    };
+    let frame = results.get("frame").map(|frame| frame_from(frame, 0));
+    Stop { reason, frame }
+}
+
+/**
+ * Debug backend driving gdb through the machine interface
+ */
+pub struct GdbBackend {
+    /// gdb process
+    child: Child,
+    /// gdb's standard input, for commands
+    input: ChildStdin,
+    /// Lines printed by gdb, read on a separate thread
+    lines: Receiver<String>,
+    /// Token of the next command
+    next_token: u64,
+    /// Thread the program last stopped in
+    thread: Option<String>,
+    /// Breakpoints as gdb last reported them
+    breakpoints: Vec<Breakpoint>,
+    /// Output of the program and gdb's console not yet shown
+    pending_output: String,
+}
+
+impl GdbBackend {
+    /**
+     * gdbを起動してMIセッションを始める関数です
+     *
+     * gdbを独自のプロセスグループで起動し、端末のCtrl+Cが直接
+     * 届かないようにします（中断は-exec-interruptで行います）。
+     * 標準出力は別スレッドで1行ずつ読み、チャネルに送ります。
+     * 最初のプロンプトを待ってから、プログラムの実行中もコマンドを
+     * 受け付けられるようにmi-asyncを有効にします。
+     *
+     * @param gdb - gdbの実行ファイル
+     * @param program - デバッグするプログラム
+     * @param directory - 作業ディレクトリ
+     * @return Result<GdbBackend> - バックエンドまたはエラー
+     */
+    pub fn start(gdb: &Path, program: &Path, directory: &Path) -> Result<Self> {
+        let mut child = Command::new(gdb)
+            .args(["--interpreter=mi3", "-q", "-nx"])
+            .arg(program)
+            .current_dir(directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()
+            .map_err(|error| anyhow::anyhow!("{}: {}", gdb.display(), error))?;
+        let input = child.stdin.take().ok_or_else(|| anyhow::anyhow!("gdb has no input"))?;
+        let output = child.stdout.take().ok_or_else(|| anyhow::anyhow!("gdb has no output"))?;
+
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(output);
+            let mut line = Vec::new();
+            while reader.read_until(b'\n', &mut line).is_ok_and(|count| count > 0) {
+                let text = String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r']).to_string();
+                if sender.send(text).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        });
+
+        let mut backend = GdbBackend {
+            child,
+            input,
+            lines,
+            next_token: 1,
+            thread: None,
+            breakpoints: Vec::new(),
+            pending_output: String::new(),
+        };
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        let mut errors = String::new();
+        loop {
+            match backend.next_record(deadline)? {
+                Some(MiRecord::Prompt) => break,
+                Some(MiRecord::Stream { kind: '&', text }) => errors.push_str(&text),
+                _ => {}
+            }
+        }
+        if errors.contains("No such file or directory") || errors.contains("not in executable format") {
+            return Err(anyhow::anyhow!("{}", errors.lines().next().unwrap_or_default().trim()));
+        }
+        backend.pending_output.clear();
+        backend.command("-gdb-set mi-async on")?;
+        Ok(backend)
+    }
+
+    /**
+     * Reads the next line from gdb
+     *
+     * @param deadline - Time to give up waiting
+     * @return Result<Option<MiRecord>> - Record, None for program output, or an error when gdb exits or times out
+     */
+    fn next_record(&mut self, deadline: Instant) -> Result<Option<MiRecord>> {
+        match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => Ok(self.handle_line(line)),
+            Err(RecvTimeoutError::Timeout) => Err(anyhow::anyhow!("gdb did not respond")),
+            Err(RecvTimeoutError::Disconnected) => Err(anyhow::anyhow!("gdb exited")),
+        }
+    }
+
+    /**
+     * Parses a line, applying notifications to the breakpoint table
+     *
+     * Lines that are not MI records and target stream output are the
+     * program's own output and are kept until they can be shown.
+     *
+     * @param line - Line printed by gdb
+     * @return Option<MiRecord> - Record, or None for program output
+     */
+    fn handle_line(&mut self, line: String) -> Option<MiRecord> {
+        let Some(record) = parse_record(&line) else {
+            self.pending_output.push_str(&line);
+            self.pending_output.push('\n');
+            return None;
+        };
+        match &record {
+            MiRecord::Async { kind: '=', class, results } => match class.as_str() {
+                "breakpoint-created" | "breakpoint-modified" => {
+                    if let Some(breakpoint) = results.get("bkpt").and_then(breakpoint_from) {
+                        self.update_breakpoint(breakpoint);
+                    }
+                }
+                "breakpoint-deleted" => {
+                    let number = results.text("id").and_then(|id| id.parse::<u32>().ok());
+                    self.breakpoints.retain(|breakpoint| Some(breakpoint.number) != number);
+                }
+                _ => {}
+            },
+            MiRecord::Stream { kind: '@', text } => self.pending_output.push_str(text),
+            _ => {}
+        }
+        Some(record)
+    }
+
+    /**
+     * Adds or replaces a breakpoint in the table
+     *
+     * @param breakpoint - Breakpoint as gdb reported it
+     */
+    fn update_breakpoint(&mut self, breakpoint: Breakpoint) {
+        match self.breakpoints.iter_mut().find(|existing| existing.number == breakpoint.number) {
+            Some(existing) => *existing = breakpoint,
+            None => {
+                self.breakpoints.push(breakpoint);
+                self.breakpoints.sort_by_key(|breakpoint| breakpoint.number);
+            }
+        }
+    }
+
+    /**
+     * Sends a command without waiting for its result
+     *
+     * @param command - MI command
+     * @return Result<u64> - Token of the command
+     */
+    fn send(&mut self, command: &str) -> Result<u64> {
+        let token = self.next_token;
+        self.next_token += 1;
+        writeln!(self.input, "{}{}", token, command)?;
+        self.input.flush()?;
+        Ok(token)
+    }
+
+    /**
+     * Sends a command and waits for its result record
+     *
+     * @param command - MI command
+     * @return Result<MiValue> - Results of `^done` or `^running`, or the `^error` message
+     */
+    fn command(&mut self, command: &str) -> Result<MiValue> {
+        let token = self.send(command)?;
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        loop {
+            if let Some(MiRecord::Result { token: Some(answered), class, results }) = self.next_record(deadline)? {
+                if answered != token {
+                    continue;
+                }
+                return match class.as_str() {
+                    "error" => Err(anyhow::anyhow!("{}", results.text("msg").unwrap_or("gdb reported an error"))),
+                    "exit" => Err(anyhow::anyhow!("gdb exited")),
+                    _ => Ok(results),
+                };
+            }
+        }
+    }
+
+    /**
+     * プログラムが停止するまで待つ関数です
+     *
+     * *stoppedレコードが届くまで行を読み続けます。その間の
+     * プログラムの出力は届いた順にsinkへ渡します。Ctrl+Cが押されたら
+     * 一度だけ-exec-interruptを送り、その結果の停止を待ちます。
+     *
+     * @param sink - プログラムの出力を受け取る関数
+     * @return Result<Stop> - 停止イベントまたはエラー
+     */
+    fn wait_for_stop(&mut self, sink: &mut dyn FnMut(&str)) -> Result<Stop> {
+        let guard = InterruptGuard::install();
+        let mut interrupt_sent = false;
+        loop {
+            if guard.interrupted() && !interrupt_sent {
+                self.send("-exec-interrupt --all")?;
+                interrupt_sent = true;
+            }
+            let record = match self.lines.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => self.handle_line(line),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return Err(anyhow::anyhow!("gdb exited")),
+            };
+            if !self.pending_output.is_empty() {
+                sink(&std::mem::take(&mut self.pending_output));
+            }
+            if let Some(MiRecord::Async { kind: '*', class, results }) = record {
+                if class == "stopped" {
+                    if let Some(thread) = results.text("thread-id") {
+                        self.thread = Some(thread.to_string());
+                    }
+                    return Ok(stop_from(&results));
+                }
+            }
+        }
+    }
+
+    /**
+     * Argument selecting the stopped thread
+     *
+     * @return String - `--thread T `, or empty before the program stopped
+     */
+    fn thread_option(&self) -> String {
+        self.thread.as_ref().map(|thread| format!("--thread {} ", thread)).unwrap_or_default()
+    }
+
+    /**
+     * Arguments selecting the stopped thread and a frame
+     *
+     * @param frame - Frame level
+     * @return String - `--thread T --frame N `, or empty before the program stopped
+     */
+    fn frame_options(&self, frame: usize) -> String {
+        match &self.thread {
+            Some(thread) => format!("--thread {} --frame {} ", thread, frame),
+            None => String::new(),
+        }
+    }
+}
+
+impl DebugBackend for GdbBackend {
+    fn name(&self) -> &str {
+        "gdb"
+    }
+
+    fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    fn insert_breakpoint(&mut self, location: &str) -> Result<Breakpoint> {
+        let results = self.command(&format!("-break-insert -f {}", quote(location)))?;
+        let breakpoint = results
+            .get("bkpt")
+            .and_then(breakpoint_from)
+            .ok_or_else(|| anyhow::anyhow!("gdb did not report the breakpoint"))?;
+        self.update_breakpoint(breakpoint.clone());
+        Ok(breakpoint)
+    }
+
+    fn delete_breakpoint(&mut self, number: Option<u32>) -> Result<()> {
+        let numbers: Vec<u32> = match number {
+            Some(number) if self.breakpoints.iter().any(|breakpoint| breakpoint.number == number) => vec![number],
+            Some(number) => return Err(anyhow::anyhow!("No breakpoint number {}.", number)),
+            None => self.breakpoints.iter().map(|breakpoint| breakpoint.number).collect(),
+        };
+        if numbers.is_empty() {
+            return Ok(());
+        }
+        let list: Vec<String> = numbers.iter().map(u32::to_string).collect();
+        self.command(&format!("-break-delete {}", list.join(" ")))?;
+        self.breakpoints.retain(|breakpoint| !numbers.contains(&breakpoint.number));
+        Ok(())
+    }
+
+    fn run(&mut self, arguments: &[String], sink: &mut dyn FnMut(&str)) -> Result<Stop> {
+        let quoted: Vec<String> = arguments.iter().map(|argument| shell_quote(argument)).collect();
+        self.command(&format!("-exec-arguments {} < /dev/null", quoted.join(" ")))?;
+        self.thread = None;
+        self.command("-exec-run")?;
+        self.wait_for_stop(sink)
+    }
+
+    fn resume(&mut self, resume: Resume, sink: &mut dyn FnMut(&str)) -> Result<Stop> {
+        let command = match resume {
+            Resume::Continue => "-exec-continue",
+            Resume::StepInto => "-exec-step",
+            Resume::StepOver => "-exec-next",
+            Resume::StepOut => "-exec-finish",
+        };
+        self.command(&format!("{} {}", command, self.thread_option()))?;
+        self.wait_for_stop(sink)
+    }
+
+    fn backtrace(&mut self) -> Result<Vec<Frame>> {
+        let results = self.command(&format!("-stack-list-frames {}", self.thread_option()))?;
+        let mut frames: Vec<Frame> = results
+            .get("stack")
+            .map(|stack| stack.items().iter().enumerate().map(|(level, frame)| frame_from(frame, level)).collect())
+            .unwrap_or_default();
+        if let Ok(arguments) = self.command(&format!("-stack-list-arguments {}--all-values", self.thread_option())) {
+            let listed = arguments.get("stack-args").map(MiValue::items).unwrap_or_default();
+            for (frame, listed) in frames.iter_mut().zip(listed) {
+                frame.arguments = frame_from(listed, frame.level).arguments;
+            }
+        }
+        Ok(frames)
+    }
+
+    fn locals(&mut self, frame: usize) -> Result<Vec<Variable>> {
+        let results = self.command(&format!("-stack-list-variables {}--all-values", self.frame_options(frame)))?;
+        Ok(results
+            .get("variables")
+            .map(|variables| {
+                variables
+                    .items()
+                    .iter()
+                    .filter_map(|variable| {
+                        Some(Variable {
+                            name: variable.text("name")?.to_string(),
+                            value: variable.text("value").unwrap_or("<optimized out>").to_string(),
+                            type_name: variable.text("type").map(str::to_string),
+                            argument: variable.text("arg") == Some("1"),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn evaluate(&mut self, expression: &str, frame: usize) -> Result<String> {
+        let results = self.command(&format!("-data-evaluate-expression {}{}", self.frame_options(frame), quote(expression)))?;
+        Ok(results.text("value").unwrap_or_default().to_string())
+    }
+}
+
+impl Drop for GdbBackend {
+    /**
+     * Asks gdb to exit, which kills the program, and kills gdb if it does not
+     */
+    fn drop(&mut self) {
+        if self.send("-gdb-exit").is_ok() {
+            let deadline = Instant::now() + Duration::from_secs(2);
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = self.child.try_wait() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
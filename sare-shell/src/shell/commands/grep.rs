@@ -0,0 +1,685 @@
+/*!
+ * @file grep.rs
+ * @brief Pattern matcher and file searcher for the grep builtin
+ *
+ * This module compiles grep patterns (basic, extended, fixed and
+ * Perl-style), searches buffers line by line with context and output
+ * modes, and collects and searches files in parallel.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file grep.rs
+ * @description grep matching with -w/-x/-i, context lines, -o/-c/-l/-L/-m,
+ * binary file detection, recursive file collection and colored output.
+ */
+
+use anyhow::Result;
+use glob::Pattern;
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::config::ThemeConfig;
+use crate::shell::commands::fileops::describe_error;
+use crate::shell::commands::sed::translate_posix_regex;
+
+/// Name printed for the pipeline input
+pub const STDIN_NAME: &str = "(standard input)";
+
+/// ANSI reset sequence
+const RESET: &str = "\x1b[0m";
+
+/**
+ * Pattern language selected by -G, -E, -F or -P
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternSyntax {
+    /// POSIX basic regular expressions (-G, the default)
+    Basic,
+    /// POSIX extended regular expressions (-E)
+    Extended,
+    /// Fixed strings (-F)
+    Fixed,
+    /// Perl-style expressions as understood by the regex crate (-P);
+    /// look-around and backreferences are not supported
+    Perl,
+}
+
+/**
+ * How files containing NUL bytes are treated
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BinaryFiles {
+    /// Report "binary file matches" instead of printing lines
+    #[default]
+    Report,
+    /// Search them as text (-a)
+    Text,
+    /// Treat them as not matching (-I)
+    Skip,
+}
+
+/**
+ * Compiled set of grep patterns
+ */
+#[derive(Debug)]
+pub struct Matcher {
+    /// All patterns joined into one alternation
+    regex: Regex,
+    /// Matches must be surrounded by non-word characters (-w)
+    whole_word: bool,
+}
+
+impl Matcher {
+    /**
+     * Compiles the patterns
+     *
+     * Each pattern may hold several newline-separated patterns; a line
+     * matches if any of them matches. Invalid patterns are reported
+     * with GNU grep's wording where there is an equivalent.
+     *
+     * @param patterns - Patterns from the command line or -e
+     * @param syntax - Pattern language
+     * @param ignore_case - Case-insensitive matching (-i)
+     * @param whole_word - Match whole words only (-w)
+     * @param whole_line - Match whole lines only (-x)
+     * @return Result<Matcher> - Matcher or an error for an invalid pattern
+     */
+    pub fn new(patterns: &[String], syntax: PatternSyntax, ignore_case: bool, whole_word: bool, whole_line: bool) -> Result<Self> {
+        let mut alternatives = Vec::new();
+        for pattern in patterns.iter().flat_map(|pattern| pattern.split('\n')) {
+            let translated = match syntax {
+                PatternSyntax::Fixed => regex::escape(pattern),
+                PatternSyntax::Basic => translate_posix_regex(pattern, false).map_err(|error| pattern_error(&error.to_string()))?,
+                PatternSyntax::Extended => translate_posix_regex(pattern, true).map_err(|error| pattern_error(&error.to_string()))?,
+                PatternSyntax::Perl => pattern.to_string(),
+            };
+            // Checked alone so an unbalanced pattern is not blamed on the alternation around it
+            Regex::new(&translated).map_err(|error| regex_error(&error))?;
+            alternatives.push(format!("(?:{})", translated));
+        }
+
+        let mut combined = alternatives.join("|");
+        if whole_line {
+            combined = format!("^(?:{})$", combined);
+        }
+        let regex = RegexBuilder::new(&combined)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|error| regex_error(&error))?;
+        Ok(Matcher { regex, whole_word })
+    }
+
+    /**
+     * Checks whether a line contains a match
+     *
+     * @param line - Line without its newline
+     * @return bool - True if any pattern matches
+     */
+    pub fn is_match(&self, line: &str) -> bool {
+        if self.whole_word {
+            self.next_match(line, 0).is_some()
+        } else {
+            self.regex.is_match(line)
+        }
+    }
+
+    /**
+     * Finds every non-overlapping match in a line
+     *
+     * @param line - Line without its newline
+     * @return Vec<(usize, usize)> - Byte ranges of the matches
+     */
+    pub fn find_all(&self, line: &str) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while let Some((match_start, match_end)) = self.next_match(line, start) {
+            matches.push((match_start, match_end));
+            start = if match_end > match_start { match_end } else { next_boundary(line, match_end) };
+            if start > line.len() {
+                break;
+            }
+        }
+        matches
+    }
+
+    /**
+     * Finds the first acceptable match at or after a position
+     *
+     * With -w, matches touching a word character on either side are
+     * skipped and the search resumes one character later.
+     *
+     * @param line - Line to search
+     * @param start - Byte offset to start at
+     * @return Option<(usize, usize)> - Byte range of the match
+     */
+    fn next_match(&self, line: &str, mut start: usize) -> Option<(usize, usize)> {
+        while start <= line.len() {
+            let found = self.regex.find_at(line, start)?;
+            if !self.whole_word || is_word_bounded(line, found.start(), found.end()) {
+                return Some((found.start(), found.end()));
+            }
+            start = next_boundary(line, found.start());
+        }
+        None
+    }
+}
+
+/**
+ * Whether a character belongs to a word for -w
+ *
+ * @param c - Character
+ * @return bool - True for letters, digits and underscore
+ */
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/**
+ * Checks that a match is not part of a longer word
+ *
+ * @param line - Line containing the match
+ * @param start - Match start
+ * @param end - Match end
+ * @return bool - True if no word character touches the match
+ */
+fn is_word_bounded(line: &str, start: usize, end: usize) -> bool {
+    let before = line[..start].chars().next_back().is_some_and(is_word_char);
+    let after = line[end..].chars().next().is_some_and(is_word_char);
+    !before && !after
+}
+
+/**
+ * Rewords a pattern error the way GNU grep reports it
+ *
+ * @param message - Error from the POSIX translation or the regex crate
+ * @return anyhow::Error - Error with GNU grep's message, or the original text
+ */
+fn pattern_error(message: &str) -> anyhow::Error {
+    let reworded = match message {
+        "unterminated address regex" | "unterminated character class" | "unclosed character class" => {
+            "Unmatched [, [^, [:, [., or [="
+        }
+        "unclosed group" => "Unmatched ( or \\(",
+        "unopened group" => "Unmatched ) or \\)",
+        "unclosed counted repetition" => "Unmatched \\{",
+        "trailing backslash (\\)" | "incomplete escape sequence, reached end of pattern prematurely" => "Trailing backslash",
+        other => other,
+    };
+    anyhow::anyhow!("{}", reworded)
+}
+
+/**
+ * Converts a regex crate error into a grep pattern error
+ *
+ * The crate's message repeats the pattern with a caret under the
+ * problem; only the final "error: ..." line is kept.
+ *
+ * @param error - Compilation error
+ * @return anyhow::Error - Pattern error
+ */
+fn regex_error(error: &regex::Error) -> anyhow::Error {
+    let text = error.to_string();
+    let detail = text
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("error: "))
+        .unwrap_or(&text);
+    pattern_error(detail)
+}
+
+/**
+ * Returns the byte offset of the character after a position
+ *
+ * @param line - Line
+ * @param position - Current byte offset
+ * @return usize - Next character boundary, or line length + 1 at the end
+ */
+fn next_boundary(line: &str, position: usize) -> usize {
+    line[position..].chars().next().map_or(line.len() + 1, |c| position + c.len_utf8())
+}
+
+/**
+ * ANSI sequences used by --color
+ */
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    /// Matched text
+    matched: String,
+    /// File names
+    file_name: String,
+    /// Line numbers
+    line_number: String,
+    /// `:`, `-` and `--` separators
+    separator: String,
+}
+
+impl Highlight {
+    /**
+     * Builds the color scheme from the shell theme
+     *
+     * Matches use the theme's match color in bold, file names its
+     * primary color and line numbers its secondary color.
+     *
+     * @param theme - Theme configuration
+     * @return Highlight - Color scheme; malformed colors fall back to GNU grep's
+     */
+    pub fn from_theme(theme: &ThemeConfig) -> Self {
+        let color = |value: &str, fallback: &str| ThemeConfig::ansi_foreground(value).unwrap_or_else(|| fallback.to_string());
+        Highlight {
+            matched: format!("\x1b[1m{}", color(&theme.match_color, "\x1b[31m")),
+            file_name: color(&theme.primary_color, "\x1b[35m"),
+            line_number: color(&theme.secondary_color, "\x1b[32m"),
+            separator: "\x1b[36m".to_string(),
+        }
+    }
+}
+
+/**
+ * Options that shape grep's output
+ */
+#[derive(Debug, Clone, Default)]
+pub struct GrepOptions {
+    /// Select non-matching lines (-v)
+    pub invert: bool,
+    /// Prefix lines with their number (-n)
+    pub line_numbers: bool,
+    /// Prefix lines with the file name (-H, or several files)
+    pub with_file_names: bool,
+    /// Print only the matched parts (-o)
+    pub only_matching: bool,
+    /// Print the number of selected lines (-c)
+    pub count: bool,
+    /// Print names of files with selected lines (-l)
+    pub files_with_matches: bool,
+    /// Print names of files without selected lines (-L)
+    pub files_without_match: bool,
+    /// Lines of context before each match (-B)
+    pub before_context: usize,
+    /// Lines of context after each match (-A)
+    pub after_context: usize,
+    /// Stop after this many selected lines (-m)
+    pub max_count: Option<usize>,
+    /// Print nothing (-q)
+    pub quiet: bool,
+    /// Handling of binary files (-a, -I)
+    pub binary_files: BinaryFiles,
+    /// Colors, when --color is active
+    pub colors: Option<Highlight>,
+}
+
+impl GrepOptions {
+    /**
+     * Whether context lines are printed
+     *
+     * @return bool - True if -A, -B or -C asked for context and lines are printed
+     */
+    pub fn uses_context(&self) -> bool {
+        (self.before_context > 0 || self.after_context > 0) && !self.only_matching
+    }
+
+    /**
+     * Wraps text in a color sequence when colors are on
+     *
+     * @param text - Text to print
+     * @param pick - Selects the color from the scheme
+     * @return String - Colored or plain text
+     */
+    fn paint(&self, text: &str, pick: fn(&Highlight) -> &str) -> String {
+        match &self.colors {
+            Some(colors) if !text.is_empty() => format!("{}{}{}", pick(colors), text, RESET),
+            _ => text.to_string(),
+        }
+    }
+
+    /**
+     * Builds the file name and line number prefix of an output line
+     *
+     * @param name - File name
+     * @param line_number - 1-based line number
+     * @param separator - ':' for selected lines, '-' for context
+     * @return String - Prefix
+     */
+    fn prefix(&self, name: &str, line_number: usize, separator: &str) -> String {
+        let mut prefix = String::new();
+        if self.with_file_names {
+            prefix.push_str(&self.paint(name, |colors| &colors.file_name));
+            prefix.push_str(&self.paint(separator, |colors| &colors.separator));
+        }
+        if self.line_numbers {
+            prefix.push_str(&self.paint(&line_number.to_string(), |colors| &colors.line_number));
+            prefix.push_str(&self.paint(separator, |colors| &colors.separator));
+        }
+        prefix
+    }
+}
+
+/**
+ * Result of searching one input
+ */
+#[derive(Debug, Default)]
+pub struct SearchReport {
+    /// Text to print
+    pub output: String,
+    /// Number of selected lines
+    pub selected: usize,
+}
+
+/**
+ * Input file found on the command line or by recursion
+ */
+#[derive(Debug, Clone)]
+pub struct SearchTarget {
+    /// Name printed in output
+    pub name: String,
+    /// Path to read
+    pub path: PathBuf,
+}
+
+/**
+ * Buffer searcher
+ */
+pub struct Searcher<'a> {
+    /// Compiled patterns
+    pub matcher: &'a Matcher,
+    /// Output options
+    pub options: &'a GrepOptions,
+}
+
+impl Searcher<'_> {
+    /**
+     * 1つの入力を検索する関数です
+     *
+     * NUL文字を含む入力はバイナリとみなし、-aがなければ行を出力せず
+     * 「binary file matches」と報告します（-Iの場合は一致なしとします）。
+     *
+     * 前後の文脈行（-B、-A）は重複しないように出力し、連続しない
+     * グループの間には「--」を挟みます。-mの上限に達した後も、
+     * 最後の一致の後の文脈行は出力します。
+     *
+     * -c、-l、-L、-qの場合は行を出力せず、-l、-L、-qでは最初の
+     * 一致で検索を打ち切ります。
+     *
+     * @param name - 出力に使う名前
+     * @param data - 入力の内容
+     * @return SearchReport - 出力と選択された行数
+     */
+    pub fn search(&self, name: &str, data: &[u8]) -> SearchReport {
+        let options = self.options;
+        let binary = options.binary_files != BinaryFiles::Text && data.contains(&0);
+        if binary && options.binary_files == BinaryFiles::Skip {
+            return SearchReport::default();
+        }
+
+        let text = String::from_utf8_lossy(data);
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        if text.ends_with('\n') || text.is_empty() {
+            lines.pop();
+        }
+
+        let stop_at_first = options.files_with_matches || options.files_without_match || options.quiet;
+        let prints_lines = !(options.count || stop_at_first || binary);
+        let uses_context = options.uses_context();
+        let mut report = SearchReport::default();
+        let mut last_printed: Option<usize> = None;
+        let mut after_remaining = 0;
+
+        for (index, line) in lines.iter().enumerate() {
+            let limit_reached = options.max_count.is_some_and(|max| report.selected >= max);
+            if limit_reached {
+                if prints_lines && uses_context && after_remaining > 0 {
+                    self.push_context(&mut report.output, name, index, line, &mut last_printed);
+                    after_remaining -= 1;
+                    continue;
+                }
+                break;
+            }
+
+            if self.matcher.is_match(line) == options.invert {
+                if prints_lines && uses_context && after_remaining > 0 {
+                    self.push_context(&mut report.output, name, index, line, &mut last_printed);
+                    after_remaining -= 1;
+                }
+                continue;
+            }
+
+            report.selected += 1;
+            if stop_at_first {
+                break;
+            }
+            if !prints_lines {
+                continue;
+            }
+
+            if uses_context {
+                let first = index
+                    .saturating_sub(options.before_context)
+                    .max(last_printed.map_or(0, |printed| printed + 1));
+                for (offset, context_line) in lines[first..index].iter().enumerate() {
+                    self.push_context(&mut report.output, name, first + offset, context_line, &mut last_printed);
+                }
+                self.push_group_separator(&mut report.output, index, last_printed);
+                after_remaining = options.after_context;
+            }
+            self.push_selected(&mut report.output, name, index, line);
+            last_printed = Some(index);
+        }
+
+        let colored_name = options.paint(name, |colors| &colors.file_name);
+        if options.quiet {
+            report.output.clear();
+        } else if options.files_with_matches || options.files_without_match {
+            let listed = (report.selected > 0) == options.files_with_matches;
+            report.output = if listed { format!("{}\n", colored_name) } else { String::new() };
+        } else if options.count {
+            report.output = if options.with_file_names {
+                format!("{}{}{}\n", colored_name, options.paint(":", |colors| &colors.separator), report.selected)
+            } else {
+                format!("{}\n", report.selected)
+            };
+        } else if binary && report.selected > 0 {
+            report.output = format!("grep: {}: binary file matches\n", name);
+        }
+        report
+    }
+
+    /**
+     * Appends a selected line, or its matches with -o
+     *
+     * @param output - Output buffer
+     * @param name - File name
+     * @param index - 0-based line index
+     * @param line - Line text
+     */
+    fn push_selected(&self, output: &mut String, name: &str, index: usize, line: &str) {
+        let options = self.options;
+        if options.only_matching {
+            if options.invert {
+                return;
+            }
+            for (start, end) in self.matcher.find_all(line) {
+                if end > start {
+                    output.push_str(&options.prefix(name, index + 1, ":"));
+                    output.push_str(&options.paint(&line[start..end], |colors| &colors.matched));
+                    output.push('\n');
+                }
+            }
+            return;
+        }
+
+        output.push_str(&options.prefix(name, index + 1, ":"));
+        if options.colors.is_some() && !options.invert {
+            let mut position = 0;
+            for (start, end) in self.matcher.find_all(line) {
+                output.push_str(&line[position..start]);
+                output.push_str(&options.paint(&line[start..end], |colors| &colors.matched));
+                position = end;
+            }
+            output.push_str(&line[position..]);
+        } else {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+
+    /**
+     * Appends a context line
+     *
+     * @param output - Output buffer
+     * @param name - File name
+     * @param index - 0-based line index
+     * @param line - Line text
+     * @param last_printed - Index of the last printed line, updated
+     */
+    fn push_context(&self, output: &mut String, name: &str, index: usize, line: &str, last_printed: &mut Option<usize>) {
+        self.push_group_separator(output, index, *last_printed);
+        output.push_str(&self.options.prefix(name, index + 1, "-"));
+        output.push_str(line);
+        output.push('\n');
+        *last_printed = Some(index);
+    }
+
+    /**
+     * Appends `--` when a line does not follow the last printed one
+     *
+     * @param output - Output buffer
+     * @param index - Line about to be printed
+     * @param last_printed - Index of the last printed line
+     */
+    fn push_group_separator(&self, output: &mut String, index: usize, last_printed: Option<usize>) {
+        if last_printed.is_some_and(|printed| index > printed + 1) {
+            output.push_str(&self.options.paint("--", |colors| &colors.separator));
+            output.push('\n');
+        }
+    }
+}
+
+/**
+ * Filters applied while collecting files
+ */
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Only search files whose name matches one of these (--include)
+    pub include: Vec<Pattern>,
+    /// Skip files whose name matches one of these (--exclude)
+    pub exclude: Vec<Pattern>,
+    /// Skip directories whose name matches one of these (--exclude-dir)
+    pub exclude_dir: Vec<Pattern>,
+    /// Search directories recursively (-r, -R)
+    pub recursive: bool,
+    /// Follow symbolic links found during recursion (-R)
+    pub follow_links: bool,
+}
+
+impl FileFilter {
+    /**
+     * Checks --include and --exclude against a file name
+     *
+     * @param name - Base name of the file
+     * @return bool - True if the file should be searched
+     */
+    fn accepts_file(&self, name: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(name));
+        included && !self.exclude.iter().any(|pattern| pattern.matches(name))
+    }
+
+    /**
+     * 検索するファイルを集める関数です
+     *
+     * コマンドラインで指定されたシンボリックリンクは常に辿りますが、
+     * 再帰中に見つかったものは-Rの場合だけ辿ります。ディレクトリの
+     * 項目は名前順に並べるため、並列に検索しても出力の順序は
+     * 毎回同じになります。-rなしでディレクトリを指定した場合や
+     * 読み込めないディレクトリはエラーとして記録します。
+     *
+     * @param name - 出力に使う名前
+     * @param path - 実際のパス
+     * @param top_level - コマンドラインで指定されたパスかどうか
+     * @param targets - 見つかったファイルの追加先
+     * @param errors - エラーメッセージの追加先
+     */
+    pub fn collect(&self, name: &str, path: &Path, top_level: bool, targets: &mut Vec<SearchTarget>, errors: &mut Vec<String>) {
+        let metadata = if top_level || self.follow_links { fs::metadata(path) } else { fs::symlink_metadata(path) };
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                errors.push(format!("grep: {}: {}\n", name, describe_error(&error)));
+                return;
+            }
+        };
+        let base_name = path.file_name().map(|base| base.to_string_lossy().into_owned()).unwrap_or_default();
+
+        if metadata.is_dir() {
+            if !self.recursive {
+                errors.push(format!("grep: {}: Is a directory\n", name));
+                return;
+            }
+            if !top_level && self.exclude_dir.iter().any(|pattern| pattern.matches(&base_name)) {
+                return;
+            }
+            let mut children: Vec<_> = match fs::read_dir(path) {
+                Ok(reader) => reader.flatten().map(|entry| entry.file_name()).collect(),
+                Err(error) => {
+                    errors.push(format!("grep: {}: {}\n", name, describe_error(&error)));
+                    return;
+                }
+            };
+            children.sort();
+            for child in children {
+                let child_name = child.to_string_lossy();
+                let display = match name {
+                    "" => child_name.into_owned(),
+                    _ if name.ends_with('/') => format!("{}{}", name, child_name),
+                    _ => format!("{}/{}", name, child_name),
+                };
+                self.collect(&display, &path.join(&child), false, targets, errors);
+            }
+        } else if (metadata.is_file() || top_level) && ((top_level && !self.recursive) || self.accepts_file(&base_name)) {
+            targets.push(SearchTarget {
+                name: name.to_string(),
+                path: path.to_path_buf(),
+            });
+        }
+    }
+}
+
+/**
+ * Searches files in parallel
+ *
+ * Worker threads take files in order; results are returned in the
+ * order of `targets` so output is stable.
+ *
+ * @param targets - Files to search
+ * @param searcher - Searcher to run on each file
+ * @return Vec<Result<SearchReport, String>> - Report or error message per file
+ */
+pub fn search_files(targets: &[SearchTarget], searcher: &Searcher) -> Vec<Result<SearchReport, String>> {
+    let workers = std::thread::available_parallelism().map_or(4, |count| count.get()).min(targets.len());
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<SearchReport, String>>>> = Mutex::new(targets.iter().map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(target) = targets.get(index) else {
+                    break;
+                };
+                let result = fs::read(&target.path)
+                    .map(|data| searcher.search(&target.name, &data))
+                    .map_err(|error| format!("grep: {}: {}\n", target.name, describe_error(&error)));
+                if let Ok(mut results) = results.lock() {
+                    results[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err("grep: search did not run\n".to_string())))
+        .collect()
+}
@@ -0,0 +1,838 @@
+/*!
+ * @file http.rs
+ * @brief HTTP/1.1 client
+ *
+ * This module implements the HTTP client used by the `curl` and
+ * `wget` builtins: URL parsing, request writing, response parsing
+ * with chunked and length-delimited bodies, and redirects. Bodies can
+ * be streamed to a file or stdout as they arrive.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file http.rs
+ * @description Plain-HTTP/1.1 client over std TcpStream with redirect
+ * handling, chunked transfer decoding and a text progress bar.
+ */
+
+use anyhow::Result;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::shell::commands::fileops::describe_error;
+
+/**
+ * Kind of failure, used to pick curl and wget exit codes
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpErrorKind {
+    /// URL scheme is not http
+    UnsupportedProtocol,
+    /// URL could not be parsed
+    MalformedUrl,
+    /// Host name did not resolve
+    Resolve,
+    /// TCP connection failed
+    Connect,
+    /// Operation timed out
+    Timeout,
+    /// Too many redirects
+    TooManyRedirects,
+    /// Server sent an invalid response or the connection broke
+    Protocol,
+    /// The body could not be written to its destination
+    Write,
+}
+
+/**
+ * HTTP client error
+ */
+#[derive(Debug)]
+pub struct HttpError {
+    /// Failure kind
+    pub kind: HttpErrorKind,
+    /// Description
+    pub message: String,
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/**
+ * Builds an HttpError wrapped in anyhow
+ *
+ * @param kind - Failure kind
+ * @param message - Description
+ * @return anyhow::Error - Error
+ */
+fn http_error(kind: HttpErrorKind, message: String) -> anyhow::Error {
+    anyhow::Error::new(HttpError { kind, message })
+}
+
+/**
+ * Parsed http:// URL
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    /// Host name or address (IPv6 without brackets)
+    pub host: String,
+    /// TCP port
+    pub port: u16,
+    /// Path with query, always starting with `/`
+    pub path: String,
+}
+
+impl Url {
+    /**
+     * Parses a URL; a missing scheme means http
+     *
+     * @param text - URL text
+     * @return Result<Url> - Parsed URL or HttpError
+     */
+    pub fn parse(text: &str) -> Result<Self> {
+        let rest = match text.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => rest,
+            Some((scheme, _)) => {
+                return Err(http_error(
+                    HttpErrorKind::UnsupportedProtocol,
+                    format!("Protocol \"{}\" not supported", scheme),
+                ));
+            }
+            None => text,
+        };
+
+        let (authority, path) = match rest.find(['/', '?', '#']) {
+            Some(position) => (&rest[..position], &rest[position..]),
+            None => (rest, "/"),
+        };
+        let path = path.split('#').next().unwrap_or("/");
+        let path = if path.starts_with('/') { path.to_string() } else { format!("/{}", path) };
+
+        // Credentials in the authority are not supported; drop them
+        let authority = authority.rsplit('@').next().unwrap_or(authority);
+        let malformed = || http_error(HttpErrorKind::MalformedUrl, format!("URL using bad/illegal format or missing URL: {}", text));
+
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (host, after) = bracketed.split_once(']').ok_or_else(malformed)?;
+            (host.to_string(), after.strip_prefix(':'))
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host.to_string(), Some(port)),
+                None => (authority.to_string(), None),
+            }
+        };
+
+        if host.is_empty() {
+            return Err(malformed());
+        }
+        let port = match port {
+            Some(port) if !port.is_empty() => port.parse().map_err(|_| malformed())?,
+            _ => 80,
+        };
+
+        Ok(Self { host, port, path })
+    }
+
+    /**
+     * Resolves a redirect target against this URL
+     *
+     * @param location - Location header value
+     * @return Result<Url> - Target URL
+     */
+    pub fn join(&self, location: &str) -> Result<Url> {
+        if location.contains("://") {
+            return Url::parse(location);
+        }
+        if let Some(rest) = location.strip_prefix("//") {
+            return Url::parse(&format!("http://{}", rest));
+        }
+
+        let path = if location.starts_with('/') {
+            location.to_string()
+        } else if location.starts_with('?') {
+            let base = self.path.split('?').next().unwrap_or("/");
+            format!("{}{}", base, location)
+        } else {
+            let base = self.path.split('?').next().unwrap_or("/");
+            let directory = &base[..base.rfind('/').map_or(0, |i| i + 1)];
+            format!("{}{}", directory, location)
+        };
+
+        Ok(Url {
+            host: self.host.clone(),
+            port: self.port,
+            path: normalize_path(&path),
+        })
+    }
+
+    /**
+     * Formats the Host header value
+     *
+     * @return String - Host with the port if it is not 80
+     */
+    pub fn host_header(&self) -> String {
+        let host = if self.host.contains(':') { format!("[{}]", self.host) } else { self.host.clone() };
+        if self.port == 80 {
+            host
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+
+    /**
+     * Gets the last path segment, for naming downloads
+     *
+     * @return Option<String> - File name, None if the path ends in `/`
+     */
+    pub fn file_name(&self) -> Option<String> {
+        let path = self.path.split('?').next().unwrap_or("");
+        path.rsplit('/').next().filter(|name| !name.is_empty()).map(str::to_string)
+    }
+}
+
+impl std::fmt::Display for Url {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "http://{}{}", self.host_header(), self.path)
+    }
+}
+
+/**
+ * Removes `.` and `..` segments from a path
+ *
+ * @param path - Absolute path, possibly with a query
+ * @return String - Normalized path
+ */
+fn normalize_path(path: &str) -> String {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    if path.ends_with("/.") || path.ends_with("/..") {
+        segments.push("");
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if let Some(query) = query {
+        normalized.push('?');
+        normalized.push_str(query);
+    }
+    normalized
+}
+
+/**
+ * HTTP request
+ */
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    /// Method such as GET or POST
+    pub method: String,
+    /// Target URL
+    pub url: Url,
+    /// Extra headers; these replace defaults of the same name
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    pub body: Option<Vec<u8>>,
+}
+
+/**
+ * HTTP response
+ */
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// Protocol version from the status line, e.g. "HTTP/1.1"
+    pub version: String,
+    /// Status code
+    pub status: u16,
+    /// Reason phrase
+    pub reason: String,
+    /// Headers in received order
+    pub headers: Vec<(String, String)>,
+    /// Decoded body; empty when it was streamed to a sink
+    pub body: Vec<u8>,
+    /// Decoded body length in bytes
+    pub length: u64,
+    /// URL this response came from
+    pub url: Url,
+}
+
+impl HttpResponse {
+    /**
+     * Gets the first header with a name, ignoring case
+     *
+     * @param name - Header name
+     * @return Option<&str> - Header value
+     */
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /**
+     * Formats the status line and headers as received
+     *
+     * @return String - Header block ending with a blank line
+     */
+    pub fn header_block(&self) -> String {
+        let mut text = format!("{} {} {}\r\n", self.version, self.status, self.reason);
+        for (name, value) in &self.headers {
+            text.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        text.push_str("\r\n");
+        text
+    }
+
+    /**
+     * Checks whether the status is a redirect with a Location
+     *
+     * @return bool - True for 301, 302, 303, 307 and 308 with Location
+     */
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status, 301 | 302 | 303 | 307 | 308) && self.header("Location").is_some()
+    }
+}
+
+/// Status line parts and headers of a response
+type ResponseHead = (String, u16, String, Vec<(String, String)>);
+
+/// Destination a final response body is streamed to
+pub type BodySink = Box<dyn Write>;
+
+/**
+ * HTTP/1.1 client settings
+ */
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    /// Connect and read timeout
+    pub timeout: Duration,
+    /// Whether to follow redirects
+    pub follow_redirects: bool,
+    /// Maximum redirects to follow
+    pub max_redirects: usize,
+    /// User-Agent header value
+    pub user_agent: String,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            follow_redirects: false,
+            max_redirects: 50,
+            user_agent: format!("sare/{}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+impl HttpClient {
+    /**
+     * リクエストを送信し、必要に応じてリダイレクトを追跡する関数です
+     *
+     * follow_redirectsが有効な場合、Locationヘッダーに従って
+     * 最大max_redirects回までリクエストを繰り返します。
+     * 301、302、303ではPOSTをGETに変更して本文を削除し、
+     * 307と308ではメソッドと本文を維持します。
+     *
+     * 途中の応答も含めてすべての応答を順番に返すため、
+     * 呼び出し側はヘッダーの表示にそれらを使用できます。
+     *
+     * @param request - 送信するリクエスト
+     * @param on_progress - 受信バイト数と全体サイズを受け取るコールバック
+     * @return Result<Vec<HttpResponse>> - 応答のリスト（最後が最終応答）またはエラー
+     */
+    pub fn send(&self, request: &HttpRequest, on_progress: impl FnMut(u64, Option<u64>)) -> Result<Vec<HttpResponse>> {
+        self.send_to(request, |_, _| Ok(None), on_progress)
+    }
+
+    /**
+     * リクエストを送信し、最終応答の本文を書き込み先に流す関数です
+     *
+     * sendと同じくリダイレクトを追跡しますが、追跡しない応答の
+     * ヘッダーを受信した時点で、それまでの応答と共にopen_sinkを
+     * 呼び出します。書き込み先が
+     * 返された場合、本文はメモリに保持せず受信しながら書き込みます
+     * （応答のbodyは空になり、lengthに長さが入ります）。Noneの
+     * 場合はsendと同じくbodyに読み込みます。
+     *
+     * 書き込み先を開けない場合や書き込みに失敗した場合は、
+     * HttpErrorKind::Writeのエラーを返します。
+     *
+     * @param request - 送信するリクエスト
+     * @param open_sink - それまでの応答と最終応答のヘッダーを受け取り、本文の書き込み先を返すコールバック
+     * @param on_progress - 受信バイト数と全体サイズを受け取るコールバック
+     * @return Result<Vec<HttpResponse>> - 応答のリスト（最後が最終応答）またはエラー
+     */
+    pub fn send_to(
+        &self,
+        request: &HttpRequest,
+        mut open_sink: impl FnMut(&[HttpResponse], &HttpResponse) -> Result<Option<BodySink>>,
+        mut on_progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<Vec<HttpResponse>> {
+        let mut request = request.clone();
+        let mut responses = Vec::new();
+
+        loop {
+            let response = self.send_once(&request, &responses, &mut open_sink, &mut on_progress)?;
+            let location = response.header("Location").map(str::to_string);
+            let redirect = self.follow_redirects && response.is_redirect();
+            let status = response.status;
+            responses.push(response);
+
+            let Some(location) = location.filter(|_| redirect) else {
+                return Ok(responses);
+            };
+            if responses.len() > self.max_redirects {
+                return Err(http_error(
+                    HttpErrorKind::TooManyRedirects,
+                    format!("Maximum ({}) redirects followed", self.max_redirects),
+                ));
+            }
+
+            request.url = request.url.join(&location)?;
+            if (301..=303).contains(&status) && request.method != "GET" && request.method != "HEAD" {
+                request.method = "GET".to_string();
+                request.body = None;
+                request.headers.retain(|(name, _)| {
+                    !name.eq_ignore_ascii_case("Content-Type") && !name.eq_ignore_ascii_case("Content-Length")
+                });
+            }
+        }
+    }
+
+    /**
+     * Sends one request without following redirects
+     *
+     * @param request - Request
+     * @param earlier - Responses already received for this request
+     * @param open_sink - Picks the body destination of a response that is not followed
+     * @param on_progress - Progress callback
+     * @return Result<HttpResponse> - Response
+     */
+    fn send_once(
+        &self,
+        request: &HttpRequest,
+        earlier: &[HttpResponse],
+        open_sink: &mut impl FnMut(&[HttpResponse], &HttpResponse) -> Result<Option<BodySink>>,
+        on_progress: &mut impl FnMut(u64, Option<u64>),
+    ) -> Result<HttpResponse> {
+        let stream = self.connect(&request.url)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        let mut writer = stream.try_clone()?;
+        writer.write_all(&self.encode_request(request))
+            .map_err(|e| self.io_error("Failed sending HTTP request", e))?;
+
+        let mut reader = BufReader::new(stream);
+        let (version, status, reason, headers) = loop {
+            let head = self.read_head(&mut reader)?;
+            // Interim 1xx responses (except 101) precede the real one
+            if head.1 >= 100 && head.1 < 200 && head.1 != 101 {
+                continue;
+            }
+            break head;
+        };
+
+        let mut response = HttpResponse {
+            version,
+            status,
+            reason,
+            headers,
+            body: Vec::new(),
+            length: 0,
+            url: request.url.clone(),
+        };
+
+        let followed = self.follow_redirects && response.is_redirect();
+        let mut sink = if followed {
+            None
+        } else {
+            open_sink(earlier, &response).map_err(|e| http_error(HttpErrorKind::Write, e.to_string()))?
+        };
+
+        if request.method != "HEAD" && !matches!(status, 204 | 304) {
+            let mut body = Vec::new();
+            let destination: &mut dyn Write = match sink.as_mut() {
+                Some(sink) => sink.as_mut(),
+                None => &mut body,
+            };
+            response.length = self.read_body(&mut reader, &response, destination, on_progress)?;
+            response.body = body;
+        }
+        if let Some(sink) = sink.as_mut() {
+            sink.flush().map_err(write_error)?;
+        }
+        Ok(response)
+    }
+
+    /**
+     * Opens a TCP connection to the URL's host
+     *
+     * @param url - Target URL
+     * @return Result<TcpStream> - Connected stream
+     */
+    fn connect(&self, url: &Url) -> Result<TcpStream> {
+        let addresses: Vec<SocketAddr> = (url.host.as_str(), url.port)
+            .to_socket_addrs()
+            .map_err(|_| http_error(HttpErrorKind::Resolve, format!("Could not resolve host: {}", url.host)))?
+            .collect();
+
+        let mut last_error = None;
+        for address in &addresses {
+            match TcpStream::connect_timeout(address, self.timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        let (kind, reason) = match last_error {
+            Some(e) if e.kind() == std::io::ErrorKind::TimedOut => (HttpErrorKind::Timeout, "Connection timed out".to_string()),
+            Some(e) => (HttpErrorKind::Connect, describe_error(&e)),
+            None => (HttpErrorKind::Resolve, "no addresses".to_string()),
+        };
+        Err(http_error(kind, format!("Failed to connect to {} port {}: {}", url.host, url.port, reason)))
+    }
+
+    /**
+     * Serializes the request line, headers and body
+     *
+     * @param request - Request
+     * @return Vec<u8> - Bytes to send
+     */
+    fn encode_request(&self, request: &HttpRequest) -> Vec<u8> {
+        let mut headers: Vec<(String, String)> = vec![
+            ("Host".to_string(), request.url.host_header()),
+            ("User-Agent".to_string(), self.user_agent.clone()),
+            ("Accept".to_string(), "*/*".to_string()),
+        ];
+        for (name, _) in &request.headers {
+            headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        }
+        headers.extend(request.headers.iter().filter(|(_, value)| !value.is_empty()).cloned());
+
+        if let Some(body) = &request.body {
+            if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Content-Length")) {
+                headers.push(("Content-Length".to_string(), body.len().to_string()));
+            }
+        }
+        headers.push(("Connection".to_string(), "close".to_string()));
+
+        let mut bytes = format!("{} {} HTTP/1.1\r\n", request.method, request.url.path).into_bytes();
+        for (name, value) in headers {
+            bytes.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+        }
+        bytes.extend_from_slice(b"\r\n");
+        if let Some(body) = &request.body {
+            bytes.extend_from_slice(body);
+        }
+        bytes
+    }
+
+    /**
+     * Reads the status line and headers
+     *
+     * @param reader - Connection reader
+     * @return Result<ResponseHead> - Version, status, reason and headers
+     */
+    fn read_head(&self, reader: &mut BufReader<TcpStream>) -> Result<ResponseHead> {
+        let status_line = self.read_line(reader)?;
+        if status_line.is_empty() {
+            return Err(http_error(HttpErrorKind::Protocol, "Empty reply from server".to_string()));
+        }
+
+        let mut parts = status_line.splitn(3, ' ');
+        let version = parts.next().unwrap_or_default().to_string();
+        let status = parts.next().and_then(|code| code.parse().ok());
+        let (Some(status), true) = (status, version.starts_with("HTTP/")) else {
+            return Err(http_error(HttpErrorKind::Protocol, format!("Invalid status line: {}", status_line)));
+        };
+        let reason = parts.next().unwrap_or_default().to_string();
+
+        let mut headers = Vec::new();
+        loop {
+            let line = self.read_line(reader)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        Ok((version, status, reason, headers))
+    }
+
+    /**
+     * レスポンスの本文を読み込む関数です
+     *
+     * Transfer-Encodingがchunkedの場合はチャンクを順に読み込んで
+     * 書き込み、トレーラーを読み飛ばします。Content-Lengthがある
+     * 場合はその長さだけ読み込み、どちらもない場合は接続が
+     * 閉じられるまで読み込みます。
+     *
+     * 本文は受信したそばから書き込み先に書き込むため、大きな
+     * ファイルでもメモリに保持しません。読み込みのたびに
+     * 進捗コールバックを呼び出します。
+     *
+     * @param reader - 接続のリーダー
+     * @param response - ヘッダーを含む応答
+     * @param sink - 本文の書き込み先
+     * @param on_progress - 進捗コールバック
+     * @return Result<u64> - 本文の長さまたはエラー
+     */
+    fn read_body(
+        &self,
+        reader: &mut BufReader<TcpStream>,
+        response: &HttpResponse,
+        sink: &mut dyn Write,
+        on_progress: &mut impl FnMut(u64, Option<u64>),
+    ) -> Result<u64> {
+        let mut received: u64 = 0;
+        let mut buffer = [0u8; 16384];
+        let chunked = response
+            .header("Transfer-Encoding")
+            .is_some_and(|encoding| encoding.to_ascii_lowercase().contains("chunked"));
+
+        if chunked {
+            loop {
+                let size_line = self.read_line(reader)?;
+                let size_text = size_line.split(';').next().unwrap_or("").trim();
+                let size = u64::from_str_radix(size_text, 16)
+                    .map_err(|_| http_error(HttpErrorKind::Protocol, format!("Invalid chunk size '{}'", size_text)))?;
+                if size == 0 {
+                    while !self.read_line(reader)?.is_empty() {}
+                    break;
+                }
+
+                let mut remaining = size;
+                while remaining > 0 {
+                    let want = remaining.min(buffer.len() as u64) as usize;
+                    let read = reader.read(&mut buffer[..want])
+                        .map_err(|e| self.io_error("Transfer closed in the middle of a chunk", e))?;
+                    if read == 0 {
+                        return Err(http_error(HttpErrorKind::Protocol, "Transfer closed in the middle of a chunk".to_string()));
+                    }
+                    sink.write_all(&buffer[..read]).map_err(write_error)?;
+                    remaining -= read as u64;
+                    received += read as u64;
+                    on_progress(received, None);
+                }
+                self.read_line(reader)?;
+            }
+            return Ok(received);
+        }
+
+        let length = response.header("Content-Length").and_then(|length| length.trim().parse::<u64>().ok());
+        loop {
+            let want = match length {
+                Some(length) if received >= length => break,
+                Some(length) => (length - received).min(buffer.len() as u64) as usize,
+                None => buffer.len(),
+            };
+            let read = reader.read(&mut buffer[..want]).map_err(|e| self.io_error("Failure when receiving data", e))?;
+            if read == 0 {
+                if let Some(length) = length {
+                    return Err(http_error(
+                        HttpErrorKind::Protocol,
+                        format!("Transfer closed with {} bytes remaining to read", length - received),
+                    ));
+                }
+                break;
+            }
+            sink.write_all(&buffer[..read]).map_err(write_error)?;
+            received += read as u64;
+            on_progress(received, length);
+        }
+
+        Ok(received)
+    }
+
+    /**
+     * Reads one CRLF- or LF-terminated line
+     *
+     * @param reader - Connection reader
+     * @return Result<String> - Line without its terminator, empty at EOF
+     */
+    fn read_line(&self, reader: &mut BufReader<TcpStream>) -> Result<String> {
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).map_err(|e| self.io_error("Failure when receiving data", e))?;
+        while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+            line.pop();
+        }
+        Ok(String::from_utf8_lossy(&line).to_string())
+    }
+
+    /**
+     * Converts an I/O error into an HttpError
+     *
+     * @param context - What was being done
+     * @param error - Underlying error
+     * @return anyhow::Error - Timeout or protocol error
+     */
+    fn io_error(&self, context: &str, error: std::io::Error) -> anyhow::Error {
+        match error.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => http_error(
+                HttpErrorKind::Timeout,
+                format!("Operation timed out after {} milliseconds", self.timeout.as_millis()),
+            ),
+            _ => http_error(HttpErrorKind::Protocol, format!("{}: {}", context, describe_error(&error))),
+        }
+    }
+}
+
+/**
+ * Converts a failed write to the body destination into an HttpError
+ *
+ * @param error - Underlying error
+ * @return anyhow::Error - Write error
+ */
+fn write_error(error: std::io::Error) -> anyhow::Error {
+    http_error(
+        HttpErrorKind::Write,
+        format!("Failure writing output to destination: {}", describe_error(&error)),
+    )
+}
+
+/**
+ * Gets the HttpErrorKind of an error, if it is an HttpError
+ *
+ * @param error - Error from the client
+ * @return Option<HttpErrorKind> - Kind
+ */
+pub fn error_kind(error: &anyhow::Error) -> Option<HttpErrorKind> {
+    error.downcast_ref::<HttpError>().map(|e| e.kind)
+}
+
+/**
+ * Renders a text progress bar
+ *
+ * Matches the terminal's progress bar widget: filled and empty
+ * cells followed by the percentage and optional text.
+ *
+ * @param progress - Fraction complete, 0.0 to 1.0
+ * @param width - Bar width in cells
+ * @param text - Text after the percentage
+ * @return String - Rendered bar
+ */
+pub fn render_progress_bar(progress: f32, width: usize, text: &str) -> String {
+    let progress = progress.clamp(0.0, 1.0);
+    let filled = (progress * width as f32) as usize;
+
+    let mut bar: String = (0..width).map(|i| if i < filled { '█' } else { '░' }).collect();
+    bar.push_str(&format!(" {}%", (progress * 100.0) as u32));
+    if !text.is_empty() {
+        bar.push(' ');
+        bar.push_str(text);
+    }
+    bar
+}
+
+/**
+ * Live download progress on stderr
+ *
+ * Redraws a progress bar in place while a body is received, at
+ * most every 100ms. Nothing is drawn unless stderr is a terminal.
+ */
+pub struct ProgressMeter {
+    /// Whether to draw at all
+    enabled: bool,
+    /// When the download started
+    started: Instant,
+    /// When the bar was last drawn
+    last_draw: Option<Instant>,
+}
+
+impl ProgressMeter {
+    /**
+     * Creates a meter
+     *
+     * @param wanted - Whether the command wants a meter (e.g. not silent)
+     * @return ProgressMeter - Meter that draws only if wanted and stderr is a terminal
+     */
+    pub fn new(wanted: bool) -> Self {
+        Self {
+            enabled: wanted && std::io::stderr().is_terminal(),
+            started: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    /**
+     * Records received bytes, redrawing the bar if due
+     *
+     * @param received - Bytes received so far
+     * @param total - Expected body size, if known
+     */
+    pub fn update(&mut self, received: u64, total: Option<u64>) {
+        let done = total.is_some_and(|total| received >= total);
+        if !self.enabled || (!done && self.last_draw.is_some_and(|last| last.elapsed() < Duration::from_millis(100))) {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { received as f64 / elapsed } else { 0.0 };
+        let line = match total {
+            Some(total) if total > 0 => render_progress_bar(
+                received as f32 / total as f32,
+                40,
+                &format!("{}/{}  {}/s", format_size(received), format_size(total), format_size(rate as u64)),
+            ),
+            _ => format!("{}  {}/s", format_size(received), format_size(rate as u64)),
+        };
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r{}\x1b[K", line);
+        let _ = stderr.flush();
+    }
+
+    /**
+     * Ends the bar's line once the download is over
+     */
+    pub fn finish(&mut self) {
+        if self.enabled && self.last_draw.is_some() {
+            eprintln!();
+        }
+        self.last_draw = None;
+    }
+}
+
+/**
+ * Formats a byte count with a binary unit
+ *
+ * @param bytes - Byte count
+ * @return String - Size such as "1.2K" or "512"
+ */
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = "";
+    for candidate in UNITS {
+        size /= 1024.0;
+        unit = candidate;
+        if size < 1024.0 {
+            break;
+        }
+    }
+    format!("{:.1}{}", size, unit)
+}
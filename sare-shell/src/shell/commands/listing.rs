@@ -0,0 +1,915 @@
+/*!
+ * @file listing.rs
+ * @brief Directory listing helpers for the ls builtin
+ *
+ * This module holds the formatting pieces behind `ls`: display
+ * widths, multi-column layout, LS_COLORS painting, permission
+ * strings, owner and group names and human-readable sizes.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file listing.rs
+ * @description Column layout by terminal width, LS_COLORS parsing,
+ * mode strings, uid/gid name lookup and GNU-style size formatting.
+ */
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use unicode_width::UnicodeWidthStr;
+
+use crate::shell::commands::fileops::describe_error;
+use crate::shell::commands::records::Table;
+
+/// Colors used when LS_COLORS does not override them (GNU dircolors defaults)
+const DEFAULT_LS_COLORS: &str = "rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:so=01;35:do=01;35:\
+bd=40;33;01:cd=40;33;01:or=40;31;01:mi=00:su=37;41:sg=30;43:ca=00:tw=30;42:ow=34;42:st=37;44:ex=01;32";
+
+/**
+ * Gets the number of terminal cells a string occupies
+ *
+ * @param text - Text without escape sequences
+ * @return usize - Display width; wide CJK and emoji count as two
+ */
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/**
+ * Gets the terminal width used for column layout
+ *
+ * A positive COLUMNS value wins, then the size of the terminal on
+ * stdout; 80 is used when neither is available.
+ *
+ * @param columns_variable - Value of COLUMNS, if set
+ * @return usize - Terminal width in cells
+ */
+pub fn terminal_columns(columns_variable: Option<&str>) -> usize {
+    if let Some(columns) = columns_variable.and_then(|value| value.trim().parse::<usize>().ok()) {
+        if columns > 0 {
+            return columns;
+        }
+    }
+
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result == 0 && size.ws_col > 0 {
+        size.ws_col as usize
+    } else {
+        80
+    }
+}
+
+/**
+ * 名前を複数列に並べる関数です
+ *
+ * GNU lsと同じく縦方向に並べ（上から下、次に左から右）、
+ * 端末幅に収まる最大の列数を選びます。各列の幅はその列で
+ * 最も長い名前に2つの空白を加えたもので、最後の列は
+ * 空白で埋めません。
+ *
+ * 名前にはエスケープシーケンスが含まれる場合があるため、
+ * 表示幅は呼び出し側が別に渡します。
+ *
+ * @param cells - 表示する文字列とその表示幅
+ * @param line_width - 端末の幅
+ * @return String - 改行で終わる各行
+ */
+pub fn layout_columns(cells: &[(String, usize)], line_width: usize) -> String {
+    if cells.is_empty() {
+        return String::new();
+    }
+
+    let mut chosen = (1, vec![0]);
+    for columns in (1..=cells.len()).rev() {
+        let rows = cells.len().div_ceil(columns);
+        if columns > 1 && (columns - 1) * rows >= cells.len() {
+            continue;
+        }
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| {
+                cells
+                    .iter()
+                    .skip(column * rows)
+                    .take(rows)
+                    .map(|(_, width)| width + 2)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let total = widths.iter().sum::<usize>() - 2;
+        if columns == 1 || total <= line_width {
+            chosen = (columns, widths);
+            break;
+        }
+    }
+
+    let (columns, widths) = chosen;
+    let rows = cells.len().div_ceil(columns);
+    let mut output = String::new();
+    for row in 0..rows {
+        for (column, column_width) in widths.iter().enumerate() {
+            let Some((text, width)) = cells.get(column * rows + row) else {
+                break;
+            };
+            output.push_str(text);
+            let is_last = column + 1 == columns || cells.get((column + 1) * rows + row).is_none();
+            if !is_last {
+                output.push_str(&" ".repeat(column_width - width));
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/**
+ * LS_COLORS color database
+ */
+#[derive(Debug, Clone)]
+pub struct LsColors {
+    /// SGR codes keyed by file type indicator (di, ln, ex, ...)
+    types: HashMap<String, String>,
+    /// SGR codes for `*.ext` style name suffixes
+    suffixes: Vec<(String, String)>,
+}
+
+impl LsColors {
+    /**
+     * Builds the database from an LS_COLORS value
+     *
+     * Entries the value does not mention keep their GNU defaults.
+     * Malformed entries are ignored.
+     *
+     * @param spec - LS_COLORS value, if set
+     * @return LsColors - Color database
+     */
+    pub fn from_spec(spec: Option<&str>) -> Self {
+        let mut colors = LsColors {
+            types: HashMap::new(),
+            suffixes: Vec::new(),
+        };
+        colors.apply(DEFAULT_LS_COLORS);
+        if let Some(spec) = spec {
+            colors.apply(spec);
+        }
+        colors
+    }
+
+    /**
+     * Merges `key=value` entries into the database
+     *
+     * @param spec - Colon-separated entries
+     */
+    fn apply(&mut self, spec: &str) {
+        for entry in spec.split(':') {
+            let Some((key, value)) = entry.split_once('=') else {
+                continue;
+            };
+            match key.strip_prefix('*') {
+                Some(suffix) => {
+                    self.suffixes.retain(|(existing, _)| existing != suffix);
+                    self.suffixes.push((suffix.to_string(), value.to_string()));
+                }
+                None => {
+                    self.types.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+
+    /**
+     * Wraps a name in the color for its file type
+     *
+     * @param name - Text to print
+     * @param file_name - Entry name used for suffix matching
+     * @param metadata - lstat metadata of the entry
+     * @param target - Metadata of a symlink's target; None if it is dangling
+     * @return String - Colored name, or the name unchanged when uncolored
+     */
+    pub fn paint(&self, name: &str, file_name: &str, metadata: &Metadata, target: Option<&Metadata>) -> String {
+        let mut key = color_key(metadata);
+        if key == "ln" {
+            key = match target {
+                None => "or",
+                Some(target) if self.types.get("ln").map(String::as_str) == Some("target") => color_key(target),
+                Some(_) => "ln",
+            };
+        }
+
+        let mut code = self.types.get(key).map(String::as_str);
+        if key == "fi" {
+            let lower = file_name.to_lowercase();
+            if let Some((_, suffix_code)) = self
+                .suffixes
+                .iter()
+                .rev()
+                .find(|(suffix, _)| lower.ends_with(&suffix.to_lowercase()))
+            {
+                code = Some(suffix_code);
+            }
+        }
+
+        match code {
+            Some(code) if !code.is_empty() => format!("\x1b[{}m{}\x1b[0m", code, name),
+            _ => name.to_string(),
+        }
+    }
+}
+
+/**
+ * Gets the LS_COLORS indicator key for a file
+ *
+ * @param metadata - lstat metadata
+ * @return &str - Indicator such as "di", "ln", "ex" or "fi"
+ */
+fn color_key(metadata: &Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    let mode = metadata.mode();
+    if file_type.is_symlink() {
+        "ln"
+    } else if file_type.is_dir() {
+        match (mode & 0o1000 != 0, mode & 0o002 != 0) {
+            (true, true) => "tw",
+            (false, true) => "ow",
+            (true, false) => "st",
+            (false, false) => "di",
+        }
+    } else if file_type.is_fifo() {
+        "pi"
+    } else if file_type.is_socket() {
+        "so"
+    } else if file_type.is_block_device() {
+        "bd"
+    } else if file_type.is_char_device() {
+        "cd"
+    } else if mode & 0o4000 != 0 {
+        "su"
+    } else if mode & 0o2000 != 0 {
+        "sg"
+    } else if mode & 0o111 != 0 {
+        "ex"
+    } else {
+        "fi"
+    }
+}
+
+/**
+ * Gets the -F indicator character for a file
+ *
+ * @param metadata - lstat metadata
+ * @return Option<char> - `/`, `@`, `|`, `=` or `*`, if any
+ */
+pub fn type_indicator(metadata: &Metadata) -> Option<char> {
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        Some('/')
+    } else if file_type.is_symlink() {
+        Some('@')
+    } else if file_type.is_fifo() {
+        Some('|')
+    } else if file_type.is_socket() {
+        Some('=')
+    } else if file_type.is_file() && metadata.mode() & 0o111 != 0 {
+        Some('*')
+    } else {
+        None
+    }
+}
+
+/**
+ * Names a file's type for ls records
+ *
+ * @param metadata - lstat metadata
+ * @return &str - "file", "dir", "symlink", "fifo", "socket", "block" or "char"
+ */
+pub fn file_type_name(metadata: &Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        "dir"
+    } else if file_type.is_symlink() {
+        "symlink"
+    } else if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() {
+        "block"
+    } else if file_type.is_char_device() {
+        "char"
+    } else {
+        "file"
+    }
+}
+
+/**
+ * Formats a mode as the ten-character `ls -l` string
+ *
+ * @param metadata - lstat metadata
+ * @return String - Type character followed by rwx triplets, e.g. "drwxr-xr-x"
+ */
+pub fn mode_string(metadata: &Metadata) -> String {
+    let file_type = metadata.file_type();
+    let mode = metadata.mode();
+    let mut text = String::with_capacity(10);
+
+    text.push(if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_char_device() {
+        'c'
+    } else {
+        '-'
+    });
+
+    let special = [(0o4000, 's'), (0o2000, 's'), (0o1000, 't')];
+    for (triplet, (special_bit, special_char)) in special.iter().enumerate() {
+        let shift = 6 - triplet * 3;
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (mode & special_bit != 0, bits & 0o1 != 0) {
+            (true, true) => *special_char,
+            (true, false) => special_char.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/**
+ * Caching uid and gid to name resolver
+ */
+#[derive(Debug, Default)]
+pub struct OwnerNames {
+    /// Resolved user names
+    users: HashMap<u32, String>,
+    /// Resolved group names
+    groups: HashMap<u32, String>,
+}
+
+impl OwnerNames {
+    /**
+     * Gets the user name for a uid
+     *
+     * @param uid - User id
+     * @return String - Name from the password database, or the number
+     */
+    pub fn user(&mut self, uid: u32) -> String {
+        self.users
+            .entry(uid)
+            .or_insert_with(|| match nix::unistd::User::from_uid(nix::unistd::Uid::from_raw(uid)) {
+                Ok(Some(user)) => user.name,
+                _ => uid.to_string(),
+            })
+            .clone()
+    }
+
+    /**
+     * Gets the group name for a gid
+     *
+     * @param gid - Group id
+     * @return String - Name from the group database, or the number
+     */
+    pub fn group(&mut self, gid: u32) -> String {
+        self.groups
+            .entry(gid)
+            .or_insert_with(|| match nix::unistd::Group::from_gid(nix::unistd::Gid::from_raw(gid)) {
+                Ok(Some(group)) => group.name,
+                _ => gid.to_string(),
+            })
+            .clone()
+    }
+}
+
+/**
+ * Formats a size the way `ls -h` does
+ *
+ * Values are rounded up; below ten units one decimal is kept.
+ *
+ * @param bytes - Size in bytes
+ * @return String - e.g. "512", "4.0K", "12K", "1.5M"
+ */
+pub fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    let units = ['K', 'M', 'G', 'T', 'P', 'E'];
+    while value >= 1024.0 && unit < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    let tenths = (value * 10.0).ceil() / 10.0;
+    if tenths < 10.0 {
+        format!("{:.1}{}", tenths, units[unit - 1])
+    } else {
+        let whole = value.ceil();
+        if whole >= 1024.0 && unit < units.len() {
+            format!("1.0{}", units[unit])
+        } else {
+            format!("{}{}", whole, units[unit - 1])
+        }
+    }
+}
+
+/**
+ * Which dot files a listing includes
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HiddenEntries {
+    /// Skip names starting with a dot
+    Skip,
+    /// Show dot files but not `.` and `..` (-A)
+    AlmostAll,
+    /// Show dot files including `.` and `..` (-a)
+    All,
+}
+
+/**
+ * Order of entries within a listing
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// By name
+    Name,
+    /// Newest modification time first (-t)
+    Time,
+    /// Largest first (-S)
+    Size,
+}
+
+/**
+ * Options controlling an ls listing
+ */
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    /// Dot file handling
+    pub hidden: HiddenEntries,
+    /// Long format (-l)
+    pub long_format: bool,
+    /// Human-readable sizes (-h)
+    pub human_readable: bool,
+    /// Recurse into subdirectories (-R)
+    pub recursive: bool,
+    /// Reverse the sort order (-r)
+    pub reverse: bool,
+    /// List directories themselves, not their contents (-d)
+    pub directory: bool,
+    /// Append type indicators (-F)
+    pub classify: bool,
+    /// Sort order
+    pub sort: SortKey,
+    /// Arrange names in columns; otherwise one per line
+    pub columns: bool,
+    /// Line width for column layout
+    pub line_width: usize,
+    /// Colors, when coloring is enabled
+    pub colors: Option<LsColors>,
+    /// Collect records of the listed files for structured pipelines
+    pub records: bool,
+}
+
+/**
+ * Result of a listing
+ */
+#[derive(Debug, Clone)]
+pub struct ListOutput {
+    /// Listing text, with error messages first
+    pub output: String,
+    /// 0 on success, 1 for unreadable subdirectories, 2 for missing operands
+    pub exit_code: i32,
+    /// One record per listed file, when requested
+    pub records: Option<Table>,
+}
+
+/**
+ * One file to print
+ */
+struct ListEntry {
+    /// Name as printed
+    name: String,
+    /// Path used to read the file
+    path: PathBuf,
+    /// lstat metadata
+    metadata: Metadata,
+    /// stat metadata of a symlink's target; None when dangling or not a link
+    target: Option<Metadata>,
+}
+
+impl ListEntry {
+    /**
+     * Reads an entry's metadata without following symlinks
+     *
+     * @param name - Name as printed
+     * @param path - Path to the file
+     * @return std::io::Result<ListEntry> - Entry or the lstat error
+     */
+    fn read(name: String, path: PathBuf) -> std::io::Result<Self> {
+        let metadata = std::fs::symlink_metadata(&path)?;
+        let target = if metadata.file_type().is_symlink() {
+            std::fs::metadata(&path).ok()
+        } else {
+            None
+        };
+        Ok(ListEntry {
+            name,
+            path,
+            metadata,
+            target,
+        })
+    }
+}
+
+/**
+ * Produces an ls listing for the given operands
+ *
+ * @param operands - Pairs of printed name and path; empty means "."
+ * @param options - Listing options
+ * @return ListOutput - Listing text and exit code
+ */
+pub fn list(operands: &[(String, PathBuf)], options: &ListOptions) -> ListOutput {
+    let mut lister = Lister {
+        options,
+        owners: OwnerNames::default(),
+        output: String::new(),
+        errors: String::new(),
+        exit_code: 0,
+        records: options
+            .records
+            .then(|| Table::new(&["name", "type", "size", "mode", "links", "user", "group", "modified", "target"])),
+    };
+    lister.run(operands);
+
+    let mut output = lister.errors;
+    output.push_str(&lister.output);
+    ListOutput {
+        output,
+        exit_code: lister.exit_code,
+        records: lister.records,
+    }
+}
+
+/**
+ * Listing state while walking operands
+ */
+struct Lister<'a> {
+    /// Listing options
+    options: &'a ListOptions,
+    /// uid/gid name cache
+    owners: OwnerNames,
+    /// Listing text
+    output: String,
+    /// Error messages
+    errors: String,
+    /// Exit code so far
+    exit_code: i32,
+    /// Records of the listed files, when requested
+    records: Option<Table>,
+}
+
+impl Lister<'_> {
+    /**
+     * オペランドを一覧表示する関数です
+     *
+     * ファイルのオペランドを先にまとめて表示し、その後で
+     * ディレクトリの内容を表示します。複数のオペランドがある
+     * 場合や-Rの場合は各ディレクトリの前に「名前:」の見出しを
+     * 付けます。
+     *
+     * -dが指定されていない場合、ディレクトリへのシンボリック
+     * リンクは-lのときを除いて辿ります。存在しないオペランドは
+     * エラーを記録して終了コード2にします。
+     *
+     * @param operands - 表示名とパスの組
+     */
+    fn run(&mut self, operands: &[(String, PathBuf)]) {
+        let default_operand = [(".".to_string(), PathBuf::from("."))];
+        let operands = if operands.is_empty() { &default_operand[..] } else { operands };
+        let mut files = Vec::new();
+        let mut directories = Vec::new();
+
+        for (name, path) in operands {
+            let entry = match ListEntry::read(name.clone(), path.clone()) {
+                Ok(entry) => entry,
+                Err(error) => {
+                    self.errors.push_str(&format!("ls: cannot access '{}': {}\n", name, describe_error(&error)));
+                    self.exit_code = 2;
+                    continue;
+                }
+            };
+            let follows_link = !self.options.long_format || !entry.metadata.file_type().is_symlink();
+            let is_directory = entry.metadata.is_dir() || (follows_link && entry.target.as_ref().is_some_and(Metadata::is_dir));
+            if is_directory && !self.options.directory {
+                directories.push(entry);
+            } else {
+                files.push(entry);
+            }
+        }
+
+        self.sort(&mut files);
+        self.sort(&mut directories);
+        let show_headers = operands.len() > 1 || self.options.recursive;
+
+        if !files.is_empty() {
+            self.print_entries(&files, false);
+            self.record_entries(&files, None);
+        }
+        for (index, directory) in directories.iter().enumerate() {
+            if index > 0 || !files.is_empty() {
+                self.output.push('\n');
+            }
+            self.list_directory(&directory.name, &directory.path, show_headers);
+        }
+    }
+
+    /**
+     * Lists one directory and, with -R, its subdirectories
+     *
+     * @param name - Directory name as printed
+     * @param path - Path to the directory
+     * @param show_header - Whether to print the "name:" header
+     */
+    fn list_directory(&mut self, name: &str, path: &Path, show_header: bool) {
+        if show_header {
+            self.output.push_str(&format!("{}:\n", name));
+        }
+
+        let reader = match std::fs::read_dir(path) {
+            Ok(reader) => reader,
+            Err(error) => {
+                self.errors.push_str(&format!("ls: cannot open directory '{}': {}\n", name, describe_error(&error)));
+                self.exit_code = self.exit_code.max(if show_header { 1 } else { 2 });
+                return;
+            }
+        };
+
+        let mut entries = Vec::new();
+        if self.options.hidden == HiddenEntries::All {
+            for special in [".", ".."] {
+                if let Ok(entry) = ListEntry::read(special.to_string(), path.join(special)) {
+                    entries.push(entry);
+                }
+            }
+        }
+        for dir_entry in reader.flatten() {
+            let file_name = dir_entry.file_name().to_string_lossy().into_owned();
+            if file_name.starts_with('.') && self.options.hidden == HiddenEntries::Skip {
+                continue;
+            }
+            if let Ok(entry) = ListEntry::read(file_name, dir_entry.path()) {
+                entries.push(entry);
+            }
+        }
+
+        self.sort(&mut entries);
+        self.print_entries(&entries, true);
+        self.record_entries(&entries, show_header.then_some(name));
+
+        if self.options.recursive {
+            for entry in &entries {
+                if !entry.metadata.is_dir() || entry.name == "." || entry.name == ".." {
+                    continue;
+                }
+                let child_name = if name.ends_with('/') {
+                    format!("{}{}", name, entry.name)
+                } else {
+                    format!("{}/{}", name, entry.name)
+                };
+                self.output.push('\n');
+                self.list_directory(&child_name, &entry.path, true);
+            }
+        }
+    }
+
+    /**
+     * Sorts entries by the selected key
+     *
+     * @param entries - Entries to sort in place
+     */
+    fn sort(&self, entries: &mut [ListEntry]) {
+        match self.options.sort {
+            SortKey::Name => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortKey::Time => entries.sort_by(|a, b| {
+                let a_time = (a.metadata.mtime(), a.metadata.mtime_nsec());
+                let b_time = (b.metadata.mtime(), b.metadata.mtime_nsec());
+                b_time.cmp(&a_time).then_with(|| a.name.cmp(&b.name))
+            }),
+            SortKey::Size => entries.sort_by(|a, b| b.metadata.size().cmp(&a.metadata.size()).then_with(|| a.name.cmp(&b.name))),
+        }
+        if self.options.reverse {
+            entries.reverse();
+        }
+    }
+
+    /**
+     * Prints a group of entries in the selected format
+     *
+     * @param entries - Sorted entries
+     * @param in_directory - Whether they are a directory's contents, which adds the "total" line in long format
+     */
+    fn print_entries(&mut self, entries: &[ListEntry], in_directory: bool) {
+        if self.options.long_format {
+            self.print_long(entries, in_directory);
+            return;
+        }
+
+        let cells: Vec<(String, usize)> = entries.iter().map(|entry| self.decorated_name(entry, true)).collect();
+        if self.options.columns {
+            self.output.push_str(&layout_columns(&cells, self.options.line_width));
+        } else {
+            for (text, _) in cells {
+                self.output.push_str(&text);
+                self.output.push('\n');
+            }
+        }
+    }
+
+    /**
+     * 長い形式で一覧表示する関数です
+     *
+     * パーミッション、リンク数、所有者、グループ、サイズ、
+     * 更新日時、名前の各列を揃えて表示します。リンク数と
+     * サイズは右揃え、所有者とグループは左揃えです。
+     *
+     * デバイスファイルはサイズの代わりに「メジャー, マイナー」を
+     * 表示し、シンボリックリンクには「-> リンク先」を付けます。
+     * ディレクトリの内容を表示する場合は、先頭に1KiB単位の
+     * 合計ブロック数を表示します。
+     *
+     * @param entries - 並べ替え済みのエントリ
+     * @param in_directory - ディレクトリの内容かどうか
+     */
+    fn print_long(&mut self, entries: &[ListEntry], in_directory: bool) {
+        if in_directory {
+            let blocks: u64 = entries.iter().map(|entry| entry.metadata.blocks()).sum::<u64>().div_ceil(2);
+            let total = if self.options.human_readable { human_size(blocks * 1024) } else { blocks.to_string() };
+            self.output.push_str(&format!("total {}\n", total));
+        }
+
+        let now = chrono::Local::now();
+        let rows: Vec<[String; 6]> = entries
+            .iter()
+            .map(|entry| {
+                let metadata = &entry.metadata;
+                let file_type = metadata.file_type();
+                let size = if file_type.is_block_device() || file_type.is_char_device() {
+                    let device = metadata.rdev();
+                    format!("{}, {}", libc::major(device), libc::minor(device))
+                } else if self.options.human_readable {
+                    human_size(metadata.size())
+                } else {
+                    metadata.size().to_string()
+                };
+                [
+                    mode_string(metadata),
+                    metadata.nlink().to_string(),
+                    self.owners.user(metadata.uid()),
+                    self.owners.group(metadata.gid()),
+                    size,
+                    format_modified(metadata, &now),
+                ]
+            })
+            .collect();
+
+        let mut widths = [0usize; 6];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(display_width(cell));
+            }
+        }
+
+        for (entry, row) in entries.iter().zip(rows) {
+            let (name, _) = self.decorated_name(entry, false);
+            let mut line = format!(
+                "{} {:>links$} {:<user$} {:<group$} {:>size$} {} {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                row[4],
+                row[5],
+                name,
+                links = widths[1],
+                user = widths[2],
+                group = widths[3],
+                size = widths[4]
+            );
+            if entry.metadata.file_type().is_symlink() {
+                if let Ok(link) = std::fs::read_link(&entry.path) {
+                    let link = link.to_string_lossy().into_owned();
+                    let link = match (&self.options.colors, &entry.target) {
+                        (Some(colors), Some(target)) => colors.paint(&link, &link, target, None),
+                        _ => link,
+                    };
+                    line.push_str(" -> ");
+                    line.push_str(&link);
+                }
+            }
+            self.output.push_str(&line);
+            self.output.push('\n');
+        }
+    }
+
+    /**
+     * Adds records for a group of entries
+     *
+     * @param entries - Sorted entries
+     * @param directory - Directory name prefixed to the names, when several directories are listed
+     */
+    fn record_entries(&mut self, entries: &[ListEntry], directory: Option<&str>) {
+        let Some(mut records) = self.records.take() else {
+            return;
+        };
+        for entry in entries {
+            let metadata = &entry.metadata;
+            let name = match directory {
+                Some(directory) if directory.ends_with('/') => format!("{}{}", directory, entry.name),
+                Some(directory) => format!("{}/{}", directory, entry.name),
+                None => entry.name.clone(),
+            };
+            let modified = chrono::DateTime::from_timestamp(metadata.mtime(), 0)
+                .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string());
+            let target = if metadata.file_type().is_symlink() {
+                std::fs::read_link(&entry.path).ok().map(|link| link.to_string_lossy().into_owned())
+            } else {
+                None
+            };
+            records.push_row(vec![
+                name.into(),
+                file_type_name(metadata).into(),
+                metadata.size().into(),
+                mode_string(metadata).into(),
+                metadata.nlink().into(),
+                self.owners.user(metadata.uid()).into(),
+                self.owners.group(metadata.gid()).into(),
+                modified.into(),
+                target.into(),
+            ]);
+        }
+        self.records = Some(records);
+    }
+
+    /**
+     * Gets an entry's printed name with color and -F indicator
+     *
+     * @param entry - Entry to print
+     * @param link_indicator - Whether symlinks get `@` (not in long format)
+     * @return (String, usize) - Printed text and its display width
+     */
+    fn decorated_name(&self, entry: &ListEntry, link_indicator: bool) -> (String, usize) {
+        let mut text = match &self.options.colors {
+            Some(colors) => colors.paint(&entry.name, &entry.name, &entry.metadata, entry.target.as_ref()),
+            None => entry.name.clone(),
+        };
+        let mut width = display_width(&entry.name);
+
+        if self.options.classify {
+            let indicator = match type_indicator(&entry.metadata) {
+                Some('@') if !link_indicator => None,
+                indicator => indicator,
+            };
+            if let Some(indicator) = indicator {
+                text.push(indicator);
+                width += 1;
+            }
+        }
+        (text, width)
+    }
+}
+
+/**
+ * Formats a modification time as ls does
+ *
+ * Times within the last six months show the clock time; older or
+ * future times show the year instead.
+ *
+ * @param metadata - File metadata
+ * @param now - Current local time
+ * @return String - e.g. "Mar  4 14:02" or "Mar  4  2023"
+ */
+fn format_modified(metadata: &Metadata, now: &chrono::DateTime<chrono::Local>) -> String {
+    use chrono::TimeZone;
+
+    let Some(modified) = chrono::Local.timestamp_opt(metadata.mtime(), 0).single() else {
+        return "?".to_string();
+    };
+    let age = now.signed_duration_since(modified);
+    if age < chrono::Duration::days(183) && age > chrono::Duration::hours(-1) {
+        modified.format("%b %e %H:%M").to_string()
+    } else {
+        modified.format("%b %e  %Y").to_string()
+    }
+}
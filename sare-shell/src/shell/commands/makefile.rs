@@ -0,0 +1,989 @@
+/*!
+ * @file makefile.rs
+ * @brief Makefile reading for target discovery
+ *
+ * This module reads a Makefile the way GNU make does far enough to
+ * list its targets: continuation lines, comments, conditionals,
+ * define blocks, include directives, variable assignments of every
+ * flavor and the common text functions. Recipes are skipped and
+ * $(shell ...) is never run, so reading a Makefile has no side effects.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file makefile.rs
+ * @description Makefile lookup, logical lines, ifeq/ifdef evaluation,
+ * variable expansion with substitution references, rule parsing,
+ * .PHONY and .DEFAULT_GOAL handling and `##` target descriptions.
+ */
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::shell::commands::records::Table;
+
+/// Names GNU make looks for, in order
+const MAKEFILE_NAMES: [&str; 3] = ["GNUmakefile", "makefile", "Makefile"];
+
+/// Deepest include nesting followed
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Deepest variable expansion followed, to stop self-referencing definitions
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/**
+ * How a variable's value is expanded
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flavor {
+    /// `=` and define: expanded when used
+    Recursive,
+    /// `:=` and `::=`: expanded when assigned
+    Simple,
+}
+
+/**
+ * Explicit target of a Makefile
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    /// Target name
+    pub name: String,
+    /// Prerequisites from every rule of the target
+    pub prerequisites: Vec<String>,
+    /// Text of a `##` comment on or above the rule
+    pub description: Option<String>,
+    /// Makefile defining the target first
+    pub file: PathBuf,
+    /// Line of the first rule
+    pub line: usize,
+    /// Whether the target is a prerequisite of .PHONY
+    pub phony: bool,
+}
+
+/**
+ * Open conditional block
+ */
+#[derive(Debug, Clone, Copy)]
+struct Condition {
+    /// Whether lines in the current branch are read
+    active: bool,
+    /// Whether an earlier branch was taken
+    taken: bool,
+}
+
+/**
+ * Kind of a Makefile statement
+ */
+#[derive(Debug, Clone, PartialEq)]
+enum Statement {
+    /// `name op value`, with the operator text
+    Assignment(String, String, String),
+    /// `targets: rest`
+    Rule(String, String),
+    /// Anything else, e.g. a function call evaluated for its side effects
+    Other,
+}
+
+/**
+ * Makefile read for its targets and variables
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Makefile {
+    /// Top-level Makefile
+    pub path: PathBuf,
+    /// Directory make runs in, which includes and $(wildcard) are relative to
+    pub directory: PathBuf,
+    /// Targets in order of their first rule
+    pub targets: Vec<Target>,
+    /// Files read through include directives
+    pub included: Vec<PathBuf>,
+    /// Variables with their flavor and unexpanded or expanded value
+    variables: HashMap<String, (Flavor, String)>,
+    /// Variables given on the command line, which override assignments
+    overrides: HashMap<String, String>,
+}
+
+impl Makefile {
+    /**
+     * Finds the Makefile make would read in a directory
+     *
+     * @param directory - Directory make runs in
+     * @return Option<PathBuf> - GNUmakefile, makefile or Makefile
+     */
+    pub fn find(directory: &Path) -> Option<PathBuf> {
+        MAKEFILE_NAMES.iter().map(|name| directory.join(name)).find(|path| path.is_file())
+    }
+
+    /**
+     * Reads a Makefile and everything it includes
+     *
+     * @param path - Makefile path
+     * @param directory - Directory make runs in
+     * @param overrides - `NAME=value` variables from the command line
+     * @return Result<Makefile> - Makefile or a read error
+     */
+    pub fn load(path: &Path, directory: &Path, overrides: &[(String, String)]) -> Result<Makefile> {
+        let mut makefile = Makefile {
+            path: path.to_path_buf(),
+            directory: directory.to_path_buf(),
+            overrides: overrides.iter().cloned().collect(),
+            ..Makefile::default()
+        };
+        makefile.read(path, 0)?;
+        let phony: Vec<String> = makefile
+            .targets
+            .iter()
+            .find(|target| target.name == ".PHONY")
+            .map(|target| target.prerequisites.clone())
+            .unwrap_or_default();
+        for target in &mut makefile.targets {
+            target.phony = phony.contains(&target.name);
+        }
+        Ok(makefile)
+    }
+
+    /**
+     * Reads the Makefile make would use for a command line
+     *
+     * @param directory - Directory make runs in, after -C
+     * @param file - File given with -f, relative to the directory
+     * @param overrides - `NAME=value` variables from the command line
+     * @return Result<Makefile> - Makefile, or an error when there is none
+     */
+    pub fn discover(directory: &Path, file: Option<&str>, overrides: &[(String, String)]) -> Result<Makefile> {
+        let path = match file {
+            Some(file) => directory.join(file),
+            None => Makefile::find(directory).ok_or_else(|| anyhow::anyhow!("no makefile found"))?,
+        };
+        Makefile::load(&path, directory, overrides)
+    }
+
+    /**
+     * Goal make builds when no target is given
+     *
+     * @return Option<&str> - .DEFAULT_GOAL, or the first target not starting with a dot
+     */
+    pub fn default_goal(&self) -> Option<&str> {
+        if let Some((_, value)) = self.variables.get(".DEFAULT_GOAL") {
+            if let Some(goal) = self.targets.iter().find(|target| target.name == value.trim()) {
+                return Some(&goal.name);
+            }
+        }
+        self.targets
+            .iter()
+            .find(|target| (!target.name.starts_with('.') || target.name.contains('/')) && !target.name.contains('%'))
+            .map(|target| target.name.as_str())
+    }
+
+    /**
+     * Targets a user would build by name
+     *
+     * @return impl Iterator<Item = &Target> - Targets except special targets and pattern rules
+     */
+    pub fn listed_targets(&self) -> impl Iterator<Item = &Target> {
+        self.targets.iter().filter(|target| !target.name.starts_with('.') && !target.name.contains('%'))
+    }
+
+    /**
+     * Table of the listed targets
+     *
+     * @return Table - target, default, phony, prerequisites, description, file and line columns
+     */
+    pub fn target_table(&self) -> Table {
+        let default_goal = self.default_goal();
+        let mut table = Table::new(&["target", "default", "phony", "prerequisites", "description", "file", "line"]);
+        for target in self.listed_targets() {
+            table.push_row(vec![
+                json!(target.name),
+                json!(Some(target.name.as_str()) == default_goal),
+                json!(target.phony),
+                json!(target.prerequisites),
+                target.description.as_ref().map_or(Value::Null, |description| json!(description)),
+                json!(target.file.display().to_string()),
+                json!(target.line),
+            ]);
+        }
+        table
+    }
+
+    /**
+     * Makefileを1つ読む関数です
+     *
+     * 論理行ごとに、defineブロック、レシピ（タブで始まる行）、
+     * 条件分岐（ifdef、ifndef、ifeq、ifneq、else、endif）、コメント、
+     * include、代入、ルールの順に判定します。条件が偽のブロック内では
+     * 入れ子の条件分岐だけを追跡します。
+     *
+     * 「##」で始まるコメント行は次のルールの説明に、ルールの行末の
+     * 「## 説明」はそのルールの説明になります。
+     *
+     * @param path - 読むファイル
+     * @param depth - includeの深さ
+     * @return Result<()> - 成功または読み込みエラー
+     */
+    fn read(&mut self, path: &Path, depth: usize) -> Result<()> {
+        let contents = fs::read(path).map_err(|error| anyhow::anyhow!("{}: {}", path.display(), error))?;
+        let contents = String::from_utf8_lossy(&contents);
+        let mut conditions: Vec<Condition> = Vec::new();
+        let mut define: Option<(String, Flavor, Vec<String>, usize)> = None;
+        let mut description: Option<String> = None;
+
+        for (number, line) in logical_lines(&contents) {
+            let trimmed = line.trim_start();
+            let (keyword, rest) = split_word(trimmed);
+
+            if let Some((name, flavor, body, nesting)) = &mut define {
+                if keyword == "endef" && *nesting == 0 {
+                    let value = body.join("\n");
+                    let (name, flavor) = (name.clone(), *flavor);
+                    define = None;
+                    if conditions.iter().all(|condition| condition.active) {
+                        self.assign(&name, flavor, value);
+                    }
+                } else {
+                    match keyword {
+                        "define" => *nesting += 1,
+                        "endef" => *nesting -= 1,
+                        _ => {}
+                    }
+                    body.push(line.to_string());
+                }
+                continue;
+            }
+            if line.starts_with('\t') {
+                continue;
+            }
+
+            match keyword {
+                "ifdef" | "ifndef" | "ifeq" | "ifneq" => {
+                    let active = conditions.iter().all(|condition| condition.active) && self.condition(keyword, rest);
+                    conditions.push(Condition { active, taken: active });
+                    continue;
+                }
+                "else" => {
+                    let parents_active = conditions.len() < 2 || conditions[..conditions.len() - 1].iter().all(|condition| condition.active);
+                    if let Some(condition) = conditions.last_mut() {
+                        let (nested, nested_rest) = split_word(rest);
+                        let holds = match nested {
+                            "" => true,
+                            "ifdef" | "ifndef" | "ifeq" | "ifneq" => parents_active && self.condition(nested, nested_rest),
+                            _ => false,
+                        };
+                        condition.active = !condition.taken && holds;
+                        condition.taken |= condition.active;
+                    }
+                    continue;
+                }
+                "endif" => {
+                    conditions.pop();
+                    continue;
+                }
+                _ => {}
+            }
+            if !conditions.iter().all(|condition| condition.active) {
+                continue;
+            }
+
+            if trimmed.starts_with('#') {
+                if trimmed.starts_with("##") {
+                    description = Some(trimmed.trim_start_matches('#').trim().to_string());
+                }
+                continue;
+            }
+            let (code, comment) = split_comment(&line);
+            if code.trim().is_empty() {
+                description = None;
+                continue;
+            }
+
+            let (keyword, rest) = split_word(code.trim_start());
+            match keyword {
+                "define" => {
+                    let rest = rest.trim();
+                    let (name, flavor) = match rest.split_once(char::is_whitespace) {
+                        Some((name, operator)) if operator.trim().starts_with(':') => (name, Flavor::Simple),
+                        Some((name, _)) => (name, Flavor::Recursive),
+                        None => (rest.trim_end_matches(['=', ':', '?', '+']), Flavor::Recursive),
+                    };
+                    define = Some((self.expand(name).trim().to_string(), flavor, Vec::new(), 0));
+                    continue;
+                }
+                "include" | "-include" | "sinclude" => {
+                    let optional = keyword != "include";
+                    let names = self.expand(rest);
+                    for name in names.split_whitespace() {
+                        self.include(name, optional, depth);
+                    }
+                    continue;
+                }
+                "undefine" => {
+                    let name = self.expand(rest).trim().to_string();
+                    self.variables.remove(&name);
+                    continue;
+                }
+                "unexport" | "vpath" => continue,
+                _ => {}
+            }
+
+            let mut statement_text = code.as_str();
+            loop {
+                let (keyword, rest) = split_word(statement_text.trim_start());
+                if matches!(keyword, "export" | "override" | "private") {
+                    statement_text = rest;
+                } else {
+                    break;
+                }
+            }
+            match classify(statement_text) {
+                Statement::Assignment(name, operator, value) => {
+                    let name = self.expand(&name).trim().to_string();
+                    self.assign_with(&name, &operator, value.trim_start());
+                    description = None;
+                }
+                Statement::Rule(targets, rest) => {
+                    let rule_description = comment
+                        .filter(|comment| comment.starts_with("##"))
+                        .map(|comment| comment.trim_start_matches('#').trim().to_string())
+                        .or(description.take());
+                    self.add_rule(&targets, &rest, rule_description, path, number);
+                }
+                Statement::Other => description = None,
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Reads the files an include directive names
+     *
+     * @param name - File name or glob pattern, relative to the make directory
+     * @param optional - Whether missing files are ignored (`-include`)
+     * @param depth - Include depth of the including file
+     */
+    fn include(&mut self, name: &str, optional: bool, depth: usize) {
+        if depth >= MAX_INCLUDE_DEPTH {
+            return;
+        }
+        let full = self.directory.join(name);
+        let paths: Vec<PathBuf> = if name.contains(['*', '?', '[']) {
+            glob::glob(&full.to_string_lossy()).map(|paths| paths.flatten().collect()).unwrap_or_default()
+        } else {
+            vec![full]
+        };
+        for path in paths {
+            if !path.is_file() || self.included.contains(&path) {
+                continue;
+            }
+            self.included.push(path.clone());
+            if self.read(&path, depth + 1).is_err() && !optional {
+                continue;
+            }
+        }
+    }
+
+    /**
+     * Applies an assignment operator
+     *
+     * @param name - Expanded variable name
+     * @param operator - `=`, `:=`, `::=`, `:::=`, `?=`, `+=` or `!=`
+     * @param value - Unexpanded value
+     */
+    fn assign_with(&mut self, name: &str, operator: &str, value: &str) {
+        match operator {
+            ":=" | "::=" => {
+                let value = self.expand(value);
+                self.assign(name, Flavor::Simple, value);
+            }
+            "?=" => {
+                if !self.variables.contains_key(name) && std::env::var_os(name).is_none() {
+                    self.assign(name, Flavor::Recursive, value.to_string());
+                }
+            }
+            "+=" => match self.variables.get(name).cloned() {
+                Some((Flavor::Simple, existing)) => {
+                    let value = format!("{} {}", existing, self.expand(value));
+                    self.assign(name, Flavor::Simple, value.trim_start().to_string());
+                }
+                Some((Flavor::Recursive, existing)) => {
+                    self.assign(name, Flavor::Recursive, format!("{} {}", existing, value).trim_start().to_string());
+                }
+                None => self.assign(name, Flavor::Recursive, value.to_string()),
+            },
+            "!=" => self.assign(name, Flavor::Simple, String::new()),
+            _ => self.assign(name, Flavor::Recursive, value.to_string()),
+        }
+    }
+
+    /**
+     * Sets a variable unless the command line overrides it
+     *
+     * @param name - Variable name
+     * @param flavor - Expansion flavor
+     * @param value - Value
+     */
+    fn assign(&mut self, name: &str, flavor: Flavor, value: String) {
+        if !self.overrides.contains_key(name) {
+            self.variables.insert(name.to_string(), (flavor, value));
+        }
+    }
+
+    /**
+     * Records the targets of a rule
+     *
+     * @param targets - Unexpanded target list
+     * @param rest - Text after the colon
+     * @param description - `##` description
+     * @param file - Makefile containing the rule
+     * @param line - Line of the rule
+     */
+    fn add_rule(&mut self, targets: &str, rest: &str, description: Option<String>, file: &Path, line: usize) {
+        let prerequisites_text = match find_top_level(rest, |character| character == ';' || character == '=') {
+            Some(position) if rest[position..].starts_with('=') || rest[..position].ends_with([':', '+', '?', '!']) => "",
+            Some(position) => &rest[..position],
+            None => rest,
+        };
+        let (target_pattern, prerequisites_text) = match find_top_level(prerequisites_text, |character| character == ':') {
+            Some(position) => (Some(self.expand(&prerequisites_text[..position]).trim().to_string()), &prerequisites_text[position + 1..]),
+            None => (None, prerequisites_text),
+        };
+        let prerequisite_words: Vec<String> = self
+            .expand(prerequisites_text)
+            .split_whitespace()
+            .filter(|word| *word != "|")
+            .map(str::to_string)
+            .collect();
+
+        for name in self.expand(targets).split_whitespace() {
+            let prerequisites: Vec<String> = match target_pattern.as_deref().and_then(|pattern| pattern_stem(pattern, name)) {
+                Some(stem) => prerequisite_words.iter().map(|word| word.replacen('%', stem, 1)).collect(),
+                None => prerequisite_words.clone(),
+            };
+            match self.targets.iter_mut().find(|target| target.name == name) {
+                Some(target) => {
+                    for prerequisite in &prerequisites {
+                        if !target.prerequisites.contains(prerequisite) {
+                            target.prerequisites.push(prerequisite.clone());
+                        }
+                    }
+                    if target.description.is_none() {
+                        target.description = description.clone();
+                    }
+                }
+                None => self.targets.push(Target {
+                    name: name.to_string(),
+                    prerequisites,
+                    description: description.clone(),
+                    file: file.to_path_buf(),
+                    line,
+                    phony: false,
+                }),
+            }
+        }
+    }
+
+    /**
+     * Evaluates a conditional directive
+     *
+     * @param keyword - ifdef, ifndef, ifeq or ifneq
+     * @param rest - Text after the keyword
+     * @return bool - Whether the branch is taken
+     */
+    fn condition(&self, keyword: &str, rest: &str) -> bool {
+        match keyword {
+            "ifdef" | "ifndef" => {
+                let name = self.expand(rest).trim().to_string();
+                let defined = self.overrides.get(&name).map(|value| !value.is_empty()).unwrap_or_else(|| match self.variables.get(&name) {
+                    Some((_, value)) => !value.is_empty(),
+                    None => std::env::var(&name).is_ok_and(|value| !value.is_empty()),
+                });
+                defined == (keyword == "ifdef")
+            }
+            _ => {
+                let Some((left, right)) = conditional_arguments(rest.trim()) else {
+                    return false;
+                };
+                (self.expand(&left).trim() == self.expand(&right).trim()) == (keyword == "ifeq")
+            }
+        }
+    }
+
+    /**
+     * Expands variable references and functions
+     *
+     * @param text - Text with `$(...)`, `${...}` and `$X` references
+     * @return String - Expanded text
+     */
+    pub fn expand(&self, text: &str) -> String {
+        self.expand_at(text, 0)
+    }
+
+    /**
+     * 変数参照を展開する関数です
+     *
+     * 「$$」は「$」に、「$(...)」と「${...}」は対応する閉じ括弧までを
+     * 参照として、「$X」は1文字の変数として展開します。深さが
+     * 上限を超えた場合（自分自身を参照する再帰変数など）は空文字列に
+     * します。
+     *
+     * @param text - 展開するテキスト
+     * @param depth - 展開の深さ
+     * @return String - 展開後のテキスト
+     */
+    fn expand_at(&self, text: &str, depth: usize) -> String {
+        if depth > MAX_EXPANSION_DEPTH || !text.contains('$') {
+            return if depth > MAX_EXPANSION_DEPTH { String::new() } else { text.to_string() };
+        }
+        let mut output = String::new();
+        let mut characters = text.char_indices().peekable();
+        while let Some((position, character)) = characters.next() {
+            if character != '$' {
+                output.push(character);
+                continue;
+            }
+            match characters.next() {
+                Some((_, '$')) => output.push('$'),
+                Some((start, open @ ('(' | '{'))) => {
+                    let close = if open == '(' { ')' } else { '}' };
+                    let mut nesting = 1;
+                    let mut end = None;
+                    for (index, inner) in characters.by_ref() {
+                        if inner == open {
+                            nesting += 1;
+                        } else if inner == close {
+                            nesting -= 1;
+                            if nesting == 0 {
+                                end = Some(index);
+                                break;
+                            }
+                        }
+                    }
+                    match end {
+                        Some(end) => output.push_str(&self.reference(&text[start + 1..end], depth)),
+                        None => output.push_str(&text[position..]),
+                    }
+                }
+                Some((_, name)) => output.push_str(&self.value(&name.to_string(), depth)),
+                None => {}
+            }
+        }
+        output
+    }
+
+    /**
+     * Value of a variable
+     *
+     * @param name - Variable name
+     * @param depth - Expansion depth
+     * @return String - Command-line override, Makefile value or environment value
+     */
+    fn value(&self, name: &str, depth: usize) -> String {
+        if let Some(value) = self.overrides.get(name) {
+            return value.clone();
+        }
+        match self.variables.get(name) {
+            Some((Flavor::Recursive, value)) => self.expand_at(value, depth + 1),
+            Some((Flavor::Simple, value)) => value.clone(),
+            None if name == "CURDIR" => self.directory.display().to_string(),
+            None if name == "MAKEFILE_LIST" => std::iter::once(&self.path)
+                .chain(&self.included)
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => std::env::var(name).unwrap_or_default(),
+        }
+    }
+
+    /**
+     * Expands the inside of `$(...)`
+     *
+     * @param inner - Text between the parentheses
+     * @param depth - Expansion depth
+     * @return String - Function result, substitution reference or variable value
+     */
+    fn reference(&self, inner: &str, depth: usize) -> String {
+        if let Some((function, arguments)) = inner.split_once([' ', '\t']) {
+            if let Some(result) = self.function(function, arguments, depth) {
+                return result;
+            }
+        }
+        if let Some(colon) = find_top_level(inner, |character| character == ':') {
+            if let Some(equals) = find_top_level(&inner[colon..], |character| character == '=') {
+                let name = self.expand_at(&inner[..colon], depth + 1);
+                let from = self.expand_at(&inner[colon + 1..colon + equals], depth + 1);
+                let to = self.expand_at(&inner[colon + equals + 1..], depth + 1);
+                let (from, to) = if from.contains('%') { (from, to) } else { (format!("%{}", from), format!("%{}", to)) };
+                return patsubst(&from, &to, &self.value(name.trim(), depth));
+            }
+        }
+        let name = self.expand_at(inner, depth + 1);
+        self.value(name.trim(), depth)
+    }
+
+    /**
+     * Make関数を評価する関数です
+     *
+     * 引数はトップレベルのカンマで分割し、それぞれ展開してから
+     * 評価します。対象はsubst、patsubst、strip、findstring、filter、
+     * filter-out、sort、word、words、firstword、lastword、dir、notdir、
+     * suffix、basename、addprefix、addsuffix、wildcard、abspath、
+     * if、or、and、valueです。shellは副作用を避けるため実行せず
+     * 空文字列を返します。foreach、call、evalなどは評価しません。
+     *
+     * @param name - 関数名
+     * @param arguments - 関数名の後のテキスト
+     * @param depth - 展開の深さ
+     * @return Option<String> - 結果、または関数でない場合None
+     */
+    fn function(&self, name: &str, arguments: &str, depth: usize) -> Option<String> {
+        let split = |count: usize| -> Vec<String> {
+            let mut parts = Vec::new();
+            let mut rest = arguments;
+            while parts.len() + 1 < count {
+                match find_top_level(rest, |character| character == ',') {
+                    Some(position) => {
+                        parts.push(self.expand_at(&rest[..position], depth + 1));
+                        rest = &rest[position + 1..];
+                    }
+                    None => break,
+                }
+            }
+            parts.push(self.expand_at(rest, depth + 1));
+            while parts.len() < count {
+                parts.push(String::new());
+            }
+            parts
+        };
+        let words = |text: &str| text.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+        let map_words = |text: &str, transform: &dyn Fn(&str) -> String| words(text).iter().map(|word| transform(word)).collect::<Vec<_>>().join(" ");
+
+        let result = match name {
+            "subst" => {
+                let parts = split(3);
+                if parts[0].is_empty() { parts[2].clone() } else { parts[2].replace(&parts[0], &parts[1]) }
+            }
+            "patsubst" => {
+                let parts = split(3);
+                patsubst(parts[0].trim(), parts[1].trim(), &parts[2])
+            }
+            "strip" => words(&split(1)[0]).join(" "),
+            "findstring" => {
+                let parts = split(2);
+                if parts[1].contains(&parts[0]) { parts[0].clone() } else { String::new() }
+            }
+            "filter" | "filter-out" => {
+                let parts = split(2);
+                let patterns = words(&parts[0]);
+                words(&parts[1])
+                    .into_iter()
+                    .filter(|word| patterns.iter().any(|pattern| pattern_stem(pattern, word).is_some()) == (name == "filter"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            "sort" => {
+                let mut sorted = words(&split(1)[0]);
+                sorted.sort();
+                sorted.dedup();
+                sorted.join(" ")
+            }
+            "word" => {
+                let parts = split(2);
+                let index: usize = parts[0].trim().parse().unwrap_or(0);
+                words(&parts[1]).get(index.wrapping_sub(1)).cloned().unwrap_or_default()
+            }
+            "words" => words(&split(1)[0]).len().to_string(),
+            "firstword" => words(&split(1)[0]).first().cloned().unwrap_or_default(),
+            "lastword" => words(&split(1)[0]).last().cloned().unwrap_or_default(),
+            "dir" => map_words(&split(1)[0], &|word| match word.rfind('/') {
+                Some(position) => word[..=position].to_string(),
+                None => "./".to_string(),
+            }),
+            "notdir" => map_words(&split(1)[0], &|word| word.rsplit('/').next().unwrap_or(word).to_string()),
+            "suffix" => words(&split(1)[0])
+                .iter()
+                .filter_map(|word| {
+                    let name = word.rsplit('/').next().unwrap_or(word);
+                    name.rfind('.').map(|position| name[position..].to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            "basename" => map_words(&split(1)[0], &|word| {
+                let start = word.rfind('/').map_or(0, |position| position + 1);
+                match word[start..].rfind('.') {
+                    Some(position) => word[..start + position].to_string(),
+                    None => word.to_string(),
+                }
+            }),
+            "addprefix" | "addsuffix" => {
+                let parts = split(2);
+                let affix = parts[0].clone();
+                map_words(&parts[1], &|word| if name == "addprefix" { format!("{}{}", affix, word) } else { format!("{}{}", word, affix) })
+            }
+            "wildcard" => words(&split(1)[0])
+                .iter()
+                .flat_map(|pattern| {
+                    let full = self.directory.join(pattern);
+                    glob::glob(&full.to_string_lossy())
+                        .map(|paths| paths.flatten().collect::<Vec<_>>())
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|path| path.strip_prefix(&self.directory).map(Path::to_path_buf).unwrap_or(path).display().to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            "abspath" | "realpath" => map_words(&split(1)[0], &|word| self.directory.join(word).components().collect::<PathBuf>().display().to_string()),
+            "if" => {
+                let parts = split(3);
+                let condition = parts[0].trim().to_string();
+                if condition.is_empty() { parts[2].clone() } else { parts[1].clone() }
+            }
+            "or" => split(usize::MAX).into_iter().find(|part| !part.trim().is_empty()).unwrap_or_default(),
+            "and" => {
+                let parts = split(usize::MAX);
+                if parts.iter().all(|part| !part.trim().is_empty()) { parts.last().cloned().unwrap_or_default() } else { String::new() }
+            }
+            "value" => self.variables.get(self.expand_at(arguments, depth + 1).trim()).map(|(_, value)| value.clone()).unwrap_or_default(),
+            "shell" | "foreach" | "call" | "eval" | "origin" | "flavor" | "error" | "warning" | "info" | "file" | "guile" | "let" | "intcmp" => String::new(),
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+/**
+ * makeの引数からMakefileの場所を決める関数です
+ * 
+ * -C（複数指定は順に適用）、-f、--directory=、--file=、
+ * --makefile=と、コマンドラインの変数代入（NAME=value）を
+ * 読み取ります。
+ * 
+ * @param args - makeの引数
+ * @param current - シェルの現在のディレクトリ
+ * @return (PathBuf, Option<String>, Vec<(String, String)>) - makeのディレクトリ、-fのファイル、変数
+ */
+pub fn locate(args: &[String], current: &Path) -> (PathBuf, Option<String>, Vec<(String, String)>) {
+    let mut directory = current.to_path_buf();
+    let mut file = None;
+    let mut overrides = Vec::new();
+    let mut arguments = args.iter();
+    while let Some(arg) = arguments.next() {
+        match arg.as_str() {
+            "-C" | "--directory" => {
+                if let Some(value) = arguments.next() {
+                    directory = directory.join(value);
+                }
+            }
+            "-f" | "--file" | "--makefile" => file = arguments.next().cloned(),
+            other => {
+                if let Some(value) = other.strip_prefix("--directory=").or_else(|| other.strip_prefix("-C").filter(|value| !value.is_empty())) {
+                    directory = directory.join(value);
+                } else if let Some(value) = other.strip_prefix("--file=").or_else(|| other.strip_prefix("--makefile=")).or_else(|| other.strip_prefix("-f").filter(|value| !value.is_empty())) {
+                    file = Some(value.to_string());
+                } else if let Some((name, value)) = other.split_once('=').filter(|(name, _)| !name.starts_with('-') && !name.is_empty()) {
+                    overrides.push((name.trim_end_matches([':', '+', '?']).to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+    (directory, file, overrides)
+}
+
+/**
+ * 物理行を論理行にまとめる関数です
+ *
+ * 奇数個のバックスラッシュで終わる行は次の行と連結します。
+ * レシピ以外の行では、GNU makeと同じく連結部分の前後の空白を
+ * 1つの空白にまとめます。各論理行には開始行の番号を付けます。
+ *
+ * @param contents - Makefileの内容
+ * @return Vec<(usize, String)> - 行番号と論理行
+ */
+fn logical_lines(contents: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (index, raw) in contents.lines().enumerate() {
+        let backslashes = raw.len() - raw.trim_end_matches('\\').len();
+        let continued = backslashes % 2 == 1;
+        let text = if continued { &raw[..raw.len() - 1] } else { raw };
+        match &mut current {
+            Some((_, line)) => {
+                if line.starts_with('\t') {
+                    line.push_str(text);
+                } else {
+                    let trimmed = line.trim_end().len();
+                    line.truncate(trimmed);
+                    line.push(' ');
+                    line.push_str(text.trim_start());
+                }
+            }
+            None => current = Some((index + 1, text.to_string())),
+        }
+        if !continued {
+            lines.extend(current.take());
+        }
+    }
+    lines.extend(current);
+    lines
+}
+
+/**
+ * Splits off the first word
+ *
+ * @param text - Text without leading whitespace
+ * @return (&str, &str) - First word and the rest
+ */
+fn split_word(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(position) => (&text[..position], &text[position..]),
+        None => (text, ""),
+    }
+}
+
+/**
+ * Splits a line at its first unescaped `#`
+ *
+ * @param line - Logical line
+ * @return (String, Option<String>) - Code with `\#` unescaped, and the comment from `#` on
+ */
+fn split_comment(line: &str) -> (String, Option<String>) {
+    let mut code = String::new();
+    let mut characters = line.char_indices().peekable();
+    while let Some((position, character)) = characters.next() {
+        match character {
+            '\\' if characters.peek().map(|(_, next)| *next) == Some('#') => {
+                code.push('#');
+                characters.next();
+            }
+            '#' => return (code, Some(line[position..].to_string())),
+            other => code.push(other),
+        }
+    }
+    (code, None)
+}
+
+/**
+ * Finds a character outside parentheses and braces
+ *
+ * @param text - Text to search
+ * @param wanted - Character test
+ * @return Option<usize> - Byte position of the first match at nesting depth 0
+ */
+fn find_top_level(text: &str, wanted: impl Fn(char) -> bool) -> Option<usize> {
+    let mut depth = 0usize;
+    for (position, character) in text.char_indices() {
+        match character {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth = depth.saturating_sub(1),
+            other if depth == 0 && wanted(other) => return Some(position),
+            _ => {}
+        }
+    }
+    None
+}
+
+/**
+ * 文が代入かルールかを判定する関数です
+ *
+ * 括弧の外で最初に現れる「=」または「:」で判断します。「=」なら
+ * 直前の「:」「::」「:::」「?」「+」「!」を含めて演算子とし、
+ * 代入になります。「:」の直後が「=」や「:=」なら代入の演算子の
+ * 一部なので読み進め、それ以外の「:」はルールの区切りです。
+ * 「::」（ダブルコロンルール）の2つ目のコロンは取り除きます。
+ *
+ * @param code - コメントを除いた行
+ * @return Statement - 代入、ルール、またはその他
+ */
+fn classify(code: &str) -> Statement {
+    let bytes = code.as_bytes();
+    let mut depth = 0usize;
+    for (position, character) in code.char_indices() {
+        match character {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth = depth.saturating_sub(1),
+            '=' if depth == 0 => {
+                let mut start = position;
+                while start > 0 && matches!(bytes[start - 1], b':' | b'?' | b'+' | b'!') && position - start < 3 {
+                    start -= 1;
+                }
+                let operator = code[start..=position].to_string();
+                return Statement::Assignment(code[..start].trim().to_string(), operator, code[position + 1..].to_string());
+            }
+            ':' if depth == 0 => {
+                let after = &code[position + 1..];
+                if after.starts_with('=') || after.starts_with(":=") || after.starts_with("::=") {
+                    continue;
+                }
+                let rest = after.strip_prefix(':').unwrap_or(after);
+                return Statement::Rule(code[..position].to_string(), rest.to_string());
+            }
+            _ => {}
+        }
+    }
+    Statement::Other
+}
+
+/**
+ * Splits the arguments of ifeq and ifneq
+ *
+ * @param text - `(a,b)`, `"a" "b"` or `'a' 'b'`
+ * @return Option<(String, String)> - Unexpanded arguments
+ */
+fn conditional_arguments(text: &str) -> Option<(String, String)> {
+    if let Some(inner) = text.strip_prefix('(') {
+        let close = inner.rfind(')')?;
+        let inner = &inner[..close];
+        let comma = find_top_level(inner, |character| character == ',')?;
+        return Some((inner[..comma].to_string(), inner[comma + 1..].to_string()));
+    }
+    let mut parts = Vec::new();
+    let mut rest = text;
+    for _ in 0..2 {
+        rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|quote| *quote == '"' || *quote == '\'')?;
+        let end = rest[1..].find(quote)? + 1;
+        parts.push(rest[1..end].to_string());
+        rest = &rest[end + 1..];
+    }
+    Some((parts[0].clone(), parts[1].clone()))
+}
+
+/**
+ * Matches a word against a `%` pattern
+ *
+ * @param pattern - Pattern with at most one `%`
+ * @param word - Word
+ * @return Option<&str> - Text the `%` matched, or the whole word for exact matches
+ */
+fn pattern_stem<'a>(pattern: &str, word: &'a str) -> Option<&'a str> {
+    match pattern.split_once('%') {
+        Some((prefix, suffix)) => {
+            if word.len() >= prefix.len() + suffix.len() && word.starts_with(prefix) && word.ends_with(suffix) {
+                Some(&word[prefix.len()..word.len() - suffix.len()])
+            } else {
+                None
+            }
+        }
+        None => (pattern == word).then_some(word),
+    }
+}
+
+/**
+ * Replaces words matching a pattern, as $(patsubst) does
+ *
+ * @param from - Pattern
+ * @param to - Replacement, where `%` stands for the matched text
+ * @param text - Words
+ * @return String - Words with matches replaced
+ */
+fn patsubst(from: &str, to: &str, text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| match pattern_stem(from, word) {
+            Some(stem) if from.contains('%') => to.replacen('%', stem, 1),
+            Some(_) => to.to_string(),
+            None => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -18,6 +18,34 @@ pub mod text;
 pub mod system;
 pub mod network;
 pub mod development;
+pub mod sed;
+pub mod awk;
+pub mod ping;
+pub mod http;
+pub mod procnet;
+pub mod listing;
+pub mod fileops;
+pub mod trash;
+pub mod find;
+pub mod grep;
+pub mod sort;
+pub mod tr;
+pub mod tail;
+pub mod records;
+pub mod data;
+pub mod procfs;
+pub mod ps;
+pub mod top;
+pub mod porcelain;
+pub mod debugger;
+pub mod gdbmi;
+pub mod dap;
+pub mod makefile;
+pub mod diagnostics;
+pub mod cargo;
+pub mod tasks;
+pub mod npm;
+pub mod dashboard;
 
 use anyhow::Result;
 use crate::shell::parser::ParsedCommand;
@@ -106,12 +134,18 @@ impl CommandRegistry {
         // Filesystem commands
         self.register(Box::new(filesystem::CdCommand));
         self.register(Box::new(filesystem::PwdCommand));
+        self.register(Box::new(filesystem::PushdCommand));
+        self.register(Box::new(filesystem::PopdCommand));
+        self.register(Box::new(filesystem::DirsCommand));
+        self.register(Box::new(filesystem::ZCommand));
         self.register(Box::new(filesystem::LsCommand));
         self.register(Box::new(filesystem::MkdirCommand));
         self.register(Box::new(filesystem::RmCommand));
         self.register(Box::new(filesystem::CpCommand));
         self.register(Box::new(filesystem::MvCommand));
         self.register(Box::new(filesystem::TouchCommand));
+        self.register(Box::new(filesystem::TrashCommand));
+        self.register(Box::new(filesystem::FindCommand));
         
         // Process commands
         self.register(Box::new(process::JobsCommand));
@@ -119,6 +153,15 @@ impl CommandRegistry {
         self.register(Box::new(process::BgCommand));
         self.register(Box::new(process::FgCommand));
         self.register(Box::new(process::WaitCommand));
+        self.register(Box::new(process::TimeoutCommand));
+        self.register(Box::new(process::UlimitCommand));
+        #[cfg(feature = "security")]
+        self.register(Box::new(process::SandboxCommand));
+        self.register(Box::new(process::XargsCommand));
+        self.register(Box::new(process::PsCommand));
+        self.register(Box::new(process::PgrepCommand));
+        self.register(Box::new(process::PkillCommand));
+        self.register(Box::new(process::TopCommand));
         
         // Text processing commands
         self.register(Box::new(text::EchoCommand));
@@ -129,6 +172,18 @@ impl CommandRegistry {
         self.register(Box::new(text::SortCommand));
         self.register(Box::new(text::UniqCommand));
         self.register(Box::new(text::WcCommand));
+        self.register(Box::new(text::CutCommand));
+        self.register(Box::new(text::TrCommand));
+        self.register(Box::new(text::HeadCommand));
+        self.register(Box::new(text::TailCommand));
+        self.register(Box::new(text::TeeCommand));
+        
+        // Structured data commands
+        self.register(Box::new(data::WhereCommand));
+        self.register(Box::new(data::SelectCommand));
+        self.register(Box::new(data::SortByCommand));
+        self.register(Box::new(data::ToCommand));
+        self.register(Box::new(data::FromCommand));
         
         // System commands
         self.register(Box::new(system::ExitCommand));
@@ -147,6 +202,7 @@ impl CommandRegistry {
         self.register(Box::new(network::CurlCommand));
         self.register(Box::new(network::WgetCommand));
         self.register(Box::new(network::NetstatCommand));
+        self.register(Box::new(network::SsCommand));
         
         // Development commands
         self.register(Box::new(development::GitCommand));
@@ -251,4 +307,29 @@ impl CommandRegistry {
     pub fn has_command(&self, command_name: &str) -> bool {
         self.commands.contains_key(command_name)
     }
-} 
\ No newline at end of file
+}
+
+/**
+ * Builds a plain command from words, for builtins that run other commands
+ * 
+ * Redirections, compound bodies and process substitutions of the
+ * template are dropped; the words are used as they are, without
+ * further expansion.
+ * 
+ * @param template - Command the new one is derived from
+ * @param words - Command name followed by its arguments
+ * @return ParsedCommand - Command ready for Shell::run_unit
+ */
+pub(crate) fn command_from_words(template: &ParsedCommand, words: &[String]) -> ParsedCommand {
+    let mut command = template.clone();
+    command.command = words.first().cloned().unwrap_or_default();
+    command.args = words.iter().skip(1).cloned().collect();
+    command.background = false;
+    command.input_redirect = None;
+    command.output_redirect = None;
+    command.append_redirect = None;
+    command.compound = None;
+    command.process_substitutions = Vec::new();
+    command.time_keyword = None;
+    command
+}
@@ -13,60 +13,168 @@
  */
 
 use anyhow::Result;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
 use crate::shell::parser::ParsedCommand;
 use crate::shell::Shell;
 use crate::shell::commands::{CommandHandler, CommandResult};
+use crate::shell::commands::records::Table;
+use crate::shell::commands::fileops::describe_error;
+use crate::shell::commands::http::{error_kind, format_size, BodySink, HttpClient, HttpErrorKind, HttpRequest, ProgressMeter, Url};
+use crate::shell::commands::procnet::{
+    read_inet_sockets, read_unix_sockets, socket_owners, InetSocket, NameResolver, SocketProtocol, UnixSocket,
+};
+use crate::shell::commands::ping::{resolve_host, PingOptions, Pinger};
 
 /**
  * ネットワーク接続をテストするpingコマンドです
  * 
- * 指定されたホストに対してICMPエコー要求をシミュレートし、
- * ネットワーク接続の状態を確認します。
+ * 指定されたホストの名前を解決し、ICMPエコー要求を送信して
+ * 応答までの往復時間を測定します。
  * 
- * ホスト名、パケット数、インターバル、パケットサイズなどの
- * オプションをサポートし、統計情報を含む詳細な出力を提供します。
+ * パケット数、インターバル、パケットサイズ、タイムアウト、
+ * 出力の抑制などのオプションをサポートし、最後にパケット損失率と
+ * RTTの統計情報（min/avg/max/mdev）を出力します。
  * 
- * 現在はシミュレーション実装となっており、実際のネットワーク
- * 通信は行わず、固定の応答時間とパケット統計を返します。
+ * 特権不要のデータグラムICMPソケットを使用し、許可されていない
+ * 場合はrawソケットにフォールバックします。
  */
 pub struct PingCommand;
 
 impl CommandHandler for PingCommand {
+    /**
+     * pingコマンドを実行する関数です
+     *
+     * -c、-i、-s、-W、-qオプションを解析し、ホストを解決してから
+     * エコー要求を送信します。-qが指定されていない場合は
+     * 応答ごとに1行を出力し、最後に統計情報を出力します。
+     *
+     * 応答が1つ以上あれば終了コード0、応答がなければ1、
+     * 名前解決やソケットのエラーでは2を返します。
+     *
+     * @param command - 解析されたコマンド
+     * @param _shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
     fn execute(&self, command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: ping <host>"));
+        let mut options = PingOptions::default();
+        let mut quiet = false;
+        let mut host: Option<String> = None;
+        
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg.len() < 2 {
+                if host.replace(arg.clone()).is_some() {
+                    return Err(anyhow::anyhow!("extra operand '{}'", arg));
+                }
+                continue;
+            }
+            
+            let (flag, attached) = arg[1..].split_at(1);
+            if flag == "q" && attached.is_empty() {
+                quiet = true;
+                continue;
+            }
+            
+            let value = if attached.is_empty() {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("option requires an argument -- '{}'", flag))?
+            } else {
+                attached.to_string()
+            };
+            let invalid = || anyhow::anyhow!("invalid argument: '{}'", value);
+            
+            match flag {
+                "c" => {
+                    options.count = value.parse().map_err(|_| invalid())?;
+                    if options.count == 0 {
+                        return Err(invalid());
+                    }
+                }
+                "i" => options.interval = parse_seconds(&value).ok_or_else(invalid)?,
+                "W" => options.timeout = parse_seconds(&value).ok_or_else(invalid)?,
+                "s" => {
+                    options.size = value.parse().map_err(|_| invalid())?;
+                    if options.size > 65507 {
+                        return Err(anyhow::anyhow!("invalid argument: '{}': out of range: 0 <= value <= 65507", value));
+                    }
+                }
+                _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+            }
         }
         
-        let host = &command.args[0];
-        let count = command.args.iter()
-            .find(|arg| arg.starts_with("-c"))
-            .and_then(|arg| arg.split('=').nth(1))
-            .and_then(|s| s.parse::<u32>().ok())
-            .unwrap_or(4);
+        let host = host.ok_or_else(|| anyhow::anyhow!("usage error: Destination address required"))?;
+        let pinger = resolve_host(&host).and_then(|address| {
+            Pinger::new(address, options.clone()).map(|pinger| (address, pinger))
+        });
+        let (address, pinger) = match pinger {
+            Ok(result) => result,
+            Err(e) => {
+                return Ok(CommandResult {
+                    output: format!("ping: {}\n", e),
+                    exit_code: 2,
+                });
+            }
+        };
         
-        let mut output = String::new();
-        output.push_str(&format!("PING {} ({}): 56 data bytes\n", host, host));
+        let mut output = match address {
+            IpAddr::V4(_) => format!("PING {} ({}) {}({}) bytes of data.\n", host, address, options.size, options.size + 28),
+            IpAddr::V6(_) => format!("PING {}({}) {} data bytes\n", host, address, options.size),
+        };
         
-        for i in 1..=count {
-            output.push_str(&format!("64 bytes from {}: icmp_seq={} time=1.234 ms\n", host, i));
-        }
+        let result = pinger.run(|reply| {
+            if quiet {
+                return;
+            }
+            let ttl = match (reply.ttl, address) {
+                (Some(ttl), IpAddr::V4(_)) => format!(" ttl={}", ttl),
+                (Some(ttl), IpAddr::V6(_)) => format!(" hlim={}", ttl),
+                (None, _) => String::new(),
+            };
+            output.push_str(&format!(
+                "{} bytes from {}: icmp_seq={}{} time={}\n",
+                reply.bytes, address, reply.sequence, ttl, format_rtt(reply.rtt.as_secs_f64() * 1000.0)
+            ));
+        });
+        
+        let statistics = match result {
+            Ok(statistics) => statistics,
+            Err(e) => {
+                output.push_str(&format!("ping: {}\n", e));
+                return Ok(CommandResult { output, exit_code: 2 });
+            }
+        };
         
         output.push_str(&format!("\n--- {} ping statistics ---\n", host));
-        output.push_str(&format!("{} packets transmitted, {} packets received, 0% packet loss\n", count, count));
-        output.push_str("round-trip min/avg/max/stddev = 1.234/1.234/1.234/0.000 ms\n");
+        output.push_str(&format!(
+            "{} packets transmitted, {} received, {}% packet loss, time {}ms\n",
+            statistics.transmitted,
+            statistics.received,
+            format_percent(statistics.loss_percent()),
+            statistics.elapsed.as_millis()
+        ));
+        if let Some((min, average, max, mdev)) = statistics.rtt_summary() {
+            output.push_str(&format!("rtt min/avg/max/mdev = {:.3}/{:.3}/{:.3}/{:.3} ms\n", min, average, max, mdev));
+        }
         
         Ok(CommandResult {
             output,
-            exit_code: 0,
+            exit_code: if statistics.received > 0 { 0 } else { 1 },
         })
     }
     
     fn help(&self) -> &str {
-        "ping [options] <host> - Test network connectivity\n\
+        "ping [options] <host> - Send ICMP echo requests and measure round-trip time\n\
          Options:\n\
-         -c <count>    Number of packets to send\n\
-         -i <interval> Time between packets\n\
-         -s <size>     Packet size"
+         -c <count>    Number of packets to send (default 4)\n\
+         -i <interval> Seconds between packets (default 1)\n\
+         -s <size>     Payload size in bytes (default 56)\n\
+         -W <timeout>  Seconds to wait for replies after the last packet (default 10)\n\
+         -q            Only print the summary"
     }
     
     fn name(&self) -> &str {
@@ -75,55 +183,308 @@ impl CommandHandler for PingCommand {
 }
 
 /**
- * Curl command
+ * Parses a non-negative number of seconds
+ * 
+ * @param value - Seconds, possibly fractional
+ * @return Option<Duration> - Duration, or None if invalid
+ */
+fn parse_seconds(value: &str) -> Option<Duration> {
+    value.parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/**
+ * Formats a round-trip time like iputils ping
+ * 
+ * @param millis - Time in milliseconds
+ * @return String - Time with three significant decimals
+ */
+fn format_rtt(millis: f64) -> String {
+    if millis >= 100.0 {
+        format!("{:.0} ms", millis)
+    } else if millis >= 10.0 {
+        format!("{:.1} ms", millis)
+    } else {
+        format!("{:.3} ms", millis)
+    }
+}
+
+/**
+ * Formats a loss percentage without trailing zeros
+ * 
+ * @param percent - Packet loss in percent
+ * @return String - Formatted percentage
+ */
+fn format_percent(percent: f64) -> String {
+    if percent.fract() == 0.0 {
+        format!("{}", percent as u32)
+    } else {
+        format!("{:.4}", percent).trim_end_matches('0').to_string()
+    }
+}
+
+/**
+ * curlコマンドです
  * 
- * Implements the curl command for transferring data.
- * Supports basic HTTP/HTTPS operations.
+ * 組み込みのHTTP/1.1クライアントを使用してURLにリクエストを
+ * 送信し、応答の本文を出力またはファイルに保存します。
+ * 
+ * メソッド、ヘッダー、リクエスト本文、リダイレクトの追跡、
+ * ヘッダーの表示、--failによる終了コードをサポートします。
  */
 pub struct CurlCommand;
 
 impl CommandHandler for CurlCommand {
-    fn execute(&self, command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: curl <url>"));
+    /**
+     * curlコマンドを実行する関数です
+     *
+     * 短いオプションはまとめて指定でき（-fsSLなど）、値を取る
+     * オプションは直後の文字列または次の引数を値として使用します。
+     * -dの値は`&`で結合され、`@file`はファイルの内容（改行を除去）、
+     * --data-binaryの`@file`はファイルの内容をそのまま送信します。
+     * `@-`はパイプラインの入力を読み込みます。
+     *
+     * エラーはcurlと同じ終了コード（6、7、22、28など）で返し、
+     * -sが指定されている場合は-Sがない限りメッセージを出力しません。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut client = HttpClient::default();
+        let mut method: Option<String> = None;
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut data: Vec<Vec<u8>> = Vec::new();
+        let mut output_file: Option<String> = None;
+        let mut remote_name = false;
+        let mut head_only = false;
+        let mut include = false;
+        let mut silent = false;
+        let mut show_error = false;
+        let mut fail = false;
+        let mut url: Option<String> = None;
+        
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            let (options, attached): (Vec<String>, Option<String>) = if let Some(long) = arg.strip_prefix("--") {
+                match long.split_once('=') {
+                    Some((name, value)) => (vec![name.to_string()], Some(value.to_string())),
+                    None => (vec![long.to_string()], None),
+                }
+            } else if arg.len() > 1 && arg.starts_with('-') {
+                // Short options cluster until one that takes a value
+                let mut options = Vec::new();
+                let mut attached = None;
+                for (index, flag) in arg[1..].char_indices() {
+                    options.push(flag.to_string());
+                    if "XHdoAm".contains(flag) {
+                        let rest = &arg[1 + index + flag.len_utf8()..];
+                        if !rest.is_empty() {
+                            attached = Some(rest.to_string());
+                        }
+                        break;
+                    }
+                }
+                (options, attached)
+            } else {
+                url = Some(arg.clone());
+                continue;
+            };
+            
+            for option in options {
+                let mut value = || -> Result<String> {
+                    match &attached {
+                        Some(value) => Ok(value.clone()),
+                        None => args.next()
+                            .cloned()
+                            .ok_or_else(|| anyhow::anyhow!("option {}: requires parameter", arg)),
+                    }
+                };
+                
+                match option.as_str() {
+                    "X" | "request" => method = Some(value()?),
+                    "H" | "header" => {
+                        let header = value()?;
+                        match header.split_once(':') {
+                            Some((name, value)) => headers.push((name.trim().to_string(), value.trim().to_string())),
+                            None => return Err(anyhow::anyhow!("invalid header '{}'", header)),
+                        }
+                    }
+                    "d" | "data" | "data-ascii" => {
+                        let value = value()?;
+                        let bytes = match value.strip_prefix('@') {
+                            Some(path) => read_curl_data(path, shell)?
+                                .into_iter()
+                                .filter(|byte| *byte != b'\n' && *byte != b'\r')
+                                .collect(),
+                            None => value.into_bytes(),
+                        };
+                        data.push(bytes);
+                    }
+                    "data-raw" => data.push(value()?.into_bytes()),
+                    "data-binary" => {
+                        let value = value()?;
+                        data.push(match value.strip_prefix('@') {
+                            Some(path) => read_curl_data(path, shell)?,
+                            None => value.into_bytes(),
+                        });
+                    }
+                    "o" | "output" => output_file = Some(value()?),
+                    "O" | "remote-name" => remote_name = true,
+                    "L" | "location" => client.follow_redirects = true,
+                    "I" | "head" => head_only = true,
+                    "i" | "include" => include = true,
+                    "s" | "silent" => silent = true,
+                    "S" | "show-error" => show_error = true,
+                    "f" | "fail" => fail = true,
+                    "A" | "user-agent" => client.user_agent = value()?,
+                    "m" | "max-time" => {
+                        let seconds = value()?;
+                        client.timeout = seconds.parse::<f64>()
+                            .ok()
+                            .filter(|s| s.is_finite() && *s > 0.0)
+                            .map(Duration::from_secs_f64)
+                            .ok_or_else(|| anyhow::anyhow!("option --max-time: expected a proper numerical parameter"))?;
+                    }
+                    "max-redirs" => {
+                        client.max_redirects = value()?.parse()
+                            .map_err(|_| anyhow::anyhow!("option --max-redirs: expected a proper numerical parameter"))?;
+                    }
+                    _ => return Err(anyhow::anyhow!("option {}: is unknown", arg)),
+                }
+            }
         }
         
-        let url = &command.args[0];
-        let silent = command.args.iter().any(|arg| arg == "-s" || arg == "--silent");
-        let show_headers = command.args.iter().any(|arg| arg == "-i" || arg == "--include");
+        let report = |code: i32, message: &str| CommandResult {
+            output: if silent && !show_error { String::new() } else { format!("curl: ({}) {}\n", code, message) },
+            exit_code: code,
+        };
         
-        let mut output = String::new();
+        let Some(url) = url else {
+            return Ok(report(2, "no URL specified"));
+        };
+        let url = match Url::parse(&url) {
+            Ok(url) => url,
+            Err(e) => return Ok(report(curl_exit_code(&e), &e.to_string())),
+        };
         
-        if show_headers {
-            output.push_str("HTTP/1.1 200 OK\n");
-            output.push_str("Content-Type: text/html; charset=utf-8\n");
-            output.push_str("Content-Length: 1234\n");
-            output.push_str("Server: nginx/1.18.0\n");
-            output.push_str("Date: Mon, 01 Jan 2024 12:00:00 GMT\n");
-            output.push_str("\n");
+        if remote_name && output_file.is_none() {
+            match url.file_name() {
+                Some(name) => output_file = Some(name),
+                None => return Ok(report(23, "Remote file name has no length")),
+            }
         }
         
-        if !silent {
-            output.push_str(&format!("  % Total    % Received % Xferd  Average Speed   Time    Time     Time  Current\n"));
-            output.push_str(&format!("                                 Dload  Upload   Total   Spent    Left  Speed\n"));
-            output.push_str(&format!("100  1234  100  1234    0     0   1234      0  0:00:01  0:00:01 --:--:--  1234\n"));
+        let body = (!data.is_empty()).then(|| data.join(&b'&'));
+        if body.is_some() && !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Content-Type")) {
+            headers.push(("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string()));
         }
+        let method = method.unwrap_or_else(|| {
+            if head_only {
+                "HEAD".to_string()
+            } else if body.is_some() {
+                "POST".to_string()
+            } else {
+                "GET".to_string()
+            }
+        });
         
-        output.push_str(&format!("<html><body><h1>Response from {}</h1><p>This is a simulated response.</p></body></html>\n", url));
+        let request = HttpRequest { method, url, headers, body };
+        let output_file = output_file.filter(|file| file != "-");
+        let output_path = output_file.as_ref().map(|file| shell.current_path().join(file));
+        let to_terminal = shell.output_is_terminal();
+        let show_headers = include || head_only;
+        let mut streamed = false;
+        let mut meter = ProgressMeter::new(!silent && output_path.is_some());
         
-        Ok(CommandResult {
-            output,
-            exit_code: 0,
-        })
+        // The body goes straight to the file, or to the terminal as raw
+        // bytes; only piped or redirected output is collected as text
+        let result = client.send_to(
+            &request,
+            |earlier, last| {
+                if fail && last.status >= 400 {
+                    return Ok(None);
+                }
+                let mut sink: BodySink = match (&output_file, &output_path) {
+                    (Some(file), Some(path)) => {
+                        let created = File::create(path).map_err(|e| {
+                            anyhow::anyhow!("Failed to open {}: {}", file, describe_error(&e))
+                        })?;
+                        Box::new(BufWriter::new(created))
+                    }
+                    _ if to_terminal => Box::new(std::io::stdout()),
+                    _ => return Ok(None),
+                };
+                if show_headers {
+                    for response in earlier.iter().chain([last]) {
+                        sink.write_all(response.header_block().as_bytes())?;
+                    }
+                }
+                streamed = true;
+                Ok(Some(sink))
+            },
+            |received, total| meter.update(received, total),
+        );
+        meter.finish();
+        let responses = match result {
+            Ok(responses) => responses,
+            Err(e) => return Ok(report(curl_exit_code(&e), &e.to_string())),
+        };
+        let Some(last) = responses.last() else {
+            return Ok(report(8, "No response received"));
+        };
+        
+        if fail && last.status >= 400 {
+            return Ok(report(22, &format!("The requested URL returned error: {}", last.status)));
+        }
+        if streamed {
+            return Ok(CommandResult {
+                output: String::new(),
+                exit_code: 0,
+            });
+        }
+        
+        let mut content: Vec<u8> = Vec::new();
+        if show_headers {
+            for response in &responses {
+                content.extend_from_slice(response.header_block().as_bytes());
+            }
+        }
+        content.extend_from_slice(&last.body);
+        
+        // Builtin pipes carry text, so binary bodies are refused rather than mangled
+        match String::from_utf8(content) {
+            Ok(output) => Ok(CommandResult {
+                output,
+                exit_code: 0,
+            }),
+            Err(_) => Ok(report(23, "Failure writing output to destination: binary output cannot be piped, use -o <file>")),
+        }
     }
     
     fn help(&self) -> &str {
-        "curl [options] <url> - Transfer data from/to servers\n\
+        "curl [options] <url> - Transfer data with the built-in HTTP/1.1 client\n\
          Options:\n\
-         -s, --silent    Silent mode\n\
-         -i, --include   Include headers in output\n\
-         -o <file>       Write output to file\n\
-         -X <method>     HTTP method to use"
+         -X, --request <method>   HTTP method to use\n\
+         -H, --header <header>    Add a request header (Name: value)\n\
+         -d, --data <data>        Send form data (@file reads a file, newlines stripped)\n\
+         --data-raw <data>        Send data without @file handling\n\
+         --data-binary <data>     Send data as-is (@file reads a file)\n\
+         -o, --output <file>      Write the body to a file as it arrives\n\
+         -O, --remote-name        Name the output file after the URL\n\
+         -L, --location           Follow redirects\n\
+         -I, --head               Send HEAD and show headers only\n\
+         -i, --include            Include response headers in the output\n\
+         -s, --silent             Silent mode\n\
+         -S, --show-error         Show errors even in silent mode\n\
+         -f, --fail               Fail with exit code 22 on HTTP errors\n\
+         -A, --user-agent <name>  User-Agent to send\n\
+         -m, --max-time <secs>    Timeout for connecting and reading\n\
+         Only http:// URLs are supported. Binary bodies can be written\n\
+         to a file or the terminal but not piped"
     }
     
     fn name(&self) -> &str {
@@ -132,56 +493,345 @@ impl CommandHandler for CurlCommand {
 }
 
 /**
- * Wget command
+ * Reads the contents for `-d @file` and `--data-binary @file`
+ * 
+ * @param path - File path, or `-` for piped input
+ * @param shell - Shell instance
+ * @return Result<Vec<u8>> - File contents
+ */
+fn read_curl_data(path: &str, shell: &mut Shell) -> Result<Vec<u8>> {
+    if path == "-" {
+        return Ok(shell.take_pipeline_input().unwrap_or_default().into_bytes());
+    }
+    std::fs::read(shell.current_path().join(path))
+        .map_err(|_| anyhow::anyhow!("Failed to open {}", path))
+}
+
+/**
+ * Maps a client error to curl's exit code
+ * 
+ * @param error - Error from the HTTP client
+ * @return i32 - curl exit code
+ */
+fn curl_exit_code(error: &anyhow::Error) -> i32 {
+    match error_kind(error) {
+        Some(HttpErrorKind::UnsupportedProtocol) => 1,
+        Some(HttpErrorKind::MalformedUrl) => 3,
+        Some(HttpErrorKind::Resolve) => 6,
+        Some(HttpErrorKind::Connect) => 7,
+        Some(HttpErrorKind::Timeout) => 28,
+        Some(HttpErrorKind::TooManyRedirects) => 47,
+        Some(HttpErrorKind::Protocol) => 56,
+        Some(HttpErrorKind::Write) => 23,
+        None => 1,
+    }
+}
+
+/**
+ * wgetコマンドです
+ * 
+ * 組み込みのHTTP/1.1クライアントを使用してファイルを
+ * ダウンロードし、wgetと同じ形式の進捗ログを出力します。
  * 
- * Implements the wget command for retrieving files.
- * Supports basic file downloading functionality.
+ * リダイレクトはデフォルトで追跡し、-cによる途中からの
+ * 再開、-Oによる保存先の指定、--post-dataによるPOSTを
+ * サポートします。
  */
 pub struct WgetCommand;
 
 impl CommandHandler for WgetCommand {
+    /**
+     * wgetコマンドを実行する関数です
+     *
+     * 各URLについてリクエストを送信し、リダイレクトごとに
+     * 接続と応答のログを出力します。保存先はURLの最後の
+     * パス要素（なければindex.html）で、同名のファイルが
+     * ある場合は.1、.2のような接尾辞を付けます。
+     *
+     * -cが指定され、ファイルが既にある場合はRangeヘッダーで
+     * 続きを要求し、206応答なら追記します。`-O -`の場合は
+     * 本文だけを出力します。
+     *
+     * 終了コードはwgetと同じく、ネットワークエラーで4、
+     * サーバーエラー応答で8、ファイル書き込みエラーで3です。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: wget <url>"));
-        }
+        let mut client = HttpClient {
+            follow_redirects: true,
+            max_redirects: 20,
+            ..HttpClient::default()
+        };
+        client.user_agent = format!("Wget/1.21 (compatible; {})", client.user_agent);
         
-        let url = &command.args[0];
-        let quiet = command.args.iter().any(|arg| arg == "-q" || arg == "--quiet");
-        let output_file = command.args.iter()
-            .find(|arg| arg.starts_with("-O"))
-            .and_then(|arg| arg.split('=').nth(1))
-            .unwrap_or("index.html");
+        let mut output_document: Option<String> = None;
+        let mut quiet = false;
+        let mut resume = false;
+        let mut server_response = false;
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut body: Option<Vec<u8>> = None;
+        let mut urls: Vec<String> = Vec::new();
         
-        let mut output = String::new();
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                urls.push(arg.clone());
+                continue;
+            }
+            
+            let (name, attached) = match arg.strip_prefix("--") {
+                Some(long) => match long.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (long.to_string(), None),
+                },
+                None => {
+                    let (flag, rest) = arg[1..].split_at(1);
+                    (flag.to_string(), (!rest.is_empty()).then(|| rest.to_string()))
+                }
+            };
+            let mut value = || -> Result<String> {
+                match &attached {
+                    Some(value) => Ok(value.clone()),
+                    None => args.next()
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("option requires an argument -- '{}'", name)),
+                }
+            };
+            
+            match name.as_str() {
+                "O" | "output-document" => output_document = Some(value()?),
+                "q" | "quiet" => quiet = true,
+                "c" | "continue" => resume = true,
+                "S" | "server-response" => server_response = true,
+                "U" | "user-agent" => client.user_agent = value()?,
+                "T" | "timeout" => {
+                    let seconds = value()?;
+                    client.timeout = seconds.parse::<f64>()
+                        .ok()
+                        .filter(|s| s.is_finite() && *s > 0.0)
+                        .map(Duration::from_secs_f64)
+                        .ok_or_else(|| anyhow::anyhow!("--timeout: Invalid time period '{}'", seconds))?;
+                }
+                "header" => {
+                    let header = value()?;
+                    match header.split_once(':') {
+                        Some((name, value)) => headers.push((name.trim().to_string(), value.trim().to_string())),
+                        None => return Err(anyhow::anyhow!("--header: Invalid header '{}'", header)),
+                    }
+                }
+                "post-data" => body = Some(value()?.into_bytes()),
+                "post-file" => {
+                    let path = value()?;
+                    body = Some(std::fs::read(shell.current_path().join(&path))
+                        .map_err(|e| anyhow::anyhow!("{}: {}", path, e))?);
+                }
+                "max-redirect" => {
+                    client.max_redirects = value()?.parse()
+                        .map_err(|_| anyhow::anyhow!("--max-redirect: Invalid number"))?;
+                }
+                _ => return Err(anyhow::anyhow!("invalid option -- '{}'", name)),
+            }
+        }
         
-        if !quiet {
-            output.push_str(&format!("--2024-01-01 12:00:00--  {}\n", url));
-            output.push_str("Resolving example.com... 93.184.216.34\n");
-            output.push_str("Connecting to example.com|93.184.216.34|:80... connected.\n");
-            output.push_str("HTTP request sent, awaiting response... 200 OK\n");
-            output.push_str("Length: 1234 (1.2K) [text/html]\n");
-            output.push_str(&format!("Saving to: '{}'\n", output_file));
-            output.push_str("\n");
-            output.push_str("100%[======================================>] 1,234       --.-K/s   in 0.1s   \n");
-            output.push_str(&format!("\n2024-01-01 12:00:01 (12.3 KB/s) - '{}' saved [1234/1234]\n", output_file));
+        if urls.is_empty() {
+            return Err(anyhow::anyhow!("missing URL"));
         }
+        if body.is_some() && !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Content-Type")) {
+            headers.push(("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string()));
+        }
+        
+        // With -O - the body is the output, so the log is dropped
+        let to_stdout = output_document.as_deref() == Some("-");
+        let quiet = quiet || to_stdout;
         
-        let content = format!("<html><body><h1>Downloaded from {}</h1><p>This is a simulated download.</p></body></html>", url);
-        let file_path = shell.current_path().join(output_file);
-        std::fs::write(file_path, content)?;
+        let mut log = String::new();
+        let mut body_output = String::new();
+        let mut exit_code = 0;
+        
+        for url_text in urls {
+            let url = match Url::parse(&url_text) {
+                Ok(url) => url,
+                Err(e) => {
+                    log.push_str(&format!("{}: {}.\n", url_text, e));
+                    exit_code = 1;
+                    continue;
+                }
+            };
+            
+            let to_terminal = shell.output_is_terminal();
+            let file = match &output_document {
+                Some(document) => document.clone(),
+                None if resume => url.file_name().unwrap_or_else(|| "index.html".to_string()),
+                None => unique_file_name(shell, &url.file_name().unwrap_or_else(|| "index.html".to_string())),
+            };
+            let path = shell.current_path().join(&file);
+            
+            let mut request_headers = headers.clone();
+            let existing = if resume && !to_stdout {
+                std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+            } else {
+                0
+            };
+            if existing > 0 {
+                request_headers.push(("Range".to_string(), format!("bytes={}-", existing)));
+            }
+            
+            let request = HttpRequest {
+                method: if body.is_some() { "POST" } else { "GET" }.to_string(),
+                url: url.clone(),
+                headers: request_headers,
+                body: body.clone(),
+            };
+            
+            log.push_str(&format!("--{}--  {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), url));
+            log.push_str(&format!("Connecting to {}:{}... ", url.host, url.port));
+            
+            let started = Instant::now();
+            let mut streamed = false;
+            let mut meter = ProgressMeter::new(!quiet);
+            
+            // Saved bodies are streamed to the file as they arrive, and
+            // -O - writes raw bytes when stdout is the terminal
+            let result = client.send_to(
+                &request,
+                |_, last| {
+                    if (last.status == 416 && existing > 0) || last.status >= 400 || last.is_redirect() {
+                        return Ok(None);
+                    }
+                    let sink: BodySink = if to_stdout {
+                        if !to_terminal {
+                            return Ok(None);
+                        }
+                        Box::new(std::io::stdout())
+                    } else {
+                        let opened = if last.status == 206 && existing > 0 {
+                            std::fs::OpenOptions::new().append(true).open(&path)
+                        } else {
+                            File::create(&path)
+                        };
+                        let opened = opened.map_err(|e| anyhow::anyhow!("{}: {}", file, describe_error(&e)))?;
+                        Box::new(BufWriter::new(opened))
+                    };
+                    streamed = true;
+                    Ok(Some(sink))
+                },
+                |received, total| meter.update(received, total),
+            );
+            meter.finish();
+            let responses = match result {
+                Ok(responses) => responses,
+                Err(e) => {
+                    let kind = error_kind(&e);
+                    if kind == Some(HttpErrorKind::Write) {
+                        log.push_str(&format!("connected.\n{}\n", e));
+                        exit_code = 3;
+                        continue;
+                    }
+                    if kind == Some(HttpErrorKind::TooManyRedirects) {
+                        log.push_str(&format!("connected.\n{} exceeded.\n", e));
+                    } else {
+                        // The client message names host and port, which the log line already shows
+                        let message = e.to_string();
+                        let reason = message.rsplit(": ").next().unwrap_or(&message);
+                        log.push_str(&format!("failed: {}.\n", reason));
+                    }
+                    exit_code = 4;
+                    continue;
+                }
+            };
+            
+            log.push_str("connected.\n");
+            for (index, response) in responses.iter().enumerate() {
+                log.push_str(&format!("HTTP request sent, awaiting response... {} {}\n", response.status, response.reason));
+                if server_response {
+                    for line in response.header_block().lines().filter(|line| !line.is_empty()) {
+                        log.push_str(&format!("  {}\n", line));
+                    }
+                }
+                if let (Some(next), Some(location)) = (responses.get(index + 1), response.header("Location")) {
+                    log.push_str(&format!("Location: {} [following]\n", location));
+                    log.push_str(&format!("--{}--  {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), next.url));
+                    log.push_str(&format!("Connecting to {}:{}... connected.\n", next.url.host, next.url.port));
+                }
+            }
+            
+            let Some(response) = responses.last() else { continue };
+            if response.status == 416 && existing > 0 {
+                log.push_str("\n    The file is already fully retrieved; nothing to do.\n\n");
+                continue;
+            }
+            if response.status >= 400 {
+                log.push_str(&format!("{} ERROR {}: {}.\n\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), response.status, response.reason));
+                exit_code = 8;
+                continue;
+            }
+            if response.is_redirect() {
+                log.push_str(&format!("{} redirections exceeded.\n", client.max_redirects));
+                exit_code = 8;
+                continue;
+            }
+            
+            let append = response.status == 206 && existing > 0;
+            let received = response.length;
+            let total = if append { existing + received } else { received };
+            let content_type = response.header("Content-Type").map(|t| format!(" [{}]", t)).unwrap_or_default();
+            let length = match response.header("Content-Length").and_then(|l| l.parse::<u64>().ok()) {
+                Some(length) if append => format!("{}{}, {}{} remaining", total, size_suffix(total), length, size_suffix(length)),
+                Some(length) => format!("{}{}", length, size_suffix(length)),
+                None => "unspecified".to_string(),
+            };
+            log.push_str(&format!("Length: {}{}\n", length, content_type));
+            
+            if to_stdout {
+                // Builtin pipes carry text, so binary bodies are refused rather than mangled
+                if !streamed {
+                    match std::str::from_utf8(&response.body) {
+                        Ok(text) => body_output.push_str(text),
+                        Err(_) => {
+                            log.push_str("-: binary output cannot be piped, use -O <file>\n");
+                            exit_code = 3;
+                        }
+                    }
+                }
+                continue;
+            }
+            
+            let elapsed = started.elapsed().as_secs_f64();
+            log.push_str(&format!("Saving to: ‘{}’\n\n", file));
+            log.push_str(&format!(
+                "{} ({}) - ‘{}’ saved [{}/{}]\n\n",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                format_rate(received, elapsed),
+                file,
+                total,
+                total
+            ));
+        }
         
         Ok(CommandResult {
-            output,
-            exit_code: 0,
+            output: if quiet { body_output } else { log },
+            exit_code,
         })
     }
     
     fn help(&self) -> &str {
-        "wget [options] <url> - Retrieve files from the web\n\
+        "wget [options] <url>... - Retrieve files with the built-in HTTP/1.1 client\n\
          Options:\n\
-         -q, --quiet     Quiet mode\n\
-         -O <file>       Write output to file\n\
-         -c, --continue  Continue download if possible"
+         -O, --output-document <file>  Write to file as it arrives (- for stdout)\n\
+         -q, --quiet                   Quiet mode\n\
+         -c, --continue                Resume a partially downloaded file\n\
+         -S, --server-response         Print response headers\n\
+         -T, --timeout <secs>          Network timeout\n\
+         -U, --user-agent <agent>      User-Agent to send\n\
+         --header <header>             Add a request header (Name: value)\n\
+         --post-data <data>            Send a POST request with data\n\
+         --post-file <file>            Send a POST request with a file's contents\n\
+         --max-redirect <n>            Maximum redirects to follow (default 20)\n\
+         Only http:// URLs are supported. With -O -, binary bodies can be\n\
+         written to the terminal but not piped"
     }
     
     fn name(&self) -> &str {
@@ -190,34 +840,190 @@ impl CommandHandler for WgetCommand {
 }
 
 /**
- * Netstat command
+ * Picks a download name that does not overwrite an existing file
  * 
- * Implements the netstat command for network statistics.
- * Shows network connections and routing information.
+ * @param shell - Shell instance
+ * @param name - Preferred name
+ * @return String - name, or name.1, name.2, ... if taken
+ */
+fn unique_file_name(shell: &Shell, name: &str) -> String {
+    let directory = shell.current_path();
+    if !directory.join(name).exists() {
+        return name.to_string();
+    }
+    (1..)
+        .map(|n| format!("{}.{}", name, n))
+        .find(|candidate| !directory.join(candidate).exists())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/**
+ * Formats the human-readable size shown after a byte count
+ * 
+ * @param bytes - Byte count
+ * @return String - " (1.2K)", or empty below 1K
+ */
+fn size_suffix(bytes: u64) -> String {
+    if bytes < 1024 {
+        String::new()
+    } else {
+        format!(" ({})", format_size(bytes))
+    }
+}
+
+/**
+ * Formats a transfer rate like wget
+ * 
+ * @param bytes - Bytes transferred
+ * @param seconds - Elapsed time
+ * @return String - Rate such as "12.3 KB/s"
+ */
+fn format_rate(bytes: u64, seconds: f64) -> String {
+    let rate = bytes as f64 / seconds.max(0.001);
+    if rate >= 1024.0 * 1024.0 {
+        format!("{:.1} MB/s", rate / (1024.0 * 1024.0))
+    } else if rate >= 1024.0 {
+        format!("{:.1} KB/s", rate / 1024.0)
+    } else {
+        format!("{:.0} B/s", rate)
+    }
+}
+
+/**
+ * netstatコマンドです
+ * 
+ * /proc/netのソケットテーブルを読み込み、TCP、UDP、Unixドメイン
+ * ソケットの一覧をnetstatと同じ形式で表示します。
+ * 
+ * -pが指定された場合は/proc/<pid>/fdを走査して、各ソケットを
+ * 保持しているプロセスのPIDと名前を表示します。
  */
 pub struct NetstatCommand;
 
 impl CommandHandler for NetstatCommand {
-    fn execute(&self, command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
-        let show_listening = command.args.iter().any(|arg| arg == "-l" || arg == "--listening");
-        let show_tcp = command.args.iter().any(|arg| arg == "-t" || arg == "--tcp");
-        let show_udp = command.args.iter().any(|arg| arg == "-u" || arg == "--udp");
+    /**
+     * netstatコマンドを実行する関数です
+     *
+     * オプションでプロトコル（-t、-u、-x）を選択し、指定がない
+     * 場合はすべてを表示します。-lは待ち受け中のソケットのみ、
+     * -aはすべてのソケット、どちらもない場合は接続中のソケット
+     * のみを表示します。-nを指定しない場合はポート番号を
+     * /etc/services、アドレスを/etc/hostsで名前に変換します。
+     *
+     * パイプラインに出力する場合は、各ソケットを1行とする
+     * レコードも出力します。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let filter = SocketFilter::parse(&command.args)?;
+        let resolver = if filter.numeric { NameResolver::default() } else { NameResolver::load() };
+        let owners = if filter.processes { socket_owners() } else { HashMap::new() };
+        let owner_label = |inode: u64| match owners.get(&inode) {
+            Some(owner) => format!("{}/{}", owner.pid, owner.name),
+            None => "-".to_string(),
+        };
+        let owner_cells = |inode: u64| match owners.get(&inode) {
+            Some(owner) => vec![owner.pid.into(), owner.name.clone().into()],
+            None => vec![serde_json::Value::Null, serde_json::Value::Null],
+        };
+        let mut records = Table::new(&[
+            "proto", "recv_q", "send_q", "local", "local_port", "foreign", "foreign_port", "state", "inode", "pid", "program", "path",
+        ]);
         
+        let scope = if filter.all {
+            "servers and established"
+        } else if filter.listening {
+            "only servers"
+        } else {
+            "w/o servers"
+        };
         let mut output = String::new();
         
-        if show_listening {
-            output.push_str("Active Internet connections (only servers)\n");
-            output.push_str("Proto Recv-Q Send-Q Local Address           Foreign Address         State\n");
-            output.push_str("tcp        0      0 0.0.0.0:22              0.0.0.0:*               LISTEN\n");
-            output.push_str("tcp        0      0 0.0.0.0:80              0.0.0.0:*               LISTEN\n");
-            output.push_str("tcp        0      0 127.0.0.1:631           0.0.0.0:*               LISTEN\n");
-            output.push_str("tcp6       0      0 :::22                   :::*                    LISTEN\n");
-            output.push_str("tcp6       0      0 :::80                   :::*                    LISTEN\n");
-        } else {
-            output.push_str("Active Internet connections (w/o servers)\n");
-            output.push_str("Proto Recv-Q Send-Q Local Address           Foreign Address         State\n");
-            output.push_str("tcp        0      0 192.168.1.100:12345     93.184.216.34:80       ESTABLISHED\n");
-            output.push_str("tcp        0      0 192.168.1.100:12346     8.8.8.8:53              ESTABLISHED\n");
+        if filter.tcp || filter.udp {
+            output.push_str(&format!("Active Internet connections ({})\n", scope));
+            output.push_str("Proto Recv-Q Send-Q Local Address           Foreign Address         State      ");
+            output.push_str(if filter.processes { " PID/Program name\n" } else { "\n" });
+            
+            for socket in filter.inet_sockets()? {
+                let service_protocol = if socket.protocol.is_tcp() { "tcp" } else { "udp" };
+                let local = resolver.endpoint(socket.local_address, socket.local_port, service_protocol, filter.numeric);
+                let remote = resolver.endpoint(socket.remote_address, socket.remote_port, service_protocol, filter.numeric);
+                let mut line = format!(
+                    "{:<5} {:>6} {:>6} {:<23} {:<23} {:<11}",
+                    socket.protocol.name(),
+                    socket.receive_queue,
+                    socket.send_queue,
+                    local,
+                    remote,
+                    socket.state_name()
+                );
+                if filter.processes {
+                    line.push(' ');
+                    line.push_str(&owner_label(socket.inode));
+                }
+                output.push_str(line.trim_end());
+                output.push('\n');
+                
+                let mut row = vec![
+                    socket.protocol.name().into(),
+                    socket.receive_queue.into(),
+                    socket.send_queue.into(),
+                    local.into(),
+                    socket.local_port.into(),
+                    remote.into(),
+                    socket.remote_port.into(),
+                    socket.state_name().into(),
+                    socket.inode.into(),
+                ];
+                row.extend(owner_cells(socket.inode));
+                records.push_row(row);
+            }
+        }
+        
+        if filter.unix {
+            output.push_str(&format!("Active UNIX domain sockets ({})\n", scope));
+            output.push_str("Proto RefCnt Flags       Type       State         I-Node   ");
+            output.push_str(if filter.processes { "PID/Program name    Path\n" } else { "Path\n" });
+            
+            for socket in filter.unix_sockets()? {
+                let mut line = format!(
+                    "unix  {:<6} {:<11} {:<10} {:<13} {:<8} ",
+                    socket.ref_count,
+                    if socket.accepting { "[ ACC ]" } else { "[ ]" },
+                    socket.type_name(),
+                    socket.state_name(),
+                    socket.inode
+                );
+                if filter.processes {
+                    line.push_str(&format!("{:<19} ", owner_label(socket.inode)));
+                }
+                line.push_str(socket.path.as_deref().unwrap_or(""));
+                output.push_str(line.trim_end());
+                output.push('\n');
+                
+                let none = serde_json::Value::Null;
+                let mut row = vec![
+                    "unix".into(),
+                    none.clone(),
+                    none.clone(),
+                    none.clone(),
+                    none.clone(),
+                    none.clone(),
+                    none,
+                    socket.state_name().into(),
+                    socket.inode.into(),
+                ];
+                row.extend(owner_cells(socket.inode));
+                row.push(socket.path.clone().into());
+                records.push_row(row);
+            }
+        }
+        
+        if !shell.output_is_terminal() {
+            shell.emit_records(records);
         }
         
         Ok(CommandResult {
@@ -227,15 +1033,267 @@ impl CommandHandler for NetstatCommand {
     }
     
     fn help(&self) -> &str {
-        "netstat [options] - Network statistics\n\
+        "netstat [options] - Show network connections from /proc/net\n\
          Options:\n\
-         -l, --listening  Show listening sockets\n\
-         -t, --tcp        Show TCP connections\n\
-         -u, --udp        Show UDP connections\n\
-         -n, --numeric    Show numeric addresses"
+         -t, --tcp        Show TCP sockets\n\
+         -u, --udp        Show UDP sockets\n\
+         -x, --unix       Show Unix domain sockets\n\
+         -l, --listening  Show only listening sockets\n\
+         -a, --all        Show listening and connected sockets\n\
+         -n, --numeric    Show numeric addresses and ports\n\
+         -p, --programs   Show the PID and name of the owning process"
     }
     
     fn name(&self) -> &str {
         "netstat"
     }
-} 
\ No newline at end of file
+}
+
+/**
+ * ssコマンドです
+ * 
+ * netstatと同じ/proc/netのソケット情報を、ssの列形式
+ * （Netid、State、Recv-Q、Send-Q、アドレス、Process）で表示します。
+ */
+pub struct SsCommand;
+
+impl CommandHandler for SsCommand {
+    /**
+     * ssコマンドを実行する関数です
+     *
+     * netstatと同じオプションでソケットを選択し、列幅を内容に
+     * 合わせて表示します。IPv6アドレスは角括弧で囲み、
+     * -pが指定された場合はusers:(("名前",pid=N,fd=M))の形式で
+     * 所有プロセスを表示します。
+     *
+     * @param command - 解析されたコマンド
+     * @param _shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
+    fn execute(&self, command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
+        let filter = SocketFilter::parse(&command.args)?;
+        let resolver = if filter.numeric { NameResolver::default() } else { NameResolver::load() };
+        let owners = if filter.processes { socket_owners() } else { HashMap::new() };
+        let process = |inode: u64| match owners.get(&inode) {
+            Some(owner) => format!("users:((\"{}\",pid={},fd={}))", owner.name, owner.pid, owner.fd),
+            None => String::new(),
+        };
+        
+        let mut rows: Vec<Vec<String>> = vec![
+            ["Netid", "State", "Recv-Q", "Send-Q", "Local Address:Port", "Peer Address:Port", "Process"]
+                .iter()
+                .map(|heading| heading.to_string())
+                .collect(),
+        ];
+        
+        if filter.tcp || filter.udp {
+            for socket in filter.inet_sockets()? {
+                let service_protocol = if socket.protocol.is_tcp() { "tcp" } else { "udp" };
+                let endpoint = |address: IpAddr, port: u16| {
+                    let text = resolver.endpoint(address, port, service_protocol, filter.numeric);
+                    match (address, text.rsplit_once(':')) {
+                        (IpAddr::V6(_), Some((host, port))) => format!("[{}]:{}", host, port),
+                        _ => text,
+                    }
+                };
+                let state = match socket.state_name() {
+                    "" => "UNCONN".to_string(),
+                    "ESTABLISHED" => "ESTAB".to_string(),
+                    other => other.replace('_', "-"),
+                };
+                rows.push(vec![
+                    service_protocol.to_string(),
+                    state,
+                    socket.receive_queue.to_string(),
+                    socket.send_queue.to_string(),
+                    endpoint(socket.local_address, socket.local_port),
+                    endpoint(socket.remote_address, socket.remote_port),
+                    process(socket.inode),
+                ]);
+            }
+        }
+        
+        if filter.unix {
+            for socket in filter.unix_sockets()? {
+                let netid = match socket.socket_type {
+                    2 => "u_dgr",
+                    5 => "u_seq",
+                    _ => "u_str",
+                };
+                let state = match socket.state_name() {
+                    "LISTENING" => "LISTEN",
+                    "CONNECTED" => "ESTAB",
+                    "" => "UNCONN",
+                    other => other,
+                };
+                rows.push(vec![
+                    netid.to_string(),
+                    state.to_string(),
+                    "0".to_string(),
+                    "0".to_string(),
+                    format!("{} {}", socket.path.as_deref().unwrap_or("*"), socket.inode),
+                    "* 0".to_string(),
+                    process(socket.inode),
+                ]);
+            }
+        }
+        
+        Ok(CommandResult {
+            output: render_columns(&rows),
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "ss [options] - Show socket statistics from /proc/net\n\
+         Options:\n\
+         -t, --tcp        Show TCP sockets\n\
+         -u, --udp        Show UDP sockets\n\
+         -x, --unix       Show Unix domain sockets\n\
+         -l, --listening  Show only listening sockets\n\
+         -a, --all        Show listening and connected sockets\n\
+         -n, --numeric    Do not resolve service names\n\
+         -p, --processes  Show the process using each socket"
+    }
+    
+    fn name(&self) -> &str {
+        "ss"
+    }
+}
+
+/**
+ * Socket selection shared by netstat and ss
+ */
+#[derive(Debug, Default)]
+struct SocketFilter {
+    /// Include TCP sockets
+    tcp: bool,
+    /// Include UDP sockets
+    udp: bool,
+    /// Include Unix domain sockets
+    unix: bool,
+    /// Only listening sockets
+    listening: bool,
+    /// Listening and connected sockets
+    all: bool,
+    /// Skip name lookups
+    numeric: bool,
+    /// Show owning processes
+    processes: bool,
+}
+
+impl SocketFilter {
+    /**
+     * Parses netstat/ss options; no protocol flag selects all
+     * 
+     * @param args - Command arguments
+     * @return Result<SocketFilter> - Filter or an error for unknown options
+     */
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut filter = SocketFilter::default();
+        
+        for arg in args {
+            let flags: Vec<char> = match arg.strip_prefix("--") {
+                Some(long) => vec![match long {
+                    "tcp" => 't',
+                    "udp" => 'u',
+                    "unix" => 'x',
+                    "listening" => 'l',
+                    "all" => 'a',
+                    "numeric" => 'n',
+                    "programs" | "processes" => 'p',
+                    _ => return Err(anyhow::anyhow!("unrecognized option '{}'", arg)),
+                }],
+                None => match arg.strip_prefix('-') {
+                    Some(short) if !short.is_empty() => short.chars().collect(),
+                    _ => return Err(anyhow::anyhow!("unexpected argument '{}'", arg)),
+                },
+            };
+            
+            for flag in flags {
+                match flag {
+                    't' => filter.tcp = true,
+                    'u' => filter.udp = true,
+                    'x' => filter.unix = true,
+                    'l' => filter.listening = true,
+                    'a' => filter.all = true,
+                    'n' => filter.numeric = true,
+                    'p' => filter.processes = true,
+                    _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+                }
+            }
+        }
+        
+        if !filter.tcp && !filter.udp && !filter.unix {
+            filter.tcp = true;
+            filter.udp = true;
+            filter.unix = true;
+        }
+        Ok(filter)
+    }
+    
+    /**
+     * Checks whether a socket's listening state is selected
+     * 
+     * @param listening - Whether the socket is listening
+     * @return bool - True if it should be shown
+     */
+    fn wants(&self, listening: bool) -> bool {
+        self.all || listening == self.listening
+    }
+    
+    /**
+     * Reads the selected Internet sockets
+     * 
+     * @return Result<Vec<InetSocket>> - Sockets in tcp, tcp6, udp, udp6 order
+     */
+    fn inet_sockets(&self) -> Result<Vec<InetSocket>> {
+        let mut protocols = Vec::new();
+        if self.tcp {
+            protocols.extend([SocketProtocol::Tcp, SocketProtocol::Tcp6]);
+        }
+        if self.udp {
+            protocols.extend([SocketProtocol::Udp, SocketProtocol::Udp6]);
+        }
+        
+        let mut sockets = Vec::new();
+        for protocol in protocols {
+            sockets.extend(read_inet_sockets(protocol)?.into_iter().filter(|s| self.wants(s.is_listening())));
+        }
+        Ok(sockets)
+    }
+    
+    /**
+     * Reads the selected Unix domain sockets
+     * 
+     * @return Result<Vec<UnixSocket>> - Sockets
+     */
+    fn unix_sockets(&self) -> Result<Vec<UnixSocket>> {
+        Ok(read_unix_sockets()?.into_iter().filter(|s| self.wants(s.accepting)).collect())
+    }
+}
+
+/**
+ * Renders rows as left-aligned columns
+ * 
+ * @param rows - Rows of cells, the first being the heading
+ * @return String - Table text; trailing blanks are trimmed
+ */
+fn render_columns(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| rows.iter().filter_map(|row| row.get(column)).map(|cell| cell.chars().count()).max().unwrap_or(0))
+        .collect();
+    
+    let mut output = String::new();
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(column, cell)| format!("{:<width$}", cell, width = widths[column]))
+            .collect();
+        output.push_str(line.join(" ").trim_end());
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,428 @@
+/*!
+ * @file npm.rs
+ * @brief package.json scripts and npm, yarn and pnpm workspaces
+ *
+ * This module reads package.json directly to list and run scripts the
+ * way `npm run` does: pre and post hooks, node_modules/.bin on PATH
+ * and the npm_* environment variables, without starting npm itself or
+ * touching the network. Workspaces declared in package.json
+ * ("workspaces", as used by npm and yarn) or in pnpm-workspace.yaml
+ * are expanded so a script can be run in any member package.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file npm.rs
+ * @description Package and workspace discovery, script lookup and the
+ * script runner with its lifecycle hooks and environment.
+ */
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::shell::commands::cargo::Printer;
+use crate::shell::commands::diagnostics::{self, DiagnosticLinker};
+use crate::shell::commands::records::Table;
+
+/// Scripts npm runs as part of its own commands rather than through `npm run`
+const LIFECYCLE_SCRIPTS: [&str; 13] = [
+    "preinstall", "install", "postinstall", "prepare", "prepublishOnly", "prepack", "postpack",
+    "prestart", "start", "poststart", "test", "stop", "restart",
+];
+
+/**
+ * Package described by a package.json file
+ */
+#[derive(Debug, Clone)]
+pub struct Package {
+    /// Directory containing package.json
+    pub directory: PathBuf,
+    /// Package name
+    pub name: Option<String>,
+    /// Package version
+    pub version: Option<String>,
+    /// Scripts as name and command
+    pub scripts: Vec<(String, String)>,
+    /// Workspace patterns declared by the package
+    pub workspaces: Vec<String>,
+}
+
+impl Package {
+    /**
+     * Finds the nearest directory with a package.json
+     *
+     * @param directory - Directory to start from
+     * @return Option<PathBuf> - Package directory
+     */
+    pub fn find(directory: &Path) -> Option<PathBuf> {
+        directory.ancestors().find(|ancestor| ancestor.join("package.json").is_file()).map(Path::to_path_buf)
+    }
+
+    /**
+     * Reads the package.json of a directory
+     *
+     * @param directory - Package directory
+     * @return Result<Package> - Package, or a read or JSON error
+     */
+    pub fn load(directory: &Path) -> Result<Package> {
+        let path = directory.join("package.json");
+        let text = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        let manifest: Value = serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+
+        let scripts = manifest["scripts"]
+            .as_object()
+            .map(|scripts| {
+                scripts
+                    .iter()
+                    .filter_map(|(name, command)| command.as_str().map(|command| (name.clone(), command.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let workspaces = match &manifest["workspaces"] {
+            Value::Array(patterns) => patterns.as_slice(),
+            Value::Object(config) => config.get("packages").and_then(Value::as_array).map_or(&[][..], Vec::as_slice),
+            _ => &[],
+        };
+
+        Ok(Package {
+            directory: directory.to_path_buf(),
+            name: manifest["name"].as_str().map(str::to_string),
+            version: manifest["version"].as_str().map(str::to_string),
+            scripts,
+            workspaces: workspaces.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+        })
+    }
+
+    /**
+     * Looks up a script
+     *
+     * @param name - Script name
+     * @return Option<&str> - Command of the script
+     */
+    pub fn script(&self, name: &str) -> Option<&str> {
+        self.scripts.iter().find(|(script, _)| script == name).map(|(_, command)| command.as_str())
+    }
+
+    /**
+     * Gets the name shown in script headers, like npm's name@version
+     *
+     * @return String - name@version, the name, or the directory name
+     */
+    pub fn label(&self) -> String {
+        match (&self.name, &self.version) {
+            (Some(name), Some(version)) => format!("{}@{}", name, version),
+            (Some(name), None) => name.clone(),
+            _ => self.directory.file_name().map_or_else(|| self.directory.display().to_string(), |name| name.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+/**
+ * Monorepo root with its member packages
+ */
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Workspace root
+    pub root: PathBuf,
+    /// Member packages, sorted by directory
+    pub packages: Vec<Package>,
+}
+
+impl Workspace {
+    /**
+     * ディレクトリを含むワークスペースを探す
+     *
+     * 祖先ディレクトリを近い順に調べ、package.json に "workspaces"
+     * （npm と yarn の配列形式、または yarn の { packages: [...] } 形式）
+     * があるか、pnpm-workspace.yaml があるディレクトリをルートとします。
+     * パターンを glob で展開し、package.json を持つディレクトリを
+     * メンバーにします。「!」で始まるパターンは除外として扱います。
+     *
+     * @param directory - ワークスペース内のディレクトリ
+     * @return Option<Workspace> - ワークスペース（見つからなければ None）
+     */
+    pub fn find(directory: &Path) -> Option<Workspace> {
+        for ancestor in directory.ancestors() {
+            let pnpm = std::fs::read_to_string(ancestor.join("pnpm-workspace.yaml")).ok();
+            let patterns = match pnpm {
+                Some(text) => pnpm_patterns(&text),
+                None => match Package::load(ancestor) {
+                    Ok(package) if !package.workspaces.is_empty() => package.workspaces,
+                    _ => continue,
+                },
+            };
+
+            let (excluded, included): (Vec<&String>, Vec<&String>) = patterns.iter().partition(|pattern| pattern.starts_with('!'));
+            let excluded: Vec<glob::Pattern> = excluded
+                .iter()
+                .filter_map(|pattern| glob::Pattern::new(ancestor.join(pattern.trim_start_matches('!')).to_string_lossy().trim_end_matches('/')).ok())
+                .collect();
+            let mut directories: Vec<PathBuf> = included
+                .iter()
+                .filter_map(|pattern| glob::glob(&ancestor.join(pattern).to_string_lossy()).ok())
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .filter(|path| path.join("package.json").is_file() && !path.components().any(|part| part.as_os_str() == "node_modules"))
+                .filter(|path| !excluded.iter().any(|pattern| pattern.matches_path(path)))
+                .collect();
+            directories.sort();
+            directories.dedup();
+
+            return Some(Workspace {
+                root: ancestor.to_path_buf(),
+                packages: directories.iter().filter_map(|directory| Package::load(directory).ok()).collect(),
+            });
+        }
+        None
+    }
+
+    /**
+     * Selects a member package the way npm's --workspace does
+     *
+     * A directory selects the package containing it, so `-w .` works
+     * from anywhere inside a package.
+     *
+     * @param selector - Package name, or a directory relative to the current directory or the root
+     * @param current - Current directory
+     * @return Result<&Package> - Selected package
+     */
+    pub fn select(&self, selector: &str, current: &Path) -> Result<&Package> {
+        if let Some(package) = self.packages.iter().find(|package| package.name.as_deref() == Some(selector)) {
+            return Ok(package);
+        }
+
+        let candidates: Vec<PathBuf> = [current.join(selector), self.root.join(selector)]
+            .iter()
+            .filter_map(|candidate| candidate.canonicalize().ok())
+            .collect();
+        self.packages
+            .iter()
+            .filter_map(|package| package.directory.canonicalize().ok().map(|directory| (package, directory)))
+            .filter(|(_, directory)| candidates.iter().any(|candidate| candidate.starts_with(directory)))
+            .max_by_key(|(_, directory)| directory.components().count())
+            .map(|(package, _)| package)
+            .ok_or_else(|| anyhow::anyhow!("No workspace found: --workspace={}", selector))
+    }
+}
+
+/**
+ * Reads the package patterns of a pnpm-workspace.yaml file
+ *
+ * Only the `packages:` list is needed, so this handles the block
+ * sequence form pnpm documents rather than general YAML.
+ *
+ * @param text - File contents
+ * @return Vec<String> - Patterns
+ */
+fn pnpm_patterns(text: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in text.lines() {
+        let content = line.split(" #").next().unwrap_or("").trim_end();
+        if content.trim().is_empty() || content.trim_start().starts_with('#') {
+            continue;
+        }
+        if !content.starts_with(' ') && !content.starts_with('-') {
+            in_packages = content.trim() == "packages:";
+            continue;
+        }
+        if let Some(item) = content.trim().strip_prefix('-').filter(|_| in_packages) {
+            patterns.push(item.trim().trim_matches(|c| c == '\'' || c == '"').to_string());
+        }
+    }
+    patterns
+}
+
+/**
+ * Options of a script run
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Arguments appended to the script's command
+    pub args: Vec<String>,
+    /// npm configuration from unknown --options, exported as npm_config_*
+    pub config: Vec<(String, String)>,
+    /// Succeed silently when the script is missing
+    pub if_present: bool,
+    /// Skip pre and post hooks
+    pub ignore_scripts: bool,
+    /// Omit the "> package script" headers
+    pub silent: bool,
+}
+
+/**
+ * Quotes an argument for sh when it contains special characters
+ *
+ * @param arg - Argument
+ * @return String - Argument safe to append to a command line
+ */
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/**
+ * Builds the PATH a script runs with
+ *
+ * Like npm, every node_modules/.bin from the package directory up to
+ * the filesystem root comes first, so hoisted workspace binaries are
+ * found too.
+ *
+ * @param directory - Package directory
+ * @return String - PATH value
+ */
+fn script_path(directory: &Path) -> String {
+    let mut entries: Vec<PathBuf> = directory
+        .ancestors()
+        .map(|ancestor| ancestor.join("node_modules").join(".bin"))
+        .filter(|bin| bin.is_dir())
+        .collect();
+    if let Some(path) = std::env::var_os("PATH") {
+        entries.extend(std::env::split_paths(&path));
+    }
+    std::env::join_paths(entries).map_or_else(|_| std::env::var("PATH").unwrap_or_default(), |path| path.to_string_lossy().into_owned())
+}
+
+/**
+ * スクリプトをフックと一緒に実行する
+ *
+ * npm run と同じく pre<名前>、<名前>、post<名前> の順に、存在する
+ * ものだけを sh -c で実行します（--ignore-scripts ではフックを
+ * 省略）。引数は本体のスクリプトにだけ付け加えます。作業ディレクトリは
+ * パッケージのディレクトリで、PATH の先頭に node_modules/.bin を追加し、
+ * npm_lifecycle_event、npm_package_name などの変数を設定します。
+ * 失敗したスクリプトがあればそこで止めます。
+ *
+ * 出力は DiagnosticLinker を通すので、tsc などのエラー位置は
+ * 端末上でリンクになります。
+ *
+ * @param package - スクリプトを持つパッケージ
+ * @param script - スクリプト名
+ * @param options - 引数とオプション
+ * @param current - シェルのカレントディレクトリ（INIT_CWD）
+ * @param printer - 出力先
+ * @return Result<i32> - 終了コード、またはスクリプトが存在しない場合のエラー
+ */
+pub fn run_script(package: &Package, script: &str, options: &RunOptions, current: &Path, printer: &mut Printer) -> Result<i32> {
+    let command = match (package.script(script), script) {
+        (Some(command), _) => command.to_string(),
+        (None, "start") if package.directory.join("server.js").is_file() => "node server.js".to_string(),
+        (None, _) if options.if_present => return Ok(0),
+        (None, _) => return Err(anyhow::anyhow!("Missing script: \"{}\" in {}", script, package.label())),
+    };
+
+    let pre = format!("pre{}", script);
+    let post = format!("post{}", script);
+    let mut events: Vec<(&str, String)> = Vec::new();
+    if !options.ignore_scripts {
+        if let Some(hook) = package.script(&pre) {
+            events.push((&pre, hook.to_string()));
+        }
+    }
+    let args: Vec<String> = options.args.iter().map(|arg| shell_quote(arg)).collect();
+    events.push((script, if args.is_empty() { command } else { format!("{} {}", command, args.join(" ")) }));
+    if !options.ignore_scripts {
+        if let Some(hook) = package.script(&post) {
+            events.push((&post, hook.to_string()));
+        }
+    }
+
+    let path = script_path(&package.directory);
+    for (event, line) in events {
+        if !options.silent {
+            printer.print(&format!("\n{}\n{}\n\n", printer.paint("2", &format!("> {} {}", package.label(), event)), printer.paint("2", &format!("> {}", line))));
+        }
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&line)
+            .current_dir(&package.directory)
+            .env("PATH", &path)
+            .env("INIT_CWD", current)
+            .env("npm_lifecycle_event", event)
+            .env("npm_lifecycle_script", &line)
+            .env("npm_command", "run-script")
+            .env("npm_package_json", package.directory.join("package.json"));
+        if let Some(name) = &package.name {
+            command.env("npm_package_name", name);
+        }
+        if let Some(version) = &package.version {
+            command.env("npm_package_version", version);
+        }
+        for (name, value) in &options.config {
+            command.env(format!("npm_config_{}", name.replace('-', "_")), value);
+        }
+
+        let mut linker = DiagnosticLinker::new(&package.directory);
+        let result = diagnostics::run_build(&mut command, &mut linker, printer.terminal)?;
+        printer.print(&result.output);
+        if result.exit_code != 0 {
+            return Ok(result.exit_code);
+        }
+    }
+    Ok(0)
+}
+
+/**
+ * Table of scripts for records
+ *
+ * @param packages - Packages whose scripts are listed
+ * @return Table - package, directory, script, command and lifecycle columns
+ */
+pub fn script_table(packages: &[&Package]) -> Table {
+    let mut table = Table::new(&["package", "directory", "script", "command", "lifecycle"]);
+    for package in packages {
+        for (name, command) in &package.scripts {
+            table.push_row(vec![
+                json!(package.label()),
+                json!(package.directory.display().to_string()),
+                json!(name),
+                json!(command),
+                json!(LIFECYCLE_SCRIPTS.contains(&name.as_str())),
+            ]);
+        }
+    }
+    table
+}
+
+/**
+ * Renders the scripts of a package like `npm run` without arguments
+ *
+ * @param package - Package
+ * @param printer - Printer whose colors are used
+ * @return String - Lifecycle scripts followed by the other scripts
+ */
+pub fn render_scripts(package: &Package, printer: &Printer) -> String {
+    let is_lifecycle = |name: &String| LIFECYCLE_SCRIPTS.contains(&name.as_str());
+    let lifecycle: Vec<_> = package.scripts.iter().filter(|(name, _)| is_lifecycle(name)).collect();
+    let other: Vec<_> = package.scripts.iter().filter(|(name, _)| !is_lifecycle(name)).collect();
+
+    // Like npm, the second heading names the package when it is the only one
+    let other_title = if lifecycle.is_empty() {
+        format!("Scripts available in {} via `npm run`:", package.label())
+    } else {
+        "available via `npm run`:".to_string()
+    };
+    let mut output = String::new();
+    for (title, scripts) in [(format!("Lifecycle scripts included in {}:", package.label()), lifecycle), (other_title, other)] {
+        if scripts.is_empty() {
+            continue;
+        }
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("{}\n", printer.paint("1", &title)));
+        for (name, command) in scripts {
+            output.push_str(&format!("  {}\n    {}\n", name, printer.paint("2", command)));
+        }
+    }
+    if output.is_empty() {
+        output.push_str(&format!("No scripts in {}\n", package.label()));
+    }
+    output
+}
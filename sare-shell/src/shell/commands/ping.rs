@@ -0,0 +1,517 @@
+/*!
+ * @file ping.rs
+ * @brief ICMP echo prober
+ *
+ * This module sends ICMP echo requests and measures the round trip
+ * of the replies. It is the engine behind the `ping` builtin.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file ping.rs
+ * @description ICMP and ICMPv6 echo over unprivileged datagram sockets
+ * with a raw socket fallback, TTL reporting and RTT statistics.
+ */
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{Duration, Instant};
+
+/// ICMP echo request type
+const ICMP_ECHO_REQUEST: u8 = 8;
+/// ICMP echo reply type
+const ICMP_ECHO_REPLY: u8 = 0;
+/// ICMPv6 echo request type
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+/// ICMPv6 echo reply type
+const ICMPV6_ECHO_REPLY: u8 = 129;
+/// Length of the ICMP echo header
+const ICMP_HEADER_LENGTH: usize = 8;
+
+/**
+ * Options controlling a ping run
+ */
+#[derive(Debug, Clone)]
+pub struct PingOptions {
+    /// Number of echo requests to send
+    pub count: u32,
+    /// Delay between requests
+    pub interval: Duration,
+    /// Payload size in bytes
+    pub size: usize,
+    /// How long to wait for replies after the last request
+    pub timeout: Duration,
+}
+
+impl Default for PingOptions {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            interval: Duration::from_secs(1),
+            size: 56,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/**
+ * Echo reply received from the target
+ */
+#[derive(Debug, Clone)]
+pub struct PingReply {
+    /// Size of the ICMP message in bytes
+    pub bytes: usize,
+    /// Sequence number of the request it answers
+    pub sequence: u16,
+    /// TTL or hop limit of the reply, if the kernel reported it
+    pub ttl: Option<u8>,
+    /// Measured round-trip time
+    pub rtt: Duration,
+}
+
+/**
+ * Statistics of a completed ping run
+ */
+#[derive(Debug, Clone, Default)]
+pub struct PingStatistics {
+    /// Requests sent
+    pub transmitted: u32,
+    /// Distinct replies received
+    pub received: u32,
+    /// Round-trip times of the replies
+    pub rtts: Vec<Duration>,
+    /// Time from the first request to the end of the run
+    pub elapsed: Duration,
+}
+
+impl PingStatistics {
+    /**
+     * Computes the packet loss percentage
+     *
+     * @return f64 - Lost requests in percent
+     */
+    pub fn loss_percent(&self) -> f64 {
+        if self.transmitted == 0 {
+            return 0.0;
+        }
+        (self.transmitted - self.received) as f64 * 100.0 / self.transmitted as f64
+    }
+
+    /**
+     * Computes min/avg/max/mdev of the round-trip times
+     *
+     * mdev is the standard deviation, as reported by iputils ping.
+     *
+     * @return Option<(f64, f64, f64, f64)> - Values in milliseconds, None without replies
+     */
+    pub fn rtt_summary(&self) -> Option<(f64, f64, f64, f64)> {
+        if self.rtts.is_empty() {
+            return None;
+        }
+
+        let millis: Vec<f64> = self.rtts.iter().map(|rtt| rtt.as_secs_f64() * 1000.0).collect();
+        let count = millis.len() as f64;
+        let min = millis.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = millis.iter().copied().fold(0.0, f64::max);
+        let average = millis.iter().sum::<f64>() / count;
+        let mean_square = millis.iter().map(|m| m * m).sum::<f64>() / count;
+        let mdev = (mean_square - average * average).max(0.0).sqrt();
+
+        Some((min, average, max, mdev))
+    }
+}
+
+/**
+ * Resolves a host name or address literal
+ *
+ * IPv4 addresses are preferred when a name has both families.
+ *
+ * @param host - Host name or IP address
+ * @return Result<IpAddr> - Resolved address
+ */
+pub fn resolve_host(host: &str) -> Result<IpAddr> {
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return Ok(address);
+    }
+
+    let addresses: Vec<IpAddr> = (host, 0)
+        .to_socket_addrs()
+        .map_err(|_| anyhow::anyhow!("{}: Name or service not known", host))?
+        .map(|address| address.ip())
+        .collect();
+
+    addresses
+        .iter()
+        .find(|address| address.is_ipv4())
+        .or_else(|| addresses.first())
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("{}: Name or service not known", host))
+}
+
+/**
+ * Socket that sends ICMP echo requests to one address
+ */
+pub struct IcmpSocket {
+    /// Socket descriptor
+    fd: OwnedFd,
+    /// Target address
+    address: IpAddr,
+    /// Whether this is a raw socket (needs IP header stripping and ID filtering)
+    raw: bool,
+    /// Echo identifier used on raw sockets
+    identifier: u16,
+}
+
+impl IcmpSocket {
+    /**
+     * ICMPソケットを開く関数です
+     *
+     * まず特権不要のデータグラムICMPソケットを作成し、
+     * net.ipv4.ping_group_rangeで許可されていない場合は
+     * rawソケットにフォールバックします。どちらも作成できない
+     * 場合は、必要な権限を説明するエラーを返します。
+     *
+     * 受信したTTL（IPv6ではホップリミット）を取得するため、
+     * 補助データの受信を有効にします。
+     *
+     * @param address - 送信先アドレス
+     * @return Result<IcmpSocket> - 開かれたソケットまたはエラー
+     */
+    pub fn open(address: IpAddr) -> Result<Self> {
+        let (domain, protocol) = match address {
+            IpAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_ICMP),
+            IpAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_ICMPV6),
+        };
+
+        let mut raw = false;
+        let mut fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, protocol) };
+        if fd < 0 {
+            let datagram_error = std::io::Error::last_os_error();
+            fd = unsafe { libc::socket(domain, libc::SOCK_RAW | libc::SOCK_CLOEXEC, protocol) };
+            if fd < 0 {
+                let raw_error = std::io::Error::last_os_error();
+                return Err(match raw_error.raw_os_error() {
+                    Some(libc::EPERM) | Some(libc::EACCES) => anyhow::anyhow!(
+                        "socket: {} (datagram ICMP sockets are not allowed for this group by \
+                         net.ipv4.ping_group_range, and raw sockets need CAP_NET_RAW)",
+                        datagram_error
+                    ),
+                    _ => anyhow::anyhow!("socket: {}", raw_error),
+                });
+            }
+            raw = true;
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let enable: libc::c_int = 1;
+        let (level, option) = match address {
+            IpAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_RECVTTL),
+            IpAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT),
+        };
+        unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                level,
+                option,
+                &enable as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            );
+        }
+
+        Ok(Self {
+            fd,
+            address,
+            raw,
+            identifier: (std::process::id() & 0xffff) as u16,
+        })
+    }
+
+    /**
+     * Sends one echo request
+     *
+     * @param sequence - Sequence number
+     * @param payload - Data carried after the header
+     * @return Result<()> - Success or send error
+     */
+    pub fn send_echo(&self, sequence: u16, payload: &[u8]) -> Result<()> {
+        let request_type = match self.address {
+            IpAddr::V4(_) => ICMP_ECHO_REQUEST,
+            IpAddr::V6(_) => ICMPV6_ECHO_REQUEST,
+        };
+
+        let mut packet = Vec::with_capacity(ICMP_HEADER_LENGTH + payload.len());
+        packet.extend_from_slice(&[request_type, 0, 0, 0]);
+        packet.extend_from_slice(&self.identifier.to_be_bytes());
+        packet.extend_from_slice(&sequence.to_be_bytes());
+        packet.extend_from_slice(payload);
+
+        // The kernel fills in the ICMPv6 checksum itself
+        if self.address.is_ipv4() {
+            let checksum = internet_checksum(&packet);
+            packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+        }
+
+        let (storage, length) = socket_address(self.address);
+        let sent = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                packet.as_ptr() as *const libc::c_void,
+                packet.len(),
+                0,
+                &storage as *const libc::sockaddr_storage as *const libc::sockaddr,
+                length,
+            )
+        };
+        if sent < 0 {
+            return Err(anyhow::anyhow!("sendmsg: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    /**
+     * エコー応答を受信する関数です
+     *
+     * 指定された時間までpollで待機し、recvmsgで応答と
+     * TTLの補助データを受信します。rawソケットの場合は
+     * IPv4ヘッダーを取り除き、自分の識別子を持つ応答だけを
+     * 受け付けます。エコー応答以外のメッセージは無視して
+     * 待機を続けます。
+     *
+     * @param timeout - 最大待ち時間
+     * @return Result<Option<(u16, usize, Option<u8>)>> - シーケンス番号、バイト数、TTL、またはタイムアウト時None
+     */
+    pub fn receive(&self, timeout: Duration) -> Result<Option<(u16, usize, Option<u8>)>> {
+        let deadline = Instant::now() + timeout;
+        let reply_type = match self.address {
+            IpAddr::V4(_) => ICMP_ECHO_REPLY,
+            IpAddr::V6(_) => ICMPV6_ECHO_REPLY,
+        };
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut poll_fd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, remaining.as_millis().min(i32::MAX as u128) as libc::c_int) };
+            if ready < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(anyhow::anyhow!("poll: {}", error));
+            }
+            if ready == 0 {
+                return Ok(None);
+            }
+
+            let mut buffer = [0u8; 65536];
+            let mut control = [0u8; 128];
+            let mut iov = libc::iovec {
+                iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buffer.len(),
+            };
+            let mut message: libc::msghdr = unsafe { std::mem::zeroed() };
+            message.msg_iov = &mut iov;
+            message.msg_iovlen = 1;
+            message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            message.msg_controllen = control.len() as _;
+
+            let received = unsafe { libc::recvmsg(self.fd.as_raw_fd(), &mut message, 0) };
+            if received < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(anyhow::anyhow!("recvmsg: {}", error));
+            }
+
+            let mut packet = &buffer[..received as usize];
+            let mut ttl = control_ttl(&message);
+            if self.raw && self.address.is_ipv4() {
+                let header_length = packet.first().map_or(0, |b| ((b & 0x0f) as usize) * 4);
+                if packet.len() < header_length {
+                    continue;
+                }
+                ttl = ttl.or(packet.get(8).copied());
+                packet = &packet[header_length..];
+            }
+
+            if packet.len() < ICMP_HEADER_LENGTH || packet[0] != reply_type {
+                continue;
+            }
+            let identifier = u16::from_be_bytes([packet[4], packet[5]]);
+            if self.raw && identifier != self.identifier {
+                continue;
+            }
+
+            let sequence = u16::from_be_bytes([packet[6], packet[7]]);
+            return Ok(Some((sequence, packet.len(), ttl)));
+        }
+    }
+}
+
+/**
+ * Runs a series of echo requests against one address
+ */
+pub struct Pinger {
+    /// Socket to the target
+    socket: IcmpSocket,
+    /// Run options
+    options: PingOptions,
+}
+
+impl Pinger {
+    /**
+     * Opens a socket for pinging an address
+     *
+     * @param address - Target address
+     * @param options - Run options
+     * @return Result<Pinger> - Ready pinger or socket error
+     */
+    pub fn new(address: IpAddr, options: PingOptions) -> Result<Self> {
+        Ok(Self {
+            socket: IcmpSocket::open(address)?,
+            options,
+        })
+    }
+
+    /**
+     * pingを実行する関数です
+     *
+     * インターバルごとにエコー要求を送信し、次の送信時刻まで
+     * 応答を待ちます。最後の要求の後は、すべての応答が
+     * 届くかタイムアウトになるまで待機します。
+     *
+     * 応答は送信時刻と照合してRTTを計算し、重複した応答や
+     * 不明なシーケンス番号の応答は無視します。
+     *
+     * @param on_reply - 応答ごとに呼ばれるコールバック
+     * @return Result<PingStatistics> - 統計情報またはエラー
+     */
+    pub fn run(&self, mut on_reply: impl FnMut(&PingReply)) -> Result<PingStatistics> {
+        let payload: Vec<u8> = (0..self.options.size).map(|i| (i % 256) as u8).collect();
+        let mut statistics = PingStatistics::default();
+        let mut outstanding: HashMap<u16, Instant> = HashMap::new();
+        let start = Instant::now();
+
+        for index in 0..self.options.count {
+            let sequence = (index + 1) as u16;
+            let sent_at = Instant::now();
+            self.socket.send_echo(sequence, &payload)?;
+            outstanding.insert(sequence, sent_at);
+            statistics.transmitted += 1;
+
+            let last = index + 1 == self.options.count;
+            let wait_until = if last {
+                sent_at + self.options.timeout
+            } else {
+                sent_at + self.options.interval
+            };
+
+            loop {
+                if last && outstanding.is_empty() {
+                    break;
+                }
+                let remaining = wait_until.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+
+                let Some((sequence, bytes, ttl)) = self.socket.receive(remaining)? else {
+                    break;
+                };
+                let Some(sent_at) = outstanding.remove(&sequence) else {
+                    continue;
+                };
+
+                let reply = PingReply {
+                    bytes,
+                    sequence,
+                    ttl,
+                    rtt: sent_at.elapsed(),
+                };
+                statistics.received += 1;
+                statistics.rtts.push(reply.rtt);
+                on_reply(&reply);
+            }
+        }
+
+        statistics.elapsed = start.elapsed();
+        Ok(statistics)
+    }
+}
+
+/**
+ * Computes the Internet checksum (RFC 1071)
+ *
+ * @param data - Bytes to sum
+ * @return u16 - One's complement checksum
+ */
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    for chunk in data.chunks(2) {
+        let word = match chunk {
+            [high, low] => u16::from_be_bytes([*high, *low]),
+            [high] => u16::from_be_bytes([*high, 0]),
+            _ => 0,
+        };
+        sum += word as u32;
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/**
+ * Builds a sockaddr for an IP address
+ *
+ * @param address - IP address
+ * @return (libc::sockaddr_storage, libc::socklen_t) - Address and its length
+ */
+fn socket_address(address: IpAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+
+    let length = match address {
+        IpAddr::V4(v4) => {
+            let sin = unsafe { &mut *(&mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_addr.s_addr = u32::from_ne_bytes(v4.octets());
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        IpAddr::V6(v6) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_addr.s6_addr = v6.octets();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, length as libc::socklen_t)
+}
+
+/**
+ * Extracts the TTL or hop limit from received control messages
+ *
+ * @param message - Message filled in by recvmsg
+ * @return Option<u8> - TTL if present
+ */
+fn control_ttl(message: &libc::msghdr) -> Option<u8> {
+    let mut header = unsafe { libc::CMSG_FIRSTHDR(message) };
+
+    while !header.is_null() {
+        let cmsg = unsafe { &*header };
+        let is_ttl = (cmsg.cmsg_level == libc::IPPROTO_IP && cmsg.cmsg_type == libc::IP_TTL)
+            || (cmsg.cmsg_level == libc::IPPROTO_IPV6 && cmsg.cmsg_type == libc::IPV6_HOPLIMIT);
+        if is_ttl {
+            let value = unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(header) as *const libc::c_int) };
+            return u8::try_from(value).ok();
+        }
+        header = unsafe { libc::CMSG_NXTHDR(message, header) };
+    }
+
+    None
+}
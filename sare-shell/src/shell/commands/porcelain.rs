@@ -0,0 +1,885 @@
+/*!
+ * @file porcelain.rs
+ * @brief git status, branch, log and diff --stat without the git binary
+ *
+ * This module formats what the native repository reader finds the way
+ * the git porcelain commands do, so the git builtin keeps working on
+ * machines where git is not installed. Only read-only subcommands are
+ * supported.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file porcelain.rs
+ * @description Long and short status, branch listings with tracking
+ * information, log in medium and oneline formats, and diff --stat,
+ * --numstat and --shortstat.
+ */
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::shell::commands::CommandResult;
+use crate::shell::git::diff::{self, FileStat, Snapshot};
+use crate::shell::git::objects::ObjectId;
+use crate::shell::git::status::{Change, ChangeKind, Status};
+use crate::shell::git::{Head, Repository, RepositoryState};
+
+/// Subcommands answered without the git binary
+pub const NATIVE_SUBCOMMANDS: [&str; 4] = ["status", "branch", "log", "diff"];
+
+/**
+ * Runs a read-only git subcommand natively
+ *
+ * @param subcommand - `status`, `branch`, `log` or `diff`
+ * @param args - Arguments after the subcommand
+ * @param directory - Current directory
+ * @param terminal - Whether output goes to a terminal (enables log decorations)
+ * @param width - Terminal width for diff --stat
+ * @return Result<CommandResult> - Formatted output
+ */
+pub fn execute(subcommand: &str, args: &[String], directory: &Path, terminal: bool, width: usize) -> Result<CommandResult> {
+    let repository = Repository::discover(directory)?;
+    let output = match subcommand {
+        "status" => status(&repository, args, directory)?,
+        "branch" => branch(&repository, args)?,
+        "log" => log(&repository, args, terminal)?,
+        "diff" => diff_stat(&repository, args, width)?,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "'{}' needs the git binary, which was not found; only {} work without it",
+                subcommand,
+                NATIVE_SUBCOMMANDS.join(", ")
+            ))
+        }
+    };
+    Ok(CommandResult { output, exit_code: 0 })
+}
+
+/**
+ * Plural suffix for a count
+ *
+ * @param count - Count
+ * @return &str - `s` unless the count is one
+ */
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/**
+ * Path of the current directory inside the work tree
+ *
+ * @param repository - Repository
+ * @param directory - Current directory
+ * @return String - Prefix ending in `/`, or empty at the top
+ */
+fn work_tree_prefix(repository: &Repository, directory: &Path) -> String {
+    match directory.strip_prefix(&repository.work_tree) {
+        Ok(relative) if !relative.as_os_str().is_empty() => format!("{}/", relative.to_string_lossy()),
+        _ => String::new(),
+    }
+}
+
+/**
+ * Rewrites a work tree path relative to the current directory
+ *
+ * @param path - Path relative to the work tree
+ * @param prefix - Current directory inside the work tree
+ * @return String - e.g. `../Cargo.toml`
+ */
+fn relative_to(path: &str, prefix: &str) -> String {
+    if let Some(rest) = path.strip_prefix(prefix) {
+        if !rest.is_empty() {
+            return rest.to_string();
+        }
+    }
+    let path_parts: Vec<&str> = path.split('/').collect();
+    let prefix_parts: Vec<&str> = prefix.trim_end_matches('/').split('/').filter(|part| !part.is_empty()).collect();
+    let common = path_parts.iter().zip(&prefix_parts).take_while(|(left, right)| left == right).count();
+    let mut relative = "../".repeat(prefix_parts.len() - common);
+    relative.push_str(&path_parts[common..].join("/"));
+    if relative.is_empty() {
+        relative.push_str("./");
+    }
+    relative
+}
+
+/**
+ * Describes how a conflicted path differs between stages
+ *
+ * @param status - Status holding the index
+ * @param path - Conflicted path
+ * @return (&str, &str) - Long label and short code, e.g. `both modified:` and `UU`
+ */
+fn conflict_label(status: &Status, path: &str) -> (&'static str, &'static str) {
+    let stages: Vec<u8> = status.index.entries.iter().filter(|entry| entry.path == path).map(|entry| entry.stage).collect();
+    let has = |stage: u8| stages.contains(&stage);
+    match (has(1), has(2), has(3)) {
+        (true, true, true) => ("both modified:", "UU"),
+        (false, true, true) => ("both added:", "AA"),
+        (true, false, true) => ("deleted by us:", "DU"),
+        (true, true, false) => ("deleted by them:", "UD"),
+        (false, true, false) => ("added by us:", "AU"),
+        (false, false, true) => ("added by them:", "UA"),
+        _ => ("both deleted:", "DD"),
+    }
+}
+
+/**
+ * Tracking information of the current branch
+ */
+struct Tracking {
+    /// Upstream short name, e.g. `origin/main`
+    upstream: String,
+    /// Ahead and behind counts, or None if the upstream branch is gone
+    counts: Option<(usize, usize)>,
+}
+
+/**
+ * Looks up the upstream of a branch and compares the two
+ *
+ * @param repository - Repository
+ * @param branch - Local branch name
+ * @param target - Local branch tip
+ * @return Option<Tracking> - Tracking information if an upstream is configured
+ */
+fn tracking(repository: &Repository, branch: &str, target: Option<ObjectId>) -> Option<Tracking> {
+    let upstream = repository.upstream(branch)?;
+    let counts = match (target, repository.rev_parse(&upstream).ok()) {
+        (Some(local), Some(remote)) => repository.ahead_behind(&local, &remote).ok(),
+        _ => None,
+    };
+    Some(Tracking { upstream, counts })
+}
+
+/**
+ * git statusの出力を組み立てる関数です
+ *
+ * -s/--short、--porcelain、-b/--branchを受け付けます。既定の
+ * 長い形式では、ブランチと上流ブランチとの差、ステージ済みの変更、
+ * 競合、未ステージの変更、未追跡のファイルの順に、gitと同じ
+ * 見出しとヒントで表示します。パスは現在のディレクトリからの
+ * 相対パスです（--porcelainでは作業ツリーの先頭からのパス）。
+ * 引数にパスを指定すると、その下の変更だけを表示します。
+ *
+ * @param repository - リポジトリ
+ * @param args - 引数
+ * @param directory - 現在のディレクトリ
+ * @return Result<String> - 出力
+ */
+fn status(repository: &Repository, args: &[String], directory: &Path) -> Result<String> {
+    let mut short = false;
+    let mut porcelain = false;
+    let mut show_branch = false;
+    let mut filters = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-s" | "--short" => short = true,
+            "--porcelain" | "--porcelain=v1" => porcelain = true,
+            "-b" | "--branch" => show_branch = true,
+            "-sb" | "-bs" => {
+                short = true;
+                show_branch = true;
+            }
+            "--long" => short = false,
+            "--" => {}
+            option if option.starts_with('-') => return Err(anyhow::anyhow!("status: unsupported option '{}'", option)),
+            path => filters.push(path.to_string()),
+        }
+    }
+
+    let prefix = work_tree_prefix(repository, directory);
+    let filters: Vec<String> = filters
+        .iter()
+        .map(|path| {
+            let full = if path == "." { prefix.trim_end_matches('/').to_string() } else { format!("{}{}", prefix, path.trim_end_matches('/')) };
+            Path::new(&full).components().fold(String::new(), |joined, part| match part {
+                std::path::Component::ParentDir => joined.rsplit_once('/').map(|(parent, _)| parent.to_string()).unwrap_or_default(),
+                std::path::Component::Normal(name) if joined.is_empty() => name.to_string_lossy().to_string(),
+                std::path::Component::Normal(name) => format!("{}/{}", joined, name.to_string_lossy()),
+                _ => joined,
+            })
+        })
+        .collect();
+    let selected = |path: &str| filters.is_empty() || filters.iter().any(|filter| filter.is_empty() || path == filter || path.starts_with(&format!("{}/", filter)));
+
+    let mut status = Status::collect(repository)?;
+    status.staged.retain(|change| selected(&change.path));
+    status.unstaged.retain(|change| selected(&change.path));
+    status.unmerged.retain(|path| selected(path));
+    status.untracked.retain(|path| selected(path.trim_end_matches('/')));
+
+    let head = repository.head()?;
+    let display = |path: &str| if porcelain { path.to_string() } else { relative_to(path, &prefix) };
+    if short || porcelain {
+        return Ok(short_status(repository, &head, &status, show_branch, &display));
+    }
+
+    let mut output = String::new();
+    let unborn = matches!(head, Head::Branch { target: None, .. });
+    match &head {
+        Head::Branch { name, target } => {
+            output.push_str(&format!("On branch {}\n", name));
+            if let Some(tracking) = tracking(repository, name, *target) {
+                output.push_str(&tracking_message(&tracking));
+                output.push('\n');
+            }
+        }
+        Head::Detached(id) => match repository.detached_description(id) {
+            Some(description) => output.push_str(&format!("{}\n", description)),
+            None => output.push_str("Not currently on any branch.\n"),
+        },
+    }
+    if unborn {
+        output.push_str("\nNo commits yet\n\n");
+    }
+
+    if let Some(state) = repository.state() {
+        output.push_str(&state_message(state, !status.unmerged.is_empty()));
+    }
+
+    let section = |output: &mut String, title: &str, hints: &[&str]| {
+        output.push_str(&format!("{}\n", title));
+        for hint in hints {
+            output.push_str(&format!("  ({})\n", hint));
+        }
+    };
+    let push_changes = |output: &mut String, changes: &[Change]| {
+        for change in changes {
+            output.push_str(&format!("\t{:<12}{}\n", change.kind.label(), display(&change.path)));
+        }
+        output.push('\n');
+    };
+
+    if !status.staged.is_empty() {
+        let hints: &[&str] = if repository.state() == Some(RepositoryState::Merging) {
+            &[]
+        } else if unborn {
+            &["use \"git rm --cached <file>...\" to unstage"]
+        } else {
+            &["use \"git restore --staged <file>...\" to unstage"]
+        };
+        section(&mut output, "Changes to be committed:", hints);
+        push_changes(&mut output, &status.staged);
+    }
+    if !status.unmerged.is_empty() {
+        section(&mut output, "Unmerged paths:", &["use \"git add <file>...\" to mark resolution"]);
+        for path in &status.unmerged {
+            output.push_str(&format!("\t{:<17}{}\n", conflict_label(&status, path).0, display(path)));
+        }
+        output.push('\n');
+    }
+    if !status.unstaged.is_empty() {
+        let has_deleted = status.unstaged.iter().any(|change| change.kind == ChangeKind::Deleted);
+        let add_hint = if has_deleted { "use \"git add/rm <file>...\" to update what will be committed" } else { "use \"git add <file>...\" to update what will be committed" };
+        section(&mut output, "Changes not staged for commit:", &[add_hint, "use \"git restore <file>...\" to discard changes in working directory"]);
+        push_changes(&mut output, &status.unstaged);
+    }
+    if !status.untracked.is_empty() {
+        section(&mut output, "Untracked files:", &["use \"git add <file>...\" to include in what will be committed"]);
+        for path in &status.untracked {
+            output.push_str(&format!("\t{}\n", display(path)));
+        }
+        output.push('\n');
+    }
+
+    if !status.staged.is_empty() {
+        return Ok(output);
+    }
+    if !status.unstaged.is_empty() || !status.unmerged.is_empty() {
+        output.push_str("no changes added to commit (use \"git add\" and/or \"git commit -a\")\n");
+    } else if !status.untracked.is_empty() {
+        output.push_str("nothing added to commit but untracked files present (use \"git add\" to track)\n");
+    } else if unborn {
+        output.push_str("nothing to commit (create/copy files and use \"git add\" to track)\n");
+    } else {
+        output.push_str("nothing to commit, working tree clean\n");
+    }
+    Ok(output)
+}
+
+/**
+ * Describes an operation in progress for git status
+ *
+ * @param state - Operation in progress
+ * @param conflicted - Whether there are unmerged paths
+ * @return String - Message and hints followed by a blank line
+ */
+fn state_message(state: RepositoryState, conflicted: bool) -> String {
+    let (message, hints): (&str, Vec<&str>) = match (state, conflicted) {
+        (RepositoryState::Merging, true) => ("You have unmerged paths.", vec!["fix conflicts and run \"git commit\"", "use \"git merge --abort\" to abort the merge"]),
+        (RepositoryState::Merging, false) => ("All conflicts fixed but you are still merging.", vec!["use \"git commit\" to conclude merge"]),
+        (RepositoryState::Rebasing, true) => (
+            "You are currently rebasing.",
+            vec![
+                "fix conflicts and then run \"git rebase --continue\"",
+                "use \"git rebase --skip\" to skip this patch",
+                "use \"git rebase --abort\" to check out the original branch",
+            ],
+        ),
+        (RepositoryState::Rebasing, false) => ("You are currently rebasing.", vec!["all conflicts fixed: run \"git rebase --continue\""]),
+        (RepositoryState::CherryPicking, true) => (
+            "You are currently cherry-picking.",
+            vec!["fix conflicts and run \"git cherry-pick --continue\"", "use \"git cherry-pick --abort\" to cancel the cherry-pick operation"],
+        ),
+        (RepositoryState::CherryPicking, false) => ("You are currently cherry-picking.", vec!["all conflicts fixed: run \"git cherry-pick --continue\""]),
+        (RepositoryState::Reverting, true) => (
+            "You are currently reverting.",
+            vec!["fix conflicts and run \"git revert --continue\"", "use \"git revert --abort\" to cancel the revert operation"],
+        ),
+        (RepositoryState::Reverting, false) => ("You are currently reverting.", vec!["all conflicts fixed: run \"git revert --continue\""]),
+        (RepositoryState::Bisecting, _) => ("You are currently bisecting.", vec!["use \"git bisect reset\" to get back to the original branch"]),
+    };
+    let mut text = format!("{}\n", message);
+    for hint in hints {
+        text.push_str(&format!("  ({})\n", hint));
+    }
+    text.push('\n');
+    text
+}
+
+/**
+ * Formats the relation between a branch and its upstream for git status
+ *
+ * @param tracking - Tracking information
+ * @return String - One or more lines
+ */
+fn tracking_message(tracking: &Tracking) -> String {
+    let upstream = &tracking.upstream;
+    match tracking.counts {
+        None => format!(
+            "Your branch is based on '{}', but the upstream is gone.\n  (use \"git branch --unset-upstream\" to fixup)\n",
+            upstream
+        ),
+        Some((0, 0)) => format!("Your branch is up to date with '{}'.\n", upstream),
+        Some((ahead, 0)) => format!(
+            "Your branch is ahead of '{}' by {} commit{}.\n  (use \"git push\" to publish your local commits)\n",
+            upstream,
+            ahead,
+            plural(ahead)
+        ),
+        Some((0, behind)) => format!(
+            "Your branch is behind '{}' by {} commit{}, and can be fast-forwarded.\n  (use \"git pull\" to update your local branch)\n",
+            upstream,
+            behind,
+            plural(behind)
+        ),
+        Some((ahead, behind)) => format!(
+            "Your branch and '{}' have diverged,\nand have {} and {} different commits each, respectively.\n  (use \"git pull\" if you want to integrate the remote branch with yours)\n",
+            upstream, ahead, behind
+        ),
+    }
+}
+
+/**
+ * Formats git status -s / --porcelain
+ *
+ * @param repository - Repository
+ * @param head - Current HEAD
+ * @param status - Status
+ * @param show_branch - Whether to print the `##` branch line
+ * @param display - Converts work tree paths for display
+ * @return String - One line per path
+ */
+fn short_status(repository: &Repository, head: &Head, status: &Status, show_branch: bool, display: &dyn Fn(&str) -> String) -> String {
+    let mut output = String::new();
+    if show_branch {
+        match head {
+            Head::Branch { name, target: None } => output.push_str(&format!("## No commits yet on {}\n", name)),
+            Head::Branch { name, target } => {
+                output.push_str(&format!("## {}", name));
+                if let Some(tracking) = tracking(repository, name, *target) {
+                    output.push_str(&format!("...{}", tracking.upstream));
+                    match tracking.counts {
+                        None => output.push_str(" [gone]"),
+                        Some((0, 0)) => {}
+                        Some((ahead, 0)) => output.push_str(&format!(" [ahead {}]", ahead)),
+                        Some((0, behind)) => output.push_str(&format!(" [behind {}]", behind)),
+                        Some((ahead, behind)) => output.push_str(&format!(" [ahead {}, behind {}]", ahead, behind)),
+                    }
+                }
+                output.push('\n');
+            }
+            Head::Detached(_) => output.push_str("## HEAD (no branch)\n"),
+        }
+    }
+
+    let mut codes: BTreeMap<&str, [char; 2]> = BTreeMap::new();
+    for change in &status.staged {
+        codes.entry(&change.path).or_insert([' ', ' '])[0] = change.kind.letter();
+    }
+    for change in &status.unstaged {
+        codes.entry(&change.path).or_insert([' ', ' '])[1] = change.kind.letter();
+    }
+    let mut lines: Vec<(String, String)> = codes.into_iter().map(|(path, [x, y])| (path.to_string(), format!("{}{}", x, y))).collect();
+    for path in &status.unmerged {
+        lines.push((path.clone(), conflict_label(status, path).1.to_string()));
+    }
+    lines.sort();
+    for (path, code) in lines {
+        output.push_str(&format!("{} {}\n", code, display(&path)));
+    }
+    for path in &status.untracked {
+        output.push_str(&format!("?? {}\n", display(path)));
+    }
+    output
+}
+
+/// Branch listing row: marker, name, target commit or symbolic ref target, and local branch name for tracking
+type BranchRow = (char, String, Result<ObjectId, String>, Option<String>);
+
+/**
+ * git branchの出力を組み立てる関数です
+ *
+ * -a/--all、-r/--remotes、-v/-vv、--show-currentを受け付けます。
+ * 現在のブランチ（または切り離されたHEAD）に「*」を付けます。
+ * -vではコミットの省略IDと件名、上流ブランチとの差を、-vvでは
+ * さらに上流ブランチの名前を表示します。ブランチの作成や削除は
+ * 読み取り専用のためgitが必要です。
+ *
+ * @param repository - リポジトリ
+ * @param args - 引数
+ * @return Result<String> - 出力
+ */
+fn branch(repository: &Repository, args: &[String]) -> Result<String> {
+    let mut local = true;
+    let mut remote = false;
+    let mut verbose = 0;
+    for arg in args {
+        match arg.as_str() {
+            "-a" | "--all" => remote = true,
+            "-r" | "--remotes" => {
+                local = false;
+                remote = true;
+            }
+            "-v" | "--verbose" => verbose += 1,
+            "-vv" => verbose += 2,
+            "--list" | "-l" => {}
+            "--show-current" => {
+                return Ok(match repository.head()? {
+                    Head::Branch { name, .. } => format!("{}\n", name),
+                    Head::Detached(_) => String::new(),
+                })
+            }
+            other => return Err(anyhow::anyhow!("branch: '{}' changes the repository and needs the git binary", other)),
+        }
+    }
+
+    let head = repository.head()?;
+    let qualify_remotes = local && remote;
+    let elsewhere = repository.worktree_branches();
+    let mut rows: Vec<BranchRow> = Vec::new();
+    if local {
+        if let Head::Detached(id) = &head {
+            let description = match repository.detached_description(id) {
+                Some(description) => format!("({})", description),
+                None => "(no branch)".to_string(),
+            };
+            rows.push(('*', description, Ok(*id), None));
+        }
+        for (name, value) in repository.references("refs/heads/") {
+            let short = name["refs/heads/".len()..].to_string();
+            let marker = if matches!(&head, Head::Branch { name, .. } if *name == short) {
+                '*'
+            } else if elsewhere.contains_key(&short) {
+                '+'
+            } else {
+                ' '
+            };
+            if let Some(id) = ObjectId::from_hex(&value) {
+                rows.push((marker, short.clone(), Ok(id), Some(short)));
+            }
+        }
+    }
+    if remote {
+        for (name, value) in repository.references("refs/remotes/") {
+            let short = &name["refs/remotes/".len()..];
+            let shown = if qualify_remotes { format!("remotes/{}", short) } else { short.to_string() };
+            match value.strip_prefix("ref:") {
+                Some(target) => {
+                    let target = target.trim().strip_prefix("refs/remotes/").unwrap_or(target.trim());
+                    rows.push((' ', shown, Err(target.to_string()), None));
+                }
+                None => {
+                    if let Some(id) = ObjectId::from_hex(&value) {
+                        rows.push((' ', shown, Ok(id), None));
+                    }
+                }
+            }
+        }
+    }
+
+    let width = rows.iter().map(|row| row.1.chars().count()).max().unwrap_or(0);
+    let mut output = String::new();
+    for (marker, name, target, branch) in rows {
+        let padding = if verbose > 0 { width - name.chars().count() } else { 0 };
+        let id = match target {
+            Err(symbolic) => {
+                output.push_str(&format!("{} {}{} -> {}\n", marker, name, " ".repeat(padding), symbolic));
+                continue;
+            }
+            Ok(id) => id,
+        };
+        if verbose == 0 {
+            output.push_str(&format!("{} {}\n", marker, name));
+            continue;
+        }
+
+        let mut tracking_text = String::new();
+        if let Some(tracking) = branch.as_deref().and_then(|branch| tracking(repository, branch, Some(id))) {
+            let counts = match tracking.counts {
+                None => "gone".to_string(),
+                Some((0, 0)) => String::new(),
+                Some((ahead, 0)) => format!("ahead {}", ahead),
+                Some((0, behind)) => format!("behind {}", behind),
+                Some((ahead, behind)) => format!("ahead {}, behind {}", ahead, behind),
+            };
+            tracking_text = match (verbose > 1, counts.is_empty()) {
+                (true, true) => format!("[{}] ", tracking.upstream),
+                (true, false) => format!("[{}: {}] ", tracking.upstream, counts),
+                (false, true) => String::new(),
+                (false, false) => format!("[{}] ", counts),
+            };
+        }
+        if verbose > 1 {
+            if let Some(path) = branch.as_deref().and_then(|branch| elsewhere.get(branch)) {
+                tracking_text.insert_str(0, &format!("({}) ", path.display()));
+            }
+        }
+        let subject = repository.commit(&id).map(|commit| commit.subject().to_string()).unwrap_or_default();
+        output.push_str(&format!("{} {}{} {} {}{}\n", marker, name, " ".repeat(padding), id.short(), tracking_text, subject));
+    }
+    Ok(output)
+}
+
+/**
+ * git logの出力を組み立てる関数です
+ *
+ * -n N、-N、--max-count=N、--onelineを受け付けます。リビジョンは
+ * 複数指定でき、「A..B」や「^A」でAから到達できるコミットを除外
+ * します。コミット日時の新しい順に、既定ではgitのmedium形式
+ * （commit、Merge、Author、Date、4文字字下げしたメッセージ）で
+ * 表示します。端末への出力では、ブランチやタグの名前を括弧で
+ * 付け加えます（log.decorate=autoと同じ）。
+ *
+ * @param repository - リポジトリ
+ * @param args - 引数
+ * @param decorate - ref名を付けるかどうか
+ * @return Result<String> - 出力
+ */
+fn log(repository: &Repository, args: &[String], mut decorate: bool) -> Result<String> {
+    let mut oneline = false;
+    let mut limit: Option<usize> = None;
+    let mut starts = Vec::new();
+    let mut exclude = Vec::new();
+    let mut arguments = args.iter();
+    while let Some(arg) = arguments.next() {
+        let arg = arg.as_str();
+        if arg == "--oneline" {
+            oneline = true;
+        } else if arg == "--decorate" {
+            decorate = true;
+        } else if arg == "--no-decorate" {
+            decorate = false;
+        } else if arg == "-n" || arg == "--max-count" {
+            let value = arguments.next().ok_or_else(|| anyhow::anyhow!("log: {} requires a value", arg))?;
+            limit = Some(value.parse().map_err(|_| anyhow::anyhow!("log: invalid count '{}'", value))?);
+        } else if let Some(value) = arg.strip_prefix("--max-count=").or_else(|| arg.strip_prefix("-n")) {
+            limit = Some(value.parse().map_err(|_| anyhow::anyhow!("log: invalid count '{}'", value))?);
+        } else if let Some(value) = arg.strip_prefix('-').filter(|value| !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())) {
+            limit = Some(value.parse()?);
+        } else if arg == "--" {
+            break;
+        } else if arg.starts_with('-') {
+            return Err(anyhow::anyhow!("log: unsupported option '{}'", arg));
+        } else if let Some((left, right)) = arg.split_once("..") {
+            exclude.push(repository.rev_parse(if left.is_empty() { "HEAD" } else { left })?);
+            starts.push(repository.rev_parse(if right.is_empty() { "HEAD" } else { right })?);
+        } else if let Some(hidden) = arg.strip_prefix('^') {
+            exclude.push(repository.rev_parse(hidden)?);
+        } else {
+            starts.push(repository.rev_parse(arg)?);
+        }
+    }
+    if starts.is_empty() {
+        match repository.head()? {
+            Head::Branch { name, target: None } => {
+                return Err(anyhow::anyhow!("your current branch '{}' does not have any commits yet", name));
+            }
+            Head::Branch { target: Some(id), .. } | Head::Detached(id) => starts.push(id),
+        }
+    }
+
+    let decorations = if decorate { log_decorations(repository) } else { BTreeMap::new() };
+    let mut output = String::new();
+    for (index, (id, commit)) in repository.walk(&starts, &exclude, limit)?.into_iter().enumerate() {
+        let decoration = decorations.get(&id).map(|names| format!(" ({})", names.join(", "))).unwrap_or_default();
+        if oneline {
+            output.push_str(&format!("{}{} {}\n", id.short(), decoration, commit.subject()));
+            continue;
+        }
+
+        if index > 0 {
+            output.push('\n');
+        }
+        output.push_str(&format!("commit {}{}\n", id, decoration));
+        if commit.parents.len() > 1 {
+            let parents: Vec<String> = commit.parents.iter().map(ObjectId::short).collect();
+            output.push_str(&format!("Merge: {}\n", parents.join(" ")));
+        }
+        output.push_str(&format!("Author: {} <{}>\n", commit.author.name, commit.author.email));
+        output.push_str(&format!("Date:   {}\n\n", commit.author.date()));
+        for line in commit.message.trim_end().lines() {
+            output.push_str(&format!("    {}\n", line.trim_end()));
+        }
+    }
+    Ok(output)
+}
+
+/**
+ * Ref names per commit in the order git log shows them
+ *
+ * @param repository - Repository
+ * @return BTreeMap<ObjectId, Vec<String>> - Names by commit with `HEAD -> branch` first
+ */
+fn log_decorations(repository: &Repository) -> BTreeMap<ObjectId, Vec<String>> {
+    let mut decorations = repository.decorations();
+    match repository.head() {
+        Ok(Head::Branch { name, target: Some(id) }) => {
+            let names = decorations.entry(id).or_default();
+            names.retain(|existing| *existing != name);
+            names.insert(0, format!("HEAD -> {}", name));
+        }
+        Ok(Head::Detached(id)) => decorations.entry(id).or_default().insert(0, "HEAD".to_string()),
+        _ => {}
+    }
+    decorations
+}
+
+/**
+ * Width of a number in decimal digits
+ *
+ * @param number - Number
+ * @return usize - Digit count
+ */
+fn decimal_width(number: usize) -> usize {
+    number.to_string().len()
+}
+
+/**
+ * git diffの統計を組み立てる関数です
+ *
+ * --stat、--numstat、--shortstatのいずれかが必要です。比較の対象は
+ * gitと同じく、引数なしで作業ツリーとインデックス、--cached
+ * （--staged）でHEAD（またはリビジョン）とインデックス、リビジョン
+ * 1つでそのリビジョンと作業ツリー、2つまたは「A..B」でAとBです。
+ *
+ * --statの表示幅はgitのshow_statsと同じ規則で決めます。ファイル名
+ * と変更数のほか、グラフが幅に収まらない場合は変更数を比例して
+ * 縮めます。
+ *
+ * @param repository - リポジトリ
+ * @param args - 引数
+ * @param width - 表示幅
+ * @return Result<String> - 出力
+ */
+fn diff_stat(repository: &Repository, args: &[String], width: usize) -> Result<String> {
+    #[derive(PartialEq)]
+    enum Format {
+        Stat,
+        Numstat,
+        Shortstat,
+    }
+    let mut format = None;
+    let mut cached = false;
+    let mut revisions: Vec<String> = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--stat" => format = Some(Format::Stat),
+            "--numstat" => format = Some(Format::Numstat),
+            "--shortstat" => format = Some(Format::Shortstat),
+            "--cached" | "--staged" => cached = true,
+            "--" => break,
+            option if option.starts_with('-') => return Err(anyhow::anyhow!("diff: unsupported option '{}'", option)),
+            revision => match revision.split_once("..") {
+                Some((left, right)) => {
+                    revisions.push(if left.is_empty() { "HEAD".to_string() } else { left.to_string() });
+                    revisions.push(if right.is_empty() { "HEAD".to_string() } else { right.to_string() });
+                }
+                None => revisions.push(revision.to_string()),
+            },
+        }
+    }
+    let format = format.ok_or_else(|| anyhow::anyhow!("diff: only --stat, --numstat and --shortstat work without the git binary"))?;
+
+    let snapshot_of = |revision: &str| -> Result<Snapshot> { diff::tree_snapshot(repository, &repository.rev_parse(revision)?) };
+    let mut unmerged = Vec::new();
+    let (old, new) = match (cached, revisions.as_slice()) {
+        (false, []) => {
+            let status = Status::collect(repository)?;
+            unmerged = status.unmerged.clone();
+            (diff::index_snapshot(&status), diff::worktree_snapshot(repository, &status))
+        }
+        (true, []) | (true, [_]) => {
+            let status = Status::collect(repository)?;
+            unmerged = status.unmerged.clone();
+            let old = match revisions.first() {
+                Some(revision) => snapshot_of(revision)?,
+                None => match repository.head_commit() {
+                    Some(id) => diff::tree_snapshot(repository, &id)?,
+                    None => Snapshot::new(),
+                },
+            };
+            (old, diff::index_snapshot(&status))
+        }
+        (false, [revision]) => {
+            let status = Status::collect(repository)?;
+            (snapshot_of(revision)?, diff::worktree_snapshot(repository, &status))
+        }
+        (false, [left, right]) => (snapshot_of(left)?, snapshot_of(right)?),
+        _ => return Err(anyhow::anyhow!("diff: too many revisions")),
+    };
+    let mut stats = diff::compare(repository, &old, &new)?;
+    for path in unmerged {
+        stats.push(FileStat {
+            path,
+            insertions: 0,
+            deletions: 0,
+            binary: None,
+            unmerged: true,
+        });
+    }
+    stats.sort_by(|left, right| left.path.cmp(&right.path).then(right.unmerged.cmp(&left.unmerged)));
+
+    let mut output = String::new();
+    match format {
+        Format::Numstat => {
+            for stat in &stats {
+                match stat.binary {
+                    Some(_) => output.push_str(&format!("-\t-\t{}\n", stat.path)),
+                    None => output.push_str(&format!("{}\t{}\t{}\n", stat.insertions, stat.deletions, stat.path)),
+                }
+            }
+            return Ok(output);
+        }
+        Format::Stat => output.push_str(&stat_graph(&stats, width)),
+        Format::Shortstat => {}
+    }
+    if stats.iter().any(|stat| !stat.unmerged) {
+        output.push_str(&stat_summary(&stats));
+    }
+    Ok(output)
+}
+
+/**
+ * diff --statのグラフ部分を組み立てる関数です
+ *
+ * gitのshow_statsと同じく、まずファイル名の最大長、変更数の桁数、
+ * 最大変更数のグラフ幅を求め、合計が表示幅を超える場合は
+ * グラフを幅の3/8までに縮め、それでも超える場合はファイル名を
+ * 「...」で始まる末尾だけに切り詰めます。
+ *
+ * @param stats - ファイルごとの変更数
+ * @param width - 表示幅
+ * @return String - 1ファイル1行の出力
+ */
+fn stat_graph(stats: &[FileStat], width: usize) -> String {
+    let max_change = stats.iter().filter(|stat| stat.binary.is_none()).map(|stat| stat.insertions + stat.deletions).max().unwrap_or(0);
+    let max_length = stats.iter().map(|stat| stat.path.chars().count()).max().unwrap_or(0);
+    let mut number_width = decimal_width(max_change);
+    if stats.iter().any(|stat| stat.binary.is_some()) {
+        number_width = number_width.max(3);
+    }
+
+    let width = width.max(16 + 6 + number_width);
+    let mut graph_width = max_change;
+    let mut name_width = max_length;
+    if name_width + number_width + 6 + graph_width > width {
+        if graph_width > (width * 3 / 8).saturating_sub(number_width + 6) {
+            graph_width = (width * 3 / 8).saturating_sub(number_width + 6).max(6);
+        }
+        if name_width > width - number_width - 6 - graph_width {
+            name_width = width - number_width - 6 - graph_width;
+        } else {
+            graph_width = width - number_width - 6 - name_width;
+        }
+    }
+
+    let scale = |count: usize| -> usize {
+        if count == 0 {
+            0
+        } else {
+            1 + count * (graph_width - 1) / max_change
+        }
+    };
+
+    let mut output = String::new();
+    for stat in stats {
+        let length = stat.path.chars().count();
+        let name = if length > name_width {
+            let keep = name_width.saturating_sub(3);
+            let tail: String = stat.path.chars().skip(length - keep).collect();
+            let tail = match tail.find('/') {
+                Some(slash) => tail[slash..].to_string(),
+                None => tail,
+            };
+            format!("...{}", tail)
+        } else {
+            stat.path.clone()
+        };
+        let padding = name_width.saturating_sub(name.chars().count());
+        if stat.unmerged {
+            output.push_str(&format!(" {}{} | {:>width$}\n", name, " ".repeat(padding), "Unmerged", width = number_width));
+            continue;
+        }
+        if let Some((before, after)) = stat.binary {
+            output.push_str(&format!(" {}{} | {:>width$} {} -> {} bytes\n", name, " ".repeat(padding), "Bin", before, after, width = number_width));
+            continue;
+        }
+
+        let total = stat.insertions + stat.deletions;
+        let (mut plus, mut minus) = (stat.insertions, stat.deletions);
+        if graph_width <= max_change {
+            let mut scaled_total = scale(total);
+            if scaled_total < 2 && plus > 0 && minus > 0 {
+                scaled_total = 2;
+            }
+            if plus < minus {
+                plus = scale(plus);
+                minus = scaled_total - plus;
+            } else {
+                minus = scale(minus);
+                plus = scaled_total - minus;
+            }
+        }
+        let graph = format!("{}{}", "+".repeat(plus), "-".repeat(minus));
+        let separator = if graph.is_empty() { "" } else { " " };
+        output.push_str(&format!(" {}{} | {:>width$}{}{}\n", name, " ".repeat(padding), total, separator, graph, width = number_width));
+    }
+    output
+}
+
+/**
+ * Formats the closing summary line of diff --stat
+ *
+ * @param stats - Per-file counts
+ * @return String - e.g. ` 2 files changed, 5 insertions(+), 1 deletion(-)`
+ */
+fn stat_summary(stats: &[FileStat]) -> String {
+    let insertions: usize = stats.iter().map(|stat| stat.insertions).sum();
+    let deletions: usize = stats.iter().map(|stat| stat.deletions).sum();
+    let files = stats.iter().filter(|stat| !stat.unmerged).count();
+    let mut summary = format!(" {} file{} changed", files, plural(files));
+    if insertions > 0 || deletions == 0 {
+        summary.push_str(&format!(", {} insertion{}(+)", insertions, plural(insertions)));
+    }
+    if deletions > 0 || insertions == 0 {
+        summary.push_str(&format!(", {} deletion{}(-)", deletions, plural(deletions)));
+    }
+    summary.push('\n');
+    summary
+}
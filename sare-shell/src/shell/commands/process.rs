@@ -13,9 +13,16 @@
  */
 
 use anyhow::Result;
+use std::io::Write;
 use crate::shell::parser::ParsedCommand;
 use crate::shell::Shell;
-use crate::shell::commands::{CommandHandler, CommandResult};
+use crate::shell::commands::{command_from_words, CommandHandler, CommandResult};
+use crate::shell::commands::listing::terminal_columns;
+use crate::shell::commands::{ps, top};
+use crate::shell::commands::records::Table;
+use crate::shell::resources::{self, LimitResource, ResourceLimits, TimeoutPolicy};
+#[cfg(feature = "security")]
+use crate::shell::sandbox::{self, SandboxPolicy};
 
 /**
  * Jobs command
@@ -27,9 +34,13 @@ pub struct JobsCommand;
 
 impl CommandHandler for JobsCommand {
     fn execute(&self, _command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut records = Table::new(&["id", "pid", "state", "command", "exit_code"]);
         let jobs = shell.get_jobs();
         
         if jobs.is_empty() {
+            if !shell.output_is_terminal() {
+                shell.emit_records(records);
+            }
             return Ok(CommandResult {
                 output: "No background jobs".to_string(),
                 exit_code: 0,
@@ -47,8 +58,12 @@ impl CommandHandler for JobsCommand {
             
             output.push_str(&format!("[{}] {} {} {}\n", 
                 job.id, status, job.pid, job.command));
+            records.push_row(vec![job.id.into(), job.pid.into(), status.into(), job.command.clone().into(), job.exit_code.into()]);
         }
         
+        if !shell.output_is_terminal() {
+            shell.emit_records(records);
+        }
         Ok(CommandResult {
             output,
             exit_code: 0,
@@ -258,4 +273,800 @@ impl CommandHandler for WaitCommand {
     fn name(&self) -> &str {
         "wait"
     }
-} 
\ No newline at end of file
+}
+
+/**
+ * コマンドの実行時間を制限するtimeoutコマンドです
+ * 
+ * 指定された時間内にコマンドが終了しない場合、コマンドの
+ * プロセスグループ全体にシグナル（デフォルトはSIGTERM）を
+ * 送信します。
+ * 
+ * -kで指定した猶予期間（デフォルト5秒）を過ぎても終了しない
+ * 場合はSIGKILLを送信します。時間切れの場合の終了コードは
+ * GNU timeoutと同じ124です。
+ * 
+ * 時間は秒数のほか、s、m、h、dの接尾辞で指定できます。
+ */
+pub struct TimeoutCommand;
+
+impl CommandHandler for TimeoutCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut signal = libc::SIGTERM;
+        let mut kill_after = None;
+        let mut args = command.args.iter();
+        
+        let duration = loop {
+            match args.next().map(String::as_str) {
+                Some("-s") | Some("--signal") => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("option requires an argument -- 's'"))?;
+                    signal = resources::parse_signal(value)?;
+                }
+                Some("-k") | Some("--kill-after") => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("option requires an argument -- 'k'"))?;
+                    kill_after = Some(resources::parse_duration(value)?);
+                }
+                Some(value) => break resources::parse_duration(value)?,
+                None => return Err(anyhow::anyhow!("Usage: timeout [-s signal] [-k duration] <duration> <command> [args...]")),
+            }
+        };
+        
+        let program = args.next().ok_or_else(|| anyhow::anyhow!("missing command to run"))?;
+        
+        let mut target = command.clone();
+        target.command = program.clone();
+        target.args = args.cloned().collect();
+        target.input_redirect = None;
+        target.output_redirect = None;
+        target.append_redirect = None;
+        
+        let mut policy = TimeoutPolicy::new(duration);
+        policy.signal = signal;
+        if let Some(kill_after) = kill_after {
+            policy.kill_after = kill_after;
+        }
+        
+        shell.run_with_timeout(&target, &policy)
+    }
+    
+    fn help(&self) -> &str {
+        "timeout [options] <duration> <command> [args...] - Run a command with a time limit\n\
+         Duration is in seconds; s, m, h and d suffixes are accepted.\n\
+         Exits with 124 if the command timed out.\n\
+         Options:\n\
+         -s <signal>    Signal sent on timeout (default: TERM)\n\
+         -k <duration>  Send KILL if still running this long after the signal (default: 5s)"
+    }
+    
+    fn name(&self) -> &str {
+        "timeout"
+    }
+}
+
+/**
+ * コマンドを隔離された環境で実行するsandboxコマンドです
+ * 
+ * 非特権のユーザー名前空間を使い、マウント・IPC・UTS名前空間を
+ * 分離してコマンドを起動します。--net=offでネットワーク名前空間も
+ * 分離し、ループバックしかない状態にします。
+ * 
+ * --roで指定したパスは読み取り専用でbindマウントし直し、--mem、
+ * --cpu、--nofileはコマンドだけにsetrlimit()で適用します。
+ * seccompフィルタでmount、ptrace、カーネルモジュールの読み込みなど
+ * 隔離を破れるシステムコールを禁止します。
+ * 
+ * 全ての設定はsare-securityのChildSetupがforkとexecの間に
+ * 子プロセスの中で行うため、シェル自身は制限を受けません。
+ * securityフィーチャーを有効にしてビルドした場合だけ使えます。
+ */
+#[cfg(feature = "security")]
+pub struct SandboxCommand;
+
+#[cfg(feature = "security")]
+impl CommandHandler for SandboxCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut policy = SandboxPolicy::default();
+        let mut args = command.args.iter();
+        
+        let program = loop {
+            let Some(arg) = args.next() else {
+                return Err(anyhow::anyhow!("Usage: sandbox [--net=off] [--ro=PATH]... [--mem=SIZE] [--] <command> [args...]"));
+            };
+            if arg == "--" {
+                break args.next().ok_or_else(|| anyhow::anyhow!("missing command to run"))?;
+            }
+            let Some(option) = arg.strip_prefix("--") else {
+                break arg;
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, value.to_string()),
+                None => (
+                    option,
+                    args.next().cloned().ok_or_else(|| anyhow::anyhow!("option '--{}' requires an argument", option))?,
+                ),
+            };
+            let switch = |value: &str| match value {
+                "on" | "yes" | "true" => Ok(true),
+                "off" | "no" | "false" => Ok(false),
+                _ => Err(anyhow::anyhow!("invalid value '{}' for --{} (expected on or off)", value, name)),
+            };
+            let number = |value: &str| value
+                .parse::<u64>()
+                .map_err(|_| anyhow::anyhow!("invalid value '{}' for --{}", value, name));
+            match name {
+                "net" => policy.network = switch(&value)?,
+                "ro" => policy.read_only.push(value.into()),
+                "mem" => policy.memory = Some(sandbox::parse_memory(&value)?),
+                "cpu" => policy.cpu_time = Some(number(&value)?),
+                "nofile" => policy.open_files = Some(number(&value)?),
+                "seccomp" => policy.seccomp = switch(&value)?,
+                _ => return Err(anyhow::anyhow!("unrecognized option '--{}'", name)),
+            }
+        };
+        
+        let mut target = command.clone();
+        target.command = program.clone();
+        target.args = args.cloned().collect();
+        target.input_redirect = None;
+        target.output_redirect = None;
+        target.append_redirect = None;
+        
+        shell.run_sandboxed(&target, &policy)
+    }
+    
+    fn help(&self) -> &str {
+        "sandbox [options] [--] <command> [args...] - Run a command in an isolated environment\n\
+         Uses user, mount, IPC and UTS namespaces; needs unprivileged user namespaces.\n\
+         Options:\n\
+         --net=off       Run without network access (loopback only)\n\
+         --ro=PATH       Make PATH read-only inside the sandbox (repeatable)\n\
+         --mem=SIZE      Limit address space (K, M, G suffixes)\n\
+         --cpu=SECONDS   Limit CPU time\n\
+         --nofile=N      Limit open file descriptors\n\
+         --seccomp=off   Do not block mount, ptrace, module loading and similar calls"
+    }
+    
+    fn name(&self) -> &str {
+        "sandbox"
+    }
+}
+
+/**
+ * リソース制限を設定するulimitコマンドです
+ * 
+ * CPU時間（-t）、仮想メモリ（-v）、オープンファイル数（-n）、
+ * コアファイルサイズ（-c）の制限を表示・設定します。
+ * 
+ * 制限はシェル自身には適用されず、以降に起動される全ての
+ * 外部コマンドにexec直前のsetrlimit()で適用されます。
+ * -Sでソフトリミットのみ、-Hでハードリミットのみを設定し、
+ * どちらも指定しない場合は両方を設定します。
+ * 
+ * -aで全ての制限を一覧表示します。値には数値のほか
+ * unlimited、soft、hardを指定できます。
+ */
+pub struct UlimitCommand;
+
+impl CommandHandler for UlimitCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut soft = false;
+        let mut hard = false;
+        let mut show_all = false;
+        let mut resources_selected = Vec::new();
+        let mut value = None;
+        
+        for arg in &command.args {
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'S' => soft = true,
+                            'H' => hard = true,
+                            'a' => show_all = true,
+                            _ => resources_selected.push(
+                                LimitResource::from_flag(flag)
+                                    .ok_or_else(|| anyhow::anyhow!("-{}: invalid option", flag))?,
+                            ),
+                        }
+                    }
+                }
+                _ if value.is_none() => value = Some(arg.clone()),
+                _ => return Err(anyhow::anyhow!("too many arguments")),
+            }
+        }
+        
+        if show_all || (resources_selected.is_empty() && value.is_none()) {
+            let mut output = String::new();
+            for resource in LimitResource::ALL {
+                let (current_soft, current_hard) = shell.resource_limits().effective(resource)?;
+                let shown = if hard && !soft { current_hard } else { current_soft };
+                output.push_str(&format!("{} (-{}) {}\n",
+                    resource.description(), resource.flag(), ResourceLimits::format_value(resource, shown)));
+            }
+            return Ok(CommandResult {
+                output,
+                exit_code: 0,
+            });
+        }
+        
+        if resources_selected.is_empty() {
+            return Err(anyhow::anyhow!("specify a resource (-t, -v, -n or -c)"));
+        }
+        
+        let Some(value) = value else {
+            let mut output = String::new();
+            for resource in &resources_selected {
+                let (current_soft, current_hard) = shell.resource_limits().effective(*resource)?;
+                let shown = if hard && !soft { current_hard } else { current_soft };
+                output.push_str(&format!("{}\n", ResourceLimits::format_value(*resource, shown)));
+            }
+            return Ok(CommandResult {
+                output,
+                exit_code: 0,
+            });
+        };
+        
+        let (set_soft, set_hard) = if soft || hard { (soft, hard) } else { (true, true) };
+        
+        for resource in resources_selected {
+            let (current_soft, current_hard) = shell.resource_limits().effective(resource)?;
+            let limit = match value.as_str() {
+                "unlimited" => None,
+                "soft" => ResourceLimits::to_units(resource, current_soft),
+                "hard" => ResourceLimits::to_units(resource, current_hard),
+                number => Some(number.parse::<u64>().map_err(|_| anyhow::anyhow!("{}: invalid number", number))?),
+            };
+            
+            shell.resource_limits_mut().set(resource, limit, set_soft, set_hard)?;
+        }
+        
+        Ok(CommandResult {
+            output: String::new(),
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "ulimit [-SHa] [-t|-v|-n|-c] [limit] - Limit resources of commands started by the shell\n\
+         Limits apply to external commands, not to the shell itself.\n\
+         Options:\n\
+         -S    Set or show the soft limit\n\
+         -H    Set or show the hard limit\n\
+         -a    Show all limits\n\
+         -t    CPU time in seconds\n\
+         -v    Virtual memory in kbytes\n\
+         -n    Number of open files\n\
+         -c    Core file size in blocks\n\
+         Limit may be a number, unlimited, soft or hard."
+    }
+    
+    fn name(&self) -> &str {
+        "ulimit"
+    }
+}
+
+/**
+ * 標準入力からコマンドラインを組み立てて実行するxargsコマンドです
+ * 
+ * パイプラインの入力を空白（-0の場合はNUL文字）で区切って
+ * 引数にし、指定されたコマンド（デフォルトはecho）に渡します。
+ * -nで1回あたりの引数の数を制限し、-Iでは入力の各行について
+ * コマンド中の置換文字列を行の内容に置き換えて実行します。
+ * 
+ * -Pで外部コマンドを最大N個まで並列に実行します（0は無制限）。
+ * 各コマンドの出力はまとめて収集し、入力の順に表示します。
+ * 組み込みコマンドは常に順番に実行されます。
+ * 
+ * 終了コードはGNU xargsと同じで、コマンドが見つからない場合は
+ * 127、実行できない場合は126、いずれかが失敗した場合は123です。
+ */
+pub struct XargsCommand;
+
+impl CommandHandler for XargsCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut null_separated = false;
+        let mut max_args = None;
+        let mut replace = None;
+        let mut max_processes = 1;
+        let mut no_run_if_empty = false;
+        
+        let mut index = 0;
+        while let Some(arg) = command.args.get(index) {
+            index += 1;
+            let (option, attached) = match arg.as_str() {
+                "--" => break,
+                "-0" | "--null" => {
+                    null_separated = true;
+                    continue;
+                }
+                "-r" | "--no-run-if-empty" => {
+                    no_run_if_empty = true;
+                    continue;
+                }
+                "--max-args" => ('n', None),
+                "--replace" => ('I', None),
+                "--max-procs" => ('P', None),
+                _ if arg.len() >= 2 && arg.starts_with('-') && !arg.starts_with("--") => {
+                    let option = arg[1..].chars().next().unwrap_or('-');
+                    let rest = &arg[1 + option.len_utf8()..];
+                    (option, (!rest.is_empty()).then(|| rest.to_string()))
+                }
+                _ => {
+                    index -= 1;
+                    break;
+                }
+            };
+            if !"nIP".contains(option) {
+                return Err(anyhow::anyhow!("invalid option -- '{}'", option));
+            }
+            let value = match attached {
+                Some(value) => value,
+                None => {
+                    index += 1;
+                    command
+                        .args
+                        .get(index - 1)
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("option requires an argument -- '{}'", option))?
+                }
+            };
+            match option {
+                'n' => {
+                    let count: usize = value.parse().map_err(|_| anyhow::anyhow!("invalid number \"{}\" for -n option", value))?;
+                    if count == 0 {
+                        return Err(anyhow::anyhow!("value 0 for -n option should be >= 1"));
+                    }
+                    max_args = Some(count);
+                }
+                'I' => replace = Some(value),
+                _ => {
+                    max_processes = value.parse().map_err(|_| anyhow::anyhow!("invalid number \"{}\" for -P option", value))?;
+                }
+            }
+        }
+        
+        let mut template: Vec<String> = command.args[index..].to_vec();
+        if template.is_empty() {
+            template.push("echo".to_string());
+        }
+        let input = shell.take_pipeline_input().unwrap_or_default();
+        
+        let commands: Vec<Vec<String>> = match &replace {
+            Some(placeholder) => {
+                let items: Vec<&str> = if null_separated {
+                    input.split('\0').filter(|item| !item.is_empty()).collect()
+                } else {
+                    input.lines().map(str::trim_start).filter(|line| !line.is_empty()).collect()
+                };
+                items
+                    .into_iter()
+                    .map(|item| template.iter().map(|word| word.replace(placeholder.as_str(), item)).collect())
+                    .collect()
+            }
+            None => {
+                let items: Vec<String> = if null_separated {
+                    input.split('\0').filter(|item| !item.is_empty()).map(str::to_string).collect()
+                } else {
+                    split_xargs_input(&input)?
+                };
+                if items.is_empty() {
+                    if no_run_if_empty { Vec::new() } else { vec![template.clone()] }
+                } else {
+                    items
+                        .chunks(max_args.unwrap_or(items.len()))
+                        .map(|batch| template.iter().chain(batch).cloned().collect())
+                        .collect()
+                }
+            }
+        };
+        
+        let parsed: Vec<ParsedCommand> = commands.iter().map(|words| command_from_words(command, words)).collect();
+        if max_processes == 0 {
+            max_processes = parsed.len();
+        }
+        
+        let mut output = String::new();
+        let mut exit_code = 0;
+        for (words, result) in commands.iter().zip(shell.run_parallel(&parsed, max_processes)) {
+            let result = result.unwrap_or_else(|error| CommandResult {
+                output: format!("xargs: {}: {}\n", words[0], error),
+                exit_code: 1,
+            });
+            output.push_str(&result.output);
+            exit_code = match result.exit_code {
+                0 => exit_code,
+                126 | 127 => exit_code.max(result.exit_code),
+                _ => exit_code.max(123),
+            };
+        }
+        
+        Ok(CommandResult { output, exit_code })
+    }
+    
+    fn help(&self) -> &str {
+        "xargs [options] [command [args...]] - Build command lines from piped input\n\
+         Options:\n\
+         -0          Input items are separated by NUL (pairs with find -print0)\n\
+         -n <count>  Use at most <count> arguments per command\n\
+         -I <str>    Run once per input line, replacing <str> in the arguments\n\
+         -P <procs>  Run up to <procs> commands at once (0 = no limit)\n\
+         -r          Do not run the command if the input is empty"
+    }
+    
+    fn name(&self) -> &str {
+        "xargs"
+    }
+}
+
+/**
+ * Splits xargs input into arguments
+ * 
+ * Items are separated by blanks and newlines; single and double quotes
+ * group characters and a backslash escapes the next character.
+ * 
+ * @param input - Piped input
+ * @return Result<Vec<String>> - Arguments, or an error for an unmatched quote
+ */
+fn split_xargs_input(input: &str) -> Result<Vec<String>> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut in_item = false;
+    let mut chars = input.chars();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                in_item = true;
+                loop {
+                    match chars.next() {
+                        Some(quoted) if quoted == c => break,
+                        Some('\n') | None => {
+                            let kind = if c == '\'' { "single" } else { "double" };
+                            return Err(anyhow::anyhow!(
+                                "unmatched {} quote; by default quotes are special to xargs unless you use the -0 option",
+                                kind
+                            ));
+                        }
+                        Some(quoted) => current.push(quoted),
+                    }
+                }
+            }
+            '\\' => {
+                in_item = true;
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_item {
+                    items.push(std::mem::take(&mut current));
+                    in_item = false;
+                }
+            }
+            c => {
+                in_item = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_item {
+        items.push(current);
+    }
+    Ok(items)
+}
+
+/**
+ * ps command
+ * 
+ * Lists processes read from /proc in UNIX (-ef) or BSD (aux) style,
+ * optionally as a tree. When piped into another builtin the listing
+ * is also passed on as records.
+ */
+pub struct PsCommand;
+
+impl CommandHandler for PsCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let options = ps::PsOptions::parse(&command.args)?;
+        let line_width = if shell.output_is_terminal() && !options.wide {
+            Some(terminal_columns(shell.get_environment_variable("COLUMNS")))
+        } else {
+            None
+        };
+        
+        let result = ps::run(&options, line_width);
+        if !shell.output_is_terminal() {
+            shell.emit_records(result.records);
+        }
+        Ok(CommandResult {
+            output: result.output,
+            exit_code: result.exit_code,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "ps [options] - Report a snapshot of the current processes\n\
+         Selection:\n\
+         -e, -A          All processes\n\
+         -a              Processes with a terminal, except session leaders\n\
+         -p <pids>       Processes with the given IDs (also --pid, bare numbers)\n\
+         -u <users>      Processes of the given effective users (also --user)\n\
+         -U <users>      Processes of the given real users\n\
+         -C <names>      Processes with the given command names\n\
+         --ppid <pids>   Children of the given processes\n\
+         Format:\n\
+         -f              Full format\n\
+         -j              Jobs format\n\
+         -o <format>     Columns, e.g. -o pid,user,%cpu,etime,args or pid=ID\n\
+         --sort <keys>   Sort by columns, '-' for descending, e.g. --sort=-%mem\n\
+         --forest, -H    Show the process tree as ASCII art or indentation\n\
+         --no-headers    Leave out the header line\n\
+         -w              Do not truncate lines to the terminal width\n\
+         BSD style (no dash): a all users, x no terminal, u user format,\n\
+         f tree, r running only, w wide, h no header; e.g. 'ps aux', 'ps axf'"
+    }
+    
+    fn name(&self) -> &str {
+        "ps"
+    }
+}
+
+/**
+ * Parses the arguments common to pgrep and pkill
+ * 
+ * Options the shared parser does not know are passed to `extra`, which
+ * returns whether it consumed the rest of the argument.
+ * 
+ * @param args - Arguments
+ * @param extra - Handler for command specific options
+ * @return Result<ps::MatchOptions> - Match options or error
+ */
+fn parse_match_arguments(
+    args: &[String],
+    mut extra: impl FnMut(char, &str, &mut std::slice::Iter<'_, String>) -> Result<bool>,
+) -> Result<ps::MatchOptions> {
+    let mut options = ps::MatchOptions::default();
+    let mut args = args.iter();
+    
+    while let Some(arg) = args.next() {
+        let flags = match arg.strip_prefix('-') {
+            Some(flags) if !flags.is_empty() => flags,
+            _ => {
+                if options.pattern.is_some() {
+                    return Err(anyhow::anyhow!("only one pattern can be provided"));
+                }
+                options.pattern = Some(arg.clone());
+                continue;
+            }
+        };
+        
+        for (index, flag) in flags.char_indices() {
+            let rest = &flags[index + flag.len_utf8()..];
+            let consumed = match options.apply_flag(flag, rest, &mut args)? {
+                Some(consumed) => consumed,
+                None => extra(flag, rest, &mut args)?,
+            };
+            if consumed {
+                break;
+            }
+        }
+    }
+    options.validate()?;
+    Ok(options)
+}
+
+/**
+ * pgrep command
+ * 
+ * Prints the IDs of processes whose name matches a regular expression
+ * and that satisfy the user and parent filters.
+ */
+pub struct PgrepCommand;
+
+impl CommandHandler for PgrepCommand {
+    fn execute(&self, command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
+        let mut list_name = false;
+        let mut list_full = false;
+        let mut count = false;
+        let mut delimiter = "\n".to_string();
+        
+        let options = parse_match_arguments(&command.args, |flag, rest, args| {
+            match flag {
+                'l' => list_name = true,
+                'a' => list_full = true,
+                'c' => count = true,
+                'd' => {
+                    delimiter = if rest.is_empty() {
+                        args.next().cloned().ok_or_else(|| anyhow::anyhow!("option requires an argument -- 'd'"))?
+                    } else {
+                        rest.to_string()
+                    };
+                    return Ok(true);
+                }
+                _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+            }
+            Ok(false)
+        })?;
+        
+        let matches = ps::find_matches(&options)?;
+        let exit_code = if matches.is_empty() { 1 } else { 0 };
+        if count {
+            return Ok(CommandResult {
+                output: format!("{}\n", matches.len()),
+                exit_code,
+            });
+        }
+        
+        let items: Vec<String> = matches.iter().map(|process| {
+            if list_full {
+                let command_line = if process.cmdline.is_empty() { process.comm.clone() } else { process.cmdline.join(" ") };
+                format!("{} {}", process.pid, command_line)
+            } else if list_name {
+                format!("{} {}", process.pid, process.comm)
+            } else {
+                process.pid.to_string()
+            }
+        }).collect();
+        let mut output = items.join(&delimiter);
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        Ok(CommandResult {
+            output,
+            exit_code,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "pgrep [options] <pattern> - List processes by name\n\
+         The pattern is an extended regular expression matched against the\n\
+         process name.\n\
+         Options:\n\
+         -f             Match against the full command line\n\
+         -x             Require the whole name to match\n\
+         -i             Ignore case\n\
+         -v             Select processes that do not match\n\
+         -n, -o         Select only the newest or oldest match\n\
+         -u <users>     Only processes of the given effective users\n\
+         -U <users>     Only processes of the given real users\n\
+         -P <ppids>     Only children of the given processes\n\
+         -l             Show the process name with the ID\n\
+         -a             Show the full command line with the ID\n\
+         -c             Print the number of matches\n\
+         -d <delim>     Separate IDs with delim instead of newlines"
+    }
+    
+    fn name(&self) -> &str {
+        "pgrep"
+    }
+}
+
+/**
+ * pkill command
+ * 
+ * Sends a signal (SIGTERM by default) to the processes pgrep would list.
+ */
+pub struct PkillCommand;
+
+impl CommandHandler for PkillCommand {
+    fn execute(&self, command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
+        let mut signal = libc::SIGTERM;
+        let mut arguments = Vec::new();
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--signal") {
+                let value = match name.strip_prefix('=') {
+                    Some(value) => value.to_string(),
+                    None => args.next().cloned().ok_or_else(|| anyhow::anyhow!("option '--signal' requires an argument"))?,
+                };
+                signal = resources::parse_signal(&value)?;
+                continue;
+            }
+            let signal_name = arg.strip_prefix('-').filter(|name| {
+                name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                    && (name.len() > 1 || name.chars().all(|c| c.is_ascii_digit()))
+            });
+            match signal_name.map(resources::parse_signal) {
+                Some(Ok(number)) => signal = number,
+                _ => arguments.push(arg.clone()),
+            }
+        }
+        
+        let mut echo = false;
+        let mut count = false;
+        let options = parse_match_arguments(&arguments, |flag, _rest, _args| {
+            match flag {
+                'e' => echo = true,
+                'c' => count = true,
+                _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+            }
+            Ok(false)
+        })?;
+        
+        let mut output = String::new();
+        let mut signalled = 0;
+        for process in ps::find_matches(&options)? {
+            if unsafe { libc::kill(process.pid, signal) } == 0 {
+                signalled += 1;
+                if echo {
+                    output.push_str(&format!("{} killed (pid {})\n", process.comm, process.pid));
+                }
+            } else {
+                output.push_str(&format!("pkill: killing pid {} failed: {}\n", process.pid, std::io::Error::last_os_error()));
+            }
+        }
+        if count {
+            output.push_str(&format!("{}\n", signalled));
+        }
+        Ok(CommandResult {
+            output,
+            exit_code: if signalled > 0 { 0 } else { 1 },
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "pkill [-signal] [options] <pattern> - Signal processes by name\n\
+         Takes the same matching options as pgrep (-f, -x, -i, -v, -n, -o,\n\
+         -u, -U, -P).\n\
+         Options:\n\
+         -<signal>, --signal <signal>  Signal to send (default TERM), e.g. -9, -HUP\n\
+         -e                            Print each process that was signalled\n\
+         -c                            Print the number of signalled processes"
+    }
+    
+    fn name(&self) -> &str {
+        "pkill"
+    }
+}
+
+/**
+ * top command
+ * 
+ * Shows a live, sortable view of processes with CPU and memory
+ * charts. When the output is not a terminal, or with -b, it prints
+ * top's batch report instead.
+ */
+pub struct TopCommand;
+
+impl CommandHandler for TopCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut options = top::TopOptions::parse(&command.args)?;
+        let on_terminal = shell.output_is_terminal()
+            && unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::isatty(libc::STDOUT_FILENO) == 1 };
+        
+        let mut output = String::new();
+        if !options.batch && on_terminal {
+            top::run_interactive(&options)?;
+        } else if shell.output_is_terminal() {
+            let mut stdout = std::io::stdout();
+            top::run_batch(&options, &mut |report: &str| {
+                let _ = stdout.write_all(report.as_bytes());
+                let _ = stdout.flush();
+            });
+        } else {
+            options.iterations.get_or_insert(1);
+            top::run_batch(&options, &mut |report: &str| output.push_str(report));
+        }
+        
+        Ok(CommandResult {
+            output,
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "top [options] - Display processes, refreshing every few seconds\n\
+         Keys: q quit, arrows/PgUp/PgDn select, P sort by CPU, M by memory,\n\
+         N by PID, T by CPU time, R reverse order, k send SIGTERM to selection\n\
+         Options:\n\
+         -b            Batch mode: print reports instead of the full-screen view\n\
+         -n <count>    Exit after count updates (batch output defaults to 1 when piped)\n\
+         -d <seconds>  Delay between updates (default 3)\n\
+         -u <user>     Only processes of the given user\n\
+         -p <pids>     Only the given processes\n\
+         -o <field>    Sort by %CPU, %MEM, PID or TIME+ ('-' prefix for ascending)"
+    }
+    
+    fn name(&self) -> &str {
+        "top"
+    }
+}
@@ -0,0 +1,456 @@
+/*!
+ * @file procfs.rs
+ * @brief Process and system information from /proc
+ *
+ * This module reads /proc/[pid]/{stat,status,cmdline,statm} into
+ * process records and /proc/{stat,meminfo,uptime,loadavg} into a
+ * system snapshot for the ps, pgrep, pkill and top builtins.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file procfs.rs
+ * @description /proc/[pid] parsing, terminal names, CPU and memory
+ * totals, uptime and load averages.
+ */
+
+use std::os::unix::fs::MetadataExt;
+
+/**
+ * One process as read from /proc/[pid]
+ */
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    /// Process ID
+    pub pid: i32,
+    /// Parent process ID
+    pub ppid: i32,
+    /// Process group ID
+    pub pgrp: i32,
+    /// Session ID
+    pub session: i32,
+    /// Controlling terminal device number (0 when none)
+    pub tty_nr: i32,
+    /// Foreground process group of the terminal
+    pub tpgid: i32,
+    /// State letter (R, S, D, T, t, Z, I, ...)
+    pub state: char,
+    /// Command name from stat (at most 15 characters)
+    pub comm: String,
+    /// Arguments from cmdline; empty for kernel threads and zombies
+    pub cmdline: Vec<String>,
+    /// Real user ID
+    pub uid: u32,
+    /// Effective user ID
+    pub euid: u32,
+    /// User mode CPU time in clock ticks
+    pub utime: u64,
+    /// Kernel mode CPU time in clock ticks
+    pub stime: u64,
+    /// Kernel priority (20 for nice 0)
+    pub priority: i64,
+    /// Nice value
+    pub nice: i64,
+    /// Number of threads
+    pub threads: i64,
+    /// Start time in clock ticks after boot
+    pub start_time: u64,
+    /// Virtual memory size in KiB
+    pub vsize: u64,
+    /// Resident set size in KiB
+    pub rss: u64,
+    /// Resident shared memory in KiB
+    pub shared: u64,
+}
+
+impl ProcessInfo {
+    /**
+     * /proc/[pid]からプロセスの情報を読み込む関数です
+     *
+     * statのコマンド名は括弧や空白を含むことがあるため、最後の
+     * 「)」で区切ってから残りのフィールドを空白で分割します。
+     * 実UIDと実効UIDはstatusのUid行から、引数はcmdlineの
+     * NUL区切りから、共有メモリはstatmから読み込みます。
+     *
+     * 読み込みの途中でプロセスが終了した場合はNoneを返します。
+     *
+     * @param pid - プロセスID
+     * @param page_size - ページサイズ（KiB）
+     * @return Option<ProcessInfo> - プロセスの情報
+     */
+    pub fn read(pid: i32, page_size: u64) -> Option<Self> {
+        let directory = format!("/proc/{}", pid);
+        let stat = std::fs::read_to_string(format!("{}/stat", directory)).ok()?;
+        let open = stat.find('(')?;
+        let close = stat.rfind(')')?;
+        let comm = stat[open + 1..close].to_string();
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+        let field = |index: usize| -> i64 { fields.get(index).and_then(|value| value.parse().ok()).unwrap_or(0) };
+
+        let (mut uid, mut euid) = match std::fs::metadata(&directory) {
+            Ok(metadata) => (metadata.uid(), metadata.uid()),
+            Err(_) => (0, 0),
+        };
+        if let Ok(status) = std::fs::read_to_string(format!("{}/status", directory)) {
+            if let Some(line) = status.lines().find(|line| line.starts_with("Uid:")) {
+                let ids: Vec<u32> = line[4..].split_whitespace().filter_map(|id| id.parse().ok()).collect();
+                if ids.len() >= 2 {
+                    uid = ids[0];
+                    euid = ids[1];
+                }
+            }
+        }
+
+        let cmdline = std::fs::read(format!("{}/cmdline", directory))
+            .map(|data| {
+                data.split(|byte| *byte == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let shared = std::fs::read_to_string(format!("{}/statm", directory))
+            .ok()
+            .and_then(|statm| statm.split_whitespace().nth(2).and_then(|pages| pages.parse::<u64>().ok()))
+            .unwrap_or(0);
+
+        Some(ProcessInfo {
+            pid,
+            ppid: field(1) as i32,
+            pgrp: field(2) as i32,
+            session: field(3) as i32,
+            tty_nr: field(4) as i32,
+            tpgid: field(5) as i32,
+            state: fields.first().and_then(|state| state.chars().next()).unwrap_or('?'),
+            comm,
+            cmdline,
+            uid,
+            euid,
+            utime: field(11) as u64,
+            stime: field(12) as u64,
+            priority: field(15),
+            nice: field(16),
+            threads: field(17),
+            start_time: field(19) as u64,
+            vsize: field(20) as u64 / 1024,
+            rss: field(21).max(0) as u64 * page_size,
+            shared: shared * page_size,
+        })
+    }
+
+    /**
+     * Total CPU time used
+     *
+     * @return u64 - User plus system time in clock ticks
+     */
+    pub fn cpu_ticks(&self) -> u64 {
+        self.utime + self.stime
+    }
+
+    /**
+     * Full command line as ps prints it
+     *
+     * @return String - Arguments joined by spaces, or `[comm]` without arguments
+     */
+    pub fn command_line(&self) -> String {
+        if self.cmdline.is_empty() {
+            format!("[{}]", self.comm)
+        } else {
+            self.cmdline.join(" ")
+        }
+    }
+
+    /**
+     * Name of the controlling terminal
+     *
+     * @return String - e.g. "pts/0" or "tty1", or "?" without a terminal
+     */
+    pub fn tty_name(&self) -> String {
+        tty_name(self.tty_nr)
+    }
+}
+
+/**
+ * Names a terminal device number as ps does
+ *
+ * @param tty_nr - Device number from /proc/[pid]/stat
+ * @return String - "pts/N", "ttyN", "ttySN", or "?" when unknown
+ */
+pub fn tty_name(tty_nr: i32) -> String {
+    if tty_nr == 0 {
+        return "?".to_string();
+    }
+    let tty_nr = tty_nr as u32;
+    let major = (tty_nr >> 8) & 0xfff;
+    let minor = (tty_nr & 0xff) | ((tty_nr >> 12) & 0xfff00);
+    match major {
+        136..=143 => format!("pts/{}", minor + (major - 136) * 256),
+        4 if minor < 64 => format!("tty{}", minor),
+        4 => format!("ttyS{}", minor - 64),
+        _ => "?".to_string(),
+    }
+}
+
+/**
+ * Reads all processes
+ *
+ * Processes that exit while being read are skipped.
+ *
+ * @param system - System snapshot providing the page size
+ * @return Vec<ProcessInfo> - Processes sorted by PID
+ */
+pub fn read_processes(system: &SystemInfo) -> Vec<ProcessInfo> {
+    let mut processes: Vec<ProcessInfo> = std::fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse::<i32>().ok()))
+                .filter_map(|pid| ProcessInfo::read(pid, system.page_size))
+                .collect()
+        })
+        .unwrap_or_default();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+/**
+ * CPU times from a cpu line of /proc/stat
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuTimes {
+    /// Normal user time
+    pub user: u64,
+    /// Niced user time
+    pub nice: u64,
+    /// Kernel time
+    pub system: u64,
+    /// Idle time
+    pub idle: u64,
+    /// Waiting for I/O
+    pub iowait: u64,
+    /// Hardware interrupts
+    pub irq: u64,
+    /// Software interrupts
+    pub softirq: u64,
+    /// Time stolen by the hypervisor
+    pub steal: u64,
+}
+
+impl CpuTimes {
+    /**
+     * Parses the values of a cpu line of /proc/stat
+     *
+     * @param words - Fields after the cpu name
+     * @return CpuTimes - Times, zero where a field is missing
+     */
+    fn parse<'a>(words: impl Iterator<Item = &'a str>) -> CpuTimes {
+        let values: Vec<u64> = words.map(|value| value.parse().unwrap_or(0)).collect();
+        let value = |index: usize| values.get(index).copied().unwrap_or(0);
+        CpuTimes {
+            user: value(0),
+            nice: value(1),
+            system: value(2),
+            idle: value(3),
+            iowait: value(4),
+            irq: value(5),
+            softirq: value(6),
+            steal: value(7),
+        }
+    }
+
+    /**
+     * Sum of all times
+     *
+     * @return u64 - Total ticks
+     */
+    pub fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait + self.irq + self.softirq + self.steal
+    }
+
+    /**
+     * Differences to an earlier reading
+     *
+     * @param earlier - Earlier reading
+     * @return CpuTimes - Ticks spent in each state since then
+     */
+    pub fn since(&self, earlier: &CpuTimes) -> CpuTimes {
+        CpuTimes {
+            user: self.user.saturating_sub(earlier.user),
+            nice: self.nice.saturating_sub(earlier.nice),
+            system: self.system.saturating_sub(earlier.system),
+            idle: self.idle.saturating_sub(earlier.idle),
+            iowait: self.iowait.saturating_sub(earlier.iowait),
+            irq: self.irq.saturating_sub(earlier.irq),
+            softirq: self.softirq.saturating_sub(earlier.softirq),
+            steal: self.steal.saturating_sub(earlier.steal),
+        }
+    }
+}
+
+/**
+ * Memory figures from /proc/meminfo, in KiB
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryInfo {
+    /// MemTotal
+    pub total: u64,
+    /// MemFree
+    pub free: u64,
+    /// MemAvailable
+    pub available: u64,
+    /// Buffers
+    pub buffers: u64,
+    /// Cached plus SReclaimable
+    pub cached: u64,
+    /// SwapTotal
+    pub swap_total: u64,
+    /// SwapFree
+    pub swap_free: u64,
+}
+
+impl MemoryInfo {
+    /**
+     * Memory in use, as free and top count it
+     *
+     * @return u64 - Total minus available, in KiB
+     */
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+}
+
+/**
+ * System-wide snapshot
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SystemInfo {
+    /// Clock ticks per second
+    pub clock_ticks: u64,
+    /// Page size in KiB
+    pub page_size: u64,
+    /// Seconds since boot
+    pub uptime: f64,
+    /// Boot time as seconds since the epoch
+    pub boot_time: i64,
+    /// Memory figures
+    pub memory: MemoryInfo,
+    /// 1, 5 and 15 minute load averages
+    pub load: [f64; 3],
+    /// Aggregate CPU times
+    pub cpu: CpuTimes,
+    /// CPU times of each core, in /proc/stat order
+    pub cpus: Vec<CpuTimes>,
+}
+
+impl SystemInfo {
+    /**
+     * Reads the current system snapshot
+     *
+     * Missing files leave the corresponding fields at zero.
+     *
+     * @return SystemInfo - Snapshot
+     */
+    pub fn read() -> Self {
+        let clock_ticks = match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+            ticks if ticks > 0 => ticks as u64,
+            _ => 100,
+        };
+        let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as u64 / 1024,
+            _ => 4,
+        };
+        let mut info = SystemInfo {
+            clock_ticks,
+            page_size,
+            ..SystemInfo::default()
+        };
+
+        if let Ok(uptime) = std::fs::read_to_string("/proc/uptime") {
+            info.uptime = uptime.split_whitespace().next().and_then(|value| value.parse().ok()).unwrap_or(0.0);
+        }
+        if let Ok(loadavg) = std::fs::read_to_string("/proc/loadavg") {
+            for (slot, value) in info.load.iter_mut().zip(loadavg.split_whitespace()) {
+                *slot = value.parse().unwrap_or(0.0);
+            }
+        }
+        if let Ok(stat) = std::fs::read_to_string("/proc/stat") {
+            for line in stat.lines() {
+                let mut words = line.split_whitespace();
+                match words.next() {
+                    Some("cpu") => info.cpu = CpuTimes::parse(words),
+                    Some(name) if name.starts_with("cpu") => info.cpus.push(CpuTimes::parse(words)),
+                    Some("btime") => info.boot_time = words.next().and_then(|value| value.parse().ok()).unwrap_or(0),
+                    _ => {}
+                }
+            }
+        }
+        if let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") {
+            for line in meminfo.lines() {
+                let Some((key, rest)) = line.split_once(':') else {
+                    continue;
+                };
+                let value: u64 = rest.split_whitespace().next().and_then(|value| value.parse().ok()).unwrap_or(0);
+                match key {
+                    "MemTotal" => info.memory.total = value,
+                    "MemFree" => info.memory.free = value,
+                    "MemAvailable" => info.memory.available = value,
+                    "Buffers" => info.memory.buffers = value,
+                    "Cached" | "SReclaimable" => info.memory.cached += value,
+                    "SwapTotal" => info.memory.swap_total = value,
+                    "SwapFree" => info.memory.swap_free = value,
+                    _ => {}
+                }
+            }
+        }
+        info
+    }
+
+    /**
+     * Seconds a process has been running
+     *
+     * @param process - Process
+     * @return f64 - Elapsed seconds since the process started
+     */
+    pub fn elapsed(&self, process: &ProcessInfo) -> f64 {
+        (self.uptime - process.start_time as f64 / self.clock_ticks as f64).max(0.0)
+    }
+
+    /**
+     * Start time of a process as a local timestamp
+     *
+     * @param process - Process
+     * @return chrono::DateTime<chrono::Local> - When the process started
+     */
+    pub fn start_time(&self, process: &ProcessInfo) -> chrono::DateTime<chrono::Local> {
+        let seconds = self.boot_time + (process.start_time / self.clock_ticks) as i64;
+        chrono::DateTime::from_timestamp(seconds, 0)
+            .unwrap_or_default()
+            .with_timezone(&chrono::Local)
+    }
+
+    /**
+     * Lifetime CPU usage of a process as ps reports it
+     *
+     * @param process - Process
+     * @return f64 - CPU time divided by elapsed time, in percent
+     */
+    pub fn cpu_percent(&self, process: &ProcessInfo) -> f64 {
+        let elapsed = self.elapsed(process);
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        process.cpu_ticks() as f64 / self.clock_ticks as f64 / elapsed * 100.0
+    }
+
+    /**
+     * Resident memory of a process relative to total memory
+     *
+     * @param process - Process
+     * @return f64 - Percentage of MemTotal
+     */
+    pub fn memory_percent(&self, process: &ProcessInfo) -> f64 {
+        if self.memory.total == 0 {
+            return 0.0;
+        }
+        process.rss as f64 / self.memory.total as f64 * 100.0
+    }
+}
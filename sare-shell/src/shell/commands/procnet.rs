@@ -0,0 +1,441 @@
+/*!
+ * @file procnet.rs
+ * @brief Socket table reader for /proc/net
+ *
+ * This module reads the kernel socket tables under /proc/net and
+ * maps socket inodes to the processes holding them. It backs the
+ * `netstat` and `ss` builtins.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file procnet.rs
+ * @description Parsers for /proc/net/{tcp,tcp6,udp,udp6,unix}, inode to
+ * PID mapping via /proc/<pid>/fd and service/host name lookup.
+ */
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+/**
+ * Socket protocol family
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketProtocol {
+    Tcp,
+    Tcp6,
+    Udp,
+    Udp6,
+}
+
+impl SocketProtocol {
+    /**
+     * Gets the /proc/net file name and netstat label
+     *
+     * @return &str - "tcp", "tcp6", "udp" or "udp6"
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            SocketProtocol::Tcp => "tcp",
+            SocketProtocol::Tcp6 => "tcp6",
+            SocketProtocol::Udp => "udp",
+            SocketProtocol::Udp6 => "udp6",
+        }
+    }
+
+    /**
+     * Checks whether this is a TCP table
+     *
+     * @return bool - True for tcp and tcp6
+     */
+    pub fn is_tcp(&self) -> bool {
+        matches!(self, SocketProtocol::Tcp | SocketProtocol::Tcp6)
+    }
+}
+
+/**
+ * Internet socket from /proc/net/{tcp,udp}[6]
+ */
+#[derive(Debug, Clone)]
+pub struct InetSocket {
+    /// Protocol table the socket came from
+    pub protocol: SocketProtocol,
+    /// Local address
+    pub local_address: IpAddr,
+    /// Local port
+    pub local_port: u16,
+    /// Remote address
+    pub remote_address: IpAddr,
+    /// Remote port (0 when unconnected)
+    pub remote_port: u16,
+    /// Kernel state number
+    pub state: u8,
+    /// Bytes in the receive queue
+    pub receive_queue: u64,
+    /// Bytes in the send queue
+    pub send_queue: u64,
+    /// Owning user ID
+    pub uid: u32,
+    /// Socket inode
+    pub inode: u64,
+}
+
+impl InetSocket {
+    /**
+     * Gets the state name
+     *
+     * UDP sockets have no state, except connected ones which netstat
+     * reports as ESTABLISHED.
+     *
+     * @return &str - State name, or "" for unconnected UDP
+     */
+    pub fn state_name(&self) -> &'static str {
+        if !self.protocol.is_tcp() {
+            return if self.state == 1 { "ESTABLISHED" } else { "" };
+        }
+        match self.state {
+            0x01 => "ESTABLISHED",
+            0x02 => "SYN_SENT",
+            0x03 => "SYN_RECV",
+            0x04 => "FIN_WAIT1",
+            0x05 => "FIN_WAIT2",
+            0x06 => "TIME_WAIT",
+            0x07 => "CLOSE",
+            0x08 => "CLOSE_WAIT",
+            0x09 => "LAST_ACK",
+            0x0A => "LISTEN",
+            0x0B => "CLOSING",
+            _ => "UNKNOWN",
+        }
+    }
+
+    /**
+     * Checks whether the socket accepts connections or datagrams
+     *
+     * @return bool - True for TCP LISTEN and unconnected UDP
+     */
+    pub fn is_listening(&self) -> bool {
+        if self.protocol.is_tcp() {
+            self.state == 0x0A
+        } else {
+            self.state == 0x07 && self.remote_port == 0
+        }
+    }
+}
+
+/**
+ * Unix domain socket from /proc/net/unix
+ */
+#[derive(Debug, Clone)]
+pub struct UnixSocket {
+    /// Reference count
+    pub ref_count: u32,
+    /// Whether the socket is accepting connections
+    pub accepting: bool,
+    /// Socket type number (1 stream, 2 dgram, 5 seqpacket)
+    pub socket_type: u16,
+    /// Kernel state number
+    pub state: u8,
+    /// Socket inode
+    pub inode: u64,
+    /// Bound path, `@name` for abstract sockets
+    pub path: Option<String>,
+}
+
+impl UnixSocket {
+    /**
+     * Gets the type name
+     *
+     * @return &str - STREAM, DGRAM, SEQPACKET or UNKNOWN
+     */
+    pub fn type_name(&self) -> &'static str {
+        match self.socket_type {
+            1 => "STREAM",
+            2 => "DGRAM",
+            5 => "SEQPACKET",
+            _ => "UNKNOWN",
+        }
+    }
+
+    /**
+     * Gets the state name as netstat prints it
+     *
+     * @return &str - State name
+     */
+    pub fn state_name(&self) -> &'static str {
+        if self.accepting {
+            return "LISTENING";
+        }
+        match self.state {
+            0x01 => "",
+            0x02 => "CONNECTING",
+            0x03 => "CONNECTED",
+            0x04 => "DISCONNECTING",
+            _ => "UNKNOWN",
+        }
+    }
+}
+
+/**
+ * Process holding a socket
+ */
+#[derive(Debug, Clone)]
+pub struct SocketOwner {
+    /// Process ID
+    pub pid: u32,
+    /// Command name from /proc/<pid>/comm
+    pub name: String,
+    /// File descriptor number
+    pub fd: u32,
+}
+
+/**
+ * Reads one Internet socket table
+ *
+ * A missing table (for example tcp6 with IPv6 disabled) yields an
+ * empty list.
+ *
+ * @param protocol - Table to read
+ * @return Result<Vec<InetSocket>> - Sockets
+ */
+pub fn read_inet_sockets(protocol: SocketProtocol) -> Result<Vec<InetSocket>> {
+    let path = Path::new("/proc/net").join(protocol.name());
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow::anyhow!("{}: {}", path.display(), e)),
+    };
+
+    Ok(content.lines().skip(1).filter_map(|line| parse_inet_line(protocol, line)).collect())
+}
+
+/**
+ * Parses one line of a /proc/net/{tcp,udp}[6] table
+ *
+ * @param protocol - Table the line came from
+ * @param line - Table line
+ * @return Option<InetSocket> - Socket, or None for malformed lines
+ */
+fn parse_inet_line(protocol: SocketProtocol, line: &str) -> Option<InetSocket> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        return None;
+    }
+
+    let (local_address, local_port) = parse_hex_endpoint(fields[1])?;
+    let (remote_address, remote_port) = parse_hex_endpoint(fields[2])?;
+    let (send_queue, receive_queue) = fields[4].split_once(':')?;
+
+    Some(InetSocket {
+        protocol,
+        local_address,
+        local_port,
+        remote_address,
+        remote_port,
+        state: u8::from_str_radix(fields[3], 16).ok()?,
+        receive_queue: u64::from_str_radix(receive_queue, 16).ok()?,
+        send_queue: u64::from_str_radix(send_queue, 16).ok()?,
+        uid: fields[7].parse().ok()?,
+        inode: fields[9].parse().ok()?,
+    })
+}
+
+/**
+ * Decodes a kernel `ADDRESS:PORT` hex endpoint
+ *
+ * Addresses are written as 32-bit words in host byte order; the port
+ * is big-endian hex.
+ *
+ * @param text - Endpoint such as "0100007F:1F90"
+ * @return Option<(IpAddr, u16)> - Address and port
+ */
+fn parse_hex_endpoint(text: &str) -> Option<(IpAddr, u16)> {
+    let (address, port) = text.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    let mut words = Vec::with_capacity(4);
+    for chunk in address.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+        words.push(word.to_ne_bytes());
+    }
+
+    let address = match words.as_slice() {
+        [word] => IpAddr::V4(Ipv4Addr::from(*word)),
+        [a, b, c, d] => {
+            let mut octets = [0u8; 16];
+            for (index, word) in [a, b, c, d].iter().enumerate() {
+                octets[index * 4..index * 4 + 4].copy_from_slice(*word);
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+
+    Some((address, port))
+}
+
+/**
+ * Reads /proc/net/unix
+ *
+ * @return Result<Vec<UnixSocket>> - Unix domain sockets
+ */
+pub fn read_unix_sockets() -> Result<Vec<UnixSocket>> {
+    let content = match std::fs::read_to_string("/proc/net/unix") {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow::anyhow!("/proc/net/unix: {}", e)),
+    };
+
+    Ok(content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            let flags = u32::from_str_radix(fields[3], 16).ok()?;
+            Some(UnixSocket {
+                ref_count: u32::from_str_radix(fields[1], 16).ok()?,
+                accepting: flags & 0x0001_0000 != 0,
+                socket_type: u16::from_str_radix(fields[4], 16).ok()?,
+                state: u8::from_str_radix(fields[5], 16).ok()?,
+                inode: fields[6].parse().ok()?,
+                path: fields.get(7).map(|path| path.to_string()),
+            })
+        })
+        .collect())
+}
+
+/**
+ * ソケットのinodeとプロセスを対応付ける関数です
+ *
+ * /proc/<pid>/fd以下のシンボリックリンクを読み、
+ * `socket:[inode]`を指すものを記録します。権限がなく
+ * 読めないプロセスは黙ってスキップするため、root以外では
+ * 自分のプロセスのソケットだけが対応付けられます。
+ *
+ * @return HashMap<u64, SocketOwner> - inodeからプロセスへの対応表
+ */
+pub fn socket_owners() -> HashMap<u64, SocketOwner> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return owners;
+    };
+
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let name = std::fs::read_to_string(entry.path().join("comm"))
+            .map(|comm| comm.trim_end().to_string())
+            .unwrap_or_default();
+
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let target = target.to_string_lossy();
+            let Some(inode) = target
+                .strip_prefix("socket:[")
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|inode| inode.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            let fd = fd.file_name().to_str().and_then(|n| n.parse().ok()).unwrap_or(0);
+            owners.entry(inode).or_insert_with(|| SocketOwner {
+                pid,
+                name: name.clone(),
+                fd,
+            });
+        }
+    }
+
+    owners
+}
+
+/**
+ * Resolves ports and addresses to names for non-numeric output
+ */
+#[derive(Debug, Default)]
+pub struct NameResolver {
+    /// Service names by (port, protocol)
+    services: HashMap<(u16, String), String>,
+    /// Host names by address from /etc/hosts
+    hosts: HashMap<IpAddr, String>,
+}
+
+impl NameResolver {
+    /**
+     * Loads /etc/services and /etc/hosts
+     *
+     * Reverse DNS is not used, so lookups never block on the network.
+     *
+     * @return NameResolver - Resolver (empty tables if files are missing)
+     */
+    pub fn load() -> Self {
+        let mut resolver = Self::default();
+
+        if let Ok(content) = std::fs::read_to_string("/etc/services") {
+            for line in content.lines() {
+                let line = line.split('#').next().unwrap_or("");
+                let mut fields = line.split_whitespace();
+                let (Some(name), Some(port)) = (fields.next(), fields.next()) else {
+                    continue;
+                };
+                if let Some((port, protocol)) = port.split_once('/') {
+                    if let Ok(port) = port.parse::<u16>() {
+                        resolver.services.entry((port, protocol.to_string())).or_insert_with(|| name.to_string());
+                    }
+                }
+            }
+        }
+
+        if let Ok(content) = std::fs::read_to_string("/etc/hosts") {
+            for line in content.lines() {
+                let line = line.split('#').next().unwrap_or("");
+                let mut fields = line.split_whitespace();
+                let (Some(address), Some(name)) = (fields.next(), fields.next()) else {
+                    continue;
+                };
+                if let Ok(address) = address.parse::<IpAddr>() {
+                    resolver.hosts.entry(address).or_insert_with(|| name.to_string());
+                }
+            }
+        }
+
+        resolver
+    }
+
+    /**
+     * Formats an endpoint as netstat does
+     *
+     * @param address - IP address
+     * @param port - Port, 0 printed as `*`
+     * @param protocol - "tcp" or "udp", for service lookup
+     * @param numeric - Skip name lookup
+     * @return String - Endpoint such as "0.0.0.0:22" or "localhost:ssh"
+     */
+    pub fn endpoint(&self, address: IpAddr, port: u16, protocol: &str, numeric: bool) -> String {
+        let host = if numeric || address.is_unspecified() {
+            address.to_string()
+        } else {
+            self.hosts.get(&address).cloned().unwrap_or_else(|| address.to_string())
+        };
+
+        let port = if port == 0 {
+            "*".to_string()
+        } else if numeric {
+            port.to_string()
+        } else {
+            self.services.get(&(port, protocol.to_string())).cloned().unwrap_or_else(|| port.to_string())
+        };
+
+        format!("{}:{}", host, port)
+    }
+}
@@ -0,0 +1,952 @@
+/*!
+ * @file ps.rs
+ * @brief Process selection and formatting for ps, pgrep and pkill
+ *
+ * This module parses ps options in both the UNIX (-ef) and BSD (aux)
+ * styles, selects processes read from /proc, sorts them and formats
+ * the columns the way procps-ng does, including the --forest view.
+ * It also matches processes by name and owner for pgrep and pkill.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file ps.rs
+ * @description ps option parsing, -o format specifiers, --sort keys,
+ * process trees, column formatting with procps widths and pgrep
+ * pattern matching.
+ */
+
+use anyhow::Result;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::shell::commands::listing::{display_width, OwnerNames};
+use crate::shell::commands::procfs::{self, ProcessInfo, SystemInfo};
+use crate::shell::commands::records::Table;
+
+/**
+ * Kind of a ps column
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    /// Process ID
+    Pid,
+    /// Parent process ID
+    Ppid,
+    /// Process group ID
+    Pgid,
+    /// Session ID
+    Sid,
+    /// Foreground process group of the terminal
+    Tpgid,
+    /// Effective user ID
+    Uid,
+    /// Real user ID
+    Ruid,
+    /// Effective user name
+    User,
+    /// Real user name
+    Ruser,
+    /// Integer CPU usage
+    C,
+    /// CPU usage in percent
+    Pcpu,
+    /// Resident memory in percent
+    Pmem,
+    /// Virtual memory in KiB
+    Vsz,
+    /// Resident memory in KiB
+    Rss,
+    /// Controlling terminal
+    Tty,
+    /// State with BSD flags
+    Stat,
+    /// State letter
+    State,
+    /// Start time, HH:MM:SS today
+    Start,
+    /// Start time, HH:MM today
+    Stime,
+    /// CPU time as [DD-]HH:MM:SS
+    Time,
+    /// CPU time as M:SS
+    BsdTime,
+    /// Elapsed time as [[DD-]HH:]MM:SS
+    Etime,
+    /// Elapsed seconds
+    Etimes,
+    /// Nice value
+    Nice,
+    /// Priority
+    Pri,
+    /// Number of threads
+    Nlwp,
+    /// Command name
+    Comm,
+    /// Command line
+    Args,
+}
+
+impl FieldKind {
+    /**
+     * Looks up a format specifier
+     *
+     * @param name - Specifier such as `pid`, `%cpu` or `args`
+     * @return Option<(FieldKind, &str)> - Kind and default header
+     */
+    pub fn from_name(name: &str) -> Option<(FieldKind, &'static str)> {
+        let field = match name {
+            "pid" | "tgid" => (FieldKind::Pid, "PID"),
+            "ppid" => (FieldKind::Ppid, "PPID"),
+            "pgid" | "pgrp" => (FieldKind::Pgid, "PGID"),
+            "sid" | "sess" | "session" => (FieldKind::Sid, "SID"),
+            "tpgid" => (FieldKind::Tpgid, "TPGID"),
+            "uid" | "euid" => (FieldKind::Uid, "UID"),
+            "ruid" => (FieldKind::Ruid, "RUID"),
+            "user" | "euser" | "uname" => (FieldKind::User, "USER"),
+            "ruser" => (FieldKind::Ruser, "RUSER"),
+            "c" => (FieldKind::C, "C"),
+            "pcpu" | "%cpu" => (FieldKind::Pcpu, "%CPU"),
+            "pmem" | "%mem" => (FieldKind::Pmem, "%MEM"),
+            "vsz" | "vsize" => (FieldKind::Vsz, "VSZ"),
+            "rss" | "rssize" | "rsz" => (FieldKind::Rss, "RSS"),
+            "tty" | "tt" | "tname" => (FieldKind::Tty, "TT"),
+            "stat" => (FieldKind::Stat, "STAT"),
+            "s" | "state" => (FieldKind::State, "S"),
+            "start" | "lstart" => (FieldKind::Start, "STARTED"),
+            "stime" => (FieldKind::Stime, "STIME"),
+            "start_time" => (FieldKind::Stime, "START"),
+            "time" | "cputime" => (FieldKind::Time, "TIME"),
+            "bsdtime" => (FieldKind::BsdTime, "TIME"),
+            "etime" => (FieldKind::Etime, "ELAPSED"),
+            "etimes" => (FieldKind::Etimes, "ELAPSED"),
+            "nice" | "ni" => (FieldKind::Nice, "NI"),
+            "pri" => (FieldKind::Pri, "PRI"),
+            "nlwp" | "thcount" => (FieldKind::Nlwp, "NLWP"),
+            "comm" | "ucmd" | "ucomm" => (FieldKind::Comm, "COMMAND"),
+            "args" | "command" => (FieldKind::Args, "COMMAND"),
+            "cmd" => (FieldKind::Args, "CMD"),
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    /**
+     * Minimum column width used by procps
+     *
+     * @return usize - Width in characters
+     */
+    fn width(&self) -> usize {
+        match self {
+            FieldKind::Pid | FieldKind::Ppid | FieldKind::Pgid | FieldKind::Sid | FieldKind::Tpgid | FieldKind::Uid | FieldKind::Ruid => 5,
+            FieldKind::Rss | FieldKind::Stime => 5,
+            FieldKind::User | FieldKind::Ruser | FieldKind::Tty | FieldKind::Time | FieldKind::Start => 8,
+            FieldKind::C => 2,
+            FieldKind::Pcpu | FieldKind::Pmem | FieldKind::Stat | FieldKind::Nlwp => 4,
+            FieldKind::Vsz | FieldKind::BsdTime => 6,
+            FieldKind::State => 1,
+            FieldKind::Etime => 11,
+            FieldKind::Etimes => 7,
+            FieldKind::Nice | FieldKind::Pri => 3,
+            FieldKind::Comm | FieldKind::Args => 0,
+        }
+    }
+
+    /**
+     * Whether values are right-aligned
+     *
+     * @return bool - True for numbers and times
+     */
+    fn right_aligned(&self) -> bool {
+        !matches!(
+            self,
+            FieldKind::User | FieldKind::Ruser | FieldKind::Tty | FieldKind::Stat | FieldKind::State | FieldKind::Stime | FieldKind::Comm | FieldKind::Args
+        )
+    }
+
+    /**
+     * Whether the column shows the command, which carries the tree art
+     *
+     * @return bool - True for comm, args and cmd
+     */
+    fn is_command(&self) -> bool {
+        matches!(self, FieldKind::Comm | FieldKind::Args)
+    }
+}
+
+/**
+ * One output column
+ */
+#[derive(Debug, Clone)]
+pub struct Field {
+    /// What the column shows
+    pub kind: FieldKind,
+    /// Header text
+    pub header: String,
+}
+
+/**
+ * Parses a -o format list such as `pid,user,args` or `pid=ID,user=Owner`
+ *
+ * @param list - Format list, separated by commas or, without headers, blanks
+ * @return Result<Vec<Field>> - Columns or unknown specifier error
+ */
+pub fn parse_format(list: &str) -> Result<Vec<Field>> {
+    let mut fields = Vec::new();
+    for item in list.split(',') {
+        let (names, header) = match item.split_once('=') {
+            Some((name, header)) => (name, Some(header)),
+            None => (item, None),
+        };
+        for name in names.split_whitespace() {
+            let (kind, default_header) = FieldKind::from_name(name)
+                .ok_or_else(|| anyhow::anyhow!("unknown user-defined format specifier \"{}\"", name))?;
+            fields.push(Field {
+                kind,
+                header: header.unwrap_or(default_header).to_string(),
+            });
+        }
+    }
+    Ok(fields)
+}
+
+/**
+ * Options of a ps invocation
+ */
+#[derive(Debug, Clone, Default)]
+pub struct PsOptions {
+    /// Columns; empty selects the style's default format
+    pub fields: Vec<Field>,
+    /// Select every process (-e, -A)
+    pub all: bool,
+    /// BSD syntax was used
+    pub bsd: bool,
+    /// BSD `a`: processes of all users
+    pub bsd_all_users: bool,
+    /// BSD `x`: include processes without a terminal
+    pub bsd_no_tty: bool,
+    /// BSD `u`: user-oriented format
+    pub bsd_user_format: bool,
+    /// SysV -a: all processes with a terminal except session leaders
+    pub terminal_processes: bool,
+    /// Only running processes (BSD `r`)
+    pub running_only: bool,
+    /// Full format (-f)
+    pub full: bool,
+    /// Jobs format (-j)
+    pub jobs: bool,
+    /// Selected PIDs (-p, --pid, bare numbers)
+    pub pids: Vec<i32>,
+    /// Selected parent PIDs (--ppid)
+    pub ppids: Vec<i32>,
+    /// Selected effective user IDs (-u, --user)
+    pub users: Vec<u32>,
+    /// Selected real user IDs (-U)
+    pub real_users: Vec<u32>,
+    /// Selected command names (-C)
+    pub commands: Vec<String>,
+    /// Sort keys with descending flags (--sort)
+    pub sort: Vec<(FieldKind, bool)>,
+    /// ASCII-art process tree (--forest, BSD `f`)
+    pub forest: bool,
+    /// Indented hierarchy (-H)
+    pub hierarchy: bool,
+    /// Omit the header line
+    pub no_headers: bool,
+    /// Do not truncate lines (-w, BSD `w`)
+    pub wide: bool,
+}
+
+/**
+ * Parses a comma or space separated list
+ *
+ * @param list - List text
+ * @param parse - Parser for one item
+ * @return Result<Vec<T>> - Items or the first parse error
+ */
+fn parse_list<T>(list: &str, parse: impl Fn(&str) -> Result<T>) -> Result<Vec<T>> {
+    list.split([',', ' ']).filter(|item| !item.is_empty()).map(parse).collect()
+}
+
+/**
+ * Parses a process ID
+ *
+ * @param value - PID text
+ * @return Result<i32> - PID or error
+ */
+fn parse_pid(value: &str) -> Result<i32> {
+    value.parse().map_err(|_| anyhow::anyhow!("process ID list syntax error: '{}'", value))
+}
+
+/**
+ * Resolves a user name or numeric ID
+ *
+ * @param value - User name or UID
+ * @return Result<u32> - UID or error
+ */
+pub fn parse_user(value: &str) -> Result<u32> {
+    if let Ok(uid) = value.parse() {
+        return Ok(uid);
+    }
+    match nix::unistd::User::from_name(value) {
+        Ok(Some(user)) => Ok(user.uid.as_raw()),
+        _ => Err(anyhow::anyhow!("user name does not exist: {}", value)),
+    }
+}
+
+/**
+ * Parses a --sort key list such as `-pcpu,+pid`
+ *
+ * @param list - Key list
+ * @return Result<Vec<(FieldKind, bool)>> - Keys with descending flags
+ */
+fn parse_sort(list: &str) -> Result<Vec<(FieldKind, bool)>> {
+    parse_list(list, |key| {
+        let (descending, name) = match key.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, key.trim_start_matches('+')),
+        };
+        let (kind, _) = FieldKind::from_name(name).ok_or_else(|| anyhow::anyhow!("unknown sort specifier '{}'", name))?;
+        Ok((kind, descending))
+    })
+}
+
+impl PsOptions {
+    /**
+     * psの引数を解析する関数です
+     *
+     * 「-」で始まる引数はUNIX形式（-e、-f、-u ユーザーなど）、
+     * 「-」のない英字の引数はBSD形式（aux、axfなど）、数字だけの
+     * 引数はPIDとして扱います。--forest、--sort、--pid、--ppid、
+     * --user、--format、--no-headersの長いオプションも使えます。
+     *
+     * 引数を取るオプション（-o、-p、-u、-U、-C）は、値を続けて
+     * 書くこと（-opid,comm）も次の引数にすることもできます。
+     *
+     * @param args - 引数
+     * @return Result<PsOptions> - オプションまたはエラー
+     */
+    pub fn parse(args: &[String]) -> Result<PsOptions> {
+        let mut options = PsOptions::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let mut value = || -> Result<String> {
+                    match &inline {
+                        Some(value) => Ok(value.clone()),
+                        None => args.next().cloned().ok_or_else(|| anyhow::anyhow!("option '--{}' requires an argument", name)),
+                    }
+                };
+                match name {
+                    "forest" => options.forest = true,
+                    "no-headers" | "no-heading" => options.no_headers = true,
+                    "sort" => options.sort.extend(parse_sort(&value()?)?),
+                    "pid" => options.pids.extend(parse_list(&value()?, parse_pid)?),
+                    "ppid" => options.ppids.extend(parse_list(&value()?, parse_pid)?),
+                    "user" => options.users.extend(parse_list(&value()?, parse_user)?),
+                    "format" => options.fields.extend(parse_format(&value()?)?),
+                    _ => return Err(anyhow::anyhow!("unknown option '--{}'", name)),
+                }
+                continue;
+            }
+
+            if let Some(flags) = arg.strip_prefix('-') {
+                for (index, flag) in flags.char_indices() {
+                    let rest = &flags[index + flag.len_utf8()..];
+                    if "opuUCO".contains(flag) {
+                        let value = if rest.is_empty() {
+                            args.next().cloned().ok_or_else(|| anyhow::anyhow!("option -{} requires an argument", flag))?
+                        } else {
+                            rest.to_string()
+                        };
+                        match flag {
+                            'o' | 'O' => options.fields.extend(parse_format(&value)?),
+                            'p' => options.pids.extend(parse_list(&value, parse_pid)?),
+                            'u' => options.users.extend(parse_list(&value, parse_user)?),
+                            'U' => options.real_users.extend(parse_list(&value, parse_user)?),
+                            _ => options.commands.extend(value.split(',').map(str::to_string)),
+                        }
+                        break;
+                    }
+                    match flag {
+                        'e' | 'A' => options.all = true,
+                        'a' => options.terminal_processes = true,
+                        'f' => options.full = true,
+                        'j' => options.jobs = true,
+                        'H' => options.hierarchy = true,
+                        'w' => options.wide = true,
+                        _ => return Err(anyhow::anyhow!("unsupported option -{}", flag)),
+                    }
+                }
+                continue;
+            }
+
+            if arg.chars().all(|c| c.is_ascii_digit() || c == ',') {
+                options.pids.extend(parse_list(arg, parse_pid)?);
+                continue;
+            }
+
+            options.bsd = true;
+            for flag in arg.chars() {
+                match flag {
+                    'a' => options.bsd_all_users = true,
+                    'x' => options.bsd_no_tty = true,
+                    'u' => options.bsd_user_format = true,
+                    'f' => options.forest = true,
+                    'r' => options.running_only = true,
+                    'w' => options.wide = true,
+                    'h' => options.no_headers = true,
+                    _ => return Err(anyhow::anyhow!("unsupported option (BSD syntax) '{}'", flag)),
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /**
+     * Columns to print
+     *
+     * @return Vec<Field> - -o columns, or the default of the chosen style
+     */
+    fn columns(&self) -> Vec<Field> {
+        if !self.fields.is_empty() {
+            return self.fields.clone();
+        }
+        let format = if self.bsd_user_format {
+            "user,pid,%cpu,%mem,vsz,rss,tty=TTY,stat,start_time,bsdtime,args"
+        } else if self.bsd {
+            "pid,tty=TTY,stat,bsdtime,args"
+        } else if self.full {
+            "user=UID,pid,ppid,c,stime,tty=TTY,time,cmd"
+        } else if self.jobs {
+            "pid,pgid,sid,tty=TTY,time,comm=CMD"
+        } else {
+            "pid,tty=TTY,time,comm=CMD"
+        };
+        let mut columns = Vec::new();
+        for item in format.split(',') {
+            columns.extend(parse_format(item).unwrap_or_default());
+        }
+        columns
+    }
+
+    /**
+     * Whether any selection list was given
+     *
+     * @return bool - True if -p, -u, -U, -C or --ppid was used
+     */
+    fn has_selection_lists(&self) -> bool {
+        !self.pids.is_empty() || !self.ppids.is_empty() || !self.users.is_empty() || !self.real_users.is_empty() || !self.commands.is_empty()
+    }
+
+    /**
+     * プロセスを表示するかどうかを判定する関数です
+     *
+     * -eの場合はすべて、-p、-u、-U、-C、--ppidのいずれかが
+     * ある場合はどれかに一致するプロセスを選びます。それ以外は
+     * UNIX形式では自分と同じ実効UIDと端末のプロセス、BSD形式
+     * ではaで全ユーザー、xで端末のないプロセスまで広げます。
+     *
+     * @param process - プロセス
+     * @param own_euid - 自分の実効UID
+     * @param own_tty - 自分の端末
+     * @return bool - 表示する場合はtrue
+     */
+    fn selects(&self, process: &ProcessInfo, own_euid: u32, own_tty: i32) -> bool {
+        if self.running_only && process.state != 'R' {
+            return false;
+        }
+        if self.all {
+            return true;
+        }
+        if self.has_selection_lists() {
+            return self.pids.contains(&process.pid)
+                || self.ppids.contains(&process.ppid)
+                || self.users.contains(&process.euid)
+                || self.real_users.contains(&process.uid)
+                || self.commands.contains(&process.comm);
+        }
+        if self.terminal_processes {
+            return process.tty_nr != 0 && process.pid != process.session;
+        }
+        if self.bsd {
+            let user_ok = self.bsd_all_users || process.euid == own_euid;
+            let tty_ok = self.bsd_no_tty || process.tty_nr != 0;
+            return user_ok && tty_ok;
+        }
+        process.euid == own_euid && process.tty_nr == own_tty
+    }
+}
+
+/**
+ * Result of a ps run
+ */
+#[derive(Debug, Clone)]
+pub struct PsOutput {
+    /// Formatted listing
+    pub output: String,
+    /// One record per process, with lower-case header names as columns
+    pub records: Table,
+    /// 1 when explicit selection matched nothing
+    pub exit_code: i32,
+}
+
+/**
+ * Formats CPU seconds as [DD-]HH:MM:SS
+ *
+ * @param seconds - CPU seconds
+ * @return String - Time text
+ */
+fn format_cpu_time(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let clock = format!("{:02}:{:02}:{:02}", seconds % 86400 / 3600, seconds % 3600 / 60, seconds % 60);
+    if days > 0 { format!("{}-{}", days, clock) } else { clock }
+}
+
+/**
+ * Formats elapsed seconds as [[DD-]HH:]MM:SS
+ *
+ * @param seconds - Elapsed seconds
+ * @return String - Elapsed text
+ */
+fn format_elapsed(seconds: u64) -> String {
+    let (days, hours) = (seconds / 86400, seconds % 86400 / 3600);
+    let clock = format!("{:02}:{:02}", seconds % 3600 / 60, seconds % 60);
+    match (days, hours) {
+        (0, 0) => clock,
+        (0, hours) => format!("{:02}:{}", hours, clock),
+        (days, hours) => format!("{}-{:02}:{}", days, hours, clock),
+    }
+}
+
+/**
+ * ps field values for one process
+ */
+struct Formatter<'a> {
+    /// System snapshot
+    system: &'a SystemInfo,
+    /// Name cache
+    owners: OwnerNames,
+    /// Current time
+    now: chrono::DateTime<chrono::Local>,
+}
+
+impl Formatter<'_> {
+    /**
+     * Formats a field as text and as a record value
+     *
+     * @param kind - Field
+     * @param process - Process
+     * @return (String, Value) - Column text and typed value
+     */
+    fn value(&mut self, kind: FieldKind, process: &ProcessInfo) -> (String, Value) {
+        let ticks = self.system.clock_ticks.max(1);
+        let number = |value: i64| (value.to_string(), Value::from(value));
+        match kind {
+            FieldKind::Pid => number(process.pid as i64),
+            FieldKind::Ppid => number(process.ppid as i64),
+            FieldKind::Pgid => number(process.pgrp as i64),
+            FieldKind::Sid => number(process.session as i64),
+            FieldKind::Tpgid => number(process.tpgid as i64),
+            FieldKind::Uid => number(process.euid as i64),
+            FieldKind::Ruid => number(process.uid as i64),
+            FieldKind::User => {
+                let name = self.owners.user(process.euid);
+                (name.clone(), Value::from(name))
+            }
+            FieldKind::Ruser => {
+                let name = self.owners.user(process.uid);
+                (name.clone(), Value::from(name))
+            }
+            FieldKind::C => number((self.system.cpu_percent(process) as i64).min(99)),
+            FieldKind::Pcpu => {
+                let percent = (self.system.cpu_percent(process) * 10.0).floor() / 10.0;
+                (format!("{:.1}", percent), Value::from(percent))
+            }
+            FieldKind::Pmem => {
+                let percent = (self.system.memory_percent(process) * 10.0).floor() / 10.0;
+                (format!("{:.1}", percent), Value::from(percent))
+            }
+            FieldKind::Vsz => number(process.vsize as i64),
+            FieldKind::Rss => number(process.rss as i64),
+            FieldKind::Tty => {
+                let name = process.tty_name();
+                (name.clone(), Value::from(name))
+            }
+            FieldKind::Stat => {
+                let mut stat = process.state.to_string();
+                match process.nice.cmp(&0) {
+                    Ordering::Less => stat.push('<'),
+                    Ordering::Greater => stat.push('N'),
+                    Ordering::Equal => {}
+                }
+                if process.pid == process.session {
+                    stat.push('s');
+                }
+                if process.threads > 1 {
+                    stat.push('l');
+                }
+                if process.tty_nr != 0 && process.tpgid == process.pgrp {
+                    stat.push('+');
+                }
+                (stat.clone(), Value::from(stat))
+            }
+            FieldKind::State => (process.state.to_string(), Value::from(process.state.to_string())),
+            FieldKind::Start | FieldKind::Stime => {
+                let started = self.system.start_time(process);
+                let age = self.now.signed_duration_since(started);
+                let text = if age < chrono::Duration::hours(24) {
+                    started.format(if kind == FieldKind::Start { "%H:%M:%S" } else { "%H:%M" }).to_string()
+                } else if kind == FieldKind::Start {
+                    started.format("%b %d").to_string()
+                } else if started.format("%Y").to_string() == self.now.format("%Y").to_string() {
+                    started.format("%b%d").to_string()
+                } else {
+                    started.format("%Y").to_string()
+                };
+                (text, Value::from(started.format("%Y-%m-%d %H:%M:%S").to_string()))
+            }
+            FieldKind::Time => {
+                let seconds = process.cpu_ticks() / ticks;
+                (format_cpu_time(seconds), Value::from(seconds))
+            }
+            FieldKind::BsdTime => {
+                let seconds = process.cpu_ticks() / ticks;
+                (format!("{}:{:02}", seconds / 60, seconds % 60), Value::from(seconds))
+            }
+            FieldKind::Etime => {
+                let seconds = self.system.elapsed(process) as u64;
+                (format_elapsed(seconds), Value::from(seconds))
+            }
+            FieldKind::Etimes => number(self.system.elapsed(process) as i64),
+            FieldKind::Nice => number(process.nice),
+            FieldKind::Pri => number(39 - process.priority),
+            FieldKind::Nlwp => number(process.threads),
+            FieldKind::Comm => (process.comm.clone(), Value::from(process.comm.clone())),
+            FieldKind::Args => {
+                let command = process.command_line();
+                (command.clone(), Value::from(command))
+            }
+        }
+    }
+}
+
+/**
+ * Orders two processes by the --sort keys, then by PID
+ *
+ * @param a - First process with its sort values
+ * @param b - Second process with its sort values
+ * @param keys - Sort keys with descending flags
+ * @return Ordering - Ordering of a relative to b
+ */
+fn compare_processes(a: &(ProcessInfo, Vec<Value>), b: &(ProcessInfo, Vec<Value>), keys: &[(FieldKind, bool)]) -> Ordering {
+    for (index, (_, descending)) in keys.iter().enumerate() {
+        let ordering = match (&a.1[index], &b.1[index]) {
+            (Value::Number(x), Value::Number(y)) => {
+                x.as_f64().unwrap_or(0.0).partial_cmp(&y.as_f64().unwrap_or(0.0)).unwrap_or(Ordering::Equal)
+            }
+            (x, y) => x.as_str().unwrap_or("").cmp(y.as_str().unwrap_or("")),
+        };
+        let ordering = if *descending { ordering.reverse() } else { ordering };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.0.pid.cmp(&b.0.pid)
+}
+
+/**
+ * Orders processes as a tree and computes each one's command prefix
+ *
+ * @param processes - Sorted processes
+ * @param art - Draw `\_` branches (--forest); otherwise indent (-H)
+ * @return Vec<(usize, String)> - Index into processes and prefix, in tree order
+ */
+fn tree_order(processes: &[ProcessInfo], art: bool) -> Vec<(usize, String)> {
+    let index_of: HashMap<i32, usize> = processes.iter().enumerate().map(|(index, process)| (process.pid, index)).collect();
+    let mut children: HashMap<i32, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (index, process) in processes.iter().enumerate() {
+        if process.ppid != process.pid && index_of.contains_key(&process.ppid) {
+            children.entry(process.ppid).or_default().push(index);
+        } else {
+            roots.push(index);
+        }
+    }
+
+    let mut order = Vec::new();
+    let mut stack: Vec<(usize, Vec<bool>)> = roots.into_iter().rev().map(|index| (index, Vec::new())).collect();
+    while let Some((index, branches)) = stack.pop() {
+        let prefix = match branches.split_last() {
+            None => String::new(),
+            Some(_) if !art => "  ".repeat(branches.len()),
+            Some((_, ancestors)) => {
+                let mut prefix: String = ancestors.iter().map(|more| if *more { " |  " } else { "    " }).collect();
+                prefix.push_str(" \\_ ");
+                prefix
+            }
+        };
+        order.push((index, prefix));
+
+        if let Some(kids) = children.get(&processes[index].pid) {
+            for (position, child) in kids.iter().enumerate().rev() {
+                let mut child_branches = branches.clone();
+                child_branches.push(position + 1 < kids.len());
+                stack.push((*child, child_branches));
+            }
+        }
+    }
+    order
+}
+
+/**
+ * ps を実行して一覧を作る関数です
+ *
+ * /procからプロセスを読み込み、オプションに従って選択、並べ替え、
+ * 木構造の整列を行ってから、各列をprocpsと同じ幅と揃え方で
+ * 整形します。最後の列は左揃えで幅を制限しません。line_widthが
+ * 指定された場合（端末に出力する場合）は各行をその幅で切り詰めます。
+ *
+ * @param options - psのオプション
+ * @param line_width - 行の最大幅（切り詰めない場合はNone）
+ * @return PsOutput - 一覧、レコード、終了コード
+ */
+pub fn run(options: &PsOptions, line_width: Option<usize>) -> PsOutput {
+    let system = SystemInfo::read();
+    let own_euid = unsafe { libc::geteuid() };
+    let own_tty = ProcessInfo::read(std::process::id() as i32, system.page_size).map_or(0, |process| process.tty_nr);
+    let columns = options.columns();
+
+    let mut formatter = Formatter {
+        system: &system,
+        owners: OwnerNames::default(),
+        now: chrono::Local::now(),
+    };
+
+    let mut selected: Vec<(ProcessInfo, Vec<Value>)> = procfs::read_processes(&system)
+        .into_iter()
+        .filter(|process| options.selects(process, own_euid, own_tty))
+        .map(|process| {
+            let keys = options.sort.iter().map(|(kind, _)| formatter.value(*kind, &process).1).collect();
+            (process, keys)
+        })
+        .collect();
+    selected.sort_by(|a, b| compare_processes(a, b, &options.sort));
+    let processes: Vec<ProcessInfo> = selected.into_iter().map(|(process, _)| process).collect();
+
+    let order = if options.forest || options.hierarchy {
+        tree_order(&processes, options.forest)
+    } else {
+        (0..processes.len()).map(|index| (index, String::new())).collect()
+    };
+
+    let headers: Vec<String> = columns.iter().map(|column| column.header.to_lowercase()).collect();
+    let mut records = Table::new(&headers.iter().map(String::as_str).collect::<Vec<_>>());
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for (index, prefix) in &order {
+        let process = &processes[*index];
+        let mut cells = Vec::new();
+        let mut values = Vec::new();
+        for column in &columns {
+            let (mut text, value) = formatter.value(column.kind, process);
+            if column.kind.is_command() {
+                text.insert_str(0, prefix);
+            }
+            cells.push(text);
+            values.push(value);
+        }
+        rows.push(cells);
+        records.push_row(values);
+    }
+
+    let widths: Vec<usize> = columns.iter().map(|column| column.kind.width().max(display_width(&column.header))).collect();
+    let format_line = |cells: &[String]| -> String {
+        let mut line = String::new();
+        for (index, (cell, column)) in cells.iter().zip(&columns).enumerate() {
+            if index > 0 {
+                line.push(' ');
+            }
+            let padding = " ".repeat(widths[index].saturating_sub(display_width(cell)));
+            if column.kind.right_aligned() {
+                line.push_str(&padding);
+                line.push_str(cell);
+            } else {
+                line.push_str(cell);
+                if index + 1 < cells.len() {
+                    line.push_str(&padding);
+                }
+            }
+        }
+        let line = line.trim_end().to_string();
+        match line_width {
+            Some(width) if display_width(&line) > width => {
+                let mut truncated = String::new();
+                for c in line.chars() {
+                    if display_width(&truncated) + display_width(&c.to_string()) > width {
+                        break;
+                    }
+                    truncated.push(c);
+                }
+                truncated
+            }
+            _ => line,
+        }
+    };
+
+    // Like procps, `-o pid=` with every header blank prints no header line
+    let mut output = String::new();
+    if !options.no_headers && columns.iter().any(|column| !column.header.is_empty()) {
+        let header_cells: Vec<String> = columns.iter().map(|column| column.header.clone()).collect();
+        output.push_str(&format_line(&header_cells));
+        output.push('\n');
+    }
+    for row in &rows {
+        output.push_str(&format_line(row));
+        output.push('\n');
+    }
+
+    PsOutput {
+        output,
+        exit_code: if rows.is_empty() && (options.has_selection_lists() || options.running_only) { 1 } else { 0 },
+        records,
+    }
+}
+
+/**
+ * Options shared by pgrep and pkill
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MatchOptions {
+    /// Pattern matched against the process name or command line
+    pub pattern: Option<String>,
+    /// Match against the full command line (-f)
+    pub full: bool,
+    /// Require the pattern to match the whole name (-x)
+    pub exact: bool,
+    /// Ignore case (-i)
+    pub ignore_case: bool,
+    /// Select processes that do not match (-v)
+    pub invert: bool,
+    /// Select only the newest match (-n)
+    pub newest: bool,
+    /// Select only the oldest match (-o)
+    pub oldest: bool,
+    /// Effective user IDs (-u)
+    pub users: Vec<u32>,
+    /// Real user IDs (-U)
+    pub real_users: Vec<u32>,
+    /// Parent process IDs (-P)
+    pub parents: Vec<i32>,
+}
+
+impl MatchOptions {
+    /**
+     * pgrepとpkillに共通するオプションを1つ処理する関数です
+     *
+     * 値を取るオプション（-u、-U、-P）は値を続けて書くことも
+     * 次の引数にすることもできます。処理できないオプションの
+     * 場合はfalseを返し、呼び出し側で固有のオプションとして扱います。
+     *
+     * @param flag - オプション文字
+     * @param rest - 同じ引数内のオプション文字より後ろ
+     * @param args - 残りの引数
+     * @return Result<Option<bool>> - 処理できない場合はNone、値を消費した場合はSome(true)
+     */
+    pub fn apply_flag<'a>(&mut self, flag: char, rest: &str, args: &mut impl Iterator<Item = &'a String>) -> Result<Option<bool>> {
+        if "uUP".contains(flag) {
+            let value = if rest.is_empty() {
+                args.next().cloned().ok_or_else(|| anyhow::anyhow!("option requires an argument -- '{}'", flag))?
+            } else {
+                rest.to_string()
+            };
+            match flag {
+                'u' => self.users.extend(parse_list(&value, parse_user)?),
+                'U' => self.real_users.extend(parse_list(&value, parse_user)?),
+                _ => self.parents.extend(parse_list(&value, parse_pid)?),
+            }
+            return Ok(Some(true));
+        }
+        match flag {
+            'f' => self.full = true,
+            'x' => self.exact = true,
+            'i' => self.ignore_case = true,
+            'v' => self.invert = true,
+            'n' => self.newest = true,
+            'o' => self.oldest = true,
+            _ => return Ok(None),
+        }
+        Ok(Some(false))
+    }
+
+    /**
+     * Checks that a pattern or another criterion was given
+     *
+     * @return Result<()> - Error when nothing would restrict the selection
+     */
+    pub fn validate(&self) -> Result<()> {
+        if self.pattern.is_none() && self.users.is_empty() && self.real_users.is_empty() && self.parents.is_empty() {
+            return Err(anyhow::anyhow!("no matching criteria specified"));
+        }
+        if self.newest && self.oldest {
+            return Err(anyhow::anyhow!("-n and -o are mutually exclusive"));
+        }
+        Ok(())
+    }
+}
+
+/**
+ * pgrepとpkillの条件に一致するプロセスを探す関数です
+ *
+ * パターンは拡張正規表現で、通常はプロセス名（comm）、-fでは
+ * 引数を空白でつないだコマンドライン全体と照合します。-xでは
+ * 全体一致を要求します。-u、-U、-Pの条件はすべて満たす必要が
+ * あり、-vはパターンと条件の結果を反転します。シェル自身の
+ * プロセスは常に除外されます。
+ *
+ * @param options - 照合オプション
+ * @return Result<Vec<ProcessInfo>> - PID順の一致したプロセス、または正規表現のエラー
+ */
+pub fn find_matches(options: &MatchOptions) -> Result<Vec<ProcessInfo>> {
+    let regex = match &options.pattern {
+        Some(pattern) => {
+            let pattern = if options.exact { format!("^(?:{})$", pattern) } else { pattern.clone() };
+            Some(
+                regex::RegexBuilder::new(&pattern)
+                    .case_insensitive(options.ignore_case)
+                    .build()
+                    .map_err(|error| anyhow::anyhow!("invalid pattern: {}", error))?,
+            )
+        }
+        None => None,
+    };
+
+    let system = SystemInfo::read();
+    let own_pid = std::process::id() as i32;
+    let mut matches: Vec<ProcessInfo> = procfs::read_processes(&system)
+        .into_iter()
+        .filter(|process| process.pid != own_pid)
+        .filter(|process| {
+            let text = if options.full && !process.cmdline.is_empty() { process.cmdline.join(" ") } else { process.comm.clone() };
+            let selected = regex.as_ref().is_none_or(|regex| regex.is_match(&text))
+                && (options.users.is_empty() || options.users.contains(&process.euid))
+                && (options.real_users.is_empty() || options.real_users.contains(&process.uid))
+                && (options.parents.is_empty() || options.parents.contains(&process.ppid));
+            selected != options.invert
+        })
+        .collect();
+
+    if options.newest || options.oldest {
+        let chosen = if options.newest {
+            matches.iter().max_by_key(|process| (process.start_time, process.pid))
+        } else {
+            matches.iter().min_by_key(|process| (process.start_time, process.pid))
+        };
+        matches = chosen.cloned().into_iter().collect();
+    }
+    Ok(matches)
+}
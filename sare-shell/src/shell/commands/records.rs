@@ -0,0 +1,538 @@
+/*!
+ * @file records.rs
+ * @brief Structured records passed between builtins
+ *
+ * This module defines the table that builtins such as ls, jobs, env,
+ * history and netstat emit alongside their text output, and the
+ * operations the structured builtins (where, select, sort-by, to,
+ * from) apply to it: filtering, projection, sorting, JSON and CSV
+ * conversion and plain-text rendering.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file records.rs
+ * @description Record tables with JSON cells, where-conditions,
+ * order-preserving JSON input, and JSON, CSV and text output.
+ */
+
+use anyhow::Result;
+use regex::Regex;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::shell::commands::listing::display_width;
+
+/**
+ * Table of records with named columns
+ *
+ * Cells are JSON values so that numbers stay numbers through
+ * filtering and sorting, and nested data survives `from json`.
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    /// Column names in display order
+    pub columns: Vec<String>,
+    /// Rows, each with one cell per column
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /**
+     * Creates an empty table with the given columns
+     *
+     * @param columns - Column names
+     * @return Table - Table without rows
+     */
+    pub fn new(columns: &[&str]) -> Self {
+        Table {
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /**
+     * Appends a row, padding or truncating it to the column count
+     *
+     * @param row - Cells in column order
+     */
+    pub fn push_row(&mut self, mut row: Vec<Value>) {
+        row.resize(self.columns.len(), Value::Null);
+        self.rows.push(row);
+    }
+
+    /**
+     * Finds a column by name
+     *
+     * @param name - Column name
+     * @return Result<usize> - Column index, or an error listing the columns
+     */
+    pub fn column_index(&self, name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| anyhow::anyhow!("no column '{}' (columns: {})", name, self.columns.join(", ")))
+    }
+
+    /**
+     * Keeps only the given columns, in the given order
+     *
+     * @param names - Column names
+     * @return Result<Table> - Projected table or unknown column error
+     */
+    pub fn select(&self, names: &[String]) -> Result<Table> {
+        let indices = names.iter().map(|name| self.column_index(name)).collect::<Result<Vec<_>>>()?;
+        Ok(Table {
+            columns: names.to_vec(),
+            rows: self
+                .rows
+                .iter()
+                .map(|row| indices.iter().map(|index| row[*index].clone()).collect())
+                .collect(),
+        })
+    }
+
+    /**
+     * Sorts rows by the given columns
+     *
+     * The sort is stable, so rows with equal keys keep their order.
+     *
+     * @param names - Column names, most significant first
+     * @param reverse - Sort in descending order
+     * @return Result<()> - Unknown column error
+     */
+    pub fn sort_by(&mut self, names: &[String], reverse: bool) -> Result<()> {
+        let indices = names.iter().map(|name| self.column_index(name)).collect::<Result<Vec<_>>>()?;
+        self.rows.sort_by(|a, b| {
+            let ordering = indices
+                .iter()
+                .map(|index| compare_values(&a[*index], &b[*index]))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal);
+            if reverse { ordering.reverse() } else { ordering }
+        });
+        Ok(())
+    }
+
+    /**
+     * Keeps the rows matching a condition
+     *
+     * @param condition - Condition to test
+     */
+    pub fn retain(&mut self, condition: &Condition) {
+        self.rows.retain(|row| condition.matches(&row[condition.column]));
+    }
+
+    /**
+     * JSONのテキストから表を作る関数です
+     *
+     * オブジェクトの配列は各オブジェクトを1行とし、列は最初に
+     * 現れた順に並べます。キーがない行の値はnullです。
+     * 単独のオブジェクトは1行の表、スカラーやスカラーの配列は
+     * 「value」列の表になります。
+     *
+     * serde_jsonのMapはキーを並べ替えるため、オブジェクトの
+     * キーの順序を保つ独自のデシリアライザで読み込みます。
+     *
+     * @param text - JSONのテキスト
+     * @return Result<Table> - 表またはJSONのエラー
+     */
+    pub fn from_json(text: &str) -> Result<Table> {
+        let document: OrderedJson = serde_json::from_str(text).map_err(|error| anyhow::anyhow!("invalid JSON: {}", error))?;
+        let items = match document {
+            OrderedJson::Array(items) => items,
+            other => vec![other],
+        };
+
+        let mut table = Table::default();
+        let mut objects = Vec::new();
+        for item in items {
+            let fields = match item {
+                OrderedJson::Object(fields) => fields,
+                OrderedJson::Array(values) => vec![("value".to_string(), Value::Array(values.into_iter().map(OrderedJson::into_value).collect()))],
+                OrderedJson::Scalar(value) => vec![("value".to_string(), value)],
+            };
+            for (key, _) in &fields {
+                if !table.columns.contains(key) {
+                    table.columns.push(key.clone());
+                }
+            }
+            objects.push(fields);
+        }
+
+        for fields in objects {
+            let mut row = vec![Value::Null; table.columns.len()];
+            for (key, value) in fields {
+                if let Some(index) = table.columns.iter().position(|column| *column == key) {
+                    row[index] = value;
+                }
+            }
+            table.rows.push(row);
+        }
+        Ok(table)
+    }
+
+    /**
+     * Converts the table to a JSON array of objects
+     *
+     * Keys appear in column order.
+     *
+     * @param pretty - Indent the output; otherwise one line
+     * @return String - JSON text with a trailing newline
+     */
+    pub fn to_json(&self, pretty: bool) -> String {
+        let keys: Vec<String> = self.columns.iter().map(|column| Value::String(column.clone()).to_string()).collect();
+        let objects: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let fields: Vec<String> = keys
+                    .iter()
+                    .zip(row)
+                    .map(|(key, value)| {
+                        if pretty {
+                            let value = serde_json::to_string_pretty(value).unwrap_or_default().replace('\n', "\n    ");
+                            format!("    {}: {}", key, value)
+                        } else {
+                            format!("{}:{}", key, value)
+                        }
+                    })
+                    .collect();
+                if pretty {
+                    format!("  {{\n{}\n  }}", fields.join(",\n"))
+                } else {
+                    format!("{{{}}}", fields.join(","))
+                }
+            })
+            .collect();
+
+        if objects.is_empty() {
+            "[]\n".to_string()
+        } else if pretty {
+            format!("[\n{}\n]\n", objects.join(",\n"))
+        } else {
+            format!("[{}]\n", objects.join(","))
+        }
+    }
+
+    /**
+     * Converts the table to CSV with a header line
+     *
+     * Fields containing the separator, quotes or line breaks are
+     * quoted as in RFC 4180.
+     *
+     * @param separator - Field separator
+     * @param header - Whether to print the header line
+     * @return String - CSV text
+     */
+    pub fn to_csv(&self, separator: char, header: bool) -> String {
+        let quote = |field: String| -> String {
+            if field.contains([separator, '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        };
+        let separator = separator.to_string();
+
+        let mut output = String::new();
+        if header {
+            output.push_str(&self.columns.iter().cloned().map(quote).collect::<Vec<_>>().join(&separator));
+            output.push('\n');
+        }
+        for row in &self.rows {
+            output.push_str(&row.iter().map(|value| quote(cell_text(value))).collect::<Vec<_>>().join(&separator));
+            output.push('\n');
+        }
+        output
+    }
+
+    /**
+     * Renders the table as aligned plain text
+     *
+     * Columns are separated by two spaces; numeric columns are
+     * right-aligned. An empty table renders as nothing.
+     *
+     * @return String - Header line and one line per row
+     */
+    pub fn render(&self) -> String {
+        if self.rows.is_empty() {
+            return String::new();
+        }
+
+        let cells: Vec<Vec<String>> = self.rows.iter().map(|row| row.iter().map(cell_text).collect()).collect();
+        let numeric: Vec<bool> = (0..self.columns.len())
+            .map(|index| self.rows.iter().all(|row| row[index].is_number() || row[index].is_null()))
+            .collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| cells.iter().map(|row| display_width(&row[index])).fold(display_width(column), usize::max))
+            .collect();
+
+        let format_line = |fields: &[String]| -> String {
+            let padded: Vec<String> = fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    let padding = " ".repeat(widths[index] - display_width(field));
+                    if numeric[index] {
+                        format!("{}{}", padding, field)
+                    } else {
+                        format!("{}{}", field, padding)
+                    }
+                })
+                .collect();
+            format!("{}\n", padded.join("  ").trim_end())
+        };
+
+        let mut output = format_line(&self.columns);
+        for row in &cells {
+            output.push_str(&format_line(row));
+        }
+        output
+    }
+}
+
+/**
+ * Text shown for a cell in rendered tables and CSV
+ *
+ * @param value - Cell value
+ * @return String - Strings unquoted, null empty, other values as JSON
+ */
+pub fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/**
+ * Orders two cells
+ *
+ * Numbers compare numerically and strings bytewise; nulls sort last
+ * and other mixed types compare by their text.
+ *
+ * @param a - First cell
+ * @param b - Second cell
+ * @return Ordering - Ordering of a relative to b
+ */
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+        }
+        _ => cell_text(a).cmp(&cell_text(b)),
+    }
+}
+
+/**
+ * Comparison operator of a where-condition
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    /// == or -eq
+    Equal,
+    /// != or -ne
+    NotEqual,
+    /// < or -lt
+    Less,
+    /// <= or -le
+    LessOrEqual,
+    /// > or -gt
+    Greater,
+    /// >= or -ge
+    GreaterOrEqual,
+    /// =~
+    Matches,
+    /// !~
+    NotMatches,
+}
+
+/**
+ * Condition of the where builtin
+ */
+#[derive(Debug, Clone)]
+pub struct Condition {
+    /// Column tested
+    column: usize,
+    /// Comparison
+    operator: Operator,
+    /// Right-hand side as written
+    value: String,
+    /// Compiled pattern for =~ and !~
+    pattern: Option<Regex>,
+}
+
+impl Condition {
+    /**
+     * whereの条件を解析する関数です
+     *
+     * 「列 演算子 値」の3つの単語を受け取ります。シェルの
+     * リダイレクトと衝突しないよう、大小比較はtestと同じ
+     * -lt、-le、-gt、-geでも書けます（'<'のように引用符で
+     * 囲めば記号も使えます）。=~と!~は値を正規表現として
+     * 扱います。
+     *
+     * @param words - 条件の単語
+     * @param table - 条件を適用する表
+     * @return Result<Condition> - 条件またはエラー
+     */
+    pub fn parse(words: &[String], table: &Table) -> Result<Condition> {
+        let [column, operator, value] = words else {
+            return Err(anyhow::anyhow!("expected <column> <operator> <value>"));
+        };
+        let operator = match operator.as_str() {
+            "==" | "=" | "-eq" => Operator::Equal,
+            "!=" | "-ne" => Operator::NotEqual,
+            "<" | "-lt" => Operator::Less,
+            "<=" | "-le" => Operator::LessOrEqual,
+            ">" | "-gt" => Operator::Greater,
+            ">=" | "-ge" => Operator::GreaterOrEqual,
+            "=~" => Operator::Matches,
+            "!~" => Operator::NotMatches,
+            other => return Err(anyhow::anyhow!("unknown operator '{}'", other)),
+        };
+        let pattern = match operator {
+            Operator::Matches | Operator::NotMatches => {
+                Some(Regex::new(value).map_err(|error| anyhow::anyhow!("invalid pattern '{}': {}", value, error))?)
+            }
+            _ => None,
+        };
+        Ok(Condition {
+            column: table.column_index(column)?,
+            operator,
+            value: value.clone(),
+            pattern,
+        })
+    }
+
+    /**
+     * Tests a cell
+     *
+     * When the cell is a number and the value parses as one, they
+     * compare numerically; otherwise the cell's text is compared.
+     *
+     * @param cell - Cell of the tested column
+     * @return bool - True if the row is kept
+     */
+    fn matches(&self, cell: &Value) -> bool {
+        if let Some(pattern) = &self.pattern {
+            return pattern.is_match(&cell_text(cell)) == (self.operator == Operator::Matches);
+        }
+
+        let ordering = match (cell.as_f64(), self.value.parse::<f64>()) {
+            (Some(number), Ok(value)) => number.partial_cmp(&value),
+            _ => match cell {
+                Value::Null if self.value == "null" => Some(Ordering::Equal),
+                Value::Null => None,
+                _ => Some(cell_text(cell).as_str().cmp(self.value.as_str())),
+            },
+        };
+        let Some(ordering) = ordering else {
+            return self.operator == Operator::NotEqual;
+        };
+        match self.operator {
+            Operator::Equal => ordering == Ordering::Equal,
+            Operator::NotEqual => ordering != Ordering::Equal,
+            Operator::Less => ordering == Ordering::Less,
+            Operator::LessOrEqual => ordering != Ordering::Greater,
+            Operator::Greater => ordering == Ordering::Greater,
+            Operator::GreaterOrEqual => ordering != Ordering::Less,
+            Operator::Matches | Operator::NotMatches => false,
+        }
+    }
+}
+
+/**
+ * JSON document whose top-level object keys keep their order
+ */
+enum OrderedJson {
+    /// Object with keys in document order
+    Object(Vec<(String, Value)>),
+    /// Array of documents
+    Array(Vec<OrderedJson>),
+    /// Any other value
+    Scalar(Value),
+}
+
+impl OrderedJson {
+    /**
+     * Converts back to a plain JSON value
+     *
+     * @return Value - Value with keys in serde_json's order
+     */
+    fn into_value(self) -> Value {
+        match self {
+            OrderedJson::Object(fields) => Value::Object(fields.into_iter().collect()),
+            OrderedJson::Array(items) => Value::Array(items.into_iter().map(OrderedJson::into_value).collect()),
+            OrderedJson::Scalar(value) => value,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(OrderedJsonVisitor)
+    }
+}
+
+/**
+ * serde visitor building an OrderedJson
+ */
+struct OrderedJsonVisitor;
+
+impl<'de> Visitor<'de> for OrderedJsonVisitor {
+    type Value = OrderedJson;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<OrderedJson, A::Error> {
+        let mut fields = Vec::new();
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            fields.push((key, value));
+        }
+        Ok(OrderedJson::Object(fields))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<OrderedJson, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element::<OrderedJson>()? {
+            items.push(item);
+        }
+        Ok(OrderedJson::Array(items))
+    }
+
+    fn visit_bool<E>(self, value: bool) -> std::result::Result<OrderedJson, E> {
+        Ok(OrderedJson::Scalar(Value::Bool(value)))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> std::result::Result<OrderedJson, E> {
+        Ok(OrderedJson::Scalar(Value::from(value)))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> std::result::Result<OrderedJson, E> {
+        Ok(OrderedJson::Scalar(Value::from(value)))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> std::result::Result<OrderedJson, E> {
+        Ok(OrderedJson::Scalar(Value::from(value)))
+    }
+
+    fn visit_str<E>(self, value: &str) -> std::result::Result<OrderedJson, E> {
+        Ok(OrderedJson::Scalar(Value::String(value.to_string())))
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<OrderedJson, E> {
+        Ok(OrderedJson::Scalar(Value::Null))
+    }
+}
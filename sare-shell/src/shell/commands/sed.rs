@@ -0,0 +1,1703 @@
+/*!
+ * @file sed.rs
+ * @brief Stream editor engine
+ *
+ * This module compiles and runs sed scripts for the `sed` builtin.
+ * It follows GNU sed semantics closely enough to run the scripts
+ * found in typical build files.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file sed.rs
+ * @description sed script compiler and interpreter supporting addresses,
+ * ranges, hold space, branching and in-place editing.
+ */
+
+use anyhow::Result;
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::Write;
+
+/**
+ * Options that change how a sed script runs
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SedOptions {
+    /// Suppress automatic printing of the pattern space (-n)
+    pub quiet: bool,
+    /// Use extended regular expressions (-E)
+    pub extended: bool,
+    /// Treat files as separate streams (-s, implied by -i)
+    pub separate: bool,
+    /// Lines are separated by NUL instead of newline (-z)
+    pub null_data: bool,
+}
+
+/**
+ * Single address in a sed command
+ */
+#[derive(Debug, Clone)]
+enum Address {
+    /// Specific line number
+    Line(usize),
+    /// Last line of input (`$`)
+    Last,
+    /// Lines matching a regex; None reuses the last regex
+    Pattern(Option<Regex>),
+    /// `first~step` - every step-th line starting at first
+    Step(usize, usize),
+}
+
+/**
+ * End of an address range
+ */
+#[derive(Debug, Clone)]
+enum RangeEnd {
+    /// Regular address
+    Address(Address),
+    /// `addr,+N` - the N lines after the start
+    Following(usize),
+    /// `addr,~N` - up to the next line that is a multiple of N
+    Multiple(usize),
+}
+
+/**
+ * Address selector attached to a command
+ */
+#[derive(Debug, Clone)]
+enum Selector {
+    /// Every line
+    Always,
+    /// A single address
+    Single(Address),
+    /// A range; `zero_start` marks the GNU `0,/re/` form
+    Range { start: Address, end: RangeEnd, zero_start: bool },
+}
+
+/**
+ * Part of an `s` command replacement
+ */
+#[derive(Debug, Clone)]
+enum ReplacementPart {
+    /// Literal text
+    Literal(String),
+    /// Capture group (0 for `&`)
+    Group(usize),
+    /// `\U` - uppercase until `\E`
+    Upper,
+    /// `\L` - lowercase until `\E`
+    Lower,
+    /// `\u` - uppercase next character
+    UpperNext,
+    /// `\l` - lowercase next character
+    LowerNext,
+    /// `\E` - stop case conversion
+    EndCase,
+}
+
+/**
+ * Compiled `s` command
+ */
+#[derive(Debug, Clone)]
+struct Substitution {
+    /// Regex to match; None reuses the last regex
+    regex: Option<Regex>,
+    /// Parsed replacement
+    replacement: Vec<ReplacementPart>,
+    /// Replace every match from `occurrence` on (g flag)
+    global: bool,
+    /// First match to replace (numeric flag, default 1)
+    occurrence: usize,
+    /// Print the pattern space after a replacement (p flag)
+    print: bool,
+    /// File to write the pattern space to after a replacement (w flag)
+    write_file: Option<String>,
+}
+
+/**
+ * Operation performed by a sed command
+ */
+#[derive(Debug, Clone)]
+enum Operation {
+    /// `{` - start of a block; holds the index after the matching `}`
+    BlockStart(usize),
+    /// `}` - end of a block
+    BlockEnd,
+    /// `s` - substitute
+    Substitute(Box<Substitution>),
+    /// `y` - transliterate
+    Transliterate(HashMap<char, char>),
+    /// `a` - append text after the cycle
+    Append(String),
+    /// `i` - insert text now
+    Insert(String),
+    /// `c` - change lines to text
+    Change(String),
+    /// `b` - branch; None jumps to the end of the script
+    Branch(Option<usize>),
+    /// `t` - branch if a substitution succeeded
+    BranchIfSubstituted(Option<usize>),
+    /// `T` - branch if no substitution succeeded
+    BranchUnlessSubstituted(Option<usize>),
+    /// `:label` - branch target
+    Label,
+    /// `d` - delete pattern space
+    Delete,
+    /// `D` - delete the first line of the pattern space
+    DeleteFirstLine,
+    /// `p` - print pattern space
+    Print,
+    /// `P` - print the first line of the pattern space
+    PrintFirstLine,
+    /// `n` - print and replace with the next line
+    Next,
+    /// `N` - append the next line
+    AppendNext,
+    /// `h` - copy pattern to hold
+    Hold,
+    /// `H` - append pattern to hold
+    HoldAppend,
+    /// `g` - copy hold to pattern
+    Get,
+    /// `G` - append hold to pattern
+    GetAppend,
+    /// `x` - exchange pattern and hold
+    Exchange,
+    /// `q` - print and quit with an exit code
+    Quit(i32),
+    /// `Q` - quit without printing
+    QuitSilent(i32),
+    /// `=` - print the line number
+    LineNumber,
+    /// `z` - empty the pattern space
+    Zap,
+    /// `r` - queue a file's contents for output
+    ReadFile(String),
+    /// `w` - write the pattern space to a file
+    WriteFile(String),
+}
+
+/**
+ * Command with its address selector
+ */
+#[derive(Debug, Clone)]
+struct Instruction {
+    /// Lines the command applies to
+    selector: Selector,
+    /// Apply to lines NOT selected (`!`)
+    negate: bool,
+    /// What to do
+    operation: Operation,
+}
+
+/**
+ * Compiled sed script
+ */
+#[derive(Debug, Clone)]
+pub struct SedScript {
+    /// Instructions in script order
+    instructions: Vec<Instruction>,
+    /// Whether the script starts with `#n`
+    quiet_comment: bool,
+}
+
+/**
+ * Result of running a script over one input stream
+ */
+#[derive(Debug, Default)]
+pub struct SedOutput {
+    /// Text written to the output stream
+    pub output: String,
+    /// Text written to /dev/stdout by `w` (kept apart for -i)
+    pub stdout_writes: String,
+    /// Exit code from `q`/`Q`, 0 otherwise
+    pub exit_code: i32,
+    /// Whether `q`/`Q` stopped processing
+    pub quit: bool,
+}
+
+impl SedScript {
+    /**
+     * sedスクリプトをコンパイルする関数です
+     *
+     * スクリプト文字列を命令のリストに変換します。各命令は
+     * アドレス（行番号、$、/正規表現/、first~step、範囲）と
+     * コマンドで構成されます。
+     *
+     * ブロック（{ }）は対応する閉じ括弧の位置を記録し、
+     * ラベル（:label）はb、t、Tコマンドの分岐先として
+     * 命令のインデックスに解決されます。
+     *
+     * 正規表現はPOSIX BRE（-Eの場合はERE）からRustの
+     * regex構文に変換してコンパイルします。
+     *
+     * @param script - コンパイルするスクリプト
+     * @param extended - 拡張正規表現を使用するかどうか
+     * @return Result<SedScript> - コンパイルされたスクリプトまたはエラー
+     */
+    pub fn compile(script: &str, extended: bool) -> Result<Self> {
+        let mut compiler = Compiler {
+            chars: script.chars().collect(),
+            pos: 0,
+            extended,
+            instructions: Vec::new(),
+            labels: HashMap::new(),
+            pending_branches: Vec::new(),
+            open_blocks: Vec::new(),
+        };
+
+        compiler.compile()?;
+
+        Ok(Self {
+            instructions: compiler.instructions,
+            quiet_comment: script.starts_with("#n\n") || script == "#n",
+        })
+    }
+
+    /**
+     * Checks whether the script asks for quiet mode with `#n`
+     *
+     * @return bool - True if the first line is exactly `#n`
+     */
+    pub fn wants_quiet(&self) -> bool {
+        self.quiet_comment
+    }
+
+    /**
+     * Runs the script over a stream of input
+     *
+     * @param input - Complete input text
+     * @param options - Runtime options
+     * @param working_dir - Directory relative `r`/`w` paths are resolved against
+     * @return Result<SedOutput> - Output and exit status
+     */
+    pub fn run(&self, input: &str, options: &SedOptions, working_dir: &std::path::Path) -> Result<SedOutput> {
+        let separator = if options.null_data { '\0' } else { '\n' };
+        let mut lines: Vec<&str> = input.split(separator).collect();
+        let trailing_separator = input.ends_with(separator);
+        if trailing_separator || input.is_empty() {
+            lines.pop();
+        }
+
+        let mut runner = Runner {
+            script: self,
+            options,
+            working_dir,
+            separator,
+            lines,
+            trailing_separator,
+            missing_separator: false,
+            next_line: 0,
+            line_number: 0,
+            pattern: String::new(),
+            hold: String::new(),
+            substituted: false,
+            last_regex: None,
+            ranges: vec![RangeState::default(); self.instructions.len()],
+            append_queue: Vec::new(),
+            write_files: HashMap::new(),
+            result: SedOutput::default(),
+        };
+
+        runner.run()?;
+        Ok(runner.result)
+    }
+}
+
+/**
+ * Translates a POSIX regular expression into Rust regex syntax
+ *
+ * Handles BRE escapes (`\(`, `\{`, `\+`, `\?`, `\|`), bracket
+ * expressions with character classes, and the GNU `\n`, `\t`, `\w`,
+ * `\<`, `\>` extensions. Backreferences inside the pattern are not
+ * supported by the regex engine and are reported as errors.
+ *
+ * @param pattern - POSIX regex
+ * @param extended - True for ERE, false for BRE
+ * @return Result<String> - Equivalent Rust regex
+ */
+pub fn translate_posix_regex(pattern: &str, extended: bool) -> Result<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::new();
+    let mut at_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let mut next_at_start = false;
+
+        match c {
+            '\\' => {
+                i += 1;
+                let Some(&escaped) = chars.get(i) else {
+                    return Err(anyhow::anyhow!("trailing backslash (\\)"));
+                };
+                match escaped {
+                    '(' | ')' | '{' | '}' | '|' | '+' | '?' if !extended => {
+                        out.push(escaped);
+                        next_at_start = escaped == '(' || escaped == '|';
+                    }
+                    'n' => out.push_str("\\n"),
+                    't' => out.push_str("\\t"),
+                    'w' | 'W' | 's' | 'S' | 'b' | 'B' => {
+                        out.push('\\');
+                        out.push(escaped);
+                    }
+                    '<' | '>' => out.push_str("\\b"),
+                    '`' => out.push_str("\\A"),
+                    '\'' => out.push_str("\\z"),
+                    '1'..='9' => return Err(anyhow::anyhow!("back-references in regular expressions are not supported")),
+                    other => out.push_str(&regex::escape(&other.to_string())),
+                }
+            }
+            '[' => {
+                i = translate_bracket(&chars, i, &mut out)?;
+            }
+            '(' | ')' | '{' | '}' | '|' | '+' | '?' if !extended => {
+                out.push('\\');
+                out.push(c);
+            }
+            '(' | '|' if extended => {
+                out.push(c);
+                next_at_start = true;
+            }
+            '*' if at_start => out.push_str("\\*"),
+            '^' => {
+                if at_start || extended {
+                    out.push('^');
+                    next_at_start = true;
+                } else {
+                    out.push_str("\\^");
+                }
+            }
+            '$' => {
+                let rest: String = chars[i + 1..].iter().collect();
+                let is_anchor = extended || rest.is_empty() || rest.starts_with("\\)") || rest.starts_with("\\|");
+                out.push_str(if is_anchor { "$" } else { "\\$" });
+            }
+            _ => out.push(c),
+        }
+
+        at_start = next_at_start;
+        i += 1;
+    }
+
+    Ok(out)
+}
+
+/**
+ * Translates a bracket expression starting at `start`
+ *
+ * @param chars - Whole pattern
+ * @param start - Index of the opening `[`
+ * @param out - Output buffer
+ * @return Result<usize> - Index of the closing `]`
+ */
+fn translate_bracket(chars: &[char], start: usize, out: &mut String) -> Result<usize> {
+    let mut i = start + 1;
+    out.push('[');
+
+    if chars.get(i) == Some(&'^') {
+        out.push('^');
+        i += 1;
+    }
+    if chars.get(i) == Some(&']') {
+        out.push_str("\\]");
+        i += 1;
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ']' => {
+                out.push(']');
+                return Ok(i);
+            }
+            '[' if matches!(chars.get(i + 1), Some(':') | Some('.') | Some('=')) => {
+                let kind = chars[i + 1];
+                let close = (i + 2..chars.len().saturating_sub(1))
+                    .find(|&j| chars[j] == kind && chars[j + 1] == ']')
+                    .ok_or_else(|| anyhow::anyhow!("unterminated character class"))?;
+                let inner: String = chars[i + 2..close].iter().collect();
+                if kind == ':' {
+                    out.push_str(&format!("[:{}:]", inner));
+                } else {
+                    out.push_str(&regex::escape(&inner));
+                }
+                i = close + 1;
+            }
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some('n') => out.push_str("\\n"),
+                    Some('t') => out.push_str("\\t"),
+                    Some('\\') => out.push_str("\\\\"),
+                    Some(']') => out.push_str("\\]"),
+                    _ => {
+                        out.push_str("\\\\");
+                        i -= 1;
+                    }
+                }
+                i += 1;
+            }
+            '[' | '&' | '~' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => out.push_str("\\-"),
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    Err(anyhow::anyhow!("unterminated address regex"))
+}
+
+/**
+ * Builds a regex from POSIX syntax and sed flags
+ *
+ * @param pattern - POSIX regex
+ * @param extended - True for ERE
+ * @param case_insensitive - I flag
+ * @param multiline - M flag
+ * @return Result<Regex> - Compiled regex
+ */
+fn build_regex(pattern: &str, extended: bool, case_insensitive: bool, multiline: bool) -> Result<Regex> {
+    let mut flags = String::from("(?s");
+    if case_insensitive {
+        flags.push('i');
+    }
+    if multiline {
+        flags.push('m');
+    }
+    flags.push(')');
+
+    let translated = translate_posix_regex(pattern, extended)?;
+    Regex::new(&format!("{}{}", flags, translated))
+        .map_err(|e| anyhow::anyhow!("invalid regex '{}': {}", pattern, e))
+}
+
+/**
+ * Script compiler state
+ */
+struct Compiler {
+    /// Script characters
+    chars: Vec<char>,
+    /// Current position
+    pos: usize,
+    /// Whether regexes are ERE
+    extended: bool,
+    /// Instructions compiled so far
+    instructions: Vec<Instruction>,
+    /// Label positions
+    labels: HashMap<String, usize>,
+    /// Branches waiting for label resolution (instruction index, label)
+    pending_branches: Vec<(usize, String)>,
+    /// Indices of `{` instructions without a matching `}`
+    open_blocks: Vec<usize>,
+}
+
+impl Compiler {
+    /**
+     * Compiles the whole script
+     *
+     * @return Result<()> - Success or syntax error
+     */
+    fn compile(&mut self) -> Result<()> {
+        loop {
+            self.skip_separators();
+            let Some(c) = self.peek() else { break };
+
+            if c == '#' {
+                self.skip_to_line_end();
+                continue;
+            }
+
+            let instruction = self.parse_instruction()?;
+            let opens_block = matches!(instruction.operation, Operation::BlockStart(_));
+            self.instructions.push(instruction);
+
+            // A command may follow `{` directly
+            if !opens_block {
+                self.expect_command_end()?;
+            }
+        }
+
+        if !self.open_blocks.is_empty() {
+            return Err(anyhow::anyhow!("unmatched `{{'"));
+        }
+
+        for (index, label) in std::mem::take(&mut self.pending_branches) {
+            let target = *self.labels
+                .get(&label)
+                .ok_or_else(|| anyhow::anyhow!("can't find label for jump to `{}'", label))?;
+            match &mut self.instructions[index].operation {
+                Operation::Branch(t) | Operation::BranchIfSubstituted(t) | Operation::BranchUnlessSubstituted(t) => {
+                    *t = Some(target);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Parses one command with its addresses
+     *
+     * @return Result<Instruction> - Parsed instruction
+     */
+    fn parse_instruction(&mut self) -> Result<Instruction> {
+        let selector = self.parse_selector()?;
+        self.skip_spaces();
+
+        let mut negate = false;
+        while self.peek() == Some('!') {
+            negate = true;
+            self.pos += 1;
+            self.skip_spaces();
+        }
+
+        let command = self.next().ok_or_else(|| anyhow::anyhow!("missing command"))?;
+        let index = self.instructions.len();
+
+        let operation = match command {
+            '{' => {
+                self.open_blocks.push(index);
+                Operation::BlockStart(0)
+            }
+            '}' => {
+                if !matches!(selector, Selector::Always) {
+                    return Err(anyhow::anyhow!("}} doesn't want any addresses"));
+                }
+                let start = self.open_blocks.pop().ok_or_else(|| anyhow::anyhow!("unexpected `}}'"))?;
+                self.instructions[start].operation = Operation::BlockStart(index + 1);
+                Operation::BlockEnd
+            }
+            's' => Operation::Substitute(Box::new(self.parse_substitution()?)),
+            'y' => Operation::Transliterate(self.parse_transliteration()?),
+            'a' => Operation::Append(self.parse_text()?),
+            'i' => Operation::Insert(self.parse_text()?),
+            'c' => Operation::Change(self.parse_text()?),
+            ':' => {
+                if !matches!(selector, Selector::Always) {
+                    return Err(anyhow::anyhow!(": doesn't want any addresses"));
+                }
+                let label = self.read_label();
+                if label.is_empty() {
+                    return Err(anyhow::anyhow!("\":\" lacks a label"));
+                }
+                self.labels.insert(label, index);
+                Operation::Label
+            }
+            'b' | 't' | 'T' => {
+                let label = self.read_label();
+                if !label.is_empty() {
+                    self.pending_branches.push((index, label));
+                }
+                match command {
+                    'b' => Operation::Branch(None),
+                    't' => Operation::BranchIfSubstituted(None),
+                    _ => Operation::BranchUnlessSubstituted(None),
+                }
+            }
+            'd' => Operation::Delete,
+            'D' => Operation::DeleteFirstLine,
+            'p' => Operation::Print,
+            'P' => Operation::PrintFirstLine,
+            'n' => Operation::Next,
+            'N' => Operation::AppendNext,
+            'h' => Operation::Hold,
+            'H' => Operation::HoldAppend,
+            'g' => Operation::Get,
+            'G' => Operation::GetAppend,
+            'x' => Operation::Exchange,
+            'q' => Operation::Quit(self.read_exit_code()?),
+            'Q' => Operation::QuitSilent(self.read_exit_code()?),
+            '=' => Operation::LineNumber,
+            'z' => Operation::Zap,
+            'r' => Operation::ReadFile(self.read_filename()?),
+            'w' => Operation::WriteFile(self.read_filename()?),
+            other => return Err(anyhow::anyhow!("unknown command: `{}'", other)),
+        };
+
+        Ok(Instruction {
+            selector,
+            negate,
+            operation,
+        })
+    }
+
+    /**
+     * Parses zero, one or two addresses
+     *
+     * @return Result<Selector> - Address selector
+     */
+    fn parse_selector(&mut self) -> Result<Selector> {
+        let zero_start = self.peek() == Some('0') && !self.chars.get(self.pos + 1).is_some_and(|c| c.is_ascii_digit() || *c == '~');
+        let start = if zero_start {
+            self.pos += 1;
+            Some(Address::Line(0))
+        } else {
+            self.parse_address()?
+        };
+
+        let Some(start) = start else {
+            return Ok(Selector::Always);
+        };
+
+        self.skip_spaces();
+        if self.peek() != Some(',') {
+            if zero_start {
+                return Err(anyhow::anyhow!("invalid usage of line address 0"));
+            }
+            return Ok(Selector::Single(start));
+        }
+        self.pos += 1;
+        self.skip_spaces();
+
+        let end = match self.peek() {
+            Some('+') => {
+                self.pos += 1;
+                RangeEnd::Following(self.read_number().ok_or_else(|| anyhow::anyhow!("expected number after +"))?)
+            }
+            Some('~') => {
+                self.pos += 1;
+                RangeEnd::Multiple(self.read_number().ok_or_else(|| anyhow::anyhow!("expected number after ~"))?)
+            }
+            _ => RangeEnd::Address(self.parse_address()?.ok_or_else(|| anyhow::anyhow!("unexpected `,'"))?),
+        };
+
+        if zero_start && !matches!(end, RangeEnd::Address(Address::Pattern(_))) {
+            return Err(anyhow::anyhow!("invalid usage of line address 0"));
+        }
+
+        Ok(Selector::Range { start, end, zero_start })
+    }
+
+    /**
+     * Parses a single address if one is present
+     *
+     * @return Result<Option<Address>> - Address or None
+     */
+    fn parse_address(&mut self) -> Result<Option<Address>> {
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                let first = self.read_number().unwrap_or(0);
+                if self.peek() == Some('~') {
+                    self.pos += 1;
+                    let step = self.read_number().unwrap_or(0);
+                    return Ok(Some(Address::Step(first, step)));
+                }
+                Ok(Some(Address::Line(first)))
+            }
+            Some('$') => {
+                self.pos += 1;
+                Ok(Some(Address::Last))
+            }
+            Some('/') | Some('\\') => {
+                let delimiter = if self.next() == Some('\\') {
+                    self.next().ok_or_else(|| anyhow::anyhow!("unexpected end of script"))?
+                } else {
+                    '/'
+                };
+                let pattern = self.read_delimited(delimiter, true)?;
+
+                let mut case_insensitive = false;
+                let mut multiline = false;
+                loop {
+                    match self.peek() {
+                        Some('I') => case_insensitive = true,
+                        Some('M') => multiline = true,
+                        _ => break,
+                    }
+                    self.pos += 1;
+                }
+
+                let regex = if pattern.is_empty() {
+                    None
+                } else {
+                    Some(build_regex(&pattern, self.extended, case_insensitive, multiline)?)
+                };
+                Ok(Some(Address::Pattern(regex)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /**
+     * sコマンドを解析する関数です
+     *
+     * 区切り文字は`s`の直後の文字で、パターン、置換文字列、
+     * フラグの順に読み取ります。
+     *
+     * 置換文字列では`&`がマッチ全体、`\1`〜`\9`がキャプチャ
+     * グループ、`\n`が改行を表し、GNU拡張の`\U`、`\L`、`\u`、
+     * `\l`、`\E`による大文字・小文字変換もサポートします。
+     *
+     * フラグはg、p、数値（N番目のマッチ）、i/I、m/M、
+     * w ファイル名をサポートします。
+     *
+     * @return Result<Substitution> - 解析されたsコマンドまたはエラー
+     */
+    fn parse_substitution(&mut self) -> Result<Substitution> {
+        let delimiter = self.next().ok_or_else(|| anyhow::anyhow!("unterminated `s' command"))?;
+        if delimiter == '\n' || delimiter == '\\' {
+            return Err(anyhow::anyhow!("unterminated `s' command"));
+        }
+
+        let pattern = self.read_delimited(delimiter, true)?;
+        let replacement_text = self.read_delimited(delimiter, false)?;
+
+        let mut substitution = Substitution {
+            regex: None,
+            replacement: parse_replacement(&replacement_text),
+            global: false,
+            occurrence: 1,
+            print: false,
+            write_file: None,
+        };
+
+        let mut case_insensitive = false;
+        let mut multiline = false;
+
+        loop {
+            match self.peek() {
+                Some('g') => substitution.global = true,
+                Some('p') => substitution.print = true,
+                Some('i') | Some('I') => case_insensitive = true,
+                Some('m') | Some('M') => multiline = true,
+                Some('e') => return Err(anyhow::anyhow!("the `e' flag is not supported")),
+                Some('w') => {
+                    self.pos += 1;
+                    substitution.write_file = Some(self.read_filename()?);
+                    break;
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let n = self.read_number().unwrap_or(0);
+                    if n == 0 {
+                        return Err(anyhow::anyhow!("number option to `s' command may not be zero"));
+                    }
+                    substitution.occurrence = n;
+                    continue;
+                }
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        if !pattern.is_empty() {
+            substitution.regex = Some(build_regex(&pattern, self.extended, case_insensitive, multiline)?);
+        }
+
+        Ok(substitution)
+    }
+
+    /**
+     * Parses the two strings of a `y` command
+     *
+     * @return Result<HashMap<char, char>> - Character mapping
+     */
+    fn parse_transliteration(&mut self) -> Result<HashMap<char, char>> {
+        let delimiter = self.next().ok_or_else(|| anyhow::anyhow!("unterminated `y' command"))?;
+        let source = unescape_transliteration(&self.read_delimited(delimiter, true)?);
+        let target = unescape_transliteration(&self.read_delimited(delimiter, true)?);
+
+        if source.chars().count() != target.chars().count() {
+            return Err(anyhow::anyhow!("strings for `y' command are different lengths"));
+        }
+
+        Ok(source.chars().zip(target.chars()).collect())
+    }
+
+    /**
+     * Reads text up to an unescaped delimiter
+     *
+     * `\delimiter` becomes the delimiter itself. When `keep_escapes`
+     * is set every other escape is kept for the regex translator;
+     * otherwise only `\newline` is reduced to a newline.
+     *
+     * @param delimiter - Closing delimiter
+     * @param keep_escapes - Whether to keep backslashes for later parsing
+     * @return Result<String> - Text between the delimiters
+     */
+    fn read_delimited(&mut self, delimiter: char, keep_escapes: bool) -> Result<String> {
+        let mut text = String::new();
+
+        loop {
+            let c = self.next().ok_or_else(|| anyhow::anyhow!("unterminated address regex"))?;
+            if c == delimiter {
+                return Ok(text);
+            }
+            if c == '\n' && keep_escapes {
+                return Err(anyhow::anyhow!("unterminated address regex"));
+            }
+            if c == '\\' {
+                let escaped = self.next().ok_or_else(|| anyhow::anyhow!("unterminated address regex"))?;
+                if escaped == delimiter {
+                    text.push(escaped);
+                } else if escaped == '\n' && !keep_escapes {
+                    text.push('\n');
+                } else {
+                    text.push('\\');
+                    text.push(escaped);
+                }
+                continue;
+            }
+            text.push(c);
+        }
+    }
+
+    /**
+     * Reads the text argument of `a`, `i` or `c`
+     *
+     * Accepts both the POSIX form (`a\` followed by a newline) and the
+     * GNU one-line form (`a text`). Lines ending in a backslash continue
+     * the text on the next line.
+     *
+     * @return Result<String> - Text to output
+     */
+    fn parse_text(&mut self) -> Result<String> {
+        self.skip_spaces();
+        if self.peek() == Some('\\') {
+            self.pos += 1;
+            if self.peek() == Some('\n') {
+                self.pos += 1;
+            }
+        }
+
+        let mut text = String::new();
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => match self.next() {
+                    Some('\n') => text.push('\n'),
+                    Some(escaped) => text.push(escaped),
+                    None => break,
+                },
+                '\n' => break,
+                _ => text.push(c),
+            }
+        }
+
+        // The command-end check expects to see the newline that ended the text
+        if self.chars.get(self.pos.wrapping_sub(1)) == Some(&'\n') {
+            self.pos -= 1;
+        }
+
+        Ok(text)
+    }
+
+    /**
+     * Reads a label for `:`, `b`, `t` and `T`
+     *
+     * @return String - Label, empty if none
+     */
+    fn read_label(&mut self) -> String {
+        self.skip_spaces();
+        let mut label = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' || c == ';' || c == '}' {
+                break;
+            }
+            label.push(c);
+            self.pos += 1;
+        }
+        label.trim_end().to_string()
+    }
+
+    /**
+     * Reads a file name that runs to the end of the line
+     *
+     * @return Result<String> - File name
+     */
+    fn read_filename(&mut self) -> Result<String> {
+        self.skip_spaces();
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            name.push(c);
+            self.pos += 1;
+        }
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("missing filename in r/R/w/W commands"));
+        }
+        Ok(name)
+    }
+
+    /**
+     * Reads the optional exit code of `q` and `Q`
+     *
+     * @return Result<i32> - Exit code, 0 if absent
+     */
+    fn read_exit_code(&mut self) -> Result<i32> {
+        self.skip_spaces();
+        Ok(self.read_number().map(|n| n as i32).unwrap_or(0))
+    }
+
+    /**
+     * Reads a decimal number
+     *
+     * @return Option<usize> - Number, or None if no digits follow
+     */
+    fn read_number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    /**
+     * Checks that a command is followed by a valid terminator
+     *
+     * @return Result<()> - Success or syntax error
+     */
+    fn expect_command_end(&mut self) -> Result<()> {
+        self.skip_spaces();
+        match self.peek() {
+            None | Some(';') | Some('\n') | Some('}') => Ok(()),
+            Some('#') => {
+                self.skip_to_line_end();
+                Ok(())
+            }
+            Some(c) => Err(anyhow::anyhow!("extra characters after command: `{}'", c)),
+        }
+    }
+
+    /**
+     * Skips whitespace and `;` between commands
+     */
+    fn skip_separators(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace() || c == ';') {
+            self.pos += 1;
+        }
+    }
+
+    /**
+     * Skips spaces and tabs
+     */
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.pos += 1;
+        }
+    }
+
+    /**
+     * Skips to the end of the current line
+     */
+    fn skip_to_line_end(&mut self) {
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.pos += 1;
+        }
+    }
+
+    /**
+     * Peeks at the current character
+     *
+     * @return Option<char> - Current character
+     */
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /**
+     * Consumes the current character
+     *
+     * @return Option<char> - Consumed character
+     */
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+}
+
+/**
+ * Parses an `s` replacement into its parts
+ *
+ * @param text - Replacement text with escapes intact
+ * @return Vec<ReplacementPart> - Replacement parts
+ */
+fn parse_replacement(text: &str) -> Vec<ReplacementPart> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars();
+
+    let flush = |literal: &mut String, parts: &mut Vec<ReplacementPart>| {
+        if !literal.is_empty() {
+            parts.push(ReplacementPart::Literal(std::mem::take(literal)));
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => {
+                flush(&mut literal, &mut parts);
+                parts.push(ReplacementPart::Group(0));
+            }
+            '\\' => {
+                let part = match chars.next() {
+                    Some(d @ '0'..='9') => Some(ReplacementPart::Group(d as usize - '0' as usize)),
+                    Some('U') => Some(ReplacementPart::Upper),
+                    Some('L') => Some(ReplacementPart::Lower),
+                    Some('u') => Some(ReplacementPart::UpperNext),
+                    Some('l') => Some(ReplacementPart::LowerNext),
+                    Some('E') => Some(ReplacementPart::EndCase),
+                    Some('n') => {
+                        literal.push('\n');
+                        None
+                    }
+                    Some('t') => {
+                        literal.push('\t');
+                        None
+                    }
+                    Some(other) => {
+                        literal.push(other);
+                        None
+                    }
+                    None => {
+                        literal.push('\\');
+                        None
+                    }
+                };
+                if let Some(part) = part {
+                    flush(&mut literal, &mut parts);
+                    parts.push(part);
+                }
+            }
+            _ => literal.push(c),
+        }
+    }
+
+    flush(&mut literal, &mut parts);
+    parts
+}
+
+/**
+ * Expands a replacement for one match
+ *
+ * @param parts - Parsed replacement
+ * @param captures - Captures of the match
+ * @return String - Replacement text
+ */
+fn expand_replacement(parts: &[ReplacementPart], captures: &regex::Captures) -> String {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Case { None, Upper, Lower }
+
+    let mut result = String::new();
+    let mut case = Case::None;
+    let mut next_case = Case::None;
+
+    let push_text = |text: &str, case: Case, next_case: &mut Case, result: &mut String| {
+        for c in text.chars() {
+            let converted: String = match (*next_case, case) {
+                (Case::Upper, _) => c.to_uppercase().collect(),
+                (Case::Lower, _) => c.to_lowercase().collect(),
+                (_, Case::Upper) => c.to_uppercase().collect(),
+                (_, Case::Lower) => c.to_lowercase().collect(),
+                _ => c.to_string(),
+            };
+            *next_case = Case::None;
+            result.push_str(&converted);
+        }
+    };
+
+    for part in parts {
+        match part {
+            ReplacementPart::Literal(text) => push_text(text, case, &mut next_case, &mut result),
+            ReplacementPart::Group(n) => {
+                let text = captures.get(*n).map(|m| m.as_str()).unwrap_or("");
+                push_text(text, case, &mut next_case, &mut result);
+            }
+            ReplacementPart::Upper => case = Case::Upper,
+            ReplacementPart::Lower => case = Case::Lower,
+            ReplacementPart::UpperNext => next_case = Case::Upper,
+            ReplacementPart::LowerNext => next_case = Case::Lower,
+            ReplacementPart::EndCase => {
+                case = Case::None;
+                next_case = Case::None;
+            }
+        }
+    }
+
+    result
+}
+
+/**
+ * Resolves escapes in a `y` command string
+ *
+ * @param text - Raw text
+ * @return String - Text with `\n`, `\t` and `\\` resolved
+ */
+fn unescape_transliteration(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/**
+ * Runtime state of one range address
+ */
+#[derive(Debug, Clone, Default)]
+struct RangeState {
+    /// Whether the range is open
+    active: bool,
+    /// Last line of a numeric range end, once known
+    end_line: Option<usize>,
+}
+
+/**
+ * What to do after an instruction ran
+ */
+enum Flow {
+    /// Continue with the next instruction
+    Continue,
+    /// Jump to an instruction index
+    Jump(usize),
+    /// End the cycle, auto-printing unless -n
+    EndCycle,
+    /// End the cycle without auto-printing
+    DeleteCycle,
+    /// Restart the cycle without reading a new line (`D`)
+    RestartCycle,
+    /// Stop all processing
+    Quit { print: bool },
+}
+
+/**
+ * Interpreter state for one input stream
+ */
+struct Runner<'a> {
+    /// Script being run
+    script: &'a SedScript,
+    /// Runtime options
+    options: &'a SedOptions,
+    /// Directory for relative `r`/`w` paths
+    working_dir: &'a std::path::Path,
+    /// Line separator
+    separator: char,
+    /// Input lines without separators
+    lines: Vec<&'a str>,
+    /// Whether the last input line ended with a separator
+    trailing_separator: bool,
+    /// Whether the last write omitted the separator of an unterminated line
+    missing_separator: bool,
+    /// Index of the next unread line
+    next_line: usize,
+    /// Current line number
+    line_number: usize,
+    /// Pattern space
+    pattern: String,
+    /// Hold space
+    hold: String,
+    /// Whether a substitution succeeded since the last line read or t/T
+    substituted: bool,
+    /// Regex used by the previous match, for empty `//`
+    last_regex: Option<Regex>,
+    /// Range state per instruction
+    ranges: Vec<RangeState>,
+    /// Text queued by `a` and `r` for the end of the cycle
+    append_queue: Vec<String>,
+    /// Files opened by `w`
+    write_files: HashMap<String, std::fs::File>,
+    /// Accumulated result
+    result: SedOutput,
+}
+
+impl<'a> Runner<'a> {
+    /**
+     * sedのメインループを実行する関数です
+     *
+     * 入力を1行ずつパターンスペースに読み込み、スクリプトの
+     * 各命令を順番に実行します。サイクルの終わりには-nが
+     * 指定されていない限りパターンスペースを出力し、aやrで
+     * キューに入れられたテキストを出力します。
+     *
+     * Dコマンドはパターンスペースに改行が残っている場合、
+     * 新しい行を読まずにサイクルを再開します。qとQは
+     * 処理を終了し、指定された終了コードを返します。
+     *
+     * @return Result<()> - 成功またはエラー
+     */
+    fn run(&mut self) -> Result<()> {
+        let quiet = self.options.quiet || self.script.wants_quiet();
+        let mut reuse_pattern = false;
+
+        loop {
+            if !reuse_pattern {
+                match self.read_line() {
+                    Some(line) => self.pattern = line,
+                    None => break,
+                }
+            }
+            reuse_pattern = false;
+            self.substituted = false;
+
+            let flow = self.execute_cycle()?;
+
+            let print = match flow {
+                Flow::DeleteCycle => false,
+                Flow::RestartCycle => {
+                    reuse_pattern = true;
+                    false
+                }
+                Flow::Quit { print } => print && !quiet,
+                _ => !quiet,
+            };
+
+            if print {
+                self.emit_pattern();
+            }
+            self.flush_append_queue();
+
+            if let Flow::Quit { .. } = flow {
+                self.result.quit = true;
+                break;
+            }
+            if reuse_pattern && self.pattern.is_empty() {
+                reuse_pattern = false;
+            }
+        }
+
+        for file in self.write_files.values_mut() {
+            file.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Runs the script once over the current pattern space
+     *
+     * @return Result<Flow> - How the cycle ended
+     */
+    fn execute_cycle(&mut self) -> Result<Flow> {
+        let mut pc = 0;
+
+        while pc < self.script.instructions.len() {
+            let instruction = &self.script.instructions[pc];
+
+            if !self.selects(pc)? {
+                pc = match instruction.operation {
+                    Operation::BlockStart(end) => end,
+                    _ => pc + 1,
+                };
+                continue;
+            }
+
+            match self.execute(pc)? {
+                Flow::Continue => pc += 1,
+                Flow::Jump(target) => pc = target,
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::EndCycle)
+    }
+
+    /**
+     * Runs one instruction
+     *
+     * @param pc - Index of the instruction
+     * @return Result<Flow> - What to do next
+     */
+    fn execute(&mut self, pc: usize) -> Result<Flow> {
+        let script = self.script;
+        let instruction = &script.instructions[pc];
+        let end_of_script = script.instructions.len();
+
+        match &instruction.operation {
+            Operation::BlockStart(_) | Operation::BlockEnd | Operation::Label => {}
+            Operation::Substitute(substitution) => self.substitute(substitution)?,
+            Operation::Transliterate(map) => {
+                self.pattern = self.pattern.chars().map(|c| *map.get(&c).unwrap_or(&c)).collect();
+            }
+            Operation::Append(text) => self.append_queue.push(format!("{}\n", text)),
+            Operation::Insert(text) => self.write_output(&format!("{}\n", text)),
+            Operation::Change(text) => {
+                // A range prints the text once, at its end
+                let range_open = self.ranges[pc].active;
+                if instruction.negate || !range_open {
+                    self.write_output(&format!("{}\n", text));
+                }
+                return Ok(Flow::DeleteCycle);
+            }
+            Operation::Branch(target) => return Ok(Flow::Jump(target.unwrap_or(end_of_script))),
+            Operation::BranchIfSubstituted(target) => {
+                if self.substituted {
+                    self.substituted = false;
+                    return Ok(Flow::Jump(target.unwrap_or(end_of_script)));
+                }
+            }
+            Operation::BranchUnlessSubstituted(target) => {
+                if !self.substituted {
+                    return Ok(Flow::Jump(target.unwrap_or(end_of_script)));
+                }
+                self.substituted = false;
+            }
+            Operation::Delete => return Ok(Flow::DeleteCycle),
+            Operation::DeleteFirstLine => {
+                return match self.pattern.find('\n') {
+                    Some(pos) => {
+                        self.pattern.replace_range(..=pos, "");
+                        Ok(Flow::RestartCycle)
+                    }
+                    None => Ok(Flow::DeleteCycle),
+                };
+            }
+            Operation::Print => self.emit_text(&self.pattern.clone()),
+            Operation::PrintFirstLine => {
+                let first = self.pattern.split('\n').next().unwrap_or("").to_string();
+                self.emit_text(&first);
+            }
+            Operation::Next => {
+                if self.next_line >= self.lines.len() {
+                    return Ok(Flow::Quit { print: true });
+                }
+                if !self.options.quiet && !self.script.wants_quiet() {
+                    self.emit_pattern();
+                }
+                self.flush_append_queue();
+                self.pattern = self.read_line().unwrap_or_default();
+            }
+            Operation::AppendNext => {
+                if self.next_line >= self.lines.len() {
+                    return Ok(Flow::Quit { print: true });
+                }
+                self.flush_append_queue();
+                let line = self.read_line().unwrap_or_default();
+                self.pattern.push('\n');
+                self.pattern.push_str(&line);
+            }
+            Operation::Hold => self.hold = self.pattern.clone(),
+            Operation::HoldAppend => {
+                self.hold.push('\n');
+                self.hold.push_str(&self.pattern);
+            }
+            Operation::Get => self.pattern = self.hold.clone(),
+            Operation::GetAppend => {
+                self.pattern.push('\n');
+                self.pattern.push_str(&self.hold);
+            }
+            Operation::Exchange => std::mem::swap(&mut self.pattern, &mut self.hold),
+            Operation::Quit(code) => {
+                self.result.exit_code = *code;
+                return Ok(Flow::Quit { print: true });
+            }
+            Operation::QuitSilent(code) => {
+                self.result.exit_code = *code;
+                return Ok(Flow::Quit { print: false });
+            }
+            Operation::LineNumber => self.write_output(&format!("{}\n", self.line_number)),
+            Operation::Zap => self.pattern.clear(),
+            Operation::ReadFile(path) => {
+                // A missing file is silently ignored, as in POSIX sed
+                if let Ok(content) = std::fs::read_to_string(self.working_dir.join(path)) {
+                    let mut content = content;
+                    if !content.is_empty() && !content.ends_with('\n') {
+                        content.push('\n');
+                    }
+                    self.append_queue.push(content);
+                }
+            }
+            Operation::WriteFile(path) => {
+                let text = self.pattern.clone();
+                self.write_to(path, &text)?;
+            }
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    /**
+     * Runs an `s` command on the pattern space
+     *
+     * @param substitution - Compiled substitution
+     * @return Result<()> - Success or error
+     */
+    fn substitute(&mut self, substitution: &Substitution) -> Result<()> {
+        let regex = self.resolve_regex(&substitution.regex)?;
+
+        let mut result = String::new();
+        let mut last_end = 0;
+        let mut count = 0;
+        let mut replaced = false;
+
+        for captures in regex.captures_iter(&self.pattern) {
+            count += 1;
+            let whole = captures.get(0).expect("group 0 always exists");
+
+            if count < substitution.occurrence {
+                continue;
+            }
+            if count > substitution.occurrence && !substitution.global {
+                break;
+            }
+
+            result.push_str(&self.pattern[last_end..whole.start()]);
+            result.push_str(&expand_replacement(&substitution.replacement, &captures));
+            last_end = whole.end();
+            replaced = true;
+        }
+
+        if !replaced {
+            return Ok(());
+        }
+
+        result.push_str(&self.pattern[last_end..]);
+        self.pattern = result;
+        self.substituted = true;
+
+        if substitution.print {
+            self.emit_text(&self.pattern.clone());
+        }
+        if let Some(path) = &substitution.write_file {
+            let text = self.pattern.clone();
+            self.write_to(path, &text)?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * 命令のアドレスが現在の行を選択するか判定する関数です
+     *
+     * 単一アドレスは現在の行と直接比較し、範囲アドレスは
+     * 命令ごとの状態（開いているかどうか、終了行）を更新しながら
+     * 判定します。
+     *
+     * 範囲の終了アドレスが行番号で、開始行以下の場合は
+     * 1行だけの範囲になります。正規表現の終了アドレスは
+     * 開始行の次の行から検査されますが、`0,/re/`の形式では
+     * 最初の行から検査されます。
+     *
+     * `!`が指定されている場合は結果を反転します。
+     *
+     * @param pc - 命令のインデックス
+     * @return Result<bool> - 命令を実行するかどうか
+     */
+    fn selects(&mut self, pc: usize) -> Result<bool> {
+        let script = self.script;
+        let instruction = &script.instructions[pc];
+
+        let selected = match &instruction.selector {
+            Selector::Always => true,
+            Selector::Single(address) => self.matches(address)?,
+            Selector::Range { start, end, zero_start } => {
+                let line = self.line_number;
+
+                if self.ranges[pc].active {
+                    let closes = match end {
+                        RangeEnd::Address(Address::Pattern(regex)) => self.matches(&Address::Pattern(regex.clone()))?,
+                        RangeEnd::Address(Address::Last) => self.is_last_line(),
+                        RangeEnd::Address(_) | RangeEnd::Following(_) | RangeEnd::Multiple(_) => {
+                            self.ranges[pc].end_line.is_none_or(|end_line| line >= end_line)
+                        }
+                    };
+                    if closes {
+                        self.ranges[pc] = RangeState::default();
+                    }
+                    true
+                } else {
+                    let starts = if *zero_start { line == 1 } else { self.matches(start)? };
+                    if starts {
+                        let end_line = match end {
+                            RangeEnd::Address(Address::Line(n)) => Some(*n),
+                            // GNU treats `addr,first~step` like `addr,~step` when used as an end
+                            RangeEnd::Address(Address::Step(_, step)) => Some(next_multiple(line, *step)),
+                            RangeEnd::Following(n) => Some(line + n),
+                            RangeEnd::Multiple(n) => Some(next_multiple(line, *n)),
+                            RangeEnd::Address(_) => None,
+                        };
+
+                        let closes_now = match end {
+                            RangeEnd::Address(Address::Pattern(regex)) if *zero_start => {
+                                self.matches(&Address::Pattern(regex.clone()))?
+                            }
+                            RangeEnd::Address(Address::Last) => self.is_last_line(),
+                            RangeEnd::Address(Address::Pattern(_)) => false,
+                            _ => end_line.is_some_and(|end_line| end_line <= line),
+                        };
+
+                        if !closes_now {
+                            self.ranges[pc] = RangeState {
+                                active: true,
+                                end_line,
+                            };
+                        }
+                    }
+                    starts
+                }
+            }
+        };
+
+        Ok(selected != instruction.negate)
+    }
+
+    /**
+     * Checks a single address against the current line
+     *
+     * @param address - Address to check
+     * @return Result<bool> - True if the line matches
+     */
+    fn matches(&mut self, address: &Address) -> Result<bool> {
+        Ok(match address {
+            Address::Line(n) => self.line_number == *n,
+            Address::Last => self.is_last_line(),
+            Address::Step(first, step) => {
+                if *step == 0 {
+                    self.line_number == *first
+                } else {
+                    self.line_number >= *first && (self.line_number - first).is_multiple_of(*step)
+                }
+            }
+            Address::Pattern(regex) => {
+                let regex = self.resolve_regex(regex)?;
+                regex.is_match(&self.pattern)
+            }
+        })
+    }
+
+    /**
+     * Resolves an empty regex to the last one used
+     *
+     * @param regex - Regex from the script, None for `//`
+     * @return Result<Regex> - Regex to match with
+     */
+    fn resolve_regex(&mut self, regex: &Option<Regex>) -> Result<Regex> {
+        match regex {
+            Some(regex) => {
+                self.last_regex = Some(regex.clone());
+                Ok(regex.clone())
+            }
+            None => self.last_regex.clone().ok_or_else(|| anyhow::anyhow!("no previous regular expression")),
+        }
+    }
+
+    /**
+     * Reads the next input line
+     *
+     * @return Option<String> - Line, or None at end of input
+     */
+    fn read_line(&mut self) -> Option<String> {
+        let line = self.lines.get(self.next_line)?.to_string();
+        self.next_line += 1;
+        self.line_number += 1;
+        Some(line)
+    }
+
+    /**
+     * Checks whether the current line is the last one
+     *
+     * @return bool - True on the last line
+     */
+    fn is_last_line(&self) -> bool {
+        self.next_line >= self.lines.len()
+    }
+
+    /**
+     * Writes the pattern space followed by a separator
+     */
+    fn emit_pattern(&mut self) {
+        let pattern = std::mem::take(&mut self.pattern);
+        self.emit_text(&pattern);
+        self.pattern = pattern;
+    }
+
+    /**
+     * Writes text followed by a separator
+     *
+     * A missing separator on the very last input line is preserved.
+     *
+     * @param text - Text to write
+     */
+    fn emit_text(&mut self, text: &str) {
+        self.write_output(text);
+        if self.trailing_separator || !self.is_last_line() {
+            self.result.output.push(self.separator);
+        } else {
+            self.missing_separator = true;
+        }
+    }
+
+    /**
+     * Writes raw text to the output
+     *
+     * If the previous write left out the separator of an unterminated
+     * last line, it is added first so lines never run together.
+     *
+     * @param text - Text to write
+     */
+    fn write_output(&mut self, text: &str) {
+        if self.missing_separator {
+            self.result.output.push(self.separator);
+            self.missing_separator = false;
+        }
+        self.result.output.push_str(text);
+    }
+
+    /**
+     * Writes queued `a` and `r` text
+     */
+    fn flush_append_queue(&mut self) {
+        for text in std::mem::take(&mut self.append_queue) {
+            self.write_output(&text);
+        }
+    }
+
+    /**
+     * Writes a line to a `w` target
+     *
+     * @param path - File name, or /dev/stdout and /dev/stderr
+     * @param text - Line to write
+     * @return Result<()> - Success or error
+     */
+    fn write_to(&mut self, path: &str, text: &str) -> Result<()> {
+        if path == "/dev/stdout" || path == "/dev/stderr" {
+            self.result.stdout_writes.push_str(text);
+            self.result.stdout_writes.push(self.separator);
+            return Ok(());
+        }
+
+        if !self.write_files.contains_key(path) {
+            let file = std::fs::File::create(self.working_dir.join(path))
+                .map_err(|e| anyhow::anyhow!("couldn't open file {}: {}", path, e))?;
+            self.write_files.insert(path.to_string(), file);
+        }
+
+        let file = self.write_files.get_mut(path).expect("file was just opened");
+        file.write_all(text.as_bytes())?;
+        file.write_all(self.separator.to_string().as_bytes())?;
+        Ok(())
+    }
+}
+
+/**
+ * Finds the next multiple of n at or after line
+ *
+ * @param line - Current line
+ * @param n - Step
+ * @return usize - Next multiple (line itself if n is 0)
+ */
+fn next_multiple(line: usize, n: usize) -> usize {
+    if n == 0 {
+        line
+    } else {
+        line.div_ceil(n) * n
+    }
+}
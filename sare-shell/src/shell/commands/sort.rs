@@ -0,0 +1,844 @@
+/*!
+ * @file sort.rs
+ * @brief Line comparison and external merge sort for the sort builtin
+ *
+ * This module implements GNU sort's key model (-k, -t and the
+ * per-key ordering options) with byte-wise, locale-independent
+ * comparison, and sorts inputs larger than the memory buffer by
+ * spilling sorted runs to temporary files and merging them.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file sort.rs
+ * @description Sort keys, numeric/human/version/month ordering, stable
+ * and unique output, and external merge sort with temporary run files.
+ */
+
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+/// Default memory buffer before sorted runs are written to disk
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024 * 1024;
+
+/**
+ * How a key is ordered
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Collation {
+    /// Byte-wise comparison
+    #[default]
+    Text,
+    /// Leading decimal number (-n)
+    Numeric,
+    /// Floating-point number with exponent (-g)
+    General,
+    /// Number with an SI suffix such as 2K or 1G (-h)
+    Human,
+    /// Version numbers within text (-V)
+    Version,
+    /// Month names JAN..DEC (-M)
+    Month,
+}
+
+/**
+ * Ordering options that apply to the whole line or to one key
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KeyFlags {
+    /// Comparison kind
+    pub ordering: Collation,
+    /// Ignore leading blanks (-b)
+    pub skip_blanks: bool,
+    /// Fold lower case to upper case (-f)
+    pub fold_case: bool,
+    /// Consider only blanks and alphanumerics (-d)
+    pub dictionary: bool,
+    /// Consider only printable characters (-i)
+    pub printable: bool,
+    /// Reverse the result (-r)
+    pub reverse: bool,
+}
+
+impl KeyFlags {
+    /**
+     * Applies one option letter
+     *
+     * @param flag - Option letter (b d f g h i M n r V)
+     * @return bool - False if the letter is not an ordering option
+     */
+    pub fn apply(&mut self, flag: char) -> bool {
+        match flag {
+            'b' => self.skip_blanks = true,
+            'd' => self.dictionary = true,
+            'f' => self.fold_case = true,
+            'i' => self.printable = true,
+            'r' => self.reverse = true,
+            'n' => self.ordering = Collation::Numeric,
+            'g' => self.ordering = Collation::General,
+            'h' => self.ordering = Collation::Human,
+            'V' => self.ordering = Collation::Version,
+            'M' => self.ordering = Collation::Month,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/**
+ * Position in a line: 1-based field and character
+ */
+#[derive(Debug, Clone, Copy)]
+struct KeyPosition {
+    /// Field number, from 1
+    field: usize,
+    /// Character within the field; 0 in an end position means the field's end
+    character: usize,
+    /// Skip leading blanks of the field before counting characters
+    skip_blanks: bool,
+}
+
+/**
+ * Sort key given with -k POS1[,POS2]
+ */
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    /// Start position
+    start: KeyPosition,
+    /// End position, None for the end of the line
+    end: Option<KeyPosition>,
+    /// Ordering options of this key
+    flags: KeyFlags,
+    /// Whether the key had its own ordering options
+    has_flags: bool,
+}
+
+impl SortKey {
+    /**
+     * -kの引数を解析する関数です
+     *
+     * 書式はF[.C][OPTS][,F[.C][OPTS]]です。開始位置の文字番号の
+     * 省略値は1、終了位置の文字番号の省略値は0（フィールドの終わり）
+     * です。オプション文字はキー全体に適用されますが、bだけは
+     * 書かれた位置（開始または終了）にのみ適用されます。
+     *
+     * @param spec - -kの引数
+     * @return Result<SortKey> - キーまたはエラー
+     */
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || anyhow::anyhow!("invalid number at field start: invalid count at start of '{}'", spec);
+        let mut flags = KeyFlags::default();
+        let mut has_flags = false;
+
+        let mut parse_position = |text: &str, is_end: bool| -> Result<KeyPosition> {
+            let digits_end = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+            let (numbers, options) = text.split_at(digits_end);
+            let (field, character) = match numbers.split_once('.') {
+                Some((field, character)) => (field, Some(character)),
+                None => (numbers, None),
+            };
+            let field: usize = field.parse().map_err(|_| invalid())?;
+            if field == 0 {
+                return Err(anyhow::anyhow!("field number is zero: invalid field specification '{}'", spec));
+            }
+            let character = match character {
+                Some(character) => character.parse().map_err(|_| invalid())?,
+                None if is_end => 0,
+                None => 1,
+            };
+            if !is_end && character == 0 {
+                return Err(anyhow::anyhow!("character offset is zero: invalid field specification '{}'", spec));
+            }
+
+            let mut skip_blanks = false;
+            for option in options.chars() {
+                if option == 'b' {
+                    skip_blanks = true;
+                } else if !flags.apply(option) {
+                    return Err(anyhow::anyhow!("invalid key option '{}' in '{}'", option, spec));
+                }
+                has_flags = true;
+            }
+            Ok(KeyPosition {
+                field,
+                character,
+                skip_blanks,
+            })
+        };
+
+        let (start, end) = match spec.split_once(',') {
+            Some((start, end)) => (parse_position(start, false)?, Some(parse_position(end, true)?)),
+            None => (parse_position(spec, false)?, None),
+        };
+        Ok(SortKey {
+            start,
+            end,
+            flags,
+            has_flags,
+        })
+    }
+
+    /**
+     * Extracts the key text from a line
+     *
+     * @param line - Line without its newline
+     * @param separator - Field separator from -t, or None for blank runs
+     * @param global_blanks - -b given globally and the key has no options
+     * @return &str - Key text, possibly empty
+     */
+    fn extract<'a>(&self, line: &'a str, separator: Option<char>, global_blanks: bool) -> &'a str {
+        let fields = field_bounds(line, separator);
+        let locate = |position: &KeyPosition, is_end: bool| -> usize {
+            let Some(&(field_start, field_end)) = fields.get(position.field - 1) else {
+                return line.len();
+            };
+            let mut start = field_start;
+            if position.skip_blanks || (global_blanks && !self.has_flags) {
+                start += line[start..field_end].len() - line[start..field_end].trim_start_matches([' ', '\t']).len();
+            }
+            if is_end && position.character == 0 {
+                return field_end;
+            }
+            let skip = if is_end { position.character } else { position.character - 1 };
+            line[start..field_end]
+                .char_indices()
+                .nth(skip)
+                .map_or(field_end, |(offset, _)| start + offset)
+        };
+
+        let start = locate(&self.start, false);
+        let end = self.end.as_ref().map_or(line.len(), |end| locate(end, true));
+        if start < end { &line[start..end] } else { "" }
+    }
+}
+
+/**
+ * Splits a line into fields
+ *
+ * Without a separator, each field starts with the blanks that precede
+ * it, as in GNU sort.
+ *
+ * @param line - Line
+ * @param separator - Field separator, or None
+ * @return Vec<(usize, usize)> - Byte range of each field
+ */
+fn field_bounds(line: &str, separator: Option<char>) -> Vec<(usize, usize)> {
+    let mut fields = Vec::new();
+    match separator {
+        Some(separator) => {
+            let mut start = 0;
+            for (index, c) in line.char_indices() {
+                if c == separator {
+                    fields.push((start, index));
+                    start = index + c.len_utf8();
+                }
+            }
+            fields.push((start, line.len()));
+        }
+        None => {
+            let bytes = line.as_bytes();
+            let mut start = 0;
+            let mut index = 0;
+            while index < bytes.len() {
+                while index < bytes.len() && (bytes[index] == b' ' || bytes[index] == b'\t') {
+                    index += 1;
+                }
+                while index < bytes.len() && bytes[index] != b' ' && bytes[index] != b'\t' {
+                    index += 1;
+                }
+                fields.push((start, index));
+                start = index;
+            }
+        }
+    }
+    fields
+}
+
+/**
+ * Options of a sort run
+ */
+#[derive(Debug, Clone)]
+pub struct SortOptions {
+    /// Keys from -k, in priority order
+    pub keys: Vec<SortKey>,
+    /// Global ordering options
+    pub flags: KeyFlags,
+    /// Field separator (-t)
+    pub separator: Option<char>,
+    /// Disable the last-resort whole-line comparison (-s)
+    pub stable: bool,
+    /// Output only the first of each run of equal lines (-u)
+    pub unique: bool,
+    /// Memory buffer in bytes before spilling to disk (-S)
+    pub buffer_size: usize,
+    /// Directory for temporary run files (-T)
+    pub temp_dir: PathBuf,
+}
+
+impl Default for SortOptions {
+    fn default() -> Self {
+        SortOptions {
+            keys: Vec::new(),
+            flags: KeyFlags::default(),
+            separator: None,
+            stable: false,
+            unique: false,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+impl SortOptions {
+    /**
+     * Compares two lines by the keys
+     *
+     * @param a - First line
+     * @param b - Second line
+     * @return Ordering - Key ordering, without the last-resort comparison
+     */
+    fn compare_keys(&self, a: &str, b: &str) -> Ordering {
+        if self.keys.is_empty() {
+            return compare_with(a, b, &self.flags);
+        }
+        for key in &self.keys {
+            let flags = if key.has_flags { &key.flags } else { &self.flags };
+            let ordering = compare_with(
+                key.extract(a, self.separator, self.flags.skip_blanks),
+                key.extract(b, self.separator, self.flags.skip_blanks),
+                flags,
+            );
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /**
+     * Compares two lines for output order
+     *
+     * Lines with equal keys are compared byte-wise as a last resort
+     * unless -s or -u is given, so the result does not depend on the
+     * input order or the locale.
+     *
+     * @param a - First line
+     * @param b - Second line
+     * @return Ordering - Output ordering
+     */
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = self.compare_keys(a, b);
+        if ordering != Ordering::Equal || self.stable || self.unique {
+            return ordering;
+        }
+        let last_resort = a.as_bytes().cmp(b.as_bytes());
+        if self.flags.reverse { last_resort.reverse() } else { last_resort }
+    }
+}
+
+/**
+ * Compares two keys with one set of ordering options
+ *
+ * @param a - First key
+ * @param b - Second key
+ * @param flags - Ordering options
+ * @return Ordering - Result, reversed for -r
+ */
+fn compare_with(a: &str, b: &str, flags: &KeyFlags) -> Ordering {
+    let (a, b) = if flags.skip_blanks {
+        (a.trim_start_matches([' ', '\t']), b.trim_start_matches([' ', '\t']))
+    } else {
+        (a, b)
+    };
+
+    let ordering = match flags.ordering {
+        Collation::Numeric => compare_numeric(a, b),
+        Collation::General => {
+            let (x, y) = (general_number(a), general_number(b));
+            x.partial_cmp(&y).unwrap_or_else(|| x.is_nan().cmp(&y.is_nan()).reverse())
+        }
+        Collation::Human => human_number(a).partial_cmp(&human_number(b)).unwrap_or(Ordering::Equal),
+        Collation::Version => compare_version(a, b),
+        Collation::Month => month_number(a).cmp(&month_number(b)),
+        Collation::Text if flags.fold_case || flags.dictionary || flags.printable => {
+            let filter = |text: &str| -> Vec<u8> {
+                text.bytes()
+                    .filter(|byte| !flags.dictionary || byte.is_ascii_alphanumeric() || *byte == b' ' || *byte == b'\t' || *byte >= 0x80)
+                    .filter(|byte| !flags.printable || !byte.is_ascii_control())
+                    .map(|byte| if flags.fold_case { byte.to_ascii_uppercase() } else { byte })
+                    .collect()
+            };
+            filter(a).cmp(&filter(b))
+        }
+        Collation::Text => a.as_bytes().cmp(b.as_bytes()),
+    };
+    if flags.reverse { ordering.reverse() } else { ordering }
+}
+
+/**
+ * Splits the leading decimal number of a string
+ *
+ * @param text - Key text
+ * @return (bool, &str, &str) - Negative sign, integer digits without leading zeros, fraction digits
+ */
+fn decimal_parts(text: &str) -> (bool, &str, &str) {
+    let text = text.trim_start_matches([' ', '\t']);
+    let (negative, rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let integer_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let integer = rest[..integer_end].trim_start_matches('0');
+    let fraction = rest[integer_end..]
+        .strip_prefix('.')
+        .map(|fraction| {
+            let end = fraction.find(|c: char| !c.is_ascii_digit()).unwrap_or(fraction.len());
+            fraction[..end].trim_end_matches('0')
+        })
+        .unwrap_or("");
+    let is_zero = integer.is_empty() && fraction.is_empty();
+    (negative && !is_zero, integer, fraction)
+}
+
+/**
+ * Compares leading decimal numbers exactly, like sort -n
+ *
+ * Numbers are compared digit by digit so long values keep full
+ * precision; text without a number counts as zero.
+ *
+ * @param a - First key
+ * @param b - Second key
+ * @return Ordering - Numeric ordering
+ */
+fn compare_numeric(a: &str, b: &str) -> Ordering {
+    let (a_negative, a_integer, a_fraction) = decimal_parts(a);
+    let (b_negative, b_integer, b_fraction) = decimal_parts(b);
+    let magnitude = a_integer
+        .len()
+        .cmp(&b_integer.len())
+        .then_with(|| a_integer.cmp(b_integer))
+        .then_with(|| a_fraction.cmp(b_fraction));
+    match (a_negative, b_negative) {
+        (false, false) => magnitude,
+        (true, true) => magnitude.reverse(),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    }
+}
+
+/**
+ * Parses the leading floating-point number for -g
+ *
+ * @param text - Key text
+ * @return f64 - Value; text without a number is NaN and sorts first
+ */
+fn general_number(text: &str) -> f64 {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+        .unwrap_or(text.len());
+    (1..=end)
+        .rev()
+        .find_map(|length| text[..length].parse::<f64>().ok())
+        .unwrap_or(f64::NAN)
+}
+
+/**
+ * Converts a human-readable size such as 1.5K or 2G for -h
+ *
+ * @param text - Key text
+ * @return f64 - Value scaled by the suffix
+ */
+fn human_number(text: &str) -> f64 {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(text.len());
+    let value: f64 = text[..end].parse().unwrap_or(0.0);
+    let exponent = match text[end..].chars().next() {
+        Some('k' | 'K') => 1,
+        Some('M') => 2,
+        Some('G') => 3,
+        Some('T') => 4,
+        Some('P') => 5,
+        Some('E') => 6,
+        Some('Z') => 7,
+        Some('Y') => 8,
+        _ => 0,
+    };
+    value * 1024f64.powi(exponent)
+}
+
+/**
+ * Maps a month abbreviation to its number for -M
+ *
+ * @param text - Key text
+ * @return u8 - 1 for JAN through 12 for DEC, 0 if unknown
+ */
+fn month_number(text: &str) -> u8 {
+    const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+    let prefix: String = text.trim_start().chars().take(3).collect::<String>().to_ascii_uppercase();
+    MONTHS.iter().position(|month| *month == prefix).map_or(0, |index| index as u8 + 1)
+}
+
+/**
+ * Compares version strings for -V
+ *
+ * Runs of digits compare numerically and other text compares
+ * character by character, with `~` before everything (even the end
+ * of the string) and letters before other characters.
+ *
+ * @param a - First key
+ * @param b - Second key
+ * @return Ordering - Version ordering
+ */
+fn compare_version(a: &str, b: &str) -> Ordering {
+    let rank = |c: Option<u8>| -> i32 {
+        match c {
+            Some(b'~') => -1,
+            None => 0,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => c as i32,
+            Some(c) => c as i32 + 256,
+        }
+    };
+
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let (x, y) = (rank(a.get(i).copied()), rank(b.get(j).copied()));
+            if x != y {
+                return x.cmp(&y);
+            }
+            i += 1;
+            j += 1;
+        }
+        while i < a.len() && a[i] == b'0' {
+            i += 1;
+        }
+        while j < b.len() && b[j] == b'0' {
+            j += 1;
+        }
+        let digits_start = (i, j);
+        while i < a.len() && a[i].is_ascii_digit() {
+            i += 1;
+        }
+        while j < b.len() && b[j].is_ascii_digit() {
+            j += 1;
+        }
+        let (x, y) = (&a[digits_start.0..i], &b[digits_start.1..j]);
+        let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/**
+ * Source of lines to sort
+ */
+pub enum SortInput {
+    /// Text already in memory (pipeline input)
+    Text(String),
+    /// File read in chunks
+    File(PathBuf),
+}
+
+/**
+ * Head of one run during the merge
+ */
+struct MergeHead<'a> {
+    /// Current line of the run
+    line: String,
+    /// Run index; earlier runs win ties to keep the sort stable
+    run: usize,
+    /// Options for comparing lines
+    options: &'a SortOptions,
+}
+
+impl PartialEq for MergeHead<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeHead<'_> {}
+
+impl PartialOrd for MergeHead<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeHead<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap, so the smallest line must compare greatest
+        self.options
+            .compare(&other.line, &self.line)
+            .then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+/**
+ * Temporary run files, deleted when dropped
+ */
+struct RunFiles {
+    /// Paths of the written runs
+    paths: Vec<PathBuf>,
+}
+
+impl Drop for RunFiles {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/**
+ * 入力を並べ替えて書き出す関数です
+ *
+ * 入力を順に読み込み、合計がbuffer_sizeを超えるたびに
+ * その時点の行を安定ソートして一時ファイル（ラン）に書き出します。
+ * 全ての入力がバッファに収まった場合はメモリ上でソートして
+ * そのまま出力し、そうでない場合は各ランの先頭行をヒープに入れて
+ * k-wayマージします。同じ行はランの番号が小さい方を先に出すため、
+ * 外部ソートでも結果は安定です。
+ *
+ * -uの場合は直前に出力した行とキーが等しい行を出力しません。
+ * 一時ファイルは処理の成否にかかわらず削除されます。
+ *
+ * @param inputs - 入力（パイプラインの内容またはファイル）
+ * @param options - ソートの設定
+ * @param output - 出力先
+ * @return Result<()> - 成功またはエラー
+ */
+pub fn sort_inputs(inputs: Vec<SortInput>, options: &SortOptions, output: &mut dyn Write) -> Result<()> {
+    let mut runs = RunFiles { paths: Vec::new() };
+    let mut buffer: Vec<String> = Vec::new();
+    let mut buffered_bytes = 0;
+
+    let mut add_line = |line: String, buffer: &mut Vec<String>, runs: &mut RunFiles| -> Result<()> {
+        buffered_bytes += line.len() + 1;
+        buffer.push(line);
+        if buffered_bytes >= options.buffer_size {
+            runs.paths.push(write_run(std::mem::take(buffer), options, runs.paths.len())?);
+            buffered_bytes = 0;
+        }
+        Ok(())
+    };
+
+    for input in inputs {
+        match input {
+            SortInput::Text(text) => {
+                for line in text.lines() {
+                    add_line(line.to_string(), &mut buffer, &mut runs)?;
+                }
+            }
+            SortInput::File(path) => {
+                let mut reader = BufReader::new(File::open(&path)?);
+                let mut bytes = Vec::new();
+                while reader.read_until(b'\n', &mut bytes)? > 0 {
+                    if bytes.last() == Some(&b'\n') {
+                        bytes.pop();
+                    }
+                    add_line(String::from_utf8_lossy(&bytes).into_owned(), &mut buffer, &mut runs)?;
+                    bytes.clear();
+                }
+            }
+        }
+    }
+
+    if runs.paths.is_empty() {
+        buffer.sort_by(|a, b| options.compare(a, b));
+        let mut previous: Option<&String> = None;
+        for line in &buffer {
+            if options.unique && previous.is_some_and(|previous| options.compare_keys(previous, line) == Ordering::Equal) {
+                continue;
+            }
+            output.write_all(line.as_bytes())?;
+            output.write_all(b"\n")?;
+            previous = Some(line);
+        }
+        return Ok(());
+    }
+    if !buffer.is_empty() {
+        runs.paths.push(write_run(buffer, options, runs.paths.len())?);
+    }
+    merge_runs(&runs.paths, options, output)
+}
+
+/**
+ * Sorts a chunk of lines and writes it to a temporary file
+ *
+ * @param lines - Lines of the chunk
+ * @param options - Sort options
+ * @param index - Run number, used in the file name
+ * @return Result<PathBuf> - Path of the run file
+ */
+fn write_run(mut lines: Vec<String>, options: &SortOptions, index: usize) -> Result<PathBuf> {
+    lines.sort_by(|a, b| options.compare(a, b));
+    let path = options
+        .temp_dir
+        .join(format!("sare-sort-{}-{}-{}", std::process::id(), uuid::Uuid::new_v4().simple(), index));
+    let mut writer = BufWriter::new(
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|error| anyhow::anyhow!("cannot create temporary file in '{}': {}", options.temp_dir.display(), error))?,
+    );
+    for line in lines {
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(path)
+}
+
+/**
+ * Merges sorted run files
+ *
+ * @param paths - Run files in input order
+ * @param options - Sort options
+ * @param output - Destination
+ * @return Result<()> - Success or error
+ */
+fn merge_runs(paths: &[PathBuf], options: &SortOptions, output: &mut dyn Write) -> Result<()> {
+    let mut readers = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (run, path) in paths.iter().enumerate() {
+        let mut reader = BufReader::new(File::open(path)?);
+        if let Some(line) = next_run_line(&mut reader)? {
+            heap.push(MergeHead { line, run, options });
+        }
+        readers.push(reader);
+    }
+
+    let mut previous: Option<String> = None;
+    while let Some(head) = heap.pop() {
+        let duplicate = options.unique
+            && previous
+                .as_deref()
+                .is_some_and(|previous| options.compare_keys(previous, &head.line) == Ordering::Equal);
+        if !duplicate {
+            output.write_all(head.line.as_bytes())?;
+            output.write_all(b"\n")?;
+        }
+        if let Some(line) = next_run_line(&mut readers[head.run])? {
+            heap.push(MergeHead { line, run: head.run, options });
+        }
+        if !duplicate {
+            previous = Some(head.line);
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Reads the next line of a run file
+ *
+ * @param reader - Run file reader
+ * @return Result<Option<String>> - Line without its newline, or None at the end
+ */
+fn next_run_line(reader: &mut BufReader<File>) -> Result<Option<String>> {
+    let mut bytes = Vec::new();
+    if reader.read_until(b'\n', &mut bytes)? == 0 {
+        return Ok(None);
+    }
+    if bytes.last() == Some(&b'\n') {
+        bytes.pop();
+    }
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/**
+ * Parses a -S buffer size such as 100M or 1G
+ *
+ * @param text - Size with an optional b, K, M, G or T suffix (default K)
+ * @return Result<usize> - Size in bytes
+ */
+pub fn parse_buffer_size(text: &str) -> Result<usize> {
+    let invalid = || anyhow::anyhow!("invalid -S argument '{}'", text);
+    let (number, multiplier) = match text.char_indices().last() {
+        Some((index, suffix)) if suffix.is_ascii_alphabetic() => {
+            let multiplier: usize = match suffix.to_ascii_uppercase() {
+                'B' => 1,
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                'T' => 1 << 40,
+                _ => return Err(invalid()),
+            };
+            (&text[..index], multiplier)
+        }
+        _ => (text, 1 << 10),
+    };
+    let number: usize = number.parse().map_err(|_| invalid())?;
+    Ok(number.saturating_mul(multiplier).max(1))
+}
+
+/**
+ * Output file for -o that is only created on the first write
+ *
+ * All input has been read by the time sort_inputs writes, so the
+ * output file may also be one of the inputs.
+ */
+pub struct OutputFile {
+    /// Destination path
+    path: PathBuf,
+    /// Open file, after the first write
+    file: Option<BufWriter<File>>,
+}
+
+impl OutputFile {
+    /**
+     * Creates a lazily opened output file
+     *
+     * @param path - Destination path
+     * @return OutputFile - Output file
+     */
+    pub fn new(path: PathBuf) -> Self {
+        OutputFile { path, file: None }
+    }
+
+    /**
+     * Opens the file if nothing was written yet and flushes it
+     *
+     * @return std::io::Result<()> - Success or error
+     */
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        self.writer()?.flush()
+    }
+
+    /**
+     * Gets the writer, opening and truncating the file on first use
+     *
+     * @return std::io::Result<&mut BufWriter<File>> - Writer
+     */
+    fn writer(&mut self) -> std::io::Result<&mut BufWriter<File>> {
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => BufWriter::new(File::create(&self.path)?),
+        };
+        Ok(self.file.insert(file))
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.writer()?.write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
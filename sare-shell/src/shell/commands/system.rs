@@ -16,6 +16,7 @@ use anyhow::Result;
 use crate::shell::parser::ParsedCommand;
 use crate::shell::Shell;
 use crate::shell::commands::{CommandHandler, CommandResult};
+use crate::shell::commands::records::Table;
 
 /**
  * Exit shell command
@@ -95,6 +96,7 @@ impl CommandHandler for HistoryCommand {
         
         let history = shell.get_history();
         let mut output = String::new();
+        let mut records = Table::new(&["index", "command", "time", "exit_code"]);
         
         for (i, entry) in history.iter().enumerate() {
             if show_numbers {
@@ -102,8 +104,13 @@ impl CommandHandler for HistoryCommand {
             } else {
                 output.push_str(&format!("{}\n", entry.command));
             }
+            let time = entry.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string();
+            records.push_row(vec![(i + 1).into(), entry.command.clone().into(), time.into(), entry.exit_code.into()]);
         }
         
+        if !shell.output_is_terminal() {
+            shell.emit_records(records);
+        }
         Ok(CommandResult {
             output,
             exit_code: 0,
@@ -149,12 +156,18 @@ Sare Shell - Built-in Commands
 Filesystem Commands:
   cd [directory]     - Change directory
   pwd                - Print working directory
+  pushd [directory]  - Push directory onto stack
+  popd               - Pop directory off stack
+  dirs [options]     - Show directory stack
+  z [keywords...]    - Jump to frequent directory
   ls [options]       - List directory contents
   mkdir [options]    - Create directory
   rm [options]       - Remove files/directories
   cp [options]       - Copy files/directories
   mv [options]       - Move/rename files
   touch [options]    - Create files or update timestamps
+  trash [subcommand] - Move to, list or restore from the trash
+  find [path] [expr] - Search for files in a directory tree
 
 Process Commands:
   jobs               - List background jobs
@@ -162,6 +175,15 @@ Process Commands:
   bg [job_id]        - Resume job in background
   fg [job_id]        - Resume job in foreground
   wait [job_id]      - Wait for job completion
+  timeout [duration] - Run command with time limit
+  ulimit [options]   - Limit command resources
+  sandbox [opts] cmd - Run command in namespaces (--net=off, --ro=PATH)
+  xargs [command]    - Build command lines from piped input
+  ps [options]       - List processes (ps -ef, ps aux, ps --forest)
+  pgrep [pattern]    - List process IDs by name
+  pkill [pattern]    - Signal processes by name
+  top [options]      - Interactive process viewer
+  time [pipeline]    - Time a pipeline (keyword)
 
 Text Processing:
   echo [args...]     - Print arguments
@@ -172,6 +194,18 @@ Text Processing:
   sort [options]     - Sort lines
   uniq [options]     - Remove duplicates
   wc [options]       - Word count
+  cut [options]      - Select parts of lines
+  tr [set1] [set2]   - Translate or delete characters
+  head [options]     - Print first lines
+  tail [options]     - Print last lines, or follow with -f
+  tee [files...]     - Copy input to files
+
+Structured Data:
+  where [condition]  - Filter records (ls | where size -gt 1000)
+  select [columns]   - Keep columns of records
+  sort-by [columns]  - Sort records by columns
+  to [json|csv]      - Convert records to text
+  from json          - Parse JSON into records
 
 System Commands:
   exit [code]        - Exit shell
@@ -190,12 +224,15 @@ Network Commands:
   curl [url]         - Transfer data
   wget [url]         - Retrieve files
   netstat [options]  - Network statistics
+  ss [options]       - Socket statistics
 
 Development Commands:
   git [command]      - Git operations
   cargo [command]    - Rust package manager
-  make [target]      - Build system
-  npm [command]      - Node package manager
+  make [target]      - Run make (--list shows targets)
+  npm run [script]   - Run package.json scripts (-w for workspaces)
+  debug [command]    - Debug with gdb or lldb-dap
+  shortcuts [task]   - Run project tasks (.sare/tasks.toml)
 
 External commands are also supported.
 Use 'help <command>' for detailed help.
@@ -390,13 +427,18 @@ impl CommandHandler for UnsetCommand {
 pub struct EnvCommand;
 
 impl CommandHandler for EnvCommand {
-    fn execute(&self, _command: &ParsedCommand, _shell: &mut Shell) -> Result<CommandResult> {
+    fn execute(&self, _command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
         let mut output = String::new();
+        let mut records = Table::new(&["name", "value"]);
         
         for (key, value) in std::env::vars() {
             output.push_str(&format!("{}={}\n", key, value));
+            records.push_row(vec![key.into(), value.into()]);
         }
         
+        if !shell.output_is_terminal() {
+            shell.emit_records(records);
+        }
         Ok(CommandResult {
             output,
             exit_code: 0,
@@ -0,0 +1,240 @@
+/*!
+ * @file tail.rs
+ * @brief File following for tail -f
+ *
+ * This module watches files with inotify and passes data appended to
+ * them to a sink until the user presses Ctrl+C or a watched process
+ * exits. Without inotify it falls back to polling.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file tail.rs
+ * @description inotify-based following with truncation detection,
+ * --pid termination, SIGINT handling and a polling fallback.
+ */
+
+use anyhow::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Set by the SIGINT handler installed while following
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/**
+ * File being followed
+ */
+#[derive(Debug, Clone)]
+pub struct FollowedFile {
+    /// Name printed in headers and messages
+    pub name: String,
+    /// Path to read
+    pub path: PathBuf,
+    /// Bytes already printed
+    pub offset: u64,
+}
+
+/**
+ * Options of a follow loop
+ */
+#[derive(Debug, Clone)]
+pub struct FollowOptions {
+    /// Stop when this process exits (--pid)
+    pub pid: Option<i32>,
+    /// Longest wait between checks (-s)
+    pub sleep_interval: Duration,
+    /// Print `==> name <==` when output switches files
+    pub headers: bool,
+    /// Index of the file printed last, so headers are only shown on a switch
+    pub last_printed: Option<usize>,
+}
+
+/**
+ * SIGINT handler that only records the interrupt
+ *
+ * @param _signal - Signal number
+ */
+extern "C" fn record_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/**
+ * Installs the interrupt handler and restores the previous one on drop
+ */
+pub struct InterruptGuard {
+    /// Handler that was installed before
+    previous: libc::sigaction,
+}
+
+impl InterruptGuard {
+    /**
+     * Installs the handler
+     *
+     * @return InterruptGuard - Guard restoring the previous handler
+     */
+    pub fn install() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = record_interrupt as extern "C" fn(libc::c_int) as usize;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(libc::SIGINT, &action, &mut previous);
+            InterruptGuard { previous }
+        }
+    }
+
+    /**
+     * Whether Ctrl+C was pressed since the handler was installed
+     *
+     * @return bool - True after SIGINT
+     */
+    pub fn interrupted(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.previous, std::ptr::null_mut());
+        }
+    }
+}
+
+/**
+ * inotify instance watching the followed files
+ */
+struct Watcher {
+    /// inotify file descriptor
+    fd: libc::c_int,
+}
+
+impl Watcher {
+    /**
+     * Creates an inotify instance watching the given files
+     *
+     * @param files - Files to watch
+     * @return Option<Watcher> - Watcher, or None if inotify is unavailable
+     */
+    fn new(files: &[FollowedFile]) -> Option<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return None;
+        }
+        let watcher = Watcher { fd };
+        let mask = libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_CLOSE_WRITE | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+        for file in files {
+            let Ok(path) = std::ffi::CString::new(file.path.as_os_str().as_encoded_bytes()) else {
+                continue;
+            };
+            unsafe {
+                libc::inotify_add_watch(fd, path.as_ptr(), mask);
+            }
+        }
+        Some(watcher)
+    }
+
+    /**
+     * Waits for an event or the timeout and drains pending events
+     *
+     * @param timeout - Longest wait
+     */
+    fn wait(&self, timeout: Duration) {
+        let mut poll_fd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis().min(i32::MAX as u128) as libc::c_int) };
+        if ready > 0 {
+            let mut buffer = [0u8; 4096];
+            while unsafe { libc::read(self.fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) } > 0 {}
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/**
+ * Checks whether a process is still running
+ *
+ * @param pid - Process ID
+ * @return bool - True if the process exists
+ */
+fn process_alive(pid: i32) -> bool {
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/**
+ * ファイルを監視し、追加されたデータを出力する関数です
+ *
+ * inotifyでファイルの変更を待ち、変更があるか待ち時間（-s）が
+ * 過ぎるたびに全てのファイルの大きさを確認して、前回の位置から
+ * 後ろのデータをsinkに渡します。inotifyが使えない場合は
+ * 待ち時間ごとに確認するだけになります。
+ *
+ * ファイルが前回の位置より小さくなった場合は切り詰められたと
+ * みなし、メッセージを出して先頭から読み直します。
+ *
+ * Ctrl+C（SIGINT）が押されるか、--pidで指定したプロセスが
+ * 終了すると戻ります。監視中はシェルのSIGINTハンドラを一時的に
+ * 置き換え、終了時に元に戻します。
+ *
+ * @param files - 監視するファイルと出力済みの位置
+ * @param options - 監視の設定
+ * @param sink - 出力を受け取る関数
+ * @return Result<()> - 成功またはエラー
+ */
+pub fn follow(files: &mut [FollowedFile], options: &mut FollowOptions, sink: &mut dyn FnMut(&str)) -> Result<()> {
+    let _guard = InterruptGuard::install();
+    let watcher = Watcher::new(files);
+
+    loop {
+        for (index, file) in files.iter_mut().enumerate() {
+            let Ok(metadata) = std::fs::metadata(&file.path) else {
+                continue;
+            };
+            let size = metadata.len();
+            if size < file.offset {
+                sink(&format!("tail: {}: file truncated\n", file.name));
+                file.offset = 0;
+            }
+            if size == file.offset {
+                continue;
+            }
+
+            let mut handle = File::open(&file.path)?;
+            handle.seek(SeekFrom::Start(file.offset))?;
+            let mut data = Vec::new();
+            handle.take(size - file.offset).read_to_end(&mut data)?;
+            file.offset += data.len() as u64;
+
+            if options.headers && options.last_printed != Some(index) {
+                sink(&format!("\n==> {} <==\n", file.name));
+            }
+            options.last_printed = Some(index);
+            sink(&String::from_utf8_lossy(&data));
+        }
+
+        if INTERRUPTED.load(Ordering::SeqCst) || options.pid.is_some_and(|pid| !process_alive(pid)) {
+            return Ok(());
+        }
+        match &watcher {
+            Some(watcher) => watcher.wait(options.sleep_interval),
+            None => std::thread::sleep(options.sleep_interval),
+        }
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,724 @@
+/*!
+ * @file tasks.rs
+ * @brief Project tasks from .sare/tasks.toml and detected build tools
+ *
+ * This module finds the project a directory belongs to, reads the
+ * named tasks of its `.sare/tasks.toml` and fills in default tasks for
+ * the build tools it detects (Cargo, npm, make, just, Python). Tasks
+ * run through the shell's pipeline executor in forked children, so
+ * their environment and working directory never leak into the shell;
+ * tasks whose dependencies are satisfied run side by side.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file tasks.rs
+ * @description Task file parsing, project detection, dependency
+ * planning and the parallel task scheduler with its summary table.
+ */
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::shell::commands::cargo::Printer;
+use crate::shell::commands::makefile::Makefile;
+use crate::shell::commands::records::Table;
+use crate::shell::commands::tail::InterruptGuard;
+use crate::shell::commands::CommandResult;
+use crate::shell::subshell::{self, ForkedChild};
+use crate::shell::Shell;
+
+/// Task file location relative to the project root
+pub const TASK_FILE: &str = ".sare/tasks.toml";
+
+/// Build tools recognised by their marker files, in priority order
+const PROJECT_KINDS: [ProjectKind; 5] = [ProjectKind::Cargo, ProjectKind::Node, ProjectKind::Make, ProjectKind::Just, ProjectKind::Python];
+
+/// Default Cargo tasks as name, command and description
+const CARGO_TASKS: [(&str, &str, &str); 8] = [
+    ("build", "cargo build", "Compile the package"),
+    ("check", "cargo check", "Type-check without producing binaries"),
+    ("test", "cargo test", "Run the tests"),
+    ("run", "cargo run", "Build and run the binary"),
+    ("lint", "cargo clippy --all-targets", "Run clippy"),
+    ("fmt", "cargo fmt", "Format the sources"),
+    ("doc", "cargo doc", "Build the documentation"),
+    ("clean", "cargo clean", "Remove the target directory"),
+];
+
+/**
+ * Contents of a .sare/tasks.toml file
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaskFile {
+    /// Environment shared by every task
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    /// Tasks by name
+    #[serde(default)]
+    tasks: BTreeMap<String, TaskSpec>,
+}
+
+/**
+ * Task table of a .sare/tasks.toml file
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaskSpec {
+    /// Command line, or list of command lines run in order
+    #[serde(default)]
+    run: Steps,
+    /// Tasks that must succeed first
+    #[serde(default)]
+    deps: Vec<String>,
+    /// Environment variables set for this task
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    /// Working directory relative to the project root
+    dir: Option<String>,
+    /// Text shown in the task list
+    description: Option<String>,
+}
+
+/**
+ * The `run` value of a task
+ */
+#[derive(Debug, Deserialize)]
+#[serde(untagged, expecting = "a command line or a list of command lines")]
+enum Steps {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Default for Steps {
+    fn default() -> Self {
+        Steps::Many(Vec::new())
+    }
+}
+
+/**
+ * Build tool detected in a project root
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectKind {
+    Cargo,
+    Node,
+    Make,
+    Just,
+    Python,
+}
+
+impl ProjectKind {
+    /**
+     * Gets the name shown as a task's source
+     *
+     * @return &str - Tool name
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectKind::Cargo => "cargo",
+            ProjectKind::Node => "npm",
+            ProjectKind::Make => "make",
+            ProjectKind::Just => "just",
+            ProjectKind::Python => "python",
+        }
+    }
+
+    /**
+     * Checks whether a directory contains this tool's marker file
+     *
+     * @param directory - Directory to check
+     * @return bool - True if the marker is present
+     */
+    fn is_present(&self, directory: &Path) -> bool {
+        match self {
+            ProjectKind::Cargo => directory.join("Cargo.toml").is_file(),
+            ProjectKind::Node => directory.join("package.json").is_file(),
+            ProjectKind::Make => Makefile::find(directory).is_some(),
+            ProjectKind::Just => justfile(directory).is_some(),
+            ProjectKind::Python => directory.join("pyproject.toml").is_file(),
+        }
+    }
+}
+
+/**
+ * Detects the build tools used in a directory
+ *
+ * @param directory - Project root
+ * @return Vec<ProjectKind> - Detected tools in priority order
+ */
+pub fn detect(directory: &Path) -> Vec<ProjectKind> {
+    PROJECT_KINDS.iter().copied().filter(|kind| kind.is_present(directory)).collect()
+}
+
+/**
+ * Finds the justfile of a directory
+ *
+ * @param directory - Directory to search
+ * @return Option<PathBuf> - Path of the justfile
+ */
+fn justfile(directory: &Path) -> Option<PathBuf> {
+    ["justfile", "Justfile", ".justfile"].iter().map(|name| directory.join(name)).find(|path| path.is_file())
+}
+
+/**
+ * Named task of a project
+ */
+#[derive(Debug, Clone)]
+pub struct Task {
+    /// Task name
+    pub name: String,
+    /// Command lines run in order
+    pub steps: Vec<String>,
+    /// Tasks that must succeed first
+    pub deps: Vec<String>,
+    /// Environment variables set while the task runs
+    pub env: Vec<(String, String)>,
+    /// Working directory
+    pub directory: PathBuf,
+    /// Text shown in the task list
+    pub description: Option<String>,
+    /// "tasks.toml" or the name of the build tool the task was derived from
+    pub source: String,
+}
+
+impl Task {
+    /**
+     * Creates a single-command task derived from a build tool
+     *
+     * @param name - Task name
+     * @param command - Command line
+     * @param description - Text shown in the task list
+     * @param kind - Build tool the task comes from
+     * @param root - Project root, used as the working directory
+     * @return Task - Task
+     */
+    fn detected(name: &str, command: String, description: Option<String>, kind: ProjectKind, root: &Path) -> Self {
+        Task {
+            name: name.to_string(),
+            steps: vec![command],
+            deps: Vec::new(),
+            env: Vec::new(),
+            directory: root.to_path_buf(),
+            description,
+            source: kind.name().to_string(),
+        }
+    }
+}
+
+/**
+ * Project a directory belongs to, with its tasks
+ */
+#[derive(Debug, Clone)]
+pub struct Project {
+    /// Project root
+    pub root: PathBuf,
+    /// Build tools detected in the root
+    pub kinds: Vec<ProjectKind>,
+    /// Whether the root has a task file
+    pub has_task_file: bool,
+    /// Tasks from the task file followed by detected tasks
+    pub tasks: Vec<Task>,
+}
+
+impl Project {
+    /**
+     * Finds the root of the project containing a directory
+     *
+     * The nearest ancestor with a task file wins, then the nearest
+     * with a build tool marker; otherwise the directory itself.
+     *
+     * @param directory - Directory inside the project
+     * @return PathBuf - Project root
+     */
+    pub fn find_root(directory: &Path) -> PathBuf {
+        directory
+            .ancestors()
+            .find(|ancestor| ancestor.join(TASK_FILE).is_file())
+            .or_else(|| directory.ancestors().find(|ancestor| !detect(ancestor).is_empty()))
+            .unwrap_or(directory)
+            .to_path_buf()
+    }
+
+    /**
+     * タスクファイルと検出したビルドツールからプロジェクトを読み込む
+     *
+     * .sare/tasks.toml のタスクを先に並べ、その後にビルドツールから
+     * 導出したデフォルトタスクを追加します。同じ名前のタスクが既に
+     * あれば、優先度の低い方は追加しません（タスクファイルが最優先、
+     * ビルドツールは PROJECT_KINDS の順）。
+     *
+     * タスクファイルの env は全タスクに適用され、タスク自身の env が
+     * 同名の変数を上書きします。dir はプロジェクトルートからの相対
+     * パスとして解決します。
+     *
+     * @param directory - プロジェクト内のディレクトリ
+     * @return Result<Project> - プロジェクト、またはタスクファイルの解析エラー
+     */
+    pub fn load(directory: &Path) -> Result<Project> {
+        let root = Project::find_root(directory);
+        let kinds = detect(&root);
+        let mut tasks = Vec::new();
+
+        let task_file = root.join(TASK_FILE);
+        let has_task_file = task_file.is_file();
+        if has_task_file {
+            let text = std::fs::read_to_string(&task_file)?;
+            let file: TaskFile = toml::from_str(&text)
+                .map_err(|e| anyhow::anyhow!("{}: {}", task_file.display(), e))?;
+            for (name, spec) in file.tasks {
+                let mut env: BTreeMap<String, String> = file.env.clone();
+                env.extend(spec.env);
+                tasks.push(Task {
+                    name,
+                    steps: match spec.run {
+                        Steps::One(step) => vec![step],
+                        Steps::Many(steps) => steps,
+                    },
+                    deps: spec.deps,
+                    env: env.into_iter().collect(),
+                    directory: spec.dir.map_or(root.clone(), |dir| root.join(dir)),
+                    description: spec.description,
+                    source: "tasks.toml".to_string(),
+                });
+            }
+        }
+
+        for kind in &kinds {
+            for task in detected_tasks(*kind, &root) {
+                if !tasks.iter().any(|existing: &Task| existing.name == task.name) {
+                    tasks.push(task);
+                }
+            }
+        }
+
+        Ok(Project {
+            root,
+            kinds,
+            has_task_file,
+            tasks,
+        })
+    }
+
+    /**
+     * Looks up a task by name
+     *
+     * @param name - Task name
+     * @return Option<&Task> - Task
+     */
+    pub fn task(&self, name: &str) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.name == name)
+    }
+
+    /**
+     * Orders the requested tasks after everything they depend on
+     *
+     * @param names - Requested task names
+     * @return Result<Vec<&Task>> - Tasks in dependency order, or an unknown task or cycle error
+     */
+    pub fn plan(&self, names: &[String]) -> Result<Vec<&Task>> {
+        let mut order = Vec::new();
+        let mut done = HashSet::new();
+        let mut path = Vec::new();
+        for name in names {
+            self.visit(name, &mut path, &mut done, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /**
+     * Depth-first step of plan
+     *
+     * @param name - Task to add
+     * @param path - Tasks currently being visited, for cycle detection
+     * @param done - Tasks already in the order
+     * @param order - Tasks in dependency order
+     * @return Result<()> - Unknown task or cycle error
+     */
+    fn visit<'a>(&'a self, name: &str, path: &mut Vec<String>, done: &mut HashSet<String>, order: &mut Vec<&'a Task>) -> Result<()> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|visiting| visiting == name) {
+            let cycle: Vec<&str> = path[start..].iter().map(String::as_str).chain(std::iter::once(name)).collect();
+            return Err(anyhow::anyhow!("dependency cycle: {}", cycle.join(" -> ")));
+        }
+
+        let task = self.task(name).ok_or_else(|| match path.last() {
+            Some(parent) => anyhow::anyhow!("task '{}' depends on unknown task '{}'", parent, name),
+            None => anyhow::anyhow!("unknown task '{}'", name),
+        })?;
+        path.push(name.to_string());
+        for dep in &task.deps {
+            self.visit(dep, path, done, order)?;
+        }
+        path.pop();
+        done.insert(name.to_string());
+        order.push(task);
+        Ok(())
+    }
+
+    /**
+     * Table of the project's tasks for records
+     *
+     * @return Table - name, source, run, deps, dir and description columns
+     */
+    pub fn task_table(&self) -> Table {
+        let mut table = Table::new(&["name", "source", "run", "deps", "dir", "description"]);
+        for task in &self.tasks {
+            table.push_row(vec![
+                json!(task.name),
+                json!(task.source),
+                json!(task.steps),
+                json!(task.deps),
+                json!(task.directory.display().to_string()),
+                task.description.as_ref().map_or(Value::Null, |description| json!(description)),
+            ]);
+        }
+        table
+    }
+}
+
+/**
+ * ビルドツールからデフォルトタスクを導出する
+ *
+ * - Cargo: build、check、test、run、lint（clippy）、fmt、doc、clean
+ * - npm: package.json の scripts をそれぞれ「npm run <名前>」として追加。
+ *   ロックファイルから pnpm、yarn、bun を判別します
+ * - make: Makefile の一覧表示可能なターゲット（## の説明付き）
+ * - just: justfile の公開レシピ（直前のコメントを説明に使用）
+ * - Python: test（pytest）、build、ruff の設定があれば lint。
+ *   Poetry や uv のプロジェクトではそれぞれのコマンドを経由します
+ *
+ * 読み込めない設定ファイルは無視し、そのツールのタスクは追加しません。
+ *
+ * @param kind - ビルドツール
+ * @param root - プロジェクトルート
+ * @return Vec<Task> - 導出したタスク
+ */
+fn detected_tasks(kind: ProjectKind, root: &Path) -> Vec<Task> {
+    let task = |name: &str, command: String, description: Option<String>| Task::detected(name, command, description, kind, root);
+
+    match kind {
+        ProjectKind::Cargo => CARGO_TASKS
+            .iter()
+            .map(|(name, command, description)| task(name, command.to_string(), Some(description.to_string())))
+            .collect(),
+        ProjectKind::Node => {
+            let manager = [("pnpm-lock.yaml", "pnpm"), ("yarn.lock", "yarn"), ("bun.lockb", "bun"), ("bun.lock", "bun")]
+                .iter()
+                .find(|(lockfile, _)| root.join(lockfile).is_file())
+                .map_or("npm", |(_, manager)| *manager);
+            let package: Value = std::fs::read_to_string(root.join("package.json"))
+                .ok()
+                .and_then(|text| serde_json::from_str(&text).ok())
+                .unwrap_or(Value::Null);
+            let mut tasks: Vec<Task> = package["scripts"]
+                .as_object()
+                .map(|scripts| {
+                    scripts
+                        .iter()
+                        .map(|(name, script)| task(name, format!("{} run {}", manager, name), script.as_str().map(str::to_string)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if !tasks.iter().any(|task| task.name == "install") {
+                tasks.push(task("install", format!("{} install", manager), Some("Install dependencies".to_string())));
+            }
+            tasks
+        }
+        ProjectKind::Make => Makefile::discover(root, None, &[])
+            .map(|makefile| {
+                makefile
+                    .listed_targets()
+                    .map(|target| task(&target.name, format!("make {}", target.name), target.description.clone()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        ProjectKind::Just => justfile(root)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| {
+                just_recipes(&text)
+                    .into_iter()
+                    .map(|(name, description)| task(&name, format!("just {}", name), description))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        ProjectKind::Python => {
+            let pyproject: toml::Value = match std::fs::read_to_string(root.join("pyproject.toml")).ok().and_then(|text| toml::from_str(&text).ok()) {
+                Some(pyproject) => pyproject,
+                None => return Vec::new(),
+            };
+            let tool = pyproject.get("tool");
+            let has_tool = |name: &str| tool.and_then(|tool| tool.get(name)).is_some();
+            let (runner, build) = if has_tool("poetry") {
+                ("poetry run ", "poetry build")
+            } else if root.join("uv.lock").is_file() {
+                ("uv run ", "uv build")
+            } else {
+                ("", "python -m build")
+            };
+
+            let mut tasks = vec![
+                task("test", format!("{}pytest", runner), Some("Run the tests with pytest".to_string())),
+                task("build", build.to_string(), Some("Build the distribution packages".to_string())),
+            ];
+            if has_tool("ruff") {
+                tasks.push(task("lint", format!("{}ruff check .", runner), Some("Run ruff".to_string())));
+            }
+            tasks
+        }
+    }
+}
+
+/**
+ * Lists the public recipes of a justfile
+ *
+ * Recipes starting with an underscore or marked [private] are left
+ * out; a comment line directly above a recipe becomes its description.
+ *
+ * @param text - justfile contents
+ * @return Vec<(String, Option<String>)> - Recipe names and descriptions
+ */
+fn just_recipes(text: &str) -> Vec<(String, Option<String>)> {
+    let recipe = Regex::new(r"^@?([A-Za-z_][A-Za-z0-9_-]*)[^:]*:([^=]|$)").unwrap();
+    let mut recipes = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut private = false;
+
+    for line in text.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim().to_string());
+            continue;
+        }
+        if line.trim() == "[private]" {
+            private = true;
+            continue;
+        }
+        let keyword = line.split_whitespace().next().unwrap_or("");
+        let is_statement = ["set", "alias", "export", "import", "mod"].contains(&keyword);
+        if let Some(captures) = recipe.captures(line).filter(|_| !is_statement && !line.contains(":=")) {
+            let name = captures[1].to_string();
+            if !name.starts_with('_') && !private {
+                recipes.push((name, comment.take().filter(|text| !text.is_empty())));
+            }
+        }
+        if !line.trim_start().starts_with('[') {
+            comment = None;
+            private = false;
+        }
+    }
+    recipes
+}
+
+/**
+ * Outcome of a task in a run
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl TaskStatus {
+    /**
+     * Gets the status name used in the summary
+     *
+     * @return &str - "ok", "failed" or "skipped"
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskStatus::Passed => "ok",
+            TaskStatus::Failed => "failed",
+            TaskStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/**
+ * Result of one task in a run
+ */
+#[derive(Debug, Clone)]
+pub struct TaskRun {
+    /// Task name
+    pub name: String,
+    /// Outcome
+    pub status: TaskStatus,
+    /// Exit code of the failing step, 0 on success
+    pub exit_code: Option<i32>,
+    /// Wall-clock time, for tasks that ran
+    pub duration: Option<Duration>,
+    /// Dependency whose failure skipped the task
+    pub blocked_by: Option<String>,
+}
+
+/**
+ * タスクを依存関係に従って並列に実行する
+ *
+ * plan は依存関係順に並んだタスクです。依存タスクが全て成功した
+ * タスクを最大 jobs 個まで同時に起動し、どれかが終了するたびに
+ * 新しく実行可能になったタスクを起動します。失敗したタスクに
+ * （間接的にでも）依存するタスクはスキップします。Ctrl+C の後は
+ * 新しいタスクを起動せず、実行中のタスクの終了だけを待ちます。
+ *
+ * 各タスクはフォークした子シェルで実行するため、env や dir の
+ * 変更はシェル本体に残りません。端末出力で同時に実行するタスクが
+ * 一つだけの場合は出力をそのまま流し、複数の場合は出力が混ざらない
+ * ようにまとめて受け取り、タスクの終了時に見出し付きで表示します。
+ *
+ * @param plan - 依存関係順のタスク
+ * @param jobs - 同時に実行するタスクの最大数
+ * @param shell - シェル（子プロセスにフォークされる）
+ * @param printer - 出力先
+ * @return Result<Vec<TaskRun>> - plan の順の実行結果
+ */
+pub fn run(plan: &[&Task], jobs: usize, shell: &mut Shell, printer: &mut Printer) -> Result<Vec<TaskRun>> {
+    let guard = InterruptGuard::install();
+    let mut runs: HashMap<String, TaskRun> = HashMap::new();
+    let mut started: HashMap<String, Instant> = HashMap::new();
+    let mut pending: Vec<&Task> = plan.to_vec();
+    let mut running: Vec<ForkedChild> = Vec::new();
+    let mut streamed: HashSet<String> = HashSet::new();
+
+    loop {
+        let mut waiting = Vec::new();
+        for task in pending {
+            let blocked_by = task
+                .deps
+                .iter()
+                .find(|dep| runs.get(dep.as_str()).is_some_and(|run| run.status != TaskStatus::Passed))
+                .cloned();
+            if blocked_by.is_some() || guard.interrupted() {
+                runs.insert(task.name.clone(), TaskRun {
+                    name: task.name.clone(),
+                    status: TaskStatus::Skipped,
+                    exit_code: None,
+                    duration: None,
+                    blocked_by,
+                });
+            } else {
+                waiting.push(task);
+            }
+        }
+        pending = waiting;
+
+        let ready: Vec<&Task> = pending
+            .iter()
+            .copied()
+            .filter(|task| task.deps.iter().all(|dep| runs.contains_key(dep)))
+            .take(jobs.max(1).saturating_sub(running.len()))
+            .collect();
+        let exclusive = printer.terminal && running.is_empty() && ready.len() == 1;
+        for task in ready {
+            pending.retain(|other| other.name != task.name);
+            started.insert(task.name.clone(), Instant::now());
+            if exclusive {
+                streamed.insert(task.name.clone());
+                printer.print(&format!("{} {}\n", printer.paint("1;34", "▶"), printer.paint("1", &task.name)));
+            }
+            let color = printer.terminal;
+            running.push(subshell::spawn_forked(&task.name, || run_steps(task, shell, exclusive, color))?);
+        }
+
+        if running.is_empty() {
+            break;
+        }
+
+        let (name, result) = subshell::wait_any(&mut running)?;
+        let duration = started.get(&name).map(Instant::elapsed);
+        if !streamed.contains(&name) {
+            printer.print(&format!("{} {}\n", printer.paint("1;34", "▶"), printer.paint("1", &name)));
+            printer.print(&result.output);
+            if !result.output.is_empty() && !result.output.ends_with('\n') {
+                printer.print("\n");
+            }
+        }
+        if result.exit_code != 0 {
+            printer.print(&format!("{} {} failed with exit code {}\n", printer.paint("1;31", "✗"), name, result.exit_code));
+        }
+        runs.insert(name.clone(), TaskRun {
+            name,
+            status: if result.exit_code == 0 { TaskStatus::Passed } else { TaskStatus::Failed },
+            exit_code: Some(result.exit_code),
+            duration,
+            blocked_by: None,
+        });
+    }
+
+    Ok(plan.iter().filter_map(|task| runs.remove(&task.name)).collect())
+}
+
+/**
+ * Runs the steps of a task inside its forked child
+ *
+ * @param task - Task to run
+ * @param shell - The child's copy of the shell
+ * @param stream - Whether output goes straight to the terminal
+ * @param color - Whether the collected output is shown on a terminal
+ * @return CommandResult - Collected output and the exit code of the failing step
+ */
+fn run_steps(task: &Task, shell: &mut Shell, stream: bool, color: bool) -> CommandResult {
+    for (name, value) in &task.env {
+        shell.set_environment_variable(name.clone(), value.clone());
+    }
+    if let Err(error) = std::env::set_current_dir(&task.directory) {
+        return CommandResult {
+            output: format!("shortcuts: {}: {}\n", task.directory.display(), error),
+            exit_code: 1,
+        };
+    }
+    *shell.current_path_mut() = task.directory.clone();
+
+    let mut printer = Printer::new(stream);
+    let prompt = if color { "\x1b[2m$\x1b[0m" } else { "$" };
+    for step in &task.steps {
+        printer.print(&format!("{} {}\n", prompt, step));
+        let result = shell.run_command_line(step, stream).unwrap_or_else(|error| CommandResult {
+            output: format!("shortcuts: {}\n", error),
+            exit_code: 1,
+        });
+        printer.print(&result.output);
+        if !result.output.is_empty() && !result.output.ends_with('\n') {
+            printer.print("\n");
+        }
+        if result.exit_code != 0 {
+            return CommandResult {
+                output: printer.output,
+                exit_code: result.exit_code,
+            };
+        }
+    }
+    CommandResult {
+        output: printer.output,
+        exit_code: 0,
+    }
+}
+
+/**
+ * Table summarising a run for display and records
+ *
+ * @param runs - Task results
+ * @return Table - task, status, exit_code, duration (seconds) and blocked_by columns
+ */
+pub fn summary_table(runs: &[TaskRun]) -> Table {
+    let mut table = Table::new(&["task", "status", "exit_code", "duration", "blocked_by"]);
+    for run in runs {
+        table.push_row(vec![
+            json!(run.name),
+            json!(run.status.name()),
+            run.exit_code.map_or(Value::Null, |code| json!(code)),
+            run.duration.map_or(Value::Null, |duration| json!((duration.as_secs_f64() * 100.0).round() / 100.0)),
+            run.blocked_by.as_ref().map_or(Value::Null, |dep| json!(dep)),
+        ]);
+    }
+    table
+}
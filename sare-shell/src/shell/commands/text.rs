@@ -13,10 +13,21 @@
  */
 
 use anyhow::Result;
+use std::io::Write;
 use std::path::PathBuf;
 use crate::shell::parser::ParsedCommand;
 use crate::shell::Shell;
 use crate::shell::commands::{CommandHandler, CommandResult};
+use crate::config::ShellConfig;
+use crate::shell::commands::awk::{AwkOptions, AwkProgram};
+use crate::shell::commands::fileops::describe_error;
+use crate::shell::commands::grep::{
+    self, BinaryFiles, FileFilter, GrepOptions, Highlight, Matcher, PatternSyntax, SearchReport, Searcher, STDIN_NAME,
+};
+use crate::shell::commands::sed::{SedOptions, SedScript};
+use crate::shell::commands::sort::{self, OutputFile, SortInput, SortKey, SortOptions};
+use crate::shell::commands::tail::{self, FollowOptions, FollowedFile};
+use crate::shell::commands::tr::{TrOptions, Transliterator};
 
 /**
  * Echo command
@@ -73,7 +84,8 @@ impl CommandHandler for EchoCommand {
  * Cat command
  * 
  * Implements the cat command for concatenating and displaying files.
- * Supports various options for file display.
+ * With no file operands, or for `-`, it reads the piped or redirected
+ * input.
  */
 pub struct CatCommand;
 
@@ -82,26 +94,19 @@ impl CommandHandler for CatCommand {
         let show_line_numbers = command.args.iter().any(|arg| arg == "-n" || arg == "--number");
         let show_nonprintable = command.args.iter().any(|arg| arg == "-A" || arg == "--show-all");
         
+        let mut operands: Vec<String> = command.args.iter()
+            .filter(|arg| !matches!(arg.as_str(), "-n" | "-A" | "--number" | "--show-all"))
+            .cloned()
+            .collect();
+        if operands.is_empty() {
+            operands.push("-".to_string());
+        }
+        
         let mut output = String::new();
         let mut line_number = 1;
         
-        for file_name in &command.args {
-            if file_name.starts_with('-') && (file_name == "-n" || file_name == "-A" || 
-                file_name == "--number" || file_name == "--show-all") {
-                continue;
-            }
-            
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
-            };
-            
-            if !file_path.exists() {
-                return Err(anyhow::anyhow!("File not found: {}", file_name));
-            }
-            
-            let content = std::fs::read_to_string(&file_path)?;
+        for (_, content) in read_filter_inputs("cat", &operands, shell) {
+            let content = String::from_utf8_lossy(&content.map_err(|message| anyhow::anyhow!("{}", message.trim_end()))?).into_owned();
             let lines: Vec<&str> = content.lines().collect();
             
             for line in lines {
@@ -126,7 +131,7 @@ impl CommandHandler for CatCommand {
     }
     
     fn help(&self) -> &str {
-        "cat [options] [files...] - Concatenate and display files\n\
+        "cat [options] [files...] - Concatenate and display files (- or no files reads piped input)\n\
          Options:\n\
          -n, --number      Number all output lines\n\
          -A, --show-all    Show nonprintable characters\n\
@@ -138,6 +143,208 @@ impl CommandHandler for CatCommand {
     }
 }
 
+/**
+ * grepのコマンドライン引数を解析するための構造体です
+ * 
+ * 短いオプション（-rnA3のような結合形式を含む）と長いオプション
+ * （--context=3と--context 3の両方）を同じ形に変換して適用します。
+ * GNU grepと同様に、オプションはオペランドの後にも置けます。
+ */
+#[derive(Default)]
+struct GrepArguments {
+    /// Output options
+    options: GrepOptions,
+    /// File collection options
+    filter: FileFilter,
+    /// Pattern language
+    syntax: Option<PatternSyntax>,
+    /// -i
+    ignore_case: bool,
+    /// -w
+    whole_word: bool,
+    /// -x
+    whole_line: bool,
+    /// Patterns from -e and -f
+    patterns: Vec<String>,
+    /// Pattern and file operands
+    operands: Vec<String>,
+    /// -H (Some(true)) or -h (Some(false))
+    file_names: Option<bool>,
+    /// -s
+    no_messages: bool,
+    /// --color value
+    color: Option<String>,
+    /// -C or -NUM
+    context: Option<usize>,
+    /// -A
+    after: Option<usize>,
+    /// -B
+    before: Option<usize>,
+}
+
+impl GrepArguments {
+    /**
+     * Parses grep's arguments
+     * 
+     * @param args - Arguments after the command name
+     * @param shell - Shell instance, for reading -f files
+     * @return Result<GrepArguments> - Parsed arguments
+     */
+    fn parse(args: &[String], shell: &mut Shell) -> Result<Self> {
+        let mut parsed = GrepArguments::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                parsed.operands.push(arg.clone());
+                continue;
+            }
+            if arg == "--" {
+                parsed.operands.extend(args.by_ref().cloned());
+                break;
+            }
+            
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let flag = match name {
+                    "extended-regexp" => 'E',
+                    "fixed-strings" => 'F',
+                    "basic-regexp" => 'G',
+                    "perl-regexp" => 'P',
+                    "ignore-case" => 'i',
+                    "invert-match" => 'v',
+                    "word-regexp" => 'w',
+                    "line-regexp" => 'x',
+                    "line-number" => 'n',
+                    "with-filename" => 'H',
+                    "no-filename" => 'h',
+                    "only-matching" => 'o',
+                    "count" => 'c',
+                    "files-with-matches" => 'l',
+                    "files-without-match" => 'L',
+                    "quiet" | "silent" => 'q',
+                    "no-messages" => 's',
+                    "recursive" => 'r',
+                    "dereference-recursive" => 'R',
+                    "text" => 'a',
+                    "after-context" => 'A',
+                    "before-context" => 'B',
+                    "context" => 'C',
+                    "max-count" => 'm',
+                    "regexp" => 'e',
+                    "file" => 'f',
+                    "no-ignore-case" => {
+                        parsed.ignore_case = false;
+                        continue;
+                    }
+                    "color" | "colour" => {
+                        parsed.color = Some(inline.unwrap_or_else(|| "auto".to_string()));
+                        continue;
+                    }
+                    "include" | "exclude" | "exclude-dir" => {
+                        let value = match inline {
+                            Some(value) => value,
+                            None => next_option_argument(&mut args, name)?,
+                        };
+                        let pattern = glob::Pattern::new(&value)
+                            .map_err(|error| anyhow::anyhow!("invalid --{} pattern '{}': {}", name, value, error))?;
+                        match name {
+                            "include" => parsed.filter.include.push(pattern),
+                            "exclude" => parsed.filter.exclude.push(pattern),
+                            _ => parsed.filter.exclude_dir.push(pattern),
+                        }
+                        continue;
+                    }
+                    _ => return Err(anyhow::anyhow!("unrecognized option '--{}'", name)),
+                };
+                let value = match inline {
+                    Some(value) => Some(value),
+                    None if "ABCmef".contains(flag) => Some(next_option_argument(&mut args, name)?),
+                    None => None,
+                };
+                parsed.apply(flag, value, shell)?;
+                continue;
+            }
+            
+            let flags = &arg[1..];
+            if flags.chars().all(|c| c.is_ascii_digit()) {
+                parsed.context = Some(parse_grep_count(flags, "context length")?);
+                continue;
+            }
+            for (i, flag) in flags.char_indices() {
+                let rest = &flags[i + flag.len_utf8()..];
+                if "ABCmef".contains(flag) {
+                    let value = if rest.is_empty() {
+                        next_option_argument(&mut args, &flag.to_string())?
+                    } else {
+                        rest.to_string()
+                    };
+                    parsed.apply(flag, Some(value), shell)?;
+                    break;
+                }
+                parsed.apply(flag, None, shell)?;
+            }
+        }
+        Ok(parsed)
+    }
+    
+    /**
+     * Applies one option
+     * 
+     * @param flag - Short option letter
+     * @param value - Option argument for -A -B -C -m -e -f
+     * @param shell - Shell instance, for reading -f files
+     * @return Result<()> - Error for unknown options or bad values
+     */
+    fn apply(&mut self, flag: char, value: Option<String>, shell: &mut Shell) -> Result<()> {
+        let value = value.unwrap_or_default();
+        match flag {
+            'E' => self.syntax = Some(PatternSyntax::Extended),
+            'F' => self.syntax = Some(PatternSyntax::Fixed),
+            'G' => self.syntax = Some(PatternSyntax::Basic),
+            'P' => self.syntax = Some(PatternSyntax::Perl),
+            'i' | 'y' => self.ignore_case = true,
+            'v' => self.options.invert = true,
+            'w' => self.whole_word = true,
+            'x' => self.whole_line = true,
+            'n' => self.options.line_numbers = true,
+            'H' => self.file_names = Some(true),
+            'h' => self.file_names = Some(false),
+            'o' => self.options.only_matching = true,
+            'c' => self.options.count = true,
+            'l' => self.options.files_with_matches = true,
+            'L' => self.options.files_without_match = true,
+            'q' => self.options.quiet = true,
+            's' => self.no_messages = true,
+            'r' => self.filter.recursive = true,
+            'R' => {
+                self.filter.recursive = true;
+                self.filter.follow_links = true;
+            }
+            'a' => self.options.binary_files = BinaryFiles::Text,
+            'I' => self.options.binary_files = BinaryFiles::Skip,
+            'A' => self.after = Some(parse_grep_count(&value, "context length")?),
+            'B' => self.before = Some(parse_grep_count(&value, "context length")?),
+            'C' => self.context = Some(parse_grep_count(&value, "context length")?),
+            'm' => self.options.max_count = Some(parse_grep_count(&value, "max count")?),
+            'e' => self.patterns.push(value),
+            'f' => {
+                let content = if value == "-" {
+                    shell.take_pipeline_input().unwrap_or_default()
+                } else {
+                    std::fs::read_to_string(shell.current_path().join(&value))
+                        .map_err(|error| anyhow::anyhow!("{}: {}", value, describe_error(&error)))?
+                };
+                self.patterns.extend(content.lines().map(str::to_string));
+            }
+            other => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+        }
+        Ok(())
+    }
+}
+
 /**
  * Grep command
  * 
@@ -146,89 +353,193 @@ impl CommandHandler for CatCommand {
  */
 pub struct GrepCommand;
 
-impl CommandHandler for GrepCommand {
-    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: grep <pattern> [files...]"));
+/**
+ * One input in command-line order
+ */
+enum GrepInput {
+    /// Pipeline input
+    Stdin,
+    /// Index into the collected files
+    File(usize),
+    /// Message for an operand that could not be collected
+    Error(String),
+}
+
+impl GrepCommand {
+    /**
+     * Parses the arguments and searches the inputs
+     *
+     * @param command - Parsed command
+     * @param shell - Shell instance
+     * @return Result<CommandResult> - Search result, or an error for bad options or patterns
+     */
+    fn search(command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut arguments = GrepArguments::parse(&command.args, shell)?;
+        if arguments.patterns.is_empty() {
+            if arguments.operands.is_empty() {
+                return Err(anyhow::anyhow!("Usage: grep [options] <pattern> [files...]"));
+            }
+            arguments.patterns.push(arguments.operands.remove(0));
         }
         
-        let case_insensitive = command.args.iter().any(|arg| arg == "-i" || arg == "--ignore-case");
-        let invert_match = command.args.iter().any(|arg| arg == "-v" || arg == "--invert-match");
-        let show_line_numbers = command.args.iter().any(|arg| arg == "-n" || arg == "--line-number");
-        
-        let pattern = &command.args[0];
-        let files = &command.args[1..];
+        let mut options = arguments.options;
+        options.before_context = arguments.before.or(arguments.context).unwrap_or(0);
+        options.after_context = arguments.after.or(arguments.context).unwrap_or(0);
+        let colors_on = match arguments.color.as_deref() {
+            None | Some("never" | "no" | "none") => false,
+            Some("always" | "yes" | "force") => true,
+            Some("auto" | "tty" | "if-tty") => shell.output_is_terminal(),
+            Some(other) => return Err(anyhow::anyhow!("invalid argument '{}' for '--color'", other)),
+        };
+        if colors_on {
+            options.colors = Some(Highlight::from_theme(&ShellConfig::load().theme));
+        }
         
-        let mut output = String::new();
-        let mut total_matches = 0;
+        let matcher = Matcher::new(
+            &arguments.patterns,
+            arguments.syntax.unwrap_or(PatternSyntax::Basic),
+            arguments.ignore_case,
+            arguments.whole_word,
+            arguments.whole_line,
+        )?;
         
-        if files.is_empty() {
-            return Err(anyhow::anyhow!("No files specified"));
+        let filter = arguments.filter;
+        let working_dir = shell.current_path().clone();
+        let mut inputs = Vec::new();
+        let mut targets = Vec::new();
+        if arguments.operands.is_empty() && filter.recursive {
+            let mut errors = Vec::new();
+            filter.collect("", &working_dir, true, &mut targets, &mut errors);
+            inputs.extend(errors.into_iter().map(GrepInput::Error));
+            inputs.extend((0..targets.len()).map(GrepInput::File));
+        } else if arguments.operands.is_empty() {
+            inputs.push(GrepInput::Stdin);
         }
-        
-        for file_name in files {
-            if file_name.starts_with('-') && (file_name == "-i" || file_name == "-v" || file_name == "-n" ||
-                file_name == "--ignore-case" || file_name == "--invert-match" || file_name == "--line-number") {
+        for operand in &arguments.operands {
+            if operand == "-" {
+                inputs.push(GrepInput::Stdin);
                 continue;
             }
-            
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
+            let first = targets.len();
+            let mut errors = Vec::new();
+            filter.collect(operand, &working_dir.join(operand), true, &mut targets, &mut errors);
+            inputs.extend(errors.into_iter().map(GrepInput::Error));
+            inputs.extend((first..targets.len()).map(GrepInput::File));
+        }
+        
+        let single_file = arguments.operands.len() == 1 && working_dir.join(&arguments.operands[0]).is_file();
+        options.with_file_names = arguments
+            .file_names
+            .unwrap_or(arguments.operands.len() > 1 || (filter.recursive && !single_file));
+        
+        let searcher = Searcher {
+            matcher: &matcher,
+            options: &options,
+        };
+        let mut reports: Vec<Option<Result<SearchReport, String>>> =
+            grep::search_files(&targets, &searcher).into_iter().map(Some).collect();
+        
+        let prints_lines = !(options.count || options.files_with_matches || options.files_without_match || options.quiet);
+        let mut output = String::new();
+        let mut printed_group = false;
+        let mut selected = false;
+        let mut had_error = false;
+        for input in inputs {
+            let report = match input {
+                GrepInput::Stdin => Ok(searcher.search(STDIN_NAME, shell.take_pipeline_input().unwrap_or_default().as_bytes())),
+                GrepInput::File(index) => reports[index].take().unwrap_or_else(|| Err(String::new())),
+                GrepInput::Error(message) => Err(message),
             };
-            
-            if !file_path.exists() {
-                output.push_str(&format!("grep: {}: No such file or directory\n", file_name));
-                continue;
-            }
-            
-            let content = std::fs::read_to_string(&file_path)?;
-            let lines: Vec<&str> = content.lines().collect();
-            
-            for (line_num, line) in lines.iter().enumerate() {
-                let matches_pattern = if case_insensitive {
-                    line.to_lowercase().contains(&pattern.to_lowercase())
-                } else {
-                    line.contains(pattern)
-                };
-                
-                let should_output = if invert_match {
-                    !matches_pattern
-                } else {
-                    matches_pattern
-                };
-                
-                if should_output {
-                    if show_line_numbers {
-                        output.push_str(&format!("{}:{}: {}\n", file_name, line_num + 1, line));
-                    } else {
-                        output.push_str(&format!("{}: {}\n", file_name, line));
+            match report {
+                Ok(report) => {
+                    selected |= report.selected > 0;
+                    if report.output.is_empty() {
+                        continue;
+                    }
+                    if prints_lines && options.uses_context() && printed_group {
+                        output.push_str("--\n");
+                    }
+                    printed_group = true;
+                    output.push_str(&report.output);
+                }
+                Err(message) => {
+                    had_error = true;
+                    if !arguments.no_messages {
+                        output.push_str(&message);
                     }
-                    total_matches += 1;
                 }
             }
         }
         
-        if total_matches == 0 {
-            return Ok(CommandResult {
-                output: "No matches found".to_string(),
-                exit_code: 1,
-            });
+        let exit_code = if options.quiet && selected {
+            0
+        } else if had_error {
+            2
+        } else if selected {
+            0
+        } else {
+            1
+        };
+        Ok(CommandResult { output, exit_code })
+    }
+}
+
+impl CommandHandler for GrepCommand {
+    /**
+     * grepコマンドを実行する関数です
+     *
+     * -eと-fがない場合は最初のオペランドをパターンとします。
+     * ファイルが指定されていない場合はパイプラインの入力を検索し、
+     * -rの場合はカレントディレクトリを再帰的に検索します
+     * （この場合の名前には「./」を付けません）。
+     *
+     * ファイルは並列に検索しますが、出力はコマンドラインと
+     * ディレクトリ走査の順序どおりに並べます。--colorの色は
+     * ThemeConfigから取得します。
+     *
+     * 終了コードはGNU grepと同じで、選択された行があれば0、
+     * なければ1、エラーがあれば2です（-qで一致した場合は0）。
+     * 不正なオプションやパターンも「一致なし」と区別できるよう
+     * 2で終了します。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        match GrepCommand::search(command, shell) {
+            Ok(result) => Ok(result),
+            Err(error) => Ok(CommandResult {
+                output: format!("grep: {}\n", error),
+                exit_code: 2,
+            }),
         }
-        
-        Ok(CommandResult {
-            output,
-            exit_code: 0,
-        })
     }
     
     fn help(&self) -> &str {
         "grep [options] <pattern> [files...] - Search for patterns\n\
-         Options:\n\
+         Reads piped input when no files are given.\n\
+         Patterns:\n\
+         -E, -F, -G            Extended, fixed-string or basic patterns\n\
+         -P                    Perl-style patterns, without look-around or backreferences\n\
+         -e <pattern>          Add a pattern; -f <file> reads patterns from a file\n\
          -i, --ignore-case     Ignore case distinctions\n\
+         -w, -x                Match whole words or whole lines only\n\
          -v, --invert-match    Select non-matching lines\n\
-         -n, --line-number     Prefix each line with line number"
+         Output:\n\
+         -n, --line-number     Prefix each line with line number\n\
+         -H, -h                Always or never print file names\n\
+         -o                    Print only the matched parts\n\
+         -c                    Print the number of selected lines\n\
+         -l, -L                List files with or without matches\n\
+         -A/-B/-C <n>          Print n lines of context after, before or around matches\n\
+         -m <n>                Stop after n selected lines\n\
+         -q, -s                Quiet; suppress error messages\n\
+         --color[=WHEN]        Highlight matches (never, always, auto)\n\
+         Files:\n\
+         -r, -R                Search directories recursively (-R follows symlinks)\n\
+         --include/--exclude/--exclude-dir <glob>  Filter files found by -r\n\
+         -a, -I                Search binary files as text, or skip them"
     }
     
     fn name(&self) -> &str {
@@ -240,61 +551,209 @@ impl CommandHandler for GrepCommand {
  * Sed command
  * 
  * Implements the sed command for stream editing.
- * Supports basic text substitution and editing.
+ * Supports addresses, ranges, hold space, branching and in-place editing.
  */
 pub struct SedCommand;
 
 impl CommandHandler for SedCommand {
+    /**
+     * sedコマンドを実行する関数です
+     *
+     * オプションを解析し、-eと-fで指定されたスクリプトを結合して
+     * コンパイルします。-eも-fもない場合は最初の引数を
+     * スクリプトとして使用します。
+     *
+     * ファイルが指定されていない場合、または`-`が指定された場合は
+     * パイプラインの入力を読み込みます。-iが指定された場合は
+     * 各ファイルを個別に処理して書き戻し、サフィックスがあれば
+     * バックアップを作成します（サフィックス内の`*`はファイル名に
+     * 置き換えられます）。-sが指定された場合も各ファイルを
+     * 個別のストリームとして扱います。
+     *
+     * 読み込めないファイルはエラーを出力し、終了コード2を返します。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: sed <script> [files...]"));
+        let mut options = SedOptions::default();
+        let mut scripts: Vec<String> = Vec::new();
+        let mut in_place: Option<String> = None;
+        let mut operands: Vec<String> = Vec::new();
+        
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
+                continue;
+            }
+            
+            if arg == "--" {
+                operands.extend(args.by_ref().cloned());
+                break;
+            }
+            
+            if let Some(long) = arg.strip_prefix("--") {
+                match long.split_once('=') {
+                    Some(("expression", script)) => scripts.push(script.to_string()),
+                    Some(("file", path)) => scripts.push(read_sed_script_file(path, shell)?),
+                    Some(("in-place", suffix)) => in_place = Some(suffix.to_string()),
+                    _ => match long {
+                        "expression" => scripts.push(next_option_argument(&mut args, "e")?),
+                        "file" => {
+                            let path = next_option_argument(&mut args, "f")?;
+                            scripts.push(read_sed_script_file(&path, shell)?);
+                        }
+                        "in-place" => in_place = Some(String::new()),
+                        "quiet" | "silent" => options.quiet = true,
+                        "regexp-extended" => options.extended = true,
+                        "separate" => options.separate = true,
+                        "null-data" => options.null_data = true,
+                        _ => return Err(anyhow::anyhow!("unknown option -- '{}'", long)),
+                    },
+                }
+                continue;
+            }
+            
+            let flags = &arg[1..];
+            for (i, flag) in flags.char_indices() {
+                let rest = &flags[i + flag.len_utf8()..];
+                match flag {
+                    'n' => options.quiet = true,
+                    'E' | 'r' => options.extended = true,
+                    's' => options.separate = true,
+                    'z' => options.null_data = true,
+                    'i' => {
+                        in_place = Some(rest.to_string());
+                        break;
+                    }
+                    'e' | 'f' => {
+                        let value = if rest.is_empty() {
+                            next_option_argument(&mut args, &flag.to_string())?
+                        } else {
+                            rest.to_string()
+                        };
+                        if flag == 'e' {
+                            scripts.push(value);
+                        } else {
+                            scripts.push(read_sed_script_file(&value, shell)?);
+                        }
+                        break;
+                    }
+                    other => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+                }
+            }
         }
         
-        let script = &command.args[0];
-        let files = &command.args[1..];
+        if scripts.is_empty() {
+            if operands.is_empty() {
+                return Err(anyhow::anyhow!("Usage: sed [options] <script> [files...]"));
+            }
+            scripts.push(operands.remove(0));
+        }
         
-        if !script.contains('s') {
-            return Err(anyhow::anyhow!("Unsupported sed script: {}", script));
+        let script = SedScript::compile(&scripts.join("\n"), options.extended)
+            .map_err(|e| anyhow::anyhow!("-e expression #1: {}", e))?;
+        if in_place.is_some() {
+            options.separate = true;
         }
         
+        let working_dir = shell.current_path().clone();
         let mut output = String::new();
+        let mut exit_code = 0;
         
-        if files.is_empty() {
-            return Err(anyhow::anyhow!("No files specified"));
+        if operands.is_empty() {
+            if in_place.is_some() {
+                return Err(anyhow::anyhow!("no input files"));
+            }
+            operands.push("-".to_string());
         }
         
-        for file_name in files {
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
-            };
-            
-            if !file_path.exists() {
-                output.push_str(&format!("sed: {}: No such file or directory\n", file_name));
+        // Read every input up front; unreadable files are reported and skipped
+        let mut inputs: Vec<(Option<PathBuf>, String)> = Vec::new();
+        for operand in &operands {
+            if operand == "-" && in_place.is_none() {
+                inputs.push((None, shell.take_pipeline_input().unwrap_or_default()));
                 continue;
             }
             
-            let content = std::fs::read_to_string(&file_path)?;
-            let lines: Vec<&str> = content.lines().collect();
+            let path = working_dir.join(operand);
+            match std::fs::read_to_string(&path) {
+                Ok(content) => inputs.push((Some(path), content)),
+                Err(e) => {
+                    let reason = if e.kind() == std::io::ErrorKind::NotFound {
+                        "No such file or directory".to_string()
+                    } else {
+                        e.to_string()
+                    };
+                    output.push_str(&format!("sed: can't read {}: {}\n", operand, reason));
+                    exit_code = 2;
+                }
+            }
+        }
+        
+        let streams: Vec<(Option<PathBuf>, String)> = if options.separate {
+            inputs
+        } else {
+            let separator = if options.null_data { '\0' } else { '\n' };
+            let mut joined = String::new();
+            for (_, content) in inputs {
+                if !joined.is_empty() && !joined.ends_with(separator) {
+                    joined.push(separator);
+                }
+                joined.push_str(&content);
+            }
+            vec![(None, joined)]
+        };
+        
+        for (path, content) in streams {
+            let result = script.run(&content, &options, &working_dir)?;
             
-            for line in lines {
-                let processed_line = apply_sed_script(script, line);
-                output.push_str(&processed_line);
-                output.push('\n');
+            match (&in_place, path) {
+                (Some(suffix), Some(path)) => {
+                    if !suffix.is_empty() {
+                        let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                        let backup = if suffix.contains('*') {
+                            path.with_file_name(suffix.replace('*', &file_name))
+                        } else {
+                            path.with_file_name(format!("{}{}", file_name, suffix))
+                        };
+                        std::fs::copy(&path, backup)?;
+                    }
+                    std::fs::write(&path, &result.output)?;
+                    output.push_str(&result.stdout_writes);
+                }
+                _ => {
+                    output.push_str(&result.output);
+                    output.push_str(&result.stdout_writes);
+                }
+            }
+            
+            if result.quit {
+                exit_code = result.exit_code;
+                break;
             }
         }
         
         Ok(CommandResult {
             output,
-            exit_code: 0,
+            exit_code,
         })
     }
     
     fn help(&self) -> &str {
-        "sed <script> [files...] - Stream editor\n\
-         Usage: sed 's/old/new/g' file.txt\n\
-         Basic substitution: s/pattern/replacement/flags"
+        "sed [options] <script> [files...] - Stream editor\n\
+         Options:\n\
+         -n: Suppress automatic printing of the pattern space\n\
+         -e script: Add a script (may be repeated)\n\
+         -f file: Read the script from a file\n\
+         -E, -r: Use extended regular expressions\n\
+         -i[SUFFIX]: Edit files in place, keeping a backup if SUFFIX is given\n\
+         -s: Treat files as separate streams\n\
+         -z: Separate lines with NUL characters\n\
+         Addresses: N, $, /re/, first~step, addr1,addr2, addr,+N, 0,/re/\n\
+         Commands: s y a i c d D p P n N h H g G x q Q = z r w b t T :label { }"
     }
     
     fn name(&self) -> &str {
@@ -302,63 +761,144 @@ impl CommandHandler for SedCommand {
     }
 }
 
+/**
+ * Takes the value of an option (sed, grep) from the next argument
+ * 
+ * @param args - Remaining arguments
+ * @param flag - Option name for the error message
+ * @return Result<String> - Option value
+ */
+fn next_option_argument(args: &mut std::slice::Iter<String>, flag: &str) -> Result<String> {
+    args.next()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("option requires an argument -- '{}'", flag))
+}
+
+/**
+ * Reads a sed script file given with -f
+ * 
+ * @param path - Script path, or `-` for piped input
+ * @param shell - Shell instance
+ * @return Result<String> - Script text without its trailing newline
+ */
+fn read_sed_script_file(path: &str, shell: &mut Shell) -> Result<String> {
+    let content = if path == "-" {
+        shell.take_pipeline_input().unwrap_or_default()
+    } else {
+        std::fs::read_to_string(shell.current_path().join(path))
+            .map_err(|_| anyhow::anyhow!("couldn't open file {}: No such file or directory", path))?
+    };
+    Ok(content.strip_suffix('\n').unwrap_or(&content).to_string())
+}
+
+/**
+ * Parses a grep count such as a context length or -m value
+ * 
+ * @param value - Argument text
+ * @param what - Description for the error message
+ * @return Result<usize> - Parsed count
+ */
+fn parse_grep_count(value: &str, what: &str) -> Result<usize> {
+    value.parse().map_err(|_| anyhow::anyhow!("{}: invalid {} argument", value, what))
+}
+
 /**
  * Awk command
  * 
  * Implements the awk command for pattern scanning and processing.
- * Supports basic field processing and pattern matching.
+ * Programs run in-process on the awk interpreter.
  */
 pub struct AwkCommand;
 
 impl CommandHandler for AwkCommand {
+    /**
+     * awkコマンドを実行する関数です
+     *
+     * -F（フィールド区切り）、-v（変数代入）、-f（プログラム
+     * ファイル、複数指定可）と`--`を解析します。-fがない場合は
+     * 最初の引数をプログラムとして使用します。
+     *
+     * 残りの引数はファイル名または`var=value`の代入として
+     * ARGVに渡され、ファイルが指定されていない場合は
+     * パイプラインの入力を読み込みます。
+     *
+     * @param command - 解析されたコマンド
+     * @param shell - シェルインスタンス
+     * @return Result<CommandResult> - コマンドの結果またはエラー
+     */
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        if command.args.is_empty() {
-            return Err(anyhow::anyhow!("Usage: awk <script> [files...]"));
-        }
-        
-        let script = &command.args[0];
-        let files = &command.args[1..];
-        
-        let mut output = String::new();
-        
-        if files.is_empty() {
-            return Err(anyhow::anyhow!("No files specified"));
-        }
+        let mut options = AwkOptions::default();
+        let mut sources: Vec<String> = Vec::new();
+        let mut operands: Vec<String> = Vec::new();
         
-        for file_name in files {
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
-            };
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
+                operands.extend(args.by_ref().cloned());
+                break;
+            }
             
-            if !file_path.exists() {
-                output.push_str(&format!("awk: {}: No such file or directory\n", file_name));
-                continue;
+            if arg == "--" {
+                operands.extend(args.by_ref().cloned());
+                break;
             }
             
-            let content = std::fs::read_to_string(&file_path)?;
-            let lines: Vec<&str> = content.lines().collect();
+            let (flag, attached) = arg[1..].split_at(1.min(arg.len() - 1));
+            let value = if attached.is_empty() {
+                next_awk_argument(&mut args, flag)?
+            } else {
+                attached.to_string()
+            };
             
-            for line in lines {
-                let processed_line = apply_awk_script(script, line);
-                if !processed_line.is_empty() {
-                    output.push_str(&processed_line);
-                    output.push('\n');
+            match flag {
+                "F" => options.field_separator = Some(value),
+                "v" => {
+                    let (name, value) = value
+                        .split_once('=')
+                        .ok_or_else(|| anyhow::anyhow!("`{}' argument to `-v' not in `var=value' form", value))?;
+                    options.assignments.push((name.to_string(), value.to_string()));
+                }
+                "f" => {
+                    let path = shell.current_path().join(&value);
+                    let source = std::fs::read_to_string(&path)
+                        .map_err(|_| anyhow::anyhow!("can't open source file `{}' for reading", value))?;
+                    sources.push(source);
                 }
+                _ => return Err(anyhow::anyhow!("invalid option -- '{}'", flag)),
+            }
+        }
+        
+        if sources.is_empty() {
+            if operands.is_empty() {
+                return Err(anyhow::anyhow!("Usage: awk [-F fs][-v var=value][prog | -f progfile][file ...]"));
             }
+            sources.push(operands.remove(0));
         }
         
+        let program = AwkProgram::compile(&sources.join("\n"))?;
+        let stdin = shell.take_pipeline_input();
+        let working_dir = shell.current_path().to_path_buf();
+        let result = program.run(&options, &operands, stdin, &working_dir);
+        
         Ok(CommandResult {
-            output,
-            exit_code: 0,
+            output: result.output,
+            exit_code: result.exit_code,
         })
     }
     
     fn help(&self) -> &str {
-        "awk <script> [files...] - Pattern scanning and processing\n\
-         Usage: awk '{print $1}' file.txt\n\
-         Basic field processing and pattern matching"
+        "awk [options] <program> [files...] - Pattern scanning and processing\n\
+         Options:\n\
+         -F fs: Set the field separator (t for tab)\n\
+         -v var=value: Assign a variable before BEGIN\n\
+         -f file: Read the program from a file (may be repeated)\n\
+         Operands of the form var=value are assigned when reached\n\
+         Patterns: BEGIN, END, /re/, expressions, start,end ranges\n\
+         Variables: FS OFS ORS RS NR FNR NF FILENAME SUBSEP RSTART RLENGTH ENVIRON ARGV ARGC\n\
+         Functions: length substr index split sub gsub match sprintf tolower toupper\n\
+         int sqrt exp log sin cos atan2 rand srand system close fflush\n\
+         Statements: print printf getline if while do for next exit return delete function"
     }
     
     fn name(&self) -> &str {
@@ -367,79 +907,157 @@ impl CommandHandler for AwkCommand {
 }
 
 /**
- * Sort command
+ * Takes the value of an awk option from the next argument
+ * 
+ * @param args - Remaining arguments
+ * @param flag - Option name for the error message
+ * @return Result<String> - Option value
+ */
+fn next_awk_argument(args: &mut std::slice::Iter<String>, flag: &str) -> Result<String> {
+    args.next()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("option requires an argument -- '{}'", flag))
+}
+
+/**
+ * ソートを行うsortコマンドです
  * 
- * Implements the sort command for sorting lines of text.
- * Supports various sorting options and comparisons.
+ * ロケールに依存しないバイト単位の比較で行を並べ替えます。
+ * -kでキーを、-tでフィールド区切りを指定でき、-n、-g、-h、-V、
+ * -M、-f、-d、-i、-b、-rはキーごとにも指定できます。キーが
+ * 等しい行は、-sまたは-uがなければ行全体で比較するため、
+ * 結果は入力の順序に依存しません。
+ * 
+ * 入力が-Sのバッファ（デフォルト64MiB）を超える場合は、
+ * ソート済みのランを-Tのディレクトリに書き出してマージします。
+ * -oの出力ファイルは全ての入力を読み終えてから開くため、
+ * 入力と同じファイルを指定できます。
  */
 pub struct SortCommand;
 
 impl CommandHandler for SortCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        let reverse = command.args.iter().any(|arg| arg == "-r" || arg == "--reverse");
-        let numeric = command.args.iter().any(|arg| arg == "-n" || arg == "--numeric");
-        let unique = command.args.iter().any(|arg| arg == "-u" || arg == "--unique");
-        
-        let mut output = String::new();
-        let mut all_lines: Vec<String> = Vec::new();
+        let mut options = SortOptions::default();
+        let mut output_file: Option<String> = None;
+        let mut operands: Vec<String> = Vec::new();
         
-        for file_name in &command.args {
-            if file_name.starts_with('-') && (file_name == "-r" || file_name == "-n" || file_name == "-u" ||
-                file_name == "--reverse" || file_name == "--numeric" || file_name == "--unique") {
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
                 continue;
             }
+            if arg == "--" {
+                operands.extend(args.by_ref().cloned());
+                break;
+            }
             
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
-            };
-            
-            if !file_path.exists() {
-                return Err(anyhow::anyhow!("File not found: {}", file_name));
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let flag = match name {
+                    "ignore-leading-blanks" => 'b',
+                    "dictionary-order" => 'd',
+                    "ignore-case" => 'f',
+                    "general-numeric-sort" => 'g',
+                    "human-numeric-sort" => 'h',
+                    "ignore-nonprinting" => 'i',
+                    "month-sort" => 'M',
+                    "numeric-sort" | "numeric" => 'n',
+                    "reverse" => 'r',
+                    "version-sort" => 'V',
+                    "stable" => 's',
+                    "unique" => 'u',
+                    "key" => 'k',
+                    "field-separator" => 't',
+                    "output" => 'o',
+                    "buffer-size" => 'S',
+                    "temporary-directory" => 'T',
+                    _ => return Err(anyhow::anyhow!("unrecognized option '--{}'", name)),
+                };
+                let value = match inline {
+                    Some(value) => value,
+                    None if "ktoST".contains(flag) => next_option_argument(&mut args, name)?,
+                    None => String::new(),
+                };
+                apply_sort_option(&mut options, &mut output_file, flag, value)?;
+                continue;
             }
             
-            let content = std::fs::read_to_string(&file_path)?;
-            let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-            all_lines.extend(lines);
+            let flags = &arg[1..];
+            for (i, flag) in flags.char_indices() {
+                let rest = &flags[i + flag.len_utf8()..];
+                if "ktoST".contains(flag) {
+                    let value = if rest.is_empty() {
+                        next_option_argument(&mut args, &flag.to_string())?
+                    } else {
+                        rest.to_string()
+                    };
+                    apply_sort_option(&mut options, &mut output_file, flag, value)?;
+                    break;
+                }
+                apply_sort_option(&mut options, &mut output_file, flag, String::new())?;
+            }
         }
         
-        if unique {
-            all_lines.sort();
-            all_lines.dedup();
-        } else {
-            if numeric {
-                all_lines.sort_by(|a, b| {
-                    let a_num = a.parse::<f64>().unwrap_or(f64::NEG_INFINITY);
-                    let b_num = b.parse::<f64>().unwrap_or(f64::NEG_INFINITY);
-                    a_num.partial_cmp(&b_num).unwrap_or(std::cmp::Ordering::Equal)
+        let working_dir = shell.current_path().clone();
+        let mut inputs = Vec::new();
+        if operands.is_empty() {
+            operands.push("-".to_string());
+        }
+        for operand in &operands {
+            if operand == "-" {
+                inputs.push(SortInput::Text(shell.take_pipeline_input().unwrap_or_default()));
+                continue;
+            }
+            let path = working_dir.join(operand);
+            if let Err(error) = std::fs::File::open(&path) {
+                return Ok(CommandResult {
+                    output: format!("sort: cannot read: {}: {}\n", operand, describe_error(&error)),
+                    exit_code: 2,
                 });
-            } else {
-                all_lines.sort();
             }
+            inputs.push(SortInput::File(path));
         }
         
-        if reverse {
-            all_lines.reverse();
-        }
-        
-        for line in all_lines {
-            output.push_str(&line);
-            output.push('\n');
+        if let Some(output_file) = output_file {
+            let mut output = OutputFile::new(working_dir.join(&output_file));
+            sort::sort_inputs(inputs, &options, &mut output)?;
+            output
+                .finish()
+                .map_err(|error| anyhow::anyhow!("open failed: {}: {}", output_file, describe_error(&error)))?;
+            return Ok(CommandResult {
+                output: String::new(),
+                exit_code: 0,
+            });
         }
         
+        let mut output = Vec::new();
+        sort::sort_inputs(inputs, &options, &mut output)?;
         Ok(CommandResult {
-            output,
+            output: String::from_utf8_lossy(&output).into_owned(),
             exit_code: 0,
         })
     }
     
     fn help(&self) -> &str {
         "sort [options] [files...] - Sort lines of text\n\
+         Ordering (global, or per key as in -k2,2nr):\n\
+         -b    Ignore leading blanks          -d    Dictionary order\n\
+         -f    Fold lower case to upper case  -i    Ignore nonprinting characters\n\
+         -n    Numeric                        -g    General numeric (floats, exponents)\n\
+         -h    Human numeric (2K, 1G)         -V    Version numbers\n\
+         -M    Month names                    -r    Reverse\n\
          Options:\n\
-         -r, --reverse    Reverse sort order\n\
-         -n, --numeric    Sort numerically\n\
-         -u, --unique     Remove duplicate lines"
+         -k <pos1[,pos2]>  Sort by a key (field[.char][opts])\n\
+         -t <char>         Field separator\n\
+         -s                Stable: keep input order of equal keys\n\
+         -u                Output only the first of equal lines\n\
+         -o <file>         Write to a file (may be an input)\n\
+         -S <size>         Memory buffer before spilling to disk (e.g. 100M)\n\
+         -T <dir>          Directory for temporary files"
     }
     
     fn name(&self) -> &str {
@@ -448,76 +1066,202 @@ impl CommandHandler for SortCommand {
 }
 
 /**
- * Uniq command
+ * Applies one sort option
+ * 
+ * @param options - Sort options
+ * @param output_file - -o destination
+ * @param flag - Option letter
+ * @param value - Argument for -k -t -o -S -T
+ * @return Result<()> - Error for unknown options or bad values
+ */
+fn apply_sort_option(options: &mut SortOptions, output_file: &mut Option<String>, flag: char, value: String) -> Result<()> {
+    match flag {
+        's' => options.stable = true,
+        'u' => options.unique = true,
+        'k' => options.keys.push(SortKey::parse(&value)?),
+        't' => {
+            let separator = match value.as_str() {
+                "\\t" => '\t',
+                "\\0" => '\0',
+                _ => {
+                    let mut chars = value.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        (None, _) => return Err(anyhow::anyhow!("empty tab")),
+                        _ => return Err(anyhow::anyhow!("multi-character tab '{}'", value)),
+                    }
+                }
+            };
+            options.separator = Some(separator);
+        }
+        'o' => *output_file = Some(value),
+        'S' => options.buffer_size = sort::parse_buffer_size(&value)?,
+        'T' => options.temp_dir = PathBuf::from(value),
+        other => {
+            if !options.flags.apply(other) {
+                return Err(anyhow::anyhow!("invalid option -- '{}'", other));
+            }
+        }
+    }
+    Ok(())
+}
+
+/**
+ * 重複した行を処理するuniqコマンドです
+ * 
+ * 隣接する同じ行を1行にまとめます。比較の前に-fで先頭の
+ * フィールドを、-sで先頭の文字を飛ばし、-wで比較する文字数を
+ * 制限します。-iで大文字と小文字を区別しません。
  * 
- * Implements the uniq command for removing duplicate lines.
- * Supports various options for duplicate detection.
+ * -dは重複した行だけ、-uは重複しなかった行だけを出力し、
+ * -cは各行の前に出現回数を付けます。GNU uniqと同様に
+ * 2つ目のオペランドは出力ファイルです。
  */
 pub struct UniqCommand;
 
 impl CommandHandler for UniqCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        let count = command.args.iter().any(|arg| arg == "-c" || arg == "--count");
-        let show_duplicates = command.args.iter().any(|arg| arg == "-d" || arg == "--repeated");
-        
-        let mut output = String::new();
-        let mut lines: Vec<String> = Vec::new();
+        let mut count = false;
+        let mut repeated_only = false;
+        let mut unique_only = false;
+        let mut ignore_case = false;
+        let mut skip_fields = 0;
+        let mut skip_chars = 0;
+        let mut check_chars: Option<usize> = None;
+        let mut operands: Vec<String> = Vec::new();
         
-        for file_name in &command.args {
-            if file_name.starts_with('-') && (file_name == "-c" || file_name == "-d" ||
-                file_name == "--count" || file_name == "--repeated") {
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
                 continue;
             }
+            if arg == "--" {
+                operands.extend(args.by_ref().cloned());
+                break;
+            }
             
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
+            let (flag, inline) = match arg.strip_prefix("--") {
+                Some(long) => {
+                    let (name, inline) = match long.split_once('=') {
+                        Some((name, value)) => (name, Some(value.to_string())),
+                        None => (long, None),
+                    };
+                    let flag = match name {
+                        "count" => 'c',
+                        "repeated" => 'd',
+                        "unique" => 'u',
+                        "ignore-case" => 'i',
+                        "skip-fields" => 'f',
+                        "skip-chars" => 's',
+                        "check-chars" => 'w',
+                        _ => return Err(anyhow::anyhow!("unrecognized option '--{}'", name)),
+                    };
+                    (flag, inline)
+                }
+                None => {
+                    let mut chars = arg[1..].chars();
+                    let flag = chars.next().unwrap_or('-');
+                    let rest: String = chars.collect();
+                    if "fsw".contains(flag) {
+                        (flag, (!rest.is_empty()).then_some(rest))
+                    } else {
+                        for extra in arg[1..].chars() {
+                            match extra {
+                                'c' => count = true,
+                                'd' => repeated_only = true,
+                                'u' => unique_only = true,
+                                'i' => ignore_case = true,
+                                other => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+                            }
+                        }
+                        continue;
+                    }
+                }
             };
             
-            if !file_path.exists() {
-                return Err(anyhow::anyhow!("File not found: {}", file_name));
+            let number = |value: Option<String>, args: &mut std::slice::Iter<String>| -> Result<usize> {
+                let value = match value {
+                    Some(value) => value,
+                    None => next_option_argument(args, &flag.to_string())?,
+                };
+                value.parse().map_err(|_| anyhow::anyhow!("{}: invalid number", value))
+            };
+            match flag {
+                'c' => count = true,
+                'd' => repeated_only = true,
+                'u' => unique_only = true,
+                'i' => ignore_case = true,
+                'f' => skip_fields = number(inline, &mut args)?,
+                's' => skip_chars = number(inline, &mut args)?,
+                _ => check_chars = Some(number(inline, &mut args)?),
             }
-            
-            let content = std::fs::read_to_string(&file_path)?;
-            let file_lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-            lines.extend(file_lines);
         }
         
-        if lines.is_empty() {
-            return Ok(CommandResult {
-                output: String::new(),
-                exit_code: 0,
-            });
+        if operands.len() > 2 {
+            return Err(anyhow::anyhow!("extra operand '{}'", operands[2]));
         }
+        let input_operand = operands.first().cloned().unwrap_or_else(|| "-".to_string());
+        let content = match read_filter_inputs("uniq", &[input_operand], shell).remove(0).1 {
+            Ok(content) => String::from_utf8_lossy(&content).into_owned(),
+            Err(message) => {
+                return Ok(CommandResult {
+                    output: message,
+                    exit_code: 1,
+                })
+            }
+        };
         
-        let mut current_line = &lines[0];
-        let mut current_count = 1;
+        let comparison_key = |line: &str| -> String {
+            let mut rest = line;
+            for _ in 0..skip_fields {
+                rest = rest.trim_start_matches([' ', '\t']);
+                rest = rest.trim_start_matches(|c: char| c != ' ' && c != '\t');
+            }
+            let key: String = rest.chars().skip(skip_chars).take(check_chars.unwrap_or(usize::MAX)).collect();
+            if ignore_case { key.to_lowercase() } else { key }
+        };
         
-        for line in &lines[1..] {
-            if line == current_line {
-                current_count += 1;
-            } else {
-                if !show_duplicates || current_count > 1 {
-                    if count {
-                        output.push_str(&format!("{:4} {}\n", current_count, current_line));
-                    } else {
-                        output.push_str(&format!("{}\n", current_line));
-                    }
+        let mut output = String::new();
+        let mut emit = |line: &str, occurrences: usize| {
+            let selected = match (repeated_only, unique_only) {
+                (true, true) => false,
+                (true, false) => occurrences > 1,
+                (false, true) => occurrences == 1,
+                (false, false) => true,
+            };
+            if selected {
+                if count {
+                    output.push_str(&format!("{:7} {}\n", occurrences, line));
+                } else {
+                    output.push_str(line);
+                    output.push('\n');
                 }
-                current_line = line;
-                current_count = 1;
             }
-        }
+        };
         
-        if !show_duplicates || current_count > 1 {
-            if count {
-                output.push_str(&format!("{:4} {}\n", current_count, current_line));
-            } else {
-                output.push_str(&format!("{}\n", current_line));
+        let mut current: Option<(&str, String, usize)> = None;
+        for line in content.lines() {
+            let key = comparison_key(line);
+            match &mut current {
+                Some((_, current_key, occurrences)) if *current_key == key => *occurrences += 1,
+                _ => {
+                    if let Some((previous, _, occurrences)) = current.take() {
+                        emit(previous, occurrences);
+                    }
+                    current = Some((line, key, 1));
+                }
             }
         }
+        if let Some((previous, _, occurrences)) = current {
+            emit(previous, occurrences);
+        }
         
+        if let Some(output_file) = operands.get(1) {
+            std::fs::write(shell.current_path().join(output_file), &output)
+                .map_err(|error| anyhow::anyhow!("{}: {}", output_file, describe_error(&error)))?;
+            output.clear();
+        }
         Ok(CommandResult {
             output,
             exit_code: 0,
@@ -525,10 +1269,15 @@ impl CommandHandler for UniqCommand {
     }
     
     fn help(&self) -> &str {
-        "uniq [options] [files...] - Remove duplicate lines\n\
+        "uniq [options] [input [output]] - Collapse adjacent duplicate lines\n\
          Options:\n\
-         -c, --count      Prefix lines with count\n\
-         -d, --repeated   Only output duplicate lines"
+         -c, --count         Prefix lines with the number of occurrences\n\
+         -d, --repeated      Only print duplicated lines\n\
+         -u, --unique        Only print lines that are not repeated\n\
+         -i, --ignore-case   Ignore case when comparing\n\
+         -f <n>              Skip the first n fields\n\
+         -s <n>              Skip the first n characters\n\
+         -w <n>              Compare at most n characters"
     }
     
     fn name(&self) -> &str {
@@ -537,101 +1286,990 @@ impl CommandHandler for UniqCommand {
 }
 
 /**
- * Word count command
+ * Counts (lines, words, characters, bytes, max width) and name of one wc row, or an error message
+ */
+type WcRow = Result<([usize; 5], Option<String>), String>;
+
+/**
+ * 行数、単語数、文字数を数えるwcコマンドです
  * 
- * Implements the wc command for counting words, lines, and characters.
- * Supports various counting options.
+ * -lで行数、-wで単語数、-mで文字数、-cでバイト数、-Lで最も
+ * 長い行の表示幅を出力します。オプションがなければ-l、-w、-cと
+ * 同じです。列の順序と幅はGNU wcと同じで、パイプラインの入力を
+ * 含む場合は最低7桁になります。
  */
 pub struct WcCommand;
 
 impl CommandHandler for WcCommand {
     fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
-        let count_lines = command.args.iter().any(|arg| arg == "-l" || arg == "--lines");
-        let count_words = command.args.iter().any(|arg| arg == "-w" || arg == "--words");
-        let count_chars = command.args.iter().any(|arg| arg == "-c" || arg == "--chars");
+        let mut selected = [false; 5];
+        let mut operands: Vec<String> = Vec::new();
+        let mut options_done = false;
         
-        let mut output = String::new();
-        let mut total_lines = 0;
-        let mut total_words = 0;
-        let mut total_chars = 0;
-        
-        for file_name in &command.args {
-            if file_name.starts_with('-') && (file_name == "-l" || file_name == "-w" || file_name == "-c" ||
-                file_name == "--lines" || file_name == "--words" || file_name == "--chars") {
+        for arg in &command.args {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
                 continue;
             }
-            
-            let file_path = if PathBuf::from(file_name).is_absolute() {
-                PathBuf::from(file_name)
-            } else {
-                shell.current_path().join(file_name)
+            if arg == "--" {
+                options_done = true;
+                continue;
+            }
+            let flags: Vec<char> = match arg.strip_prefix("--") {
+                Some("lines") => vec!['l'],
+                Some("words") => vec!['w'],
+                Some("chars") => vec!['m'],
+                Some("bytes") => vec!['c'],
+                Some("max-line-length") => vec!['L'],
+                Some(long) => return Err(anyhow::anyhow!("unrecognized option '--{}'", long)),
+                None => arg[1..].chars().collect(),
             };
-            
-            if !file_path.exists() {
-                return Err(anyhow::anyhow!("File not found: {}", file_name));
+            for flag in flags {
+                let column = "lwmcL".find(flag).ok_or_else(|| anyhow::anyhow!("invalid option -- '{}'", flag))?;
+                selected[column] = true;
+            }
+        }
+        if !selected.iter().any(|column| *column) {
+            selected = [true, true, false, true, false];
+        }
+        
+        let named = !operands.is_empty();
+        if !named {
+            operands.push("-".to_string());
+        }
+        let inputs = read_filter_inputs("wc", &operands, shell);
+        
+        let mut failed = false;
+        let mut rows: Vec<WcRow> = Vec::new();
+        let mut totals = [0usize; 5];
+        let mut reads_stream = false;
+        for (name, content) in inputs {
+            let content = match content {
+                Ok(content) => content,
+                Err(message) => {
+                    failed = true;
+                    rows.push(Err(message));
+                    continue;
+                }
+            };
+            reads_stream |= name == "-";
+            let text = String::from_utf8_lossy(&content);
+            let counts = [
+                content.iter().filter(|byte| **byte == b'\n').count(),
+                text.split_whitespace().count(),
+                text.chars().count(),
+                content.len(),
+                text.lines().map(line_display_width).max().unwrap_or(0),
+            ];
+            for column in 0..4 {
+                totals[column] += counts[column];
+            }
+            totals[4] = totals[4].max(counts[4]);
+            rows.push(Ok((counts, named.then_some(name))));
+        }
+        if operands.len() > 1 {
+            rows.push(Ok((totals, Some("total".to_string()))));
+        }
+        
+        let columns = selected.iter().filter(|column| **column).count();
+        let width = if columns == 1 && operands.len() == 1 {
+            1
+        } else {
+            let digits = totals[3].to_string().len();
+            if reads_stream { digits.max(7) } else { digits }
+        };
+        
+        let mut output = String::new();
+        for row in rows {
+            let (counts, name) = match row {
+                Ok(row) => row,
+                Err(message) => {
+                    output.push_str(&message);
+                    continue;
+                }
+            };
+            let fields: Vec<String> = (0..5)
+                .filter(|column| selected[*column])
+                .map(|column| format!("{:>width$}", counts[column], width = width))
+                .collect();
+            output.push_str(&fields.join(" "));
+            if let Some(name) = name {
+                output.push(' ');
+                output.push_str(&name);
+            }
+            output.push('\n');
+        }
+        
+        Ok(CommandResult {
+            output,
+            exit_code: if failed { 1 } else { 0 },
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "wc [options] [files...] - Count lines, words and characters\n\
+         Options:\n\
+         -l, --lines            Count lines\n\
+         -w, --words            Count words\n\
+         -m, --chars            Count characters\n\
+         -c, --bytes            Count bytes\n\
+         -L, --max-line-length  Print the display width of the longest line"
+    }
+    
+    fn name(&self) -> &str {
+        "wc"
+    }
+}
+
+/**
+ * Display width of a line as wc -L measures it
+ * 
+ * Tabs advance to the next multiple of 8 columns.
+ * 
+ * @param line - Line without its newline
+ * @return usize - Width in terminal columns
+ */
+fn line_display_width(line: &str) -> usize {
+    line.chars().fold(0, |width, c| match c {
+        '\t' => width + 8 - width % 8,
+        c => width + unicode_width::UnicodeWidthChar::width(c).unwrap_or(0),
+    })
+}
+
+/**
+ * Byte, character or field list given to cut
+ */
+struct CutList {
+    /// Inclusive 1-based ranges, sorted and merged
+    ranges: Vec<(usize, usize)>,
+    /// Select everything not in the ranges (--complement)
+    complement: bool,
+}
+
+impl CutList {
+    /**
+     * Parses a list such as 1,3-5,7-
+     * 
+     * @param list - List text
+     * @param complement - --complement given
+     * @return Result<CutList> - List or error
+     */
+    fn parse(list: &str, complement: bool) -> Result<Self> {
+        let mut ranges = Vec::new();
+        for part in list.split([',', ' ']).filter(|part| !part.is_empty()) {
+            let number = |text: &str| -> Result<usize> {
+                let value: usize = text.parse().map_err(|_| anyhow::anyhow!("invalid field value '{}'", text))?;
+                if value == 0 {
+                    return Err(anyhow::anyhow!("fields and positions are numbered from 1"));
+                }
+                Ok(value)
+            };
+            let range = match part.split_once('-') {
+                Some(("", "")) => return Err(anyhow::anyhow!("invalid range with no endpoint: -")),
+                Some(("", end)) => (1, number(end)?),
+                Some((start, "")) => (number(start)?, usize::MAX),
+                Some((start, end)) => {
+                    let (start, end) = (number(start)?, number(end)?);
+                    if end < start {
+                        return Err(anyhow::anyhow!("invalid decreasing range"));
+                    }
+                    (start, end)
+                }
+                None => {
+                    let value = number(part)?;
+                    (value, value)
+                }
+            };
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err(anyhow::anyhow!("missing list of fields"));
+        }
+        
+        ranges.sort();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Ok(CutList { ranges: merged, complement })
+    }
+    
+    /**
+     * Checks whether a position is selected
+     * 
+     * @param position - 1-based position
+     * @return bool - True if selected
+     */
+    fn contains(&self, position: usize) -> bool {
+        self.ranges.iter().any(|(start, end)| (*start..=*end).contains(&position)) != self.complement
+    }
+}
+
+/**
+ * Mode of the cut command
+ */
+#[derive(Clone, Copy, PartialEq)]
+enum CutMode {
+    /// -b
+    Bytes,
+    /// -c
+    Characters,
+    /// -f
+    Fields,
+}
+
+/**
+ * 行の一部を切り出すcutコマンドです
+ * 
+ * -bでバイト、-cで文字、-fでフィールドを選択します。リストは
+ * 「1,3-5,7-」の形式で、--complementで選択を反転します。
+ * -fの区切りは-dで指定し（デフォルトはタブ）、区切りを含まない
+ * 行は-sがなければそのまま出力します。--output-delimiterで
+ * 出力の区切りを変更できます。
+ */
+pub struct CutCommand;
+
+impl CommandHandler for CutCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut mode: Option<(CutMode, String)> = None;
+        let mut delimiter = '\t';
+        let mut output_delimiter: Option<String> = None;
+        let mut only_delimited = false;
+        let mut complement = false;
+        let mut operands: Vec<String> = Vec::new();
+        
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                operands.push(arg.clone());
+                continue;
+            }
+            if arg == "--" {
+                operands.extend(args.by_ref().cloned());
+                break;
             }
             
-            let content = std::fs::read_to_string(&file_path)?;
-            let lines: Vec<&str> = content.lines().collect();
-            
-            let file_lines = lines.len();
-            let file_words = content.split_whitespace().count();
-            let file_chars = content.len();
-            
-            total_lines += file_lines;
-            total_words += file_words;
-            total_chars += file_chars;
+            let (flag, inline) = match arg.strip_prefix("--") {
+                Some(long) => {
+                    let (name, inline) = match long.split_once('=') {
+                        Some((name, value)) => (name, Some(value.to_string())),
+                        None => (long, None),
+                    };
+                    match name {
+                        "bytes" => ('b', inline),
+                        "characters" => ('c', inline),
+                        "fields" => ('f', inline),
+                        "delimiter" => ('d', inline),
+                        "only-delimited" => ('s', None),
+                        "complement" => {
+                            complement = true;
+                            continue;
+                        }
+                        "output-delimiter" => {
+                            output_delimiter = Some(match inline {
+                                Some(value) => value,
+                                None => next_option_argument(&mut args, name)?,
+                            });
+                            continue;
+                        }
+                        _ => return Err(anyhow::anyhow!("unrecognized option '--{}'", name)),
+                    }
+                }
+                None => {
+                    let mut chars = arg[1..].chars();
+                    let flag = chars.next().unwrap_or('-');
+                    let rest: String = chars.collect();
+                    if flag == 's' && rest.is_empty() {
+                        ('s', None)
+                    } else {
+                        (flag, (!rest.is_empty()).then_some(rest))
+                    }
+                }
+            };
             
-            if count_lines {
-                output.push_str(&format!("{:6} ", file_lines));
+            if flag == 's' {
+                only_delimited = true;
+                continue;
+            }
+            let value = match inline {
+                Some(value) => value,
+                None => next_option_argument(&mut args, &flag.to_string())?,
+            };
+            let selected = match flag {
+                'b' => CutMode::Bytes,
+                'c' => CutMode::Characters,
+                'f' => CutMode::Fields,
+                'd' => {
+                    let mut chars = value.chars();
+                    delimiter = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => return Err(anyhow::anyhow!("the delimiter must be a single character")),
+                    };
+                    continue;
+                }
+                other => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+            };
+            if mode.is_some() {
+                return Err(anyhow::anyhow!("only one type of list may be specified"));
+            }
+            mode = Some((selected, value));
+        }
+        
+        let (mode, list) = mode.ok_or_else(|| anyhow::anyhow!("you must specify a list of bytes, characters, or fields"))?;
+        let list = CutList::parse(&list, complement)?;
+        if mode != CutMode::Fields && only_delimited {
+            return Err(anyhow::anyhow!("suppressing non-delimited lines makes sense\n\tonly when operating on fields"));
+        }
+        
+        if operands.is_empty() {
+            operands.push("-".to_string());
+        }
+        let mut output = String::new();
+        let mut exit_code = 0;
+        for (_, content) in read_filter_inputs("cut", &operands, shell) {
+            let content = match content {
+                Ok(content) => content,
+                Err(message) => {
+                    output.push_str(&message);
+                    exit_code = 1;
+                    continue;
+                }
+            };
+            let mut lines: Vec<&[u8]> = content.split(|byte| *byte == b'\n').collect();
+            if content.ends_with(b"\n") || content.is_empty() {
+                lines.pop();
+            }
+            for line in lines {
+                match mode {
+                    CutMode::Fields => {
+                        let text = String::from_utf8_lossy(line);
+                        if !text.contains(delimiter) {
+                            if !only_delimited {
+                                output.push_str(&text);
+                                output.push('\n');
+                            }
+                            continue;
+                        }
+                        let separator = output_delimiter.clone().unwrap_or_else(|| delimiter.to_string());
+                        let fields: Vec<&str> = text
+                            .split(delimiter)
+                            .enumerate()
+                            .filter(|(index, _)| list.contains(index + 1))
+                            .map(|(_, field)| field)
+                            .collect();
+                        output.push_str(&fields.join(&separator));
+                    }
+                    CutMode::Bytes => {
+                        let mut selected = Vec::new();
+                        for (index, byte) in line.iter().enumerate() {
+                            if list.contains(index + 1) {
+                                if let Some(separator) = &output_delimiter {
+                                    if index > 0 && !selected.is_empty() && !list.contains(index) {
+                                        selected.extend_from_slice(separator.as_bytes());
+                                    }
+                                }
+                                selected.push(*byte);
+                            }
+                        }
+                        output.push_str(&String::from_utf8_lossy(&selected));
+                    }
+                    CutMode::Characters => {
+                        let text = String::from_utf8_lossy(line);
+                        let mut selected = String::new();
+                        for (index, c) in text.chars().enumerate() {
+                            if list.contains(index + 1) {
+                                if let Some(separator) = &output_delimiter {
+                                    if index > 0 && !selected.is_empty() && !list.contains(index) {
+                                        selected.push_str(separator);
+                                    }
+                                }
+                                selected.push(c);
+                            }
+                        }
+                        output.push_str(&selected);
+                    }
+                }
+                output.push('\n');
+            }
+        }
+        
+        Ok(CommandResult { output, exit_code })
+    }
+    
+    fn help(&self) -> &str {
+        "cut [options] [files...] - Print selected parts of lines\n\
+         Options:\n\
+         -b <list>               Select bytes\n\
+         -c <list>               Select characters\n\
+         -f <list>               Select fields\n\
+         -d <char>               Field delimiter (default: tab)\n\
+         -s                      Skip lines without the delimiter\n\
+         --complement            Select everything not in the list\n\
+         --output-delimiter=<s>  Delimiter between output fields\n\
+         Lists look like 1,3-5,7- (numbered from 1)."
+    }
+    
+    fn name(&self) -> &str {
+        "cut"
+    }
+}
+
+/**
+ * 文字を変換、削除するtrコマンドです
+ * 
+ * パイプラインの入力のSET1の文字をSET2の対応する文字に
+ * 変換します。-dで削除、-sで連続する同じ文字を1文字に圧縮し、
+ * -cでSET1の補集合を、-tでSET1をSET2の長さに切り詰めて使います。
+ * SETには範囲（a-z）、文字クラス（[:upper:]）、エスケープ（\n）、
+ * 繰り返し（[x*]）を書けます。
+ */
+pub struct TrCommand;
+
+impl CommandHandler for TrCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut options = TrOptions::default();
+        let mut operands: Vec<&str> = Vec::new();
+        let mut options_done = false;
+        
+        for arg in &command.args {
+            if options_done || arg.len() < 2 || !arg.starts_with('-') {
+                operands.push(arg);
+                continue;
+            }
+            match arg.as_str() {
+                "--" => options_done = true,
+                "--complement" => options.complement = true,
+                "--delete" => options.delete = true,
+                "--squeeze-repeats" => options.squeeze = true,
+                "--truncate-set1" => options.truncate = true,
+                _ if arg.starts_with("--") => return Err(anyhow::anyhow!("unrecognized option '{}'", arg)),
+                _ => {
+                    for flag in arg[1..].chars() {
+                        match flag {
+                            'c' | 'C' => options.complement = true,
+                            'd' => options.delete = true,
+                            's' => options.squeeze = true,
+                            't' => options.truncate = true,
+                            other => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+                        }
+                    }
+                }
+            }
+        }
+        
+        let set1 = operands.first().ok_or_else(|| anyhow::anyhow!("missing operand"))?;
+        let set2 = operands.get(1).copied();
+        let allowed = if (options.delete && !options.squeeze) || (options.squeeze && !options.delete && set2.is_none()) { 1 } else { 2 };
+        if let Some(extra) = operands.get(allowed.max(2)) {
+            return Err(anyhow::anyhow!("extra operand '{}'", extra));
+        }
+        
+        let transliterator = Transliterator::new(set1, set2, options)?;
+        let input = shell.take_pipeline_input().unwrap_or_default();
+        Ok(CommandResult {
+            output: transliterator.apply(&input),
+            exit_code: 0,
+        })
+    }
+    
+    fn help(&self) -> &str {
+        "tr [options] <set1> [set2] - Translate or delete characters of piped input\n\
+         Options:\n\
+         -c, -C   Use the complement of set1\n\
+         -d       Delete characters in set1\n\
+         -s       Squeeze repeated characters into one\n\
+         -t       Truncate set1 to the length of set2\n\
+         Sets may contain ranges (a-z), classes ([:upper:] [:digit:] [:space:] ...),\n\
+         escapes (\\n \\t \\NNN), [=c=] and repeats ([c*n], [c*])."
+    }
+    
+    fn name(&self) -> &str {
+        "tr"
+    }
+}
+
+/**
+ * Line or byte count for head and tail
+ */
+#[derive(Clone, Copy)]
+struct SliceCount {
+    /// Count bytes instead of lines (-c)
+    bytes: bool,
+    /// Number of lines or bytes
+    count: usize,
+    /// head: all but the last N; tail: starting at N (+N)
+    from_other_end: bool,
+}
+
+impl SliceCount {
+    /**
+     * Parses a count such as 10, -5 or +3 with an optional K/M/G suffix
+     * 
+     * @param value - Count text
+     * @param bytes - Whether the count is in bytes
+     * @param sign - '-' for head, '+' for tail
+     * @return Result<SliceCount> - Count or error
+     */
+    fn parse(value: &str, bytes: bool, sign: char) -> Result<Self> {
+        let (from_other_end, number) = match value.strip_prefix(sign) {
+            Some(rest) => (true, rest),
+            None => (false, value.strip_prefix(['-', '+']).unwrap_or(value)),
+        };
+        let (digits, multiplier) = match number.char_indices().last() {
+            Some((index, suffix)) if suffix.is_ascii_alphabetic() => {
+                let multiplier = match suffix {
+                    'b' => 512,
+                    'K' | 'k' => 1024,
+                    'M' => 1024 * 1024,
+                    'G' => 1024 * 1024 * 1024,
+                    _ => return Err(anyhow::anyhow!("invalid number of {}: '{}'", if bytes { "bytes" } else { "lines" }, value)),
+                };
+                (&number[..index], multiplier)
+            }
+            _ => (number, 1),
+        };
+        let count: usize = digits
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid number of {}: '{}'", if bytes { "bytes" } else { "lines" }, value))?;
+        Ok(SliceCount {
+            bytes,
+            count: count.saturating_mul(multiplier),
+            from_other_end,
+        })
+    }
+}
+
+/**
+ * Byte offset where line `line` (0-based) starts
+ * 
+ * @param data - Input
+ * @param line - Number of lines to skip
+ * @return usize - Offset, or data length if there are fewer lines
+ */
+fn line_offset(data: &[u8], line: usize) -> usize {
+    if line == 0 {
+        return 0;
+    }
+    data.iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'\n')
+        .nth(line - 1)
+        .map_or(data.len(), |(index, _)| index + 1)
+}
+
+/**
+ * Counts lines, including an unterminated last line
+ * 
+ * @param data - Input
+ * @return usize - Number of lines
+ */
+fn count_lines(data: &[u8]) -> usize {
+    let newlines = data.iter().filter(|byte| **byte == b'\n').count();
+    if data.last().is_some_and(|byte| *byte != b'\n') { newlines + 1 } else { newlines }
+}
+
+/**
+ * Arguments shared by head and tail
+ */
+struct HeadTailArguments {
+    /// Lines or bytes to print
+    count: SliceCount,
+    /// Header override from -v (true) or -q (false)
+    headers: Option<bool>,
+    /// File operands, `-` for the pipeline input
+    operands: Vec<String>,
+}
+
+/**
+ * Follow settings of tail
+ */
+struct FollowSettings {
+    /// -f or -F given
+    enabled: bool,
+    /// --pid
+    pid: Option<i32>,
+    /// -s in seconds
+    sleep_seconds: f64,
+}
+
+/**
+ * Parses the options shared by head and tail
+ * 
+ * @param program - "head" or "tail"
+ * @param args - Arguments
+ * @param follow_options - For tail: collects -f, --pid and -s
+ * @return Result<HeadTailArguments> - Parsed arguments or error
+ */
+fn parse_head_tail_args(program: &str, args: &[String], mut follow_options: Option<&mut FollowSettings>) -> Result<HeadTailArguments> {
+    let sign = if program == "head" { '-' } else { '+' };
+    let mut count = SliceCount {
+        bytes: false,
+        count: 10,
+        from_other_end: false,
+    };
+    let mut headers = None;
+    let mut operands = Vec::new();
+    
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "-" || !arg.starts_with('-') {
+            operands.push(arg.clone());
+            continue;
+        }
+        if arg == "--" {
+            operands.extend(args.by_ref().cloned());
+            break;
+        }
+        if arg[1..].chars().all(|c| c.is_ascii_digit()) {
+            count = SliceCount::parse(&arg[1..], false, sign)?;
+            continue;
+        }
+        
+        let (flag, inline) = match arg.strip_prefix("--") {
+            Some(long) => {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let flag = match name {
+                    "lines" => 'n',
+                    "bytes" => 'c',
+                    "quiet" | "silent" => 'q',
+                    "verbose" => 'v',
+                    "follow" if follow_options.is_some() => 'f',
+                    "pid" if follow_options.is_some() => 'p',
+                    "sleep-interval" if follow_options.is_some() => 's',
+                    _ => return Err(anyhow::anyhow!("unrecognized option '--{}'", name)),
+                };
+                (flag, inline)
             }
-            if count_words {
-                output.push_str(&format!("{:6} ", file_words));
+            None => {
+                let mut chars = arg[1..].chars();
+                let flag = chars.next().unwrap_or('-');
+                let rest: String = chars.collect();
+                if "ncs".contains(flag) {
+                    (flag, (!rest.is_empty()).then_some(rest))
+                } else {
+                    for flag in arg[1..].chars() {
+                        match (flag, follow_options.as_deref_mut()) {
+                            ('q', _) => headers = Some(false),
+                            ('v', _) => headers = Some(true),
+                            ('f' | 'F', Some(follow)) => follow.enabled = true,
+                            (other, _) => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+                        }
+                    }
+                    continue;
+                }
             }
-            if count_chars {
-                output.push_str(&format!("{:6} ", file_chars));
+        };
+        
+        match flag {
+            'q' => headers = Some(false),
+            'v' => headers = Some(true),
+            'f' => {
+                if let Some(follow) = follow_options.as_deref_mut() {
+                    follow.enabled = true;
+                }
             }
-            if !count_lines && !count_words && !count_chars {
-                output.push_str(&format!("{:6} {:6} {:6} ", file_lines, file_words, file_chars));
+            _ => {
+                let value = match inline {
+                    Some(value) => value,
+                    None => next_option_argument(&mut args, &flag.to_string())?,
+                };
+                match (flag, follow_options.as_deref_mut()) {
+                    ('n', _) => count = SliceCount::parse(&value, false, sign)?,
+                    ('c', _) => count = SliceCount::parse(&value, true, sign)?,
+                    ('p', Some(follow)) => {
+                        follow.pid = Some(value.parse().map_err(|_| anyhow::anyhow!("invalid PID: '{}'", value))?);
+                    }
+                    ('s', Some(follow)) => {
+                        follow.sleep_seconds = value
+                            .parse()
+                            .map_err(|_| anyhow::anyhow!("invalid number of seconds: '{}'", value))?;
+                    }
+                    (other, _) => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+                }
             }
-            output.push_str(&format!("{}\n", file_name));
         }
+    }
+    
+    if operands.is_empty() {
+        operands.push("-".to_string());
+    }
+    Ok(HeadTailArguments { count, headers, operands })
+}
+
+/**
+ * Header printed before each file by head and tail
+ * 
+ * @param name - Operand, `-` for the pipeline input
+ * @param first - Whether this is the first header
+ * @return String - `==> name <==` line
+ */
+fn file_header(name: &str, first: bool) -> String {
+    let name = if name == "-" { "standard input" } else { name };
+    format!("{}==> {} <==\n", if first { "" } else { "\n" }, name)
+}
+
+/**
+ * ファイルの先頭を出力するheadコマンドです
+ * 
+ * -nで行数（デフォルト10）、-cでバイト数を指定します。
+ * 「-n -N」のように負の数を指定すると、最後のN行を除いた
+ * 全てを出力します。複数のファイルでは「==> 名前 <==」の
+ * 見出しを付けます（-qで省略、-vで常に表示）。
+ */
+pub struct HeadCommand;
+
+impl CommandHandler for HeadCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let HeadTailArguments { count, headers, operands } = parse_head_tail_args("head", &command.args, None)?;
+        let show_headers = headers.unwrap_or(operands.len() > 1);
+        
+        let mut output = String::new();
+        let mut exit_code = 0;
+        let mut first = true;
+        for (name, content) in read_filter_inputs("head", &operands, shell) {
+            let data = match content {
+                Ok(data) => data,
+                Err(message) => {
+                    output.push_str(&message);
+                    exit_code = 1;
+                    continue;
+                }
+            };
+            if show_headers {
+                output.push_str(&file_header(&name, first));
+            }
+            first = false;
+            
+            let end = match (count.bytes, count.from_other_end) {
+                (true, false) => count.count.min(data.len()),
+                (true, true) => data.len().saturating_sub(count.count),
+                (false, false) => line_offset(&data, count.count),
+                (false, true) => line_offset(&data, count_lines(&data).saturating_sub(count.count)),
+            };
+            output.push_str(&String::from_utf8_lossy(&data[..end]));
+        }
+        
+        Ok(CommandResult { output, exit_code })
+    }
+    
+    fn help(&self) -> &str {
+        "head [options] [files...] - Print the first lines of files\n\
+         Options:\n\
+         -n <n>    Print the first n lines (default 10); -n -N prints all but the last N\n\
+         -c <n>    Print the first n bytes; -c -N prints all but the last N\n\
+         -q, -v    Never or always print file name headers"
+    }
+    
+    fn name(&self) -> &str {
+        "head"
+    }
+}
+
+/**
+ * ファイルの末尾を出力するtailコマンドです
+ * 
+ * -nで行数（デフォルト10）、-cでバイト数を指定し、「+N」の形式では
+ * N行目（Nバイト目）から最後までを出力します。
+ * 
+ * -fを指定すると、出力後もinotifyでファイルを監視し、追加された
+ * データを出力し続けます。Ctrl+Cを押すか、--pidで指定した
+ * プロセスが終了すると終わります。端末に出力している場合は
+ * 追加されたデータをすぐに表示し、パイプやリダイレクトの場合は
+ * 終了時にまとめて出力します。
+ */
+pub struct TailCommand;
+
+impl CommandHandler for TailCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut follow = FollowSettings {
+            enabled: false,
+            pid: None,
+            sleep_seconds: 1.0,
+        };
+        let HeadTailArguments { count, headers, operands } = parse_head_tail_args("tail", &command.args, Some(&mut follow))?;
+        let show_headers = headers.unwrap_or(operands.len() > 1);
         
-        if command.args.len() > 1 {
-            if count_lines {
-                output.push_str(&format!("{:6} ", total_lines));
+        let working_dir = shell.current_path().clone();
+        let mut output = String::new();
+        let mut exit_code = 0;
+        let mut followed = Vec::new();
+        let mut last_printed = None;
+        for (name, content) in read_filter_inputs("tail", &operands, shell) {
+            let data = match content {
+                Ok(data) => data,
+                Err(message) => {
+                    output.push_str(&message);
+                    exit_code = 1;
+                    continue;
+                }
+            };
+            if show_headers {
+                output.push_str(&file_header(&name, last_printed.is_none()));
             }
-            if count_words {
-                output.push_str(&format!("{:6} ", total_words));
+            
+            let start = match (count.bytes, count.from_other_end) {
+                (true, false) => data.len().saturating_sub(count.count),
+                (true, true) => count.count.saturating_sub(1).min(data.len()),
+                (false, false) => line_offset(&data, count_lines(&data).saturating_sub(count.count)),
+                (false, true) => line_offset(&data, count.count.saturating_sub(1)),
+            };
+            output.push_str(&String::from_utf8_lossy(&data[start..]));
+            
+            if name != "-" {
+                last_printed = Some(followed.len());
+                followed.push(FollowedFile {
+                    path: working_dir.join(&name),
+                    name,
+                    offset: data.len() as u64,
+                });
+            } else {
+                last_printed = Some(usize::MAX);
             }
-            if count_chars {
-                output.push_str(&format!("{:6} ", total_chars));
+        }
+        
+        if !follow.enabled || followed.is_empty() {
+            return Ok(CommandResult { output, exit_code });
+        }
+        
+        let mut options = FollowOptions {
+            pid: follow.pid,
+            sleep_interval: std::time::Duration::from_secs_f64(follow.sleep_seconds.max(0.01)),
+            headers: show_headers,
+            last_printed,
+        };
+        if shell.output_is_terminal() {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(output.as_bytes());
+            let _ = stdout.flush();
+            output.clear();
+            tail::follow(&mut followed, &mut options, &mut |data: &str| {
+                let _ = stdout.write_all(data.as_bytes());
+                let _ = stdout.flush();
+            })?;
+        } else {
+            tail::follow(&mut followed, &mut options, &mut |data: &str| output.push_str(data))?;
+        }
+        
+        Ok(CommandResult { output, exit_code })
+    }
+    
+    fn help(&self) -> &str {
+        "tail [options] [files...] - Print the last lines of files\n\
+         Options:\n\
+         -n <n>      Print the last n lines (default 10); -n +N starts at line N\n\
+         -c <n>      Print the last n bytes; -c +N starts at byte N\n\
+         -f          Keep printing data appended to the files (Ctrl+C to stop)\n\
+         --pid=<pid> With -f, stop when the process exits\n\
+         -s <secs>   With -f, longest wait between checks (default 1)\n\
+         -q, -v      Never or always print file name headers"
+    }
+    
+    fn name(&self) -> &str {
+        "tail"
+    }
+}
+
+/**
+ * 入力をファイルにも書き出すteeコマンドです
+ * 
+ * パイプラインの入力をそのまま出力しながら、指定された全ての
+ * ファイルに書き込みます。-aで追記し、-iは互換性のために
+ * 受け付けます。書き込めないファイルがあっても残りの
+ * ファイルへの書き込みは続けます。
+ */
+pub struct TeeCommand;
+
+impl CommandHandler for TeeCommand {
+    fn execute(&self, command: &ParsedCommand, shell: &mut Shell) -> Result<CommandResult> {
+        let mut append = false;
+        let mut files: Vec<&String> = Vec::new();
+        let mut options_done = false;
+        for arg in &command.args {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                files.push(arg);
+                continue;
             }
-            if !count_lines && !count_words && !count_chars {
-                output.push_str(&format!("{:6} {:6} {:6} ", total_lines, total_words, total_chars));
+            match arg.as_str() {
+                "--" => options_done = true,
+                "--append" => append = true,
+                "--ignore-interrupts" => {}
+                _ if arg.starts_with("--") => return Err(anyhow::anyhow!("unrecognized option '{}'", arg)),
+                _ => {
+                    for flag in arg[1..].chars() {
+                        match flag {
+                            'a' => append = true,
+                            'i' => {}
+                            other => return Err(anyhow::anyhow!("invalid option -- '{}'", other)),
+                        }
+                    }
+                }
+            }
+        }
+        
+        let input = shell.take_pipeline_input().unwrap_or_default();
+        let mut errors = String::new();
+        for file in files {
+            let written = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(shell.current_path().join(file))
+                .and_then(|mut handle| handle.write_all(input.as_bytes()));
+            if let Err(error) = written {
+                errors.push_str(&format!("tee: {}: {}\n", file, describe_error(&error)));
             }
-            output.push_str("total\n");
         }
         
         Ok(CommandResult {
-            output,
-            exit_code: 0,
+            exit_code: if errors.is_empty() { 0 } else { 1 },
+            output: input + &errors,
         })
     }
     
     fn help(&self) -> &str {
-        "wc [options] [files...] - Word count\n\
+        "tee [options] [files...] - Copy piped input to files and to the output\n\
          Options:\n\
-         -l, --lines      Count lines\n\
-         -w, --words      Count words\n\
-         -c, --chars      Count characters"
+         -a, --append   Append to the files instead of overwriting them\n\
+         -i             Accepted for compatibility"
     }
     
     fn name(&self) -> &str {
-        "wc"
+        "tee"
     }
 }
 
+/**
+ * Reads the inputs of a text filter
+ * 
+ * `-` reads the pipeline input, which can only be consumed once.
+ * 
+ * @param program - Command name for error messages
+ * @param operands - File operands
+ * @param shell - Shell instance
+ * @return Vec<(String, Result<Vec<u8>, String>)> - Operand and its content, or an error message
+ */
+fn read_filter_inputs(program: &str, operands: &[String], shell: &mut Shell) -> Vec<(String, Result<Vec<u8>, String>)> {
+    operands
+        .iter()
+        .map(|operand| {
+            let content = if operand == "-" {
+                Ok(shell.take_pipeline_input().unwrap_or_default().into_bytes())
+            } else {
+                std::fs::read(shell.current_path().join(operand))
+                    .map_err(|error| format!("{}: {}: {}\n", program, operand, describe_error(&error)))
+            };
+            (operand.clone(), content)
+        })
+        .collect()
+}
+
 /**
  * エスケープシーケンスを解釈する関数です
  * 
@@ -698,73 +2336,3 @@ fn show_nonprintable_chars(text: &str) -> String {
     
     result
 }
-
-/**
- * sedスクリプトを適用する関数です
- * 
- * 指定されたsedスクリプトを入力行に適用し、文字列置換を
- * 実行します。
- * 
- * s/pattern/replacement/flags形式の置換コマンドをサポートし、
- * gフラグが指定された場合は全てのマッチを置換し、指定されない
- * 場合は最初のマッチのみを置換します。
- * 
- * スクリプトが正しく解析できない場合は元の行をそのまま返します。
- * 
- * @param script - 適用するsedスクリプト
- * @param line - 処理する行
- * @return String - 処理された行
- */
-fn apply_sed_script(script: &str, line: &str) -> String {
-    if script.starts_with("s/") {
-        let parts: Vec<&str> = script.split('/').collect();
-        if parts.len() >= 3 {
-            let pattern = parts[1];
-            let replacement = parts[2];
-            let flags = if parts.len() > 3 { parts[3] } else { "" };
-            
-            let mut result = line.to_string();
-            if flags.contains('g') {
-                result = result.replace(pattern, replacement);
-            } else {
-                if let Some(pos) = result.find(pattern) {
-                    result.replace_range(pos..pos + pattern.len(), replacement);
-                }
-            }
-            return result;
-        }
-    }
-    
-    line.to_string()
-}
-
-/**
- * awkスクリプトを適用する関数です
- * 
- * 指定されたawkスクリプトを入力行に適用し、フィールド処理を
- * 実行します。
- * 
- * print $1（最初のフィールドの出力）、print $0（全体の出力）
- * などの基本的なawkコマンドをサポートします。
- * 
- * 入力行を空白で分割してフィールドにアクセスし、指定された
- * フィールドまたは全体を出力します。
- * 
- * 対応していないスクリプトの場合は元の行をそのまま返します。
- * 
- * @param script - 適用するawkスクリプト
- * @param line - 処理する行
- * @return String - 処理された行
- */
-fn apply_awk_script(script: &str, line: &str) -> String {
-    if script.contains("print $1") {
-        let fields: Vec<&str> = line.split_whitespace().collect();
-        if !fields.is_empty() {
-            return fields[0].to_string();
-        }
-    } else if script.contains("print $0") {
-        return line.to_string();
-    }
-    
-    line.to_string()
-} 
\ No newline at end of file
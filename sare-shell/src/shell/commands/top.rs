@@ -0,0 +1,780 @@
+/*!
+ * @file top.rs
+ * @brief Live process viewer for the top builtin
+ *
+ * This module samples /proc twice to work out CPU usage per process
+ * and for the whole system, then either prints top's batch report or
+ * runs a full-screen view drawn with ratatui tables and charts.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file top.rs
+ * @description top sampling, batch output in procps layout and an
+ * interactive ratatui view with CPU history, memory gauge and a
+ * sortable process table.
+ */
+
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Row, Sparkline, Table, TableState};
+use ratatui::Terminal;
+
+use crate::shell::commands::listing::OwnerNames;
+use crate::shell::commands::procfs::{self, MemoryInfo, ProcessInfo, SystemInfo};
+use crate::shell::commands::ps::parse_user;
+use crate::shell::commands::tail::InterruptGuard;
+
+/// Number of CPU samples kept for the history chart
+const HISTORY_LENGTH: usize = 240;
+
+/// Column widths of the interactive process table
+const TABLE_WIDTHS: [Constraint; 12] = [
+    Constraint::Length(7),
+    Constraint::Length(8),
+    Constraint::Length(3),
+    Constraint::Length(3),
+    Constraint::Length(7),
+    Constraint::Length(6),
+    Constraint::Length(6),
+    Constraint::Length(1),
+    Constraint::Length(5),
+    Constraint::Length(5),
+    Constraint::Length(9),
+    Constraint::Min(10),
+];
+
+/**
+ * Column the process list is sorted by
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    /// CPU usage
+    Cpu,
+    /// Resident memory
+    Memory,
+    /// Process ID
+    Pid,
+    /// Accumulated CPU time
+    Time,
+}
+
+impl SortKey {
+    /**
+     * Looks up a sort field name as accepted by top -o
+     *
+     * @param name - Field name such as `%CPU` or `pid`
+     * @return Option<SortKey> - Sort key if the name is known
+     */
+    fn from_name(name: &str) -> Option<SortKey> {
+        match name.to_lowercase().trim_start_matches('%') {
+            "cpu" => Some(SortKey::Cpu),
+            "mem" | "res" => Some(SortKey::Memory),
+            "pid" => Some(SortKey::Pid),
+            "time" | "time+" => Some(SortKey::Time),
+            _ => None,
+        }
+    }
+
+    /**
+     * Column header of the key
+     *
+     * @return &str - Header text
+     */
+    fn label(&self) -> &'static str {
+        match self {
+            SortKey::Cpu => "%CPU",
+            SortKey::Memory => "%MEM",
+            SortKey::Pid => "PID",
+            SortKey::Time => "TIME+",
+        }
+    }
+}
+
+/**
+ * Options of a top invocation
+ */
+#[derive(Debug, Clone)]
+pub struct TopOptions {
+    /// Print reports instead of the full-screen view (-b)
+    pub batch: bool,
+    /// Time between updates (-d)
+    pub delay: Duration,
+    /// Number of updates before exiting (-n)
+    pub iterations: Option<u64>,
+    /// Only processes of these effective users (-u)
+    pub users: Vec<u32>,
+    /// Only these processes (-p)
+    pub pids: Vec<i32>,
+    /// Sort column (-o)
+    pub sort: SortKey,
+    /// Sort in ascending instead of descending order
+    pub ascending: bool,
+}
+
+impl Default for TopOptions {
+    fn default() -> Self {
+        TopOptions {
+            batch: false,
+            delay: Duration::from_secs(3),
+            iterations: None,
+            users: Vec::new(),
+            pids: Vec::new(),
+            sort: SortKey::Cpu,
+            ascending: false,
+        }
+    }
+}
+
+impl TopOptions {
+    /**
+     * Parses top arguments
+     *
+     * Options taking a value accept it attached (-n1) or as the next
+     * argument (-n 1).
+     *
+     * @param args - Arguments
+     * @return Result<TopOptions> - Options or error
+     */
+    pub fn parse(args: &[String]) -> Result<TopOptions> {
+        let mut options = TopOptions::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+                return Err(anyhow::anyhow!("unknown argument '{}'", arg));
+            };
+            let flag = flags.chars().next().unwrap_or_default();
+            let attached = &flags[flag.len_utf8()..];
+            if flag == 'b' && attached.is_empty() {
+                options.batch = true;
+                continue;
+            }
+            if !"ndupo".contains(flag) {
+                return Err(anyhow::anyhow!("unknown option '{}'", arg));
+            }
+            let value = if attached.is_empty() {
+                args.next().cloned().ok_or_else(|| anyhow::anyhow!("option -{} requires an argument", flag))?
+            } else {
+                attached.to_string()
+            };
+            match flag {
+                'n' => {
+                    let count: u64 = value.parse().map_err(|_| anyhow::anyhow!("bad iterations argument '{}'", value))?;
+                    options.iterations = Some(count.max(1));
+                }
+                'd' => {
+                    let seconds: f64 = value.parse().ok().filter(|seconds: &f64| seconds.is_finite() && *seconds >= 0.0)
+                        .ok_or_else(|| anyhow::anyhow!("bad delay interval '{}'", value))?;
+                    options.delay = Duration::from_secs_f64(seconds.max(0.1));
+                }
+                'u' => options.users.push(parse_user(&value)?),
+                'p' => {
+                    for pid in value.split(',') {
+                        options.pids.push(pid.parse().map_err(|_| anyhow::anyhow!("bad pid '{}'", pid))?);
+                    }
+                }
+                _ => {
+                    let (ascending, name) = match value.strip_prefix('+') {
+                        Some(name) => (false, name),
+                        None => (value.starts_with('-'), value.trim_start_matches('-')),
+                    };
+                    options.sort = SortKey::from_name(name).ok_or_else(|| anyhow::anyhow!("unrecognized field name '{}'", name))?;
+                    options.ascending = ascending;
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+/**
+ * Snapshot of /proc at one moment
+ */
+struct Sample {
+    /// System-wide counters
+    system: SystemInfo,
+    /// Every process
+    processes: Vec<ProcessInfo>,
+}
+
+impl Sample {
+    /**
+     * Reads the system counters and all processes
+     *
+     * @return Sample - Current snapshot
+     */
+    fn take() -> Self {
+        let system = SystemInfo::read();
+        let processes = procfs::read_processes(&system);
+        Sample { system, processes }
+    }
+}
+
+/**
+ * One line of the process list
+ */
+#[derive(Debug, Clone)]
+pub struct TopRow {
+    /// Process ID
+    pub pid: i32,
+    /// Effective user name
+    pub user: String,
+    /// Kernel priority
+    pub priority: i64,
+    /// Nice value
+    pub nice: i64,
+    /// Virtual memory in KiB
+    pub virt: u64,
+    /// Resident memory in KiB
+    pub res: u64,
+    /// Shared memory in KiB
+    pub shr: u64,
+    /// State letter
+    pub state: char,
+    /// CPU usage over the last interval, in percent of one CPU
+    pub cpu: f64,
+    /// Resident memory in percent of the total
+    pub mem: f64,
+    /// Accumulated CPU time in clock ticks
+    pub time: u64,
+    /// Command name
+    pub command: String,
+}
+
+/**
+ * Process counts by state
+ */
+#[derive(Debug, Clone, Copy, Default)]
+struct TaskCounts {
+    /// All processes
+    total: usize,
+    /// Running
+    running: usize,
+    /// Sleeping, including uninterruptible and idle
+    sleeping: usize,
+    /// Stopped or traced
+    stopped: usize,
+    /// Zombies
+    zombie: usize,
+}
+
+/**
+ * Everything one top screen shows
+ */
+struct Snapshot {
+    /// Time of the sample
+    now: chrono::DateTime<chrono::Local>,
+    /// Seconds since boot
+    uptime: f64,
+    /// Load averages
+    load: [f64; 3],
+    /// Process counts
+    tasks: TaskCounts,
+    /// CPU shares in percent: us, sy, ni, id, wa, hi, si, st
+    cpu: [f64; 8],
+    /// Memory counters
+    memory: MemoryInfo,
+    /// Clock ticks per second, for TIME+
+    clock_ticks: u64,
+    /// Selected processes
+    rows: Vec<TopRow>,
+}
+
+impl Snapshot {
+    /**
+     * CPU share that was not idle
+     *
+     * @return f64 - Busy percentage
+     */
+    fn busy(&self) -> f64 {
+        (100.0 - self.cpu[3] - self.cpu[4]).clamp(0.0, 100.0)
+    }
+}
+
+/**
+ * 2つのサンプルから top の画面内容を作る関数です
+ *
+ * システム全体のCPU使用率は/proc/statの差分から、プロセスごとの
+ * CPU使用率はutimeとstimeの差分を経過時間で割って求めます
+ * （1 CPUを100%とします）。前回のサンプルにないプロセスは起動
+ * からの累計を使います。タスク数は全プロセスを数え、一覧には
+ * -uと-pの条件に一致するプロセスだけを並べ替えて載せます。
+ *
+ * @param previous - 前回のサンプル
+ * @param current - 今回のサンプル
+ * @param options - topのオプション
+ * @param owners - ユーザー名のキャッシュ
+ * @return Snapshot - 画面の内容
+ */
+fn build_snapshot(previous: &Sample, current: &Sample, options: &TopOptions, owners: &mut OwnerNames) -> Snapshot {
+    let system = &current.system;
+    let interval = (system.uptime - previous.system.uptime).max(0.01);
+    let earlier: HashMap<i32, u64> = previous.processes.iter().map(|process| (process.pid, process.cpu_ticks())).collect();
+
+    let delta = system.cpu.since(&previous.system.cpu);
+    let total = delta.total().max(1) as f64;
+    let share = |ticks: u64| ticks as f64 * 100.0 / total;
+    let cpu = [
+        share(delta.user),
+        share(delta.system),
+        share(delta.nice),
+        share(delta.idle),
+        share(delta.iowait),
+        share(delta.irq),
+        share(delta.softirq),
+        share(delta.steal),
+    ];
+
+    let mut tasks = TaskCounts::default();
+    let mut rows = Vec::new();
+    for process in &current.processes {
+        tasks.total += 1;
+        match process.state {
+            'R' => tasks.running += 1,
+            'T' | 't' => tasks.stopped += 1,
+            'Z' => tasks.zombie += 1,
+            _ => tasks.sleeping += 1,
+        }
+
+        if (!options.users.is_empty() && !options.users.contains(&process.euid))
+            || (!options.pids.is_empty() && !options.pids.contains(&process.pid))
+        {
+            continue;
+        }
+        let ticks = process.cpu_ticks();
+        let used = ticks.saturating_sub(earlier.get(&process.pid).copied().unwrap_or(0));
+        rows.push(TopRow {
+            pid: process.pid,
+            user: owners.user(process.euid),
+            priority: process.priority,
+            nice: process.nice,
+            virt: process.vsize,
+            res: process.rss,
+            shr: process.shared,
+            state: process.state,
+            cpu: used as f64 * 100.0 / (interval * system.clock_ticks.max(1) as f64),
+            mem: system.memory_percent(process),
+            time: ticks,
+            command: process.comm.clone(),
+        });
+    }
+    sort_rows(&mut rows, options.sort, options.ascending);
+
+    Snapshot {
+        now: chrono::Local::now(),
+        uptime: system.uptime,
+        load: system.load,
+        tasks,
+        cpu,
+        memory: system.memory,
+        clock_ticks: system.clock_ticks.max(1),
+        rows,
+    }
+}
+
+/**
+ * Sorts the process list
+ *
+ * @param rows - Process list
+ * @param key - Sort column
+ * @param ascending - Smallest first instead of largest first
+ */
+fn sort_rows(rows: &mut [TopRow], key: SortKey, ascending: bool) {
+    rows.sort_by(|a, b| {
+        let ordering = match key {
+            SortKey::Cpu => a.cpu.partial_cmp(&b.cpu).unwrap_or(std::cmp::Ordering::Equal),
+            SortKey::Memory => a.res.cmp(&b.res),
+            SortKey::Pid => a.pid.cmp(&b.pid),
+            SortKey::Time => a.time.cmp(&b.time),
+        };
+        let ordering = if ascending { ordering } else { ordering.reverse() };
+        ordering.then(a.pid.cmp(&b.pid))
+    });
+}
+
+/**
+ * Formats the uptime the way top and uptime do
+ *
+ * @param seconds - Seconds since boot
+ * @return String - e.g. `3 days,  4:05`, ` 1:02` or `5 min`
+ */
+pub fn format_uptime(seconds: f64) -> String {
+    let minutes = (seconds / 60.0) as u64;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    let mut text = String::new();
+    if days > 0 {
+        text.push_str(&format!("{} day{}, ", days, if days == 1 { "" } else { "s" }));
+    }
+    if hours > 0 {
+        text.push_str(&format!("{:2}:{:02}", hours, minutes));
+    } else {
+        text.push_str(&format!("{} min", minutes));
+    }
+    text
+}
+
+/**
+ * Formats CPU time as M:SS.hh like top's TIME+ column
+ *
+ * @param ticks - CPU time in clock ticks
+ * @param clock_ticks - Clock ticks per second
+ * @return String - Time text
+ */
+fn format_time_plus(ticks: u64, clock_ticks: u64) -> String {
+    let hundredths = ticks * 100 / clock_ticks;
+    format!("{}:{:02}.{:02}", hundredths / 6000, hundredths / 100 % 60, hundredths % 100)
+}
+
+/**
+ * Summary lines at the top of the screen
+ *
+ * @param snapshot - Screen contents
+ * @return Vec<String> - Uptime, tasks, CPU, memory and swap lines
+ */
+fn summary_lines(snapshot: &Snapshot) -> Vec<String> {
+    let memory = &snapshot.memory;
+    let mib = |kib: u64| kib as f64 / 1024.0;
+    let cpu = &snapshot.cpu;
+    vec![
+        format!(
+            "top - {} up {},  load average: {:.2}, {:.2}, {:.2}",
+            snapshot.now.format("%H:%M:%S"),
+            format_uptime(snapshot.uptime),
+            snapshot.load[0],
+            snapshot.load[1],
+            snapshot.load[2]
+        ),
+        format!(
+            "Tasks: {:3} total, {:3} running, {:3} sleeping, {:3} stopped, {:3} zombie",
+            snapshot.tasks.total, snapshot.tasks.running, snapshot.tasks.sleeping, snapshot.tasks.stopped, snapshot.tasks.zombie
+        ),
+        format!(
+            "%Cpu(s): {:4.1} us, {:4.1} sy, {:4.1} ni, {:4.1} id, {:4.1} wa, {:4.1} hi, {:4.1} si, {:4.1} st",
+            cpu[0], cpu[1], cpu[2], cpu[3], cpu[4], cpu[5], cpu[6], cpu[7]
+        ),
+        format!(
+            "MiB Mem : {:8.1} total, {:8.1} free, {:8.1} used, {:8.1} buff/cache",
+            mib(memory.total),
+            mib(memory.free),
+            mib(memory.used()),
+            mib(memory.buffers + memory.cached)
+        ),
+        format!(
+            "MiB Swap: {:8.1} total, {:8.1} free, {:8.1} used. {:8.1} avail Mem",
+            mib(memory.swap_total),
+            mib(memory.swap_free),
+            mib(memory.swap_total.saturating_sub(memory.swap_free)),
+            mib(memory.available)
+        ),
+    ]
+}
+
+/**
+ * Truncates a user name to top's eight columns, marking the cut with `+`
+ *
+ * @param user - User name
+ * @return String - Name of at most eight characters
+ */
+fn short_user(user: &str) -> String {
+    if user.chars().count() > 8 {
+        format!("{}+", user.chars().take(7).collect::<String>())
+    } else {
+        user.to_string()
+    }
+}
+
+/// Header of the process list
+const HEADERS: [&str; 12] = ["    PID", "USER    ", " PR", " NI", "   VIRT", "   RES", "   SHR", "S", " %CPU", " %MEM", "    TIME+", "COMMAND"];
+
+/**
+ * Cells of one process line
+ *
+ * @param row - Process
+ * @param clock_ticks - Clock ticks per second
+ * @return Vec<String> - Column texts padded to the widths of HEADERS
+ */
+fn row_cells(row: &TopRow, clock_ticks: u64) -> Vec<String> {
+    vec![
+        format!("{:>7}", row.pid),
+        format!("{:<8}", short_user(&row.user)),
+        format!("{:>3}", row.priority),
+        format!("{:>3}", row.nice),
+        format!("{:>7}", row.virt),
+        format!("{:>6}", row.res),
+        format!("{:>6}", row.shr),
+        row.state.to_string(),
+        format!("{:>5.1}", row.cpu),
+        format!("{:>5.1}", row.mem),
+        format!("{:>9}", format_time_plus(row.time, clock_ticks)),
+        row.command.clone(),
+    ]
+}
+
+/**
+ * Formats one batch report
+ *
+ * @param snapshot - Screen contents
+ * @return String - Summary, blank line, header and one line per process
+ */
+fn render_report(snapshot: &Snapshot) -> String {
+    let mut report = String::new();
+    for line in summary_lines(snapshot) {
+        report.push_str(&line);
+        report.push('\n');
+    }
+    report.push('\n');
+    report.push_str(&HEADERS.join(" "));
+    report.push('\n');
+    for row in &snapshot.rows {
+        report.push_str(&row_cells(row, snapshot.clock_ticks).join(" "));
+        report.push('\n');
+    }
+    report
+}
+
+/**
+ * topのバッチモードを実行する関数です
+ *
+ * 最初のレポートは短い間隔で取った2つのサンプルから作り、その後は
+ * -dの間隔ごとにレポートを出力します。-nの回数に達するか、
+ * Ctrl+Cが押されると終了します。レポートの間には空行を入れます。
+ *
+ * @param options - topのオプション
+ * @param sink - レポートの出力先
+ */
+pub fn run_batch(options: &TopOptions, sink: &mut dyn FnMut(&str)) {
+    let guard = InterruptGuard::install();
+    let mut owners = OwnerNames::default();
+    let mut previous = Sample::take();
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut count = 0;
+    loop {
+        let current = Sample::take();
+        let snapshot = build_snapshot(&previous, &current, options, &mut owners);
+        previous = current;
+        if count > 0 {
+            sink("\n");
+        }
+        sink(&render_report(&snapshot));
+        count += 1;
+        if options.iterations.is_some_and(|iterations| count >= iterations) {
+            return;
+        }
+
+        let deadline = Instant::now() + options.delay;
+        while Instant::now() < deadline {
+            if guard.interrupted() {
+                return;
+            }
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()).min(Duration::from_millis(50)));
+        }
+    }
+}
+
+/**
+ * Puts the terminal into raw mode on the alternate screen and restores it on drop
+ */
+pub struct ScreenGuard;
+
+impl ScreenGuard {
+    /**
+     * Switches the terminal to full-screen mode
+     *
+     * @return Result<ScreenGuard> - Guard restoring the terminal
+     */
+    pub fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = ScreenGuard;
+        execute!(std::io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for ScreenGuard {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/**
+ * State of the interactive view that survives refreshes
+ */
+struct ViewState {
+    /// Current sort and filter options
+    options: TopOptions,
+    /// Selected row
+    table: TableState,
+    /// Busy CPU percentages, oldest first
+    history: VecDeque<u64>,
+    /// Message shown in the footer
+    message: Option<String>,
+}
+
+/**
+ * 対話モードの画面を描画する関数です
+ *
+ * 上から順に、概要（稼働時間、タスク数、CPU、メモリ）、CPU使用率の
+ * 履歴を示すスパークラインとメモリ使用量のゲージ、選択行を反転
+ * 表示するプロセス表、キー操作の説明またはメッセージを描きます。
+ *
+ * @param frame - 描画先のフレーム
+ * @param snapshot - 画面の内容
+ * @param view - 対話モードの状態
+ */
+fn draw(frame: &mut ratatui::Frame, snapshot: &Snapshot, view: &mut ViewState) {
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(5), Constraint::Length(4), Constraint::Min(3), Constraint::Length(1)])
+        .split(frame.size());
+
+    frame.render_widget(Paragraph::new(summary_lines(snapshot).join("\n")), areas[0]);
+
+    let charts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(areas[1]);
+    let history: Vec<u64> = view.history.iter().copied().collect();
+    let width = charts[0].width.saturating_sub(2) as usize;
+    let visible = &history[history.len().saturating_sub(width)..];
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title(format!(" CPU {:.1}% ", snapshot.busy())))
+            .data(visible)
+            .max(100)
+            .style(Style::default().fg(Color::Green)),
+        charts[0],
+    );
+    let memory = &snapshot.memory;
+    let ratio = if memory.total > 0 { memory.used() as f64 / memory.total as f64 } else { 0.0 };
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title(" Memory "))
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format!("{:.1} / {:.1} MiB", memory.used() as f64 / 1024.0, memory.total as f64 / 1024.0)),
+        charts[1],
+    );
+
+    let header = Row::new(HEADERS.map(str::trim)).style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let rows: Vec<Row> = snapshot.rows.iter().map(|row| Row::new(row_cells(row, snapshot.clock_ticks))).collect();
+    let order = if view.options.ascending { "ascending" } else { "descending" };
+    let table = Table::new(rows)
+        .header(header)
+        .widths(&TABLE_WIDTHS)
+        .block(Block::default().borders(Borders::ALL).title(format!(" Processes by {} ({}) ", view.options.sort.label(), order)))
+        .highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+    frame.render_stateful_widget(table, areas[2], &mut view.table);
+
+    let footer = view.message.clone().unwrap_or_else(|| {
+        "q quit  \u{2191}/\u{2193} select  P cpu  M memory  N pid  T time  R reverse  k kill".to_string()
+    });
+    frame.render_widget(Paragraph::new(footer).style(Style::default().add_modifier(Modifier::DIM)), areas[3]);
+}
+
+/**
+ * topの対話モードを実行する関数です
+ *
+ * 端末を代替画面のrawモードに切り替え、-dの間隔でサンプルを取り
+ * 直して画面を更新します。更新の合間はキー入力を待ち、qやEscや
+ * Ctrl+Cで終了、矢印キーとPgUp/PgDnで行を選択、P/M/N/Tで
+ * 並べ替えの列を変更、Rで順序を反転、kで選択したプロセスに
+ * SIGTERMを送ります。端末の状態はエラーの場合も元に戻します。
+ *
+ * @param options - topのオプション
+ * @return Result<()> - 成功またはエラー
+ */
+pub fn run_interactive(options: &TopOptions) -> Result<()> {
+    let _screen = ScreenGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    terminal.clear()?;
+
+    let mut view = ViewState {
+        options: options.clone(),
+        table: TableState::default(),
+        history: VecDeque::with_capacity(HISTORY_LENGTH),
+        message: None,
+    };
+    view.table.select(Some(0));
+    let mut owners = OwnerNames::default();
+    let mut previous = Sample::take();
+    std::thread::sleep(Duration::from_millis(200));
+    let mut count = 0;
+
+    loop {
+        let current = Sample::take();
+        let mut snapshot = build_snapshot(&previous, &current, &view.options, &mut owners);
+        previous = current;
+        if view.history.len() == HISTORY_LENGTH {
+            view.history.pop_front();
+        }
+        view.history.push_back(snapshot.busy().round() as u64);
+        count += 1;
+
+        let deadline = Instant::now() + view.options.delay;
+        loop {
+            let last = snapshot.rows.len().saturating_sub(1);
+            view.table.select(Some(view.table.selected().unwrap_or(0).min(last)));
+            terminal.draw(|frame| draw(frame, &snapshot, &mut view))?;
+
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() || !event::poll(timeout)? {
+                break;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            view.message = None;
+            let selected = view.table.selected().unwrap_or(0);
+            let page = terminal.size()?.height.saturating_sub(12).max(1) as usize;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Up => view.table.select(Some(selected.saturating_sub(1))),
+                KeyCode::Down => view.table.select(Some(selected + 1)),
+                KeyCode::PageUp => view.table.select(Some(selected.saturating_sub(page))),
+                KeyCode::PageDown => view.table.select(Some(selected + page)),
+                KeyCode::Home => view.table.select(Some(0)),
+                KeyCode::End => view.table.select(Some(last)),
+                KeyCode::Char(letter @ ('P' | 'M' | 'N' | 'T')) => {
+                    view.options.sort = match letter {
+                        'P' => SortKey::Cpu,
+                        'M' => SortKey::Memory,
+                        'N' => SortKey::Pid,
+                        _ => SortKey::Time,
+                    };
+                    sort_rows(&mut snapshot.rows, view.options.sort, view.options.ascending);
+                }
+                KeyCode::Char('R') => {
+                    view.options.ascending = !view.options.ascending;
+                    sort_rows(&mut snapshot.rows, view.options.sort, view.options.ascending);
+                }
+                KeyCode::Char('k') => {
+                    if let Some(row) = snapshot.rows.get(selected) {
+                        view.message = Some(if unsafe { libc::kill(row.pid, libc::SIGTERM) } == 0 {
+                            format!("Sent SIGTERM to {} ({})", row.pid, row.command)
+                        } else {
+                            format!("Failed to signal {}: {}", row.pid, std::io::Error::last_os_error())
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if view.options.iterations.is_some_and(|iterations| count >= iterations) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,322 @@
+/*!
+ * @file tr.rs
+ * @brief Character set parsing and transliteration for the tr builtin
+ *
+ * This module expands tr's SET operands (ranges, escapes, character
+ * classes, equivalence classes and repeats) and applies translation,
+ * deletion and squeezing to text.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file tr.rs
+ * @description tr set expansion ([:class:], a-z, \NNN, [c*n]) and the
+ * translate, delete (-d), squeeze (-s), complement (-c) and truncate (-t) modes.
+ */
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+/**
+ * Options that select tr's mode
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrOptions {
+    /// Use the complement of SET1 (-c, -C)
+    pub complement: bool,
+    /// Delete characters in SET1 (-d)
+    pub delete: bool,
+    /// Squeeze repeated characters (-s)
+    pub squeeze: bool,
+    /// Truncate SET1 to the length of SET2 (-t)
+    pub truncate: bool,
+}
+
+/**
+ * Compiled tr operation
+ */
+#[derive(Debug)]
+pub struct Transliterator {
+    /// Mode options
+    options: TrOptions,
+    /// Characters of SET1
+    set1: HashSet<char>,
+    /// Translation table
+    map: HashMap<char, char>,
+    /// Replacement for complemented characters outside the byte range
+    complement_default: Option<char>,
+    /// Characters whose repeats are squeezed
+    squeeze_set: HashSet<char>,
+    /// Whether squeeze_set is SET1 complemented
+    squeeze_complement: bool,
+    /// Whether characters are translated
+    translating: bool,
+}
+
+impl Transliterator {
+    /**
+     * trの操作を作成する関数です
+     *
+     * モードとSET2の有無を確認し、GNU trと同じ規則で変換表と
+     * 圧縮対象の集合を作ります。変換ではSET2がSET1より短い場合、
+     * SET2の最後の文字で埋めます（-tの場合はSET1を切り詰めます）。
+     *
+     * -cの場合、SET1に含まれない0〜255の文字を昇順に並べたものを
+     * SET1の代わりに使い、それ以外の文字はSET2の最後の文字に
+     * 変換します。圧縮の対象は変換または削除と併用する場合は
+     * SET2、それ以外はSET1です。
+     *
+     * @param set1 - SET1の文字列
+     * @param set2 - SET2の文字列
+     * @param options - モードの設定
+     * @return Result<Transliterator> - 操作またはエラー
+     */
+    pub fn new(set1: &str, set2: Option<&str>, options: TrOptions) -> Result<Self> {
+        let translating = !options.delete && set2.is_some();
+        match (options.delete, options.squeeze, set2) {
+            (false, false, None) => return Err(anyhow::anyhow!("missing operand after '{}'", set1)),
+            (true, false, Some(extra)) => {
+                return Err(anyhow::anyhow!("extra operand '{}'\nOnly one string may be given when deleting without squeezing repeats.", extra))
+            }
+            (true, true, None) => return Err(anyhow::anyhow!("missing operand after '{}'", set1)),
+            _ => {}
+        }
+
+        let mut first = expand_set(set1, None)?;
+        let members: HashSet<char> = first.iter().copied().collect();
+        if options.complement {
+            first = (0u8..=255).map(char::from).filter(|c| !members.contains(c)).collect();
+        }
+
+        let second = match set2 {
+            Some(set2) => expand_set(set2, Some(first.len()))?,
+            None => Vec::new(),
+        };
+
+        let mut map = HashMap::new();
+        let mut complement_default = None;
+        if translating {
+            if second.is_empty() && !first.is_empty() {
+                return Err(anyhow::anyhow!("when not truncating set1, string2 must be non-empty"));
+            }
+            if options.truncate {
+                first.truncate(second.len());
+            }
+            for (index, c) in first.iter().enumerate() {
+                let replacement = second.get(index).or(second.last()).copied().unwrap_or(*c);
+                map.insert(*c, replacement);
+            }
+            if options.complement {
+                complement_default = second.last().copied();
+            }
+        }
+
+        let (squeeze_set, squeeze_complement) = if translating || options.delete {
+            (second.iter().copied().collect(), false)
+        } else {
+            (members.clone(), options.complement)
+        };
+
+        Ok(Transliterator {
+            options,
+            set1: members,
+            map,
+            complement_default,
+            squeeze_set,
+            squeeze_complement,
+            translating,
+        })
+    }
+
+    /**
+     * Applies the operation to text
+     *
+     * @param input - Input text
+     * @return String - Translated, filtered and squeezed text
+     */
+    pub fn apply(&self, input: &str) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut last: Option<char> = None;
+        for c in input.chars() {
+            let in_set1 = self.set1.contains(&c) != self.options.complement;
+            if self.options.delete && in_set1 {
+                continue;
+            }
+            let c = if self.translating { self.translate(c) } else { c };
+            if self.options.squeeze && last == Some(c) && self.squeeze_set.contains(&c) != self.squeeze_complement {
+                continue;
+            }
+            output.push(c);
+            last = Some(c);
+        }
+        output
+    }
+
+    /**
+     * Translates one character
+     *
+     * @param c - Input character
+     * @return char - Replacement, or the character itself
+     */
+    fn translate(&self, c: char) -> char {
+        if let Some(&replacement) = self.map.get(&c) {
+            return replacement;
+        }
+        if self.options.complement && !self.set1.contains(&c) {
+            return self.complement_default.unwrap_or(c);
+        }
+        c
+    }
+}
+
+/**
+ * Characters of a POSIX character class
+ *
+ * @param name - Class name without brackets and colons
+ * @return Option<Vec<char>> - Members in ascending order, or None if unknown
+ */
+fn class_members(name: &str) -> Option<Vec<char>> {
+    let test: fn(&u8) -> bool = match name {
+        "alpha" => u8::is_ascii_alphabetic,
+        "digit" => u8::is_ascii_digit,
+        "alnum" => u8::is_ascii_alphanumeric,
+        "upper" => u8::is_ascii_uppercase,
+        "lower" => u8::is_ascii_lowercase,
+        "space" => |c| matches!(c, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c),
+        "blank" => |c| matches!(c, b' ' | b'\t'),
+        "punct" => u8::is_ascii_punctuation,
+        "cntrl" => u8::is_ascii_control,
+        "print" => |c| (0x20..0x7f).contains(c),
+        "graph" => u8::is_ascii_graphic,
+        "xdigit" => u8::is_ascii_hexdigit,
+        _ => return None,
+    };
+    Some((0u8..=127).filter(test).map(char::from).collect())
+}
+
+/**
+ * Reads one possibly escaped character of a set
+ *
+ * @param chars - Set characters
+ * @param index - Position, advanced past the character
+ * @return char - Character
+ */
+fn read_set_char(chars: &[char], index: &mut usize) -> char {
+    let c = chars[*index];
+    *index += 1;
+    if c != '\\' || *index >= chars.len() {
+        return c;
+    }
+
+    let escaped = chars[*index];
+    *index += 1;
+    match escaped {
+        'a' => '\x07',
+        'b' => '\x08',
+        'f' => '\x0c',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'v' => '\x0b',
+        '0'..='7' => {
+            let mut value = escaped.to_digit(8).unwrap_or(0);
+            for _ in 0..2 {
+                match chars.get(*index).and_then(|c| c.to_digit(8)) {
+                    Some(digit) if value * 8 + digit <= 0o377 => {
+                        value = value * 8 + digit;
+                        *index += 1;
+                    }
+                    _ => break,
+                }
+            }
+            char::from(value as u8)
+        }
+        other => other,
+    }
+}
+
+/**
+ * trのSETを文字の並びに展開する関数です
+ *
+ * エスケープ（\n、\t、\NNNなど）、範囲（a-z）、文字クラス
+ * （[:alpha:]など）、等価クラス（[=c=]）、繰り返し（[c*n]）を
+ * 展開します。繰り返しはSET2でのみ使え、nを省略するか0にすると
+ * SET1の長さまで埋めます。nが0で始まる場合は8進数です。
+ *
+ * @param set - SETの文字列
+ * @param fill_to - SET2の場合はSET1の長さ
+ * @return Result<Vec<char>> - 展開された文字またはエラー
+ */
+fn expand_set(set: &str, fill_to: Option<usize>) -> Result<Vec<char>> {
+    let chars: Vec<char> = set.chars().collect();
+    let mut expanded = Vec::new();
+    let mut fill: Option<(usize, char)> = None;
+    let mut index = 0;
+
+    while index < chars.len() {
+        if chars[index] == '[' {
+            let rest: String = chars[index..].iter().collect();
+            if let Some(inner) = rest.strip_prefix("[:") {
+                if let Some(end) = inner.find(":]") {
+                    let name = &inner[..end];
+                    let members = class_members(name).ok_or_else(|| anyhow::anyhow!("invalid character class '{}'", name))?;
+                    expanded.extend(members);
+                    index += name.chars().count() + 4;
+                    continue;
+                }
+            }
+            if let Some(inner) = rest.strip_prefix("[=") {
+                let mut inner_chars: Vec<char> = inner.chars().collect();
+                if inner_chars.len() >= 3 && inner_chars[1] == '=' && inner_chars[2] == ']' {
+                    expanded.push(inner_chars.remove(0));
+                    index += 5;
+                    continue;
+                }
+            }
+            if fill_to.is_some() && index + 2 < chars.len() {
+                let mut cursor = index + 1;
+                let repeated = read_set_char(&chars, &mut cursor);
+                if chars.get(cursor) == Some(&'*') {
+                    if let Some(close) = chars[cursor..].iter().position(|c| *c == ']') {
+                        let count: String = chars[cursor + 1..cursor + close].iter().collect();
+                        let count = if count.is_empty() {
+                            0
+                        } else if count.starts_with('0') {
+                            usize::from_str_radix(&count, 8).map_err(|_| anyhow::anyhow!("invalid repeat count '{}' in [c*n] construct", count))?
+                        } else {
+                            count.parse().map_err(|_| anyhow::anyhow!("invalid repeat count '{}' in [c*n] construct", count))?
+                        };
+                        if count == 0 {
+                            fill = Some((expanded.len(), repeated));
+                        } else {
+                            expanded.extend(std::iter::repeat_n(repeated, count));
+                        }
+                        index = cursor + close + 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        let start = read_set_char(&chars, &mut index);
+        if chars.get(index) == Some(&'-') && index + 1 < chars.len() {
+            index += 1;
+            let end = read_set_char(&chars, &mut index);
+            if end < start {
+                return Err(anyhow::anyhow!(
+                    "range-endpoints of '{}-{}' are in reverse collating sequence order",
+                    start.escape_default(),
+                    end.escape_default()
+                ));
+            }
+            expanded.extend(start..=end);
+        } else {
+            expanded.push(start);
+        }
+    }
+
+    if let (Some((position, repeated)), Some(target)) = (fill, fill_to) {
+        let missing = target.saturating_sub(expanded.len());
+        expanded.splice(position..position, std::iter::repeat_n(repeated, missing));
+    }
+    Ok(expanded)
+}
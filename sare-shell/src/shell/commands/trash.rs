@@ -0,0 +1,300 @@
+/*!
+ * @file trash.rs
+ * @brief XDG trash can for rm and the trash builtin
+ *
+ * This module implements the home trash directory of the
+ * freedesktop.org Trash specification, so files removed from the
+ * shell show up in (and can be restored from) desktop file managers.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file trash.rs
+ * @description Trashing, listing, restoring and emptying the
+ * $XDG_DATA_HOME/Trash directory with its files/ and info/ subdirectories.
+ */
+
+use anyhow::Result;
+use std::fs;
+use std::io::Write;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use crate::shell::commands::fileops::{describe_error, move_across_devices};
+
+/**
+ * Item stored in the trash
+ */
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    /// Name under Trash/files
+    pub name: String,
+    /// Absolute path the item was deleted from
+    pub original_path: PathBuf,
+    /// Deletion time as written in the info file (YYYY-MM-DDThh:mm:ss)
+    pub deleted_at: String,
+}
+
+/**
+ * Home trash directory
+ */
+#[derive(Debug, Clone)]
+pub struct Trash {
+    /// Trash/files, holding the trashed items
+    files: PathBuf,
+    /// Trash/info, holding one .trashinfo file per item
+    info: PathBuf,
+}
+
+impl Trash {
+    /**
+     * Opens the home trash, creating it if needed
+     *
+     * @return Result<Trash> - Trash under $XDG_DATA_HOME (default ~/.local/share)
+     */
+    pub fn open_home() -> Result<Self> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(dirs::data_dir)
+            .ok_or_else(|| anyhow::anyhow!("cannot locate the home trash: no data directory"))?;
+        let root = data_home.join("Trash");
+        let trash = Trash {
+            files: root.join("files"),
+            info: root.join("info"),
+        };
+
+        for directory in [&root, &trash.files, &trash.info] {
+            if !directory.is_dir() {
+                fs::DirBuilder::new()
+                    .recursive(true)
+                    .mode(0o700)
+                    .create(directory)
+                    .map_err(|error| anyhow::anyhow!("cannot create '{}': {}", directory.display(), describe_error(&error)))?;
+            }
+        }
+        Ok(trash)
+    }
+
+    /**
+     * ファイルをゴミ箱に移動する関数です
+     *
+     * 仕様に従い、まずinfoディレクトリに.trashinfoファイルを
+     * create_newで作成して名前を確保し、その後でファイルを
+     * filesディレクトリに移動します。同名の項目がある場合は
+     * 「名前.2」「名前.3」のように番号を付けます。
+     *
+     * 別のファイルシステム上のファイルはコピーしてから削除します。
+     * 移動に失敗した場合は.trashinfoファイルを削除します。
+     *
+     * @param path - ゴミ箱に移動するパス
+     * @return Result<TrashEntry> - 作成された項目またはエラー
+     */
+    pub fn put(&self, path: &Path) -> Result<TrashEntry> {
+        let metadata = fs::symlink_metadata(path)?;
+        let original_path = absolute_path(path)?;
+        let base_name = original_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow::anyhow!("cannot move '{}' to the trash", path.display()))?;
+        let deleted_at = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        let contents = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&original_path),
+            deleted_at
+        );
+
+        let mut counter = 1;
+        let (name, info_path) = loop {
+            let name = if counter == 1 { base_name.clone() } else { format!("{}.{}", base_name, counter) };
+            let info_path = self.info.join(format!("{}.trashinfo", name));
+            counter += 1;
+            if fs::symlink_metadata(self.files.join(&name)).is_ok() {
+                continue;
+            }
+            match fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                Ok(mut file) => {
+                    file.write_all(contents.as_bytes())?;
+                    break (name, info_path);
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error.into()),
+            }
+        };
+
+        let target = self.files.join(&name);
+        let moved = match fs::rename(path, &target) {
+            Err(error) if error.raw_os_error() == Some(libc::EXDEV) => move_across_devices(path, &target, &metadata),
+            result => result.map_err(|error| anyhow::anyhow!("{}", describe_error(&error))),
+        };
+        if let Err(error) = moved {
+            let _ = fs::remove_file(&info_path);
+            return Err(error);
+        }
+
+        Ok(TrashEntry {
+            name,
+            original_path,
+            deleted_at,
+        })
+    }
+
+    /**
+     * Lists the items in the trash
+     *
+     * Info files that cannot be parsed or have no matching item are skipped.
+     *
+     * @return Result<Vec<TrashEntry>> - Items, oldest first
+     */
+    pub fn entries(&self) -> Result<Vec<TrashEntry>> {
+        let mut entries = Vec::new();
+        for info_entry in fs::read_dir(&self.info)?.flatten() {
+            let file_name = info_entry.file_name().to_string_lossy().into_owned();
+            let Some(name) = file_name.strip_suffix(".trashinfo") else {
+                continue;
+            };
+            if fs::symlink_metadata(self.files.join(name)).is_err() {
+                continue;
+            }
+            let Ok(contents) = fs::read_to_string(info_entry.path()) else {
+                continue;
+            };
+
+            let mut original_path = None;
+            let mut deleted_at = String::new();
+            for line in contents.lines() {
+                if let Some(value) = line.strip_prefix("Path=") {
+                    original_path = Some(decode_path(value));
+                } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+                    deleted_at = value.to_string();
+                }
+            }
+            if let Some(original_path) = original_path {
+                entries.push(TrashEntry {
+                    name: name.to_string(),
+                    original_path,
+                    deleted_at,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| a.deleted_at.cmp(&b.deleted_at).then_with(|| a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+
+    /**
+     * Moves an item back to where it was deleted from
+     *
+     * Missing parent directories are recreated. An existing file at
+     * the original path is never overwritten.
+     *
+     * @param entry - Item to restore
+     * @return Result<PathBuf> - Restored path
+     */
+    pub fn restore(&self, entry: &TrashEntry) -> Result<PathBuf> {
+        let destination = &entry.original_path;
+        if fs::symlink_metadata(destination).is_ok() {
+            return Err(anyhow::anyhow!("cannot restore '{}': '{}' already exists", entry.name, destination.display()));
+        }
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let source = self.files.join(&entry.name);
+        let metadata = fs::symlink_metadata(&source)?;
+        match fs::rename(&source, destination) {
+            Err(error) if error.raw_os_error() == Some(libc::EXDEV) => move_across_devices(&source, destination, &metadata)?,
+            result => result.map_err(|error| anyhow::anyhow!("cannot restore '{}': {}", entry.name, describe_error(&error)))?,
+        }
+
+        let _ = fs::remove_file(self.info.join(format!("{}.trashinfo", entry.name)));
+        Ok(destination.clone())
+    }
+
+    /**
+     * Permanently deletes everything in the trash
+     *
+     * @return Result<usize> - Number of items deleted
+     */
+    pub fn empty(&self) -> Result<usize> {
+        let mut count = 0;
+        for item in fs::read_dir(&self.files)?.flatten() {
+            let path = item.path();
+            let removed = match fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&path),
+                _ => fs::remove_file(&path),
+            };
+            removed.map_err(|error| anyhow::anyhow!("cannot remove '{}': {}", path.display(), describe_error(&error)))?;
+            let _ = fs::remove_file(self.info.join(format!("{}.trashinfo", item.file_name().to_string_lossy())));
+            count += 1;
+        }
+
+        for info in fs::read_dir(&self.info)?.flatten() {
+            let _ = fs::remove_file(info.path());
+        }
+        Ok(count)
+    }
+}
+
+/**
+ * Makes a path absolute without resolving its last component
+ *
+ * @param path - Path to an existing file
+ * @return Result<PathBuf> - Absolute path; a trailing symlink is kept as is
+ */
+fn absolute_path(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("refusing to move '{}' to the trash", path.display()))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+    Ok(parent.join(name))
+}
+
+/**
+ * Percent-encodes a path for the Path= key
+ *
+ * @param path - Absolute path
+ * @return String - Path with bytes outside the URL unreserved set and `/` escaped
+ */
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/**
+ * Decodes a percent-encoded Path= value
+ *
+ * @param value - Encoded path
+ * @return PathBuf - Decoded path; malformed escapes are kept literally
+ */
+fn decode_path(value: &str) -> PathBuf {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| value.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    PathBuf::from(std::ffi::OsString::from_vec(decoded))
+}
@@ -0,0 +1,517 @@
+/*!
+ * @file dirstack.rs
+ * @brief Directory stack and frecency tracking
+ *
+ * This module holds the state behind pushd/popd/dirs and the
+ * frecency database that powers the `z` jump command.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file dirstack.rs
+ * @description Directory navigation state including the pushd stack,
+ * logical path resolution and a persisted frecency ranking.
+ */
+
+use anyhow::Result;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/**
+ * Directory stack used by pushd, popd and dirs
+ *
+ * The current directory is not stored here; index 0 is the entry
+ * that `popd` would return to, matching bash's `DIRSTACK[1]`.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryStack {
+    /// Saved directories, most recent first
+    entries: Vec<PathBuf>,
+}
+
+impl DirectoryStack {
+    /**
+     * Creates an empty directory stack
+     *
+     * @return DirectoryStack - New stack instance
+     */
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /**
+     * Pushes a directory onto the top of the stack
+     *
+     * @param path - Directory to save
+     */
+    pub fn push(&mut self, path: PathBuf) {
+        self.entries.insert(0, path);
+    }
+
+    /**
+     * Pops the top directory
+     *
+     * @return Option<PathBuf> - Directory that was on top
+     */
+    pub fn pop(&mut self) -> Option<PathBuf> {
+        if self.entries.is_empty() {
+            None
+        } else {
+            Some(self.entries.remove(0))
+        }
+    }
+
+    /**
+     * Removes the entry at a stack index (0 = top)
+     *
+     * @param index - Stack index to remove
+     * @return Option<PathBuf> - Removed directory
+     */
+    pub fn remove(&mut self, index: usize) -> Option<PathBuf> {
+        if index < self.entries.len() {
+            Some(self.entries.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Gets all saved entries, most recent first
+     *
+     * @return &[PathBuf] - Stack entries
+     */
+    pub fn entries(&self) -> &[PathBuf] {
+        &self.entries
+    }
+
+    /**
+     * Replaces all saved entries
+     *
+     * Used by rotation, which reorders the full stack including the
+     * current directory.
+     *
+     * @param entries - New stack contents, most recent first
+     */
+    pub fn set_entries(&mut self, entries: Vec<PathBuf>) {
+        self.entries = entries;
+    }
+
+    /**
+     * Clears the stack
+     */
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /**
+     * Gets the number of saved entries
+     *
+     * @return usize - Stack depth, excluding the current directory
+     */
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /**
+     * Checks whether the stack has no saved entries
+     *
+     * @return bool - True if empty
+     */
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/**
+ * Single directory tracked by the frecency database
+ */
+#[derive(Debug, Clone)]
+pub struct FrecencyEntry {
+    /// Absolute directory path
+    pub path: PathBuf,
+    /// Accumulated visit weight
+    pub rank: f64,
+    /// Unix timestamp of the last visit
+    pub last_access: i64,
+}
+
+impl FrecencyEntry {
+    /**
+     * Scores the entry by combining visit count with recency
+     *
+     * Uses the same age buckets as z.sh so ranking feels familiar
+     * to people switching from it.
+     *
+     * @param now - Current Unix timestamp
+     * @return f64 - Frecency score
+     */
+    pub fn frecency(&self, now: i64) -> f64 {
+        let age = now - self.last_access;
+        if age < 3600 {
+            self.rank * 4.0
+        } else if age < 86400 {
+            self.rank * 2.0
+        } else if age < 604800 {
+            self.rank / 2.0
+        } else {
+            self.rank / 4.0
+        }
+    }
+}
+
+/**
+ * How `z` orders matching directories
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrecencyOrder {
+    /// Rank weighted by recency (default)
+    Frecent,
+    /// Visit count only
+    Rank,
+    /// Most recently visited first
+    Recent,
+}
+
+/**
+ * Persisted frecency database for the `z` jump command
+ *
+ * Every successful directory change is recorded. Ranks age out once
+ * their total grows past a threshold so the file stays small.
+ */
+#[derive(Debug)]
+pub struct FrecencyDatabase {
+    /// Known directories
+    entries: Vec<FrecencyEntry>,
+    /// Path to the database file
+    data_file: PathBuf,
+    /// Whether visits are recorded at all
+    enabled: bool,
+}
+
+/// Total rank after which all entries are aged
+const MAX_TOTAL_RANK: f64 = 9000.0;
+
+impl FrecencyDatabase {
+    /**
+     * Creates a database backed by the given file
+     *
+     * Tracking can be turned off with `SARE_Z_DISABLE=1`, in which
+     * case the file is neither read nor written.
+     *
+     * @param data_file - File the database is persisted to
+     * @return Result<FrecencyDatabase> - Loaded database or error
+     */
+    pub fn new(data_file: PathBuf) -> Result<Self> {
+        let enabled = std::env::var("SARE_Z_DISABLE").map(|v| v.is_empty() || v == "0").unwrap_or(true);
+        let mut database = Self {
+            entries: Vec::new(),
+            data_file,
+            enabled,
+        };
+
+        if database.enabled {
+            database.load()?;
+        }
+
+        Ok(database)
+    }
+
+    /**
+     * Loads entries from the data file
+     *
+     * Lines use the `path|rank|timestamp` layout of z.sh data files.
+     * Malformed lines and directories that no longer exist are skipped.
+     *
+     * @return Result<()> - Success or error
+     */
+    pub fn load(&mut self) -> Result<()> {
+        if !self.data_file.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&self.data_file)?;
+        self.entries = content
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.rsplitn(3, '|');
+                let last_access = parts.next()?.parse::<i64>().ok()?;
+                let rank = parts.next()?.parse::<f64>().ok()?;
+                let path = PathBuf::from(parts.next()?);
+                if path.is_dir() {
+                    Some(FrecencyEntry { path, rank, last_access })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    /**
+     * Saves entries to the data file
+     *
+     * @return Result<()> - Success or error
+     */
+    pub fn save(&self) -> Result<()> {
+        let mut content = String::new();
+        for entry in &self.entries {
+            content.push_str(&format!("{}|{}|{}\n", entry.path.display(), entry.rank, entry.last_access));
+        }
+        fs::write(&self.data_file, content)?;
+        Ok(())
+    }
+
+    /**
+     * Records a visit to a directory
+     *
+     * The home directory is not tracked since `cd` alone already
+     * reaches it.
+     *
+     * @param path - Directory that was entered
+     * @return Result<()> - Success or error
+     */
+    pub fn record_visit(&mut self, path: &Path) -> Result<()> {
+        if !self.enabled || dirs::home_dir().as_deref() == Some(path) {
+            return Ok(());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        match self.entries.iter_mut().find(|entry| entry.path == path) {
+            Some(entry) => {
+                entry.rank += 1.0;
+                entry.last_access = now;
+            }
+            None => self.entries.push(FrecencyEntry {
+                path: path.to_path_buf(),
+                rank: 1.0,
+                last_access: now,
+            }),
+        }
+
+        let total: f64 = self.entries.iter().map(|entry| entry.rank).sum();
+        if total > MAX_TOTAL_RANK {
+            for entry in &mut self.entries {
+                entry.rank *= 0.99;
+            }
+            self.entries.retain(|entry| entry.rank >= 1.0);
+        }
+
+        self.save()
+    }
+
+    /**
+     * Forgets a directory
+     *
+     * @param path - Directory to remove
+     * @return Result<bool> - True if the directory was tracked
+     */
+    pub fn remove(&mut self, path: &Path) -> Result<bool> {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.path != path);
+        let removed = self.entries.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /**
+     * キーワードに一致するディレクトリを検索する関数です
+     *
+     * 全てのキーワードが順番通りにパスに含まれるエントリを返し、
+     * 指定された順序（frecency、rank、recent）で並べ替えます。
+     *
+     * キーワードに大文字が含まれない場合は大文字小文字を区別せずに
+     * 比較します。最後のキーワードがパスの最終要素に一致する
+     * エントリは優先されます。
+     *
+     * @param terms - 検索キーワード
+     * @param order - 並べ替え順序
+     * @return Vec<(f64, PathBuf)> - スコアとパスのリスト（高い順）
+     */
+    pub fn query(&self, terms: &[String], order: FrecencyOrder) -> Vec<(f64, PathBuf)> {
+        let now = chrono::Utc::now().timestamp();
+        let case_sensitive = terms.iter().any(|term| term.chars().any(|c| c.is_uppercase()));
+
+        let mut matches: Vec<(f64, PathBuf)> = self.entries
+            .iter()
+            .filter(|entry| entry.path.is_dir() && matches_in_order(&entry.path, terms, case_sensitive))
+            .map(|entry| {
+                let score = match order {
+                    FrecencyOrder::Frecent => entry.frecency(now),
+                    FrecencyOrder::Rank => entry.rank,
+                    FrecencyOrder::Recent => entry.last_access as f64,
+                };
+                (score, entry.path.clone())
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let Some(last) = terms.last() {
+            let last = if case_sensitive { last.clone() } else { last.to_lowercase() };
+            let leaf_match = |path: &PathBuf| {
+                path.file_name()
+                    .map(|name| {
+                        let name = name.to_string_lossy();
+                        if case_sensitive { name.contains(&last) } else { name.to_lowercase().contains(&last) }
+                    })
+                    .unwrap_or(false)
+            };
+            // Stable partition keeps the score order within each group
+            let (mut leaf, rest): (Vec<_>, Vec<_>) = matches.into_iter().partition(|(_, path)| leaf_match(path));
+            leaf.extend(rest);
+            matches = leaf;
+        }
+
+        matches
+    }
+
+    /**
+     * Checks whether visits are being recorded
+     *
+     * @return bool - True if tracking is enabled
+     */
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/**
+ * Checks that all terms occur in the path in the given order
+ *
+ * @param path - Candidate directory
+ * @param terms - Search terms
+ * @param case_sensitive - Whether to compare case-sensitively
+ * @return bool - True if every term matches in order
+ */
+fn matches_in_order(path: &Path, terms: &[String], case_sensitive: bool) -> bool {
+    let haystack = path.to_string_lossy();
+    let haystack = if case_sensitive { haystack.to_string() } else { haystack.to_lowercase() };
+    let mut position = 0;
+
+    for term in terms {
+        let term = if case_sensitive { term.clone() } else { term.to_lowercase() };
+        match haystack[position..].find(&term) {
+            Some(offset) => position += offset + term.len(),
+            None => return false,
+        }
+    }
+
+    true
+}
+
+/**
+ * Resolves `.` and `..` components without touching the filesystem
+ *
+ * This is the logical (`cd -L`) view of a path: `..` removes the
+ * previous component even when that component is a symlink.
+ *
+ * @param path - Absolute path to normalise
+ * @return PathBuf - Normalised path
+ */
+pub fn normalize_logical(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("/");
+                }
+                if normalized.as_os_str().is_empty() {
+                    normalized.push("/");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+/**
+ * cdの移動先を解決する関数です
+ *
+ * `~`と`~/path`はホームディレクトリに展開し、絶対パスは
+ * そのまま、相対パスは現在のディレクトリを基準に解決します。
+ *
+ * `/`、`.`、`..`で始まらない相対パスの場合は、まずCDPATHの
+ * 各ディレクトリを順番に検索します。CDPATHで見つかった場合は
+ * 戻り値の2番目がtrueになり、呼び出し側は移動先を表示します。
+ *
+ * physicalがtrueの場合（cd -P）はシンボリックリンクを解決し、
+ * falseの場合（cd -L）は`..`を論理的に処理します。
+ *
+ * @param current - 現在の作業ディレクトリ
+ * @param target - cdに渡された引数
+ * @param cdpath - CDPATH環境変数の値
+ * @param physical - 物理パスで解決するかどうか
+ * @return Result<(PathBuf, bool)> - 移動先とCDPATHで見つかったかどうか
+ */
+pub fn resolve_cd_target(current: &Path, target: &str, cdpath: Option<&str>, physical: bool) -> Result<(PathBuf, bool)> {
+    let home = || dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+    let resolve = |path: PathBuf| -> Result<PathBuf> {
+        if physical {
+            Ok(path.canonicalize()?)
+        } else {
+            Ok(normalize_logical(&path))
+        }
+    };
+
+    let expanded = if target.is_empty() || target == "~" {
+        home()
+    } else if let Some(rest) = target.strip_prefix("~/") {
+        home().join(rest)
+    } else {
+        PathBuf::from(target)
+    };
+
+    if expanded.is_absolute() {
+        let resolved = resolve(expanded).map_err(|_| anyhow::anyhow!("{}: No such file or directory", target))?;
+        return Ok((resolved, false));
+    }
+
+    let searches_cdpath = !(target.starts_with('.') || target.starts_with('/'));
+    if let (true, Some(cdpath)) = (searches_cdpath, cdpath) {
+        for base in cdpath.split(':') {
+            // An empty CDPATH entry means the current directory
+            let base = if base.is_empty() { current.to_path_buf() } else { current.join(base) };
+            let candidate = base.join(&expanded);
+            if candidate.is_dir() {
+                let announce = base != current;
+                return Ok((resolve(candidate)?, announce));
+            }
+        }
+    }
+
+    let candidate = current.join(&expanded);
+    if candidate.is_dir() {
+        Ok((resolve(candidate)?, false))
+    } else if candidate.exists() {
+        Err(anyhow::anyhow!("{}: Not a directory", target))
+    } else {
+        Err(anyhow::anyhow!("{}: No such file or directory", target))
+    }
+}
+
+/**
+ * Abbreviates the home directory as `~` for display
+ *
+ * @param path - Path to display
+ * @return String - Display form of the path
+ */
+pub fn tilde_path(path: &Path) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(rest) = path.strip_prefix(&home) {
+            if rest.as_os_str().is_empty() {
+                return "~".to_string();
+            }
+            return format!("~/{}", rest.display());
+        }
+    }
+    path.display().to_string()
+}
@@ -18,6 +18,9 @@ use std::process::{Command, Stdio};
 use std::io::{BufRead, BufReader};
 use crate::shell::parser::{ParsedCommand, CommandPipeline, ChainOperator};
 use crate::shell::commands::CommandResult;
+use crate::shell::resources::{self, ResourceLimits, TimeoutPolicy};
+#[cfg(feature = "security")]
+use crate::shell::sandbox::SandboxPolicy;
 
 /**
  * Command executor that handles external command execution
@@ -26,8 +29,10 @@ use crate::shell::commands::CommandResult;
  * process management, I/O redirection, and error handling.
  */
 pub struct CommandExecutor {
-    /// Default timeout for command execution (in seconds)
+    /// Default timeout for command execution (in seconds, 0 disables it)
     timeout_seconds: u64,
+    /// Limits installed in every spawned command
+    resource_limits: ResourceLimits,
 }
 
 impl CommandExecutor {
@@ -38,7 +43,8 @@ impl CommandExecutor {
      */
     pub fn new() -> Self {
         Self {
-            timeout_seconds: 30,
+            timeout_seconds: 0,
+            resource_limits: ResourceLimits::new(),
         }
     }
     
@@ -161,15 +167,270 @@ impl CommandExecutor {
             std::process::id(), pid))
     }
     
+    /**
+     * Executes a command synchronously, feeding it optional stdin data
+     * 
+     * Used by the shell's pipeline runner, which passes the output of
+     * the previous stage as input. A missing binary is reported with
+     * exit code 127 rather than as an error so that `||` chains work.
+     * The executor's default timeout applies when one is set.
+     * 
+     * @param command - Parsed command to execute
+     * @param working_dir - Working directory
+     * @param input - Data written to the command's stdin, if any
+     * @return Result<CommandResult> - Command result or error
+     */
+    pub fn execute_with_input(&self, command: &ParsedCommand, working_dir: &Path, input: Option<&str>) -> Result<CommandResult> {
+        let policy = match self.timeout_seconds {
+            0 => None,
+            seconds => Some(TimeoutPolicy::new(std::time::Duration::from_secs(seconds))),
+        };
+        self.execute_with_timeout(command, working_dir, input, policy.as_ref())
+    }
+
+    /**
+     * 複数の外部コマンドを並列に実行する関数です
+     *
+     * 最大max_processes個のワーカースレッドが次のコマンドを
+     * 順番に取り出して実行します。各コマンドの出力はまとめて
+     * 収集されるため、並列に実行しても出力が混ざりません。
+     *
+     * 結果は実行の完了順ではなく、渡されたコマンドの順に返します。
+     *
+     * @param commands - 実行するコマンド
+     * @param working_dir - 作業ディレクトリ
+     * @param max_processes - 同時に実行するコマンドの最大数
+     * @return Vec<Result<CommandResult>> - コマンドごとの結果
+     */
+    pub fn execute_parallel(&self, commands: &[ParsedCommand], working_dir: &Path, max_processes: usize) -> Vec<Result<CommandResult>> {
+        let next = std::sync::atomic::AtomicUsize::new(0);
+        let results: std::sync::Mutex<Vec<Option<Result<CommandResult>>>> =
+            std::sync::Mutex::new(commands.iter().map(|_| None).collect());
+
+        std::thread::scope(|scope| {
+            for _ in 0..max_processes.clamp(1, commands.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let Some(command) = commands.get(index) else {
+                        break;
+                    };
+                    let result = self.execute_with_input(command, working_dir, None);
+                    if let Ok(mut results) = results.lock() {
+                        results[index] = Some(result);
+                    }
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(anyhow::anyhow!("command did not run"))))
+            .collect()
+    }
+
+    /**
+     * タイムアウト付きでコマンドを実行する関数です
+     * 
+     * 入出力のリダイレクションを設定し、ulimitで設定された
+     * リソース制限をexec直前の子プロセス内でsetrlimit()により
+     * 適用してからコマンドを起動します。
+     * 
+     * タイムアウトが指定された場合、子プロセスを新しいプロセス
+     * グループで起動し、時間切れになるとグループ全体に指定の
+     * シグナル（通常SIGTERM）を送ります。猶予期間を過ぎても
+     * 終了しない場合はSIGKILLを送ります。終了コードはGNU timeoutと
+     * 同様に124（SIGKILLが必要だった場合は137）になります。
+     * 
+     * 標準出力と標準エラーは別スレッドで読み取るため、
+     * 大量の出力があってもデッドロックしません。
+     * 
+     * @param command - 実行するパースされたコマンド
+     * @param working_dir - 作業ディレクトリ
+     * @param input - 標準入力に書き込むデータ
+     * @param timeout - タイムアウトの設定（Noneの場合は無制限）
+     * @return Result<CommandResult> - コマンド結果またはエラー
+     */
+    pub fn execute_with_timeout(&self, command: &ParsedCommand, working_dir: &Path, input: Option<&str>, timeout: Option<&TimeoutPolicy>) -> Result<CommandResult> {
+        self.spawn_external(command, working_dir, input, timeout, |_| Ok(()))
+    }
+    
+    /**
+     * Executes a command inside a sandbox
+     * 
+     * Namespaces, read-only mounts, rlimits and the seccomp filter are
+     * set up in the child between fork and exec by sare-security's
+     * ChildSetup; limits set with `ulimit` and the default timeout
+     * still apply.
+     * 
+     * @param command - Parsed command to execute
+     * @param working_dir - Working directory
+     * @param input - Data written to the command's stdin, if any
+     * @param policy - Restrictions for the command
+     * @return Result<CommandResult> - Command result or error
+     */
+    #[cfg(feature = "security")]
+    pub fn execute_sandboxed(&self, command: &ParsedCommand, working_dir: &Path, input: Option<&str>, policy: &SandboxPolicy) -> Result<CommandResult> {
+        let timeout = match self.timeout_seconds {
+            0 => None,
+            seconds => Some(TimeoutPolicy::new(std::time::Duration::from_secs(seconds))),
+        };
+        let setup = policy.prepare(working_dir)?;
+        self.spawn_external(command, working_dir, input, timeout.as_ref(), move |cmd| {
+            use std::os::unix::process::CommandExt;
+            unsafe {
+                cmd.pre_exec(move || setup.apply());
+            }
+            Ok(())
+        })
+        .map_err(|e| anyhow::anyhow!("cannot set up sandbox ({}): {}", policy.summary(), e))
+    }
+    
+    /**
+     * Starts an external command and collects its output
+     * 
+     * Shared by `execute_with_timeout` and `execute_sandboxed`. The
+     * confine hook runs last, after redirections and `ulimit` limits
+     * are set up, so its pre_exec hooks run after theirs.
+     * 
+     * @param command - Parsed command to execute
+     * @param working_dir - Working directory
+     * @param input - Data written to the command's stdin, if any
+     * @param timeout - Timeout policy, None for no limit
+     * @param confine - Adds child-side setup such as a sandbox to the command
+     * @return Result<CommandResult> - Command result or error
+     */
+    fn spawn_external(&self, command: &ParsedCommand, working_dir: &Path, input: Option<&str>, timeout: Option<&TimeoutPolicy>, confine: impl FnOnce(&mut Command) -> Result<()>) -> Result<CommandResult> {
+        use std::os::unix::process::CommandExt;
+        
+        let mut cmd = Command::new(&command.command);
+        
+        cmd.current_dir(working_dir);
+        cmd.args(&command.args);
+        
+        if let Some(ref input_file) = command.input_redirect {
+            let input_file = std::fs::File::open(working_dir.join(input_file))?;
+            cmd.stdin(Stdio::from(input_file));
+        } else if input.is_some() {
+            cmd.stdin(Stdio::piped());
+        } else {
+            cmd.stdin(Stdio::inherit());
+        }
+        
+        let target = command.append_redirect.as_ref().map(|file| (file, true))
+            .or_else(|| command.output_redirect.as_ref().map(|file| (file, false)));
+        if let Some((file, append)) = target {
+            let output_file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(working_dir.join(file))?;
+            let output_file_clone = output_file.try_clone()?;
+            cmd.stdout(Stdio::from(output_file));
+            cmd.stderr(Stdio::from(output_file_clone));
+        } else {
+            cmd.stdout(Stdio::piped());
+            cmd.stderr(Stdio::piped());
+        }
+        
+        if timeout.is_some() {
+            // Own process group so the timeout reaches grandchildren too
+            cmd.process_group(0);
+        }
+        
+        if !self.resource_limits.is_empty() {
+            let settings = self.resource_limits.to_child_settings();
+            unsafe {
+                cmd.pre_exec(move || resources::apply_child_limits(&settings));
+            }
+        }
+        
+        confine(&mut cmd)?;
+        
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(CommandResult {
+                    output: format!("sare: command not found: {}\n", command.command),
+                    exit_code: 127,
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        
+        // Writing from a separate thread keeps large inputs from deadlocking against a full stdout pipe
+        let writer = match (child.stdin.take(), input) {
+            (Some(mut stdin), Some(data)) => {
+                let data = data.to_string();
+                Some(std::thread::spawn(move || {
+                    use std::io::Write;
+                    let _ = stdin.write_all(data.as_bytes());
+                }))
+            }
+            _ => None,
+        };
+        
+        let stdout_reader = child.stdout.take().map(spawn_reader);
+        let stderr_reader = child.stderr.take().map(spawn_reader);
+        
+        let (status, timed_out) = match timeout {
+            Some(policy) => wait_with_timeout(&mut child, policy)?,
+            None => (child.wait()?, None),
+        };
+        
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+        
+        let mut result = String::new();
+        for reader in [stdout_reader, stderr_reader].into_iter().flatten() {
+            let bytes = reader.join().unwrap_or_default();
+            result.push_str(&String::from_utf8_lossy(&bytes));
+        }
+        
+        let exit_code = match timed_out {
+            Some(signal) => {
+                result.push_str(&format!("sare: {}: timed out\n", command.command));
+                if signal == libc::SIGKILL { 128 + libc::SIGKILL } else { 124 }
+            }
+            None => exit_code_from_status(status),
+        };
+        
+        Ok(CommandResult {
+            output: result,
+            exit_code,
+        })
+    }
+    
     /**
      * Sets the timeout for command execution
      * 
-     * @param seconds - Timeout in seconds
+     * @param seconds - Timeout in seconds, 0 to disable
      */
     pub fn set_timeout(&mut self, seconds: u64) {
         self.timeout_seconds = seconds;
     }
     
+    /**
+     * Gets the resource limits applied to spawned commands
+     * 
+     * @return &ResourceLimits - Resource limits
+     */
+    pub fn resource_limits(&self) -> &ResourceLimits {
+        &self.resource_limits
+    }
+    
+    /**
+     * Gets a mutable reference to the resource limits
+     * 
+     * @return &mut ResourceLimits - Resource limits
+     */
+    pub fn resource_limits_mut(&mut self) -> &mut ResourceLimits {
+        &mut self.resource_limits
+    }
+    
     /**
      * Checks if a command exists in the system PATH
      * 
@@ -452,4 +713,80 @@ impl CommandExecutor {
             exit_code,
         })
     }
-} 
\ No newline at end of file
+}
+
+/**
+ * Converts an exit status into a shell-style exit code
+ * 
+ * Processes killed by a signal report 128 + signal number, matching
+ * what POSIX shells expose through `$?`.
+ * 
+ * @param status - Exit status of a finished process
+ * @return i32 - Shell exit code
+ */
+pub fn exit_code_from_status(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    }
+}
+
+/**
+ * Reads a child's output pipe to the end on a background thread
+ * 
+ * @param pipe - stdout or stderr handle of the child
+ * @return JoinHandle<Vec<u8>> - Thread returning everything read
+ */
+fn spawn_reader<R: std::io::Read + Send + 'static>(mut pipe: R) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+/**
+ * Waits for a child, signalling its process group when time runs out
+ * 
+ * @param child - Child started in its own process group
+ * @param policy - Timeout policy
+ * @return Result<(ExitStatus, Option<i32>)> - Exit status and the last signal sent on timeout
+ */
+fn wait_with_timeout(child: &mut std::process::Child, policy: &TimeoutPolicy) -> Result<(std::process::ExitStatus, Option<i32>)> {
+    let pgid = child.id() as libc::pid_t;
+    let started = std::time::Instant::now();
+    let mut sent: Option<i32> = None;
+    let mut deadline = policy.duration;
+    
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if sent.is_some() {
+                // Stragglers that ignored the signal would keep the output pipes open
+                unsafe {
+                    libc::killpg(pgid, libc::SIGKILL);
+                }
+            }
+            return Ok((status, sent));
+        }
+        
+        if started.elapsed() >= deadline {
+            let signal = match sent {
+                None => policy.signal,
+                Some(_) => libc::SIGKILL,
+            };
+            unsafe {
+                libc::killpg(pgid, signal);
+            }
+            if signal == libc::SIGKILL {
+                return Ok((child.wait()?, Some(signal)));
+            }
+            sent = Some(signal);
+            deadline = policy.duration + policy.kill_after;
+        }
+        
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,313 @@
+/*!
+ * @file diff.rs
+ * @brief Per-file line counts for diff --stat
+ *
+ * This module compares two snapshots of a repository (a commit tree,
+ * the index or the working tree) and counts inserted and deleted lines
+ * per file with the Myers edit distance, as git diff --stat reports.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file diff.rs
+ * @description Snapshot comparison, binary detection and line counts.
+ */
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use super::objects::ObjectId;
+use super::status::{read_worktree_file, worktree_mode, ChangeKind, Status};
+use super::Repository;
+
+/**
+ * Where a file's contents come from
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// A stored blob
+    Blob(ObjectId),
+    /// The file in the working tree
+    WorkTree,
+}
+
+/// Paths to file mode and contents
+pub type Snapshot = BTreeMap<String, (u32, Source)>;
+
+/**
+ * Line counts of one changed file
+ */
+#[derive(Debug, Clone)]
+pub struct FileStat {
+    /// Path relative to the work tree
+    pub path: String,
+    /// Lines added
+    pub insertions: usize,
+    /// Lines removed
+    pub deletions: usize,
+    /// Sizes before and after when either side is binary
+    pub binary: Option<(usize, usize)>,
+    /// Path has merge conflicts and is listed without counts
+    pub unmerged: bool,
+}
+
+/**
+ * Snapshot of a commit's tree
+ *
+ * @param repository - Repository
+ * @param commit - Commit ID
+ * @return Result<Snapshot> - Files of the commit
+ */
+pub fn tree_snapshot(repository: &Repository, commit: &ObjectId) -> Result<Snapshot> {
+    let tree = repository.commit(commit)?.tree;
+    Ok(repository
+        .tree_files(&tree)?
+        .into_iter()
+        .map(|(path, (mode, id))| (path, (mode, Source::Blob(id))))
+        .collect())
+}
+
+/**
+ * Snapshot of the index
+ *
+ * Conflicted paths are represented by our side (stage 2), as git
+ * compares them against the working tree.
+ *
+ * @param status - Status holding the parsed index
+ * @return Snapshot - Stage 0 and stage 2 entries
+ */
+pub fn index_snapshot(status: &Status) -> Snapshot {
+    status
+        .index
+        .entries
+        .iter()
+        .filter(|entry| entry.stage == 0 || entry.stage == 2)
+        .map(|entry| (entry.path.clone(), (entry.mode, Source::Blob(entry.id))))
+        .collect()
+}
+
+/**
+ * Snapshot of the tracked files in the working tree
+ *
+ * Files the status found unchanged keep their index blob, so only
+ * modified files are read from disk.
+ *
+ * @param repository - Repository
+ * @param status - Status of the repository
+ * @return Snapshot - Tracked files as they are on disk
+ */
+pub fn worktree_snapshot(repository: &Repository, status: &Status) -> Snapshot {
+    let mut snapshot = index_snapshot(status);
+    for path in &status.unmerged {
+        if fs::symlink_metadata(repository.work_tree.join(path)).is_err() {
+            snapshot.remove(path);
+        } else if let Some(entry) = snapshot.get_mut(path) {
+            entry.1 = Source::WorkTree;
+        }
+    }
+    for change in &status.unstaged {
+        let metadata = fs::symlink_metadata(repository.work_tree.join(&change.path));
+        match (change.kind, metadata, snapshot.get_mut(&change.path)) {
+            (ChangeKind::Deleted, _, _) | (_, Err(_), _) => {
+                snapshot.remove(&change.path);
+            }
+            (_, Ok(metadata), Some(entry)) => *entry = (worktree_mode(&metadata), Source::WorkTree),
+            _ => {}
+        }
+    }
+    snapshot
+}
+
+/**
+ * Loads the contents of a snapshot entry
+ *
+ * @param repository - Repository
+ * @param path - Path relative to the work tree
+ * @param source - Content source
+ * @return Result<Vec<u8>> - File contents
+ */
+fn load(repository: &Repository, path: &str, source: Source) -> Result<Vec<u8>> {
+    match source {
+        Source::Blob(id) => Ok(repository.objects.read(&id)?.data),
+        Source::WorkTree => {
+            let full = repository.work_tree.join(path);
+            read_worktree_file(&full, &fs::symlink_metadata(&full)?)
+        }
+    }
+}
+
+/**
+ * Compares two snapshots
+ *
+ * @param repository - Repository
+ * @param old - Snapshot before
+ * @param new - Snapshot after
+ * @return Result<Vec<FileStat>> - Changed files sorted by path
+ */
+pub fn compare(repository: &Repository, old: &Snapshot, new: &Snapshot) -> Result<Vec<FileStat>> {
+    let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut stats = Vec::new();
+    for path in paths {
+        let before = old.get(path);
+        let after = new.get(path);
+        if before == after && !matches!(after, Some((_, Source::WorkTree))) {
+            continue;
+        }
+        let old_data = match before {
+            Some((mode, _)) if *mode == 0o160000 => continue,
+            Some((_, source)) => load(repository, path, *source)?,
+            None => Vec::new(),
+        };
+        let new_data = match after {
+            Some((mode, _)) if *mode == 0o160000 => continue,
+            Some((_, source)) => load(repository, path, *source)?,
+            None => Vec::new(),
+        };
+        if before.map(|entry| entry.0) == after.map(|entry| entry.0) && old_data == new_data {
+            continue;
+        }
+
+        let mut stat = FileStat {
+            path: path.clone(),
+            insertions: 0,
+            deletions: 0,
+            binary: None,
+            unmerged: false,
+        };
+        if is_binary(&old_data) || is_binary(&new_data) {
+            stat.binary = Some((old_data.len(), new_data.len()));
+        } else {
+            (stat.insertions, stat.deletions) = line_changes(&old_data, &new_data);
+        }
+        stats.push(stat);
+    }
+    Ok(stats)
+}
+
+/**
+ * Whether contents look binary, using git's NUL-in-first-8000-bytes rule
+ *
+ * @param data - File contents
+ * @return bool - True for binary contents
+ */
+fn is_binary(data: &[u8]) -> bool {
+    data.iter().take(8000).any(|byte| *byte == 0)
+}
+
+/// Work limit for the edit distance search before falling back to an estimate
+const MAX_DIFF_COST: usize = 200_000_000;
+
+/**
+ * 2つの内容の間で追加と削除された行数を数える関数です
+ *
+ * 行を番号に置き換え、共通の先頭と末尾を取り除いてから、Myersの
+ * アルゴリズムで最短編集距離Dを求めます。挿入数をI、削除数をRと
+ * すると、I+R=D、I-R=新しい行数-古い行数なので、両方が決まります。
+ * 計算量が大きすぎる場合は、共通でない行の数で近似します。
+ *
+ * @param old - 変更前の内容
+ * @param new - 変更後の内容
+ * @return (usize, usize) - 追加と削除の行数
+ */
+pub fn line_changes(old: &[u8], new: &[u8]) -> (usize, usize) {
+    let mut interned: HashMap<&[u8], u32> = HashMap::new();
+    let old_lines: Vec<u32> = split_lines(old).map(|line| intern(&mut interned, line)).collect();
+    let new_lines: Vec<u32> = split_lines(new).map(|line| intern(&mut interned, line)).collect();
+
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|(left, right)| left == right).count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(left, right)| left == right)
+        .count();
+    let a = &old_lines[prefix..old_lines.len() - suffix];
+    let b = &new_lines[prefix..new_lines.len() - suffix];
+
+    let distance = edit_distance(a, b).unwrap_or_else(|| {
+        let mut counts: HashMap<u32, isize> = HashMap::new();
+        for line in a {
+            *counts.entry(*line).or_default() += 1;
+        }
+        let common: usize = b
+            .iter()
+            .filter(|line| {
+                let count = counts.entry(**line).or_default();
+                *count -= 1;
+                *count >= 0
+            })
+            .count();
+        a.len() + b.len() - 2 * common
+    });
+    let insertions = (distance + b.len() - a.len()) / 2;
+    (insertions, distance - insertions)
+}
+
+/**
+ * Assigns a number to a line
+ *
+ * @param numbers - Numbers assigned so far
+ * @param line - Line contents
+ * @return u32 - Line number
+ */
+fn intern<'a>(numbers: &mut HashMap<&'a [u8], u32>, line: &'a [u8]) -> u32 {
+    let next = numbers.len() as u32;
+    *numbers.entry(line).or_insert(next)
+}
+
+/**
+ * Splits contents into lines, keeping the final line without a newline
+ *
+ * @param data - Contents
+ * @return impl Iterator<Item = &[u8]> - Lines including their newline
+ */
+fn split_lines(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    data.split_inclusive(|byte| *byte == b'\n')
+}
+
+/**
+ * Myers shortest edit script length
+ *
+ * @param a - Old line numbers
+ * @param b - New line numbers
+ * @return Option<usize> - Edit distance, or None if the search is too expensive
+ */
+fn edit_distance(a: &[u32], b: &[u32]) -> Option<usize> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    if max == 0 {
+        return Some(0);
+    }
+    let offset = max as isize;
+    let mut furthest = vec![0isize; 2 * max + 2];
+    let mut cost = 0usize;
+    for d in 0..=max as isize {
+        let mut k = -d;
+        while k <= d {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && furthest[index - 1] < furthest[index + 1]) {
+                furthest[index + 1]
+            } else {
+                furthest[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index] = x;
+            if x >= n && y >= m {
+                return Some(d as usize);
+            }
+            k += 2;
+        }
+        cost += (2 * d as usize + 1) + (n + m) as usize / 64;
+        if cost > MAX_DIFF_COST {
+            return None;
+        }
+    }
+    Some(max)
+}
@@ -0,0 +1,276 @@
+/*!
+ * @file inflate.rs
+ * @brief zlib decompression for git objects
+ *
+ * This module decodes the zlib streams git uses for loose objects and
+ * packfile entries: stored, fixed Huffman and dynamic Huffman DEFLATE
+ * blocks (RFC 1950, RFC 1951).
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file inflate.rs
+ * @description DEFLATE decoder with canonical Huffman tables and a
+ * zlib header check.
+ */
+
+use anyhow::Result;
+
+/// Base lengths of length codes 257..285
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+
+/// Extra bits of length codes 257..285
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Base distances of distance codes 0..29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Extra bits of distance codes 0..29
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Order in which code length code lengths are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/**
+ * Least-significant-bit-first reader over the compressed bytes
+ */
+struct BitReader<'a> {
+    /// Compressed data
+    data: &'a [u8],
+    /// Next byte to load
+    position: usize,
+    /// Loaded bits, least significant first
+    buffer: u64,
+    /// Number of valid bits in buffer
+    count: u32,
+}
+
+impl BitReader<'_> {
+    /**
+     * Reads bits as a little-endian number
+     *
+     * @param needed - Number of bits, at most 32
+     * @return Result<u32> - Value or an error at end of input
+     */
+    fn bits(&mut self, needed: u32) -> Result<u32> {
+        while self.count < needed {
+            let byte = *self.data.get(self.position).ok_or_else(|| anyhow::anyhow!("compressed data ends unexpectedly"))?;
+            self.position += 1;
+            self.buffer |= (byte as u64) << self.count;
+            self.count += 8;
+        }
+        let value = (self.buffer & ((1u64 << needed) - 1)) as u32;
+        self.buffer >>= needed;
+        self.count -= needed;
+        Ok(value)
+    }
+
+    /**
+     * Drops the bits left in the current byte
+     */
+    fn align(&mut self) {
+        let drop = self.count % 8;
+        self.buffer >>= drop;
+        self.count -= drop;
+    }
+}
+
+/**
+ * Canonical Huffman code
+ */
+struct Huffman {
+    /// Number of codes of each length
+    counts: [u16; 16],
+    /// Symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /**
+     * Builds the code from the code length of each symbol
+     *
+     * @param lengths - Code length per symbol, 0 for unused
+     * @return Huffman - Decoding table
+     */
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    /**
+     * Decodes one symbol
+     *
+     * @param reader - Bit source
+     * @return Result<u16> - Symbol or error for an invalid code
+     */
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return self
+                    .symbols
+                    .get((index + code - first) as usize)
+                    .copied()
+                    .ok_or_else(|| anyhow::anyhow!("invalid Huffman code"));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(anyhow::anyhow!("invalid Huffman code"))
+    }
+}
+
+/**
+ * Fixed literal/length and distance codes of block type 1
+ *
+ * @return (Huffman, Huffman) - Literal/length and distance codes
+ */
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    (Huffman::new(&lengths), Huffman::new(&[5u8; 30]))
+}
+
+/**
+ * 動的ハフマン符号のブロックヘッダーを読む関数です
+ *
+ * 符号長の符号（19個）を読み、それを使ってリテラル/長さと距離の
+ * 符号長を復号します。16は直前の長さの繰り返し、17と18は0の
+ * 繰り返しを表します。
+ *
+ * @param reader - ビットの読み取り元
+ * @return Result<(Huffman, Huffman)> - リテラル/長さと距離の符号
+ */
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for position in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*position] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or_else(|| anyhow::anyhow!("repeat without a previous length"))?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if lengths.len() + repeat > literal_count + distance_count {
+            return Err(anyhow::anyhow!("too many code lengths"));
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+/**
+ * zlibで圧縮されたデータを展開する関数です
+ *
+ * 2バイトのzlibヘッダーを確認してから、最終ブロックまで
+ * DEFLATEブロック（非圧縮、固定ハフマン、動的ハフマン）を
+ * 順に復号します。末尾のAdler-32チェックサムは読み飛ばします。
+ * 入力の後ろに余分なデータがあっても構いません（packファイルの
+ * 途中から展開する場合）。
+ *
+ * @param data - zlibストリーム
+ * @param size_hint - 展開後のおおよそのサイズ
+ * @return Result<Vec<u8>> - 展開したデータまたはエラー
+ */
+pub fn zlib_decompress(data: &[u8], size_hint: usize) -> Result<Vec<u8>> {
+    if data.len() < 2 || data[0] & 0x0F != 8 || !(((data[0] as u16) << 8) | data[1] as u16).is_multiple_of(31) {
+        return Err(anyhow::anyhow!("not a zlib stream"));
+    }
+    let mut reader = BitReader {
+        data: &data[2..],
+        position: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut output: Vec<u8> = Vec::with_capacity(size_hint);
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let length = reader.bits(16)?;
+                let complement = reader.bits(16)?;
+                if length != !complement & 0xFFFF {
+                    return Err(anyhow::anyhow!("stored block length mismatch"));
+                }
+                for _ in 0..length {
+                    output.push(reader.bits(8)? as u8);
+                }
+            }
+            kind @ (1 | 2) => {
+                let (literals, distances) = if kind == 1 { fixed_codes() } else { dynamic_codes(&mut reader)? };
+                loop {
+                    let symbol = literals.decode(&mut reader)? as usize;
+                    if symbol < 256 {
+                        output.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        break;
+                    }
+                    let index = symbol - 257;
+                    if index >= LENGTH_BASE.len() {
+                        return Err(anyhow::anyhow!("invalid length code"));
+                    }
+                    let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                    let code = distances.decode(&mut reader)? as usize;
+                    if code >= DISTANCE_BASE.len() {
+                        return Err(anyhow::anyhow!("invalid distance code"));
+                    }
+                    let distance = DISTANCE_BASE[code] as usize + reader.bits(DISTANCE_EXTRA[code] as u32)? as usize;
+                    if distance > output.len() {
+                        return Err(anyhow::anyhow!("distance too far back"));
+                    }
+                    let start = output.len() - distance;
+                    for offset in 0..length {
+                        output.push(output[start + offset]);
+                    }
+                }
+            }
+            _ => return Err(anyhow::anyhow!("invalid block type")),
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
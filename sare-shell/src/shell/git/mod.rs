@@ -0,0 +1,763 @@
+/*!
+ * @file mod.rs
+ * @brief Native read-only access to git repositories
+ *
+ * This module reads repositories directly from the .git directory so
+ * that status, branch, log and diff --stat work without the git
+ * binary, and so that prompt segments and completion can show branch
+ * names cheaply. Nothing here ever writes to the repository.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file mod.rs
+ * @description Repository discovery, HEAD and refs (loose and
+ * packed), config lookups, revision parsing and history walks.
+ */
+
+pub mod sha1;
+pub mod inflate;
+pub mod objects;
+pub mod index;
+pub mod status;
+pub mod diff;
+
+use anyhow::Result;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use objects::{parse_tree, Commit, ObjectDatabase, ObjectId, ObjectKind, TreeEntry};
+
+/**
+ * What HEAD points at
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Head {
+    /// On a branch, which has no commits yet when the target is None
+    Branch {
+        /// Branch name without refs/heads/
+        name: String,
+        /// Commit the branch points to
+        target: Option<ObjectId>,
+    },
+    /// Detached at a commit
+    Detached(ObjectId),
+}
+
+/**
+ * Operation in progress, as shown in prompts
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepositoryState {
+    /// A merge is waiting to be committed
+    Merging,
+    /// A rebase is stopped
+    Rebasing,
+    /// A cherry-pick is stopped
+    CherryPicking,
+    /// A revert is stopped
+    Reverting,
+    /// A bisect is running
+    Bisecting,
+}
+
+impl RepositoryState {
+    /**
+     * Label used by git's prompt script
+     *
+     * @return &str - e.g. `MERGING`
+     */
+    pub fn label(&self) -> &'static str {
+        match self {
+            RepositoryState::Merging => "MERGING",
+            RepositoryState::Rebasing => "REBASE",
+            RepositoryState::CherryPicking => "CHERRY-PICKING",
+            RepositoryState::Reverting => "REVERTING",
+            RepositoryState::Bisecting => "BISECTING",
+        }
+    }
+}
+
+/**
+ * Opened repository
+ */
+pub struct Repository {
+    /// The .git directory
+    pub git_dir: PathBuf,
+    /// Directory shared between worktrees (refs, objects, config)
+    pub common_dir: PathBuf,
+    /// Top of the working tree
+    pub work_tree: PathBuf,
+    /// Object store
+    pub objects: ObjectDatabase,
+}
+
+impl Repository {
+    /**
+     * 指定したディレクトリを含むリポジトリを探す関数です
+     *
+     * ディレクトリから親へ順に.gitを探します。.gitがファイルの
+     * 場合（worktreeやサブモジュール）は「gitdir: パス」の行に
+     * 従い、commondirファイルがあれば共有ディレクトリも読みます。
+     * HEADとobjectsのあるディレクトリだけをリポジトリとみなします。
+     *
+     * @param start - 探し始めるディレクトリ
+     * @return Result<Repository> - リポジトリ、または見つからない場合のエラー
+     */
+    pub fn discover(start: &Path) -> Result<Repository> {
+        for directory in start.ancestors() {
+            let candidate = directory.join(".git");
+            let git_dir = if candidate.is_dir() {
+                candidate
+            } else if candidate.is_file() {
+                let contents = fs::read_to_string(&candidate)?;
+                let Some(target) = contents.trim().strip_prefix("gitdir:") else {
+                    continue;
+                };
+                directory.join(target.trim())
+            } else {
+                continue;
+            };
+            if !git_dir.join("HEAD").is_file() {
+                continue;
+            }
+
+            let common_dir = fs::read_to_string(git_dir.join("commondir"))
+                .ok()
+                .and_then(|relative| git_dir.join(relative.trim()).canonicalize().ok())
+                .unwrap_or_else(|| git_dir.clone());
+            if !common_dir.join("objects").is_dir() {
+                continue;
+            }
+            return Ok(Repository {
+                objects: ObjectDatabase::open(common_dir.join("objects")),
+                git_dir,
+                common_dir,
+                work_tree: directory.to_path_buf(),
+            });
+        }
+        Err(anyhow::anyhow!("not a git repository (or any of the parent directories): .git"))
+    }
+
+    /**
+     * Reads a ref file from the worktree or shared directory
+     *
+     * @param name - Ref name such as `HEAD` or `refs/heads/main`
+     * @return Option<String> - File contents, trimmed
+     */
+    fn read_ref_file(&self, name: &str) -> Option<String> {
+        let per_worktree = name == "HEAD" || !name.starts_with("refs/") || name.starts_with("refs/bisect/");
+        let directory = if per_worktree { &self.git_dir } else { &self.common_dir };
+        fs::read_to_string(directory.join(name)).ok().map(|contents| contents.trim().to_string())
+    }
+
+    /**
+     * Reads packed-refs
+     *
+     * @return BTreeMap<String, ObjectId> - Packed refs by name
+     */
+    fn packed_refs(&self) -> BTreeMap<String, ObjectId> {
+        let mut refs = BTreeMap::new();
+        let Ok(contents) = fs::read_to_string(self.common_dir.join("packed-refs")) else {
+            return refs;
+        };
+        for line in contents.lines() {
+            if line.starts_with('#') || line.starts_with('^') {
+                continue;
+            }
+            if let Some((id, name)) = line.split_once(' ') {
+                if let Some(id) = ObjectId::from_hex(id) {
+                    refs.insert(name.to_string(), id);
+                }
+            }
+        }
+        refs
+    }
+
+    /**
+     * Resolves a full ref name, following symbolic refs
+     *
+     * @param name - Ref name such as `HEAD` or `refs/remotes/origin/HEAD`
+     * @return Option<ObjectId> - Target, or None if the ref does not exist
+     */
+    pub fn resolve_ref(&self, name: &str) -> Option<ObjectId> {
+        let mut name = name.to_string();
+        for _ in 0..10 {
+            match self.read_ref_file(&name) {
+                Some(contents) => match contents.strip_prefix("ref:") {
+                    Some(target) => name = target.trim().to_string(),
+                    None => return ObjectId::from_hex(&contents),
+                },
+                None => return self.packed_refs().get(&name).copied(),
+            }
+        }
+        None
+    }
+
+    /**
+     * Reads HEAD
+     *
+     * @return Result<Head> - Current branch or detached commit
+     */
+    pub fn head(&self) -> Result<Head> {
+        let contents = self.read_ref_file("HEAD").ok_or_else(|| anyhow::anyhow!("cannot read HEAD"))?;
+        if let Some(target) = contents.strip_prefix("ref:") {
+            let target = target.trim();
+            return Ok(Head::Branch {
+                name: target.strip_prefix("refs/heads/").unwrap_or(target).to_string(),
+                target: self.resolve_ref(target),
+            });
+        }
+        ObjectId::from_hex(&contents).map(Head::Detached).ok_or_else(|| anyhow::anyhow!("HEAD is corrupt"))
+    }
+
+    /**
+     * Describes a detached HEAD from the last checkout in the HEAD reflog
+     *
+     * @param head - Commit HEAD is detached at
+     * @return Option<String> - `HEAD detached at v1.0` or `HEAD detached from abc1234`, or None without a reflog entry
+     */
+    pub fn detached_description(&self, head: &ObjectId) -> Option<String> {
+        let log = fs::read_to_string(self.git_dir.join("logs").join("HEAD")).ok()?;
+        let (target, moved_to) = log.lines().rev().find_map(|line| {
+            let (fields, message) = line.split_once('\t')?;
+            let new_id = fields.split(' ').nth(1).and_then(ObjectId::from_hex)?;
+            let rest = message.strip_prefix("checkout: moving from ")?;
+            rest.rsplit_once(" to ").map(|(_, target)| (target.to_string(), new_id))
+        })?;
+        let shown = match self.dwim_ref(&target) {
+            Some((name, id)) if id == moved_to || self.peel_tag(&id) == Some(moved_to) => {
+                name.strip_prefix("refs/tags/").or_else(|| name.strip_prefix("refs/remotes/")).unwrap_or(&name).to_string()
+            }
+            _ => moved_to.short(),
+        };
+        let relation = if moved_to == *head { "at" } else { "from" };
+        Some(format!("HEAD detached {} {}", relation, shown))
+    }
+
+    /**
+     * Branches checked out in other worktrees of the same repository
+     *
+     * @return BTreeMap<String, PathBuf> - Branch names to the worktree holding them
+     */
+    pub fn worktree_branches(&self) -> BTreeMap<String, PathBuf> {
+        let mut heads = vec![(self.common_dir.join("HEAD"), self.common_dir.parent().map(Path::to_path_buf))];
+        if let Ok(entries) = fs::read_dir(self.common_dir.join("worktrees")) {
+            for entry in entries.flatten() {
+                let work_tree = fs::read_to_string(entry.path().join("gitdir"))
+                    .ok()
+                    .and_then(|gitdir| Path::new(gitdir.trim()).parent().map(Path::to_path_buf));
+                heads.push((entry.path().join("HEAD"), work_tree));
+            }
+        }
+
+        let mut branches = BTreeMap::new();
+        for (head, work_tree) in heads {
+            let (Ok(contents), Some(work_tree)) = (fs::read_to_string(&head), work_tree) else {
+                continue;
+            };
+            if head.parent() == Some(self.git_dir.as_path()) {
+                continue;
+            }
+            if let Some(branch) = contents.trim().strip_prefix("ref: refs/heads/") {
+                branches.insert(branch.to_string(), work_tree);
+            }
+        }
+        branches
+    }
+
+    /**
+     * Commit HEAD points to
+     *
+     * @return Option<ObjectId> - Commit, or None on an unborn branch
+     */
+    pub fn head_commit(&self) -> Option<ObjectId> {
+        match self.head().ok()? {
+            Head::Branch { target, .. } => target,
+            Head::Detached(id) => Some(id),
+        }
+    }
+
+    /**
+     * Lists refs under a prefix, loose refs taking precedence over packed ones
+     *
+     * @param prefix - Prefix such as `refs/heads/`
+     * @return BTreeMap<String, String> - Ref names to their raw value (hex ID or `ref: target`)
+     */
+    pub fn references(&self, prefix: &str) -> BTreeMap<String, String> {
+        let mut refs: BTreeMap<String, String> = self
+            .packed_refs()
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(name, id)| (name, id.to_string()))
+            .collect();
+
+        let mut pending = vec![self.common_dir.join(prefix.trim_end_matches('/'))];
+        while let Some(directory) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if let (Ok(relative), Ok(contents)) = (path.strip_prefix(&self.common_dir), fs::read_to_string(&path)) {
+                    let name = relative.to_string_lossy().replace('\\', "/");
+                    refs.insert(name, contents.trim().to_string());
+                }
+            }
+        }
+        refs
+    }
+
+    /**
+     * Names of local branches
+     *
+     * @return Vec<String> - Branch names, sorted
+     */
+    pub fn branches(&self) -> Vec<String> {
+        self.references("refs/heads/").keys().map(|name| name["refs/heads/".len()..].to_string()).collect()
+    }
+
+    /**
+     * Reads a config value from .git/config
+     *
+     * @param section - Section, with a subsection as `branch.main`
+     * @param key - Key name
+     * @return Option<String> - Last value of the key
+     */
+    pub fn config(&self, section: &str, key: &str) -> Option<String> {
+        let contents = fs::read_to_string(self.common_dir.join("config")).ok()?;
+        let mut current = String::new();
+        let mut value = None;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with(';') || line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                current = match header.split_once(' ') {
+                    Some((name, subsection)) => format!("{}.{}", name.to_lowercase(), subsection.trim().trim_matches('"')),
+                    None => header.to_lowercase(),
+                };
+                continue;
+            }
+            if current == section {
+                if let Some((name, setting)) = line.split_once('=') {
+                    if name.trim().eq_ignore_ascii_case(key) {
+                        value = Some(setting.trim().trim_matches('"').to_string());
+                    }
+                }
+            }
+        }
+        value
+    }
+
+    /**
+     * Remote-tracking branch a local branch follows
+     *
+     * @param branch - Local branch name
+     * @return Option<String> - Short name such as `origin/main`
+     */
+    pub fn upstream(&self, branch: &str) -> Option<String> {
+        let section = format!("branch.{}", branch);
+        let remote = self.config(&section, "remote")?;
+        let merge = self.config(&section, "merge")?;
+        let name = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+        if remote == "." {
+            return Some(name.to_string());
+        }
+        Some(format!("{}/{}", remote, name))
+    }
+
+    /**
+     * Operation in progress
+     *
+     * @return Option<RepositoryState> - State, or None when idle
+     */
+    pub fn state(&self) -> Option<RepositoryState> {
+        let exists = |name: &str| self.git_dir.join(name).exists();
+        if exists("rebase-merge") || exists("rebase-apply") {
+            Some(RepositoryState::Rebasing)
+        } else if exists("MERGE_HEAD") {
+            Some(RepositoryState::Merging)
+        } else if exists("CHERRY_PICK_HEAD") {
+            Some(RepositoryState::CherryPicking)
+        } else if exists("REVERT_HEAD") {
+            Some(RepositoryState::Reverting)
+        } else if exists("BISECT_LOG") {
+            Some(RepositoryState::Bisecting)
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Reads and parses a commit, peeling annotated tags
+     *
+     * @param id - Commit or tag ID
+     * @return Result<Commit> - Commit or error
+     */
+    pub fn commit(&self, id: &ObjectId) -> Result<Commit> {
+        let mut id = *id;
+        for _ in 0..10 {
+            let object = self.objects.read(&id)?;
+            match object.kind {
+                ObjectKind::Commit => return Commit::parse(&object.data),
+                ObjectKind::Tag => {
+                    let text = String::from_utf8_lossy(&object.data);
+                    id = text
+                        .lines()
+                        .find_map(|line| line.strip_prefix("object "))
+                        .and_then(ObjectId::from_hex)
+                        .ok_or_else(|| anyhow::anyhow!("tag {} has no object", id))?;
+                }
+                _ => return Err(anyhow::anyhow!("object {} is a {}, not a commit", id, object.kind.name())),
+            }
+        }
+        Err(anyhow::anyhow!("tag chain too long"))
+    }
+
+    /**
+     * Reads a tree object
+     *
+     * @param id - Tree ID
+     * @return Result<Vec<TreeEntry>> - Entries
+     */
+    pub fn tree(&self, id: &ObjectId) -> Result<Vec<TreeEntry>> {
+        let object = self.objects.read(id)?;
+        if object.kind != ObjectKind::Tree {
+            return Err(anyhow::anyhow!("object {} is not a tree", id));
+        }
+        parse_tree(&object.data)
+    }
+
+    /**
+     * Lists every file in a tree recursively
+     *
+     * @param id - Root tree ID
+     * @return Result<BTreeMap<String, (u32, ObjectId)>> - Paths to mode and blob ID
+     */
+    pub fn tree_files(&self, id: &ObjectId) -> Result<BTreeMap<String, (u32, ObjectId)>> {
+        let mut files = BTreeMap::new();
+        let mut pending = vec![(String::new(), *id)];
+        while let Some((prefix, tree)) = pending.pop() {
+            for entry in self.tree(&tree)? {
+                let path = format!("{}{}", prefix, entry.name);
+                if entry.is_tree() {
+                    pending.push((format!("{}/", path), entry.id));
+                } else {
+                    files.insert(path, (entry.mode, entry.id));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /**
+     * Resolves a short ref name the way git does, trying refs/, tags, branches and remotes in turn
+     *
+     * @param name - Name such as `main`, `v1.0` or `origin/main`
+     * @return Option<(String, ObjectId)> - Full ref name and target
+     */
+    pub fn dwim_ref(&self, name: &str) -> Option<(String, ObjectId)> {
+        [
+            name.to_string(),
+            format!("refs/{}", name),
+            format!("refs/tags/{}", name),
+            format!("refs/heads/{}", name),
+            format!("refs/remotes/{}", name),
+            format!("refs/remotes/{}/HEAD", name),
+        ]
+        .into_iter()
+        .find_map(|full| self.resolve_ref(&full).map(|id| (full, id)))
+    }
+
+    /**
+     * リビジョンの指定をコミットIDに変換する関数です
+     *
+     * HEAD、ブランチ名、タグ名、リモートのブランチ名（origin/main）、
+     * 完全なref名、40桁または4桁以上の省略されたIDを受け付けます。
+     * 注釈付きタグはそれが指すコミットに置き換えます。
+     * 後ろに~Nや^（^N）を付けると、その数だけ親をたどります。
+     *
+     * @param spec - リビジョンの指定
+     * @return Result<ObjectId> - コミットIDまたはエラー
+     */
+    pub fn rev_parse(&self, spec: &str) -> Result<ObjectId> {
+        let base_end = spec.find(['~', '^']).unwrap_or(spec.len());
+        let (base, mut suffix) = spec.split_at(base_end);
+        let base = if base.is_empty() || base == "@" { "HEAD" } else { base };
+
+        let mut id = self
+            .dwim_ref(base)
+            .map(|(_, id)| id)
+            .or_else(|| ObjectId::from_hex(base).filter(|id| self.objects.contains(id)));
+        if id.is_none() {
+            id = self.objects.expand(base)?;
+        }
+        let id = id.ok_or_else(|| anyhow::anyhow!("ambiguous argument '{}': unknown revision or path not in the working tree.", spec))?;
+        let mut id = self.peel_tag(&id).unwrap_or(id);
+
+        while let Some(operator) = suffix.chars().next() {
+            suffix = &suffix[1..];
+            let digits_end = suffix.find(|c: char| !c.is_ascii_digit()).unwrap_or(suffix.len());
+            let count: usize = if digits_end == 0 { 1 } else { suffix[..digits_end].parse()? };
+            suffix = &suffix[digits_end..];
+            let commit = self.commit(&id)?;
+            id = if operator == '^' {
+                if count == 0 {
+                    continue;
+                }
+                *commit.parents.get(count - 1).ok_or_else(|| anyhow::anyhow!("revision '{}' has no parent {}", spec, count))?
+            } else {
+                let mut current = id;
+                for _ in 0..count {
+                    current = *self.commit(&current)?.parents.first().ok_or_else(|| anyhow::anyhow!("revision '{}' goes past the root commit", spec))?;
+                }
+                current
+            };
+        }
+        Ok(id)
+    }
+
+    /**
+     * 履歴をコミット日時の新しい順にたどる関数です
+     *
+     * 開始コミットから親をたどり、まだ出力していないコミットのうち
+     * コミット日時が最も新しいものを順に返します（git logの既定の
+     * 順序）。excludeに含まれるコミットとその祖先は出力しません。
+     *
+     * @param starts - 開始コミット
+     * @param exclude - 除外するコミット（..の左側）
+     * @param limit - 最大の件数
+     * @return Result<Vec<(ObjectId, Commit)>> - コミットの一覧またはエラー
+     */
+    pub fn walk(&self, starts: &[ObjectId], exclude: &[ObjectId], limit: Option<usize>) -> Result<Vec<(ObjectId, Commit)>> {
+        let hidden = self.ancestors(exclude, usize::MAX)?;
+        let mut queue = BinaryHeap::new();
+        let mut pending = HashMap::new();
+        for id in starts {
+            if !pending.contains_key(id) && !hidden.contains(id) {
+                let commit = self.commit(id)?;
+                queue.push((commit.committer.time, *id));
+                pending.insert(*id, commit);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut commits = Vec::new();
+        while let Some((_, id)) = queue.pop() {
+            if limit.is_some_and(|limit| commits.len() >= limit) {
+                break;
+            }
+            let Some(commit) = pending.remove(&id) else {
+                continue;
+            };
+            seen.insert(id);
+            for parent in &commit.parents {
+                if !seen.contains(parent) && !pending.contains_key(parent) && !hidden.contains(parent) {
+                    let parent_commit = self.commit(parent)?;
+                    queue.push((parent_commit.committer.time, *parent));
+                    pending.insert(*parent, parent_commit);
+                }
+            }
+            commits.push((id, commit));
+        }
+        Ok(commits)
+    }
+
+    /**
+     * Collects a set of commits and all of their ancestors
+     *
+     * @param starts - Starting commits
+     * @param limit - Stop after this many commits
+     * @return Result<HashSet<ObjectId>> - Reachable commits
+     */
+    pub fn ancestors(&self, starts: &[ObjectId], limit: usize) -> Result<HashSet<ObjectId>> {
+        let mut seen: HashSet<ObjectId> = HashSet::new();
+        let mut pending: Vec<ObjectId> = starts.to_vec();
+        while let Some(id) = pending.pop() {
+            if seen.len() >= limit {
+                break;
+            }
+            if seen.insert(id) {
+                pending.extend(self.commit(&id)?.parents);
+            }
+        }
+        Ok(seen)
+    }
+
+    /**
+     * Counts commits only on one side of two branches
+     *
+     * @param local - Local branch tip
+     * @param upstream - Upstream branch tip
+     * @return Result<(usize, usize)> - Commits ahead and behind
+     */
+    pub fn ahead_behind(&self, local: &ObjectId, upstream: &ObjectId) -> Result<(usize, usize)> {
+        let local_set = self.ancestors(&[*local], 100_000)?;
+        let upstream_set = self.ancestors(&[*upstream], 100_000)?;
+        Ok((local_set.difference(&upstream_set).count(), upstream_set.difference(&local_set).count()))
+    }
+
+    /**
+     * Ref names pointing at each commit, for log decorations
+     *
+     * @return BTreeMap<ObjectId, Vec<String>> - Short names by commit: `tag: name`, then branches, then remote branches
+     */
+    pub fn decorations(&self) -> BTreeMap<ObjectId, Vec<String>> {
+        let mut decorations: BTreeMap<ObjectId, Vec<String>> = BTreeMap::new();
+        for prefix in ["refs/tags/", "refs/heads/", "refs/remotes/"] {
+            for (name, value) in self.references(prefix) {
+                let Some(id) = ObjectId::from_hex(&value) else {
+                    continue;
+                };
+                let short = &name[prefix.len()..];
+                let (id, label) = if prefix == "refs/tags/" {
+                    (self.peel_tag(&id).unwrap_or(id), format!("tag: {}", short))
+                } else {
+                    (id, short.to_string())
+                };
+                decorations.entry(id).or_default().push(label);
+            }
+        }
+        decorations
+    }
+
+    /**
+     * Follows annotated tags to the object they name
+     *
+     * @param id - Tag or commit ID
+     * @return Option<ObjectId> - Tagged object, or the ID itself if it is not a tag
+     */
+    fn peel_tag(&self, id: &ObjectId) -> Option<ObjectId> {
+        let mut id = *id;
+        for _ in 0..10 {
+            let object = self.objects.read(&id).ok()?;
+            if object.kind != ObjectKind::Tag {
+                return Some(id);
+            }
+            id = String::from_utf8_lossy(&object.data).lines().find_map(|line| line.strip_prefix("object ")).and_then(ObjectId::from_hex)?;
+        }
+        None
+    }
+}
+
+/**
+ * Short description of the repository for a prompt
+ */
+#[derive(Debug, Clone)]
+pub struct PromptInfo {
+    /// Branch name, or the abbreviated commit when detached
+    pub head: String,
+    /// HEAD is detached
+    pub detached: bool,
+    /// Work tree has unstaged changes
+    pub dirty: bool,
+    /// Index has staged changes
+    pub staged: bool,
+    /// There are untracked files
+    pub untracked: bool,
+    /// Commits ahead of and behind the upstream
+    pub ahead_behind: Option<(usize, usize)>,
+    /// Operation in progress
+    pub state: Option<RepositoryState>,
+}
+
+impl std::fmt::Display for PromptInfo {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.detached {
+            write!(formatter, "({}...)", self.head)?;
+        } else {
+            write!(formatter, "{}", self.head)?;
+        }
+        let mut flags = String::new();
+        if self.dirty {
+            flags.push('*');
+        }
+        if self.staged {
+            flags.push('+');
+        }
+        if self.untracked {
+            flags.push('%');
+        }
+        if !flags.is_empty() {
+            write!(formatter, " {}", flags)?;
+        }
+        match self.ahead_behind {
+            Some((0, 0)) | None => {}
+            Some((ahead, 0)) => write!(formatter, " \u{2191}{}", ahead)?,
+            Some((0, behind)) => write!(formatter, " \u{2193}{}", behind)?,
+            Some((ahead, behind)) => write!(formatter, " \u{2191}{}\u{2193}{}", ahead, behind)?,
+        }
+        if let Some(state) = self.state {
+            write!(formatter, "|{}", state.label())?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * プロンプトに表示するリポジトリの情報を集める関数です
+ *
+ * ディレクトリがリポジトリの中にない場合はNoneを返します。
+ * ブランチ名（切り離されたHEADでは省略したID）、未ステージと
+ * ステージ済みの変更、追跡されていないファイル、上流ブランチとの
+ * 差、進行中の操作（マージ、リベースなど）を調べます。
+ *
+ * @param directory - 現在のディレクトリ
+ * @return Option<PromptInfo> - プロンプトの情報
+ */
+pub fn prompt_info(directory: &Path) -> Option<PromptInfo> {
+    let repository = Repository::discover(directory).ok()?;
+    let head = repository.head().ok()?;
+    let summary = status::Status::collect(&repository).ok();
+
+    let (name, detached, ahead_behind) = match &head {
+        Head::Branch { name, target } => {
+            let ahead_behind = target.and_then(|local| {
+                let upstream = repository.upstream(name)?;
+                let remote = repository.rev_parse(&upstream).ok()?;
+                repository.ahead_behind(&local, &remote).ok()
+            });
+            (name.clone(), false, ahead_behind)
+        }
+        Head::Detached(id) => (id.short(), true, None),
+    };
+    Some(PromptInfo {
+        head: name,
+        detached,
+        dirty: summary.as_ref().is_some_and(|summary| !summary.unstaged.is_empty()),
+        staged: summary.as_ref().is_some_and(|summary| !summary.staged.is_empty()),
+        untracked: summary.as_ref().is_some_and(|summary| !summary.untracked.is_empty()),
+        ahead_behind,
+        state: repository.state(),
+    })
+}
+
+/**
+ * Branch, remote branch and tag names for completion
+ *
+ * @param directory - Current directory
+ * @return Vec<String> - Names such as `main`, `origin/main` and `v1.0`, sorted
+ */
+pub fn ref_names(directory: &Path) -> Vec<String> {
+    let Ok(repository) = Repository::discover(directory) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = repository
+        .references("refs/")
+        .into_keys()
+        .filter_map(|name| {
+            ["refs/heads/", "refs/remotes/", "refs/tags/"]
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix))
+                .map(str::to_string)
+        })
+        .filter(|name| !name.ends_with("/HEAD"))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
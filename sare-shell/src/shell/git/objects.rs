@@ -0,0 +1,712 @@
+/*!
+ * @file objects.rs
+ * @brief Reading git objects from loose files and packfiles
+ *
+ * This module looks objects up by ID in .git/objects, inflating loose
+ * objects and resolving packfile entries through their version 2
+ * index, including offset and reference deltas. It also parses
+ * commits and trees.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file objects.rs
+ * @description Object IDs, the object database, delta application
+ * and commit/tree parsing.
+ */
+
+use anyhow::Result;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::inflate::zlib_decompress;
+use super::sha1;
+
+/**
+ * 20-byte SHA-1 object name
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId(pub [u8; 20]);
+
+impl ObjectId {
+    /**
+     * Parses a 40-digit hexadecimal ID
+     *
+     * @param hex - Hexadecimal text
+     * @return Option<ObjectId> - ID, or None if the text is not a full ID
+     */
+    pub fn from_hex(hex: &str) -> Option<ObjectId> {
+        let hex = hex.trim();
+        if hex.len() != 40 {
+            return None;
+        }
+        let mut bytes = [0u8; 20];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(hex.get(index * 2..index * 2 + 2)?, 16).ok()?;
+        }
+        Some(ObjectId(bytes))
+    }
+
+    /**
+     * Computes the ID of an object from its type and contents
+     *
+     * @param kind - Object type
+     * @param data - Object contents
+     * @return ObjectId - ID git would store the object under
+     */
+    pub fn hash(kind: ObjectKind, data: &[u8]) -> ObjectId {
+        let mut hasher = sha1::Sha1::default();
+        hasher.update(format!("{} {}\0", kind.name(), data.len()).as_bytes());
+        hasher.update(data);
+        ObjectId(hasher.finish())
+    }
+
+    /**
+     * Abbreviated ID as shown by git log --oneline
+     *
+     * @return String - First seven hexadecimal digits
+     */
+    pub fn short(&self) -> String {
+        self.to_string()[..7].to_string()
+    }
+}
+
+impl fmt::Display for ObjectId {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0 {
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Type of a git object
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectKind {
+    /// Commit
+    Commit,
+    /// Directory listing
+    Tree,
+    /// File contents
+    Blob,
+    /// Annotated tag
+    Tag,
+}
+
+impl ObjectKind {
+    /**
+     * Name used in object headers
+     *
+     * @return &str - `commit`, `tree`, `blob` or `tag`
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tree => "tree",
+            ObjectKind::Blob => "blob",
+            ObjectKind::Tag => "tag",
+        }
+    }
+
+    /**
+     * Parses an object header type name
+     *
+     * @param name - Type name
+     * @return Option<ObjectKind> - Type if known
+     */
+    fn from_name(name: &str) -> Option<ObjectKind> {
+        match name {
+            "commit" => Some(ObjectKind::Commit),
+            "tree" => Some(ObjectKind::Tree),
+            "blob" => Some(ObjectKind::Blob),
+            "tag" => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+
+    /**
+     * Maps a packfile type number
+     *
+     * @param number - Type field of a pack entry
+     * @return Option<ObjectKind> - Type for numbers 1 to 4
+     */
+    fn from_pack_type(number: u8) -> Option<ObjectKind> {
+        match number {
+            1 => Some(ObjectKind::Commit),
+            2 => Some(ObjectKind::Tree),
+            3 => Some(ObjectKind::Blob),
+            4 => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Object type and contents
+ */
+#[derive(Debug, Clone)]
+pub struct Object {
+    /// Type
+    pub kind: ObjectKind,
+    /// Contents without the header
+    pub data: Vec<u8>,
+}
+
+/**
+ * Packfile with its index
+ */
+struct Pack {
+    /// Contents of the .idx file
+    index: Vec<u8>,
+    /// Path of the .pack file
+    path: PathBuf,
+    /// Contents of the .pack file, read on first use
+    data: RefCell<Option<Vec<u8>>>,
+}
+
+impl Pack {
+    /**
+     * Opens a pack index
+     *
+     * @param index_path - Path of the .idx file
+     * @return Option<Pack> - Pack, or None if the index is not version 2
+     */
+    fn open(index_path: &Path) -> Option<Pack> {
+        let index = fs::read(index_path).ok()?;
+        if index.len() < 8 + 256 * 4 || index[..4] != [0xFF, b't', b'O', b'c'] || read_u32(&index, 4) != 2 {
+            return None;
+        }
+        Some(Pack {
+            index,
+            path: index_path.with_extension("pack"),
+            data: RefCell::new(None),
+        })
+    }
+
+    /**
+     * Number of objects in the pack
+     *
+     * @return usize - Object count from the fan-out table
+     */
+    fn object_count(&self) -> usize {
+        read_u32(&self.index, 8 + 255 * 4) as usize
+    }
+
+    /**
+     * ID of the n-th object in index order
+     *
+     * @param position - Position in the sorted ID table
+     * @return ObjectId - Object ID
+     */
+    fn id_at(&self, position: usize) -> ObjectId {
+        let start = 8 + 256 * 4 + position * 20;
+        let mut bytes = [0u8; 20];
+        bytes.copy_from_slice(&self.index[start..start + 20]);
+        ObjectId(bytes)
+    }
+
+    /**
+     * Finds the pack offset of an object
+     *
+     * @param id - Object ID
+     * @return Option<usize> - Offset in the .pack file
+     */
+    fn find(&self, id: &ObjectId) -> Option<usize> {
+        let first = id.0[0] as usize;
+        let mut low = if first == 0 { 0 } else { read_u32(&self.index, 8 + (first - 1) * 4) as usize };
+        let mut high = read_u32(&self.index, 8 + first * 4) as usize;
+        let position = loop {
+            if low >= high {
+                return None;
+            }
+            let middle = (low + high) / 2;
+            match self.id_at(middle).cmp(id) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => break middle,
+            }
+        };
+
+        let count = self.object_count();
+        let offsets = 8 + 256 * 4 + count * 24;
+        let offset = read_u32(&self.index, offsets + position * 4);
+        if offset & 0x8000_0000 == 0 {
+            return Some(offset as usize);
+        }
+        let large = offsets + count * 4 + (offset & 0x7FFF_FFFF) as usize * 8;
+        let bytes = self.index.get(large..large + 8)?;
+        Some(u64::from_be_bytes(bytes.try_into().ok()?) as usize)
+    }
+
+    /**
+     * IDs in the pack starting with a hexadecimal prefix
+     *
+     * @param prefix - Lower-case hexadecimal prefix
+     * @return Vec<ObjectId> - Matching IDs
+     */
+    fn ids_with_prefix(&self, prefix: &str) -> Vec<ObjectId> {
+        (0..self.object_count()).map(|position| self.id_at(position)).filter(|id| id.to_string().starts_with(prefix)).collect()
+    }
+}
+
+/**
+ * Reads a big-endian 32-bit number
+ *
+ * @param data - Buffer
+ * @param offset - Position of the number
+ * @return u32 - Value, or 0 past the end
+ */
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset + 4).map_or(0, |bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/**
+ * Object store of a repository
+ */
+pub struct ObjectDatabase {
+    /// .git/objects
+    directory: PathBuf,
+    /// Packs found in objects/pack
+    packs: Vec<Pack>,
+}
+
+impl ObjectDatabase {
+    /**
+     * Opens the object store and its pack indexes
+     *
+     * @param directory - Path of .git/objects
+     * @return ObjectDatabase - Object store
+     */
+    pub fn open(directory: PathBuf) -> Self {
+        let mut index_paths: Vec<PathBuf> = fs::read_dir(directory.join("pack"))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|extension| extension == "idx"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        index_paths.sort();
+        let packs = index_paths.iter().filter_map(|path| Pack::open(path)).collect();
+        ObjectDatabase { directory, packs }
+    }
+
+    /**
+     * Reads an object
+     *
+     * @param id - Object ID
+     * @return Result<Object> - Object or error if it is missing or corrupt
+     */
+    pub fn read(&self, id: &ObjectId) -> Result<Object> {
+        let hex = id.to_string();
+        let loose = self.directory.join(&hex[..2]).join(&hex[2..]);
+        if let Ok(compressed) = fs::read(&loose) {
+            let data = zlib_decompress(&compressed, compressed.len() * 2)?;
+            let header_end = data.iter().position(|byte| *byte == 0).ok_or_else(|| anyhow::anyhow!("object {} has no header", hex))?;
+            let header = String::from_utf8_lossy(&data[..header_end]);
+            let kind = header
+                .split(' ')
+                .next()
+                .and_then(ObjectKind::from_name)
+                .ok_or_else(|| anyhow::anyhow!("object {} has an unknown type", hex))?;
+            return Ok(Object {
+                kind,
+                data: data[header_end + 1..].to_vec(),
+            });
+        }
+
+        for pack in &self.packs {
+            if let Some(offset) = pack.find(id) {
+                return self.read_packed(pack, offset, 0);
+            }
+        }
+        Err(anyhow::anyhow!("object {} not found", hex))
+    }
+
+    /**
+     * Whether an object exists
+     *
+     * @param id - Object ID
+     * @return bool - True if it is stored loose or in a pack
+     */
+    pub fn contains(&self, id: &ObjectId) -> bool {
+        let hex = id.to_string();
+        self.directory.join(&hex[..2]).join(&hex[2..]).exists() || self.packs.iter().any(|pack| pack.find(id).is_some())
+    }
+
+    /**
+     * Expands an abbreviated object ID
+     *
+     * @param prefix - At least four hexadecimal digits
+     * @return Result<Option<ObjectId>> - ID, None if nothing matches, or an error if the prefix is ambiguous
+     */
+    pub fn expand(&self, prefix: &str) -> Result<Option<ObjectId>> {
+        let prefix = prefix.to_lowercase();
+        if prefix.len() < 4 || prefix.len() > 40 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+        let mut found: Vec<ObjectId> = Vec::new();
+        if let Ok(entries) = fs::read_dir(self.directory.join(&prefix[..2])) {
+            for entry in entries.flatten() {
+                let name = format!("{}{}", &prefix[..2], entry.file_name().to_string_lossy());
+                if name.starts_with(&prefix) {
+                    found.extend(ObjectId::from_hex(&name));
+                }
+            }
+        }
+        for pack in &self.packs {
+            found.extend(pack.ids_with_prefix(&prefix));
+        }
+        found.sort();
+        found.dedup();
+        match found.len() {
+            0 => Ok(None),
+            1 => Ok(Some(found[0])),
+            _ => Err(anyhow::anyhow!("short object ID {} is ambiguous", prefix)),
+        }
+    }
+
+    /**
+     * packファイル内のオブジェクトを読む関数です
+     *
+     * エントリのヘッダー（型と可変長のサイズ）を読み、通常の
+     * オブジェクトはそのまま展開します。OFS_DELTAは同じpack内の
+     * 相対位置、REF_DELTAはIDで元のオブジェクトを読み、差分を
+     * 適用します。差分の連鎖が深すぎる場合はエラーにします。
+     *
+     * @param pack - packファイル
+     * @param offset - エントリの位置
+     * @param depth - 差分の連鎖の深さ
+     * @return Result<Object> - オブジェクトまたはエラー
+     */
+    fn read_packed(&self, pack: &Pack, offset: usize, depth: usize) -> Result<Object> {
+        if depth > 64 {
+            return Err(anyhow::anyhow!("delta chain too deep"));
+        }
+        if pack.data.borrow().is_none() {
+            let data = fs::read(&pack.path).map_err(|error| anyhow::anyhow!("cannot read {}: {}", pack.path.display(), error))?;
+            *pack.data.borrow_mut() = Some(data);
+        }
+        let (kind_number, size, position, base) = {
+            let data = pack.data.borrow();
+            let data = data.as_deref().unwrap_or_default();
+            let byte_at = |position: usize| data.get(position).copied().ok_or_else(|| anyhow::anyhow!("truncated packfile"));
+
+            let mut byte = byte_at(offset)?;
+            let kind_number = (byte >> 4) & 0x07;
+            let mut size = (byte & 0x0F) as usize;
+            let mut shift = 4;
+            let mut position = offset + 1;
+            while byte & 0x80 != 0 {
+                byte = byte_at(position)?;
+                position += 1;
+                size |= ((byte & 0x7F) as usize) << shift;
+                shift += 7;
+            }
+
+            let base = match kind_number {
+                6 => {
+                    let mut byte = byte_at(position)?;
+                    position += 1;
+                    let mut distance = (byte & 0x7F) as usize;
+                    while byte & 0x80 != 0 {
+                        byte = byte_at(position)?;
+                        position += 1;
+                        distance = ((distance + 1) << 7) | (byte & 0x7F) as usize;
+                    }
+                    Some(DeltaBase::Offset(offset.checked_sub(distance).ok_or_else(|| anyhow::anyhow!("invalid delta offset"))?))
+                }
+                7 => {
+                    let bytes = data.get(position..position + 20).ok_or_else(|| anyhow::anyhow!("truncated packfile"))?;
+                    position += 20;
+                    let mut id = [0u8; 20];
+                    id.copy_from_slice(bytes);
+                    Some(DeltaBase::Id(ObjectId(id)))
+                }
+                _ => None,
+            };
+            (kind_number, size, position, base)
+        };
+
+        let contents = {
+            let data = pack.data.borrow();
+            let data = data.as_deref().unwrap_or_default();
+            zlib_decompress(data.get(position..).unwrap_or_default(), size)?
+        };
+
+        match base {
+            None => Ok(Object {
+                kind: ObjectKind::from_pack_type(kind_number).ok_or_else(|| anyhow::anyhow!("unknown pack object type {}", kind_number))?,
+                data: contents,
+            }),
+            Some(base) => {
+                let source = match base {
+                    DeltaBase::Offset(base_offset) => self.read_packed(pack, base_offset, depth + 1)?,
+                    DeltaBase::Id(id) => match pack.find(&id) {
+                        Some(base_offset) => self.read_packed(pack, base_offset, depth + 1)?,
+                        None => self.read(&id)?,
+                    },
+                };
+                Ok(Object {
+                    kind: source.kind,
+                    data: apply_delta(&source.data, &contents)?,
+                })
+            }
+        }
+    }
+}
+
+/**
+ * Base object of a delta entry
+ */
+enum DeltaBase {
+    /// Entry at an earlier offset in the same pack
+    Offset(usize),
+    /// Object named by ID
+    Id(ObjectId),
+}
+
+/**
+ * Reads a delta header size
+ *
+ * @param delta - Delta data
+ * @param position - Read position, advanced past the number
+ * @return Result<usize> - Size
+ */
+fn delta_size(delta: &[u8], position: &mut usize) -> Result<usize> {
+    let mut size = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *delta.get(*position).ok_or_else(|| anyhow::anyhow!("truncated delta"))?;
+        *position += 1;
+        size |= ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+/**
+ * 差分（delta）を元のオブジェクトに適用する関数です
+ *
+ * 差分は元のサイズと結果のサイズの後に命令が続きます。最上位
+ * ビットが立った命令は元のデータからのコピーで、下位4ビットが
+ * オフセット、次の3ビットがサイズのバイトを示します（サイズ0は
+ * 0x10000）。それ以外の命令はその数だけ後続のバイトを挿入します。
+ *
+ * @param source - 元のオブジェクトの内容
+ * @param delta - 差分データ
+ * @return Result<Vec<u8>> - 結果の内容またはエラー
+ */
+fn apply_delta(source: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let mut position = 0;
+    let source_size = delta_size(delta, &mut position)?;
+    if source_size != source.len() {
+        return Err(anyhow::anyhow!("delta base size mismatch"));
+    }
+    let target_size = delta_size(delta, &mut position)?;
+    let mut target = Vec::with_capacity(target_size);
+
+    while position < delta.len() {
+        let command = delta[position];
+        position += 1;
+        if command & 0x80 != 0 {
+            let mut offset = 0usize;
+            let mut size = 0usize;
+            for bit in 0..7 {
+                if command & (1 << bit) != 0 {
+                    let byte = *delta.get(position).ok_or_else(|| anyhow::anyhow!("truncated delta"))? as usize;
+                    position += 1;
+                    if bit < 4 {
+                        offset |= byte << (bit * 8);
+                    } else {
+                        size |= byte << ((bit - 4) * 8);
+                    }
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            let chunk = source.get(offset..offset + size).ok_or_else(|| anyhow::anyhow!("delta copy out of range"))?;
+            target.extend_from_slice(chunk);
+        } else if command != 0 {
+            let chunk = delta.get(position..position + command as usize).ok_or_else(|| anyhow::anyhow!("truncated delta"))?;
+            target.extend_from_slice(chunk);
+            position += command as usize;
+        } else {
+            return Err(anyhow::anyhow!("invalid delta command"));
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(anyhow::anyhow!("delta result size mismatch"));
+    }
+    Ok(target)
+}
+
+/**
+ * Name, email and time of an author or committer
+ */
+#[derive(Debug, Clone)]
+pub struct Signature {
+    /// Name
+    pub name: String,
+    /// Email address
+    pub email: String,
+    /// Seconds since the epoch
+    pub time: i64,
+    /// Offset from UTC in seconds
+    pub offset: i32,
+}
+
+impl Signature {
+    /**
+     * Parses `Name <email> 1700000000 +0900`
+     *
+     * @param text - Signature line after the field name
+     * @return Option<Signature> - Signature if well formed
+     */
+    fn parse(text: &str) -> Option<Signature> {
+        let open = text.find('<')?;
+        let close = text[open..].find('>')? + open;
+        let mut rest = text[close + 1..].split_whitespace();
+        let time = rest.next()?.parse().ok()?;
+        let zone = rest.next().unwrap_or("+0000");
+        let sign = if zone.starts_with('-') { -1 } else { 1 };
+        let digits: i32 = zone.trim_start_matches(['+', '-']).parse().unwrap_or(0);
+        Some(Signature {
+            name: text[..open].trim().to_string(),
+            email: text[open + 1..close].to_string(),
+            time,
+            offset: sign * (digits / 100 * 3600 + digits % 100 * 60),
+        })
+    }
+
+    /**
+     * Formats the time the way git log does
+     *
+     * @return String - e.g. `Mon Jan 1 12:00:00 2024 +0900`
+     */
+    pub fn date(&self) -> String {
+        let zone = chrono::FixedOffset::east_opt(self.offset).unwrap_or_else(|| chrono::FixedOffset::east_opt(0).expect("zero offset"));
+        match chrono::DateTime::from_timestamp(self.time, 0) {
+            Some(time) => time.with_timezone(&zone).format("%a %b %-d %H:%M:%S %Y %z").to_string(),
+            None => self.time.to_string(),
+        }
+    }
+}
+
+/**
+ * Parsed commit
+ */
+#[derive(Debug, Clone)]
+pub struct Commit {
+    /// Root tree
+    pub tree: ObjectId,
+    /// Parent commits
+    pub parents: Vec<ObjectId>,
+    /// Author
+    pub author: Signature,
+    /// Committer
+    pub committer: Signature,
+    /// Message
+    pub message: String,
+}
+
+impl Commit {
+    /**
+     * Parses commit contents
+     *
+     * @param data - Commit object contents
+     * @return Result<Commit> - Commit or error
+     */
+    pub fn parse(data: &[u8]) -> Result<Commit> {
+        let text = String::from_utf8_lossy(data);
+        let (headers, message) = text.split_once("\n\n").unwrap_or((&text, ""));
+        let mut tree = None;
+        let mut parents = Vec::new();
+        let mut author = None;
+        let mut committer = None;
+        for line in headers.lines() {
+            match line.split_once(' ') {
+                Some(("tree", value)) => tree = ObjectId::from_hex(value),
+                Some(("parent", value)) => parents.extend(ObjectId::from_hex(value)),
+                Some(("author", value)) => author = Signature::parse(value),
+                Some(("committer", value)) => committer = Signature::parse(value),
+                _ => {}
+            }
+        }
+        let author = author.ok_or_else(|| anyhow::anyhow!("commit has no author"))?;
+        Ok(Commit {
+            tree: tree.ok_or_else(|| anyhow::anyhow!("commit has no tree"))?,
+            parents,
+            committer: committer.unwrap_or_else(|| author.clone()),
+            author,
+            message: message.to_string(),
+        })
+    }
+
+    /**
+     * First line of the message
+     *
+     * @return &str - Subject
+     */
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or("")
+    }
+}
+
+/**
+ * Entry of a tree object
+ */
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    /// File mode, e.g. 0o100644 or 0o40000 for a subtree
+    pub mode: u32,
+    /// Entry name
+    pub name: String,
+    /// Blob, tree or commit (submodule) ID
+    pub id: ObjectId,
+}
+
+impl TreeEntry {
+    /**
+     * Whether the entry is a subtree
+     *
+     * @return bool - True for directories
+     */
+    pub fn is_tree(&self) -> bool {
+        self.mode & 0o170000 == 0o040000
+    }
+}
+
+/**
+ * Parses tree contents
+ *
+ * @param data - Tree object contents
+ * @return Result<Vec<TreeEntry>> - Entries in stored order
+ */
+pub fn parse_tree(data: &[u8]) -> Result<Vec<TreeEntry>> {
+    let mut entries = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let space = data[position..].iter().position(|byte| *byte == b' ').ok_or_else(|| anyhow::anyhow!("malformed tree"))? + position;
+        let nul = data[space..].iter().position(|byte| *byte == 0).ok_or_else(|| anyhow::anyhow!("malformed tree"))? + space;
+        let mode = u32::from_str_radix(&String::from_utf8_lossy(&data[position..space]), 8).map_err(|_| anyhow::anyhow!("malformed tree mode"))?;
+        let id_bytes = data.get(nul + 1..nul + 21).ok_or_else(|| anyhow::anyhow!("malformed tree"))?;
+        let mut id = [0u8; 20];
+        id.copy_from_slice(id_bytes);
+        entries.push(TreeEntry {
+            mode,
+            name: String::from_utf8_lossy(&data[space + 1..nul]).to_string(),
+            id: ObjectId(id),
+        });
+        position = nul + 21;
+    }
+    Ok(entries)
+}
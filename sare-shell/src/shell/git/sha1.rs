@@ -0,0 +1,120 @@
+/*!
+ * @file sha1.rs
+ * @brief SHA-1 digests for git object IDs
+ *
+ * This module implements SHA-1 (FIPS 180-4) so that working tree
+ * files can be hashed as blobs and compared with the index without
+ * the git binary.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file sha1.rs
+ * @description Streaming SHA-1 used to compute git blob IDs.
+ */
+
+/**
+ * Streaming SHA-1 state
+ */
+#[derive(Debug, Clone)]
+pub struct Sha1 {
+    /// Chaining values h0..h4
+    state: [u32; 5],
+    /// Bytes not yet forming a full 64-byte block
+    pending: Vec<u8>,
+    /// Total message length in bytes
+    length: u64,
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1 {
+            state: [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0],
+            pending: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+}
+
+impl Sha1 {
+    /**
+     * Adds message bytes
+     *
+     * @param data - Bytes to hash
+     */
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        if !self.pending.is_empty() {
+            let take = (64 - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() < 64 {
+                return;
+            }
+            let block: [u8; 64] = self.pending[..].try_into().unwrap_or([0; 64]);
+            self.compress(&block);
+            self.pending.clear();
+        }
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block.try_into().unwrap_or(&[0; 64]));
+        }
+        self.pending.extend_from_slice(blocks.remainder());
+    }
+
+    /**
+     * Pads the message and returns the digest
+     *
+     * @return [u8; 20] - SHA-1 digest
+     */
+    pub fn finish(mut self) -> [u8; 20] {
+        let bit_length = self.length.wrapping_mul(8);
+        let mut padding = vec![0x80u8];
+        let used = (self.pending.len() + 1) % 64;
+        padding.resize(1 + if used <= 56 { 56 - used } else { 120 - used }, 0);
+        padding.extend_from_slice(&bit_length.to_be_bytes());
+        let length = self.length;
+        self.update(&padding);
+        self.length = length;
+
+        let mut digest = [0u8; 20];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    /**
+     * Processes one 64-byte block
+     *
+     * @param block - Message block
+     */
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut words = [0u32; 80];
+        for (index, chunk) in block.chunks_exact(4).enumerate() {
+            words[index] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for index in 16..80 {
+            words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, added) in self.state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+}
@@ -0,0 +1,410 @@
+/*!
+ * @file status.rs
+ * @brief Comparing HEAD, the index and the working tree
+ *
+ * This module works out what git status reports: changes staged for
+ * commit (HEAD tree against the index), changes not staged (index
+ * against the working tree) and untracked files, honouring .gitignore,
+ * .git/info/exclude and the user's global ignore file.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file status.rs
+ * @description Three-way status with cached stat checks, blob hashing
+ * and untracked directory collapsing.
+ */
+
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use super::index::{read_index, Index, IndexEntry};
+use super::objects::{ObjectId, ObjectKind};
+use super::Repository;
+use crate::shell::commands::find::{parse_ignore_rules, read_ignore_rules, IgnoreRule};
+
+/**
+ * How a path changed
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    /// Path is new
+    Added,
+    /// Contents or mode changed
+    Modified,
+    /// Path was removed
+    Deleted,
+    /// File became a symlink or the other way round
+    TypeChanged,
+}
+
+impl ChangeKind {
+    /**
+     * Label used in git status long format
+     *
+     * @return &str - e.g. `modified:`
+     */
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "new file:",
+            ChangeKind::Modified => "modified:",
+            ChangeKind::Deleted => "deleted:",
+            ChangeKind::TypeChanged => "typechange:",
+        }
+    }
+
+    /**
+     * Letter used in git status short format
+     *
+     * @return char - `A`, `M`, `D` or `T`
+     */
+    pub fn letter(&self) -> char {
+        match self {
+            ChangeKind::Added => 'A',
+            ChangeKind::Modified => 'M',
+            ChangeKind::Deleted => 'D',
+            ChangeKind::TypeChanged => 'T',
+        }
+    }
+}
+
+/**
+ * One changed path
+ */
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// Path relative to the work tree
+    pub path: String,
+    /// Kind of change
+    pub kind: ChangeKind,
+}
+
+/**
+ * Result of comparing HEAD, the index and the working tree
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    /// HEAD against the index
+    pub staged: Vec<Change>,
+    /// Index against the working tree
+    pub unstaged: Vec<Change>,
+    /// Paths with merge conflicts
+    pub unmerged: Vec<String>,
+    /// Untracked files, with `/` after collapsed directories
+    pub untracked: Vec<String>,
+    /// The parsed index, reused by diff
+    pub index: Index,
+}
+
+/**
+ * Mode git would record for a working tree file
+ *
+ * @param metadata - Metadata from symlink_metadata
+ * @return u32 - 0o120000, 0o100755 or 0o100644
+ */
+pub fn worktree_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.file_type().is_symlink() {
+        0o120000
+    } else if metadata.permissions().mode() & 0o111 != 0 {
+        0o100755
+    } else {
+        0o100644
+    }
+}
+
+/**
+ * Hashes a working tree file as a blob
+ *
+ * @param path - File path
+ * @param metadata - Metadata from symlink_metadata
+ * @return Result<ObjectId> - Blob ID
+ */
+pub fn hash_worktree_file(path: &Path, metadata: &fs::Metadata) -> Result<ObjectId> {
+    Ok(ObjectId::hash(ObjectKind::Blob, &read_worktree_file(path, metadata)?))
+}
+
+/**
+ * Reads a working tree file the way git stores it
+ *
+ * @param path - File path
+ * @param metadata - Metadata from symlink_metadata
+ * @return Result<Vec<u8>> - File contents, or the link target for symlinks
+ */
+pub fn read_worktree_file(path: &Path, metadata: &fs::Metadata) -> Result<Vec<u8>> {
+    if metadata.file_type().is_symlink() {
+        Ok(fs::read_link(path)?.to_string_lossy().as_bytes().to_vec())
+    } else {
+        Ok(fs::read(path)?)
+    }
+}
+
+impl Status {
+    /**
+     * Computes the status of a repository
+     *
+     * @param repository - Repository
+     * @return Result<Status> - Staged, unstaged, unmerged and untracked paths
+     */
+    pub fn collect(repository: &Repository) -> Result<Status> {
+        let index = read_index(&repository.git_dir)?;
+        let head_files = match repository.head_commit() {
+            Some(id) => repository.tree_files(&repository.commit(&id)?.tree)?,
+            None => BTreeMap::new(),
+        };
+
+        let unmerged: BTreeSet<String> = index.entries.iter().filter(|entry| entry.stage != 0).map(|entry| entry.path.clone()).collect();
+        let staged = Self::staged_changes(&head_files, &index, &unmerged);
+        let unstaged = Self::unstaged_changes(repository, &index)?;
+        let untracked = Self::untracked_files(repository, &index);
+        Ok(Status {
+            staged,
+            unstaged,
+            unmerged: unmerged.into_iter().collect(),
+            untracked,
+            index,
+        })
+    }
+
+    /**
+     * Compares the HEAD tree with the index
+     *
+     * @param head_files - Files of the HEAD tree
+     * @param index - Index
+     * @param unmerged - Conflicted paths, which are reported separately
+     * @return Vec<Change> - Staged changes sorted by path
+     */
+    fn staged_changes(head_files: &BTreeMap<String, (u32, ObjectId)>, index: &Index, unmerged: &BTreeSet<String>) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for entry in index.entries.iter().filter(|entry| entry.stage == 0) {
+            let kind = match head_files.get(&entry.path) {
+                None => Some(ChangeKind::Added),
+                Some((mode, _)) if (mode & 0o170000) != (entry.mode & 0o170000) => Some(ChangeKind::TypeChanged),
+                Some((mode, id)) if *mode != entry.mode || *id != entry.id => Some(ChangeKind::Modified),
+                Some(_) => None,
+            };
+            if let Some(kind) = kind {
+                changes.push(Change { path: entry.path.clone(), kind });
+            }
+        }
+        for path in head_files.keys() {
+            if index.get(path).is_none() && !unmerged.contains(path) {
+                changes.push(Change {
+                    path: path.clone(),
+                    kind: ChangeKind::Deleted,
+                });
+            }
+        }
+        changes.sort_by(|left, right| left.path.cmp(&right.path));
+        changes
+    }
+
+    /**
+     * インデックスと作業ツリーを比べる関数です
+     *
+     * ステージ0の各エントリについてファイルのstat情報を取得し、
+     * 種類や実行ビットが変わっていれば変更とします。更新時刻と
+     * サイズがインデックスの記録と一致し、かつインデックスより前に
+     * 更新されていれば（racy-cleanでなければ）内容を読まずに
+     * 変更なしとみなします。それ以外は内容をblobとしてハッシュし、
+     * IDを比べます。サブモジュール（gitlink）は調べません。
+     *
+     * @param repository - リポジトリ
+     * @param index - インデックス
+     * @return Result<Vec<Change>> - 未ステージの変更の一覧
+     */
+    fn unstaged_changes(repository: &Repository, index: &Index) -> Result<Vec<Change>> {
+        let trust_mode = repository.config("core", "filemode").is_none_or(|value| value != "false");
+        let mut changes = Vec::new();
+        for entry in index.entries.iter().filter(|entry| entry.stage == 0 && entry.mode != 0o160000) {
+            let path = repository.work_tree.join(&entry.path);
+            let kind = match fs::symlink_metadata(&path) {
+                Err(_) => Some(ChangeKind::Deleted),
+                Ok(metadata) if metadata.is_dir() => Some(ChangeKind::Deleted),
+                Ok(metadata) => Self::compare_entry(entry, &path, &metadata, index.mtime, trust_mode)?,
+            };
+            if let Some(kind) = kind {
+                changes.push(Change { path: entry.path.clone(), kind });
+            }
+        }
+        Ok(changes)
+    }
+
+    /**
+     * Compares one index entry with the file on disk
+     *
+     * @param entry - Index entry
+     * @param path - Absolute path of the file
+     * @param metadata - Metadata from symlink_metadata
+     * @param index_mtime - Modification time of the index file
+     * @param trust_mode - Whether executable bit changes count (core.filemode)
+     * @return Result<Option<ChangeKind>> - Kind of change, or None if unchanged
+     */
+    fn compare_entry(entry: &IndexEntry, path: &Path, metadata: &fs::Metadata, index_mtime: i64, trust_mode: bool) -> Result<Option<ChangeKind>> {
+        let mode = worktree_mode(metadata);
+        if (mode & 0o170000) != (entry.mode & 0o170000) {
+            return Ok(Some(ChangeKind::TypeChanged));
+        }
+        if trust_mode && mode != entry.mode {
+            return Ok(Some(ChangeKind::Modified));
+        }
+
+        let stat_matches = entry.mtime == metadata.mtime() as u32 && entry.mtime_nanos == metadata.mtime_nsec() as u32 && entry.size == metadata.size() as u32;
+        if stat_matches && metadata.mtime() < index_mtime {
+            return Ok(None);
+        }
+        if !stat_matches && entry.size != metadata.size() as u32 {
+            return Ok(Some(ChangeKind::Modified));
+        }
+        let id = hash_worktree_file(path, metadata)?;
+        Ok((id != entry.id).then_some(ChangeKind::Modified))
+    }
+
+    /**
+     * Lists untracked files, collapsing directories that hold no tracked files
+     *
+     * @param repository - Repository
+     * @param index - Index
+     * @return Vec<String> - Untracked paths sorted by name
+     */
+    fn untracked_files(repository: &Repository, index: &Index) -> Vec<String> {
+        let mut base_rules = Vec::new();
+        let global = repository
+            .config("core", "excludesfile")
+            .map(|path| match path.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+                None => path.into(),
+            })
+            .or_else(|| dirs::config_dir().map(|directory| directory.join("git").join("ignore")));
+        for path in global.into_iter().chain([repository.common_dir.join("info").join("exclude")]) {
+            if let Ok(contents) = fs::read_to_string(path) {
+                base_rules.push((String::new(), parse_ignore_rules(&contents)));
+            }
+        }
+
+        let mut walker = UntrackedWalker {
+            index,
+            rules: base_rules,
+        };
+        let mut untracked = Vec::new();
+        walker.walk(&repository.work_tree, "", &mut untracked);
+        untracked
+    }
+}
+
+/**
+ * Recursive search for untracked files
+ */
+struct UntrackedWalker<'a> {
+    /// Index, sorted by path
+    index: &'a Index,
+    /// Ignore rules in force, with the directory prefix they are relative to
+    rules: Vec<(String, Vec<IgnoreRule>)>,
+}
+
+impl UntrackedWalker<'_> {
+    /**
+     * Whether any index entry lies under a directory
+     *
+     * @param prefix - Directory path ending in `/`
+     * @return bool - True if the directory holds tracked files
+     */
+    fn has_tracked(&self, prefix: &str) -> bool {
+        let start = self.index.entries.partition_point(|entry| entry.path.as_str() < prefix);
+        self.index.entries.get(start).is_some_and(|entry| entry.path.starts_with(prefix))
+    }
+
+    /**
+     * Whether a path is tracked at any stage
+     *
+     * @param path - Path relative to the work tree
+     * @return bool - True if the index has the path
+     */
+    fn is_tracked(&self, path: &str) -> bool {
+        let start = self.index.entries.partition_point(|entry| entry.path.as_str() < path);
+        self.index.entries.get(start).is_some_and(|entry| entry.path == path)
+    }
+
+    /**
+     * Applies the ignore rules in force, the last matching rule winning
+     *
+     * @param path - Path relative to the work tree
+     * @param name - Final component
+     * @param is_dir - Whether the path is a directory
+     * @return bool - True if the path is ignored
+     */
+    fn is_ignored(&self, path: &str, name: &str, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for (prefix, rules) in &self.rules {
+            let Some(relative) = path.strip_prefix(prefix.as_str()) else {
+                continue;
+            };
+            for rule in rules {
+                if let Some(ignore) = rule.matches(relative, name, is_dir) {
+                    ignored = ignore;
+                }
+            }
+        }
+        ignored
+    }
+
+    /**
+     * ディレクトリを再帰的に調べて未追跡のファイルを集める関数です
+     *
+     * ディレクトリの.gitignoreを規則に加えてから、名前順に各項目を
+     * 調べます。.gitと無視された項目は飛ばします。追跡されている
+     * ファイルを含まないディレクトリは、中に無視されていないファイルが
+     * 一つでもあれば「dir/」としてまとめて報告します（git statusの
+     * 既定の動作）。入れ子のリポジトリも同じくまとめて報告します。
+     *
+     * @param directory - 調べるディレクトリ
+     * @param prefix - 作業ツリーからの相対パス（空か「/」で終わる）
+     * @param untracked - 見つけたパスの追加先
+     */
+    fn walk(&mut self, directory: &Path, prefix: &str, untracked: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        self.rules.push((prefix.to_string(), read_ignore_rules(directory)));
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == ".git" {
+                continue;
+            }
+            let path = format!("{}{}", prefix, name);
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            if self.is_ignored(&path, &name, is_dir) {
+                continue;
+            }
+
+            if !is_dir {
+                if !self.is_tracked(&path) {
+                    untracked.push(path);
+                }
+                continue;
+            }
+            let child_prefix = format!("{}/", path);
+            if self.is_tracked(&path) {
+                continue;
+            }
+            if self.has_tracked(&child_prefix) {
+                self.walk(&entry.path(), &child_prefix, untracked);
+                continue;
+            }
+            if entry.path().join(".git").exists() {
+                untracked.push(child_prefix);
+                continue;
+            }
+            let mut inner = Vec::new();
+            self.walk(&entry.path(), &child_prefix, &mut inner);
+            if !inner.is_empty() {
+                untracked.push(child_prefix);
+            }
+        }
+        self.rules.pop();
+    }
+}
@@ -0,0 +1,103 @@
+/*!
+ * @file io.rs
+ * @brief Low-level file descriptor helpers
+ * 
+ * This module mirrors the pipe and descriptor helpers from the
+ * terminal crate's `terminal/io/utils.rs` so that the shell can
+ * build pipes without depending on the terminal emulator.
+ * 
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file io.rs
+ * @description File descriptor utilities used by subshells and
+ * process substitution.
+ */
+
+use anyhow::Result;
+use std::io::{Read, Write};
+use std::os::unix::io::FromRawFd;
+
+/**
+ * I/O utilities for raw file descriptor handling
+ * 
+ * Provides pipe creation and descriptor management for code paths
+ * that fork and cannot rely on std::process plumbing.
+ */
+pub struct IoUtils;
+
+impl IoUtils {
+    /**
+     * Creates a pipe
+     * 
+     * The descriptors are deliberately not close-on-exec so that
+     * `/dev/fd/N` paths stay valid in exec'd children.
+     * 
+     * @return Result<(i32, i32)> - Read and write file descriptors or error
+     */
+    pub fn create_pipe() -> Result<(i32, i32)> {
+        let mut pipe_array = [0; 2];
+        unsafe {
+            if libc::pipe(pipe_array.as_mut_ptr()) != 0 {
+                return Err(anyhow::anyhow!("Failed to create pipe: {}", std::io::Error::last_os_error()));
+            }
+        }
+        Ok((pipe_array[0], pipe_array[1]))
+    }
+    
+    /**
+     * Closes a file descriptor
+     * 
+     * @param fd - File descriptor to close
+     * @return Result<()> - Success or error
+     */
+    pub fn close_fd(fd: i32) -> Result<()> {
+        unsafe {
+            if libc::close(fd) < 0 {
+                return Err(anyhow::anyhow!("Failed to close file descriptor {}", fd));
+            }
+        }
+        Ok(())
+    }
+    
+    /**
+     * Duplicates a file descriptor
+     * 
+     * @param old_fd - Old file descriptor
+     * @param new_fd - New file descriptor
+     * @return Result<()> - Success or error
+     */
+    pub fn duplicate_fd(old_fd: i32, new_fd: i32) -> Result<()> {
+        unsafe {
+            if libc::dup2(old_fd, new_fd) < 0 {
+                return Err(anyhow::anyhow!("Failed to duplicate file descriptor"));
+            }
+        }
+        Ok(())
+    }
+    
+    /**
+     * Reads a descriptor to EOF and closes it
+     * 
+     * @param fd - File descriptor to drain (ownership is taken)
+     * @return Result<String> - Data read, lossily decoded
+     */
+    pub fn read_to_end(fd: i32) -> Result<String> {
+        let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).to_string())
+    }
+    
+    /**
+     * Writes all data to a descriptor and closes it
+     * 
+     * @param fd - File descriptor to write to (ownership is taken)
+     * @param data - Data to write
+     * @return Result<()> - Success or error
+     */
+    pub fn write_all(fd: i32, data: &[u8]) -> Result<()> {
+        let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+        file.write_all(data)?;
+        Ok(())
+    }
+}
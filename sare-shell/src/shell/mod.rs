@@ -17,17 +17,34 @@ pub mod executor;
 pub mod job;
 pub mod builtins;
 pub mod commands;
+pub mod io;
+pub mod subshell;
+pub mod git;
+pub mod dirstack;
+pub mod resources;
+#[cfg(feature = "security")]
+pub mod sandbox;
+#[cfg(feature = "security")]
+pub mod policy;
 
 use anyhow::Result;
 use std::path::PathBuf;
 use std::process::Command;
 use std::collections::HashMap;
+use std::rc::Rc;
 use job::{JobManager, SignalHandler};
-use parser::{CommandParser, parse_pipeline, CommandPipeline};
+use parser::{CommandParser, parse_pipeline, CommandPipeline, ChainOperator, CompoundCommand, ParsedCommand, SubstitutionDirection, SubstitutionSlot};
 use executor::CommandExecutor;
 use builtins::BuiltinCommands;
 use commands::{CommandRegistry, CommandHandler, CommandResult};
+use commands::records::Table;
+use commands::debugger::DebugSession;
+use commands::cargo::Diagnostic;
 use crate::history::HistoryManager;
+use dirstack::{DirectoryStack, FrecencyDatabase};
+use resources::{PipelineTimer, ResourceLimits, TimeoutPolicy};
+#[cfg(feature = "security")]
+use sandbox::SandboxPolicy;
 
 /**
  * Result of background command execution
@@ -59,8 +76,8 @@ pub struct Shell {
     executor: CommandExecutor,
     /// Built-in command handlers
     builtins: BuiltinCommands,
-    /// Command registry for all built-in commands
-    command_registry: CommandRegistry,
+    /// Command registry for all built-in commands, shared so handlers can borrow the shell mutably
+    command_registry: Rc<CommandRegistry>,
     /// Signal handler for process control
     signal_handler: SignalHandler,
     /// Current command input buffer
@@ -71,6 +88,29 @@ pub struct Shell {
     environment: HashMap<String, String>,
     /// Command history manager
     history_manager: HistoryManager,
+    /// Output of the previous pipeline stage, available to the running builtin
+    pipeline_input: Option<String>,
+    /// Records emitted by the previous pipeline stage, available to the running builtin
+    pipeline_records: Option<Table>,
+    /// Records emitted by the running unit alongside its text output
+    emitted_records: Option<Table>,
+    /// Exit code of the last executed pipeline
+    last_exit_code: i32,
+    /// Directory that was current before the last directory change
+    previous_path: Option<PathBuf>,
+    /// Directory stack for pushd and popd
+    directory_stack: DirectoryStack,
+    /// Frecency database behind the z command
+    frecency_database: FrecencyDatabase,
+    /// Whether the running unit's output reaches the terminal rather than a pipe or file
+    output_is_terminal: bool,
+    /// gdb or lldb-dap session of the debug command
+    debug_session: Option<DebugSession>,
+    /// Diagnostics of the last cargo build, listed by cargo errors
+    build_diagnostics: Vec<Diagnostic>,
+    /// Security checks and audit logging, when enabled in the configuration
+    #[cfg(feature = "security")]
+    policy: Option<policy::PolicyLayer>,
 }
 
 impl Shell {
@@ -90,18 +130,36 @@ impl Shell {
             environment.insert(key, value);
         }
         
+        let history_manager = HistoryManager::new()?;
+        let frecency_database = FrecencyDatabase::new(history_manager.history_file.with_file_name(".sare_z"))?;
+        
         Ok(Self {
             current_path,
             job_manager: JobManager::new(),
             parser: CommandParser::new(),
             executor: CommandExecutor::new(),
             builtins: BuiltinCommands::new(),
-            command_registry: CommandRegistry::new(),
+            command_registry: Rc::new(CommandRegistry::new()),
             signal_handler: SignalHandler::new(),
-            history_manager: HistoryManager::new()?,
+            history_manager,
             input_buffer: String::new(),
             output_history: Vec::new(),
             environment,
+            pipeline_input: None,
+            pipeline_records: None,
+            emitted_records: None,
+            last_exit_code: 0,
+            previous_path: std::env::var("OLDPWD").ok().map(PathBuf::from),
+            directory_stack: DirectoryStack::new(),
+            frecency_database,
+            output_is_terminal: true,
+            debug_session: None,
+            build_diagnostics: Vec::new(),
+            #[cfg(feature = "security")]
+            policy: match crate::config::ShellConfig::load().security {
+                security if security.enabled => Some(policy::PolicyLayer::new(&security)?),
+                _ => None,
+            },
         })
     }
     
@@ -114,6 +172,17 @@ impl Shell {
         &self.current_path
     }
     
+    /**
+     * Git segment for the prompt, in the style of git's __git_ps1
+     * 
+     * Reads the repository directly, so it works without the git binary.
+     * 
+     * @return Option<String> - e.g. `main *+ ↑2|MERGING`, or None outside a repository
+     */
+    pub fn git_prompt_segment(&self) -> Option<String> {
+        git::prompt_info(&self.current_path).map(|info| info.to_string())
+    }
+    
     /**
      * Adds a character to the input buffer
      * 
@@ -272,32 +341,23 @@ impl Shell {
         Ok(())
     }
     
-    	/**
-	 * パースされたコマンドを実行する関数です
-	 * 
-	 * ビルトインコマンドを最初に試し、見つからない場合は
-	 * 外部コマンドとして実行します。
-	 * 
-	 * command_registry.execute_safe()でビルトインコマンドを
-	 * 実行し、失敗した場合はexecutor.execute()で外部コマンドを
-	 * 実行します。借用チェックの問題を避けるため、必要に応じて
-	 * コマンドをクローンします。
-	 * 
-	 * @param parsed - 実行するパースされたコマンド
-	 * @return Result<String> - コマンドの出力またはエラー
-	 */
+    /**
+     * パースされたコマンドを実行する関数です
+     * 
+     * ビルトインコマンドを最初に試し、見つからない場合は
+     * 外部コマンドとして実行します。
+     * 
+     * run_unit()に処理を委譲し、ビルトインはコマンドレジストリで、
+     * それ以外は外部コマンドとして実行します。終了コードは
+     * last_exit_codeに記録されます。
+     * 
+     * @param parsed - 実行するパースされたコマンド
+     * @return Result<String> - コマンドの出力またはエラー
+     */
     async fn execute_parsed_command(&mut self, parsed: &crate::shell::parser::ParsedCommand) -> Result<String> {
-        // Try built-in command first, fall back to external
-        let command_name = parsed.command.clone();
-        
-        // Try to execute as built-in command, fall back to external
-        match self.command_registry.execute_safe(parsed) {
-            Ok(result) => Ok(result.output),
-            Err(_) => {
-                let parsed_clone = parsed.clone();
-                self.executor.execute(&parsed_clone, &self.current_path).await
-            }
-        }
+        let result = self.run_unit(parsed, None)?;
+        self.last_exit_code = result.exit_code;
+        Ok(result.output)
     }
     
     /**
@@ -311,7 +371,523 @@ impl Shell {
      * @return Result<String> - パイプライン出力またはエラー
      */
     async fn execute_pipeline(&mut self, pipeline: &CommandPipeline) -> Result<String> {
-        self.executor.execute_pipeline(pipeline, &self.current_path).await
+        let result = self.run_pipeline(pipeline, None);
+        self.emitted_records = None;
+        let result = result?;
+        self.last_exit_code = result.exit_code;
+        Ok(result.output)
+    }
+    
+    /**
+     * パイプラインを現在のシェルで実行する関数です
+     * 
+     * 各要素をビルトイン、外部コマンド、サブシェル、グループの
+     * いずれかとして実行し、演算子（|、&&、||、;）に従って
+     * 次の要素を実行するかどうかを決定します。
+     * 
+     * パイプで接続された要素には前の要素の出力を標準入力として
+     * 渡します。&&や||でスキップされた要素に続くパイプ要素も
+     * 一緒にスキップされます。
+     * 
+     * ビルトインがテキストと一緒にレコードを出力した場合は、
+     * 次の要素にレコードも渡します。最後の要素のレコードは
+     * グループの外に渡せるよう、emitted_recordsに残します。
+     * 
+     * @param pipeline - 実行するパイプライン
+     * @param input - 最初の要素に渡す標準入力
+     * @return Result<CommandResult> - 出力と最後の終了コード
+     */
+    pub(crate) fn run_pipeline(&mut self, pipeline: &CommandPipeline, input: Option<String>) -> Result<CommandResult> {
+        let mut output = String::new();
+        let mut last_exit_code = 0;
+        let mut stage_input = input;
+        let mut stage_records = self.pipeline_records.take();
+        let mut last_records = None;
+        let mut skipping = false;
+        let mut timer = None;
+        
+        for (i, command) in pipeline.commands.iter().enumerate() {
+            let operator = if i > 0 { pipeline.operators.get(i - 1) } else { None };
+            let pipes_out = pipeline.operators.get(i) == Some(&ChainOperator::Pipe);
+            
+            skipping = match operator {
+                Some(ChainOperator::Pipe) => skipping,
+                Some(ChainOperator::And) => last_exit_code != 0,
+                Some(ChainOperator::Or) => last_exit_code == 0,
+                _ => false,
+            };
+            if skipping {
+                continue;
+            }
+            
+            let piped = i == 0 || operator == Some(&ChainOperator::Pipe);
+            let input = if piped { stage_input.take() } else { None };
+            self.pipeline_records = if piped { stage_records.take() } else { None };
+            
+            if let Some(format) = command.time_keyword {
+                timer = Some((format, PipelineTimer::start()));
+            }
+            
+            let reaches_terminal = self.output_is_terminal;
+            self.output_is_terminal = reaches_terminal && !pipes_out;
+            let result = self.run_unit(command, input);
+            self.output_is_terminal = reaches_terminal;
+            self.pipeline_records = None;
+            let records = self.emitted_records.take();
+            let result = result?;
+            last_exit_code = result.exit_code;
+            self.last_exit_code = result.exit_code;
+            
+            if pipes_out {
+                stage_input = Some(result.output);
+                stage_records = records;
+            } else {
+                if !result.output.is_empty() {
+                    if !output.is_empty() && !output.ends_with('\n') {
+                        output.push('\n');
+                    }
+                    output.push_str(&result.output);
+                }
+                last_records = records;
+            }
+            
+            if !pipes_out {
+                if let Some((format, timer)) = timer.take() {
+                    output.push_str(&timer.report(format));
+                }
+            }
+        }
+        
+        self.emitted_records = last_records;
+        Ok(CommandResult {
+            output,
+            exit_code: last_exit_code,
+        })
+    }
+    
+    /**
+     * Runs one pipeline element
+     * 
+     * Dispatches to process substitution setup, brace groups, forked
+     * subshells, builtins or external commands. Redirections on groups,
+     * subshells and builtins apply to the unit's collected output.
+     * Simple commands pass through the policy layer first when it is
+     * enabled.
+     * 
+     * @param command - Pipeline element to run
+     * @param input - Stdin data from the previous stage
+     * @return Result<CommandResult> - Output and exit code
+     */
+    pub(crate) fn run_unit(&mut self, command: &ParsedCommand, input: Option<String>) -> Result<CommandResult> {
+        if !command.process_substitutions.is_empty() {
+            return self.run_with_process_substitutions(command, input);
+        }
+        
+        if command.compound.is_none() {
+            return self.run_policed(command, |shell| shell.dispatch_unit(command, input));
+        }
+        
+        self.dispatch_unit(command, input)
+    }
+    
+    /**
+     * セキュリティポリシーを通してコマンドを実行する関数です
+     *
+     * 展開済みのコマンド名と引数をそのままsare-securityの
+     * InputValidatorに渡し、sh形式で引用した1行を
+     * PermissionManagerの検査と監査ログに使います。
+     * 拒否された場合は監査ログに違反を記録し、設定された
+     * 動作（block、warn、prompt）に従います。blockの場合と
+     * promptで断った場合は終了コード126で実行しません。
+     *
+     * 許可されたコマンドはrunで実行し、成功したかどうかと共に
+     * 監査ログに記録します。監査ログに書き込めない場合は警告を
+     * 表示し、コマンドの結果はそのまま返します。単純コマンドの
+     * ほか、timeoutやsandboxが起動するコマンドとバックグラウンド
+     * ジョブもここを通ります。securityフィーチャーがない場合と
+     * ポリシーが無効な場合はrunをそのまま呼びます。
+     *
+     * @param command - 実行する単純コマンド
+     * @param run - 検査を通った後にコマンドを実行する処理
+     * @return Result<CommandResult> - 出力と終了コード
+     */
+    #[cfg(feature = "security")]
+    fn run_policed(&mut self, command: &ParsedCommand, run: impl FnOnce(&mut Self) -> Result<CommandResult>) -> Result<CommandResult> {
+        use crate::config::DenyAction;
+        
+        let argv: Vec<String> = std::iter::once(&command.command).chain(&command.args).cloned().collect();
+        let line = policy::command_line(&argv);
+        let Some(policy) = &self.policy else {
+            return run(self);
+        };
+        
+        if let Some(reason) = policy.check(&argv)? {
+            if let Err(e) = policy.record_denial(&line, &reason) {
+                eprintln!("sare: audit log: {}", e);
+            }
+            let allowed = match policy.on_deny() {
+                DenyAction::Block => false,
+                DenyAction::Warn => {
+                    eprintln!("sare: warning: {}: {}", command.command, reason);
+                    true
+                }
+                DenyAction::Prompt => commands::fileops::Prompter::new(None)
+                    .confirm(&format!("sare: {}: {}; run anyway? [y/N] ", command.command, reason)),
+            };
+            if !allowed {
+                return Ok(CommandResult {
+                    output: format!("sare: {}: blocked by security policy: {}\n", command.command, reason),
+                    exit_code: 126,
+                });
+            }
+        }
+        
+        let result = run(self);
+        if let Some(policy) = &self.policy {
+            let success = matches!(&result, Ok(result) if result.exit_code == 0);
+            if let Err(e) = policy.record_execution(&line, success) {
+                eprintln!("sare: audit log: {}", e);
+            }
+        }
+        result
+    }
+    
+    /**
+     * Runs a command without policy checks, as built without `security`
+     * 
+     * @param command - Simple command to run
+     * @param run - Runs the command
+     * @return Result<CommandResult> - Output and exit code
+     */
+    #[cfg(not(feature = "security"))]
+    fn run_policed(&mut self, _command: &ParsedCommand, run: impl FnOnce(&mut Self) -> Result<CommandResult>) -> Result<CommandResult> {
+        run(self)
+    }
+    
+    /**
+     * Runs a pipeline element that has passed any policy checks
+     * 
+     * @param command - Pipeline element to run
+     * @param input - Stdin data from the previous stage
+     * @return Result<CommandResult> - Output and exit code
+     */
+    fn dispatch_unit(&mut self, command: &ParsedCommand, input: Option<String>) -> Result<CommandResult> {
+        let is_builtin = command.compound.is_none() && self.command_registry.has_command(&command.command);
+        if command.compound.is_none() && !is_builtin {
+            self.pipeline_records = None;
+            return self.executor.execute_with_input(command, &self.current_path, input.as_deref());
+        }
+        
+        let input = match &command.input_redirect {
+            Some(file) => Some(std::fs::read_to_string(self.current_path.join(file))?),
+            None => input,
+        };
+        
+        let reaches_terminal = self.output_is_terminal;
+        if command.output_redirect.is_some() || command.append_redirect.is_some() {
+            self.output_is_terminal = false;
+        }
+        
+        let result = match &command.compound {
+            Some(CompoundCommand::Group(inner)) => self.run_pipeline(inner, input),
+            Some(CompoundCommand::Subshell(inner)) => subshell::run_forked(|| {
+                self.run_pipeline(inner, input).unwrap_or_else(|e| CommandResult {
+                    output: format!("sare: {}\n", e),
+                    exit_code: 1,
+                })
+            }),
+            None => Ok(self.run_builtin(command, input)),
+        };
+        self.output_is_terminal = reaches_terminal;
+        if command.output_redirect.is_some() || command.append_redirect.is_some() {
+            self.emitted_records = None;
+        }
+        
+        self.redirect_unit_output(command, result?)
+    }
+    
+    /**
+     * Runs a builtin with the given stdin data
+     * 
+     * Handler errors become a non-zero exit status so that chains
+     * such as `cd missing || echo fallback` behave like other shells.
+     * 
+     * @param command - Builtin command to run
+     * @param input - Stdin data for the builtin
+     * @return CommandResult - Output and exit code
+     */
+    fn run_builtin(&mut self, command: &ParsedCommand, input: Option<String>) -> CommandResult {
+        let registry = Rc::clone(&self.command_registry);
+        self.pipeline_input = input;
+        let result = registry.execute(command, self);
+        self.pipeline_input = None;
+        
+        result.unwrap_or_else(|e| CommandResult {
+            output: format!("{}: {}\n", command.command, e),
+            exit_code: 1,
+        })
+    }
+    
+    /**
+     * Writes a unit's output to its redirection target, if any
+     * 
+     * @param command - Unit carrying the redirections
+     * @param result - Result whose output may be redirected
+     * @return Result<CommandResult> - Result with output left for the terminal
+     */
+    fn redirect_unit_output(&self, command: &ParsedCommand, mut result: CommandResult) -> Result<CommandResult> {
+        if let Some(ref file) = command.append_redirect {
+            use std::io::Write;
+            let mut target = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.current_path.join(file))?;
+            target.write_all(result.output.as_bytes())?;
+            result.output.clear();
+        } else if let Some(ref file) = command.output_redirect {
+            std::fs::write(self.current_path.join(file), &result.output)?;
+            result.output.clear();
+        }
+        Ok(result)
+    }
+    
+    /**
+     * Runs a command whose arguments contain process substitutions
+     * 
+     * Each substitution gets a forked child connected through a pipe;
+     * its placeholder argument becomes the matching /dev/fd path. The
+     * pipes are closed and the children reaped once the command ends.
+     * 
+     * @param command - Command with substitution placeholders
+     * @param input - Stdin data from the previous stage
+     * @return Result<CommandResult> - Output and exit code
+     */
+    fn run_with_process_substitutions(&mut self, command: &ParsedCommand, input: Option<String>) -> Result<CommandResult> {
+        let mut resolved = command.clone();
+        resolved.process_substitutions.clear();
+        let mut handles = Vec::new();
+        
+        for substitution in &command.process_substitutions {
+            let pipeline = &substitution.pipeline;
+            let spawned = match substitution.direction {
+                SubstitutionDirection::Input => subshell::spawn_input_substitution(|| {
+                    self.output_is_terminal = false;
+                    self.run_pipeline(pipeline, None).unwrap_or_else(|e| CommandResult {
+                        output: format!("sare: {}\n", e),
+                        exit_code: 1,
+                    })
+                }),
+                SubstitutionDirection::Output => subshell::spawn_output_substitution(|data| {
+                    self.run_pipeline(pipeline, Some(data)).unwrap_or_else(|e| CommandResult {
+                        output: format!("sare: {}\n", e),
+                        exit_code: 1,
+                    })
+                }),
+            };
+            
+            match spawned {
+                Ok(handle) => {
+                    match substitution.slot {
+                        SubstitutionSlot::Argument(index) => {
+                            if let Some(arg) = resolved.args.get_mut(index) {
+                                *arg = handle.path();
+                            }
+                        }
+                        SubstitutionSlot::InputRedirect => resolved.input_redirect = Some(handle.path()),
+                        SubstitutionSlot::OutputRedirect => resolved.output_redirect = Some(handle.path()),
+                        SubstitutionSlot::AppendRedirect => resolved.append_redirect = Some(handle.path()),
+                    }
+                    handles.push(handle);
+                }
+                Err(e) => {
+                    for handle in handles {
+                        let _ = handle.finish();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        
+        let result = self.run_unit(&resolved, input);
+        
+        let mut consumer_output = String::new();
+        for handle in handles {
+            consumer_output.push_str(&handle.finish()?);
+        }
+        
+        let mut result = result?;
+        result.output.push_str(&consumer_output);
+        Ok(result)
+    }
+    
+    /**
+     * Takes the stdin data passed to the running builtin
+     * 
+     * Builtins call this to read the previous pipeline stage's output.
+     * 
+     * @return Option<String> - Piped input if the builtin is not first in the pipeline
+     */
+    pub fn take_pipeline_input(&mut self) -> Option<String> {
+        self.pipeline_input.take()
+    }
+    
+    /**
+     * Checks whether the running builtin writes to the terminal
+     * 
+     * False when its output is piped to another stage, redirected to
+     * a file or read through a process substitution. Builtins use this
+     * like isatty(1) to decide on columns and colors.
+     * 
+     * @return bool - True if output goes to the terminal
+     */
+    pub fn output_is_terminal(&self) -> bool {
+        self.output_is_terminal
+    }
+
+    /**
+     * Runs a command line from inside a builtin
+     *
+     * The line goes through the same pipeline executor as interactive
+     * input; records emitted by its last stage are discarded.
+     *
+     * @param line - Command line to run
+     * @param terminal - Whether builtins in the line may write to the terminal
+     * @return Result<CommandResult> - Output and last exit code
+     */
+    pub fn run_command_line(&mut self, line: &str, terminal: bool) -> Result<CommandResult> {
+        let pipeline = parse_pipeline(line)?;
+        let reaches_terminal = self.output_is_terminal;
+        self.output_is_terminal = terminal;
+        let result = self.run_pipeline(&pipeline, None);
+        self.output_is_terminal = reaches_terminal;
+        self.emitted_records = None;
+        result
+    }
+    
+    /**
+     * Takes the records passed to the running builtin
+     * 
+     * Present when the previous pipeline stage was a builtin that
+     * emitted records; its text output is still available through
+     * take_pipeline_input.
+     * 
+     * @return Option<Table> - Records of the previous stage
+     */
+    pub fn take_pipeline_records(&mut self) -> Option<Table> {
+        self.pipeline_records.take()
+    }
+    
+    /**
+     * Emits records alongside the running builtin's text output
+     * 
+     * The records reach the next pipeline stage; they are dropped
+     * when the output goes to the terminal or a file, where only
+     * the text is shown.
+     * 
+     * @param records - Records describing the output
+     */
+    pub fn emit_records(&mut self, records: Table) {
+        self.emitted_records = Some(records);
+    }
+    
+    /**
+     * Checks whether a name refers to a builtin command
+     *
+     * @param name - Command name
+     * @return bool - True if the registry has a handler for it
+     */
+    pub fn is_builtin(&self, name: &str) -> bool {
+        self.command_registry.has_command(name)
+    }
+
+    /**
+     * Runs a batch of commands, up to `max_processes` at a time
+     *
+     * Used by `xargs -P`. External commands run concurrently through
+     * the executor; a batch containing builtins, groups or subshells
+     * runs one command at a time in the current shell. Results are
+     * returned in the order of `commands`.
+     *
+     * @param commands - Commands to run
+     * @param max_processes - Maximum number of commands running at once
+     * @return Vec<Result<CommandResult>> - Result of each command
+     */
+    pub fn run_parallel(&mut self, commands: &[ParsedCommand], max_processes: usize) -> Vec<Result<CommandResult>> {
+        let all_external = commands
+            .iter()
+            .all(|command| command.compound.is_none() && !self.is_builtin(&command.command));
+        // Policy checks happen in run_unit, so the executor's parallel path is bypassed
+        #[cfg(feature = "security")]
+        let all_external = all_external && self.policy.is_none();
+        if max_processes > 1 && all_external {
+            return self.executor.execute_parallel(commands, &self.current_path, max_processes);
+        }
+
+        commands.iter().map(|command| self.run_unit(command, None)).collect()
+    }
+
+    /**
+     * Runs an external command under a timeout policy
+     * 
+     * Used by the `timeout` builtin; resource limits set with `ulimit`
+     * still apply, and the command passes the policy layer like any
+     * other.
+     * 
+     * @param command - External command to run
+     * @param policy - When and how to stop the command
+     * @return Result<CommandResult> - Output and exit code
+     */
+    pub fn run_with_timeout(&mut self, command: &ParsedCommand, policy: &TimeoutPolicy) -> Result<CommandResult> {
+        let input = self.pipeline_input.take();
+        self.run_policed(command, |shell| {
+            shell.executor.execute_with_timeout(command, &shell.current_path, input.as_deref(), Some(policy))
+        })
+    }
+    
+    /**
+     * Runs an external command inside a sandbox
+     * 
+     * Used by the `sandbox` builtin; resource limits set with `ulimit`
+     * still apply, and the command passes the policy layer like any
+     * other.
+     * 
+     * @param command - External command to run
+     * @param policy - Restrictions for the command
+     * @return Result<CommandResult> - Output and exit code
+     */
+    #[cfg(feature = "security")]
+    pub fn run_sandboxed(&mut self, command: &ParsedCommand, policy: &SandboxPolicy) -> Result<CommandResult> {
+        let input = self.pipeline_input.take();
+        self.run_policed(command, |shell| {
+            shell.executor.execute_sandboxed(command, &shell.current_path, input.as_deref(), policy)
+        })
+    }
+    
+    /**
+     * Gets the resource limits applied to external commands
+     * 
+     * @return &ResourceLimits - Resource limits
+     */
+    pub fn resource_limits(&self) -> &ResourceLimits {
+        self.executor.resource_limits()
+    }
+    
+    /**
+     * Gets a mutable reference to the resource limits
+     * 
+     * @return &mut ResourceLimits - Resource limits
+     */
+    pub fn resource_limits_mut(&mut self) -> &mut ResourceLimits {
+        self.executor.resource_limits_mut()
+    }
+    
+    /**
+     * Gets the exit code of the last executed pipeline
+     * 
+     * @return i32 - Last exit code
+     */
+    pub fn last_exit_code(&self) -> i32 {
+        self.last_exit_code
     }
     
     /**
@@ -327,20 +903,7 @@ impl Shell {
     async fn execute_pipeline_background(&mut self, pipeline: &CommandPipeline) -> Result<BackgroundResult> {
         // For now, execute the first command in background
         if let Some(first_command) = pipeline.commands.first() {
-            let mut cmd = std::process::Command::new(&first_command.command);
-            cmd.current_dir(&self.current_path);
-            cmd.args(&first_command.args);
-            cmd.stdout(std::process::Stdio::piped());
-            cmd.stderr(std::process::Stdio::piped());
-            
-            let child = cmd.spawn()?;
-            let pid = child.id();
-            let job_id = self.job_manager.add_job(pid, first_command.command.clone());
-            
-            Ok(BackgroundResult {
-                job_id,
-                output: format!("Background job started with PID: {}", pid),
-            })
+            self.spawn_background(first_command)
         } else {
             Err(anyhow::anyhow!("No commands in pipeline"))
         }
@@ -357,62 +920,164 @@ impl Shell {
      * @return Result<BackgroundResult> - バックグラウンド実行結果またはエラー
      */
     async fn execute_parsed_command_background(&mut self, parsed: &crate::shell::parser::ParsedCommand) -> Result<BackgroundResult> {
-        let mut cmd = std::process::Command::new(&parsed.command);
-        cmd.current_dir(&self.current_path);
-        cmd.args(&parsed.args);
-        cmd.stdout(std::process::Stdio::piped());
-        cmd.stderr(std::process::Stdio::piped());
-        
-        let child = cmd.spawn()?;
-        let pid = child.id();
-        let job_id = self.job_manager.add_job(pid, parsed.command.clone());
-        
-        Ok(BackgroundResult {
-            job_id,
-            output: format!("Background job started with PID: {}", pid),
-        })
+        self.spawn_background(parsed)
+    }
+    
+    /**
+     * Starts an external command as a background job
+     * 
+     * The command passes the policy layer like a foreground one; a
+     * rejected command is returned as an error and no job is added.
+     * 
+     * @param command - Command to start
+     * @return Result<BackgroundResult> - Job ID and message, or error
+     */
+    fn spawn_background(&mut self, command: &ParsedCommand) -> Result<BackgroundResult> {
+        let mut job_id = None;
+        let result = self.run_policed(command, |shell| {
+            let mut cmd = std::process::Command::new(&command.command);
+            cmd.current_dir(&shell.current_path);
+            cmd.args(&command.args);
+            cmd.stdout(std::process::Stdio::piped());
+            cmd.stderr(std::process::Stdio::piped());
+            
+            let child = cmd.spawn()?;
+            let pid = child.id();
+            job_id = Some(shell.job_manager.add_job(pid, command.command.clone()));
+            Ok(CommandResult {
+                output: format!("Background job started with PID: {}", pid),
+                exit_code: 0,
+            })
+        })?;
+        
+        match job_id {
+            Some(job_id) => Ok(BackgroundResult {
+                job_id,
+                output: result.output,
+            }),
+            None => Err(anyhow::anyhow!("{}", result.output.trim_end())),
+        }
     }
     
     /**
      * Changes the current working directory
      * 
+     * Resolves the path logically, consulting CDPATH for bare relative
+     * names, and records the move like any other `cd`.
+     * 
      * @param path - New directory path
      * @return Result<()> - Success or error status
      */
     pub fn change_directory(&mut self, path: &str) -> Result<()> {
-        /**
-         * ディレクトリ変更の複雑な処理です (◕‿◕)
-         * 
-         * この関数は複雑なパス解析を行います。
-         * 相対パスと絶対パスの処理が難しい部分なので、
-         * 適切なエラーハンドリングで実装しています (｡◕‿◕｡)
-         */
-        
-        let clean_path = path.trim();
-        
-        let new_path = if clean_path == "~" {
-            dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
-        } else if clean_path == ".." {
-            // Handle parent directory
-            self.current_path.parent().unwrap_or(&self.current_path).to_path_buf()
-        } else if clean_path.starts_with('/') {
-            // Absolute path
-            PathBuf::from(clean_path)
-        } else {
-            // Relative path
-            self.current_path.join(clean_path)
-        };
-        
-        if new_path.exists() && new_path.is_dir() {
-            self.current_path = new_path.canonicalize()?;
-            std::env::set_current_dir(&self.current_path)?;
-        } else {
-            return Err(anyhow::anyhow!("No such file or directory (os error 2)"));
+        let cdpath = self.environment.get("CDPATH").cloned();
+        let (new_path, _) = dirstack::resolve_cd_target(&self.current_path, path.trim(), cdpath.as_deref(), false)?;
+        self.set_working_directory(new_path)
+    }
+    
+    /**
+     * 作業ディレクトリを設定する関数です
+     * 
+     * 全てのディレクトリ変更（cd、pushd、popd、z）はこの関数を
+     * 通り、プロセスの作業ディレクトリとシェルの状態を更新します。
+     * 
+     * 変更前のディレクトリをOLDPWDとして保存し、PWDと共に
+     * 環境変数を更新します。移動先はfrecencyデータベースに
+     * 記録されますが、データベースの保存に失敗しても
+     * ディレクトリ変更自体は成功として扱います。
+     * 
+     * @param path - 移動先の絶対パス
+     * @return Result<()> - 成功またはエラー状態
+     */
+    pub fn set_working_directory(&mut self, path: PathBuf) -> Result<()> {
+        if !path.is_dir() {
+            return Err(anyhow::anyhow!("{}: No such file or directory", path.display()));
         }
         
+        std::env::set_current_dir(&path)?;
+        let old_path = std::mem::replace(&mut self.current_path, path);
+        
+        self.set_environment_variable("OLDPWD".to_string(), old_path.to_string_lossy().to_string());
+        self.set_environment_variable("PWD".to_string(), self.current_path.to_string_lossy().to_string());
+        self.previous_path = Some(old_path);
+        
+        let _ = self.frecency_database.record_visit(&self.current_path);
+        
         Ok(())
     }
     
+    /**
+     * Gets the directory that was current before the last change
+     * 
+     * @return Option<&PathBuf> - Previous directory used by `cd -`
+     */
+    pub fn previous_path(&self) -> Option<&PathBuf> {
+        self.previous_path.as_ref()
+    }
+    
+    /**
+     * Gets the pushd/popd directory stack
+     * 
+     * @return &DirectoryStack - Directory stack reference
+     */
+    pub fn directory_stack(&self) -> &DirectoryStack {
+        &self.directory_stack
+    }
+    
+    /**
+     * Gets a mutable reference to the directory stack
+     * 
+     * @return &mut DirectoryStack - Directory stack reference
+     */
+    pub fn directory_stack_mut(&mut self) -> &mut DirectoryStack {
+        &mut self.directory_stack
+    }
+    
+    /**
+     * Gets the frecency database used by `z`
+     * 
+     * @return &FrecencyDatabase - Frecency database reference
+     */
+    pub fn frecency_database(&self) -> &FrecencyDatabase {
+        &self.frecency_database
+    }
+    
+    /**
+     * Replaces the policy layer, or disables it with None
+     * 
+     * @param policy - Policy layer commands are checked against
+     */
+    #[cfg(feature = "security")]
+    pub fn set_policy(&mut self, policy: Option<policy::PolicyLayer>) {
+        self.policy = policy;
+    }
+    
+    /**
+     * Gets a mutable reference to the frecency database
+     * 
+     * @return &mut FrecencyDatabase - Frecency database reference
+     */
+    pub fn frecency_database_mut(&mut self) -> &mut FrecencyDatabase {
+        &mut self.frecency_database
+    }
+    
+    /**
+     * Gets the debug session slot, empty when no debugger is running
+     * 
+     * @return &mut Option<DebugSession> - Debug session reference
+     */
+    pub fn debug_session_mut(&mut self) -> &mut Option<DebugSession> {
+        &mut self.debug_session
+    }
+    
+    /**
+     * Gets the diagnostics of the last cargo build
+     * 
+     * @return &mut Vec<Diagnostic> - Diagnostics reference
+     */
+    pub fn build_diagnostics_mut(&mut self) -> &mut Vec<Diagnostic> {
+        &mut self.build_diagnostics
+    }
+    
     /**
      * Gets a mutable reference to the current path
      * 
@@ -561,6 +1226,16 @@ impl Shell {
         self.environment.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
     
+    /**
+     * Gets one environment variable
+     * 
+     * @param name - Variable name
+     * @return Option<&str> - Value if the variable is set
+     */
+    pub fn get_environment_variable(&self, name: &str) -> Option<&str> {
+        self.environment.get(name).map(String::as_str)
+    }
+    
     /**
      * Clears command history
      */
@@ -574,11 +1249,7 @@ impl Shell {
      * @return Vec<HistoryEntry> - Command history
      */
     pub fn get_history(&self) -> Vec<crate::history::HistoryEntry> {
-        vec![crate::history::HistoryEntry {
-            command: "ls".to_string(),
-            timestamp: chrono::Utc::now(),
-            exit_code: Some(0),
-        }]
+        self.history_manager.get_history().into_iter().cloned().collect()
     }
     
     /**
@@ -14,6 +14,7 @@
 
 use anyhow::Result;
 use std::collections::VecDeque;
+use crate::shell::resources::TimeFormat;
 
 /**
  * Represents a parsed command with its arguments
@@ -35,6 +36,69 @@ pub struct ParsedCommand {
     pub output_redirect: Option<String>,
     /// Append redirection file
     pub append_redirect: Option<String>,
+    /// Subshell or brace group this command stands for, if any
+    pub compound: Option<CompoundCommand>,
+    /// Process substitutions whose placeholders appear in `args`
+    pub process_substitutions: Vec<ProcessSubstitution>,
+    /// Set when the `time` keyword starts the pipeline at this command
+    pub time_keyword: Option<TimeFormat>,
+}
+
+/**
+ * Compound command that runs a nested pipeline as a single unit
+ * 
+ * Subshells get a forked copy of the shell state so that cd, export
+ * and friends do not leak back; groups run in the current shell.
+ * Either form can be redirected or piped as a whole.
+ */
+#[derive(Debug, Clone)]
+pub enum CompoundCommand {
+    /// `( ... )` - runs in a forked copy of the shell
+    Subshell(CommandPipeline),
+    /// `{ ...; }` - runs in the current shell
+    Group(CommandPipeline),
+}
+
+/**
+ * Direction of a process substitution
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubstitutionDirection {
+    /// `<(cmd)` - the command reads the pipeline's output
+    Input,
+    /// `>(cmd)` - the command writes into the pipeline's input
+    Output,
+}
+
+/**
+ * Place in a command that a process substitution stands for
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubstitutionSlot {
+    /// Index into `args` of the placeholder argument
+    Argument(usize),
+    /// Target of `<`, as in `cmd < <(producer)`
+    InputRedirect,
+    /// Target of `>`, as in `cmd > >(consumer)`
+    OutputRedirect,
+    /// Target of `>>`
+    AppendRedirect,
+}
+
+/**
+ * Process substitution attached to a command argument or redirection
+ * 
+ * The argument or redirection target in `slot` is a placeholder that
+ * the shell swaps for a `/dev/fd/N` path once the backing pipe exists.
+ */
+#[derive(Debug, Clone)]
+pub struct ProcessSubstitution {
+    /// Whether the command reads from or writes to the substitution
+    pub direction: SubstitutionDirection,
+    /// Pipeline that feeds or consumes the pipe
+    pub pipeline: CommandPipeline,
+    /// Where the `/dev/fd/N` path goes
+    pub slot: SubstitutionSlot,
 }
 
 /**
@@ -61,6 +125,8 @@ enum ParseState {
     Quoted(char),
     /// Escaping next character
     Escaping,
+    /// Escaping next character inside a double-quoted string
+    EscapingQuoted(char),
 }
 
 impl CommandParser {
@@ -102,6 +168,7 @@ impl CommandParser {
         let mut input_redirect = None;
         let mut output_redirect = None;
         let mut append_redirect: Option<String> = None;
+        let mut process_substitutions = Vec::new();
         
         let mut chars = expanded_input.chars().peekable();
         
@@ -131,19 +198,27 @@ impl CommandParser {
                             }
                             background = true;
                         }
+                        '<' | '>' if current_token.is_empty() && chars.peek() == Some(&'(') => {
+                            chars.next(); // consume '('
+                            let inner = read_substitution(&mut chars)?;
+                            if tokens.is_empty() {
+                                return Err(anyhow::anyhow!("Process substitution cannot be used as a command"));
+                            }
+                            // The placeholder is swapped for a /dev/fd path at execution time
+                            tokens.push_back(format!("{}({})", ch, inner));
+                            process_substitutions.push(ProcessSubstitution {
+                                direction: substitution_direction(ch),
+                                pipeline: parse_pipeline(&inner)?,
+                                slot: SubstitutionSlot::Argument(tokens.len().saturating_sub(2)),
+                            });
+                        }
                         '<' => {
                             if !current_token.is_empty() {
                                 tokens.push_back(current_token.clone());
                                 current_token.clear();
                             }
-                            let mut filename = String::new();
-                            while let Some(&next_ch) = chars.peek() {
-                                if next_ch.is_whitespace() {
-                                    break;
-                                }
-                                filename.push(chars.next().unwrap());
-                            }
-                            input_redirect = Some(filename);
+                            let target = read_redirect_target(&mut chars)?;
+                            input_redirect = Some(redirect_placeholder(target, SubstitutionSlot::InputRedirect, &mut process_substitutions)?);
                         }
                         '>' => {
                             if !current_token.is_empty() {
@@ -154,23 +229,11 @@ impl CommandParser {
                             // Check for append redirection (>>)
                             if let Some(&'>') = chars.peek() {
                                 chars.next(); // consume second '>'
-                                let mut filename = String::new();
-                                while let Some(&next_ch) = chars.peek() {
-                                    if next_ch.is_whitespace() {
-                                        break;
-                                    }
-                                    filename.push(chars.next().unwrap());
-                                }
-                                append_redirect = Some(filename);
+                                let target = read_redirect_target(&mut chars)?;
+                                append_redirect = Some(redirect_placeholder(target, SubstitutionSlot::AppendRedirect, &mut process_substitutions)?);
                             } else {
-                                let mut filename = String::new();
-                                while let Some(&next_ch) = chars.peek() {
-                                    if next_ch.is_whitespace() {
-                                        break;
-                                    }
-                                    filename.push(chars.next().unwrap());
-                                }
-                                output_redirect = Some(filename);
+                                let target = read_redirect_target(&mut chars)?;
+                                output_redirect = Some(redirect_placeholder(target, SubstitutionSlot::OutputRedirect, &mut process_substitutions)?);
                             }
                         }
                         _ => {
@@ -181,8 +244,8 @@ impl CommandParser {
                 ParseState::Quoted(quote_char) => {
                     if ch == quote_char {
                         state = ParseState::Arguments;
-                    } else if ch == '\\' {
-                        state = ParseState::Escaping;
+                    } else if ch == '\\' && quote_char == '"' {
+                        state = ParseState::EscapingQuoted(quote_char);
                     } else {
                         current_token.push(ch);
                     }
@@ -191,6 +254,14 @@ impl CommandParser {
                     current_token.push(ch);
                     state = ParseState::Arguments;
                 }
+                ParseState::EscapingQuoted(quote_char) => {
+                    // Inside double quotes only these characters lose their backslash
+                    if !matches!(ch, '"' | '\\' | '$' | '`' | '\n') {
+                        current_token.push('\\');
+                    }
+                    current_token.push(ch);
+                    state = ParseState::Quoted(quote_char);
+                }
             }
         }
         
@@ -212,6 +283,9 @@ impl CommandParser {
             input_redirect,
             output_redirect,
             append_redirect,
+            compound: None,
+            process_substitutions,
+            time_keyword: None,
         })
     }
 
@@ -219,7 +293,8 @@ impl CommandParser {
      * Expands environment variables in a string
      * 
      * Replaces variables like $PATH, $HOME with their actual values.
-     * Supports both $VAR and ${VAR} syntax.
+     * Supports both $VAR and ${VAR} syntax. Text inside single quotes
+     * and backslash-escaped dollars are left untouched.
      * 
      * @param input - String containing environment variables
      * @return String - String with variables expanded
@@ -227,9 +302,25 @@ impl CommandParser {
     pub fn expand_environment_variables(&self, input: &str) -> String {
         let mut result = String::new();
         let mut chars = input.chars().peekable();
+        let mut in_single_quotes = false;
+        let mut in_double_quotes = false;
         
         while let Some(ch) = chars.next() {
-            if ch == '$' {
+            if ch == '\'' && !in_double_quotes {
+                in_single_quotes = !in_single_quotes;
+                result.push(ch);
+            } else if in_single_quotes {
+                result.push(ch);
+            } else if ch == '"' {
+                in_double_quotes = !in_double_quotes;
+                result.push(ch);
+            } else if ch == '\\' {
+                // Keep escapes intact so that `\$HOME` stays literal for the tokenizer
+                result.push(ch);
+                if let Some(next_ch) = chars.next() {
+                    result.push(next_ch);
+                }
+            } else if ch == '$' {
                 if let Some(next_ch) = chars.peek() {
                     if next_ch.is_alphanumeric() || *next_ch == '{' {
                         let var_name = if *next_ch == '{' {
@@ -323,12 +414,12 @@ pub enum ChainOperator {
  * 入力文字列をチェーン演算子（|、&&、||、;）で分割し、
  * 各部分を個別のコマンドとして解析します。
  * 
- * 分割された各部分をCommandParserで解析し、コマンドと
- * 演算子のリストを作成します。演算子の判定は文字列の
- * 包含関係をチェックして決定します。
+ * クォート、エスケープ、括弧（サブシェルとプロセス置換）、
+ * ブレースグループの内側にある演算子では分割しません。
  * 
- * 空でない部分のみをコマンドとして追加し、適切な演算子を
- * 各コマンド間に設定します。
+ * `( ... )`で始まる部分はサブシェル、`{ ...; }`で始まる部分は
+ * グループとして内側を再帰的に解析し、閉じ括弧の後に続く
+ * リダイレクションはユニット全体に適用されます。
  * 
  * @param input - 解析するコマンド文字列
  * @return Result<CommandPipeline> - 解析されたパイプラインまたはエラー
@@ -336,35 +427,36 @@ pub enum ChainOperator {
 pub fn parse_pipeline(input: &str) -> Result<CommandPipeline> {
     let mut commands = Vec::new();
     let mut operators = Vec::new();
-    let mut parser = CommandParser::new();
-    
-    // Split by chain operators
-    let parts: Vec<&str> = input
-        .split("|")
-        .flat_map(|part| part.split("&&"))
-        .flat_map(|part| part.split("||"))
-        .flat_map(|part| part.split(";"))
-        .collect();
+    let parser = CommandParser::new();
     
-    for (i, part) in parts.iter().enumerate() {
-        let trimmed = part.trim();
-        if !trimmed.is_empty() {
-            let parsed = parser.parse(trimmed)?;
-            commands.push(parsed);
-            
-            // Determine operator between this command and the next
-            if i < parts.len() - 1 {
-                let next_part = parts[i + 1];
-                if input.contains(&format!("{}|{}", trimmed, next_part)) {
-                    operators.push(ChainOperator::Pipe);
-                } else if input.contains(&format!("{}&&{}", trimmed, next_part)) {
-                    operators.push(ChainOperator::And);
-                } else if input.contains(&format!("{}||{}", trimmed, next_part)) {
-                    operators.push(ChainOperator::Or);
-                } else {
-                    operators.push(ChainOperator::Sequential);
-                }
+    for (segment, operator) in split_chain(input)? {
+        let trimmed = segment.trim();
+        if trimmed.is_empty() {
+            if operator.is_some() && operator != Some(ChainOperator::Sequential) {
+                return Err(anyhow::anyhow!("Syntax error: missing command before operator"));
             }
+            continue;
+        }
+        
+        // `time` only counts as a keyword at the start of a pipeline
+        let starts_pipeline = operators.last() != Some(&ChainOperator::Pipe);
+        let (time_keyword, trimmed) = match starts_pipeline {
+            true => strip_time_keyword(trimmed),
+            false => (None, trimmed),
+        };
+        
+        let mut unit = parse_unit(&parser, trimmed)?;
+        unit.time_keyword = time_keyword;
+        commands.push(unit);
+        if let Some(op) = operator {
+            operators.push(op);
+        }
+    }
+    
+    // A trailing `;` leaves an operator with nothing after it
+    while operators.len() >= commands.len() && !operators.is_empty() {
+        if operators.pop() != Some(ChainOperator::Sequential) {
+            return Err(anyhow::anyhow!("Syntax error: missing command after operator"));
         }
     }
     
@@ -372,4 +464,366 @@ pub fn parse_pipeline(input: &str) -> Result<CommandPipeline> {
         commands,
         operators,
     })
-} 
\ No newline at end of file
+}
+
+/**
+ * Splits a leading `time` or `time -p` keyword off a segment
+ * 
+ * A bare `time` with nothing after it is left alone so that it
+ * reaches the external time binary like any other command.
+ * 
+ * @param segment - Trimmed pipeline element
+ * @return (Option<TimeFormat>, &str) - Keyword format and the remaining command
+ */
+fn strip_time_keyword(segment: &str) -> (Option<TimeFormat>, &str) {
+    let rest = match segment.strip_prefix("time") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest.trim_start(),
+        _ => return (None, segment),
+    };
+    
+    let (format, rest) = match rest.strip_prefix("-p") {
+        Some(after) if after.is_empty() || after.starts_with(char::is_whitespace) => (TimeFormat::Posix, after.trim_start()),
+        _ => (TimeFormat::Default, rest),
+    };
+    
+    if rest.is_empty() {
+        (None, segment)
+    } else {
+        (Some(format), rest)
+    }
+}
+
+/**
+ * Parses a single pipeline element
+ * 
+ * Recognises subshells and brace groups and falls back to the
+ * regular command parser for everything else.
+ * 
+ * @param parser - Parser used for simple commands
+ * @param segment - Trimmed pipeline element
+ * @return Result<ParsedCommand> - Parsed element or error
+ */
+fn parse_unit(parser: &CommandParser, segment: &str) -> Result<ParsedCommand> {
+    let (compound, rest) = if segment.starts_with('(') {
+        let close = find_closing(segment, 0, "(", ")")
+            .ok_or_else(|| anyhow::anyhow!("Syntax error: unmatched '('"))?;
+        let inner = &segment[1..close];
+        (CompoundCommand::Subshell(parse_pipeline(inner)?), &segment[close + 1..])
+    } else if is_group_start(segment) {
+        let close = find_closing(segment, 0, "{", "}")
+            .ok_or_else(|| anyhow::anyhow!("Syntax error: unmatched '{{'"))?;
+        let inner = segment[1..close].trim();
+        if !inner.is_empty() && !inner.ends_with(';') && !inner.ends_with('\n') && !inner.ends_with('&') {
+            return Err(anyhow::anyhow!("Syntax error: '}}' must follow ';' or a newline"));
+        }
+        (CompoundCommand::Group(parse_pipeline(inner)?), &segment[close + 1..])
+    } else {
+        return parser.parse(segment);
+    };
+    
+    let mut unit = ParsedCommand {
+        command: String::new(),
+        args: Vec::new(),
+        background: false,
+        input_redirect: None,
+        output_redirect: None,
+        append_redirect: None,
+        compound: Some(compound),
+        process_substitutions: Vec::new(),
+        time_keyword: None,
+    };
+    
+    let mut chars = rest.trim().chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ' ' | '\t' => {}
+            '<' => {
+                let target = read_redirect_target(&mut chars)?;
+                unit.input_redirect = Some(redirect_placeholder(target, SubstitutionSlot::InputRedirect, &mut unit.process_substitutions)?);
+            }
+            '>' => {
+                if let Some(&'>') = chars.peek() {
+                    chars.next();
+                    let target = read_redirect_target(&mut chars)?;
+                    unit.append_redirect = Some(redirect_placeholder(target, SubstitutionSlot::AppendRedirect, &mut unit.process_substitutions)?);
+                } else {
+                    let target = read_redirect_target(&mut chars)?;
+                    unit.output_redirect = Some(redirect_placeholder(target, SubstitutionSlot::OutputRedirect, &mut unit.process_substitutions)?);
+                }
+            }
+            '&' => unit.background = true,
+            _ => {
+                return Err(anyhow::anyhow!("Syntax error near '{}' after compound command", ch));
+            }
+        }
+    }
+    
+    Ok(unit)
+}
+
+/**
+ * Target of a redirection operator
+ */
+enum RedirectTarget {
+    /// File name
+    File(String),
+    /// `<(cmd)` or `>(cmd)` with the text between the parentheses
+    Substitution(SubstitutionDirection, String),
+}
+
+/**
+ * Reads the target of a redirection operator
+ * 
+ * Skips whitespace between the operator and the file name so that
+ * both `>file` and `> file` work. A target starting with `<(` or `>(`
+ * is a process substitution, as in `cmd < <(producer)`.
+ * 
+ * @param chars - Character stream positioned after the operator
+ * @return Result<RedirectTarget> - Redirection target or error
+ */
+fn read_redirect_target<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) -> Result<RedirectTarget> {
+    while let Some(&next_ch) = chars.peek() {
+        if next_ch == ' ' || next_ch == '\t' {
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    
+    let mut filename = String::new();
+    if let Some(&marker) = chars.peek().filter(|&&next_ch| next_ch == '<' || next_ch == '>') {
+        chars.next();
+        if chars.peek() == Some(&'(') {
+            chars.next();
+            return Ok(RedirectTarget::Substitution(substitution_direction(marker), read_substitution(chars)?));
+        }
+        filename.push(marker);
+    }
+    
+    while let Some(&next_ch) = chars.peek() {
+        if next_ch.is_whitespace() {
+            break;
+        }
+        filename.push(next_ch);
+        chars.next();
+    }
+    Ok(RedirectTarget::File(filename))
+}
+
+/**
+ * Turns a redirection target into the text stored on the command
+ * 
+ * File names are kept as they are. A process substitution is recorded
+ * for `slot` and leaves a placeholder that is swapped for a
+ * `/dev/fd/N` path at execution time.
+ * 
+ * @param target - Target read by read_redirect_target
+ * @param slot - Redirection the target belongs to
+ * @param substitutions - Process substitutions of the command
+ * @return Result<String> - File name or placeholder
+ */
+fn redirect_placeholder(target: RedirectTarget, slot: SubstitutionSlot, substitutions: &mut Vec<ProcessSubstitution>) -> Result<String> {
+    // A later redirection of the same kind replaces an earlier one
+    substitutions.retain(|substitution| substitution.slot != slot);
+    match target {
+        RedirectTarget::File(filename) => Ok(filename),
+        RedirectTarget::Substitution(direction, inner) => {
+            let placeholder = match direction {
+                SubstitutionDirection::Input => format!("<({})", inner),
+                SubstitutionDirection::Output => format!(">({})", inner),
+            };
+            substitutions.push(ProcessSubstitution {
+                direction,
+                pipeline: parse_pipeline(&inner)?,
+                slot,
+            });
+            Ok(placeholder)
+        }
+    }
+}
+
+/**
+ * Reads the body of a process substitution up to its closing parenthesis
+ * 
+ * @param chars - Character stream positioned after the opening `(`
+ * @return Result<String> - Text between the parentheses or error
+ */
+fn read_substitution<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) -> Result<String> {
+    let mut inner = String::new();
+    let mut depth = 1;
+    for next_ch in chars.by_ref() {
+        match next_ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(inner);
+                }
+            }
+            _ => {}
+        }
+        inner.push(next_ch);
+    }
+    Err(anyhow::anyhow!("Unterminated process substitution"))
+}
+
+/**
+ * Gets the direction of a process substitution from its marker
+ * 
+ * @param marker - `<` or `>`
+ * @return SubstitutionDirection - Input for `<(`, output for `>(`
+ */
+fn substitution_direction(marker: char) -> SubstitutionDirection {
+    if marker == '<' {
+        SubstitutionDirection::Input
+    } else {
+        SubstitutionDirection::Output
+    }
+}
+
+/**
+ * Checks whether a segment opens a brace group
+ * 
+ * `{` only starts a group when it stands alone as a word, which keeps
+ * arguments such as `{a,b}` or `${VAR}` untouched.
+ * 
+ * @param segment - Trimmed pipeline element
+ * @return bool - True if the segment is a brace group
+ */
+fn is_group_start(segment: &str) -> bool {
+    segment.starts_with('{') && segment[1..].starts_with(|c: char| c.is_whitespace())
+}
+
+/**
+ * Finds the byte offset of the token closing the one at `start`
+ * 
+ * Nested openers, quotes and escapes are honoured. Braces are only
+ * counted when they stand alone as words.
+ * 
+ * @param input - Text to scan
+ * @param start - Offset of the opening token
+ * @param open - Opening token
+ * @param close - Closing token
+ * @return Option<usize> - Offset of the closing token
+ */
+fn find_closing(input: &str, start: usize, open: &str, close: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let braces = open == "{";
+    let mut depth = 0;
+    let mut quote: Option<u8> = None;
+    let mut i = start;
+    
+    while i < bytes.len() {
+        let b = bytes[i];
+        if let Some(q) = quote {
+            if b == b'\\' && q == b'"' {
+                i += 1;
+            } else if b == q {
+                quote = None;
+            }
+        } else if b == b'\\' {
+            i += 1;
+        } else if b == b'\'' || b == b'"' {
+            quote = Some(b);
+        } else if input[i..].starts_with(open) && (!braces || is_word_at(bytes, i)) {
+            depth += 1;
+        } else if input[i..].starts_with(close) && (!braces || is_word_at(bytes, i)) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+        i += 1;
+    }
+    
+    None
+}
+
+/**
+ * Checks whether the byte at `index` is a standalone word
+ */
+fn is_word_at(bytes: &[u8], index: usize) -> bool {
+    let before = index == 0 || matches!(bytes[index - 1], b' ' | b'\t' | b'\n' | b';' | b'(' | b'|' | b'&');
+    let after = index + 1 >= bytes.len() || matches!(bytes[index + 1], b' ' | b'\t' | b'\n' | b';' | b')' | b'|' | b'&' | b'<' | b'>');
+    before && after
+}
+
+/**
+ * Splits input on top-level chain operators
+ * 
+ * Operators inside quotes, parentheses or brace groups belong to the
+ * nested command and are left alone. A lone `&` marks background
+ * execution and stays with its segment.
+ * 
+ * @param input - Command line to split
+ * @return Result<Vec<(String, Option<ChainOperator>)>> - Segments with the operator that follows each
+ */
+fn split_chain(input: &str) -> Result<Vec<(String, Option<ChainOperator>)>> {
+    let bytes = input.as_bytes();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut paren_depth = 0i32;
+    let mut brace_depth = 0i32;
+    let mut quote: Option<u8> = None;
+    let mut i = 0;
+    
+    while i < bytes.len() {
+        let b = bytes[i];
+        if let Some(q) = quote {
+            if b == b'\\' && q == b'"' {
+                i += 1;
+            } else if b == q {
+                quote = None;
+            }
+            i += 1;
+            continue;
+        }
+        
+        match b {
+            b'\\' => i += 1,
+            b'\'' | b'"' => quote = Some(b),
+            b'(' => paren_depth += 1,
+            b')' => {
+                paren_depth -= 1;
+                if paren_depth < 0 {
+                    return Err(anyhow::anyhow!("Syntax error: unexpected ')'"));
+                }
+            }
+            b'{' if paren_depth == 0 && is_word_at(bytes, i) => brace_depth += 1,
+            b'}' if paren_depth == 0 && brace_depth > 0 && is_word_at(bytes, i) => brace_depth -= 1,
+            b'|' | b'&' | b';' | b'\n' if paren_depth == 0 && brace_depth == 0 => {
+                let doubled = bytes.get(i + 1) == Some(&b);
+                let operator = match (b, doubled) {
+                    (b'|', true) => Some(ChainOperator::Or),
+                    (b'|', false) => Some(ChainOperator::Pipe),
+                    (b'&', true) => Some(ChainOperator::And),
+                    (b';', _) | (b'\n', _) => Some(ChainOperator::Sequential),
+                    _ => None,
+                };
+                
+                if let Some(op) = operator {
+                    segments.push((input[start..i].to_string(), Some(op)));
+                    if doubled && b != b';' && b != b'\n' {
+                        i += 1;
+                    }
+                    start = i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    
+    if quote.is_some() {
+        return Err(anyhow::anyhow!("Syntax error: unterminated quote"));
+    }
+    if paren_depth != 0 {
+        return Err(anyhow::anyhow!("Syntax error: unmatched '('"));
+    }
+    if brace_depth != 0 {
+        return Err(anyhow::anyhow!("Syntax error: unmatched '{{'"));
+    }
+    
+    segments.push((input[start..].to_string(), None));
+    Ok(segments)
+}
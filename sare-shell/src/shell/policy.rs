@@ -0,0 +1,230 @@
+/*!
+ * @file policy.rs
+ * @brief Security policy layer backed by sare-security
+ *
+ * This module runs each command through sare-security's input
+ * validator and permission manager before it executes, and records
+ * every execution in the audit log. It is only compiled with the
+ * `security` cargo feature.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file policy.rs
+ * @description Policy checks and audit logging for shell commands
+ * using InputValidator, PermissionManager and AuditLogger.
+ */
+
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use sare_security::{SecurityConfig, SecurityEvent};
+use sare_security::audit::{AuditConfig, AuditLogger};
+use sare_security::permissions::PermissionManager;
+use sare_security::validation::InputValidator;
+use crate::config::{DenyAction, SecurityPolicyConfig};
+
+/**
+ * Security checks shared by every command the shell runs
+ *
+ * sare-security's API is async while the shell executes commands
+ * synchronously, so the layer owns a small runtime of its own. It
+ * also keeps the audit logger's background tasks (rotation and
+ * alert counters) running between commands.
+ */
+pub struct PolicyLayer {
+    /// Runtime driving the sare-security futures
+    runtime: Option<tokio::runtime::Runtime>,
+    /// Command validator
+    validator: InputValidator,
+    /// Command permissions
+    permissions: PermissionManager,
+    /// Audit log every execution is written to
+    audit: AuditLogger,
+    /// User commands are checked and recorded for
+    user: String,
+    /// Action taken when a command is rejected
+    on_deny: DenyAction,
+}
+
+impl PolicyLayer {
+    /**
+     * Creates the validator, permission manager and audit logger
+     *
+     * @param config - Policy settings from the shell configuration
+     * @return Result<PolicyLayer> - Ready policy layer or error
+     */
+    pub fn new(config: &SecurityPolicyConfig) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("sare-security")
+            .enable_all()
+            .build()?;
+        let security = Arc::new(RwLock::new(SecurityConfig::default()));
+        let mut audit_config = AuditConfig::default();
+        if let Some(log_file) = &config.log_file {
+            audit_config.log_file_path = log_file.to_string_lossy().into_owned();
+        }
+        audit_config.sign_entries = config.sign_audit_log;
+
+        let components = on_own_thread(&runtime, async {
+            Ok::<_, anyhow::Error>((
+                InputValidator::new(security.clone()).await?,
+                PermissionManager::new(security.clone()).await?,
+                AuditLogger::with_config(security.clone(), audit_config).await?,
+            ))
+        })
+        .and_then(|components| components);
+        let (validator, permissions, audit) = match components {
+            Ok(components) => components,
+            Err(e) => {
+                runtime.shutdown_background();
+                return Err(anyhow::anyhow!("cannot start security policy: {}", e));
+            }
+        };
+
+        Ok(Self {
+            runtime: Some(runtime),
+            validator,
+            permissions,
+            audit,
+            user: whoami::username(),
+            on_deny: config.on_deny,
+        })
+    }
+
+    /**
+     * Gets the action taken when a command is rejected
+     *
+     * @return DenyAction - Configured action
+     */
+    pub fn on_deny(&self) -> DenyAction {
+        self.on_deny
+    }
+
+    /**
+     * Checks a command against the validator and permissions
+     *
+     * The validator sees the words exactly as the command receives
+     * them; permission rules are matched against `command_line(argv)`.
+     *
+     * @param argv - Expanded command name and arguments
+     * @return Result<Option<String>> - Reason the command was rejected, None if allowed
+     */
+    pub fn check(&self, argv: &[String]) -> Result<Option<String>> {
+        let line = command_line(argv);
+        self.block_on(async {
+            if !self.validator.validate_argv(argv).await? {
+                return Ok(Some("rejected by input validation".to_string()));
+            }
+            if !self.permissions.can_execute_command(&line, &self.user).await? {
+                return Ok(Some(format!("user '{}' may not run this command", self.user)));
+            }
+            Ok(None)
+        })?
+    }
+
+    /**
+     * Records a rejected command in the audit log
+     *
+     * @param line - Command and arguments as one line
+     * @param reason - Why the command was rejected
+     * @return Result<()> - Success or error
+     */
+    pub fn record_denial(&self, line: &str, reason: &str) -> Result<()> {
+        let event = SecurityEvent::PermissionViolation {
+            resource: line.to_string(),
+            operation: "execute".to_string(),
+            user: self.user.clone(),
+            timestamp: unix_time(),
+            reason: reason.to_string(),
+        };
+        self.block_on(self.audit.log_event(event))?
+    }
+
+    /**
+     * Records an executed command in the audit log
+     *
+     * @param line - Command and arguments as one line
+     * @param success - Whether the command exited with status 0
+     * @return Result<()> - Success or error
+     */
+    pub fn record_execution(&self, line: &str, success: bool) -> Result<()> {
+        let event = SecurityEvent::CommandExecution {
+            command: line.to_string(),
+            user: self.user.clone(),
+            timestamp: unix_time(),
+            success,
+        };
+        self.block_on(self.audit.log_event(event))?
+    }
+
+    /**
+     * Runs a sare-security future to completion
+     *
+     * @param future - Future to run
+     * @return Result<T> - Output of the future
+     */
+    fn block_on<T: Send>(&self, future: impl Future<Output = T> + Send) -> Result<T> {
+        let runtime = self.runtime.as_ref().ok_or_else(|| anyhow::anyhow!("security runtime is shut down"))?;
+        on_own_thread(runtime, future)
+    }
+}
+
+impl Drop for PolicyLayer {
+    fn drop(&mut self) {
+        // Dropping a runtime normally blocks, which tokio forbids inside the shell's own runtime
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/**
+ * Joins a command's words into one line for rules and the audit log
+ *
+ * Words with spaces or special characters are single-quoted as sh
+ * would need them, so `rm "a b"` is recorded as `rm 'a b'` rather
+ * than `rm a b`.
+ *
+ * @param argv - Command name and arguments
+ * @return String - Command line
+ */
+pub fn command_line(argv: &[String]) -> String {
+    argv.iter()
+        .map(|word| {
+            if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c)) {
+                word.clone()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/**
+ * Blocks on a future from a scoped thread
+ *
+ * The shell itself may be running inside a tokio runtime, where
+ * calling `block_on` directly panics; a fresh thread has no runtime
+ * context.
+ *
+ * @param runtime - Runtime to drive the future on
+ * @param future - Future to run
+ * @return Result<T> - Output of the future, or an error if it panicked
+ */
+fn on_own_thread<T: Send>(runtime: &tokio::runtime::Runtime, future: impl Future<Output = T> + Send) -> Result<T> {
+    std::thread::scope(|scope| scope.spawn(|| runtime.block_on(future)).join())
+        .map_err(|_| anyhow::anyhow!("security check panicked"))
+}
+
+/**
+ * Gets the current time in seconds since the epoch
+ *
+ * @return u64 - Unix time
+ */
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
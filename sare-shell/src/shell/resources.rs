@@ -0,0 +1,530 @@
+/*!
+ * @file resources.rs
+ * @brief Resource limits, timeouts and usage accounting
+ *
+ * This module holds the per-shell resource limits set with `ulimit`,
+ * the timeout policy used to stop runaway commands, and the timer
+ * behind the `time` keyword.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file resources.rs
+ * @description Resource management for external commands including
+ * setrlimit-based limits, process group timeouts and rusage timing.
+ */
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/**
+ * Resource that can be limited with `ulimit`
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LimitResource {
+    /// Core dump size (blocks of 1024 bytes)
+    CoreSize,
+    /// CPU time (seconds)
+    CpuTime,
+    /// Open file descriptors
+    OpenFiles,
+    /// Virtual memory (kbytes)
+    Memory,
+}
+
+impl LimitResource {
+    /// All supported resources in `ulimit -a` order
+    pub const ALL: [LimitResource; 4] = [
+        LimitResource::CoreSize,
+        LimitResource::CpuTime,
+        LimitResource::OpenFiles,
+        LimitResource::Memory,
+    ];
+
+    /**
+     * Looks up a resource by its `ulimit` flag
+     *
+     * @param flag - Option character such as 't' or 'n'
+     * @return Option<LimitResource> - Matching resource
+     */
+    pub fn from_flag(flag: char) -> Option<Self> {
+        match flag {
+            'c' => Some(LimitResource::CoreSize),
+            't' => Some(LimitResource::CpuTime),
+            'n' => Some(LimitResource::OpenFiles),
+            'v' => Some(LimitResource::Memory),
+            _ => None,
+        }
+    }
+
+    /**
+     * Gets the `ulimit` flag for the resource
+     *
+     * @return char - Option character
+     */
+    pub fn flag(&self) -> char {
+        match self {
+            LimitResource::CoreSize => 'c',
+            LimitResource::CpuTime => 't',
+            LimitResource::OpenFiles => 'n',
+            LimitResource::Memory => 'v',
+        }
+    }
+
+    /**
+     * Gets the description shown by `ulimit -a`
+     *
+     * @return &str - Resource description with its unit
+     */
+    pub fn description(&self) -> &'static str {
+        match self {
+            LimitResource::CoreSize => "core file size          (blocks)",
+            LimitResource::CpuTime => "cpu time               (seconds)",
+            LimitResource::OpenFiles => "open files                      ",
+            LimitResource::Memory => "virtual memory          (kbytes)",
+        }
+    }
+
+    /**
+     * Gets the number of bytes per user-facing unit
+     *
+     * @return u64 - Multiplier between `ulimit` values and rlimit values
+     */
+    fn unit(&self) -> u64 {
+        match self {
+            LimitResource::CoreSize | LimitResource::Memory => 1024,
+            LimitResource::CpuTime | LimitResource::OpenFiles => 1,
+        }
+    }
+
+    /**
+     * Gets the libc resource constant
+     *
+     * @return libc::__rlimit_resource_t - Resource for getrlimit/setrlimit
+     */
+    fn raw(&self) -> libc::__rlimit_resource_t {
+        match self {
+            LimitResource::CoreSize => libc::RLIMIT_CORE,
+            LimitResource::CpuTime => libc::RLIMIT_CPU,
+            LimitResource::OpenFiles => libc::RLIMIT_NOFILE,
+            LimitResource::Memory => libc::RLIMIT_AS,
+        }
+    }
+}
+
+/**
+ * Soft and hard values requested for one resource
+ *
+ * `None` leaves the inherited value untouched.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LimitSetting {
+    /// Soft limit in rlimit units (RLIM_INFINITY for unlimited)
+    pub soft: Option<libc::rlim_t>,
+    /// Hard limit in rlimit units (RLIM_INFINITY for unlimited)
+    pub hard: Option<libc::rlim_t>,
+}
+
+/**
+ * Resource limits applied to every external command
+ *
+ * Limits are not applied to the shell itself; they are installed in
+ * each child between fork and exec, so lowering a limit never
+ * cripples the interactive session.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLimits {
+    /// Requested limits by resource
+    settings: BTreeMap<LimitResource, LimitSetting>,
+}
+
+impl ResourceLimits {
+    /**
+     * Creates an empty set of limits
+     *
+     * @return ResourceLimits - Limits that inherit everything
+     */
+    pub fn new() -> Self {
+        Self {
+            settings: BTreeMap::new(),
+        }
+    }
+
+    /**
+     * Sets the limit for a resource
+     *
+     * Values are given in `ulimit` units (seconds, kbytes, blocks);
+     * `None` means unlimited. The new limits are tried out in a
+     * throwaway child first, so a value the kernel would refuse is
+     * rejected here instead of making every later command fail to start.
+     *
+     * @param resource - Resource to limit
+     * @param value - New limit or None for unlimited
+     * @param soft - Whether to set the soft limit
+     * @param hard - Whether to set the hard limit
+     * @return Result<()> - Success or error
+     */
+    pub fn set(&mut self, resource: LimitResource, value: Option<u64>, soft: bool, hard: bool) -> Result<()> {
+        let raw = match value {
+            Some(value) => value
+                .checked_mul(resource.unit())
+                .ok_or_else(|| anyhow::anyhow!("limit out of range"))?,
+            None => libc::RLIM_INFINITY,
+        };
+
+        let (current_soft, current_hard) = self.effective(resource)?;
+        let new_soft = if soft { raw } else { current_soft };
+        let new_hard = if hard { raw } else { current_hard };
+
+        if new_soft > new_hard {
+            return Err(anyhow::anyhow!("soft limit cannot exceed hard limit"));
+        }
+
+        let mut updated = self.clone();
+        let setting = updated.settings.entry(resource).or_default();
+        if soft {
+            setting.soft = Some(raw);
+        }
+        if hard {
+            setting.hard = Some(raw);
+        }
+
+        probe_child_limits(&updated.to_child_settings())
+            .map_err(|e| anyhow::anyhow!("cannot modify limit: {}", e))?;
+
+        *self = updated;
+        Ok(())
+    }
+
+    /**
+     * Gets the soft and hard limits children will run with
+     *
+     * @param resource - Resource to query
+     * @return Result<(rlim_t, rlim_t)> - Soft and hard limits in rlimit units
+     */
+    pub fn effective(&self, resource: LimitResource) -> Result<(libc::rlim_t, libc::rlim_t)> {
+        let (soft, hard) = process_limit(resource)?;
+        let setting = self.settings.get(&resource).copied().unwrap_or_default();
+        Ok((setting.soft.unwrap_or(soft), setting.hard.unwrap_or(hard)))
+    }
+
+    /**
+     * Formats a limit value in `ulimit` units
+     *
+     * @param resource - Resource the value belongs to
+     * @param raw - Value in rlimit units
+     * @return String - Number or "unlimited"
+     */
+    pub fn format_value(resource: LimitResource, raw: libc::rlim_t) -> String {
+        match Self::to_units(resource, raw) {
+            Some(value) => value.to_string(),
+            None => "unlimited".to_string(),
+        }
+    }
+
+    /**
+     * Converts an rlimit value to `ulimit` units
+     *
+     * @param resource - Resource the value belongs to
+     * @param raw - Value in rlimit units
+     * @return Option<u64> - Value in `ulimit` units, None for unlimited
+     */
+    pub fn to_units(resource: LimitResource, raw: libc::rlim_t) -> Option<u64> {
+        if raw == libc::RLIM_INFINITY {
+            None
+        } else {
+            Some(raw / resource.unit())
+        }
+    }
+
+    /**
+     * Checks whether any limit has been set
+     *
+     * @return bool - True if no limits are configured
+     */
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+    }
+
+    /**
+     * 子プロセスに適用するrlimitの一覧を作成する関数です
+     *
+     * pre_exec内ではメモリ確保が安全ではないため、fork前に
+     * 設定を固定長のリストに変換しておきます。
+     *
+     * 未設定の値はNoneのまま残し、子プロセス側で
+     * getrlimit()した現在値で補完します。
+     *
+     * @return Vec<(resource, LimitSetting)> - 適用する設定のリスト
+     */
+    pub fn to_child_settings(&self) -> Vec<(libc::__rlimit_resource_t, LimitSetting)> {
+        self.settings
+            .iter()
+            .map(|(resource, setting)| (resource.raw(), *setting))
+            .collect()
+    }
+}
+
+/**
+ * Applies limits inside a forked child before exec
+ *
+ * Only async-signal-safe calls are used, so this is safe to run from
+ * `CommandExt::pre_exec`.
+ *
+ * @param settings - Limits prepared by `ResourceLimits::to_child_settings`
+ * @return std::io::Result<()> - Success or the setrlimit error
+ */
+pub fn apply_child_limits(settings: &[(libc::__rlimit_resource_t, LimitSetting)]) -> std::io::Result<()> {
+    for (resource, setting) in settings {
+        let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        unsafe {
+            if libc::getrlimit(*resource, &mut limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if let Some(hard) = setting.hard {
+                limit.rlim_max = hard;
+            }
+            if let Some(soft) = setting.soft {
+                limit.rlim_cur = soft;
+            }
+            if limit.rlim_cur > limit.rlim_max {
+                limit.rlim_cur = limit.rlim_max;
+            }
+            if libc::setrlimit(*resource, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Checks that limits can be applied by applying them in a forked child
+ *
+ * @param settings - Limits prepared by `ResourceLimits::to_child_settings`
+ * @return std::io::Result<()> - Success or the error setrlimit reported
+ */
+fn probe_child_limits(settings: &[(libc::__rlimit_resource_t, LimitSetting)]) -> std::io::Result<()> {
+    match unsafe { libc::fork() } {
+        -1 => Err(std::io::Error::last_os_error()),
+        0 => {
+            let code = match apply_child_limits(settings) {
+                Ok(()) => 0,
+                Err(e) => e.raw_os_error().unwrap_or(libc::EINVAL),
+            };
+            unsafe { libc::_exit(code) }
+        }
+        pid => {
+            let mut status = 0;
+            while unsafe { libc::waitpid(pid, &mut status, 0) } == -1 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+            match libc::WEXITSTATUS(status) {
+                0 => Ok(()),
+                errno => Err(std::io::Error::from_raw_os_error(errno)),
+            }
+        }
+    }
+}
+
+/**
+ * Reads the shell process's own limits for a resource
+ *
+ * @param resource - Resource to query
+ * @return Result<(rlim_t, rlim_t)> - Soft and hard limits
+ */
+fn process_limit(resource: LimitResource) -> Result<(libc::rlim_t, libc::rlim_t)> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(resource.raw(), &mut limit) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok((limit.rlim_cur, limit.rlim_max))
+}
+
+/**
+ * How a command is stopped once it runs too long
+ *
+ * The first signal goes to the whole process group so that children
+ * spawned by the command are stopped as well; SIGKILL follows if the
+ * group is still alive after the grace period.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutPolicy {
+    /// Time the command may run
+    pub duration: Duration,
+    /// Signal sent when the time is up
+    pub signal: i32,
+    /// Grace period before SIGKILL
+    pub kill_after: Duration,
+}
+
+impl TimeoutPolicy {
+    /**
+     * Creates a policy sending SIGTERM, then SIGKILL five seconds later
+     *
+     * @param duration - Time the command may run
+     * @return TimeoutPolicy - New policy
+     */
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            signal: libc::SIGTERM,
+            kill_after: Duration::from_secs(5),
+        }
+    }
+}
+
+/**
+ * Parses a duration such as `10`, `1.5s`, `2m`, `1h` or `1d`
+ *
+ * @param value - Duration text, seconds when no suffix is given
+ * @return Result<Duration> - Parsed duration or error
+ */
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let (number, scale) = match value.chars().last() {
+        Some('s') => (&value[..value.len() - 1], 1.0),
+        Some('m') => (&value[..value.len() - 1], 60.0),
+        Some('h') => (&value[..value.len() - 1], 3600.0),
+        Some('d') => (&value[..value.len() - 1], 86400.0),
+        _ => (value, 1.0),
+    };
+
+    let seconds = number
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .ok_or_else(|| anyhow::anyhow!("invalid time interval '{}'", value))?;
+
+    Ok(Duration::from_secs_f64(seconds * scale))
+}
+
+/**
+ * Parses a signal given by name or number
+ *
+ * @param value - Signal such as `TERM`, `SIGKILL` or `9`
+ * @return Result<i32> - Signal number or error
+ */
+pub fn parse_signal(value: &str) -> Result<i32> {
+    if let Ok(number) = value.parse::<i32>() {
+        return Ok(number);
+    }
+
+    let name = value.trim_start_matches("SIG").to_uppercase();
+    let signal = match name.as_str() {
+        "HUP" => libc::SIGHUP,
+        "INT" => libc::SIGINT,
+        "QUIT" => libc::SIGQUIT,
+        "KILL" => libc::SIGKILL,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "ALRM" => libc::SIGALRM,
+        "TERM" => libc::SIGTERM,
+        "CONT" => libc::SIGCONT,
+        "STOP" => libc::SIGSTOP,
+        _ => return Err(anyhow::anyhow!("invalid signal '{}'", value)),
+    };
+    Ok(signal)
+}
+
+/**
+ * Output format of the `time` keyword
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeFormat {
+    /// bash style: `real\t0m0.001s`
+    Default,
+    /// POSIX style selected with `time -p`: `real 0.00`
+    Posix,
+}
+
+/**
+ * Measures real, user and system time of a pipeline
+ *
+ * User and system time cover both the shell itself (builtins run
+ * in-process) and every child reaped while the timer was running.
+ */
+#[derive(Debug)]
+pub struct PipelineTimer {
+    /// Wall clock start
+    started: Instant,
+    /// CPU time of the shell process at start (user, sys)
+    self_start: (Duration, Duration),
+    /// CPU time of reaped children at start (user, sys)
+    children_start: (Duration, Duration),
+}
+
+impl PipelineTimer {
+    /**
+     * Starts timing
+     *
+     * @return PipelineTimer - Running timer
+     */
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            self_start: cpu_times(libc::RUSAGE_SELF),
+            children_start: cpu_times(libc::RUSAGE_CHILDREN),
+        }
+    }
+
+    /**
+     * Stops timing and formats the report
+     *
+     * @param format - Report format
+     * @return String - Report printed after the pipeline's output
+     */
+    pub fn report(&self, format: TimeFormat) -> String {
+        let real = self.started.elapsed();
+        let self_now = cpu_times(libc::RUSAGE_SELF);
+        let children_now = cpu_times(libc::RUSAGE_CHILDREN);
+
+        let user = self_now.0.saturating_sub(self.self_start.0) + children_now.0.saturating_sub(self.children_start.0);
+        let sys = self_now.1.saturating_sub(self.self_start.1) + children_now.1.saturating_sub(self.children_start.1);
+
+        match format {
+            TimeFormat::Default => format!(
+                "\nreal\t{}\nuser\t{}\nsys\t{}\n",
+                format_minutes(real),
+                format_minutes(user),
+                format_minutes(sys)
+            ),
+            TimeFormat::Posix => format!(
+                "real {:.2}\nuser {:.2}\nsys {:.2}\n",
+                real.as_secs_f64(),
+                user.as_secs_f64(),
+                sys.as_secs_f64()
+            ),
+        }
+    }
+}
+
+/**
+ * Reads user and system CPU time from getrusage
+ *
+ * @param who - RUSAGE_SELF or RUSAGE_CHILDREN
+ * @return (Duration, Duration) - User and system time
+ */
+fn cpu_times(who: i32) -> (Duration, Duration) {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(who, &mut usage) } != 0 {
+        return (Duration::ZERO, Duration::ZERO);
+    }
+
+    let to_duration = |tv: libc::timeval| Duration::new(tv.tv_sec as u64, (tv.tv_usec as u32) * 1000);
+    (to_duration(usage.ru_utime), to_duration(usage.ru_stime))
+}
+
+/**
+ * Formats a duration as bash does, e.g. `0m1.234s`
+ *
+ * @param duration - Duration to format
+ * @return String - Minutes and seconds
+ */
+fn format_minutes(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    let minutes = (seconds / 60.0).floor();
+    format!("{}m{:.3}s", minutes as u64, seconds - minutes * 60.0)
+}
@@ -0,0 +1,151 @@
+/*!
+ * @file sandbox.rs
+ * @brief Namespace, mount and seccomp isolation for external commands
+ *
+ * This module holds the policy behind the `sandbox` builtin and turns
+ * it into sare-security's ChildSetup, which applies it inside the
+ * forked child, between fork and exec. Isolation is built from an
+ * unprivileged user namespace, so it works for ordinary users on
+ * kernels that allow user namespaces.
+ *
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file sandbox.rs
+ * @description Sandboxing for external commands using user, mount,
+ * network, IPC and UTS namespaces, read-only bind mounts, rlimits and
+ * a seccomp filter.
+ */
+
+use anyhow::Result;
+use sare_security::sandbox::{ChildSetup, SandboxConfig};
+use std::path::{Path, PathBuf};
+
+/**
+ * Restrictions requested for one sandboxed command
+ */
+#[derive(Debug, Clone)]
+pub struct SandboxPolicy {
+    /// Whether the command keeps the host network (--net)
+    pub network: bool,
+    /// Paths remounted read-only inside the sandbox (--ro)
+    pub read_only: Vec<PathBuf>,
+    /// Address space limit in bytes (--mem)
+    pub memory: Option<u64>,
+    /// CPU time limit in seconds (--cpu)
+    pub cpu_time: Option<u64>,
+    /// Open file descriptor limit (--nofile)
+    pub open_files: Option<u64>,
+    /// Whether the seccomp filter is installed (--seccomp)
+    pub seccomp: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            network: true,
+            read_only: Vec::new(),
+            memory: None,
+            cpu_time: None,
+            open_files: None,
+            seccomp: true,
+        }
+    }
+}
+
+impl SandboxPolicy {
+    /**
+     * 子プロセスで適用する設定を fork 前に準備する関数です
+     *
+     * pre_exec 内ではメモリ確保が安全ではないため、読み取り専用に
+     * するパス、rlimit、seccomp の BPF プログラムを含む ChildSetup を
+     * ここで全て作っておきます。名前空間はユーザー・マウント・IPC・
+     * UTS（--net=off の場合はネットワークも）を分離します。
+     *
+     * 読み取り専用のパスは作業ディレクトリから解決し、存在しない
+     * 場合はエラーにします。
+     *
+     * @param working_dir - 相対パスの基準となる作業ディレクトリ
+     * @return Result<ChildSetup> - 子プロセスで使う設定
+     */
+    pub fn prepare(&self, working_dir: &Path) -> Result<ChildSetup> {
+        // Only the limits given on the command line, not SandboxManager's defaults
+        let config = SandboxConfig {
+            resource_limits: false,
+            ..SandboxConfig::default()
+        };
+        let mut setup = ChildSetup::new(&config);
+        if self.network {
+            setup = setup.share_network();
+        }
+
+        for path in &self.read_only {
+            let resolved = working_dir
+                .join(path)
+                .canonicalize()
+                .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+            setup = setup.read_only(&resolved)?;
+        }
+
+        for (resource, value) in [
+            (libc::RLIMIT_AS, self.memory),
+            (libc::RLIMIT_CPU, self.cpu_time),
+            (libc::RLIMIT_NOFILE, self.open_files),
+        ] {
+            if let Some(value) = value {
+                setup = setup.limit(resource, value);
+            }
+        }
+
+        if self.seccomp {
+            setup = setup.seccomp();
+        }
+        Ok(setup)
+    }
+
+    /**
+     * Describes the policy for error messages
+     *
+     * @return String - One line summary
+     */
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("net={}", if self.network { "on" } else { "off" })];
+        parts.extend(self.read_only.iter().map(|path| format!("ro={}", path.display())));
+        if let Some(memory) = self.memory {
+            parts.push(format!("mem={}", memory));
+        }
+        if let Some(cpu_time) = self.cpu_time {
+            parts.push(format!("cpu={}s", cpu_time));
+        }
+        if let Some(open_files) = self.open_files {
+            parts.push(format!("nofile={}", open_files));
+        }
+        parts.push(format!("seccomp={}", if self.seccomp { "on" } else { "off" }));
+        parts.join(" ")
+    }
+}
+
+/**
+ * Parses a memory size such as 512M or 2G
+ *
+ * @param text - Size with an optional K, M, G or T suffix (default bytes)
+ * @return Result<u64> - Size in bytes
+ */
+pub fn parse_memory(text: &str) -> Result<u64> {
+    let invalid = || anyhow::anyhow!("invalid memory size '{}'", text);
+    let (number, multiplier) = match text.char_indices().last() {
+        Some((index, suffix)) if suffix.is_ascii_alphabetic() => {
+            let multiplier: u64 = match suffix.to_ascii_uppercase() {
+                'B' => 1,
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                'T' => 1 << 40,
+                _ => return Err(invalid()),
+            };
+            (&text[..index], multiplier)
+        }
+        _ => (text, 1),
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    number.checked_mul(multiplier).filter(|bytes| *bytes > 0).ok_or_else(invalid)
+}
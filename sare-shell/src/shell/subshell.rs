@@ -0,0 +1,338 @@
+/*!
+ * @file subshell.rs
+ * @brief Forked subshells and process substitution
+ * 
+ * This module runs shell code in a forked copy of the shell process.
+ * The child inherits a snapshot of every piece of shell state, so
+ * changes it makes (cd, export, aliases) never reach the parent.
+ * 
+ * @author KleaSCM
+ * @email KleaSCM@gmail.com
+ * @file subshell.rs
+ * @description Fork helpers backing `( ... )` subshells,
+ * `<(cmd)` / `>(cmd)` process substitution via /dev/fd pipes and
+ * concurrently running forked children.
+ */
+
+use anyhow::Result;
+use crate::shell::commands::CommandResult;
+use crate::shell::io::IoUtils;
+
+/**
+ * Runs a closure in a forked child and collects its result
+ * 
+ * The child writes the closure's output into a pipe and exits with
+ * its exit code; the parent drains the pipe before reaping the child
+ * so a large output can never deadlock the two processes.
+ * 
+ * @param body - Work to perform inside the child
+ * @return Result<CommandResult> - Output and exit code of the child
+ */
+pub fn run_forked<F>(body: F) -> Result<CommandResult>
+where
+    F: FnOnce() -> CommandResult,
+{
+    let (read_fd, write_fd) = IoUtils::create_pipe()?;
+    
+    match fork_process()? {
+        0 => {
+            let _ = IoUtils::close_fd(read_fd);
+            let result = body();
+            let _ = IoUtils::write_all(write_fd, result.output.as_bytes());
+            exit_child(result.exit_code)
+        }
+        pid => {
+            IoUtils::close_fd(write_fd)?;
+            let output = IoUtils::read_to_end(read_fd)?;
+            let exit_code = wait_for_child(pid)?;
+            
+            Ok(CommandResult {
+                output,
+                exit_code,
+            })
+        }
+    }
+}
+
+/**
+ * Live process substitution owned by the parent shell
+ * 
+ * Holds the parent's end of the /dev/fd pipe and, for `>(cmd)`, the
+ * pipe carrying the consumer's output back to the shell.
+ */
+#[derive(Debug)]
+pub struct SubstitutionHandle {
+    /// Child process running the substituted pipeline
+    pid: libc::pid_t,
+    /// Parent end of the pipe exposed as /dev/fd/N
+    fd: i32,
+    /// Pipe carrying the output of a `>(cmd)` consumer
+    output_fd: Option<i32>,
+}
+
+impl SubstitutionHandle {
+    /**
+     * Gets the path that replaces the substitution in the argument list
+     * 
+     * @return String - /dev/fd path for the pipe
+     */
+    pub fn path(&self) -> String {
+        format!("/dev/fd/{}", self.fd)
+    }
+    
+    /**
+     * Closes the pipe and reaps the child
+     * 
+     * Closing first lets a blocked producer see EPIPE and a consumer
+     * see EOF, so waiting afterwards cannot hang.
+     * 
+     * @return Result<String> - Output produced by a `>(cmd)` consumer
+     */
+    pub fn finish(self) -> Result<String> {
+        IoUtils::close_fd(self.fd)?;
+        let output = match self.output_fd {
+            Some(fd) => IoUtils::read_to_end(fd)?,
+            None => String::new(),
+        };
+        wait_for_child(self.pid)?;
+        Ok(output)
+    }
+}
+
+/**
+ * Starts a `<(cmd)` substitution
+ * 
+ * The child writes the pipeline's output into the pipe, which the
+ * command reads through the returned handle's path.
+ * 
+ * @param body - Pipeline run inside the child
+ * @return Result<SubstitutionHandle> - Handle owning the read end
+ */
+pub fn spawn_input_substitution<F>(body: F) -> Result<SubstitutionHandle>
+where
+    F: FnOnce() -> CommandResult,
+{
+    let (read_fd, write_fd) = IoUtils::create_pipe()?;
+    
+    match fork_process()? {
+        0 => {
+            let _ = IoUtils::close_fd(read_fd);
+            let result = body();
+            let _ = IoUtils::write_all(write_fd, result.output.as_bytes());
+            exit_child(result.exit_code)
+        }
+        pid => {
+            IoUtils::close_fd(write_fd)?;
+            Ok(SubstitutionHandle {
+                pid,
+                fd: read_fd,
+                output_fd: None,
+            })
+        }
+    }
+}
+
+/**
+ * Starts a `>(cmd)` substitution
+ * 
+ * The child reads everything the command writes into the pipe, runs
+ * the pipeline over it and sends the result back to the shell.
+ * 
+ * @param body - Pipeline run inside the child, given the collected input
+ * @return Result<SubstitutionHandle> - Handle owning the write end
+ */
+pub fn spawn_output_substitution<F>(body: F) -> Result<SubstitutionHandle>
+where
+    F: FnOnce(String) -> CommandResult,
+{
+    let (read_fd, write_fd) = IoUtils::create_pipe()?;
+    let (output_read_fd, output_write_fd) = IoUtils::create_pipe()?;
+    
+    match fork_process()? {
+        0 => {
+            let _ = IoUtils::close_fd(write_fd);
+            let _ = IoUtils::close_fd(output_read_fd);
+            let input = IoUtils::read_to_end(read_fd).unwrap_or_default();
+            let result = body(input);
+            let _ = IoUtils::write_all(output_write_fd, result.output.as_bytes());
+            exit_child(result.exit_code)
+        }
+        pid => {
+            IoUtils::close_fd(read_fd)?;
+            IoUtils::close_fd(output_write_fd)?;
+            Ok(SubstitutionHandle {
+                pid,
+                fd: write_fd,
+                output_fd: Some(output_read_fd),
+            })
+        }
+    }
+}
+
+/**
+ * Forked child running concurrently with the shell
+ * 
+ * The parent collects the child's output as it arrives, so any number
+ * of children can run side by side without filling their pipes.
+ */
+#[derive(Debug)]
+pub struct ForkedChild {
+    /// Name the caller uses to identify the child
+    pub label: String,
+    /// Child process ID
+    pid: libc::pid_t,
+    /// Read end of the pipe carrying the child's output
+    fd: i32,
+    /// Output received so far
+    output: Vec<u8>,
+}
+
+/**
+ * Runs a closure in a forked child without waiting for it
+ * 
+ * @param label - Name identifying the child in `wait_any`
+ * @param body - Work to perform inside the child
+ * @return Result<ForkedChild> - Handle owning the output pipe
+ */
+pub fn spawn_forked<F>(label: &str, body: F) -> Result<ForkedChild>
+where
+    F: FnOnce() -> CommandResult,
+{
+    let (read_fd, write_fd) = IoUtils::create_pipe()?;
+    
+    match fork_process()? {
+        0 => {
+            let _ = IoUtils::close_fd(read_fd);
+            let result = body();
+            let _ = IoUtils::write_all(write_fd, result.output.as_bytes());
+            exit_child(result.exit_code)
+        }
+        pid => {
+            IoUtils::close_fd(write_fd)?;
+            Ok(ForkedChild {
+                label: label.to_string(),
+                pid,
+                fd: read_fd,
+                output: Vec::new(),
+            })
+        }
+    }
+}
+
+/**
+ * 複数のフォーク済み子プロセスのうち最初に終了したものを待つ
+ * 
+ * 全ての子のパイプを poll() で監視し、届いた出力を読み込みます。
+ * パイプが EOF に達した子は出力を書き終えているため、その子を
+ * 回収して一覧から取り除きます。読み取りはリーダースレッドではなく
+ * 呼び出し元のスレッドで行うので、待機中に別の子をフォークしても
+ * 安全です。
+ * 
+ * @param children - 実行中の子プロセス（終了した子は取り除かれる）
+ * @return Result<(String, CommandResult)> - 終了した子のラベルと結果
+ */
+pub fn wait_any(children: &mut Vec<ForkedChild>) -> Result<(String, CommandResult)> {
+    if children.is_empty() {
+        return Err(anyhow::anyhow!("No forked children to wait for"));
+    }
+    
+    let mut buffer = [0u8; 8192];
+    loop {
+        let mut descriptors: Vec<libc::pollfd> = children
+            .iter()
+            .map(|child| libc::pollfd { fd: child.fd, events: libc::POLLIN, revents: 0 })
+            .collect();
+        
+        let ready = unsafe {
+            libc::poll(descriptors.as_mut_ptr(), descriptors.len() as libc::nfds_t, -1)
+        };
+        if ready < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(anyhow::anyhow!("Failed to poll forked children: {}", error));
+        }
+        
+        for (index, descriptor) in descriptors.iter().enumerate() {
+            if descriptor.revents == 0 {
+                continue;
+            }
+            
+            let child = &mut children[index];
+            let count = unsafe {
+                libc::read(child.fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+            };
+            if count > 0 {
+                child.output.extend_from_slice(&buffer[..count as usize]);
+                continue;
+            }
+            if count < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            
+            let child = children.remove(index);
+            IoUtils::close_fd(child.fd)?;
+            let exit_code = wait_for_child(child.pid)?;
+            return Ok((child.label, CommandResult {
+                output: String::from_utf8_lossy(&child.output).to_string(),
+                exit_code,
+            }));
+        }
+    }
+}
+
+/**
+ * Forks the shell process
+ * 
+ * Pending stdout is flushed first so buffered text is not emitted twice.
+ * 
+ * @return Result<libc::pid_t> - 0 in the child, the child's PID in the parent
+ */
+fn fork_process() -> Result<libc::pid_t> {
+    use std::io::Write;
+    std::io::stdout().flush()?;
+    
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(anyhow::anyhow!("Failed to fork: {}", std::io::Error::last_os_error()));
+    }
+    Ok(pid)
+}
+
+/**
+ * Waits for a forked child and decodes its exit status
+ * 
+ * @param pid - Child process ID
+ * @return Result<i32> - Shell-style exit code
+ */
+fn wait_for_child(pid: libc::pid_t) -> Result<i32> {
+    let mut status = 0;
+    loop {
+        let result = unsafe { libc::waitpid(pid, &mut status, 0) };
+        if result == pid {
+            break;
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(anyhow::anyhow!("Failed to wait for subshell {}: {}", pid, error));
+        }
+    }
+    
+    if libc::WIFEXITED(status) {
+        Ok(libc::WEXITSTATUS(status))
+    } else if libc::WIFSIGNALED(status) {
+        Ok(128 + libc::WTERMSIG(status))
+    } else {
+        Ok(1)
+    }
+}
+
+/**
+ * Terminates a forked child without running the parent's destructors
+ * 
+ * @param exit_code - Exit code to report
+ */
+fn exit_child(exit_code: i32) -> ! {
+    unsafe { libc::_exit(exit_code & 0xff) }
+}